@@ -0,0 +1,13 @@
+-- Migration to add TLS certificates served by the proxy listeners
+-- snis holds a JSON array of hostnames the certificate is selected for
+
+CREATE TABLE IF NOT EXISTS certificates (
+    id VARCHAR(255) PRIMARY KEY,
+    cert TEXT NOT NULL,
+    `key` TEXT NOT NULL,
+    snis JSON NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_certificates_updated_at ON certificates(updated_at);
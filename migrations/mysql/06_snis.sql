@@ -0,0 +1,26 @@
+-- Migration to manage SNI hostnames separately from certificates, and to
+-- track certificate and SNI deletions for incremental configuration updates
+
+CREATE TABLE IF NOT EXISTS snis (
+    id VARCHAR(255) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL UNIQUE,
+    certificate_id VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    FOREIGN KEY (certificate_id) REFERENCES certificates(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_snis_updated_at ON snis(updated_at);
+
+CREATE TABLE IF NOT EXISTS certificate_deletions (
+    id VARCHAR(255) PRIMARY KEY,
+    deleted_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS sni_deletions (
+    id VARCHAR(255) PRIMARY KEY,
+    deleted_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX certificate_deletions_timestamp_idx ON certificate_deletions(deleted_at);
+CREATE INDEX sni_deletions_timestamp_idx ON sni_deletions(deleted_at);
@@ -0,0 +1,11 @@
+-- Migration to keep a history of configuration versions for diffs and rollbacks
+
+CREATE TABLE IF NOT EXISTS config_versions (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    author VARCHAR(255) NOT NULL,
+    description TEXT NOT NULL,
+    document LONGTEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_config_versions_created_at ON config_versions(created_at);
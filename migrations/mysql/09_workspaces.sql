@@ -0,0 +1,13 @@
+-- Migration to add workspaces
+-- Proxies, consumers and plugin configs belong to a workspace; existing rows
+-- move to the default workspace. Consumer usernames are unique per workspace.
+
+ALTER TABLE proxies ADD COLUMN workspace VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE consumers ADD COLUMN workspace VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE plugin_configs ADD COLUMN workspace VARCHAR(255) NOT NULL DEFAULT 'default';
+
+ALTER TABLE consumers DROP INDEX username;
+ALTER TABLE consumers ADD UNIQUE KEY idx_consumers_workspace_username (workspace, username);
+
+CREATE INDEX idx_proxies_workspace ON proxies(workspace);
+CREATE INDEX idx_plugin_configs_workspace ON plugin_configs(workspace);
@@ -0,0 +1,10 @@
+-- Migration to add revoked Admin API tokens, kept until they expire
+
+CREATE TABLE IF NOT EXISTS admin_token_revocations (
+    jti VARCHAR(64) PRIMARY KEY,
+    username VARCHAR(255) NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_admin_token_revocations_expires_at ON admin_token_revocations(expires_at);
@@ -0,0 +1,15 @@
+-- Migration to add long-lived Admin API tokens, which may be limited to some
+-- entity types and tags. The scope is stored as a JSON object.
+
+CREATE TABLE IF NOT EXISTS admin_api_tokens (
+    id VARCHAR(64) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    username VARCHAR(255) NOT NULL,
+    role VARCHAR(20) NOT NULL,
+    workspace VARCHAR(255) NULL,
+    scope_json TEXT NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_admin_api_tokens_username ON admin_api_tokens(username);
@@ -0,0 +1,4 @@
+-- Migration to add per-proxy upstream DNS resolvers
+-- Stores a JSON list of the DNS servers to resolve the backend host with, in fallback order
+
+ALTER TABLE proxies ADD COLUMN dns_resolvers JSON NULL;
@@ -0,0 +1,4 @@
+-- Migration to add per-proxy API versioning
+-- Stores the JSON versions of the proxy, how requests name them and their backends
+
+ALTER TABLE proxies ADD COLUMN api_versioning JSON NULL;
@@ -0,0 +1,4 @@
+-- Migration to add a per-proxy Host header toward the backend
+-- Stores the template of the Host header, NULL sending the backend address or the client's Host
+
+ALTER TABLE proxies ADD COLUMN upstream_host VARCHAR(255) NULL;
@@ -0,0 +1,4 @@
+-- Migration to add a per-proxy egress proxy for backend connections
+-- Stores the URL of the egress proxy or 'direct', NULL using the gateway-wide egress proxy
+
+ALTER TABLE proxies ADD COLUMN egress_proxy VARCHAR(255) NULL;
@@ -0,0 +1,13 @@
+-- Migration to add TLS certificates served by the proxy listeners
+-- snis holds a JSON array of hostnames the certificate is selected for
+
+CREATE TABLE IF NOT EXISTS certificates (
+    id VARCHAR(255) PRIMARY KEY,
+    cert TEXT NOT NULL,
+    key TEXT NOT NULL,
+    snis JSONB NOT NULL DEFAULT '[]'::jsonb,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_certificates_updated_at ON certificates(updated_at);
@@ -0,0 +1,26 @@
+-- Migration to manage SNI hostnames separately from certificates, and to
+-- track certificate and SNI deletions for incremental configuration updates
+
+CREATE TABLE IF NOT EXISTS snis (
+    id VARCHAR(255) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL UNIQUE,
+    certificate_id VARCHAR(255) NOT NULL REFERENCES certificates(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_snis_certificate_id ON snis(certificate_id);
+CREATE INDEX IF NOT EXISTS idx_snis_updated_at ON snis(updated_at);
+
+CREATE TABLE IF NOT EXISTS certificate_deletions (
+    id TEXT PRIMARY KEY,
+    deleted_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS sni_deletions (
+    id TEXT PRIMARY KEY,
+    deleted_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS certificate_deletions_timestamp_idx ON certificate_deletions(deleted_at);
+CREATE INDEX IF NOT EXISTS sni_deletions_timestamp_idx ON sni_deletions(deleted_at);
@@ -0,0 +1,11 @@
+-- Migration to keep a history of configuration versions for diffs and rollbacks
+
+CREATE TABLE IF NOT EXISTS config_versions (
+    id BIGSERIAL PRIMARY KEY,
+    author VARCHAR(255) NOT NULL,
+    description TEXT NOT NULL,
+    document TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_config_versions_created_at ON config_versions(created_at);
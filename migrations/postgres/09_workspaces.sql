@@ -0,0 +1,13 @@
+-- Migration to add workspaces
+-- Proxies, consumers and plugin configs belong to a workspace; existing rows
+-- move to the default workspace. Consumer usernames are unique per workspace.
+
+ALTER TABLE proxies ADD COLUMN IF NOT EXISTS workspace VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE consumers ADD COLUMN IF NOT EXISTS workspace VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE plugin_configs ADD COLUMN IF NOT EXISTS workspace VARCHAR(255) NOT NULL DEFAULT 'default';
+
+ALTER TABLE consumers DROP CONSTRAINT IF EXISTS consumers_username_key;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_consumers_workspace_username ON consumers(workspace, username);
+
+CREATE INDEX IF NOT EXISTS idx_proxies_workspace ON proxies(workspace);
+CREATE INDEX IF NOT EXISTS idx_plugin_configs_workspace ON plugin_configs(workspace);
@@ -0,0 +1,11 @@
+-- Migration to add entity tags
+-- Proxies, consumers and plugin configs carry free-form tags used to filter
+-- listings and to scope declarative exports and imports.
+
+ALTER TABLE proxies ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE consumers ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE plugin_configs ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+
+CREATE INDEX IF NOT EXISTS idx_proxies_tags ON proxies USING GIN (tags);
+CREATE INDEX IF NOT EXISTS idx_consumers_tags ON consumers USING GIN (tags);
+CREATE INDEX IF NOT EXISTS idx_plugin_configs_tags ON plugin_configs USING GIN (tags);
@@ -0,0 +1,9 @@
+-- Migration to add Admin API user accounts and their roles
+
+CREATE TABLE IF NOT EXISTS admin_users (
+    username VARCHAR(255) PRIMARY KEY,
+    password_hash TEXT NOT NULL,
+    role VARCHAR(20) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
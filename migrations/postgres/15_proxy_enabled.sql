@@ -0,0 +1,4 @@
+-- Migration to add the enabled flag of proxies
+-- Disabled proxies keep their configuration but are left out of the routes
+
+ALTER TABLE proxies ADD COLUMN IF NOT EXISTS enabled BOOLEAN NOT NULL DEFAULT TRUE;
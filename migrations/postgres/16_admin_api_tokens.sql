@@ -0,0 +1,15 @@
+-- Migration to add long-lived Admin API tokens, which may be limited to some
+-- entity types and tags. The scope is stored as a JSON object.
+
+CREATE TABLE IF NOT EXISTS admin_api_tokens (
+    id VARCHAR(64) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    username VARCHAR(255) NOT NULL,
+    role VARCHAR(20) NOT NULL,
+    workspace VARCHAR(255),
+    scope_json TEXT NOT NULL DEFAULT '{}',
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_admin_api_tokens_username ON admin_api_tokens(username);
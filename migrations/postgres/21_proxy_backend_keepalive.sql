@@ -0,0 +1,4 @@
+-- Migration to add per-proxy backend keepalive settings
+-- Stores the JSON idle timeout, requests per connection and TCP options of the backend connections
+
+ALTER TABLE proxies ADD COLUMN IF NOT EXISTS backend_keepalive JSONB NULL;
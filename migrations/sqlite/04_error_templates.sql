@@ -0,0 +1,4 @@
+-- Migration to add per-proxy error response templates
+-- Stores a JSON map of status key ("404", "5xx", "default") to template
+
+ALTER TABLE proxies ADD COLUMN error_templates TEXT;
@@ -0,0 +1,13 @@
+-- Migration to add TLS certificates served by the proxy listeners
+-- snis holds a JSON array of hostnames the certificate is selected for
+
+CREATE TABLE IF NOT EXISTS certificates (
+    id TEXT PRIMARY KEY,
+    cert TEXT NOT NULL,
+    key TEXT NOT NULL,
+    snis TEXT NOT NULL DEFAULT '[]', -- JSON as TEXT
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_certificates_updated_at ON certificates(updated_at);
@@ -0,0 +1,26 @@
+-- Migration to manage SNI hostnames separately from certificates, and to
+-- track certificate and SNI deletions for incremental configuration updates
+
+CREATE TABLE IF NOT EXISTS snis (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    certificate_id TEXT NOT NULL REFERENCES certificates(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_snis_certificate_id ON snis(certificate_id);
+CREATE INDEX IF NOT EXISTS idx_snis_updated_at ON snis(updated_at);
+
+CREATE TABLE IF NOT EXISTS certificate_deletions (
+    id TEXT PRIMARY KEY,
+    deleted_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS sni_deletions (
+    id TEXT PRIMARY KEY,
+    deleted_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS certificate_deletions_timestamp_idx ON certificate_deletions(deleted_at);
+CREATE INDEX IF NOT EXISTS sni_deletions_timestamp_idx ON sni_deletions(deleted_at);
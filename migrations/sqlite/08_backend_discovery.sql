@@ -0,0 +1,4 @@
+-- Migration to add service discovery of backend instances
+-- Stores the JSON registry settings of a proxy, NULL for a static backend
+
+ALTER TABLE proxies ADD COLUMN backend_discovery TEXT;
@@ -0,0 +1,13 @@
+-- Migration to add workspaces
+-- Proxies, consumers and plugin configs belong to a workspace; existing rows
+-- move to the default workspace. SQLite can't drop the UNIQUE constraint on
+-- consumers.username without rebuilding the table, which would cascade to
+-- plugin_configs, so usernames stay unique across workspaces here.
+
+ALTER TABLE proxies ADD COLUMN workspace TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE consumers ADD COLUMN workspace TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE plugin_configs ADD COLUMN workspace TEXT NOT NULL DEFAULT 'default';
+
+CREATE INDEX IF NOT EXISTS idx_proxies_workspace ON proxies(workspace);
+CREATE INDEX IF NOT EXISTS idx_consumers_workspace ON consumers(workspace);
+CREATE INDEX IF NOT EXISTS idx_plugin_configs_workspace ON plugin_configs(workspace);
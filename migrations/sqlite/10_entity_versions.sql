@@ -0,0 +1,8 @@
+-- Migration to add entity versions
+-- Every update of a proxy, consumer or plugin config increments its version
+-- and only applies when the row still has the version the update was based
+-- on, so concurrent Admin API edits cannot silently overwrite each other.
+
+ALTER TABLE proxies ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE consumers ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE plugin_configs ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
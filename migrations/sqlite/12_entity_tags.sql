@@ -0,0 +1,8 @@
+-- Migration to add entity tags
+-- Proxies, consumers and plugin configs carry free-form tags used to filter
+-- listings and to scope declarative exports and imports. Tags are stored as a
+-- JSON array.
+
+ALTER TABLE proxies ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE consumers ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE plugin_configs ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
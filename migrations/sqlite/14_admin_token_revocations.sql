@@ -0,0 +1,10 @@
+-- Migration to add revoked Admin API tokens, kept until they expire
+
+CREATE TABLE IF NOT EXISTS admin_token_revocations (
+    jti TEXT PRIMARY KEY,
+    username TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    revoked_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_admin_token_revocations_expires_at ON admin_token_revocations(expires_at);
@@ -0,0 +1,15 @@
+-- Migration to add long-lived Admin API tokens, which may be limited to some
+-- entity types and tags. The scope is stored as a JSON object.
+
+CREATE TABLE IF NOT EXISTS admin_api_tokens (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    username TEXT NOT NULL,
+    role TEXT NOT NULL,
+    workspace TEXT,
+    scope_json TEXT NOT NULL DEFAULT '{}',
+    expires_at TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_admin_api_tokens_username ON admin_api_tokens(username);
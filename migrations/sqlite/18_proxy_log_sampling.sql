@@ -0,0 +1,4 @@
+-- Migration to add per-proxy access log sampling
+-- Stores the JSON sampling settings that replace the global ones for the proxy's requests
+
+ALTER TABLE proxies ADD COLUMN log_sampling TEXT;
@@ -0,0 +1,4 @@
+-- Migration to add a per-proxy request rate cap
+-- Stores the requests per second the proxy accepts before any plugin runs
+
+ALTER TABLE proxies ADD COLUMN max_requests_per_second INTEGER;
@@ -0,0 +1,4 @@
+-- Migration to add per-proxy backend keepalive settings
+-- Stores the JSON idle timeout, requests per connection and TCP options of the backend connections
+
+ALTER TABLE proxies ADD COLUMN backend_keepalive TEXT;
@@ -0,0 +1,4 @@
+-- Migration to add the per-proxy casing of backend request headers
+-- Stores title or preserve, NULL sending header names in lowercase
+
+ALTER TABLE proxies ADD COLUMN backend_header_case TEXT;
@@ -0,0 +1,362 @@
+pub mod storage;
+
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{anyhow, bail, Context, Result};
+use arc_swap::ArcSwap;
+use chrono::{DateTime, TimeZone, Utc};
+use dashmap::DashMap;
+use instant_acme::{
+    Account, AuthorizationStatus, ChallengeType, Identifier, NewAccount, NewOrder, Order, OrderStatus,
+};
+use once_cell::sync::Lazy;
+use rcgen::{CertificateParams, DistinguishedName};
+use tokio_rustls::rustls::sign::CertifiedKey;
+use tracing::{debug, error, info, warn};
+
+use crate::config::data_model::{AcmeChallengeType, Certificate};
+use crate::config::env_config::EnvConfig;
+use crate::proxy::tls::SniCertificates;
+use self::storage::AcmeStorage;
+
+/// Path prefix the CA requests HTTP-01 challenge responses from
+pub const CHALLENGE_PATH_PREFIX: &str = "/.well-known/acme-challenge/";
+
+/// How often issued certificates are checked for renewal
+const RENEWAL_CHECK_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// How long to wait before retrying after a failed issuance
+const RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Upper bound on order status polls before an issuance is abandoned
+const MAX_ORDER_POLLS: u32 = 30;
+
+/// State shared between the ACME manager and the proxy listeners
+struct AcmeState {
+    // Pending HTTP-01 challenges: token -> key authorization
+    challenges: DashMap<String, String>,
+    // Certificates issued by this process, served until they reach the configuration
+    certificates: ArcSwap<SniCertificates>,
+}
+
+static STATE: Lazy<AcmeState> = Lazy::new(|| AcmeState {
+    challenges: DashMap::new(),
+    certificates: ArcSwap::from_pointee(SniCertificates::default()),
+});
+
+/// Returns the key authorization for a pending HTTP-01 challenge request path
+pub fn challenge_response(path: &str) -> Option<String> {
+    let token = path.strip_prefix(CHALLENGE_PATH_PREFIX)?;
+    STATE.challenges.get(token).map(|key_authorization| key_authorization.clone())
+}
+
+/// Finds a certificate issued by this process for a server name
+pub fn resolve_certificate(server_name: &str) -> Option<Arc<CertifiedKey>> {
+    STATE.certificates.load().resolve(server_name)
+}
+
+/// Registers a pending HTTP-01 challenge with the proxy listeners
+pub fn publish_challenge(token: &str, key_authorization: &str) {
+    STATE.challenges.insert(token.to_string(), key_authorization.to_string());
+}
+
+pub fn remove_challenge(token: &str) {
+    STATE.challenges.remove(token);
+}
+
+/// A challenge published during an order, cleaned up once the order completes
+enum PublishedChallenge {
+    Http { token: String },
+    Dns { domain: String, value: String },
+}
+
+/// Obtains a certificate for FERRUM_ACME_DOMAINS and keeps it renewed.
+/// Certificates are persisted through an AcmeStorage so restarts don't
+/// trigger new orders and, in database mode, so every node serves them.
+pub struct AcmeManager {
+    directory_url: String,
+    email: Option<String>,
+    domains: Vec<String>,
+    challenge: AcmeChallengeType,
+    dns_hook: Option<String>,
+    dns_propagation: Duration,
+    renew_before: chrono::Duration,
+    storage: Arc<dyn AcmeStorage>,
+}
+
+impl AcmeManager {
+    pub fn new(config: &EnvConfig, storage: Arc<dyn AcmeStorage>) -> Self {
+        Self {
+            directory_url: config.acme_directory_url.clone(),
+            email: config.acme_email.clone(),
+            domains: config.acme_domains.clone(),
+            challenge: config.acme_challenge,
+            dns_hook: config.acme_dns_hook.clone(),
+            dns_propagation: Duration::from_secs(config.acme_dns_propagation_secs),
+            renew_before: chrono::Duration::days(config.acme_renew_before_days as i64),
+            storage,
+        }
+    }
+
+    /// Runs the issuance and renewal loop in the background
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            info!("ACME certificate management enabled for {}", self.domains.join(", "));
+
+            loop {
+                let wait = match self.ensure_certificate().await {
+                    Ok(()) => RENEWAL_CHECK_INTERVAL,
+                    Err(e) => {
+                        error!("ACME certificate issuance failed, retrying in {:?}: {:#}", RETRY_INTERVAL, e);
+                        RETRY_INTERVAL
+                    }
+                };
+                tokio::time::sleep(wait).await;
+            }
+        })
+    }
+
+    /// ID of the certificate record holding the certificate for the configured domains
+    pub fn certificate_id(&self) -> String {
+        format!("acme-{}", self.domains[0].replace('*', "wildcard"))
+    }
+
+    /// Serves the stored certificate and issues a new one if it is missing,
+    /// close to expiry, or no longer covers the configured domains
+    pub async fn ensure_certificate(&self) -> Result<()> {
+        let id = self.certificate_id();
+        let existing = self.storage.load_certificate(&id).await?;
+
+        if let Some(certificate) = &existing {
+            Self::publish(certificate);
+
+            if !self.needs_renewal(certificate)? {
+                debug!("ACME certificate {} is up to date", id);
+                return Ok(());
+            }
+        }
+
+        info!("Requesting ACME certificate {} for {}", id, self.domains.join(", "));
+        let mut certificate = self.issue(&id).await?;
+        if let Some(existing) = existing {
+            certificate.created_at = existing.created_at;
+        }
+
+        self.storage.save_certificate(&certificate).await
+            .context("Failed to store the issued certificate")?;
+        Self::publish(&certificate);
+
+        info!("Issued ACME certificate {}, valid until {}", id, expires_at(&certificate.cert)?);
+        Ok(())
+    }
+
+    fn needs_renewal(&self, certificate: &Certificate) -> Result<bool> {
+        let mut snis = certificate.snis.clone();
+        let mut domains = self.domains.clone();
+        snis.sort();
+        domains.sort();
+        if snis != domains {
+            info!("Configured ACME domains changed, reissuing certificate {}", certificate.id);
+            return Ok(true);
+        }
+
+        Ok(expires_at(&certificate.cert)? - self.renew_before <= Utc::now())
+    }
+
+    /// Makes a certificate available to the proxy listeners of this process
+    fn publish(certificate: &Certificate) {
+        STATE.certificates.store(Arc::new(SniCertificates::build(std::slice::from_ref(certificate), &[])));
+    }
+
+    async fn account(&self) -> Result<Account> {
+        if let Some(credentials) = self.storage.load_account().await? {
+            return Account::from_credentials(credentials).context("Failed to restore ACME account");
+        }
+
+        let contact: Vec<String> = self.email.iter().map(|email| format!("mailto:{}", email)).collect();
+        let contact: Vec<&str> = contact.iter().map(String::as_str).collect();
+
+        let (account, credentials) = Account::create(
+            &NewAccount {
+                contact: &contact,
+                terms_of_service_agreed: true,
+                only_return_existing: false,
+            },
+            &self.directory_url,
+            None,
+        )
+        .await
+        .context("Failed to create ACME account")?;
+
+        self.storage.save_account(&credentials).await?;
+        info!("Created ACME account at {}", self.directory_url);
+        Ok(account)
+    }
+
+    async fn issue(&self, id: &str) -> Result<Certificate> {
+        let account = self.account().await?;
+
+        let identifiers: Vec<Identifier> = self.domains.iter()
+            .map(|domain| Identifier::Dns(domain.clone()))
+            .collect();
+        let mut order = account.new_order(&NewOrder { identifiers: &identifiers })
+            .await
+            .context("Failed to create ACME order")?;
+
+        let mut published = Vec::new();
+        let result = self.complete_order(&mut order, &mut published).await;
+
+        for challenge in published {
+            match challenge {
+                PublishedChallenge::Http { token } => remove_challenge(&token),
+                PublishedChallenge::Dns { domain, value } => {
+                    if let Err(e) = self.run_dns_hook("clear", &domain, &value).await {
+                        warn!("Failed to clear DNS-01 record for {}: {:#}", domain, e);
+                    }
+                }
+            }
+        }
+
+        let (cert, key) = result?;
+        let now = Utc::now();
+
+        Ok(Certificate {
+            id: id.to_string(),
+            cert,
+            key,
+            snis: self.domains.clone(),
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// Answers the order's challenges, finalizes it and returns the
+    /// certificate chain and private key as PEM
+    async fn complete_order(
+        &self,
+        order: &mut Order,
+        published: &mut Vec<PublishedChallenge>,
+    ) -> Result<(String, String)> {
+        let challenge_type = match self.challenge {
+            AcmeChallengeType::Http01 => ChallengeType::Http01,
+            AcmeChallengeType::Dns01 => ChallengeType::Dns01,
+        };
+
+        let authorizations = order.authorizations().await
+            .context("Failed to fetch ACME authorizations")?;
+        let mut ready_urls = Vec::new();
+
+        for authorization in &authorizations {
+            let Identifier::Dns(domain) = &authorization.identifier;
+
+            match authorization.status {
+                AuthorizationStatus::Pending => {},
+                AuthorizationStatus::Valid => continue,
+                status => bail!("Authorization for {} is {:?}", domain, status),
+            }
+
+            let challenge = authorization.challenges.iter()
+                .find(|challenge| challenge.r#type == challenge_type)
+                .ok_or_else(|| anyhow!("No {:?} challenge offered for {}", challenge_type, domain))?;
+            let key_authorization = order.key_authorization(challenge);
+
+            match self.challenge {
+                AcmeChallengeType::Http01 => {
+                    publish_challenge(&challenge.token, key_authorization.as_str());
+                    published.push(PublishedChallenge::Http { token: challenge.token.clone() });
+                },
+                AcmeChallengeType::Dns01 => {
+                    let value = key_authorization.dns_value();
+                    self.run_dns_hook("set", domain, &value).await?;
+                    published.push(PublishedChallenge::Dns { domain: domain.clone(), value });
+                },
+            }
+
+            ready_urls.push(challenge.url.clone());
+        }
+
+        if self.challenge == AcmeChallengeType::Dns01 && !ready_urls.is_empty() {
+            debug!("Waiting {:?} for DNS-01 records to propagate", self.dns_propagation);
+            tokio::time::sleep(self.dns_propagation).await;
+        }
+
+        for url in &ready_urls {
+            order.set_challenge_ready(url).await
+                .context("Failed to mark ACME challenge as ready")?;
+        }
+
+        self.wait_for_status(order, OrderStatus::Ready).await?;
+
+        let mut params = CertificateParams::new(self.domains.clone());
+        params.distinguished_name = DistinguishedName::new();
+        let key_pair = rcgen::Certificate::from_params(params)
+            .context("Failed to generate certificate key")?;
+        let csr = key_pair.serialize_request_der()
+            .context("Failed to build certificate signing request")?;
+
+        order.finalize(&csr).await.context("Failed to finalize ACME order")?;
+
+        let mut delay = Duration::from_millis(500);
+        for _ in 0..MAX_ORDER_POLLS {
+            if let Some(cert_chain) = order.certificate().await.context("Failed to download certificate")? {
+                return Ok((cert_chain, key_pair.serialize_private_key_pem()));
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(Duration::from_secs(10));
+        }
+
+        bail!("Timed out waiting for the ACME certificate to be issued")
+    }
+
+    async fn wait_for_status(&self, order: &mut Order, expected: OrderStatus) -> Result<()> {
+        let mut delay = Duration::from_millis(500);
+
+        for _ in 0..MAX_ORDER_POLLS {
+            tokio::time::sleep(delay).await;
+
+            let state = order.refresh().await.context("Failed to refresh ACME order")?;
+            if state.status == expected {
+                return Ok(());
+            }
+            if state.status == OrderStatus::Invalid {
+                bail!("ACME order became invalid: {:?}", state.error);
+            }
+
+            delay = (delay * 2).min(Duration::from_secs(10));
+        }
+
+        bail!("Timed out waiting for the ACME order to become {:?}", expected)
+    }
+
+    /// Calls FERRUM_ACME_DNS_HOOK as `<hook> set|clear <domain> <txt-value>`
+    /// to publish or remove the _acme-challenge TXT record
+    async fn run_dns_hook(&self, action: &str, domain: &str, value: &str) -> Result<()> {
+        let hook = self.dns_hook.as_deref()
+            .context("FERRUM_ACME_DNS_HOOK must be set for dns-01 challenges")?;
+
+        let status = tokio::process::Command::new(hook)
+            .arg(action)
+            .arg(domain.trim_start_matches("*."))
+            .arg(value)
+            .status()
+            .await
+            .context(format!("Failed to run DNS hook {}", hook))?;
+
+        if !status.success() {
+            bail!("DNS hook {} {} {} exited with {}", hook, action, domain, status);
+        }
+        Ok(())
+    }
+}
+
+/// Reads the notAfter date of the leaf certificate in a PEM chain
+pub fn expires_at(cert_pem: &str) -> Result<DateTime<Utc>> {
+    let (_, pem) = x509_parser::pem::parse_x509_pem(cert_pem.as_bytes())
+        .map_err(|e| anyhow!("Failed to parse certificate PEM: {}", e))?;
+    let certificate = pem.parse_x509()
+        .map_err(|e| anyhow!("Failed to parse certificate: {}", e))?;
+
+    Utc.timestamp_opt(certificate.validity().not_after.timestamp(), 0)
+        .single()
+        .context("Certificate expiry is out of range")
+}
@@ -0,0 +1,123 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use async_trait::async_trait;
+use instant_acme::AccountCredentials;
+use tracing::debug;
+
+use crate::config::data_model::Certificate;
+use crate::database::DatabaseClient;
+
+const ACCOUNT_FILE: &str = "account.json";
+
+/// Where issued certificates and the ACME account key are kept between restarts
+#[async_trait]
+pub trait AcmeStorage: Send + Sync {
+    async fn load_certificate(&self, id: &str) -> Result<Option<Certificate>>;
+    async fn save_certificate(&self, certificate: &Certificate) -> Result<()>;
+    async fn load_account(&self) -> Result<Option<AccountCredentials>>;
+    async fn save_account(&self, credentials: &AccountCredentials) -> Result<()>;
+}
+
+/// Stores certificates and the account as JSON files under FERRUM_ACME_STORAGE_PATH.
+/// Used in file mode, where there is no database to share certificates through.
+pub struct FileAcmeStorage {
+    dir: PathBuf,
+}
+
+impl FileAcmeStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .context(format!("Failed to create ACME storage directory: {}", dir.display()))?;
+        Ok(Self { dir })
+    }
+
+    fn certificate_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+        match std::fs::read(path) {
+            Ok(data) => Ok(Some(serde_json::from_slice(&data)
+                .context(format!("Failed to parse {}", path.display()))?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).context(format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Writes through a temporary file so a crash never leaves a truncated
+    /// file behind, and keeps private keys readable by the owner only
+    fn write_json<T: serde::Serialize>(path: &Path, value: &T) -> Result<()> {
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(value)?)
+            .context(format!("Failed to write {}", tmp_path.display()))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o600))?;
+        }
+
+        std::fs::rename(&tmp_path, path)
+            .context(format!("Failed to write {}", path.display()))?;
+        debug!("Wrote {}", path.display());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AcmeStorage for FileAcmeStorage {
+    async fn load_certificate(&self, id: &str) -> Result<Option<Certificate>> {
+        Self::read_json(&self.certificate_path(id))
+    }
+
+    async fn save_certificate(&self, certificate: &Certificate) -> Result<()> {
+        Self::write_json(&self.certificate_path(&certificate.id), certificate)
+    }
+
+    async fn load_account(&self) -> Result<Option<AccountCredentials>> {
+        Self::read_json(&self.dir.join(ACCOUNT_FILE))
+    }
+
+    async fn save_account(&self, credentials: &AccountCredentials) -> Result<()> {
+        Self::write_json(&self.dir.join(ACCOUNT_FILE), credentials)
+    }
+}
+
+/// Stores certificates in the certificates table, so every gateway reading
+/// the database (and the data planes fed by a control plane) serves them.
+/// The account key stays on the local disk of the node that issues.
+pub struct DatabaseAcmeStorage {
+    db_client: DatabaseClient,
+    account: FileAcmeStorage,
+}
+
+impl DatabaseAcmeStorage {
+    pub fn new(db_client: DatabaseClient, account_dir: impl Into<PathBuf>) -> Result<Self> {
+        Ok(Self {
+            db_client,
+            account: FileAcmeStorage::new(account_dir)?,
+        })
+    }
+}
+
+#[async_trait]
+impl AcmeStorage for DatabaseAcmeStorage {
+    async fn load_certificate(&self, id: &str) -> Result<Option<Certificate>> {
+        let certificates = self.db_client.load_certificates().await?;
+        Ok(certificates.into_iter().find(|certificate| certificate.id == id))
+    }
+
+    async fn save_certificate(&self, certificate: &Certificate) -> Result<()> {
+        self.db_client.upsert_certificate(certificate).await
+    }
+
+    async fn load_account(&self) -> Result<Option<AccountCredentials>> {
+        self.account.load_account().await
+    }
+
+    async fn save_account(&self, credentials: &AccountCredentials) -> Result<()> {
+        self.account.save_account(credentials).await
+    }
+}
+
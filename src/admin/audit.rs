@@ -0,0 +1,206 @@
+use std::net::SocketAddr;
+use hyper::{Method, StatusCode};
+use serde_json::Value;
+use tracing::error;
+
+use crate::admin::AdminApiState;
+use crate::config::data_model::{AuditEntry, Configuration};
+use crate::modes::OperationMode;
+
+const REDACTED: &str = "[redacted]";
+
+/// What an Admin API write changes, derived from its method and path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditTarget {
+    pub action: &'static str,
+    pub entity_type: &'static str,
+    /// None for creations, whose ID is only known from the response, and for
+    /// changes to the whole configuration
+    pub entity_id: Option<String>,
+    /// A credential sub-resource of a consumer; secrets are not recorded
+    pub credentials: bool,
+}
+
+impl AuditTarget {
+    pub fn from_request(method: &Method, path: &str) -> Option<Self> {
+        let action = match *method {
+            Method::POST => "create",
+            Method::PUT | Method::PATCH => "update",
+            Method::DELETE => "delete",
+            _ => return None,
+        };
+
+        let target = |action, entity_type, entity_id: Option<&str>| Some(Self {
+            action,
+            entity_type,
+            entity_id: entity_id.map(str::to_string),
+            credentials: false,
+        });
+
+        match path {
+            "/config/import" => return target("import", "configuration", None),
+            "/config/bulk" => return target("bulk_import", "configuration", None),
+            "/config/validate" | "/debug/route" => return None,
+            "/users" => return target(action, "user", None),
+            "/tokens" => return target(action, "api_token", None),
+            "/auth/revoke" => return target("revoke", "admin_token", None),
+            _ => {},
+        }
+        if let Some(version_id) = path.strip_prefix("/config/versions/").and_then(|rest| rest.strip_suffix("/rollback")) {
+            return target("rollback", "configuration", Some(version_id));
+        }
+        if let Some(version_id) = path.strip_prefix("/config/versions/").and_then(|rest| rest.strip_suffix("/promote")) {
+            return target("promote", "configuration", Some(version_id));
+        }
+        if let Some(group) = path.strip_prefix("/config/pins/") {
+            return target(action, "group_pin", Some(group));
+        }
+        if let Some(node_id) = path.strip_prefix("/nodes/").and_then(|rest| rest.strip_suffix("/resync")) {
+            return target("resync", "node", Some(node_id));
+        }
+        if let Some(proxy_id) = path.strip_prefix("/proxies/").and_then(|rest| rest.strip_suffix("/tap")) {
+            return target(action, "tap", Some(proxy_id));
+        }
+        if let Some(username) = path.strip_prefix("/users/") {
+            return target(action, "user", Some(username));
+        }
+        if let Some(token_id) = path.strip_prefix("/tokens/") {
+            return target(action, "api_token", Some(token_id));
+        }
+
+        for (prefix, entity_type) in [
+            ("/proxies", "proxy"),
+            ("/consumers", "consumer"),
+            ("/plugins/config", "plugin_config"),
+            ("/plugins/global", "plugin_config"),
+            ("/certificates", "certificate"),
+            ("/snis", "sni"),
+        ] {
+            let rest = match path.strip_prefix(prefix) {
+                Some(rest) => rest,
+                None => continue,
+            };
+            if rest.is_empty() {
+                return target(action, entity_type, None);
+            }
+            let rest = match rest.strip_prefix('/') {
+                Some(rest) => rest,
+                None => continue,
+            };
+            return match rest.split_once("/credentials/") {
+                // Adding, replacing or revoking a credential updates the consumer
+                Some((consumer_id, _)) => Some(Self {
+                    action: "update",
+                    entity_type,
+                    entity_id: Some(consumer_id.to_string()),
+                    credentials: true,
+                }),
+                None => match rest.rsplit_once('/') {
+                    // Enabling or disabling a global plugin updates its config
+                    Some((plugin_id, "enable" | "disable")) => target("update", entity_type, Some(plugin_id)),
+                    _ => target(action, entity_type, Some(rest)),
+                },
+            };
+        }
+        None
+    }
+
+    /// The addressed entity as it is in the loaded configuration
+    pub fn snapshot(&self, config: &Configuration) -> Option<Value> {
+        if self.credentials {
+            return None;
+        }
+        let id = self.entity_id.as_deref()?;
+        let entity = match self.entity_type {
+            "proxy" => serde_json::to_value(config.proxies.iter().find(|p| p.id == id)?),
+            "consumer" => serde_json::to_value(config.consumers.iter().find(|c| c.id == id)?),
+            "plugin_config" => serde_json::to_value(config.plugin_configs.iter().find(|pc| pc.id == id)?),
+            "certificate" => serde_json::to_value(config.certificates.iter().find(|c| c.id == id)?),
+            "sni" => serde_json::to_value(config.snis.iter().find(|s| s.id == id)?),
+            _ => return None,
+        };
+        entity.ok().map(|entity| redact(self.entity_type, entity))
+    }
+}
+
+/// Replaces consumer credentials, certificate private keys and newly issued API
+/// tokens, which must not end up in the audit log
+pub fn redact(entity_type: &str, mut entity: Value) -> Value {
+    if let Some(object) = entity.as_object_mut() {
+        match entity_type {
+            "consumer" => {
+                if let Some(Value::Object(credentials)) = object.get_mut("credentials") {
+                    for value in credentials.values_mut() {
+                        *value = Value::from(REDACTED);
+                    }
+                }
+            },
+            "certificate" => {
+                if object.contains_key("key") {
+                    object.insert("key".to_string(), Value::from(REDACTED));
+                }
+            },
+            "api_token" => {
+                if object.contains_key("token") {
+                    object.insert("token".to_string(), Value::from(REDACTED));
+                }
+            },
+            _ => {},
+        }
+    }
+    entity
+}
+
+/// The ID of the changed entity: from the path, or for creations from the
+/// entity returned in the response body (a user's is its username)
+pub fn entity_id(target: &AuditTarget, response_body: &[u8]) -> Option<String> {
+    target.entity_id.clone().or_else(|| {
+        serde_json::from_slice::<Value>(response_body).ok()
+            .and_then(|body| body.get("id").or_else(|| body.get("username")).and_then(Value::as_str).map(str::to_string))
+    })
+}
+
+/// Whether a response to an audited request is a change to record: a
+/// successful one, on a gateway that keeps its configuration in a database
+pub fn records_entry(state: &AdminApiState, status: StatusCode) -> bool {
+    matches!(state.operation_mode, OperationMode::Database | OperationMode::ControlPlane | OperationMode::Hybrid)
+        && status.is_success()
+}
+
+/// Writes the audit log entry for a successful change. `response_body` is the
+/// body returned to the client: the entity after the change, or the changes
+/// made by an import. Failures are logged; the change itself has already been made.
+pub async fn record(
+    state: &AdminApiState,
+    actor: &str,
+    request: String,
+    target: AuditTarget,
+    before: Option<Value>,
+    response_body: &[u8],
+    remote_addr: SocketAddr,
+) {
+    let after = if target.credentials || target.action == "delete" {
+        None
+    } else {
+        serde_json::from_slice::<Value>(response_body).ok()
+            .map(|after| redact(target.entity_type, after))
+    };
+    let entity_id = entity_id(&target, response_body);
+
+    let entry = AuditEntry {
+        id: 0,
+        actor: actor.to_string(),
+        action: target.action.to_string(),
+        entity_type: target.entity_type.to_string(),
+        entity_id,
+        request,
+        before,
+        after,
+        source_ip: Some(remote_addr.ip().to_string()),
+        created_at: chrono::Utc::now(),
+    };
+
+    if let Err(e) = state.db_client.insert_audit_entry(&entry).await {
+        error!("Failed to record audit log entry for {}: {:#}", entry.request, e);
+    }
+}
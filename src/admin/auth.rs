@@ -1,18 +1,72 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use anyhow::Result;
-use jsonwebtoken::{encode, EncodingKey, Header, Algorithm};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Algorithm, Validation};
 use serde::{Serialize, Deserialize};
 
+use crate::config::data_model::{AdminRole, ApiToken, TokenScope};
+
+/// `typ` claim of refresh tokens, which are only accepted by /auth/refresh
+pub const REFRESH_TOKEN_TYPE: &str = "refresh";
+
 /// Claims structure for JWT tokens
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,  // Subject (username)
     pub exp: u64,     // Expiration time
     pub iat: u64,     // Issued at
+    /// Workspace the token is limited to; unset for cluster-wide admins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    /// Role of the user the token was issued to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<AdminRole>,
+    /// Token ID, set on tokens issued by the gateway so they can be revoked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    /// Kind of token; unset for access tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typ: Option<String>,
+    /// Entity types and tags an API token created through /tokens is limited to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<TokenScope>,
+}
+
+impl Claims {
+    /// Tokens without a role, minted with the shared secret outside the
+    /// gateway, keep the full access they had before roles existed
+    pub fn role(&self) -> AdminRole {
+        self.role.unwrap_or(AdminRole::Admin)
+    }
+
+    pub fn is_refresh_token(&self) -> bool {
+        self.typ.as_deref() == Some(REFRESH_TOKEN_TYPE)
+    }
+
+    /// The entity types and tags the token is limited to, if any
+    pub fn scope(&self) -> Option<&TokenScope> {
+        self.scope.as_ref().filter(|scope| !scope.is_unrestricted())
+    }
+}
+
+/// Lifetimes of the tokens issued by /auth/login and /auth/refresh
+#[derive(Debug, Clone, Copy)]
+pub struct TokenSettings {
+    pub access_ttl: Duration,
+    pub refresh_ttl: Duration,
 }
 
-/// Generates a JWT token for admin authentication
-pub fn generate_admin_token(username: &str, secret: &str, expiry_seconds: u64) -> Result<String> {
+/// An access or refresh token issued to an Admin API user
+#[derive(Debug)]
+pub struct IssuedToken {
+    pub token: String,
+    pub jti: String,
+    /// Expiration time, in seconds since the epoch
+    pub exp: u64,
+}
+
+/// Generates a JWT token for admin authentication. A token with a workspace
+/// can only manage the proxies, consumers and plugin configs of that workspace.
+pub fn generate_admin_token(username: &str, workspace: Option<&str>, role: Option<AdminRole>, secret: &str, expiry_seconds: u64) -> Result<String> {
     // Get current time
     let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
     
@@ -21,12 +75,72 @@ pub fn generate_admin_token(username: &str, secret: &str, expiry_seconds: u64) -
         sub: username.to_string(),
         iat: now,
         exp: now + expiry_seconds,
+        workspace: workspace.map(str::to_string),
+        role,
+        jti: None,
+        typ: None,
+        scope: None,
+    };
+    
+    encode_claims(&claims, secret)
+}
+
+/// Issues a revocable access token, or a refresh token, to a user
+pub fn issue_token(username: &str, role: AdminRole, refresh: bool, secret: &str, ttl: Duration) -> Result<IssuedToken> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let jti = uuid::Uuid::new_v4().to_string();
+    
+    let claims = Claims {
+        sub: username.to_string(),
+        iat: now,
+        exp: now + ttl.as_secs(),
+        workspace: None,
+        role: Some(role),
+        jti: Some(jti.clone()),
+        typ: refresh.then(|| REFRESH_TOKEN_TYPE.to_string()),
+        scope: None,
     };
     
+    Ok(IssuedToken {
+        token: encode_claims(&claims, secret)?,
+        jti,
+        exp: claims.exp,
+    })
+}
+
+/// Encodes the token of an API token record; its ID is the token's `jti`, so
+/// deleting the record can revoke it
+pub fn issue_api_token(api_token: &ApiToken, secret: &str) -> Result<String> {
+    let claims = Claims {
+        sub: api_token.username.clone(),
+        iat: api_token.created_at.timestamp().max(0) as u64,
+        exp: api_token.expires_at.timestamp().max(0) as u64,
+        workspace: api_token.workspace.clone(),
+        role: Some(api_token.role),
+        jti: Some(api_token.id.clone()),
+        typ: None,
+        scope: Some(api_token.scope.clone()),
+    };
+    
+    encode_claims(&claims, secret)
+}
+
+/// Checks a token's signature and expiry and returns its claims
+pub fn decode_token(token: &str, secret: &str) -> Result<Claims> {
+    let token_data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &Validation::new(Algorithm::HS256),
+    )?;
+    
+    Ok(token_data.claims)
+}
+
+fn encode_claims(claims: &Claims, secret: &str) -> Result<String> {
     // Generate token
     let token = encode(
         &Header::new(Algorithm::HS256),
-        &claims,
+        claims,
         &EncodingKey::from_secret(secret.as_bytes()),
     )?;
     
@@ -0,0 +1,90 @@
+use std::time::Duration;
+use hyper::header::{self, HeaderValue};
+use hyper::{Body, Method, Request, Response, StatusCode};
+
+/// Methods browsers may use across origins
+const ALLOWED_METHODS: &str = "GET, POST, PUT, PATCH, DELETE, OPTIONS";
+
+/// Request headers the Admin API reads
+const ALLOWED_HEADERS: &str = "Authorization, Content-Type, If-Match";
+
+/// Response headers scripts may read besides the safelisted ones: the entity
+/// version sent back in If-Match
+const EXPOSED_HEADERS: &str = "ETag";
+
+/// Which browser origins may call the Admin API, from FERRUM_ADMIN_CORS_ALLOWED_ORIGINS
+#[derive(Debug, Clone)]
+pub struct CorsPolicy {
+    /// Exact origins such as `https://admin.example.com`, or `*` for any
+    allowed_origins: Vec<String>,
+    /// How long browsers may cache a preflight response
+    max_age: Duration,
+}
+
+impl CorsPolicy {
+    /// None when no origins are allowed, so responses carry no CORS headers
+    pub fn new(allowed_origins: Vec<String>, max_age: Duration) -> Option<Self> {
+        if allowed_origins.is_empty() {
+            return None;
+        }
+        Some(Self { allowed_origins, max_age })
+    }
+
+    fn allows_any(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+
+    /// The Access-Control-Allow-Origin value for a request's Origin, if it is allowed
+    fn allow_origin(&self, origin: &str) -> Option<HeaderValue> {
+        // Tokens travel in the Authorization header rather than cookies, so
+        // allowing any origin does not expose a logged-in session
+        if self.allows_any() {
+            return Some(HeaderValue::from_static("*"));
+        }
+        self.allowed_origins.iter()
+            .find(|allowed| allowed.eq_ignore_ascii_case(origin))
+            .and_then(|_| HeaderValue::from_str(origin).ok())
+    }
+
+    /// Answers a preflight request. Origins that are not allowed get no CORS
+    /// headers, which makes the browser refuse the actual request.
+    pub fn preflight(&self, origin: &str) -> Response<Body> {
+        let mut response = Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Body::empty())
+            .unwrap();
+        if let Some(allow_origin) = self.allow_origin(origin) {
+            let headers = response.headers_mut();
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+            headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, HeaderValue::from_static(ALLOWED_METHODS));
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, HeaderValue::from_static(ALLOWED_HEADERS));
+            headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from(self.max_age.as_secs()));
+        }
+        self.vary(&mut response);
+        response
+    }
+
+    /// Adds the CORS headers to a response for a request from `origin`
+    pub fn apply(&self, origin: &str, response: &mut Response<Body>) {
+        if let Some(allow_origin) = self.allow_origin(origin) {
+            let headers = response.headers_mut();
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_static(EXPOSED_HEADERS));
+        }
+        self.vary(response);
+    }
+
+    /// Responses that depend on the Origin must not be cached for other origins
+    fn vary(&self, response: &mut Response<Body>) {
+        if !self.allows_any() {
+            response.headers_mut().append(header::VARY, HeaderValue::from_static("Origin"));
+        }
+    }
+}
+
+/// Whether a request is a CORS preflight rather than an OPTIONS request of its own
+pub fn is_preflight(req: &Request<Body>) -> bool {
+    req.method() == Method::OPTIONS
+        && req.headers().contains_key(header::ORIGIN)
+        && req.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+}
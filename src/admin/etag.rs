@@ -0,0 +1,39 @@
+use hyper::{Body, Request, Response, StatusCode};
+
+use crate::admin::problem;
+use crate::database::StaleVersion;
+
+/// The entity version an update was based on, from its `If-Match` header.
+/// Accepts the ETag returned by a GET (`"3"`) as well as the bare version.
+pub fn if_match_version(req: &Request<Body>) -> Result<i64, Response<Body>> {
+    let header = match req.headers().get(hyper::header::IF_MATCH) {
+        Some(header) => header,
+        None => return Err(problem::response(
+            StatusCode::PRECONDITION_REQUIRED,
+            "If-Match header with the entity version is required",
+        )),
+    };
+
+    header.to_str().ok()
+        .map(|value| value.trim().trim_start_matches("W/").trim_matches('"'))
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|version| *version >= 1)
+        .ok_or_else(|| problem::response(
+            StatusCode::BAD_REQUEST,
+            "If-Match must be the ETag or version number of the entity",
+        ))
+}
+
+/// ETag header value for an entity version
+pub fn etag(version: i64) -> String {
+    format!("\"{}\"", version)
+}
+
+/// 409 for an update rejected because the entity changed since it was read
+pub fn stale_response(error: &anyhow::Error) -> Option<Response<Body>> {
+    let stale = error.downcast_ref::<StaleVersion>()?;
+    Some(problem::response(
+        StatusCode::CONFLICT,
+        format!("{}; fetch it again and reapply the change", stale),
+    ))
+}
@@ -0,0 +1,92 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::admin::audit::AuditTarget;
+
+/// Events kept for subscribers that fall behind before they are told they missed some
+const EVENT_BUFFER: usize = 256;
+
+/// A change made to the configuration through the Admin API, as streamed by GET /events
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeEvent {
+    /// Increases by one with every event published by this instance
+    pub id: u64,
+    /// `created`, `updated`, `deleted`, `imported`, `bulk_imported` or `rolled_back`
+    pub event: &'static str,
+    pub entity_type: &'static str,
+    /// None for changes to the whole configuration
+    pub entity_id: Option<String>,
+    /// Set when a consumer's credentials changed rather than the consumer itself
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub credentials: bool,
+    pub actor: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Publishes the changes made through this Admin API to the clients following GET /events
+pub struct ChangeEvents {
+    sender: broadcast::Sender<ChangeEvent>,
+    next_id: AtomicU64,
+}
+
+impl ChangeEvents {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self {
+            sender,
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Publishes a successful change. `entity_id` is the ID of the changed
+    /// entity, known from the response for creations.
+    pub fn publish(&self, target: &AuditTarget, entity_id: Option<String>, actor: &str) -> ChangeEvent {
+        let event = ChangeEvent {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            event: event_name(target.action),
+            entity_type: target.entity_type,
+            entity_id,
+            credentials: target.credentials,
+            actor: actor.to_string(),
+            timestamp: Utc::now(),
+        };
+        // Nobody may be listening, which is fine
+        let _ = self.sender.send(event.clone());
+        event
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for ChangeEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The event name of an audit log action
+pub fn event_name(action: &str) -> &'static str {
+    match action {
+        "create" => "created",
+        "update" => "updated",
+        "delete" => "deleted",
+        "import" => "imported",
+        "bulk_import" => "bulk_imported",
+        "rollback" => "rolled_back",
+        _ => "changed",
+    }
+}
+
+/// Formats an event as a Server-Sent Events message
+pub fn sse_message(event: &ChangeEvent) -> String {
+    format!(
+        "id: {}\nevent: {}\ndata: {}\n\n",
+        event.id,
+        event.event,
+        serde_json::to_string(event).unwrap_or_default(),
+    )
+}
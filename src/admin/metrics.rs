@@ -1,12 +1,15 @@
 use std::sync::Arc;
 use std::collections::HashMap;
 use anyhow::Result;
-use hyper::{Body, Response, StatusCode};
+use hyper::{Body, Request, Response, StatusCode};
 use serde::Serialize;
 use chrono::{DateTime, Utc};
 
+use crate::metrics::{fleet, latency, recent, ProxyTraffic};
+use crate::metrics::latency::LatencyPercentiles;
 use crate::modes::OperationMode;
 use crate::admin::AdminApiState;
+use crate::admin::problem;
 
 /// Runtime metrics for the gateway
 #[derive(Debug, Serialize)]
@@ -32,6 +35,12 @@ struct Metrics {
     
     /// Map of response status codes to counts observed in the last second
     status_codes_last_second: HashMap<String, usize>,
+    
+    /// Latency percentiles in milliseconds over the last minute, of all requests
+    latency_ms_last_minute: LatencyPercentiles,
+    
+    /// The same for each proxy that served requests in the last minute
+    proxy_latency_ms_last_minute: HashMap<String, LatencyPercentiles>,
 }
 
 /// Enum representing the status of the configuration source
@@ -47,21 +56,40 @@ enum ConfigSourceStatus {
     Unknown,
 }
 
+/// The database modes report whether configuration reads are failing; the
+/// other modes have no database to check
+fn config_source_status(state: &AdminApiState) -> ConfigSourceStatus {
+    match state.operation_mode {
+        OperationMode::Database | OperationMode::ControlPlane | OperationMode::Hybrid => {
+            if state.db_client.is_degraded() {
+                ConfigSourceStatus::Degraded
+            } else {
+                ConfigSourceStatus::Online
+            }
+        },
+        _ => ConfigSourceStatus::Unknown,
+    }
+}
+
 /// Handler for the /admin/metrics endpoint
 pub async fn get_metrics(state: Arc<AdminApiState>) -> Result<Response<Body>> {
     // Get the current configuration
     let config = state.shared_config.read().await;
     
+    // Latency percentiles of the requests this instance served
+    let (overall_latency, proxy_latencies) = latency::last_minute();
+    
     // Create the metrics object
     let metrics = Metrics {
         mode: state.operation_mode.to_string(),
         config_last_updated_at: config.last_updated_at,
-        // Remove metrics_collector for now
-        config_source_status: ConfigSourceStatus::Unknown,
+        config_source_status: config_source_status(&state),
         proxy_count: config.proxies.len(),
         consumer_count: config.consumers.len(),
         rps_current: 0.0,
         status_codes_last_second: std::collections::HashMap::new(),
+        latency_ms_last_minute: overall_latency,
+        proxy_latency_ms_last_minute: proxy_latencies,
     };
     
     // Serialize to JSON
@@ -74,3 +102,95 @@ pub async fn get_metrics(state: Arc<AdminApiState>) -> Result<Response<Body>> {
         .body(Body::from(json))
         .unwrap())
 }
+
+/// Handler for the /metrics endpoint, in the Prometheus text format. On the
+/// Control Plane this includes the metrics aggregated over all Data Planes.
+pub async fn get_prometheus_metrics(req: Request<Body>) -> Result<Response<Body>> {
+    fleet::refresh();
+    crate::metrics::metrics_handler(req).await
+}
+
+/// Handler for the /nodes/{id}/metrics endpoint: the traffic a Data Plane
+/// node reported in its last health report
+pub async fn get_node_metrics(node_id: &str) -> Result<Response<Body>> {
+    match fleet::node_metrics(node_id) {
+        Some(node) => Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .body(Body::from(serde_json::to_string(&node)?))
+            .unwrap()),
+        None => Ok(problem::response(StatusCode::NOT_FOUND, "Node has not reported any metrics")),
+    }
+}
+
+/// Latency quantiles reported for a proxy or consumer
+const QUANTILES: &[(&str, f64)] = &[("p50", 0.5), ("p90", 0.9), ("p95", 0.95), ("p99", 0.99)];
+
+fn not_found_response(message: &str) -> Response<Body> {
+    problem::response(StatusCode::NOT_FOUND, message)
+}
+
+/// Requests, rate, status codes and latency quantiles of the last minute of traffic
+fn traffic_summary(traffic: &ProxyTraffic) -> serde_json::Map<String, serde_json::Value> {
+    let latency: serde_json::Map<String, serde_json::Value> = QUANTILES.iter()
+        .map(|(name, quantile)| (name.to_string(), serde_json::json!(traffic.latency_quantile(*quantile) * 1000.0)))
+        .collect();
+    let mut summary = serde_json::Map::new();
+    summary.insert("window_seconds".to_string(), serde_json::json!(recent::WINDOW_SECONDS));
+    summary.insert("requests".to_string(), serde_json::json!(traffic.requests));
+    summary.insert("requests_per_second".to_string(), serde_json::json!(traffic.requests as f64 / recent::WINDOW_SECONDS as f64));
+    summary.insert("status_codes".to_string(), serde_json::json!(traffic.status_codes));
+    summary.insert("latency_ms".to_string(), serde_json::Value::Object(latency));
+    summary
+}
+
+/// Handler for the /proxies/{id}/metrics endpoint: the proxy's traffic over
+/// the last minute. A Control Plane, which serves no traffic itself, reports
+/// the last interval its Data Plane nodes reported, summed over the fleet.
+pub async fn get_proxy_metrics(proxy_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if !state.shared_config.read().await.proxies.iter().any(|proxy| proxy.id == proxy_id) {
+        return Ok(not_found_response("Proxy not found"));
+    }
+    
+    let body = if state.operation_mode == OperationMode::ControlPlane {
+        let stats = fleet::proxy_stats(proxy_id).unwrap_or_default();
+        serde_json::json!({
+            "proxy_id": proxy_id,
+            "source": "fleet",
+            "requests": stats.requests,
+            "requests_per_second": stats.requests_per_second,
+            "status_codes": stats.status_codes,
+            "latency_ms": { "p99": stats.p99_latency_ms },
+        })
+    } else {
+        let mut summary = traffic_summary(&recent::proxy(proxy_id));
+        summary.insert("proxy_id".to_string(), serde_json::json!(proxy_id));
+        serde_json::Value::Object(summary)
+    };
+    
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap())
+}
+
+/// Handler for the /consumers/{id}/metrics endpoint: the traffic of the
+/// requests the consumer authenticated over the last minute
+pub async fn get_consumer_metrics(consumer_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::ControlPlane {
+        return Ok(problem::response(StatusCode::CONFLICT, "Data Plane nodes do not report traffic by consumer"));
+    }
+    if !state.shared_config.read().await.consumers.iter().any(|consumer| consumer.id == consumer_id) {
+        return Ok(not_found_response("Consumer not found"));
+    }
+    
+    let mut summary = traffic_summary(&recent::consumer(consumer_id));
+    summary.insert("consumer_id".to_string(), serde_json::json!(consumer_id));
+    
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .body(Body::from(serde_json::Value::Object(summary).to_string()))
+        .unwrap())
+}
@@ -9,28 +9,38 @@ use hyper::server::conn::Http;
 use hyper::service::service_fn;
 use hyper::{Body, Request, Response, StatusCode, Method};
 use chrono::Utc;
-use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm, TokenData};
-use serde::{Serialize, Deserialize};
 
 use crate::config::env_config::EnvConfig;
-use crate::config::data_model::{Configuration, Proxy, Consumer, PluginConfig};
+use crate::config::data_model::{Configuration, Proxy, Consumer, PluginConfig, TlsPolicy};
 use crate::database::DatabaseClient;
+use crate::proxy::bind;
 use crate::proxy::tls;
+use crate::proxy::client_auth::ClientAuth;
 use crate::modes::OperationMode;
 use crate::proxy::update_manager::RouterUpdate;
+use crate::grpc::debug::NodeDebugger;
+use crate::grpc::pinning::GroupPins;
+use crate::health;
+use auth::{Claims, TokenSettings};
+use cors::CorsPolicy;
+use events::ChangeEvents;
+use ui::UiBundle;
 
 mod routes;
 mod auth;
+mod cors;
+mod ui;
 mod metrics;
+mod workspaces;
+mod scopes;
+mod etag;
+mod validation;
+pub mod audit;
+pub mod events;
+pub mod openapi;
 pub mod pagination;
-
-/// Claims structure for JWT tokens
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    sub: String,  // Subject (username)
-    exp: u64,     // Expiration time
-    iat: u64,     // Issued at
-}
+pub mod problem;
+pub mod rbac;
 
 /// The Admin API server
 pub struct AdminServer {
@@ -38,6 +48,11 @@ pub struct AdminServer {
     shared_config: Arc<RwLock<Configuration>>,
     db_client: DatabaseClient,
     jwt_secret: String,
+    group_pins: Option<Arc<GroupPins>>,
+    node_debugger: Option<Arc<NodeDebugger>>,
+    events: Arc<ChangeEvents>,
+    cors: Option<Arc<CorsPolicy>>,
+    ui: Option<Arc<UiBundle>>,
 }
 
 impl AdminServer {
@@ -47,14 +62,48 @@ impl AdminServer {
         db_client: DatabaseClient,
         jwt_secret: String,
     ) -> Result<Self> {
+        let cors = CorsPolicy::new(env_config.admin_cors_allowed_origins.clone(), env_config.admin_cors_max_age)
+            .map(Arc::new);
+        let ui = match &env_config.admin_ui_path {
+            Some(path) => {
+                let bundle = UiBundle::load(std::path::Path::new(path))?;
+                info!("Serving the Admin UI from {} ({} files) under {}", path, bundle.len(), ui::UI_PREFIX);
+                Some(Arc::new(bundle))
+            },
+            None => None,
+        };
         Ok(Self {
             env_config,
             shared_config,
             db_client,
             jwt_secret,
+            group_pins: None,
+            node_debugger: None,
+            events: Arc::new(ChangeEvents::new()),
+            cors,
+            ui,
         })
     }
     
+    /// Manage the configuration versions Data Plane groups are pinned to
+    pub fn with_group_pins(mut self, group_pins: Arc<GroupPins>) -> Self {
+        self.group_pins = Some(group_pins);
+        self
+    }
+    
+    /// Inspect and resync the Data Plane nodes connected to the Control Plane
+    pub fn with_node_debugger(mut self, node_debugger: Arc<NodeDebugger>) -> Self {
+        self.node_debugger = Some(node_debugger);
+        self
+    }
+    
+    fn token_settings(&self) -> TokenSettings {
+        TokenSettings {
+            access_ttl: self.env_config.admin_token_ttl,
+            refresh_ttl: self.env_config.admin_refresh_token_ttl,
+        }
+    }
+    
     pub async fn start(self) -> Result<()> {
         // Warn if neither HTTP nor HTTPS is enabled
         if self.env_config.admin_http_port.is_none() && self.env_config.admin_https_port.is_none() {
@@ -64,56 +113,107 @@ impl AdminServer {
         
         // Start HTTP server if enabled
         if let Some(http_port) = self.env_config.admin_http_port {
-            let addr = format!("0.0.0.0:{}", http_port).parse::<SocketAddr>()?;
-            let shared_config = Arc::clone(&self.shared_config);
-            let db_client = self.db_client.clone();
-            let jwt_secret = self.jwt_secret.clone();
-            let operation_mode = self.env_config.mode;
-            
-            info!("Starting HTTP admin server on {}", addr);
-            
-            tokio::spawn(async move {
-                if let Err(e) = Self::run_http_server(
-                    addr, 
-                    shared_config, 
-                    db_client,
-                    jwt_secret,
-                    operation_mode,
-                ).await {
-                    error!("HTTP admin server error: {}", e);
+            let bound = bind::tcp_listeners(&self.env_config.admin_http_bind_addresses, http_port)
+                .context("Failed to bind HTTP admin listener")?;
+            for (index, listener) in bound.into_iter().enumerate() {
+                let addr = listener.local_addr()?;
+                if index == 0 {
+                    health::record_listener("admin_http", addr);
                 }
-            });
-        }
-        
-        // Start HTTPS server if enabled
-        if let Some(https_port) = self.env_config.admin_https_port {
-            if let (Some(cert_path), Some(key_path)) = (
-                &self.env_config.admin_tls_cert_path,
-                &self.env_config.admin_tls_key_path,
-            ) {
-                let addr = format!("0.0.0.0:{}", https_port).parse::<SocketAddr>()?;
                 let shared_config = Arc::clone(&self.shared_config);
                 let db_client = self.db_client.clone();
                 let jwt_secret = self.jwt_secret.clone();
-                let cert_path = cert_path.clone();
-                let key_path = key_path.clone();
                 let operation_mode = self.env_config.mode;
+                let config_version_retention = self.env_config.config_version_retention;
+                let token_settings = self.token_settings();
+                let group_pins = self.group_pins.clone();
+                let node_debugger = self.node_debugger.clone();
+                let events = Arc::clone(&self.events);
+                let cors = self.cors.clone();
+                let ui = self.ui.clone();
                 
-                info!("Starting HTTPS admin server on {}", addr);
+                info!("Starting HTTP admin server on {}", addr);
                 
                 tokio::spawn(async move {
-                    if let Err(e) = Self::run_https_server(
-                        addr,
-                        cert_path,
-                        key_path,
-                        shared_config,
+                    if let Err(e) = Self::run_http_server(
+                        listener,
+                        shared_config, 
                         db_client,
                         jwt_secret,
                         operation_mode,
+                        config_version_retention,
+                        token_settings,
+                        group_pins,
+                        node_debugger,
+                        events,
+                        cors,
+                        ui,
                     ).await {
-                        error!("HTTPS admin server error: {}", e);
+                        error!("HTTP admin server error: {}", e);
                     }
                 });
+            }
+        }
+        
+        // Start HTTPS server if enabled
+        if let Some(https_port) = self.env_config.admin_https_port {
+            if let (Some(cert_path), Some(key_path)) = (
+                &self.env_config.admin_tls_cert_path,
+                &self.env_config.admin_tls_key_path,
+            ) {
+                let bound = bind::tcp_listeners(&self.env_config.admin_https_bind_addresses, https_port)
+                    .context("Failed to bind HTTPS admin listener")?;
+                for (index, listener) in bound.into_iter().enumerate() {
+                    let addr = listener.local_addr()?;
+                    if index == 0 {
+                        health::record_listener("admin_https", addr);
+                    }
+                    let shared_config = Arc::clone(&self.shared_config);
+                    let db_client = self.db_client.clone();
+                    let jwt_secret = self.jwt_secret.clone();
+                    let cert_path = cert_path.clone();
+                    let key_path = key_path.clone();
+                    let operation_mode = self.env_config.mode;
+                    let config_version_retention = self.env_config.config_version_retention;
+                    let token_settings = self.token_settings();
+                    let group_pins = self.group_pins.clone();
+                    let node_debugger = self.node_debugger.clone();
+                    let events = Arc::clone(&self.events);
+                    let cors = self.cors.clone();
+                    let ui = self.ui.clone();
+                    let tls_policy = self.env_config.admin_tls_policy.clone();
+                    let client_auth = ClientAuth::new(
+                        self.env_config.admin_tls_client_auth,
+                        self.env_config.admin_tls_client_ca_path.as_deref(),
+                        &self.env_config.tls_client_crl_paths,
+                        self.env_config.tls_client_ocsp,
+                    ).context("Failed to configure admin client certificate verification")?;
+                    
+                    info!("Starting HTTPS admin server on {}", addr);
+                    
+                    tokio::spawn(async move {
+                        if let Err(e) = Self::run_https_server(
+                            listener,
+                            cert_path,
+                            key_path,
+                            shared_config,
+                            db_client,
+                            jwt_secret,
+                            operation_mode,
+                            config_version_retention,
+                            token_settings,
+                            tls_policy,
+                            client_auth,
+                            group_pins,
+                            node_debugger,
+                            events,
+                            cors,
+                            ui,
+                        ).await {
+                            error!("HTTPS admin server error: {}", e);
+                        }
+                    });
+                }
             } else {
                 warn!("HTTPS admin port is enabled but TLS certificate and/or key path is not provided. HTTPS admin server will not start.");
             }
@@ -126,15 +226,19 @@ impl AdminServer {
     }
     
     async fn run_http_server(
-        addr: SocketAddr,
+        listener: TcpListener,
         shared_config: Arc<RwLock<Configuration>>,
         db_client: DatabaseClient,
         jwt_secret: String,
         operation_mode: OperationMode,
+        config_version_retention: u64,
+        token_settings: TokenSettings,
+        group_pins: Option<Arc<GroupPins>>,
+        node_debugger: Option<Arc<NodeDebugger>>,
+        events: Arc<ChangeEvents>,
+        cors: Option<Arc<CorsPolicy>>,
+        ui: Option<Arc<UiBundle>>,
     ) -> Result<()> {
-        // Create TCP listener
-        let listener = TcpListener::bind(addr).await?;
-        
         // Create state for request handlers
         let state = Arc::new(AdminApiState {
             shared_config,
@@ -142,6 +246,13 @@ impl AdminServer {
             jwt_secret,
             operation_mode,
             update_tx: None,
+            config_version_retention,
+            token_settings,
+            group_pins,
+            node_debugger,
+            events,
+            cors,
+            ui,
         });
         
         // Accept and serve connections
@@ -168,7 +279,7 @@ impl AdminServer {
                         service_fn(move |req| {
                             let state = Arc::clone(&state_clone);
                             async move {
-                                handle_request(req, state).await
+                                handle_request(req, state, remote_addr).await
                             }
                         }),
                     )
@@ -181,21 +292,27 @@ impl AdminServer {
     }
     
     async fn run_https_server(
-        addr: SocketAddr,
+        listener: TcpListener,
         cert_path: String,
         key_path: String,
         shared_config: Arc<RwLock<Configuration>>,
         db_client: DatabaseClient,
         jwt_secret: String,
         operation_mode: OperationMode,
+        config_version_retention: u64,
+        token_settings: TokenSettings,
+        tls_policy: TlsPolicy,
+        client_auth: Option<Arc<ClientAuth>>,
+        group_pins: Option<Arc<GroupPins>>,
+        node_debugger: Option<Arc<NodeDebugger>>,
+        events: Arc<ChangeEvents>,
+        cors: Option<Arc<CorsPolicy>>,
+        ui: Option<Arc<UiBundle>>,
     ) -> Result<()> {
         // Load TLS configuration
-        let tls_config = tls::load_server_config(&cert_path, &key_path)
+        let tls_config = tls::load_server_config(&cert_path, &key_path, &tls_policy, client_auth.as_deref())
             .context("Failed to load TLS configuration")?;
         
-        // Create TCP listener
-        let listener = TcpListener::bind(addr).await?;
-        
         // Create state for request handlers
         let state = Arc::new(AdminApiState {
             shared_config,
@@ -203,6 +320,13 @@ impl AdminServer {
             jwt_secret,
             operation_mode,
             update_tx: None,
+            config_version_retention,
+            token_settings,
+            group_pins,
+            node_debugger,
+            events,
+            cors,
+            ui,
         });
         
         // Accept and serve connections
@@ -217,6 +341,7 @@ impl AdminServer {
             
             // Clone necessary components for the connection handler
             let state_clone = Arc::clone(&state);
+            let client_auth = client_auth.clone();
             let tls_config = tls_config.clone();
             
             // Perform TLS handshake
@@ -228,18 +353,36 @@ impl AdminServer {
                 }
             };
             
-            // Configure HTTP server
-            let http = Http::new();
+            // Configure HTTP server, serving only the protocols offered through ALPN
+            let mut http = Http::new();
+            http.http1_only(!tls_policy.allows_http2())
+                .http2_only(!tls_policy.allows_http1());
             
             // Spawn a task to serve the connection
             tokio::spawn(async move {
+                // The handshake verified the chain; revocation over OCSP is checked here
+                let client_certificate = match &client_auth {
+                    Some(client_auth) => match client_auth.authorize(tls_stream.get_ref().1.peer_certificates()).await {
+                        Ok(client_certificate) => client_certificate,
+                        Err(e) => {
+                            warn!("Rejecting admin client certificate from {}: {:#}", remote_addr, e);
+                            return;
+                        }
+                    },
+                    None => None,
+                };
+                
                 if let Err(e) = http
                     .serve_connection(
                         tls_stream,
-                        service_fn(move |req| {
+                        service_fn(move |mut req| {
                             let state = Arc::clone(&state_clone);
+                            // Lets users log in with their certificate
+                            if let Some(client_certificate) = &client_certificate {
+                                req.extensions_mut().insert(Arc::clone(client_certificate));
+                            }
                             async move {
-                                handle_request(req, state).await
+                                handle_request(req, state, remote_addr).await
                             }
                         }),
                     )
@@ -259,34 +402,142 @@ pub struct AdminApiState {
     pub jwt_secret: String,
     pub operation_mode: OperationMode,
     pub update_tx: Option<broadcast::Sender<RouterUpdate>>,
+    pub config_version_retention: u64,
+    /// Lifetimes of the tokens issued by /auth/login and /auth/refresh
+    pub token_settings: TokenSettings,
+    /// Data Plane groups pinned to a configuration version, on a Control Plane
+    pub group_pins: Option<Arc<GroupPins>>,
+    /// Inspects and resyncs connected Data Plane nodes, on a Control Plane
+    pub node_debugger: Option<Arc<NodeDebugger>>,
+    /// Changes made through the Admin API, streamed by GET /events
+    pub events: Arc<ChangeEvents>,
+    /// Browser origins allowed to call the API
+    pub cors: Option<Arc<CorsPolicy>>,
+    /// Management UI served under /ui/
+    pub ui: Option<Arc<UiBundle>>,
 }
 
-/// Handle an incoming request to the Admin API
+/// Handle an incoming request to the Admin API, adding CORS headers for allowed browser origins
 async fn handle_request(
     req: Request<Body>,
     state: Arc<AdminApiState>,
+    remote_addr: SocketAddr,
 ) -> Result<Response<Body>, hyper::Error> {
-    // Check if this is a health check (doesn't require authentication)
-    if req.uri().path() == "/health" || req.uri().path() == "/status" {
+    let origin = match &state.cors {
+        Some(_) => req.headers().get(hyper::header::ORIGIN)
+            .and_then(|origin| origin.to_str().ok())
+            .map(str::to_string),
+        None => None,
+    };
+    
+    // Preflights carry no token, so they are answered before authentication
+    if let (Some(cors), Some(origin)) = (&state.cors, &origin) {
+        if cors::is_preflight(&req) {
+            return Ok(cors.preflight(origin));
+        }
+    }
+    
+    let mut response = match &state.ui {
+        // The UI is public; it calls the API with the token of the user logged into it
+        Some(ui) if ui::is_ui_path(req.uri().path()) => ui.serve(&req),
+        _ => handle_api_request(req, Arc::clone(&state), remote_addr).await?,
+    };
+    
+    if let (Some(cors), Some(origin)) = (&state.cors, &origin) {
+        cors.apply(origin, &mut response);
+    }
+    Ok(response)
+}
+
+/// Handle a request to the API itself
+async fn handle_api_request(
+    req: Request<Body>,
+    state: Arc<AdminApiState>,
+    remote_addr: SocketAddr,
+) -> Result<Response<Body>, hyper::Error> {
+    // Health checks don't require authentication. /health is readiness for load
+    // balancers and orchestrators: 503 until a configuration has been loaded,
+    // with the state of what the gateway depends on.
+    if req.uri().path() == "/health" {
+        let report = health::report(state.operation_mode);
+        let status = if report.is_ready() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+        return Ok(Response::builder()
+            .status(status)
+            .header("Content-Type", "application/json")
+            .header("Cache-Control", "no-store")
+            .body(Body::from(serde_json::to_string(&report).unwrap_or_default()))
+            .unwrap());
+    }
+    
+    // Liveness: the Admin API answers
+    if req.uri().path() == "/status" {
         return Ok(Response::builder()
             .status(StatusCode::OK)
             .body(Body::from(r#"{"status":"ok"}"#))
             .unwrap());
     }
     
+    // Scrapers hold no token, so the metrics can be exposed at a public path
+    if let Some(response) = crate::metrics::endpoint::admin_response(&req).await {
+        return Ok(response);
+    }
+    
+    // The API description is public, so clients and tools can be generated from it
+    if req.method() == Method::GET && req.uri().path() == "/openapi.json" {
+        return Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .body(Body::from(openapi::document_json()))
+            .unwrap());
+    }
+    
+    // Logging in and refreshing tokens are how clients get a token in the first place
+    match (req.method(), req.uri().path()) {
+        (&Method::POST, "/auth/login") => return Ok(respond(routes::auth::login(req, state).await)),
+        (&Method::POST, "/auth/refresh") => return Ok(respond(routes::auth::refresh(req, state).await)),
+        _ => {},
+    }
+    
     // Authenticate the request (except for health check)
-    match authenticate_request(&req, &state.jwt_secret) {
+    match authenticate_request(&req, &state).await {
         Ok(claims) => {
+            let method = req.method().clone();
+            let path = req.uri().path().to_string();
+            let author = claims.sub.clone();
+            
+            // The entity a write changes, as it was before the change
+            let audit_target = audit::AuditTarget::from_request(&method, &path);
+            let before = match &audit_target {
+                Some(target) => target.snapshot(&*state.shared_config.read().await),
+                None => None,
+            };
+            
             // Request is authenticated, route it to the appropriate handler
             match route_request(req, state.clone(), claims).await {
-                Ok(response) => Ok(response),
+                Ok(response) => {
+                    // Keep a version of the configuration after every change to it
+                    let versioned = routes::versions::records_version(&state, &method, &path, response.status());
+                    if versioned {
+                        routes::versions::record_version(&state, &author, &method, &path).await;
+                    }
+                    
+                    // Every change gets an audit log entry, also those to users,
+                    // tokens and rollouts that leave the configuration as it is
+                    if let Some(target) = audit_target.filter(|_| audit::records_entry(&state, response.status())) {
+                        let (parts, body) = response.into_parts();
+                        let body = hyper::body::to_bytes(body).await?;
+                        if versioned {
+                            state.events.publish(&target, audit::entity_id(&target, &body), &author);
+                        }
+                        audit::record(&state, &author, format!("{} {}", method, path), target, before, &body, remote_addr).await;
+                        return Ok(Response::from_parts(parts, Body::from(body)));
+                    }
+                    Ok(response)
+                },
                 Err(e) => {
-                    error!("Error handling admin request: {}", e);
+                    error!("Error handling admin request: {:#}", e);
                     
-                    Ok(Response::builder()
-                        .status(StatusCode::INTERNAL_SERVER_ERROR)
-                        .body(Body::from(format!("{{\"error\":\"{}\"}}", e)))
-                        .unwrap())
+                    Ok(problem::internal_error_response())
                 }
             }
         },
@@ -294,17 +545,13 @@ async fn handle_request(
             // Authentication failed
             debug!("Authentication failed: {}", e);
             
-            Ok(Response::builder()
-                .status(StatusCode::UNAUTHORIZED)
-                .header("WWW-Authenticate", "Bearer")
-                .body(Body::from(r#"{"error":"Unauthorized"}"#))
-                .unwrap())
+            Ok(problem::unauthorized_response("A valid bearer token is required"))
         }
     }
 }
 
 /// Authenticate a request using JWT
-fn authenticate_request(req: &Request<Body>, jwt_secret: &str) -> Result<Claims> {
+async fn authenticate_request(req: &Request<Body>, state: &AdminApiState) -> Result<Claims> {
     // Get the Authorization header
     let auth_header = req.headers()
         .get("Authorization")
@@ -319,13 +566,35 @@ fn authenticate_request(req: &Request<Body>, jwt_secret: &str) -> Result<Claims>
     let token = &auth_str[7..]; // Skip "Bearer "
     
     // Validate the token
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(jwt_secret.as_bytes()),
-        &Validation::new(Algorithm::HS256),
-    )?;
+    let claims = auth::decode_token(token, &state.jwt_secret)?;
+    if claims.is_refresh_token() {
+        return Err(anyhow::anyhow!("Refresh tokens are only accepted by /auth/refresh"));
+    }
+    if let Some(jti) = &claims.jti {
+        if state.db_client.is_admin_token_revoked(jti).await? {
+            return Err(anyhow::anyhow!("Token {} has been revoked", jti));
+        }
+    }
     
-    Ok(token_data.claims)
+    Ok(claims)
+}
+
+/// Turns a handler error into a 500 response
+fn respond(result: Result<Response<Body>>) -> Response<Body> {
+    result.unwrap_or_else(|e| {
+        error!("Error handling admin request: {:#}", e);
+        
+        problem::internal_error_response()
+    })
+}
+
+/// The segment of `path` between `prefix` and `suffix`, such as the proxy ID
+/// of `/proxies/{id}/tap`. None when the segment is empty or spans several
+/// segments, so that such paths are not found.
+fn path_param<'a>(path: &'a str, prefix: &str, suffix: &str) -> Option<&'a str> {
+    path.strip_prefix(prefix)
+        .and_then(|rest| rest.strip_suffix(suffix))
+        .filter(|param| !param.is_empty() && !param.contains('/'))
 }
 
 /// Route a request to the appropriate handler
@@ -334,6 +603,54 @@ async fn route_request(
     state: Arc<AdminApiState>,
     claims: Claims,
 ) -> Result<Response<Body>> {
+    let mut req = req;
+
+    let required = rbac::required_role(req.method(), req.uri().path());
+    if claims.role() < required {
+        return Ok(rbac::forbidden(claims.role(), required));
+    }
+
+    // Workspace-scoped tokens only manage the proxies, consumers and plugin configs of their workspace
+    if let Some(workspace) = claims.workspace.as_deref() {
+        let path = req.uri().path().to_string();
+        if !workspaces::is_workspace_route(&path) {
+            return Ok(workspaces::forbidden(workspace));
+        }
+        if !workspaces::owns_entity(&*state.shared_config.read().await, &path, workspace) {
+            return Ok(problem::response(StatusCode::NOT_FOUND, "Entity not found"));
+        }
+        if matches!(*req.method(), Method::POST | Method::PUT | Method::PATCH) && !path.contains("/credentials/") {
+            req = match workspaces::scope_body(req, workspace).await? {
+                Ok(req) => req,
+                Err(response) => return Ok(response),
+            };
+        }
+    }
+    // API tokens with a scope only manage the entity types and tagged entities it allows
+    if let Some(scope) = claims.scope() {
+        let path = req.uri().path().to_string();
+        if !scopes::entity_type(&path).map_or(false, |entity_type| scope.allows(entity_type)) {
+            return Ok(scopes::forbidden());
+        }
+        if !scope.tags.is_empty() && scopes::is_global_plugin_path(&path) {
+            return Ok(scopes::forbidden());
+        }
+        if !scopes::owns_entity(&*state.shared_config.read().await, &path, scope) {
+            return Ok(problem::response(StatusCode::NOT_FOUND, "Entity not found"));
+        }
+        if !scope.tags.is_empty() {
+            if matches!(*req.method(), Method::POST | Method::PUT | Method::PATCH) && !path.contains("/credentials/") {
+                req = match scopes::scope_body(req, scope, &state.shared_config).await? {
+                    Ok(req) => req,
+                    Err(response) => return Ok(response),
+                };
+            } else if req.method() == Method::GET {
+                req = scopes::scope_listing(req, scope)?;
+            }
+        }
+    }
+    let list_workspace = workspaces::list_filter(&req, claims.workspace.as_deref());
+
     // Extract path and method
     let path = req.uri().path();
     let method = req.method();
@@ -341,11 +658,27 @@ async fn route_request(
     // Route based on path and method
     match (method, path) {
         (&Method::GET, "/proxies") => {
-            routes::proxies::list_proxies(state.clone()).await
+            routes::proxies::list_proxies(&req, list_workspace.as_deref(), state.clone()).await
         },
         (&Method::POST, "/proxies") => {
             routes::proxies::create_proxy(req, state.clone()).await
         },
+        (&Method::POST, path) if path_param(path, "/proxies/", "/tap").is_some() => {
+            let proxy_id = path_param(path, "/proxies/", "/tap").unwrap();
+            routes::taps::start_tap(proxy_id, req, state.clone()).await
+        },
+        (&Method::GET, path) if path_param(path, "/proxies/", "/tap").is_some() => {
+            let proxy_id = path_param(path, "/proxies/", "/tap").unwrap();
+            routes::taps::get_tap(proxy_id).await
+        },
+        (&Method::DELETE, path) if path_param(path, "/proxies/", "/tap").is_some() => {
+            let proxy_id = path_param(path, "/proxies/", "/tap").unwrap();
+            routes::taps::stop_tap(proxy_id).await
+        },
+        (&Method::GET, path) if path_param(path, "/proxies/", "/metrics").is_some() => {
+            let proxy_id = path_param(path, "/proxies/", "/metrics").unwrap();
+            metrics::get_proxy_metrics(proxy_id, state.clone()).await
+        },
         (&Method::GET, path) if path.starts_with("/proxies/") => {
             let proxy_id = &path[9..]; // Skip "/proxies/"
             routes::proxies::get_proxy(proxy_id, state.clone()).await
@@ -354,72 +687,45 @@ async fn route_request(
             let proxy_id = &path[9..]; // Skip "/proxies/"
             routes::proxies::update_proxy(proxy_id, req, state.clone()).await
         },
+        (&Method::PATCH, path) if path.starts_with("/proxies/") => {
+            let proxy_id = &path[9..]; // Skip "/proxies/"
+            routes::proxies::patch_proxy(proxy_id, req, state.clone()).await
+        },
         (&Method::DELETE, path) if path.starts_with("/proxies/") => {
             let proxy_id = &path[9..]; // Skip "/proxies/"
             routes::proxies::delete_proxy(proxy_id, state.clone()).await
         },
         (&Method::GET, "/consumers") => {
-            routes::consumers::list_consumers(state.clone()).await
+            routes::consumers::list_consumers(&req, list_workspace.as_deref(), state.clone()).await
         },
         (&Method::POST, "/consumers") => {
             routes::consumers::create_consumer(req, state.clone()).await
         },
+        (&Method::GET, path) if path_param(path, "/consumers/", "/metrics").is_some() => {
+            let consumer_id = path_param(path, "/consumers/", "/metrics").unwrap();
+            metrics::get_consumer_metrics(consumer_id, state.clone()).await
+        },
+        (_, path) if routes::credentials::CredentialPath::parse(path).is_some() => {
+            let credential_path = routes::credentials::CredentialPath::parse(path).unwrap();
+            routes::credentials::handle(req, credential_path, state.clone()).await
+        },
         (&Method::GET, path) if path.starts_with("/consumers/") => {
-            if path.contains("/credentials/") {
-                // Handle credentials endpoint
-                let parts: Vec<&str> = path.split('/').collect();
-                if parts.len() == 4 {
-                    let consumer_id = parts[2];
-                    let credential_type = parts[3];
-                    routes::consumers::get_consumer_credentials(consumer_id, credential_type, state.clone()).await
-                } else {
-                    Err(anyhow::anyhow!("Invalid path format"))
-                }
-            } else {
-                // Handle consumer endpoint
-                let consumer_id = &path[11..]; // Skip "/consumers/"
-                routes::consumers::get_consumer(consumer_id, state.clone()).await
-            }
+            let consumer_id = &path[11..]; // Skip "/consumers/"
+            routes::consumers::get_consumer(consumer_id, state.clone()).await
         },
         (&Method::PUT, path) if path.starts_with("/consumers/") => {
-            if path.contains("/credentials/") {
-                // Handle credentials endpoint
-                let parts: Vec<&str> = path.split('/').collect();
-                if parts.len() == 4 {
-                    let consumer_id = parts[2];
-                    let credential_type = parts[3];
-                    routes::consumers::update_consumer_credentials(consumer_id, credential_type, req, state.clone()).await
-                } else {
-                    Err(anyhow::anyhow!("Invalid path format"))
-                }
-            } else {
-                // Handle consumer endpoint
-                let consumer_id = &path[11..]; // Skip "/consumers/"
-                routes::consumers::update_consumer(consumer_id, req, state.clone()).await
-            }
+            let consumer_id = &path[11..]; // Skip "/consumers/"
+            routes::consumers::update_consumer(consumer_id, req, state.clone()).await
         },
         (&Method::DELETE, path) if path.starts_with("/consumers/") => {
-            if path.contains("/credentials/") {
-                // Handle credentials endpoint
-                let parts: Vec<&str> = path.split('/').collect();
-                if parts.len() == 4 {
-                    let consumer_id = parts[2];
-                    let credential_type = parts[3];
-                    routes::consumers::delete_consumer_credentials(consumer_id, credential_type, state.clone()).await
-                } else {
-                    Err(anyhow::anyhow!("Invalid path format"))
-                }
-            } else {
-                // Handle consumer endpoint
-                let consumer_id = &path[11..]; // Skip "/consumers/"
-                routes::consumers::delete_consumer(consumer_id, state.clone()).await
-            }
+            let consumer_id = &path[11..]; // Skip "/consumers/"
+            routes::consumers::delete_consumer(consumer_id, state.clone()).await
         },
         (&Method::GET, "/plugins") => { // Endpoint to list available plugin *types*
             routes::plugins::list_plugin_types(state.clone()).await
         },
         (&Method::GET, "/plugins/config") => { // Endpoint to list created plugin *configurations*
-            routes::plugins::list_plugin_configs(state.clone()).await
+            routes::plugins::list_plugin_configs(&req, list_workspace.as_deref(), state.clone()).await
         },
         (&Method::POST, "/plugins/config") => {
             routes::plugins::create_plugin_config(req, state.clone()).await
@@ -436,15 +742,392 @@ async fn route_request(
             let config_id = &path[15..]; // Skip "/plugins/config/"
             routes::plugins::delete_plugin_config(config_id, state.clone()).await
         },
+        (&Method::GET, "/plugins/global") => {
+            routes::global_plugins::list_global_plugins(&req, list_workspace.as_deref(), state.clone()).await
+        },
+        (&Method::POST, "/plugins/global") => {
+            routes::global_plugins::create_global_plugin(req, state.clone()).await
+        },
+        (&Method::POST, path) if path_param(path, "/plugins/global/", "/enable").is_some() => {
+            let plugin_id = path_param(path, "/plugins/global/", "/enable").unwrap();
+            routes::global_plugins::set_global_plugin_enabled(plugin_id, true, state.clone()).await
+        },
+        (&Method::POST, path) if path_param(path, "/plugins/global/", "/disable").is_some() => {
+            let plugin_id = path_param(path, "/plugins/global/", "/disable").unwrap();
+            routes::global_plugins::set_global_plugin_enabled(plugin_id, false, state.clone()).await
+        },
+        (&Method::GET, path) if path_param(path, "/plugins/global/", "").is_some() => {
+            let plugin_id = path_param(path, "/plugins/global/", "").unwrap();
+            routes::global_plugins::get_global_plugin(plugin_id, state.clone()).await
+        },
+        (&Method::PUT, path) if path_param(path, "/plugins/global/", "").is_some() => {
+            let plugin_id = path_param(path, "/plugins/global/", "").unwrap();
+            routes::global_plugins::update_global_plugin(plugin_id, req, state.clone()).await
+        },
+        (&Method::DELETE, path) if path_param(path, "/plugins/global/", "").is_some() => {
+            let plugin_id = path_param(path, "/plugins/global/", "").unwrap();
+            routes::global_plugins::delete_global_plugin(plugin_id, state.clone()).await
+        },
+        (&Method::GET, "/certificates") => {
+            routes::certificates::list_certificates(&req, state.clone()).await
+        },
+        (&Method::POST, "/certificates") => {
+            routes::certificates::create_certificate(req, state.clone()).await
+        },
+        (&Method::GET, path) if path.starts_with("/certificates/") => {
+            let certificate_id = &path[14..]; // Skip "/certificates/"
+            routes::certificates::get_certificate(certificate_id, state.clone()).await
+        },
+        (&Method::PUT, path) if path.starts_with("/certificates/") => {
+            let certificate_id = path[14..].to_string(); // Skip "/certificates/"
+            routes::certificates::update_certificate(&certificate_id, req, state.clone()).await
+        },
+        (&Method::DELETE, path) if path.starts_with("/certificates/") => {
+            let certificate_id = &path[14..]; // Skip "/certificates/"
+            routes::certificates::delete_certificate(certificate_id, state.clone()).await
+        },
+        (&Method::GET, "/snis") => {
+            routes::snis::list_snis(&req, state.clone()).await
+        },
+        (&Method::POST, "/snis") => {
+            routes::snis::create_sni(req, state.clone()).await
+        },
+        (&Method::GET, path) if path.starts_with("/snis/") => {
+            let sni_id = &path[6..]; // Skip "/snis/"
+            routes::snis::get_sni(sni_id, state.clone()).await
+        },
+        (&Method::PUT, path) if path.starts_with("/snis/") => {
+            let sni_id = path[6..].to_string(); // Skip "/snis/"
+            routes::snis::update_sni(&sni_id, req, state.clone()).await
+        },
+        (&Method::DELETE, path) if path.starts_with("/snis/") => {
+            let sni_id = &path[6..]; // Skip "/snis/"
+            routes::snis::delete_sni(sni_id, state.clone()).await
+        },
+        (&Method::GET, "/config/export") => {
+            routes::config::export_config(&req, state.clone()).await
+        },
+        (&Method::POST, "/config/import") => {
+            routes::config::import_config(req, state.clone()).await
+        },
+        (&Method::POST, "/config/bulk") => {
+            routes::config::bulk_config(req, state.clone()).await
+        },
+        (&Method::POST, "/config/validate") => {
+            routes::config::validate_config(req, state.clone()).await
+        },
+        (&Method::GET, "/config/versions") => {
+            routes::versions::list_versions(&req, state.clone()).await
+        },
+        (&Method::POST, path) if path_param(path, "/config/versions/", "/rollback").is_some() => {
+            let version_id = path_param(path, "/config/versions/", "/rollback").unwrap();
+            routes::versions::rollback_version(version_id, state.clone()).await
+        },
+        (&Method::POST, path) if path_param(path, "/config/versions/", "/promote").is_some() => {
+            let version_id = path_param(path, "/config/versions/", "/promote").unwrap();
+            routes::versions::promote_version(version_id, req, &claims.sub, state.clone()).await
+        },
+        (&Method::GET, "/config/pins") => {
+            routes::versions::list_pins(state.clone()).await
+        },
+        (&Method::DELETE, path) if path.starts_with("/config/pins/") => {
+            let group = &path[13..]; // Skip "/config/pins/"
+            routes::versions::unpin_group(group, state.clone()).await
+        },
+        (&Method::GET, path) if path.starts_with("/config/versions/") => {
+            let version_id = &path[17..]; // Skip "/config/versions/"
+            routes::versions::get_version(version_id, state.clone()).await
+        },
+        (&Method::GET, "/config/diff") => {
+            routes::versions::diff_versions(&req, state.clone()).await
+        },
+        (&Method::GET, "/events") => {
+            routes::events::stream_events(&req, state.clone()).await
+        },
+        (&Method::GET, "/audit") => {
+            routes::audit::list_audit_log(&req, state.clone()).await
+        },
         (&Method::GET, "/admin/metrics") => {
             metrics::get_metrics(state.clone()).await
         },
+        (&Method::GET, "/metrics") => {
+            metrics::get_prometheus_metrics(req).await
+        },
+        (&Method::GET, "/nodes/proxy-health") => {
+            routes::nodes::list_degraded_proxies(state.clone()).await
+        },
+        (&Method::GET, path) if path_param(path, "/nodes/", "/metrics").is_some() => {
+            let node_id = path_param(path, "/nodes/", "/metrics").unwrap();
+            metrics::get_node_metrics(node_id).await
+        },
+        (&Method::GET, path) if path_param(path, "/nodes/", "/config").is_some() => {
+            let node_id = path_param(path, "/nodes/", "/config").unwrap();
+            routes::nodes::get_node_config(node_id, state.clone()).await
+        },
+        (&Method::POST, path) if path_param(path, "/nodes/", "/resync").is_some() => {
+            let node_id = path_param(path, "/nodes/", "/resync").unwrap();
+            routes::nodes::resync_node(node_id, state.clone()).await
+        },
+        (&Method::GET, path) if path_param(path, "/nodes/", "/health").is_some() => {
+            let node_id = path_param(path, "/nodes/", "/health").unwrap();
+            routes::nodes::get_node_health(node_id, &req, state.clone()).await
+        },
+        (&Method::GET, "/status/detail") => {
+            routes::status::get_status_detail(state.clone()).await
+        },
+        (&Method::GET, "/users") => {
+            routes::users::list_users(state.clone()).await
+        },
+        (&Method::POST, "/users") => {
+            routes::users::create_user(req, state.clone()).await
+        },
+        (&Method::GET, path) if path.starts_with("/users/") => {
+            let username = &path[7..]; // Skip "/users/"
+            routes::users::get_user(username, state.clone()).await
+        },
+        (&Method::PUT, path) if path.starts_with("/users/") => {
+            let username = path[7..].to_string(); // Skip "/users/"
+            routes::users::update_user(&username, req, state.clone()).await
+        },
+        (&Method::DELETE, path) if path.starts_with("/users/") => {
+            let username = &path[7..]; // Skip "/users/"
+            routes::users::delete_user(username, state.clone()).await
+        },
+        (&Method::GET, "/tokens") => {
+            routes::tokens::list_tokens(&claims, state.clone()).await
+        },
+        (&Method::POST, "/tokens") => {
+            routes::tokens::create_token(req, &claims, state.clone()).await
+        },
+        (&Method::GET, path) if path.starts_with("/tokens/") => {
+            let token_id = &path[8..]; // Skip "/tokens/"
+            routes::tokens::get_token(token_id, &claims, state.clone()).await
+        },
+        (&Method::DELETE, path) if path.starts_with("/tokens/") => {
+            let token_id = &path[8..]; // Skip "/tokens/"
+            routes::tokens::delete_token(token_id, &claims, state.clone()).await
+        },
+        (&Method::POST, "/debug/route") => {
+            routes::debug::debug_route(req, state.clone()).await
+        },
+        (&Method::GET, "/dns/cache") => {
+            routes::dns::get_dns_cache().await
+        },
+        (&Method::DELETE, path) if path.starts_with("/dns/cache/") => {
+            let name = &path[11..]; // Skip "/dns/cache/"
+            routes::dns::invalidate_dns_entry(name).await
+        },
+        (&Method::POST, "/auth/revoke") => {
+            routes::auth::revoke(req, &claims, state.clone()).await
+        },
         _ => {
             // Route not found
-            Ok(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body(Body::from(r#"{"error":"Not Found"}"#))
-                .unwrap())
+            Ok(problem::response(StatusCode::NOT_FOUND, "No Admin API endpoint matches this method and path"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::database::DatabaseType;
+    
+    async fn test_state(dir: &tempfile::TempDir) -> Arc<AdminApiState> {
+        let db_url = format!("sqlite://{}?mode=rwc", dir.path().join("ferrum.db").display());
+        let db_client = DatabaseClient::new(DatabaseType::SQLite, &db_url).await.unwrap();
+        db_client.migrate().await.unwrap();
+        
+        Arc::new(AdminApiState {
+            shared_config: Arc::new(RwLock::new(Configuration::default())),
+            db_client,
+            jwt_secret: "secret".to_string(),
+            operation_mode: OperationMode::Database,
+            update_tx: None,
+            config_version_retention: 10,
+            token_settings: TokenSettings {
+                access_ttl: Duration::from_secs(3600),
+                refresh_ttl: Duration::from_secs(86400),
+            },
+            group_pins: None,
+            node_debugger: None,
+            events: Arc::new(ChangeEvents::new()),
+            cors: None,
+            ui: None,
+        })
+    }
+    
+    fn admin_claims() -> Claims {
+        Claims {
+            sub: "admin".to_string(),
+            exp: u64::MAX,
+            iat: 0,
+            workspace: None,
+            role: None,
+            jti: None,
+            typ: None,
+            scope: None,
+        }
+    }
+    
+    async fn route(state: &Arc<AdminApiState>, method: Method, path: &str) -> StatusCode {
+        let req = Request::builder().method(method).uri(path).body(Body::empty()).unwrap();
+        route_request(req, Arc::clone(state), admin_claims()).await.unwrap().status()
+    }
+    
+    async fn route_as(state: &Arc<AdminApiState>, claims: Claims, method: Method, path: &str, body: serde_json::Value) -> StatusCode {
+        let req = Request::builder().method(method).uri(path).body(Body::from(body.to_string())).unwrap();
+        route_request(req, Arc::clone(state), claims).await.unwrap().status()
+    }
+    
+    fn tagged_proxy(id: &str, tags: &[&str]) -> crate::config::data_model::Proxy {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": null,
+            "tags": tags,
+            "listen_path": format!("/{}", id),
+            "backend_protocol": "http",
+            "backend_host": "localhost",
+            "backend_port": 8080,
+            "backend_path": null,
+            "backend_connect_timeout_ms": 1000,
+            "backend_read_timeout_ms": 1000,
+            "backend_write_timeout_ms": 1000,
+            "created_at": "2026-01-01T00:00:00Z",
+            "updated_at": "2026-01-01T00:00:00Z",
+        })).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_writable_modes_record_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = test_state(&dir).await;
+        
+        // Hybrid mode writes to the database like database and control plane mode
+        for (mode, writable) in [
+            (OperationMode::Database, true),
+            (OperationMode::ControlPlane, true),
+            (OperationMode::Hybrid, true),
+            (OperationMode::File, false),
+            (OperationMode::DataPlane, false),
+            (OperationMode::Docker, false),
+        ] {
+            Arc::get_mut(&mut state).unwrap().operation_mode = mode;
+            assert_eq!(routes::versions::records_version(&state, &Method::POST, "/proxies", StatusCode::CREATED), writable, "{}", mode);
+            assert_eq!(audit::records_entry(&state, StatusCode::CREATED), writable, "{}", mode);
         }
+        
+        // Reads, validation and failed changes are not recorded
+        Arc::get_mut(&mut state).unwrap().operation_mode = OperationMode::Hybrid;
+        assert!(!routes::versions::records_version(&state, &Method::GET, "/proxies", StatusCode::OK));
+        assert!(!routes::versions::records_version(&state, &Method::POST, "/config/validate", StatusCode::OK));
+        assert!(!routes::versions::records_version(&state, &Method::POST, "/proxies", StatusCode::BAD_REQUEST));
+        assert!(!audit::records_entry(&state, StatusCode::BAD_REQUEST));
+    }
+    
+    #[test]
+    fn test_path_param() {
+        assert_eq!(path_param("/proxies/p1/tap", "/proxies/", "/tap"), Some("p1"));
+        assert_eq!(path_param("/proxies/tap", "/proxies/", "/tap"), None);
+        assert_eq!(path_param("/proxies//tap", "/proxies/", "/tap"), None);
+        assert_eq!(path_param("/proxies/a/b/tap", "/proxies/", "/tap"), None);
+        assert_eq!(path_param("/consumers/c1/tap", "/proxies/", "/tap"), None);
+    }
+    
+    #[tokio::test]
+    async fn test_routes_with_empty_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(&dir).await;
+        
+        // A missing ID is not found rather than read out of the path by offset
+        assert_eq!(route(&state, Method::GET, "/proxies/tap").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::POST, "/proxies/tap").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::DELETE, "/proxies//tap").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::GET, "/proxies/metrics").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::GET, "/consumers/metrics").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::POST, "/config/versions/rollback").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::POST, "/config/versions/latest/rollback").await, StatusCode::BAD_REQUEST);
+        assert_eq!(route(&state, Method::POST, "/config/versions/promote").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::GET, "/nodes/metrics").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::GET, "/nodes/config").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::POST, "/nodes/resync").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::GET, "/nodes/health").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::POST, "/plugins/global/enable").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::POST, "/plugins/global/disable").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::POST, "/plugins/global//enable").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::GET, "/plugins/global/").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::PUT, "/plugins/global/a/b").await, StatusCode::NOT_FOUND);
+        assert_eq!(route(&state, Method::DELETE, "/plugins/global/").await, StatusCode::NOT_FOUND);
+    }
+    
+    #[tokio::test]
+    async fn test_tag_scoped_token_stays_in_scope() {
+        use crate::config::data_model::TokenScope;
+        
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(&dir).await;
+        {
+            let mut config = state.shared_config.write().await;
+            config.proxies.push(tagged_proxy("team-a-orders", &["team-a"]));
+            config.proxies.push(tagged_proxy("team-b-orders", &["team-b"]));
+        }
+        let scoped = || Claims {
+            scope: Some(TokenScope { entity_types: Vec::new(), tags: vec!["team-a".to_string()] }),
+            ..admin_claims()
+        };
+        let plugin_config = |proxy_id: &str, scope: &str| serde_json::json!({
+            "plugin_name": "key_auth",
+            "config": {},
+            "scope": scope,
+            "proxy_id": proxy_id,
+            "enabled": true,
+        });
+        
+        // Global plugins apply to every proxy, so they are out of reach
+        assert_eq!(route_as(&state, scoped(), Method::POST, "/plugins/global", serde_json::json!({})).await, StatusCode::FORBIDDEN);
+        assert_eq!(route_as(&state, scoped(), Method::GET, "/plugins/global", serde_json::json!({})).await, StatusCode::FORBIDDEN);
+        assert_eq!(route_as(&state, scoped(), Method::POST, "/plugins/global/g1/disable", serde_json::json!({})).await, StatusCode::FORBIDDEN);
+        assert_eq!(route_as(&state, scoped(), Method::POST, "/plugins/config", plugin_config("team-a-orders", "global")).await, StatusCode::FORBIDDEN);
+        
+        // Plugin configs may only be added to proxies carrying the token's tags
+        assert_eq!(route_as(&state, scoped(), Method::POST, "/plugins/config", plugin_config("team-b-orders", "proxy")).await, StatusCode::FORBIDDEN);
+        assert_eq!(route_as(&state, scoped(), Method::POST, "/plugins/config", plugin_config("missing", "proxy")).await, StatusCode::FORBIDDEN);
+        assert_ne!(route_as(&state, scoped(), Method::POST, "/plugins/config", plugin_config("team-a-orders", "proxy")).await, StatusCode::FORBIDDEN);
+    }
+    
+    #[tokio::test]
+    async fn test_status_detail() {
+        use crate::config::data_model::AdminRole;
+        
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(&dir).await;
+        state.shared_config.write().await.proxies.push(tagged_proxy("orders", &[]));
+        health::record_listener("admin_http", "127.0.0.1:9000".parse().unwrap());
+        
+        // Unlike /status, the detail needs a token
+        let req = Request::builder().uri("/status/detail").body(Body::empty()).unwrap();
+        let response = handle_api_request(req, Arc::clone(&state), "127.0.0.1:4000".parse().unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        
+        // Read access is enough
+        let read_only = Claims { role: Some(AdminRole::ReadOnly), ..admin_claims() };
+        assert_eq!(rbac::required_role(&Method::GET, "/status/detail"), AdminRole::ReadOnly);
+        let req = Request::builder().uri("/status/detail").body(Body::empty()).unwrap();
+        let response = route_request(req, Arc::clone(&state), read_only).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("cache-control").unwrap(), "no-store");
+        
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let detail: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(detail["build"]["name"], env!("CARGO_PKG_NAME"));
+        assert_eq!(detail["build"]["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(detail["mode"], serde_json::to_value(OperationMode::Database).unwrap());
+        assert!(detail["uptime_seconds"].as_i64().unwrap() >= 0);
+        assert_eq!(detail["listeners"]["admin_http"], "127.0.0.1:9000");
+        assert!(detail["active"]["connections"].is_i64());
+        assert!(detail["active"]["requests"].is_i64());
+        assert_eq!(detail["config"]["proxies"], 1);
+        assert!(detail["health"]["status"].is_string());
+        assert!(detail["health"]["config"].is_object());
     }
 }
@@ -0,0 +1,260 @@
+//! OpenAPI 3.0 description of the Admin API, served at GET /openapi.json
+
+use hyper::Method;
+use once_cell::sync::Lazy;
+use schemars::gen::SchemaSettings;
+use serde_json::{json, Map, Value};
+
+use crate::config::data_model::{AdminUser, ApiToken, Certificate, Consumer, PluginConfig, Proxy, Sni};
+use super::{problem, rbac};
+
+/// What a request or response body holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schema {
+    /// One entity of the named component schema
+    Entity(&'static str),
+    /// A page of entities of the named component schema
+    List(&'static str),
+    /// A JSON object not described further
+    Object,
+    /// No body
+    Empty,
+}
+
+/// An operation of the Admin API
+#[derive(Debug, Clone, Copy)]
+pub struct Route {
+    pub method: &'static str,
+    /// Path with `{name}` parameters
+    pub path: &'static str,
+    pub tag: &'static str,
+    pub summary: &'static str,
+    pub request: Schema,
+    /// Status code and body of a successful response
+    pub status: u16,
+    pub response: Schema,
+}
+
+const fn route(method: &'static str, path: &'static str, tag: &'static str, summary: &'static str, request: Schema, status: u16, response: Schema) -> Route {
+    Route { method, path, tag, summary, request, status, response }
+}
+
+use Schema::{Empty, Entity, List, Object};
+
+pub const ROUTES: &[Route] = &[
+    route("GET", "/openapi.json", "Meta", "Get this document", Empty, 200, Object),
+    route("GET", "/status", "Meta", "Check that the Admin API is running", Empty, 200, Object),
+    route("GET", "/health", "Meta", "Check readiness, with 503 until a configuration is loaded", Empty, 200, Object),
+    route("GET", "/status/detail", "Meta", "Get build, uptime, listener, load and health detail of this instance", Empty, 200, Object),
+    route("POST", "/auth/login", "Authentication", "Exchange a password or client certificate for tokens", Object, 200, Object),
+    route("POST", "/auth/refresh", "Authentication", "Exchange a refresh token for new tokens", Object, 200, Object),
+    route("POST", "/auth/revoke", "Authentication", "Revoke a token", Object, 204, Empty),
+    route("GET", "/proxies", "Proxies", "List proxies", Empty, 200, List("Proxy")),
+    route("POST", "/proxies", "Proxies", "Create a proxy", Entity("Proxy"), 201, Entity("Proxy")),
+    route("GET", "/proxies/{proxy_id}", "Proxies", "Get a proxy", Empty, 200, Entity("Proxy")),
+    route("PUT", "/proxies/{proxy_id}", "Proxies", "Update a proxy", Entity("Proxy"), 200, Entity("Proxy")),
+    route("PATCH", "/proxies/{proxy_id}", "Proxies", "Change some fields of a proxy, such as enabled", Object, 200, Entity("Proxy")),
+    route("DELETE", "/proxies/{proxy_id}", "Proxies", "Delete a proxy", Empty, 204, Empty),
+    route("GET", "/proxies/{proxy_id}/metrics", "Proxies", "Get a proxy's traffic over the last minute", Empty, 200, Object),
+    route("POST", "/proxies/{proxy_id}/tap", "Proxies", "Capture a proxy's next requests on this instance", Object, 201, Object),
+    route("GET", "/proxies/{proxy_id}/tap", "Proxies", "Get the requests a tap has captured", Empty, 200, Object),
+    route("DELETE", "/proxies/{proxy_id}/tap", "Proxies", "Stop a tap and discard its captures", Empty, 204, Empty),
+    route("GET", "/consumers", "Consumers", "List consumers", Empty, 200, List("Consumer")),
+    route("POST", "/consumers", "Consumers", "Create a consumer", Entity("Consumer"), 201, Entity("Consumer")),
+    route("GET", "/consumers/{consumer_id}", "Consumers", "Get a consumer", Empty, 200, Entity("Consumer")),
+    route("PUT", "/consumers/{consumer_id}", "Consumers", "Update a consumer", Entity("Consumer"), 200, Entity("Consumer")),
+    route("DELETE", "/consumers/{consumer_id}", "Consumers", "Delete a consumer", Empty, 204, Empty),
+    route("GET", "/consumers/{consumer_id}/metrics", "Consumers", "Get a consumer's traffic over the last minute", Empty, 200, Object),
+    route("GET", "/consumers/{consumer_id}/credentials", "Consumers", "List a consumer's credentials by type, without secrets", Empty, 200, Object),
+    route("GET", "/consumers/{consumer_id}/credentials/{credential_type}", "Consumers", "Get a consumer's credential, without secrets", Empty, 200, Object),
+    route("PUT", "/consumers/{consumer_id}/credentials/{credential_type}", "Consumers", "Set a consumer's credential", Object, 200, Object),
+    route("DELETE", "/consumers/{consumer_id}/credentials/{credential_type}", "Consumers", "Delete a consumer's credential", Empty, 204, Empty),
+    route("POST", "/consumers/{consumer_id}/credentials/key_auth", "Consumers", "Add an API key, returned only in this response", Object, 201, Object),
+    route("DELETE", "/consumers/{consumer_id}/credentials/key_auth/{key_id}", "Consumers", "Revoke an API key", Empty, 204, Empty),
+    route("POST", "/consumers/{consumer_id}/credentials/key_auth/{key_id}/rotate", "Consumers", "Replace an API key, keeping the old one valid for a grace period", Object, 201, Object),
+    route("GET", "/plugins", "Plugins", "List the available plugin types", Empty, 200, Object),
+    route("GET", "/plugins/config", "Plugins", "List plugin configs", Empty, 200, List("PluginConfig")),
+    route("POST", "/plugins/config", "Plugins", "Create a plugin config", Entity("PluginConfig"), 201, Entity("PluginConfig")),
+    route("GET", "/plugins/config/{config_id}", "Plugins", "Get a plugin config", Empty, 200, Entity("PluginConfig")),
+    route("PUT", "/plugins/config/{config_id}", "Plugins", "Update a plugin config", Entity("PluginConfig"), 200, Entity("PluginConfig")),
+    route("DELETE", "/plugins/config/{config_id}", "Plugins", "Delete a plugin config", Empty, 204, Empty),
+    route("GET", "/plugins/global", "Plugins", "List global plugins, which run for every proxy of their workspace", Empty, 200, List("PluginConfig")),
+    route("POST", "/plugins/global", "Plugins", "Add a global plugin", Object, 201, Entity("PluginConfig")),
+    route("GET", "/plugins/global/{plugin_id}", "Plugins", "Get a global plugin", Empty, 200, Entity("PluginConfig")),
+    route("PUT", "/plugins/global/{plugin_id}", "Plugins", "Update a global plugin", Object, 200, Entity("PluginConfig")),
+    route("DELETE", "/plugins/global/{plugin_id}", "Plugins", "Delete a global plugin", Empty, 204, Empty),
+    route("POST", "/plugins/global/{plugin_id}/enable", "Plugins", "Enable a global plugin", Empty, 200, Entity("PluginConfig")),
+    route("POST", "/plugins/global/{plugin_id}/disable", "Plugins", "Disable a global plugin without deleting it", Empty, 200, Entity("PluginConfig")),
+    route("GET", "/certificates", "Certificates", "List certificates", Empty, 200, List("Certificate")),
+    route("POST", "/certificates", "Certificates", "Upload a certificate", Object, 201, Entity("Certificate")),
+    route("GET", "/certificates/{certificate_id}", "Certificates", "Get a certificate", Empty, 200, Entity("Certificate")),
+    route("PUT", "/certificates/{certificate_id}", "Certificates", "Replace a certificate", Object, 200, Entity("Certificate")),
+    route("DELETE", "/certificates/{certificate_id}", "Certificates", "Delete a certificate and its SNIs", Empty, 204, Empty),
+    route("GET", "/snis", "SNIs", "List SNIs", Empty, 200, List("Sni")),
+    route("POST", "/snis", "SNIs", "Map a hostname to a certificate", Entity("Sni"), 201, Entity("Sni")),
+    route("GET", "/snis/{sni_id}", "SNIs", "Get an SNI", Empty, 200, Entity("Sni")),
+    route("PUT", "/snis/{sni_id}", "SNIs", "Update an SNI", Entity("Sni"), 200, Entity("Sni")),
+    route("DELETE", "/snis/{sni_id}", "SNIs", "Delete an SNI", Empty, 204, Empty),
+    route("GET", "/config/export", "Configuration", "Export the configuration", Empty, 200, Object),
+    route("POST", "/config/import", "Configuration", "Replace the configuration with a document", Object, 200, Object),
+    route("POST", "/config/bulk", "Configuration", "Create or update many entities in one transaction", Object, 200, Object),
+    route("POST", "/config/validate", "Configuration", "Check a document without applying it", Object, 200, Object),
+    route("GET", "/config/versions", "Configuration", "List configuration versions", Empty, 200, Object),
+    route("GET", "/config/versions/{version_id}", "Configuration", "Get a configuration version", Empty, 200, Object),
+    route("POST", "/config/versions/{version_id}/rollback", "Configuration", "Roll back to a version", Empty, 200, Object),
+    route("POST", "/config/versions/{version_id}/promote", "Configuration", "Pin a Data Plane group to a version", Object, 200, Object),
+    route("GET", "/config/pins", "Configuration", "List the pinned groups", Empty, 200, Object),
+    route("DELETE", "/config/pins/{group}", "Configuration", "Unpin a group", Empty, 204, Empty),
+    route("GET", "/config/diff", "Configuration", "Compare two versions", Empty, 200, Object),
+    route("GET", "/audit", "Audit", "List audit log entries", Empty, 200, Object),
+    route("GET", "/events", "Events", "Stream configuration changes as Server-Sent Events", Empty, 200, Empty),
+    route("GET", "/admin/metrics", "Metrics", "Get runtime metrics", Empty, 200, Object),
+    route("GET", "/metrics", "Metrics", "Get metrics in the Prometheus text format", Empty, 200, Empty),
+    route("GET", "/nodes/proxy-health", "Nodes", "List the proxies with backends down on each node", Empty, 200, Object),
+    route("GET", "/nodes/{node_id}/metrics", "Nodes", "Get the traffic a Data Plane node reported", Empty, 200, Object),
+    route("GET", "/nodes/{node_id}/config", "Nodes", "Get the configuration last sent to a node", Empty, 200, Object),
+    route("POST", "/nodes/{node_id}/resync", "Nodes", "Send a node a full snapshot", Empty, 202, Object),
+    route("GET", "/nodes/{node_id}/health", "Nodes", "Get or follow a node's health reports", Empty, 200, Object),
+    route("POST", "/debug/route", "Debugging", "Show how a request would be routed, without sending it", Object, 200, Object),
+    route("GET", "/dns/cache", "DNS", "List the DNS cache of this instance, with its hit and miss counts", Empty, 200, Object),
+    route("DELETE", "/dns/cache/{host}", "DNS", "Drop a name's cached addresses, SRV records and failure", Empty, 204, Empty),
+    route("GET", "/users", "Users", "List users", Empty, 200, Object),
+    route("POST", "/users", "Users", "Create a user", Object, 201, Entity("AdminUser")),
+    route("GET", "/users/{username}", "Users", "Get a user", Empty, 200, Entity("AdminUser")),
+    route("PUT", "/users/{username}", "Users", "Change a user's password or role", Object, 200, Entity("AdminUser")),
+    route("DELETE", "/users/{username}", "Users", "Delete a user", Empty, 204, Empty),
+    route("GET", "/tokens", "Tokens", "List API tokens", Empty, 200, Object),
+    route("POST", "/tokens", "Tokens", "Create an API token, limited to entity types and tags", Object, 201, Entity("ApiToken")),
+    route("GET", "/tokens/{token_id}", "Tokens", "Get an API token", Empty, 200, Entity("ApiToken")),
+    route("DELETE", "/tokens/{token_id}", "Tokens", "Revoke and delete an API token", Empty, 204, Empty),
+];
+
+/// Routes that answer without a token
+const PUBLIC_PATHS: &[&str] = &["/openapi.json", "/auth/login", "/auth/refresh"];
+
+static DOCUMENT: Lazy<String> = Lazy::new(|| document().to_string());
+
+/// The document as served, built once
+pub fn document_json() -> &'static str {
+    &DOCUMENT
+}
+
+/// Builds the OpenAPI document of the Admin API
+pub fn document() -> Value {
+    let mut paths = Map::new();
+    for route in ROUTES {
+        let item = paths.entry(route.path).or_insert_with(|| json!({}));
+        item[route.method.to_lowercase()] = operation(route);
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Ferrum Gateway Admin API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas(),
+            "securitySchemes": {
+                "bearerAuth": { "type": "http", "scheme": "bearer", "bearerFormat": "JWT" },
+            },
+        },
+        "security": [{ "bearerAuth": [] }],
+    })
+}
+
+fn operation(route: &Route) -> Value {
+    let method = Method::from_bytes(route.method.as_bytes()).expect("Routes use standard methods");
+    // Parameters are the `{name}` segments of the path
+    let parameters: Vec<Value> = route.path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{').and_then(|segment| segment.strip_suffix('}')))
+        .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }))
+        .collect();
+
+    let mut response = json!({ "description": route.summary });
+    if let Some(schema) = schema_json(route.response) {
+        response["content"] = json!({ "application/json": { "schema": schema } });
+    }
+
+    let mut operation = json!({
+        "tags": [route.tag],
+        "summary": route.summary,
+        "parameters": parameters,
+        "responses": {
+            route.status.to_string(): response,
+            "default": {
+                "description": "The request failed",
+                "content": { problem::CONTENT_TYPE: { "schema": { "$ref": "#/components/schemas/Problem" } } },
+            },
+        },
+        "x-required-role": rbac::required_role(&method, route.path).as_str(),
+    });
+    if let Some(schema) = schema_json(route.request) {
+        operation["requestBody"] = json!({
+            "required": true,
+            "content": { "application/json": { "schema": schema } },
+        });
+    }
+    if PUBLIC_PATHS.contains(&route.path) {
+        operation["security"] = json!([]);
+    }
+    operation
+}
+
+fn schema_json(schema: Schema) -> Option<Value> {
+    let reference = |name: &str| json!({ "$ref": format!("#/components/schemas/{}", name) });
+    match schema {
+        Schema::Entity(name) => Some(reference(name)),
+        Schema::List(name) => Some(json!({
+            "type": "object",
+            "properties": {
+                "data": { "type": "array", "items": reference(name) },
+                "pagination": { "type": "object" },
+            },
+        })),
+        Schema::Object => Some(json!({ "type": "object" })),
+        Schema::Empty => None,
+    }
+}
+
+/// The entity schemas, with the definitions they refer to
+fn schemas() -> Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    generator.subschema_for::<Proxy>();
+    generator.subschema_for::<Consumer>();
+    generator.subschema_for::<PluginConfig>();
+    generator.subschema_for::<Certificate>();
+    generator.subschema_for::<Sni>();
+    generator.subschema_for::<AdminUser>();
+    generator.subschema_for::<ApiToken>();
+    let mut schemas = serde_json::to_value(generator.take_definitions()).expect("Admin API schemas are serializable");
+    schemas["Problem"] = problem_schema();
+    schemas
+}
+
+/// The body of every error response, see `problem::Problem`
+fn problem_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["type", "title", "status", "detail"],
+        "properties": {
+            "type": { "type": "string" },
+            "title": { "type": "string" },
+            "status": { "type": "integer" },
+            "detail": { "type": "string" },
+            "errors": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["field", "code", "message"],
+                    "properties": {
+                        "field": { "type": "string" },
+                        "code": { "type": "string" },
+                        "message": { "type": "string" },
+                    },
+                },
+            },
+        },
+    })
+}
@@ -1,6 +1,18 @@
+use std::cmp::Ordering;
 use std::sync::Arc;
-use hyper::{Request, Body};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hyper::{Request, Response, Body, StatusCode};
 use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::admin::problem;
+
+/// Query params that are not field filters
+const RESERVED_PARAMS: &[&str] = &["page", "offset", "limit", "cursor", "sort", "fields", "workspace"];
+
+/// Query params that choose a page, left out of the `next` link
+const PAGE_PARAMS: &[&str] = &["page", "offset", "cursor"];
 
 /// Pagination settings from query params
 #[derive(Debug, Clone, Deserialize)]
@@ -12,6 +24,10 @@ pub struct PaginationQuery {
     /// Number of items per page
     #[serde(default = "default_limit")]
     pub limit: usize,
+    
+    /// Number of items to skip, in place of `page`
+    #[serde(default)]
+    pub offset: Option<usize>,
 }
 
 fn default_page() -> usize {
@@ -46,6 +62,14 @@ pub struct PaginationMeta {
     
     /// Total number of pages
     pub pages: usize,
+    
+    /// Cursor for the next page, when there is one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    
+    /// Link to the next page, with the same filters, sort and fields
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
 }
 
 impl PaginationQuery {
@@ -58,6 +82,7 @@ impl PaginationQuery {
         
         let mut page = default_page();
         let mut limit = default_limit();
+        let mut offset = None;
         
         for (k, v) in pairs {
             match k.as_str() {
@@ -68,6 +93,11 @@ impl PaginationQuery {
                         }
                     }
                 },
+                "offset" => {
+                    if let Ok(o) = v.parse::<usize>() {
+                        offset = Some(o);
+                    }
+                },
                 "limit" => {
                     if let Ok(l) = v.parse::<usize>() {
                         if l > 0 {
@@ -80,12 +110,17 @@ impl PaginationQuery {
             }
         }
         
-        Self { page, limit }
+        // An offset names the page it starts
+        if let Some(offset) = offset {
+            page = offset / limit + 1;
+        }
+        
+        Self { page, limit, offset }
     }
     
     /// Calculate offset for database queries
     pub fn offset(&self) -> usize {
-        (self.page - 1) * self.limit
+        self.offset.unwrap_or((self.page - 1) * self.limit)
     }
     
     /// Create pagination metadata
@@ -101,6 +136,8 @@ impl PaginationQuery {
             limit: self.limit,
             total,
             pages,
+            next_cursor: None,
+            next: None,
         }
     }
     
@@ -128,3 +165,291 @@ pub fn create_paginated_response<T: Serialize>(items: Vec<T>, meta: PaginationMe
         pagination: meta,
     }
 }
+
+/// How a field filter compares values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    /// `field=value`; array fields match when any element is equal
+    Equals,
+    /// `field_contains=value`, case-insensitive
+    Contains,
+}
+
+/// Filter on a field of the listed entities; nested fields are addressed with dots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldFilter {
+    pub field: String,
+    pub op: FilterOp,
+    pub value: String,
+}
+
+impl FieldFilter {
+    fn matches(&self, item: &Value) -> bool {
+        let path: Vec<&str> = self.field.split('.').collect();
+        let mut values = Vec::new();
+        field_values(item, &path, &mut values);
+        values.into_iter().any(|value| self.matches_value(value))
+    }
+    
+    fn matches_value(&self, value: &Value) -> bool {
+        match value {
+            Value::String(text) => self.matches_text(text),
+            Value::Null | Value::Array(_) | Value::Object(_) => false,
+            other => self.matches_text(&other.to_string()),
+        }
+    }
+    
+    /// Whether a value, as text, passes the filter
+    pub fn matches_text(&self, text: &str) -> bool {
+        match self.op {
+            FilterOp::Equals => text == self.value,
+            FilterOp::Contains => text.to_lowercase().contains(&self.value.to_lowercase()),
+        }
+    }
+}
+
+/// The values at a dotted path; arrays on the way are searched element by
+/// element, so `plugins.plugin_config_id` reaches every association of a proxy
+fn field_values<'a>(value: &'a Value, path: &[&str], values: &mut Vec<&'a Value>) {
+    match (path.split_first(), value) {
+        (None, Value::Array(elements)) => values.extend(elements),
+        (None, value) => values.push(value),
+        (Some((field, rest)), Value::Object(object)) => {
+            if let Some(value) = object.get(*field) {
+                field_values(value, rest, values);
+            }
+        },
+        (Some(_), Value::Array(elements)) => {
+            for element in elements {
+                field_values(element, path, values);
+            }
+        },
+        (Some(_), _) => {},
+    }
+}
+
+/// Position after the last item of a page: its sort value and ID
+#[derive(Debug, Serialize, Deserialize)]
+struct Cursor {
+    sort: String,
+    value: Value,
+    id: Value,
+}
+
+/// Filtering, sorting, field selection and cursor settings from query params,
+/// on top of page/offset/limit
+///
+/// Results are ordered by `sort` (default `id`, prefix `-` for descending) with
+/// the ID breaking ties, so a cursor stays valid while entities are added or removed.
+#[derive(Debug, Clone)]
+pub struct ListQuery {
+    pub pagination: PaginationQuery,
+    pub filters: Vec<FieldFilter>,
+    pub sort: String,
+    pub descending: bool,
+    pub cursor: Option<String>,
+    /// Fields to return of each item (`fields=id,name`); all of them when unset
+    pub fields: Option<Vec<String>>,
+    /// Path and the query params other than the page, to link the next page with
+    link_base: (String, Vec<(String, String)>),
+}
+
+impl ListQuery {
+    /// Extract list parameters from the request query string. Errors describe
+    /// the invalid parameter.
+    pub fn from_request(req: &Request<Body>) -> Result<Self, String> {
+        Self::from_request_sorted(req, "id")
+    }
+    
+    /// As `from_request`, with another order than by ID when `sort` is not given
+    pub fn from_request_sorted(req: &Request<Body>, default_sort: &str) -> Result<Self, String> {
+        let (sort, descending) = parse_sort(default_sort)?;
+        let mut query = Self {
+            pagination: PaginationQuery::from_request(req),
+            filters: Vec::new(),
+            sort,
+            descending,
+            cursor: None,
+            fields: None,
+            link_base: (req.uri().path().to_string(), Vec::new()),
+        };
+        
+        let query_string = req.uri().query().unwrap_or("");
+        for (key, value) in url::form_urlencoded::parse(query_string.as_bytes()) {
+            let value = value.into_owned();
+            if !PAGE_PARAMS.contains(&key.as_ref()) {
+                query.link_base.1.push((key.to_string(), value.clone()));
+            }
+            match key.as_ref() {
+                "sort" => (query.sort, query.descending) = parse_sort(&value)?,
+                "cursor" => query.cursor = Some(value),
+                "fields" => {
+                    let fields: Vec<String> = value.split(',')
+                        .map(str::trim)
+                        .filter(|field| !field.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                    if fields.is_empty() {
+                        return Err("'fields' must name at least one field".to_string());
+                    }
+                    query.fields = Some(fields);
+                },
+                key if RESERVED_PARAMS.contains(&key) => {},
+                key => {
+                    let (field, op) = match key.strip_suffix("_contains") {
+                        Some(field) => (field, FilterOp::Contains),
+                        None => (key, FilterOp::Equals),
+                    };
+                    query.filters.push(FieldFilter { field: field.to_string(), op, value });
+                },
+            }
+        }
+        Ok(query)
+    }
+    
+    /// Removes the filters on a field, for handlers that filter on it themselves
+    pub fn take_filters(&mut self, field: &str) -> Vec<FieldFilter> {
+        let (taken, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.filters).into_iter()
+            .partition(|filter| filter.field == field);
+        self.filters = kept;
+        taken
+    }
+    
+    /// Filter, sort and paginate items. A cursor takes precedence over `page`.
+    pub fn apply<T: Serialize>(&self, items: impl IntoIterator<Item = T>) -> Result<(Vec<Value>, PaginationMeta), String> {
+        let mut matching = Vec::new();
+        for item in items {
+            let item = serde_json::to_value(item).map_err(|e| e.to_string())?;
+            if self.filters.iter().all(|filter| filter.matches(&item)) {
+                matching.push(item);
+            }
+        }
+        
+        let sort_pointer = json_pointer(&self.sort);
+        let key = |item: &Value| -> (Value, Value) {
+            (
+                item.pointer(&sort_pointer).cloned().unwrap_or(Value::Null),
+                item.get("id").cloned().unwrap_or(Value::Null),
+            )
+        };
+        let order = |a: &(Value, Value), b: &(Value, Value)| -> Ordering {
+            let ordering = compare_values(&a.0, &b.0).then_with(|| compare_values(&a.1, &b.1));
+            if self.descending { ordering.reverse() } else { ordering }
+        };
+        matching.sort_by(|a, b| order(&key(a), &key(b)));
+        
+        let total = matching.len();
+        let start = match &self.cursor {
+            Some(cursor) => {
+                let cursor = self.decode_cursor(cursor)?;
+                let position = (cursor.value, cursor.id);
+                matching.partition_point(|item| order(&key(item), &position) != Ordering::Greater)
+            },
+            None => self.pagination.offset().min(total),
+        };
+        let end = (start + self.pagination.limit).min(total);
+        
+        let mut meta = self.pagination.create_meta(total);
+        if end < total {
+            if let Some(last) = end.checked_sub(1).and_then(|last| matching.get(last)) {
+                let cursor = self.encode_cursor(key(last));
+                meta.next = Some(self.next_link(&cursor));
+                meta.next_cursor = Some(cursor);
+            }
+        }
+        
+        matching.truncate(end);
+        let mut page = matching.split_off(start);
+        if let Some(fields) = &self.fields {
+            page = page.iter().map(|item| select_fields(item, fields)).collect();
+        }
+        Ok((page, meta))
+    }
+    
+    fn next_link(&self, cursor: &str) -> String {
+        let (path, params) = &self.link_base;
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params)
+            .append_pair("cursor", cursor)
+            .finish();
+        format!("{}?{}", path, query)
+    }
+    
+    fn encode_cursor(&self, (value, id): (Value, Value)) -> String {
+        let cursor = Cursor { sort: self.sort.clone(), value, id };
+        URL_SAFE_NO_PAD.encode(json!(cursor).to_string())
+    }
+    
+    fn decode_cursor(&self, cursor: &str) -> Result<Cursor, String> {
+        let cursor: Cursor = URL_SAFE_NO_PAD.decode(cursor).ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| "'cursor' is not a valid cursor".to_string())?;
+        if cursor.sort != self.sort {
+            return Err(format!("'cursor' was issued for sort '{}'", cursor.sort));
+        }
+        Ok(cursor)
+    }
+}
+
+/// 400 response for list parameters `ListQuery` rejected
+pub fn invalid_query_response(message: &str) -> Response<Body> {
+    problem::response(StatusCode::BAD_REQUEST, message)
+}
+
+/// `name` sorts ascending, `-name` descending
+fn parse_sort(sort: &str) -> Result<(String, bool), String> {
+    let (field, descending) = match sort.strip_prefix('-') {
+        Some(field) => (field, true),
+        None => (sort, false),
+    };
+    if field.is_empty() {
+        return Err("'sort' must name a field".to_string());
+    }
+    Ok((field.to_string(), descending))
+}
+
+/// The named fields of an item, nested fields under their parents; fields
+/// the item does not have are left out
+fn select_fields(item: &Value, fields: &[String]) -> Value {
+    let mut selected = Value::Object(Default::default());
+    for field in fields {
+        let value = match item.pointer(&json_pointer(field)) {
+            Some(value) => value.clone(),
+            None => continue,
+        };
+        let mut target = &mut selected;
+        let mut parts = field.split('.').peekable();
+        while let Some(part) = parts.next() {
+            let object = match target {
+                Value::Object(object) => object,
+                // A parent field was selected whole
+                _ => break,
+            };
+            if parts.peek().is_none() {
+                object.insert(part.to_string(), value);
+                break;
+            }
+            target = object.entry(part).or_insert_with(|| Value::Object(Default::default()));
+        }
+    }
+    selected
+}
+
+/// `backend.host` -> `/backend/host`
+fn json_pointer(field: &str) -> String {
+    field.split('.')
+        .map(|part| format!("/{}", part.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+/// Orders missing values first, then numbers, strings and everything else by its JSON text
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Less,
+        (_, Value::Null) => Ordering::Greater,
+        (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()).unwrap_or(Ordering::Equal),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (a, b) => a.to_string().cmp(&b.to_string()),
+    }
+}
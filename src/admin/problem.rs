@@ -0,0 +1,139 @@
+use hyper::header::HeaderValue;
+use hyper::{Body, Response, StatusCode};
+use serde::Serialize;
+
+use crate::config::validation::ValidationIssue;
+
+/// Media type of every Admin API error response (RFC 7807)
+pub const CONTENT_TYPE: &str = "application/problem+json";
+
+/// `type` of a request body that is not JSON
+pub const INVALID_BODY: &str = "urn:ferrumgw:problem:invalid-body";
+
+/// `type` of an entity with invalid fields; `errors` lists them
+pub const VALIDATION: &str = "urn:ferrumgw:problem:validation";
+
+/// One invalid field of a request body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    /// Path of the field in the body, such as `listen_path` or `plugins[0].plugin_config_id`
+    pub field: String,
+    /// Machine readable kind of problem, such as `duplicate_listen_path` or `schema`
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, code: &'static str, message: impl Into<String>) -> Self {
+        Self { field: field.into(), code, message: message.into() }
+    }
+
+    /// An issue found in a configuration, about the entity at `prefix` such as `proxies[3]`.
+    /// Issues of other entities give None.
+    pub fn from_issue(issue: &ValidationIssue, prefix: &str) -> Option<Self> {
+        let field = issue.path.strip_prefix(prefix)?;
+        let field = match field.strip_prefix('.') {
+            Some(field) => field,
+            None if field.is_empty() => "(body)",
+            None => return None,
+        };
+        Some(Self::new(field, issue.code, issue.message.clone()))
+    }
+}
+
+impl From<&ValidationIssue> for FieldError {
+    fn from(issue: &ValidationIssue) -> Self {
+        Self::new(issue.path.clone(), issue.code, issue.message.clone())
+    }
+}
+
+/// An error response body. `type` is `about:blank` unless the problem has
+/// more to it than its status, as validation errors do.
+#[derive(Debug, Clone, Serialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: &'static str,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode, detail: impl Into<String>) -> Self {
+        Self {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: detail.into(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// 400 for a body that could not be parsed at all
+    pub fn invalid_body(detail: impl Into<String>) -> Self {
+        Self {
+            problem_type: INVALID_BODY,
+            title: "Invalid request body".to_string(),
+            ..Self::new(StatusCode::BAD_REQUEST, detail)
+        }
+    }
+
+    /// 422 listing the fields of an entity that are invalid
+    pub fn validation(kind: &str, errors: Vec<FieldError>) -> Self {
+        let detail = match errors.len() {
+            1 => format!("The {} has an invalid field", kind),
+            count => format!("The {} has {} invalid fields", kind, count),
+        };
+        Self {
+            problem_type: VALIDATION,
+            title: "Validation failed".to_string(),
+            errors,
+            ..Self::new(StatusCode::UNPROCESSABLE_ENTITY, detail)
+        }
+    }
+
+    /// Names the field a problem such as a conflict is about
+    pub fn with_field(mut self, field: impl Into<String>, code: &'static str) -> Self {
+        let message = self.detail.clone();
+        self.errors.push(FieldError::new(field, code, message));
+        self
+    }
+
+    pub fn status(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    pub fn into_response(self) -> Response<Body> {
+        let body = serde_json::to_string(&self).unwrap_or_default();
+        Response::builder()
+            .status(self.status())
+            .header("Content-Type", CONTENT_TYPE)
+            .body(Body::from(body))
+            .unwrap()
+    }
+}
+
+/// An error response with just a status and a message
+pub fn response(status: StatusCode, detail: impl Into<String>) -> Response<Body> {
+    Problem::new(status, detail).into_response()
+}
+
+/// 409 for writes refused because the gateway reads its configuration from a file
+pub fn file_mode_response() -> Response<Body> {
+    response(StatusCode::CONFLICT, "Cannot modify config — currently running in File Mode")
+}
+
+/// 401 asking for a bearer token
+pub fn unauthorized_response(detail: impl Into<String>) -> Response<Body> {
+    let mut response = response(StatusCode::UNAUTHORIZED, detail);
+    response.headers_mut().insert("WWW-Authenticate", HeaderValue::from_static("Bearer"));
+    response
+}
+
+/// 500 for a handler that failed. The error is logged rather than echoed, since
+/// it may describe the database or the host.
+pub fn internal_error_response() -> Response<Body> {
+    response(StatusCode::INTERNAL_SERVER_ERROR, "The request could not be completed; see the gateway log")
+}
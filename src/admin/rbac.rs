@@ -0,0 +1,37 @@
+use hyper::{Body, Method, Response, StatusCode};
+
+use crate::admin::problem;
+use crate::config::data_model::AdminRole;
+
+/// The least role that may make a request. Reading is open to every role;
+/// changing entities and tapping proxies need an operator; user management and operations on the
+/// whole configuration or on Data Plane nodes need an admin.
+pub fn required_role(method: &Method, path: &str) -> AdminRole {
+    if path == "/users" || path.starts_with("/users/") {
+        return AdminRole::Admin;
+    }
+    // Tapped requests can carry personal data, so even reading them needs an operator
+    if path.starts_with("/proxies/") && path.ends_with("/tap") {
+        return AdminRole::Operator;
+    }
+    // Validating, debugging routes and revoking one's own tokens change nothing.
+    // Any user may manage API tokens of their own, capped at their role.
+    if matches!(*method, Method::GET | Method::HEAD) || (method == Method::POST && (path == "/config/validate" || path == "/debug/route" || path == "/auth/revoke"))
+        || path == "/tokens" || path.starts_with("/tokens/") {
+        return AdminRole::ReadOnly;
+    }
+
+    let admin_only = path == "/config/import"
+        || path.starts_with("/config/pins/")
+        || path.starts_with("/nodes/")
+        || (path.starts_with("/config/versions/") && (path.ends_with("/rollback") || path.ends_with("/promote")));
+    if admin_only {
+        AdminRole::Admin
+    } else {
+        AdminRole::Operator
+    }
+}
+
+pub fn forbidden(role: AdminRole, required: AdminRole) -> Response<Body> {
+    problem::response(StatusCode::FORBIDDEN, format!("Role {} may not make this request, it needs {}", role.as_str(), required.as_str()))
+}
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use hyper::{Body, Request, Response, StatusCode};
+
+use crate::admin::AdminApiState;
+use crate::admin::problem;
+use crate::admin::pagination::{PaginationQuery, create_paginated_response, invalid_query_response};
+use crate::config::data_model::AuditFilter;
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+/// Handler for GET /audit endpoint - lists audit log entries, newest first,
+/// filtered by `actor`, `action`, `entity_type`, `entity_id`, `since` and `until`
+/// and paginated by `page` or `cursor`
+pub async fn list_audit_log(req: &Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let mut filter = AuditFilter::default();
+    let query_string = req.uri().query().unwrap_or("");
+    for (key, value) in url::form_urlencoded::parse(query_string.as_bytes()) {
+        let value = value.into_owned();
+        match key.as_ref() {
+            "actor" => filter.actor = Some(value),
+            "action" => filter.action = Some(value),
+            "entity_type" => filter.entity_type = Some(value),
+            "entity_id" => filter.entity_id = Some(value),
+            "since" | "until" => {
+                let time = match DateTime::parse_from_rfc3339(&value) {
+                    Ok(time) => time.with_timezone(&Utc),
+                    Err(_) => return Ok(problem::response(StatusCode::BAD_REQUEST, format!("'{}' must be an RFC 3339 timestamp", key))),
+                };
+                if key == "since" {
+                    filter.since = Some(time);
+                } else {
+                    filter.until = Some(time);
+                }
+            },
+            "cursor" => match value.parse::<i64>() {
+                Ok(id) => filter.before_id = Some(id),
+                Err(_) => return Ok(invalid_query_response("'cursor' is not a valid cursor")),
+            },
+            _ => {},
+        }
+    }
+
+    let pagination = PaginationQuery::from_request(req);
+    // A cursor takes precedence over `page`
+    let offset = if filter.before_id.is_some() { 0 } else { pagination.offset() };
+    let (entries, total) = state.db_client
+        .list_audit_entries(&filter, pagination.limit, offset)
+        .await?;
+    // Entries are listed newest first, so the next page starts below the last ID
+    let mut meta = pagination.create_meta(total);
+    if entries.len() == pagination.limit && total > offset + entries.len() {
+        meta.next_cursor = entries.last().map(|entry| entry.id.to_string());
+    }
+    let response = create_paginated_response(entries, meta);
+
+    Ok(json_response(StatusCode::OK, serde_json::to_string(&response)?))
+}
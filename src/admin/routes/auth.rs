@@ -0,0 +1,175 @@
+use std::sync::Arc;
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use hyper::{Body, Request, Response, StatusCode};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::admin::AdminApiState;
+use crate::admin::problem;
+use crate::admin::auth::{self, Claims};
+use crate::config::credentials;
+use crate::config::data_model::{AdminRole, AdminUser};
+use crate::proxy::client_auth::ClientCertificate;
+
+/// Body of POST /auth/login. Without a password, the user is the common name
+/// of the client certificate presented to the HTTPS Admin API.
+#[derive(Debug, Default, Deserialize)]
+struct LoginRequest {
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+}
+
+/// Body of POST /auth/refresh
+#[derive(Debug, Deserialize)]
+struct RefreshRequest {
+    refresh_token: String,
+}
+
+/// Body of POST /auth/revoke; without a token, the token making the request is revoked
+#[derive(Debug, Default, Deserialize)]
+struct RevokeRequest {
+    #[serde(default)]
+    token: Option<String>,
+}
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+/// Reads an optional JSON body; an empty body gives the default
+async fn parse_body<T: serde::de::DeserializeOwned + Default>(req: Request<Body>) -> Result<Result<T, Response<Body>>> {
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+    if body_bytes.is_empty() {
+        return Ok(Ok(T::default()));
+    }
+    Ok(serde_json::from_slice(&body_bytes).map_err(|e| problem::response(StatusCode::BAD_REQUEST, format!("Invalid request body: {}", e))))
+}
+
+fn expires_at(exp: u64) -> DateTime<Utc> {
+    Utc.timestamp_opt(exp as i64, 0).single().unwrap_or_else(Utc::now)
+}
+
+/// Issues an access token and a refresh token to a user
+fn issue_tokens(user: &AdminUser, state: &AdminApiState) -> Result<Response<Body>> {
+    let settings = state.token_settings;
+    let access = auth::issue_token(&user.username, user.role, false, &state.jwt_secret, settings.access_ttl)?;
+    let refresh = auth::issue_token(&user.username, user.role, true, &state.jwt_secret, settings.refresh_ttl)?;
+
+    Ok(json_response(StatusCode::OK, serde_json::json!({
+        "access_token": access.token,
+        "token_type": "Bearer",
+        "expires_in": settings.access_ttl.as_secs(),
+        "refresh_token": refresh.token,
+        "refresh_expires_in": settings.refresh_ttl.as_secs(),
+        "role": user.role,
+    }).to_string()))
+}
+
+/// Handler for POST /auth/login endpoint - exchanges a username and password,
+/// or a client certificate, for an access token and a refresh token
+pub async fn login(req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let client_certificate = req.extensions().get::<Arc<ClientCertificate>>().cloned();
+    let request: LoginRequest = match parse_body(req).await? {
+        Ok(request) => request,
+        Err(response) => return Ok(response),
+    };
+
+    let username = match (&request.password, client_certificate.as_deref().and_then(ClientCertificate::common_name)) {
+        (Some(_), _) => match &request.username {
+            Some(username) => username.clone(),
+            None => return Ok(problem::response(StatusCode::BAD_REQUEST, "username is required with a password")),
+        },
+        // A certificate can only log in as the user it names
+        (None, Some(common_name)) if request.username.as_deref().map_or(true, |username| username == common_name) => common_name.to_string(),
+        (None, _) => return Ok(problem::unauthorized_response("A password or a client certificate naming the user is required")),
+    };
+
+    let user = match state.db_client.get_admin_user(&username).await? {
+        Some(user) => user,
+        None => {
+            warn!("Admin API login failed for unknown user {}", username);
+            return Ok(problem::unauthorized_response("Invalid credentials"));
+        },
+    };
+    if let Some(password) = &request.password {
+        if password.is_empty() || !credentials::verify_password(password, &user.password_hash) {
+            warn!("Admin API login failed for user {}: wrong password", username);
+            return Ok(problem::unauthorized_response("Invalid credentials"));
+        }
+    }
+
+    info!("Admin API user {} logged in with role {}", user.username, user.role.as_str());
+    issue_tokens(&user, &state)
+}
+
+/// Handler for POST /auth/refresh endpoint - exchanges a refresh token for a
+/// new access token and refresh token. Each refresh token is accepted once.
+pub async fn refresh(req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+    let request: RefreshRequest = match serde_json::from_slice(&body_bytes) {
+        Ok(request) => request,
+        Err(e) => return Ok(problem::response(StatusCode::BAD_REQUEST, format!("Invalid request body: {}", e))),
+    };
+
+    let claims = match auth::decode_token(&request.refresh_token, &state.jwt_secret) {
+        Ok(claims) if claims.is_refresh_token() => claims,
+        _ => return Ok(problem::unauthorized_response("Invalid refresh token")),
+    };
+    let jti = match &claims.jti {
+        Some(jti) => jti,
+        None => return Ok(problem::unauthorized_response("Invalid refresh token")),
+    };
+    if state.db_client.is_admin_token_revoked(jti).await? {
+        return Ok(problem::unauthorized_response("Refresh token has been revoked or already used"));
+    }
+
+    // The role may have changed, and the user may be gone, since the last login
+    let user = match state.db_client.get_admin_user(&claims.sub).await? {
+        Some(user) => user,
+        None => return Ok(problem::unauthorized_response("User no longer exists")),
+    };
+
+    state.db_client.revoke_admin_token(jti, &claims.sub, expires_at(claims.exp)).await?;
+    issue_tokens(&user, &state)
+}
+
+/// Handler for POST /auth/revoke endpoint - revokes a token issued by the
+/// gateway until it expires. Users can revoke their own tokens, admins anyone's.
+pub async fn revoke(req: Request<Body>, claims: &Claims, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let request: RevokeRequest = match parse_body(req).await? {
+        Ok(request) => request,
+        Err(response) => return Ok(response),
+    };
+
+    let other = match &request.token {
+        Some(token) => match auth::decode_token(token, &state.jwt_secret) {
+            Ok(other) => Some(other),
+            Err(e) => return Ok(problem::response(StatusCode::BAD_REQUEST, format!("Invalid token: {}", e))),
+        },
+        None => None,
+    };
+    let target = other.as_ref().unwrap_or(claims);
+
+    if target.sub != claims.sub && claims.role() < AdminRole::Admin {
+        return Ok(problem::response(StatusCode::FORBIDDEN, "Only admins may revoke the tokens of other users"));
+    }
+    let jti = match &target.jti {
+        Some(jti) => jti,
+        None => return Ok(problem::response(StatusCode::BAD_REQUEST, "Token was not issued by the gateway and cannot be revoked")),
+    };
+
+    state.db_client.revoke_admin_token(jti, &target.sub, expires_at(target.exp)).await?;
+    info!("Admin API token {} of {} revoked by {}", jti, target.sub, claims.sub);
+
+    Ok(Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(Body::empty())
+        .unwrap())
+}
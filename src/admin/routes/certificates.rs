@@ -0,0 +1,208 @@
+use std::sync::Arc;
+use anyhow::Result;
+use hyper::{Body, Request, Response, StatusCode};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{debug, error};
+
+use crate::admin::AdminApiState;
+use crate::admin::problem;
+use crate::admin::pagination::{ListQuery, create_paginated_response, invalid_query_response};
+use crate::config::data_model::Certificate;
+use crate::modes::OperationMode;
+use crate::proxy::tls;
+use crate::proxy::update_manager::RouterUpdate;
+
+/// Body of POST /certificates and PUT /certificates/{id}
+#[derive(Debug, Deserialize)]
+struct CertificateRequest {
+    #[serde(default)]
+    id: Option<String>,
+    cert: String,
+    key: String,
+    #[serde(default)]
+    snis: Vec<String>,
+}
+
+/// Certificates are returned without their private key
+fn certificate_json(certificate: &Certificate) -> Value {
+    let mut json = serde_json::to_value(certificate).unwrap_or_default();
+    if let Some(object) = json.as_object_mut() {
+        object.remove("key");
+    }
+    json
+}
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn notify_config_changed(state: &AdminApiState) {
+    if let Some(update_tx) = &state.update_tx {
+        if let Err(e) = update_tx.send(RouterUpdate::ConfigChanged) {
+            debug!("Failed to notify router update: {}", e);
+        }
+    }
+}
+
+/// Parses and validates a certificate request body, returning an error response when invalid
+async fn parse_certificate_request(req: Request<Body>) -> Result<Result<CertificateRequest, Response<Body>>> {
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+
+    let request = match serde_json::from_slice::<CertificateRequest>(&body_bytes) {
+        Ok(request) => request,
+        Err(e) => return Ok(Err(problem::response(StatusCode::BAD_REQUEST, format!("Invalid certificate data: {}", e)))),
+    };
+
+    // Reject certificates the listeners would not be able to serve
+    if let Err(e) = tls::load_certified_key(&request.cert, &request.key) {
+        return Ok(Err(problem::response(StatusCode::BAD_REQUEST, format!("Invalid certificate: {:#}", e))));
+    }
+
+    Ok(Ok(request))
+}
+
+/// Handler for GET /certificates endpoint - lists all certificates
+pub async fn list_certificates(req: &Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let query = match ListQuery::from_request(req) {
+        Ok(query) => query,
+        Err(message) => return Ok(invalid_query_response(&message)),
+    };
+
+    let config = state.shared_config.read().await;
+    let (paginated, pagination_meta) = match query.apply(config.certificates.iter().map(certificate_json)) {
+        Ok(page) => page,
+        Err(message) => return Ok(invalid_query_response(&message)),
+    };
+    let response = create_paginated_response(paginated, pagination_meta);
+
+    Ok(json_response(StatusCode::OK, serde_json::to_string(&response)?))
+}
+
+/// Handler for POST /certificates endpoint - uploads a new certificate
+pub async fn create_certificate(req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let request = match parse_certificate_request(req).await? {
+        Ok(request) => request,
+        Err(response) => return Ok(response),
+    };
+
+    let id = request.id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    {
+        let config = state.shared_config.read().await;
+        if config.certificates.iter().any(|c| c.id == id) {
+            return Ok(problem::response(StatusCode::CONFLICT, format!("A certificate with ID '{}' already exists", id)));
+        }
+    }
+
+    let now = chrono::Utc::now();
+    let certificate = Certificate {
+        id,
+        cert: request.cert,
+        key: request.key,
+        snis: request.snis,
+        created_at: now,
+        updated_at: now,
+    };
+
+    match state.db_client.upsert_certificate(&certificate).await {
+        Ok(_) => {
+            notify_config_changed(&state);
+            Ok(json_response(StatusCode::CREATED, certificate_json(&certificate).to_string()))
+        },
+        Err(e) => {
+            error!("Failed to create certificate in database: {}", e);
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create certificate"))
+        }
+    }
+}
+
+/// Handler for GET /certificates/{id} endpoint - gets a specific certificate
+pub async fn get_certificate(certificate_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let config = state.shared_config.read().await;
+
+    match config.certificates.iter().find(|c| c.id == certificate_id) {
+        Some(certificate) => Ok(json_response(StatusCode::OK, certificate_json(certificate).to_string())),
+        None => Ok(problem::response(StatusCode::NOT_FOUND, "Certificate not found")),
+    }
+}
+
+/// Handler for PUT /certificates/{id} endpoint - replaces a certificate's PEM data and SNIs
+pub async fn update_certificate(certificate_id: &str, req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let request = match parse_certificate_request(req).await? {
+        Ok(request) => request,
+        Err(response) => return Ok(response),
+    };
+
+    if request.id.as_deref().map_or(false, |id| id != certificate_id) {
+        return Ok(problem::response(StatusCode::BAD_REQUEST, "Certificate ID in path does not match ID in body"));
+    }
+
+    let created_at = {
+        let config = state.shared_config.read().await;
+        match config.certificates.iter().find(|c| c.id == certificate_id) {
+            Some(existing) => existing.created_at,
+            None => return Ok(problem::response(StatusCode::NOT_FOUND, "Certificate not found")),
+        }
+    };
+
+    let certificate = Certificate {
+        id: certificate_id.to_string(),
+        cert: request.cert,
+        key: request.key,
+        snis: request.snis,
+        created_at,
+        updated_at: chrono::Utc::now(),
+    };
+
+    match state.db_client.upsert_certificate(&certificate).await {
+        Ok(_) => {
+            notify_config_changed(&state);
+            Ok(json_response(StatusCode::OK, certificate_json(&certificate).to_string()))
+        },
+        Err(e) => {
+            error!("Failed to update certificate in database: {}", e);
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update certificate"))
+        }
+    }
+}
+
+/// Handler for DELETE /certificates/{id} endpoint - deletes a certificate and its SNIs
+pub async fn delete_certificate(certificate_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    {
+        let config = state.shared_config.read().await;
+        if !config.certificates.iter().any(|c| c.id == certificate_id) {
+            return Ok(problem::response(StatusCode::NOT_FOUND, "Certificate not found"));
+        }
+    }
+
+    match state.db_client.delete_certificate(certificate_id).await {
+        Ok(_) => {
+            notify_config_changed(&state);
+            Ok(Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty())
+                .unwrap())
+        },
+        Err(e) => {
+            error!("Failed to delete certificate from database: {}", e);
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete certificate"))
+        }
+    }
+}
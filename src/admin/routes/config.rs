@@ -0,0 +1,232 @@
+use std::sync::Arc;
+use anyhow::Result;
+use hyper::{Body, Request, Response, StatusCode};
+use tracing::{debug, error, info};
+
+use crate::admin::AdminApiState;
+use crate::admin::problem;
+use crate::config::declarative::{self, ConfigDiff};
+use crate::config::document::{self, Format};
+use crate::config::file_config;
+use crate::config::validation;
+use crate::modes::OperationMode;
+use crate::proxy::update_manager::RouterUpdate;
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn query_param(req: &Request<Body>, name: &str) -> Option<String> {
+    let query_string = req.uri().query().unwrap_or("");
+    url::form_urlencoded::parse(query_string.as_bytes())
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.to_string())
+}
+
+/// Tags from `?tags=a,b` that scope an export or import; empty when not given
+fn tag_scope(req: &Request<Body>) -> Vec<String> {
+    query_param(req, "tags")
+        .map(|tags| tags.split(',').map(str::trim).filter(|tag| !tag.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn content_type(format: Format) -> &'static str {
+    match format {
+        Format::Json => "application/json",
+        Format::Yaml => "application/yaml",
+        Format::Toml => "application/toml",
+    }
+}
+
+/// Format of an import body, from its Content-Type. JSON unless YAML or TOML is named.
+fn body_format(req: &Request<Body>) -> Format {
+    let content_type = req.headers()
+        .get(hyper::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    if content_type.contains("yaml") {
+        Format::Yaml
+    } else if content_type.contains("toml") {
+        Format::Toml
+    } else {
+        Format::Json
+    }
+}
+
+/// Handler for GET /config/export endpoint - dumps the full configuration as one document
+pub async fn export_config(req: &Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let format = match query_param(req, "format").as_deref() {
+        None | Some("json") => Format::Json,
+        Some("yaml") | Some("yml") => Format::Yaml,
+        Some("toml") => Format::Toml,
+        Some(other) => return Ok(problem::response(StatusCode::BAD_REQUEST, format!("Unsupported export format '{}', expected json, yaml or toml", other))),
+    };
+
+    let tags = tag_scope(req);
+    let config = state.shared_config.read().await;
+    let document = if tags.is_empty() {
+        declarative::render(&config, format)?
+    } else {
+        declarative::render(&declarative::select_tags(&config, &tags), format)?
+    };
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", content_type(format))
+        .body(Body::from(document))
+        .unwrap())
+}
+
+/// Handler for POST /config/import endpoint - replaces the configuration with
+/// the posted document. With `?dry_run=true` only the changes are reported;
+/// with `?tags=` only the proxies, consumers and plugin configs carrying those
+/// tags are replaced.
+pub async fn import_config(req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let dry_run = query_param(&req, "dry_run").map_or(false, |v| v == "true" || v == "1");
+    let tags = tag_scope(&req);
+    let format = body_format(&req);
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+
+    let content = match std::str::from_utf8(&body_bytes) {
+        Ok(content) => content,
+        Err(_) => return Ok(problem::response(StatusCode::BAD_REQUEST, "Configuration document must be UTF-8")),
+    };
+
+    let current = state.shared_config.read().await.clone();
+
+    // A scoped document is checked together with the entities outside its scope
+    let parsed = if tags.is_empty() {
+        declarative::parse(content, format)
+    } else {
+        declarative::parse_unchecked(content, format).and_then(|document| {
+            let desired = declarative::scope_to_tags(&current, document, &tags);
+            declarative::validate(&desired)?;
+            Ok(desired)
+        })
+    };
+    let desired = match parsed {
+        Ok(desired) => desired,
+        Err(e) => return Ok(problem::response(StatusCode::BAD_REQUEST, format!("{:#}", e))),
+    };
+
+    if dry_run {
+        let diff = ConfigDiff::compute(&current, &desired);
+        return Ok(json_response(
+            StatusCode::OK,
+            serde_json::json!({ "dry_run": true, "changes": diff }).to_string(),
+        ));
+    }
+
+    match declarative::apply(&state.db_client, &current, &desired).await {
+        Ok(diff) => {
+            if !diff.is_empty() {
+                info!("Configuration imported through the Admin API");
+                if let Some(update_tx) = &state.update_tx {
+                    if let Err(e) = update_tx.send(RouterUpdate::ConfigChanged) {
+                        debug!("Failed to notify router update: {}", e);
+                    }
+                }
+            }
+            Ok(json_response(
+                StatusCode::OK,
+                serde_json::json!({ "dry_run": false, "changes": diff }).to_string(),
+            ))
+        },
+        Err(e) => {
+            error!("Failed to import configuration: {:#}", e);
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to import configuration"))
+        }
+    }
+}
+
+/// Handler for POST /config/bulk endpoint - creates or updates the posted
+/// proxies, consumers and plugin configs in one database transaction. Entities
+/// are matched by ID; nothing is deleted. On any conflict nothing is written.
+pub async fn bulk_config(req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let format = body_format(&req);
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+
+    let content = match std::str::from_utf8(&body_bytes) {
+        Ok(content) => content,
+        Err(_) => return Ok(problem::response(StatusCode::BAD_REQUEST, "Configuration document must be UTF-8")),
+    };
+
+    let bulk = match document::parse_str(content, format).and_then(file_config::from_document) {
+        Ok(bulk) => bulk,
+        Err(e) => return Ok(problem::response(StatusCode::BAD_REQUEST, format!("{:#}", e))),
+    };
+    if !bulk.certificates.is_empty() || !bulk.snis.is_empty() {
+        return Ok(problem::response(StatusCode::BAD_REQUEST, "Bulk imports take proxies, consumers and plugin_configs; use /certificates and /snis for the others"));
+    }
+
+    // Conflicts with the batch itself or the existing configuration are found before anything is written
+    let current = state.shared_config.read().await.clone();
+    let merged = declarative::merge(&current, &bulk);
+    let report = validation::check(&merged);
+    if !report.valid {
+        return Ok(Problem {
+            errors: report.errors.iter().map(FieldError::from).collect(),
+            ..Problem::new(StatusCode::CONFLICT, "Bulk import conflicts with the configuration, nothing was written")
+        }.into_response());
+    }
+
+    match declarative::apply_bulk(&state.db_client, &current, &merged).await {
+        Ok(diff) => {
+            if !diff.is_empty() {
+                info!("Bulk import applied through the Admin API");
+                if let Some(update_tx) = &state.update_tx {
+                    if let Err(e) = update_tx.send(RouterUpdate::ConfigChanged) {
+                        debug!("Failed to notify router update: {}", e);
+                    }
+                }
+            }
+            Ok(json_response(
+                StatusCode::OK,
+                serde_json::json!({ "changes": diff }).to_string(),
+            ))
+        },
+        Err(e) => {
+            // The database rejected a write, typically a uniqueness constraint, and rolled back the rest
+            error!("Bulk import rolled back: {:#}", e);
+            Ok(problem::response(StatusCode::CONFLICT, format!("Bulk import rolled back, nothing was written: {:#}", e)))
+        }
+    }
+}
+
+/// Handler for POST /config/validate endpoint - checks the posted document and
+/// reports errors and warnings without applying anything. Also available in File mode.
+pub async fn validate_config(req: Request<Body>, _state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let format = body_format(&req);
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+
+    let content = match std::str::from_utf8(&body_bytes) {
+        Ok(content) => content,
+        Err(_) => return Ok(problem::response(StatusCode::BAD_REQUEST, "Configuration document must be UTF-8")),
+    };
+
+    let value = match document::parse_str(content, format) {
+        Ok(value) => value,
+        Err(e) => return Ok(problem::response(StatusCode::BAD_REQUEST, format!("{:#}", e))),
+    };
+
+    let (mut report, config) = validation::check_document(value);
+    if let Some(config) = config {
+        validation::check_hostnames(&mut report, &config).await;
+    }
+
+    Ok(json_response(StatusCode::OK, serde_json::to_string(&report)?))
+}
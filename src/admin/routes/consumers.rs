@@ -2,24 +2,36 @@ use std::sync::Arc;
 use anyhow::Result;
 use hyper::{Body, Request, Response, StatusCode};
 use tracing::{debug, error, info};
-use serde_json::Value;
-use bcrypt::{hash, DEFAULT_COST};
 
 use crate::admin::AdminApiState;
+use crate::admin::problem::{self, Problem};
+use crate::admin::validation;
+use crate::admin::etag;
+use crate::config::credentials;
 use crate::config::data_model::Consumer;
 use crate::modes::OperationMode;
-use crate::admin::pagination::{PaginationQuery, create_paginated_response};
+use crate::admin::pagination::{ListQuery, create_paginated_response, invalid_query_response};
 
 /// Handler for GET /consumers endpoint - lists all consumers
-pub async fn list_consumers(state: Arc<AdminApiState>) -> Result<Response<Body>> {
-    // Extract pagination parameters
-    let pagination = PaginationQuery::from_request(&Request::new(Body::empty()));
+pub async fn list_consumers(req: &Request<Body>, workspace: Option<&str>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    // Extract pagination, filter and sort parameters
+    let query = match ListQuery::from_request(req) {
+        Ok(query) => query,
+        Err(message) => return Ok(invalid_query_response(&message)),
+    };
     
     // Get the current configuration
     let config = state.shared_config.read().await;
     
-    // Apply pagination to the consumers
-    let (paginated_consumers, pagination_meta) = pagination.paginate(&config.consumers);
+    // Only list the consumers of the requested workspace
+    let consumers = config.consumers.iter()
+        .filter(|item| workspace.map_or(true, |workspace| item.workspace == workspace));
+    
+    // Apply filters, sorting and pagination to the consumers
+    let (paginated_consumers, pagination_meta) = match query.apply(consumers) {
+        Ok(page) => page,
+        Err(message) => return Ok(invalid_query_response(&message)),
+    };
     
     // Create the paginated response
     let response = create_paginated_response(paginated_consumers, pagination_meta);
@@ -39,42 +51,41 @@ pub async fn list_consumers(state: Arc<AdminApiState>) -> Result<Response<Body>>
 pub async fn create_consumer(req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
     // Check operation mode
     if state.operation_mode == OperationMode::File {
-        return Ok(Response::builder()
-            .status(StatusCode::CONFLICT)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Cannot modify config — currently running in File Mode"}"#))
-            .unwrap());
+        return Ok(problem::file_mode_response());
     }
     
     // Read the request body
     let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
     
     // Deserialize the consumer from JSON
-    let mut consumer = serde_json::from_slice::<Consumer>(&body_bytes)
-        .map_err(|e| anyhow::anyhow!("Invalid consumer data: {}", e))?;
+    let mut consumer = match validation::parse_entity::<Consumer>(&body_bytes, "consumers", "consumer") {
+        Ok(consumer) => consumer,
+        Err(response) => return Ok(response),
+    };
     
-    // Verify username uniqueness
+    // Verify username uniqueness within the workspace
     {
         let config = state.shared_config.read().await;
         for existing_consumer in &config.consumers {
-            if existing_consumer.username == consumer.username {
-                return Ok(Response::builder()
-                    .status(StatusCode::CONFLICT)
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(format!(
-                        r#"{{"error":"A consumer with username '{}' already exists"}}"#,
-                        consumer.username
-                    )))
-                    .unwrap());
+            if existing_consumer.username == consumer.username && existing_consumer.workspace == consumer.workspace {
+                return Ok(duplicate_username_response(&consumer));
             }
         }
     }
     
+    let errors = validation::check_consumer(&consumer);
+    if !errors.is_empty() {
+        return Ok(Problem::validation("consumer", errors).into_response());
+    }
+    
     // Set timestamps
     let now = chrono::Utc::now();
     consumer.created_at = now;
     consumer.updated_at = now;
     
+    // Only hashes of API keys and passwords are stored or echoed back
+    credentials::hash_consumer(&mut consumer, state.db_client.password_hashing())?;
+    
     // Create the consumer in the database
     match state.db_client.create_consumer(&consumer).await {
         Ok(id) => {
@@ -93,11 +104,7 @@ pub async fn create_consumer(req: Request<Body>, state: Arc<AdminApiState>) -> R
         Err(e) => {
             error!("Failed to create consumer in database: {}", e);
             
-            Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .header("Content-Type", "application/json")
-                .body(Body::from(format!(r#"{{"error":"Failed to create consumer: {}"}}"#, e)))
-                .unwrap())
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create consumer"))
         }
     }
 }
@@ -138,6 +145,7 @@ pub async fn get_consumer(consumer_id: &str, state: Arc<AdminApiState>) -> Resul
     Ok(Response::builder()
         .status(StatusCode::OK)
         .header("Content-Type", "application/json")
+        .header("ETag", etag::etag(consumer.version))
         .body(Body::from(json))
         .unwrap())
 }
@@ -146,27 +154,29 @@ pub async fn get_consumer(consumer_id: &str, state: Arc<AdminApiState>) -> Resul
 pub async fn update_consumer(consumer_id: &str, req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
     // Check operation mode
     if state.operation_mode == OperationMode::File {
-        return Ok(Response::builder()
-            .status(StatusCode::CONFLICT)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Cannot modify config — currently running in File Mode"}"#))
-            .unwrap());
+        return Ok(problem::file_mode_response());
     }
     
+    // The version the client read, checked by the database when writing
+    let expected_version = match etag::if_match_version(&req) {
+        Ok(version) => version,
+        Err(response) => return Ok(response),
+    };
+    
     // Read the request body
     let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
     
     // Deserialize the consumer from JSON
-    let mut updated_consumer = serde_json::from_slice::<Consumer>(&body_bytes)
-        .map_err(|e| anyhow::anyhow!("Invalid consumer data: {}", e))?;
+    let mut updated_consumer = match validation::parse_entity::<Consumer>(&body_bytes, "consumers", "consumer") {
+        Ok(consumer) => consumer,
+        Err(response) => return Ok(response),
+    };
     
     // Ensure the ID in the path matches the ID in the body
     if updated_consumer.id != consumer_id {
-        return Ok(Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Consumer ID in the path does not match the ID in the request body"}"#))
-            .unwrap());
+        return Ok(Problem::new(StatusCode::BAD_REQUEST, "Consumer ID in the path does not match the ID in the request body")
+            .with_field("id", "id_mismatch")
+            .into_response());
     }
     
     // Check if the consumer exists and username is unique
@@ -176,34 +186,37 @@ pub async fn update_consumer(consumer_id: &str, req: Request<Body>, state: Arc<A
         // Check if the consumer exists
         let exists = config.consumers.iter().any(|c| c.id == consumer_id);
         if !exists {
-            return Ok(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .header("Content-Type", "application/json")
-                .body(Body::from(r#"{"error":"Consumer not found"}"#))
-                .unwrap());
+            return Ok(problem::response(StatusCode::NOT_FOUND, "Consumer not found"));
         }
         
         // Check username uniqueness (only if changed)
         for existing_consumer in &config.consumers {
-            if existing_consumer.username == updated_consumer.username && existing_consumer.id != consumer_id {
-                return Ok(Response::builder()
-                    .status(StatusCode::CONFLICT)
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(format!(
-                        r#"{{"error":"A consumer with username '{}' already exists"}}"#,
-                        updated_consumer.username
-                    )))
-                    .unwrap());
+            if existing_consumer.username == updated_consumer.username
+                && existing_consumer.workspace == updated_consumer.workspace
+                && existing_consumer.id != consumer_id
+            {
+                return Ok(duplicate_username_response(&updated_consumer));
             }
         }
     }
     
-    // Update the timestamp
+    let errors = validation::check_consumer(&updated_consumer);
+    if !errors.is_empty() {
+        return Ok(Problem::validation("consumer", errors).into_response());
+    }
+    
+    // Update the timestamp and version
     updated_consumer.updated_at = chrono::Utc::now();
+    updated_consumer.version = expected_version;
+    
+    // Only hashes of API keys and passwords are stored or echoed back
+    credentials::hash_consumer(&mut updated_consumer, state.db_client.password_hashing())?;
     
     // Update the consumer in the database
     match state.db_client.update_consumer(&updated_consumer).await {
         Ok(_) => {
+            updated_consumer.version += 1;
+            
             // Serialize the updated consumer to JSON
             let json = serde_json::to_string(&updated_consumer)?;
             
@@ -211,17 +224,17 @@ pub async fn update_consumer(consumer_id: &str, req: Request<Body>, state: Arc<A
             Ok(Response::builder()
                 .status(StatusCode::OK)
                 .header("Content-Type", "application/json")
+                .header("ETag", etag::etag(updated_consumer.version))
                 .body(Body::from(json))
                 .unwrap())
         },
         Err(e) => {
+            if let Some(response) = etag::stale_response(&e) {
+                return Ok(response);
+            }
             error!("Failed to update consumer in database: {}", e);
             
-            Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .header("Content-Type", "application/json")
-                .body(Body::from(format!(r#"{{"error":"Failed to update consumer: {}"}}"#, e)))
-                .unwrap())
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update consumer"))
         }
     }
 }
@@ -230,11 +243,7 @@ pub async fn update_consumer(consumer_id: &str, req: Request<Body>, state: Arc<A
 pub async fn delete_consumer(consumer_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
     // Check operation mode
     if state.operation_mode == OperationMode::File {
-        return Ok(Response::builder()
-            .status(StatusCode::CONFLICT)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Cannot modify config — currently running in File Mode"}"#))
-            .unwrap());
+        return Ok(problem::file_mode_response());
     }
     
     // Check if the consumer exists
@@ -242,11 +251,7 @@ pub async fn delete_consumer(consumer_id: &str, state: Arc<AdminApiState>) -> Re
         let config = state.shared_config.read().await;
         
         if !config.consumers.iter().any(|c| c.id == consumer_id) {
-            return Ok(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .header("Content-Type", "application/json")
-                .body(Body::from(r#"{"error":"Consumer not found"}"#))
-                .unwrap());
+            return Ok(problem::response(StatusCode::NOT_FOUND, "Consumer not found"));
         }
     }
     
@@ -262,209 +267,14 @@ pub async fn delete_consumer(consumer_id: &str, state: Arc<AdminApiState>) -> Re
         Err(e) => {
             error!("Failed to delete consumer from database: {}", e);
             
-            Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .header("Content-Type", "application/json")
-                .body(Body::from(format!(r#"{{"error":"Failed to delete consumer: {}"}}"#, e)))
-                .unwrap())
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete consumer"))
         }
     }
 }
 
-/// Handler for GET /consumers/{id}/credentials/{credential_type} endpoint - gets specific credentials
-pub async fn get_consumer_credentials(consumer_id: &str, credential_type: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
-    // Get the current configuration
-    let config = state.shared_config.read().await;
-    
-    // Find the consumer with the specified ID
-    let consumer = config.consumers
-        .iter()
-        .find(|c| c.id == consumer_id)
-        .ok_or_else(|| anyhow::anyhow!("Consumer not found"))?;
-    
-    // Get the specified credential
-    let credential = consumer.credentials.get(credential_type)
-        .ok_or_else(|| anyhow::anyhow!("Credential type not found for this consumer"))?;
-    
-    // Serialize the credential to JSON
-    let json = serde_json::to_string(credential)?;
-    
-    // Return the response
-    Ok(Response::builder()
-        .status(StatusCode::OK)
-        .header("Content-Type", "application/json")
-        .body(Body::from(json))
-        .unwrap())
-}
-
-/// Handler for PUT /consumers/{id}/credentials/{credential_type} endpoint - updates specific credentials
-pub async fn update_consumer_credentials(consumer_id: &str, credential_type: &str, req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
-    // Check operation mode
-    if state.operation_mode == OperationMode::File {
-        return Ok(Response::builder()
-            .status(StatusCode::CONFLICT)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Cannot modify config — currently running in File Mode"}"#))
-            .unwrap());
-    }
-    
-    // Read the request body
-    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
-    
-    // Deserialize the credential from JSON
-    let mut credential = serde_json::from_slice::<Value>(&body_bytes)
-        .map_err(|e| anyhow::anyhow!("Invalid credential data: {}", e))?;
-    
-    // Hash the credential if necessary
-    hash_credential(credential_type, &mut credential)?;
-    
-    // Get the current consumer
-    let mut consumer = {
-        let config = state.shared_config.read().await;
-        
-        // Find the consumer with the specified ID
-        let consumer = config.consumers
-            .iter()
-            .find(|c| c.id == consumer_id)
-            .cloned()
-            .ok_or_else(|| anyhow::anyhow!("Consumer not found"))?;
-        
-        consumer
-    };
-    
-    // Update the credential in the consumer object
-    consumer.credentials.insert(credential_type.to_string(), credential.clone());
-    consumer.updated_at = chrono::Utc::now();
-    
-    // Update the consumer in the database
-    match state.db_client.update_consumer(&consumer).await {
-        Ok(_) => {
-            // Return the response
-            Ok(Response::builder()
-                .status(StatusCode::OK)
-                .header("Content-Type", "application/json")
-                .body(Body::from(serde_json::to_string(&credential)?))
-                .unwrap())
-        },
-        Err(e) => {
-            error!("Failed to update consumer credentials in database: {}", e);
-            
-            Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .header("Content-Type", "application/json")
-                .body(Body::from(format!(r#"{{"error":"Failed to update consumer credentials: {}"}}"#, e)))
-                .unwrap())
-        }
-    }
-}
-
-/// Handler for DELETE /consumers/{id}/credentials/{credential_type} endpoint - deletes specific credentials
-pub async fn delete_consumer_credentials(consumer_id: &str, credential_type: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
-    // Check operation mode
-    if state.operation_mode == OperationMode::File {
-        return Ok(Response::builder()
-            .status(StatusCode::CONFLICT)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Cannot modify config — currently running in File Mode"}"#))
-            .unwrap());
-    }
-    
-    // Get the current consumer
-    let mut consumer = {
-        let config = state.shared_config.read().await;
-        
-        // Find the consumer with the specified ID
-        let consumer = config.consumers
-            .iter()
-            .find(|c| c.id == consumer_id)
-            .cloned()
-            .ok_or_else(|| anyhow::anyhow!("Consumer not found"))?;
-        
-        consumer
-    };
-    
-    // Check if the credential exists
-    if !consumer.credentials.contains_key(credential_type) {
-        return Ok(Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Credential type not found for this consumer"}"#))
-            .unwrap());
-    }
-    
-    // Remove the credential
-    consumer.credentials.remove(credential_type);
-    consumer.updated_at = chrono::Utc::now();
-    
-    // Update the consumer in the database
-    match state.db_client.update_consumer(&consumer).await {
-        Ok(_) => {
-            // Return the response
-            Ok(Response::builder()
-                .status(StatusCode::NO_CONTENT)
-                .body(Body::empty())
-                .unwrap())
-        },
-        Err(e) => {
-            error!("Failed to delete consumer credentials from database: {}", e);
-            
-            Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .header("Content-Type", "application/json")
-                .body(Body::from(format!(r#"{{"error":"Failed to delete consumer credentials: {}"}}"#, e)))
-                .unwrap())
-        }
-    }
-}
-
-/// Hash all sensitive credential values in a credentials map
-pub async fn hash_sensitive_credentials(credentials: &mut std::collections::HashMap<String, Value>) -> Result<()> {
-    for (cred_type, cred_value) in credentials.iter_mut() {
-        hash_credential(cred_type, cred_value)?;
-    }
-    
-    Ok(())
-}
-
-/// Hash a credential value based on its type
-fn hash_credential(cred_type: &str, cred_value: &mut Value) -> Result<()> {
-    match cred_type {
-        "key-auth" => {
-            // Key authentication credentials
-            if let Some(key) = cred_value.get_mut("key").and_then(|v| v.as_str()) {
-                // Only hash if it's not already hashed
-                if !key.starts_with("$2y$") && !key.starts_with("$2a$") && !key.starts_with("$2b$") {
-                    let hashed = hash(key, DEFAULT_COST)?;
-                    *cred_value.get_mut("key").unwrap() = Value::String(hashed);
-                }
-            }
-        },
-        "basic-auth" => {
-            // Basic authentication credentials
-            if let Some(password) = cred_value.get_mut("password").and_then(|v| v.as_str()) {
-                // Only hash if it's not already hashed
-                if !password.starts_with("$2y$") && !password.starts_with("$2a$") && !password.starts_with("$2b$") {
-                    let hashed = hash(password, DEFAULT_COST)?;
-                    *cred_value.get_mut("password").unwrap() = Value::String(hashed);
-                }
-            }
-        },
-        "jwt" => {
-            // JWT credentials don't need hashing
-        },
-        "oauth2" => {
-            // OAuth2 credentials (client secret should be hashed)
-            if let Some(secret) = cred_value.get_mut("client_secret").and_then(|v| v.as_str()) {
-                // Only hash if it's not already hashed
-                if !secret.starts_with("$2y$") && !secret.starts_with("$2a$") && !secret.starts_with("$2b$") {
-                    let hashed = hash(secret, DEFAULT_COST)?;
-                    *cred_value.get_mut("client_secret").unwrap() = Value::String(hashed);
-                }
-            }
-        },
-        // Add other credential types as needed
-        _ => {}
-    }
-    
-    Ok(())
+/// 409 response when the consumer's username is taken in its workspace
+fn duplicate_username_response(consumer: &Consumer) -> Response<Body> {
+    Problem::new(StatusCode::CONFLICT, format!("A consumer with username '{}' already exists", consumer.username))
+        .with_field("username", "duplicate_username")
+        .into_response()
 }
@@ -0,0 +1,390 @@
+use std::sync::Arc;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use tracing::{error, info};
+
+use crate::admin::AdminApiState;
+use crate::admin::problem;
+use crate::admin::etag;
+use crate::config::credentials::{self, CredentialType};
+use crate::config::data_model::Consumer;
+use crate::modes::OperationMode;
+
+/// A path under /consumers/{id}/credentials
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CredentialPath {
+    pub consumer_id: String,
+    pub credential_type: Option<String>,
+    /// An API key of a key_auth credential
+    pub key_id: Option<String>,
+    /// POST .../key_auth/{key_id}/rotate
+    pub rotate: bool,
+}
+
+impl CredentialPath {
+    pub fn parse(path: &str) -> Option<Self> {
+        let rest = path.strip_prefix("/consumers/")?;
+        let mut segments = rest.split('/');
+        let consumer_id = segments.next().filter(|id| !id.is_empty())?;
+        if segments.next()? != "credentials" {
+            return None;
+        }
+        let credential_type = segments.next().filter(|segment| !segment.is_empty());
+        let key_id = segments.next().filter(|segment| !segment.is_empty());
+        let rotate = match segments.next() {
+            Some("rotate") if key_id.is_some() => true,
+            Some(_) => return None,
+            None => false,
+        };
+        if segments.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            consumer_id: consumer_id.to_string(),
+            credential_type: credential_type.map(str::to_string),
+            key_id: key_id.map(str::to_string),
+            rotate,
+        })
+    }
+}
+
+/// How long a rotated API key keeps working when the request does not say
+const DEFAULT_ROTATION_GRACE_SECONDS: u64 = 24 * 60 * 60;
+
+/// Longest grace period a rotation may give the old key
+const MAX_ROTATION_GRACE_SECONDS: u64 = 90 * 24 * 60 * 60;
+
+/// Body of POST /consumers/{id}/credentials/key_auth; without a key, one is generated
+#[derive(Debug, Default, Deserialize)]
+struct NewApiKeyRequest {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    key: Option<String>,
+    /// When the key stops being accepted; by default it does not expire
+    #[serde(default)]
+    expires_at: Option<DateTime<Utc>>,
+}
+
+/// Body of POST /consumers/{id}/credentials/key_auth/{key_id}/rotate
+#[derive(Debug, Default, Deserialize)]
+struct RotateApiKeyRequest {
+    /// How long the old key keeps working, from now
+    #[serde(default)]
+    grace_period_seconds: Option<u64>,
+    /// The new key's; by default the old key's name is kept
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    key: Option<String>,
+    #[serde(default)]
+    expires_at: Option<DateTime<Utc>>,
+}
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn unknown_type_response(credential_type: &str) -> Response<Body> {
+    let types: Vec<&str> = CredentialType::ALL.iter().map(CredentialType::as_str).collect();
+    problem::response(
+        StatusCode::BAD_REQUEST,
+        format!("Unknown credential type '{}', expected one of: {}", credential_type, types.join(", ")),
+    )
+}
+
+/// The credential of a type as stored, under its current name or an older one
+fn stored_credential<'a>(consumer: &'a Consumer, credential_type: CredentialType) -> Option<(&'static str, &'a Value)> {
+    credential_type.storage_keys().iter()
+        .find_map(|key| consumer.credentials.get(*key).map(|credential| (*key, credential)))
+}
+
+/// Routes a request under /consumers/{id}/credentials
+pub async fn handle(req: Request<Body>, path: CredentialPath, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let consumer = match state.shared_config.read().await.consumers.iter().find(|c| c.id == path.consumer_id) {
+        Some(consumer) => consumer.clone(),
+        None => return Ok(problem::response(StatusCode::NOT_FOUND, "Consumer not found")),
+    };
+
+    let credential_type = match &path.credential_type {
+        Some(name) => match CredentialType::parse(name) {
+            Some(credential_type) => Some(credential_type),
+            None => return Ok(unknown_type_response(name)),
+        },
+        None => None,
+    };
+
+    match (req.method().clone(), credential_type, path.key_id.as_deref()) {
+        (Method::GET, None, None) => Ok(list_credentials(&consumer)),
+        (Method::GET, Some(credential_type), None) => Ok(get_credential(&consumer, credential_type)),
+        (Method::PUT, Some(credential_type), None) => set_credential(consumer, credential_type, req, &state).await,
+        (Method::POST, Some(CredentialType::KeyAuth), None) => create_api_key(consumer, req, &state).await,
+        (Method::DELETE, Some(credential_type), None) => delete_credential(consumer, credential_type, &state).await,
+        (Method::POST, Some(CredentialType::KeyAuth), Some(key_id)) if path.rotate => rotate_api_key(consumer, key_id, req, &state).await,
+        (Method::DELETE, Some(CredentialType::KeyAuth), Some(key_id)) if !path.rotate => revoke_api_key(consumer, key_id, &state).await,
+        _ => Ok(problem::response(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed for this credential path")),
+    }
+}
+
+/// GET /consumers/{id}/credentials - the consumer's credentials by type, without secrets
+fn list_credentials(consumer: &Consumer) -> Response<Body> {
+    let credentials: Map<String, Value> = CredentialType::ALL.iter()
+        .filter_map(|credential_type| stored_credential(consumer, *credential_type)
+            .map(|(_, credential)| (credential_type.as_str().to_string(), credential_type.metadata(credential))))
+        .collect();
+    json_response(StatusCode::OK, json!({ "credentials": credentials }).to_string())
+}
+
+/// GET /consumers/{id}/credentials/{type} - one credential, without secrets
+fn get_credential(consumer: &Consumer, credential_type: CredentialType) -> Response<Body> {
+    match stored_credential(consumer, credential_type) {
+        Some((_, credential)) => json_response(StatusCode::OK, credential_type.metadata(credential).to_string()),
+        None => problem::response(StatusCode::NOT_FOUND, "Credential type not found for this consumer"),
+    }
+}
+
+/// PUT /consumers/{id}/credentials/{type} - sets a password or identity mapping,
+/// hashing secrets before they are stored
+async fn set_credential(mut consumer: Consumer, credential_type: CredentialType, req: Request<Body>, state: &AdminApiState) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+    let mut credential: Value = match serde_json::from_slice(&body_bytes) {
+        Ok(credential) => credential,
+        Err(e) => return Ok(problem::response(StatusCode::BAD_REQUEST, format!("Invalid credential data: {}", e))),
+    };
+    if let Err(message) = credential_type.validate(&credential) {
+        return Ok(problem::response(StatusCode::BAD_REQUEST, message));
+    }
+
+    credentials::hash_credential(credential_type.as_str(), &mut credential, state.db_client.password_hashing())?;
+    credential["updated_at"] = json!(chrono::Utc::now());
+
+    // Older spellings of the type are replaced by the current one
+    for key in credential_type.storage_keys() {
+        consumer.credentials.remove(*key);
+    }
+    consumer.credentials.insert(credential_type.as_str().to_string(), credential.clone());
+
+    if let Err(response) = save(&consumer, state, "update consumer credentials").await {
+        return Ok(response);
+    }
+    Ok(json_response(StatusCode::OK, credential_type.metadata(&credential).to_string()))
+}
+
+/// POST /consumers/{id}/credentials/key_auth - adds an API key. The key is
+/// only returned in this response; the consumer keeps its digest.
+async fn create_api_key(mut consumer: Consumer, req: Request<Body>, state: &AdminApiState) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let request: NewApiKeyRequest = match read_optional_body(req).await? {
+        Ok(request) => request,
+        Err(response) => return Ok(response),
+    };
+    if let Err(message) = check_new_key(request.key.as_deref(), request.expires_at) {
+        return Ok(problem::response(StatusCode::BAD_REQUEST, message));
+    }
+
+    let mut new_key = credentials::new_api_key(request.key, request.name);
+    if let Some(expires_at) = request.expires_at {
+        new_key.stored["expires_at"] = json!(expires_at);
+    }
+
+    // Keys stored under an older spelling move to the current one
+    let mut credential = match stored_credential(&consumer, CredentialType::KeyAuth) {
+        Some((_, credential)) => credential.clone(),
+        None => json!({}),
+    };
+    for key in CredentialType::KeyAuth.storage_keys() {
+        consumer.credentials.remove(*key);
+    }
+    match credential.get_mut("keys").and_then(Value::as_array_mut) {
+        Some(keys) => keys.push(new_key.stored.clone()),
+        None => credential["keys"] = json!([new_key.stored.clone()]),
+    }
+    consumer.credentials.insert(CredentialType::KeyAuth.as_str().to_string(), credential);
+
+    if let Err(response) = save(&consumer, state, "create API key").await {
+        return Ok(response);
+    }
+    info!("Created API key {} for consumer {}", new_key.id, consumer.id);
+
+    let mut body = new_key.stored;
+    body["key"] = Value::String(new_key.key);
+    Ok(json_response(StatusCode::CREATED, body.to_string()))
+}
+
+/// POST /consumers/{id}/credentials/key_auth/{key_id}/rotate - adds a key that
+/// replaces an existing one, which keeps working until its grace period ends
+async fn rotate_api_key(mut consumer: Consumer, key_id: &str, req: Request<Body>, state: &AdminApiState) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let request: RotateApiKeyRequest = match read_optional_body(req).await? {
+        Ok(request) => request,
+        Err(response) => return Ok(response),
+    };
+    if let Err(message) = check_new_key(request.key.as_deref(), request.expires_at) {
+        return Ok(problem::response(StatusCode::BAD_REQUEST, message));
+    }
+    let grace_period_seconds = request.grace_period_seconds.unwrap_or(DEFAULT_ROTATION_GRACE_SECONDS);
+    if grace_period_seconds > MAX_ROTATION_GRACE_SECONDS {
+        return Ok(problem::response(
+            StatusCode::BAD_REQUEST,
+            format!("'grace_period_seconds' must be at most {}", MAX_ROTATION_GRACE_SECONDS),
+        ));
+    }
+
+    let storage_key = match stored_credential(&consumer, CredentialType::KeyAuth) {
+        Some((storage_key, _)) => storage_key,
+        None => return Ok(problem::response(StatusCode::NOT_FOUND, "API key not found")),
+    };
+    let keys = match consumer.credentials.get_mut(storage_key)
+        .and_then(|credential| credential.get_mut("keys"))
+        .and_then(Value::as_array_mut)
+    {
+        Some(keys) => keys,
+        None => return Ok(problem::response(StatusCode::NOT_FOUND, "API key not found")),
+    };
+    let position = match keys.iter().position(|key| key.get("id").and_then(Value::as_str) == Some(key_id)) {
+        Some(position) => position,
+        None => return Ok(problem::response(StatusCode::NOT_FOUND, "API key not found")),
+    };
+
+    let now = Utc::now();
+    let current_expiry = credentials::api_key_expiry(&keys[position]);
+    if current_expiry.map_or(false, |expires_at| expires_at <= now) {
+        return Ok(problem::response(StatusCode::CONFLICT, "API key has already expired"));
+    }
+    // A key already due to expire sooner keeps its expiry
+    let grace_end = now + chrono::Duration::seconds(grace_period_seconds as i64);
+    let old_expires_at = current_expiry.map_or(grace_end, |expires_at| expires_at.min(grace_end));
+
+    let name = request.name.or_else(|| keys[position].get("name").and_then(Value::as_str).map(str::to_string));
+    let mut new_key = credentials::new_api_key(request.key, name);
+    if let Some(expires_at) = request.expires_at {
+        new_key.stored["expires_at"] = json!(expires_at);
+    }
+    keys[position]["expires_at"] = json!(old_expires_at);
+    keys[position]["rotated_to"] = Value::String(new_key.id.clone());
+    keys.push(new_key.stored.clone());
+
+    if let Err(response) = save(&consumer, state, "rotate API key").await {
+        return Ok(response);
+    }
+    info!("Rotated API key {} of consumer {} to {}, the old key expires at {}", key_id, consumer.id, new_key.id, old_expires_at);
+
+    let mut body = new_key.stored;
+    body["key"] = Value::String(new_key.key);
+    body["previous"] = json!({ "id": key_id, "expires_at": old_expires_at });
+    Ok(json_response(StatusCode::CREATED, body.to_string()))
+}
+
+/// DELETE /consumers/{id}/credentials/key_auth/{key_id} - revokes one API key
+async fn revoke_api_key(mut consumer: Consumer, key_id: &str, state: &AdminApiState) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let storage_key = match stored_credential(&consumer, CredentialType::KeyAuth) {
+        Some((storage_key, _)) => storage_key,
+        None => return Ok(problem::response(StatusCode::NOT_FOUND, "API key not found")),
+    };
+    let keys = consumer.credentials.get_mut(storage_key)
+        .and_then(|credential| credential.get_mut("keys"))
+        .and_then(Value::as_array_mut);
+    let removed = match keys {
+        Some(keys) => {
+            let before = keys.len();
+            keys.retain(|key| key.get("id").and_then(Value::as_str) != Some(key_id));
+            keys.len() < before
+        },
+        None => false,
+    };
+    if !removed {
+        return Ok(problem::response(StatusCode::NOT_FOUND, "API key not found"));
+    }
+
+    if let Err(response) = save(&consumer, state, "revoke API key").await {
+        return Ok(response);
+    }
+    info!("Revoked API key {} of consumer {}", key_id, consumer.id);
+    Ok(Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(Body::empty())
+        .unwrap())
+}
+
+/// DELETE /consumers/{id}/credentials/{type} - removes a credential, with all its API keys
+async fn delete_credential(mut consumer: Consumer, credential_type: CredentialType, state: &AdminApiState) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let mut removed = false;
+    for key in credential_type.storage_keys() {
+        removed |= consumer.credentials.remove(*key).is_some();
+    }
+    if !removed {
+        return Ok(problem::response(StatusCode::NOT_FOUND, "Credential type not found for this consumer"));
+    }
+
+    if let Err(response) = save(&consumer, state, "delete consumer credentials").await {
+        return Ok(response);
+    }
+    Ok(Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(Body::empty())
+        .unwrap())
+}
+
+/// Reads a JSON body that may be left out, in which case every field takes its default
+async fn read_optional_body<T: Default + serde::de::DeserializeOwned>(req: Request<Body>) -> Result<Result<T, Response<Body>>> {
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+    if body_bytes.is_empty() {
+        return Ok(Ok(T::default()));
+    }
+    Ok(serde_json::from_slice(&body_bytes)
+        .map_err(|e| problem::response(StatusCode::BAD_REQUEST, format!("Invalid API key request: {}", e))))
+}
+
+/// Checks the key and expiry a client chose for a new API key
+fn check_new_key(key: Option<&str>, expires_at: Option<DateTime<Utc>>) -> Result<(), &'static str> {
+    if key.map_or(false, str::is_empty) {
+        return Err("'key' must be non-empty");
+    }
+    if expires_at.map_or(false, |expires_at| expires_at <= Utc::now()) {
+        return Err("'expires_at' must be in the future");
+    }
+    Ok(())
+}
+
+/// Stores the changed consumer. A consumer changed since it was loaded, or a
+/// database failure, is returned as the error response to send.
+async fn save(consumer: &Consumer, state: &AdminApiState, action: &str) -> Result<(), Response<Body>> {
+    let mut consumer = consumer.clone();
+    consumer.updated_at = chrono::Utc::now();
+    match state.db_client.update_consumer(&consumer).await {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            // The credentials were changed from the consumer last loaded
+            if let Some(response) = etag::stale_response(&e) {
+                return Err(response);
+            }
+            error!("Failed to {} in database: {}", action, e);
+            Err(problem::response(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to {}", action)))
+        },
+    }
+}
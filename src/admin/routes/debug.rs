@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use anyhow::Result;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::admin::AdminApiState;
+use crate::admin::problem;
+use crate::config::snapshot::ConfigurationSnapshot;
+use crate::plugins::PluginRegistry;
+use crate::proxy::handler::{self, RequestContext};
+use crate::proxy::router::Router;
+
+/// Plugins that only rewrite the request, and so can be run on the described
+/// request without side effects
+const REWRITE_PLUGINS: &[&str] = &["request_transformer"];
+
+/// Body of POST /debug/route, describing a request as a client would send it
+#[derive(Debug, Deserialize)]
+struct RouteDebugRequest {
+    #[serde(default = "default_method")]
+    method: String,
+    #[serde(default)]
+    host: Option<String>,
+    /// Path with an optional query string
+    path: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn bad_request(message: String) -> Response<Body> {
+    problem::response(StatusCode::BAD_REQUEST, message)
+}
+
+/// Handler for POST /debug/route endpoint - shows which proxy a request would
+/// match, the plugins that would run on it in order, and the backend URL it
+/// would be sent to, without sending anything
+pub async fn debug_route(req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+    let request: RouteDebugRequest = match serde_json::from_slice(&body_bytes) {
+        Ok(request) => request,
+        Err(e) => return Ok(bad_request(format!("Invalid request body: {}", e))),
+    };
+
+    let mut builder = Request::builder()
+        .method(match Method::from_bytes(request.method.to_uppercase().as_bytes()) {
+            Ok(method) => method,
+            Err(_) => return Ok(bad_request(format!("Invalid method: {}", request.method))),
+        })
+        .uri(&request.path);
+    if let Some(host) = &request.host {
+        builder = builder.header("Host", host);
+    }
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    let mut simulated = match builder.body(Body::empty()) {
+        Ok(simulated) => simulated,
+        Err(e) => return Ok(bad_request(format!("Invalid request: {}", e))),
+    };
+
+    // Route against a snapshot of the configuration the Admin API holds, the way the proxy does
+    let snapshot = ConfigurationSnapshot::shared(state.shared_config.read().await.clone());
+    let router = Router::new(Arc::clone(&snapshot));
+    let proxy = match router.route(&simulated) {
+        Some(proxy) => proxy,
+        None => return Ok(json_response(StatusCode::OK, json!({
+            "matched": false,
+            "path": simulated.uri().path(),
+        }).to_string())),
+    };
+    let snapshot = snapshot.load_full();
+
+    let registry = PluginRegistry::new();
+    let mut context = RequestContext {
+        proxy: proxy.clone(),
+        client_addr: SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        consumer: None,
+        consumers: snapshot.consumer_index(&proxy.workspace),
+        client_certificate: None,
+        latency: Default::default(),
+        request_bytes: 0,
+    };
+
+    // Plugins run in the order the proxy lists them, in every phase
+    let mut plugins = Vec::new();
+    for (position, association) in proxy.plugins.iter().enumerate() {
+        let plugin_config = match snapshot.plugin_config_by_id(&association.plugin_config_id) {
+            Some(plugin_config) => plugin_config,
+            None => {
+                plugins.push(json!({
+                    "position": position + 1,
+                    "plugin_config_id": association.plugin_config_id,
+                    "skipped": "Plugin config not found",
+                }));
+                continue;
+            },
+        };
+        let config = association.embedded_config.clone().unwrap_or_else(|| plugin_config.config.clone());
+
+        let mut entry = json!({
+            "position": position + 1,
+            "plugin_config_id": plugin_config.id,
+            "plugin_name": plugin_config.plugin_name,
+            "scope": plugin_config.scope,
+            "enabled": plugin_config.enabled,
+            "embedded_config": association.embedded_config.is_some(),
+        });
+        match registry.create_plugin(&plugin_config.plugin_name, config) {
+            Ok(plugin) if REWRITE_PLUGINS.contains(&plugin.name()) => {
+                if let Err(e) = plugin.before_proxy(&mut simulated, &mut context).await {
+                    entry["error"] = Value::String(format!("{:#}", e));
+                }
+            },
+            Ok(_) => {},
+            Err(e) => entry["skipped"] = Value::String(format!("{:#}", e)),
+        }
+        plugins.push(entry);
+    }
+
+    let backend_path = router.construct_backend_path(&simulated, &proxy);
+    let upstream_url = handler::backend_uri(&proxy, &proxy.backend_host, proxy.backend_port, &backend_path, simulated.uri().query())
+        .map(|uri| uri.to_string())
+        .unwrap_or_else(|e| format!("{:#}", e));
+    let mut upstream_headers: HashMap<String, String> = simulated.headers().iter()
+        .filter(|(name, _)| name.as_str() != "host")
+        .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+        .collect();
+    let client_host = simulated.headers().get("host").and_then(|host| host.to_str().ok());
+    upstream_headers.insert("host".to_string(), handler::upstream_host_header(&proxy, &proxy.backend_host, proxy.backend_port, client_host));
+
+    Ok(json_response(StatusCode::OK, json!({
+        "matched": true,
+        "proxy": {
+            "id": proxy.id,
+            "name": proxy.name,
+            "workspace": proxy.workspace,
+            "listen_path": proxy.listen_path,
+        },
+        "plugins": plugins,
+        "upstream": {
+            "url": upstream_url,
+            "headers": upstream_headers,
+            // The backend is picked per request from the service registry
+            "backend_discovery": proxy.backend_discovery,
+        },
+    }).to_string()))
+}
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use hyper::{Body, Response, StatusCode};
+use serde_json::json;
+use tracing::info;
+
+use crate::admin::problem;
+use crate::health;
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn no_cache_response() -> Response<Body> {
+    problem::response(StatusCode::CONFLICT, "This instance does not proxy requests and has no DNS cache")
+}
+
+/// Handler for GET /dns/cache endpoint - the counters of this instance's DNS
+/// cache and every name it holds, with overrides and cached failures
+pub async fn get_dns_cache() -> Result<Response<Body>> {
+    let dns_cache = match health::dns_cache() {
+        Some(dns_cache) => dns_cache,
+        None => return Ok(no_cache_response()),
+    };
+
+    let body = json!({
+        "stats": dns_cache.stats(),
+        "entries": dns_cache.entries(),
+    });
+    Ok(json_response(StatusCode::OK, body.to_string()))
+}
+
+/// Handler for DELETE /dns/cache/{host} endpoint - drops what is cached for a
+/// name so the next request looks it up again
+pub async fn invalidate_dns_entry(name: &str) -> Result<Response<Body>> {
+    let dns_cache = match health::dns_cache() {
+        Some(dns_cache) => dns_cache,
+        None => return Ok(no_cache_response()),
+    };
+    if !dns_cache.invalidate(name) {
+        return Ok(problem::response(StatusCode::NOT_FOUND, format!("Nothing is cached for {}", name)));
+    }
+
+    info!("Invalidated the DNS cache entry of {}", name);
+    Ok(Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(Body::empty())
+        .unwrap())
+}
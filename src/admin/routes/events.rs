@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+use hyper::{Body, Request, Response, StatusCode};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::debug;
+
+use crate::admin::AdminApiState;
+use crate::admin::events;
+
+/// How often a comment is written to idle streams, so proxies and load
+/// balancers in between do not close them
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Handler for GET /events endpoint - streams the changes made through the
+/// Admin API as Server-Sent Events. `?entity_type=proxy,consumer` limits the
+/// stream to some entity types.
+pub async fn stream_events(req: &Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let entity_types: Option<Vec<String>> = url::form_urlencoded::parse(req.uri().query().unwrap_or("").as_bytes())
+        .find(|(key, _)| key == "entity_type")
+        .map(|(_, value)| value.split(',').map(|entity_type| entity_type.trim().to_string()).collect());
+
+    let mut changes = state.events.subscribe();
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        // Tells the client how long to wait before reconnecting
+        if sender.send_data("retry: 5000\n\n".into()).await.is_err() {
+            return;
+        }
+        let mut keep_alive = tokio::time::interval(KEEP_ALIVE_INTERVAL);
+        keep_alive.tick().await;
+        loop {
+            let message = tokio::select! {
+                change = changes.recv() => match change {
+                    Ok(event) => {
+                        let wanted = entity_types.as_ref()
+                            .map_or(true, |entity_types| entity_types.iter().any(|entity_type| entity_type == event.entity_type));
+                        if !wanted {
+                            continue;
+                        }
+                        events::sse_message(&event)
+                    },
+                    // The client missed changes and should reload what it watches
+                    Err(RecvError::Lagged(missed)) => format!("event: lagged\ndata: {{\"missed\":{}}}\n\n", missed),
+                    Err(RecvError::Closed) => break,
+                },
+                _ = keep_alive.tick() => ": keep-alive\n\n".to_string(),
+            };
+            if sender.send_data(message.into()).await.is_err() {
+                break;
+            }
+        }
+        debug!("Admin API event stream closed");
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/event-stream")
+        .header("Cache-Control", "no-cache")
+        .body(body)
+        .unwrap())
+}
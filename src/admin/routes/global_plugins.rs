@@ -0,0 +1,236 @@
+use std::sync::Arc;
+use anyhow::Result;
+use hyper::{Body, Request, Response, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+use crate::admin::AdminApiState;
+use crate::admin::problem;
+use crate::admin::etag;
+use crate::admin::pagination::{ListQuery, create_paginated_response, invalid_query_response};
+use crate::config::data_model::{PluginConfig, PluginScope};
+use crate::modes::OperationMode;
+use crate::plugins::PluginRegistry;
+
+/// Body of POST /plugins/global and PUT /plugins/global/{id}
+#[derive(Debug, Deserialize)]
+struct GlobalPluginRequest {
+    plugin_name: String,
+    #[serde(default = "empty_config")]
+    config: Value,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    #[serde(default)]
+    workspace: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn empty_config() -> Value {
+    json!({})
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn not_found_response() -> Response<Body> {
+    problem::response(StatusCode::NOT_FOUND, "Global plugin not found")
+}
+
+fn entity_response(status: StatusCode, plugin_config: &PluginConfig) -> Result<Response<Body>> {
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .header("ETag", etag::etag(plugin_config.version))
+        .body(Body::from(serde_json::to_string(plugin_config)?))
+        .unwrap())
+}
+
+/// Parses a request body and checks that the plugin can be created from it.
+/// A global plugin runs for every request, so a broken one is refused up front.
+async fn parse_request(req: Request<Body>) -> Result<Result<GlobalPluginRequest, Response<Body>>> {
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+    let request = match serde_json::from_slice::<GlobalPluginRequest>(&body_bytes) {
+        Ok(request) => request,
+        Err(e) => return Ok(Err(problem::response(StatusCode::BAD_REQUEST, format!("Invalid global plugin data: {}", e)))),
+    };
+    if let Err(e) = PluginRegistry::new().create_plugin(&request.plugin_name, request.config.clone()) {
+        return Ok(Err(problem::response(StatusCode::BAD_REQUEST, format!("{:#}", e))));
+    }
+    Ok(Ok(request))
+}
+
+/// The global plugin config with an ID, as in the loaded configuration
+async fn find(plugin_id: &str, state: &AdminApiState) -> Option<PluginConfig> {
+    state.shared_config.read().await.plugin_configs.iter()
+        .find(|pc| pc.id == plugin_id && pc.scope == PluginScope::Global)
+        .cloned()
+}
+
+/// Handler for GET /plugins/global endpoint - lists the plugin configs that run for every proxy
+pub async fn list_global_plugins(req: &Request<Body>, workspace: Option<&str>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let query = match ListQuery::from_request(req) {
+        Ok(query) => query,
+        Err(message) => return Ok(invalid_query_response(&message)),
+    };
+
+    let config = state.shared_config.read().await;
+    let plugins = config.plugin_configs.iter()
+        .filter(|pc| pc.scope == PluginScope::Global)
+        .filter(|pc| workspace.map_or(true, |workspace| pc.workspace == workspace));
+
+    let (page, pagination_meta) = match query.apply(plugins) {
+        Ok(page) => page,
+        Err(message) => return Ok(invalid_query_response(&message)),
+    };
+    let response = create_paginated_response(page, pagination_meta);
+    Ok(json_response(StatusCode::OK, serde_json::to_string(&response)?))
+}
+
+/// Handler for POST /plugins/global endpoint - adds a plugin to every proxy of its workspace
+pub async fn create_global_plugin(req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let request = match parse_request(req).await? {
+        Ok(request) => request,
+        Err(response) => return Ok(response),
+    };
+
+    let now = chrono::Utc::now();
+    let mut plugin_config = PluginConfig {
+        id: String::new(),
+        plugin_name: request.plugin_name,
+        workspace: request.workspace.unwrap_or_else(|| "default".to_string()),
+        version: 1,
+        tags: request.tags,
+        config: request.config,
+        scope: PluginScope::Global,
+        proxy_id: None,
+        enabled: request.enabled,
+        created_at: now,
+        updated_at: now,
+    };
+
+    match state.db_client.create_plugin_config(&plugin_config).await {
+        Ok(id) => {
+            plugin_config.id = id;
+            info!("Created global plugin {} ({})", plugin_config.id, plugin_config.plugin_name);
+            entity_response(StatusCode::CREATED, &plugin_config)
+        },
+        Err(e) => {
+            error!("Failed to create global plugin in database: {}", e);
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create global plugin"))
+        },
+    }
+}
+
+/// Handler for GET /plugins/global/{id} endpoint
+pub async fn get_global_plugin(plugin_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    match find(plugin_id, &state).await {
+        Some(plugin_config) => entity_response(StatusCode::OK, &plugin_config),
+        None => Ok(not_found_response()),
+    }
+}
+
+/// Handler for PUT /plugins/global/{id} endpoint - replaces the plugin, its
+/// config and whether it is enabled
+pub async fn update_global_plugin(plugin_id: &str, req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let expected_version = match etag::if_match_version(&req) {
+        Ok(version) => version,
+        Err(response) => return Ok(response),
+    };
+    let request = match parse_request(req).await? {
+        Ok(request) => request,
+        Err(response) => return Ok(response),
+    };
+    let mut plugin_config = match find(plugin_id, &state).await {
+        Some(plugin_config) => plugin_config,
+        None => return Ok(not_found_response()),
+    };
+
+    plugin_config.plugin_name = request.plugin_name;
+    plugin_config.config = request.config;
+    plugin_config.enabled = request.enabled;
+    plugin_config.tags = request.tags;
+    if let Some(workspace) = request.workspace {
+        plugin_config.workspace = workspace;
+    }
+    plugin_config.version = expected_version;
+    save(plugin_config, &state).await
+}
+
+/// Handler for POST /plugins/global/{id}/enable and /disable endpoints - turns
+/// a global plugin on or off without losing its config
+pub async fn set_global_plugin_enabled(plugin_id: &str, enabled: bool, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let mut plugin_config = match find(plugin_id, &state).await {
+        Some(plugin_config) => plugin_config,
+        None => return Ok(not_found_response()),
+    };
+    if plugin_config.enabled == enabled {
+        return entity_response(StatusCode::OK, &plugin_config);
+    }
+
+    plugin_config.enabled = enabled;
+    info!("{} global plugin {}", if enabled { "Enabling" } else { "Disabling" }, plugin_id);
+    save(plugin_config, &state).await
+}
+
+/// Handler for DELETE /plugins/global/{id} endpoint
+pub async fn delete_global_plugin(plugin_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    if find(plugin_id, &state).await.is_none() {
+        return Ok(not_found_response());
+    }
+
+    match state.db_client.delete_plugin_config(plugin_id).await {
+        Ok(_) => Ok(Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Body::empty())
+            .unwrap()),
+        Err(e) => {
+            error!("Failed to delete global plugin from database: {}", e);
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete global plugin"))
+        },
+    }
+}
+
+/// Stores a changed global plugin whose `version` is the one it was based on
+async fn save(mut plugin_config: PluginConfig, state: &AdminApiState) -> Result<Response<Body>> {
+    plugin_config.updated_at = chrono::Utc::now();
+    match state.db_client.update_plugin_config(&plugin_config).await {
+        Ok(_) => {
+            plugin_config.version += 1;
+            entity_response(StatusCode::OK, &plugin_config)
+        },
+        Err(e) => {
+            if let Some(response) = etag::stale_response(&e) {
+                return Ok(response);
+            }
+            error!("Failed to update global plugin in database: {}", e);
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update global plugin"))
+        },
+    }
+}
@@ -1,3 +1,19 @@
 pub mod proxies;
+pub mod taps;
 pub mod consumers;
 pub mod plugins;
+pub mod global_plugins;
+pub mod certificates;
+pub mod snis;
+pub mod config;
+pub mod versions;
+pub mod audit;
+pub mod nodes;
+pub mod users;
+pub mod tokens;
+pub mod auth;
+pub mod debug;
+pub mod dns;
+pub mod events;
+pub mod credentials;
+pub mod status;
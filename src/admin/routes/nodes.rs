@@ -0,0 +1,123 @@
+use std::sync::Arc;
+use anyhow::Result;
+use hyper::{Body, Request, Response, StatusCode};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, info};
+
+use crate::admin::AdminApiState;
+use crate::admin::problem;
+use crate::grpc::debug::{HealthRecord, NodeDebugger};
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn debugger(state: &AdminApiState) -> Result<&Arc<NodeDebugger>, Response<Body>> {
+    state.node_debugger.as_ref().ok_or_else(|| problem::response(StatusCode::CONFLICT, "Data Plane nodes can only be inspected in Control Plane mode"))
+}
+
+fn node_not_connected_response(node_id: &str) -> Response<Body> {
+    problem::response(StatusCode::NOT_FOUND, format!("Data Plane node {} is not connected", node_id))
+}
+
+/// Handler for GET /nodes/{id}/config endpoint - the configuration the Control
+/// Plane last sent a connected node, with the version the node reported running
+pub async fn get_node_config(node_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let debugger = match debugger(&state) {
+        Ok(debugger) => debugger,
+        Err(response) => return Ok(response),
+    };
+
+    match debugger.effective_config(node_id).await {
+        Some(effective) => Ok(json_response(StatusCode::OK, serde_json::to_string(&effective)?)),
+        None => Ok(node_not_connected_response(node_id)),
+    }
+}
+
+/// Handler for POST /nodes/{id}/resync endpoint - sends a connected node the
+/// full snapshot of its version in place of deltas
+pub async fn resync_node(node_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let debugger = match debugger(&state) {
+        Ok(debugger) => debugger,
+        Err(response) => return Ok(response),
+    };
+
+    match debugger.resync(node_id).await {
+        Some(version) => {
+            info!("Forced a full configuration resync of Data Plane node {} (v{})", node_id, version);
+            Ok(json_response(
+                StatusCode::ACCEPTED,
+                serde_json::json!({ "node_id": node_id, "version": version }).to_string(),
+            ))
+        },
+        None => Ok(node_not_connected_response(node_id)),
+    }
+}
+
+/// Handler for GET /nodes/proxy-health endpoint - the proxies with backends
+/// down in the last health report of each node
+pub async fn list_degraded_proxies(state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let debugger = match debugger(&state) {
+        Ok(debugger) => debugger,
+        Err(response) => return Ok(response),
+    };
+
+    let proxies = debugger.degraded_proxies();
+    Ok(json_response(StatusCode::OK, serde_json::json!({ "proxies": proxies }).to_string()))
+}
+
+/// Handler for GET /nodes/{id}/health endpoint - the node's recent health
+/// reports, oldest first. With `?follow=true` the response stays open and
+/// each new report is written as a line of JSON.
+pub async fn get_node_health(node_id: &str, req: &Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let debugger = match debugger(&state) {
+        Ok(debugger) => debugger,
+        Err(response) => return Ok(response),
+    };
+
+    let follow = url::form_urlencoded::parse(req.uri().query().unwrap_or("").as_bytes())
+        .any(|(key, value)| key == "follow" && value == "true");
+    if !follow {
+        let reports = debugger.health_reports(node_id);
+        return Ok(json_response(StatusCode::OK, serde_json::json!({ "reports": reports }).to_string()));
+    }
+
+    // Subscribe before reading the history so no report falls in between
+    let mut reports = debugger.subscribe();
+    let history = debugger.health_reports(node_id);
+    let (mut sender, body) = Body::channel();
+    let node_id = node_id.to_string();
+    tokio::spawn(async move {
+        for record in &history {
+            if sender.send_data(health_line(record).into()).await.is_err() {
+                return;
+            }
+        }
+        loop {
+            match reports.recv().await {
+                Ok(record) if record.node_id == node_id => {
+                    if sender.send_data(health_line(&record).into()).await.is_err() {
+                        break;
+                    }
+                },
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+        debug!("Stopped following the health reports of Data Plane node {}", node_id);
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/x-ndjson")
+        .body(body)
+        .unwrap())
+}
+
+fn health_line(record: &HealthRecord) -> String {
+    format!("{}\n", serde_json::to_string(record).unwrap_or_default())
+}
@@ -4,20 +4,21 @@ use hyper::{Body, Request, Response, StatusCode};
 use tracing::{debug, error, info};
 
 use crate::admin::AdminApiState;
+use crate::admin::problem::{self, Problem};
+use crate::admin::validation;
+use crate::admin::etag;
 use crate::config::data_model::PluginConfig;
-use crate::plugins::PluginManager;
+use crate::plugins::PluginRegistry;
 use crate::modes::OperationMode;
-use crate::admin::pagination::{PaginationQuery, create_paginated_response};
+use crate::admin::pagination::{ListQuery, PaginationQuery, create_paginated_response, invalid_query_response};
 
 /// Handler for GET /plugins endpoint - lists all available plugin types
 pub async fn list_plugin_types(state: Arc<AdminApiState>) -> Result<Response<Body>> {
     // Extract pagination parameters
     let pagination = PaginationQuery::from_request(&Request::new(Body::empty()));
     
-    // Use PluginManager to list available plugin types
-    // Note: PluginManager needs access to shared config to potentially load plugin details
-    let plugin_manager = PluginManager::new(state.shared_config.clone());
-    let available = plugin_manager.available_plugins();
+    // Use the PluginRegistry to list available plugin types
+    let available = PluginRegistry::new().available_plugins();
     
     // Apply pagination to the plugins
     let (paginated_plugins, pagination_meta) = pagination.paginate(&available);
@@ -37,15 +38,25 @@ pub async fn list_plugin_types(state: Arc<AdminApiState>) -> Result<Response<Bod
 }
 
 /// Handler for GET /plugins/config endpoint - lists all plugin configurations
-pub async fn list_plugin_configs(state: Arc<AdminApiState>) -> Result<Response<Body>> {
-    // Extract pagination parameters
-    let pagination = PaginationQuery::from_request(&Request::new(Body::empty()));
+pub async fn list_plugin_configs(req: &Request<Body>, workspace: Option<&str>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    // Extract pagination, filter and sort parameters
+    let query = match ListQuery::from_request(req) {
+        Ok(query) => query,
+        Err(message) => return Ok(invalid_query_response(&message)),
+    };
     
     // Get the current configuration
     let config = state.shared_config.read().await;
     
-    // Apply pagination to the plugin configs
-    let (paginated_configs, pagination_meta) = pagination.paginate(&config.plugin_configs);
+    // Only list the plugin configs of the requested workspace
+    let configs = config.plugin_configs.iter()
+        .filter(|item| workspace.map_or(true, |workspace| item.workspace == workspace));
+    
+    // Apply filters, sorting and pagination to the plugin configs
+    let (paginated_configs, pagination_meta) = match query.apply(configs) {
+        Ok(page) => page,
+        Err(message) => return Ok(invalid_query_response(&message)),
+    };
     
     // Create the paginated response
     let response = create_paginated_response(paginated_configs, pagination_meta);
@@ -65,33 +76,25 @@ pub async fn list_plugin_configs(state: Arc<AdminApiState>) -> Result<Response<B
 pub async fn create_plugin_config(req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
     // Check operation mode
     if state.operation_mode == OperationMode::File {
-        return Ok(Response::builder()
-            .status(StatusCode::CONFLICT)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Cannot modify config — currently running in File Mode"}"#))
-            .unwrap());
+        return Ok(problem::file_mode_response());
     }
     
     // Read the request body
     let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
     
     // Deserialize the plugin config from JSON
-    let mut plugin_config = serde_json::from_slice::<PluginConfig>(&body_bytes)
-        .map_err(|e| anyhow::anyhow!("Invalid plugin config data: {}", e))?;
-    
-    // Validate the plugin type
-    let plugin_manager = PluginManager::new(state.shared_config.clone());
-    let available_plugins = plugin_manager.available_plugins();
+    let mut plugin_config = match validation::parse_entity::<PluginConfig>(&body_bytes, "plugin_configs", "plugin config") {
+        Ok(plugin_config) => plugin_config,
+        Err(response) => return Ok(response),
+    };
     
-    if !available_plugins.contains(&plugin_config.plugin_name) {
-        return Ok(Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .header("Content-Type", "application/json")
-            .body(Body::from(format!(
-                r#"{{"error":"Invalid plugin type: {}"}}"#,
-                plugin_config.plugin_name
-            )))
-            .unwrap());
+    // Validate the plugin type, its settings and the proxy it is scoped to
+    {
+        let config = state.shared_config.read().await;
+        let errors = validation::check_plugin_config(&config, &plugin_config);
+        if !errors.is_empty() {
+            return Ok(Problem::validation("plugin config", errors).into_response());
+        }
     }
     
     // Add timestamp
@@ -118,11 +121,7 @@ pub async fn create_plugin_config(req: Request<Body>, state: Arc<AdminApiState>)
         Err(e) => {
             error!("Failed to create plugin config in database: {}", e);
             
-            Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .header("Content-Type", "application/json")
-                .body(Body::from(format!(r#"{{"error":"Failed to create plugin config: {}"}}"#, e)))
-                .unwrap())
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create plugin config"))
         }
     }
 }
@@ -145,6 +144,7 @@ pub async fn get_plugin_config(config_id: &str, state: Arc<AdminApiState>) -> Re
     Ok(Response::builder()
         .status(StatusCode::OK)
         .header("Content-Type", "application/json")
+        .header("ETag", etag::etag(plugin_config.version))
         .body(Body::from(json))
         .unwrap())
 }
@@ -153,63 +153,55 @@ pub async fn get_plugin_config(config_id: &str, state: Arc<AdminApiState>) -> Re
 pub async fn update_plugin_config(config_id: &str, req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
     // Check operation mode
     if state.operation_mode == OperationMode::File {
-        return Ok(Response::builder()
-            .status(StatusCode::CONFLICT)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Cannot modify config — currently running in File Mode"}"#))
-            .unwrap());
+        return Ok(problem::file_mode_response());
     }
     
+    // The version the client read, checked by the database when writing
+    let expected_version = match etag::if_match_version(&req) {
+        Ok(version) => version,
+        Err(response) => return Ok(response),
+    };
+    
     // Read the request body
     let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
     
     // Deserialize the plugin config from JSON
-    let mut updated_config = serde_json::from_slice::<PluginConfig>(&body_bytes)
-        .map_err(|e| anyhow::anyhow!("Invalid plugin config data: {}", e))?;
+    let mut updated_config = match validation::parse_entity::<PluginConfig>(&body_bytes, "plugin_configs", "plugin config") {
+        Ok(plugin_config) => plugin_config,
+        Err(response) => return Ok(response),
+    };
     
     // Ensure the ID in the path matches the ID in the body
     if updated_config.id != config_id {
-        return Ok(Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Plugin config ID in path does not match ID in body"}"#))
-            .unwrap());
+        return Ok(Problem::new(StatusCode::BAD_REQUEST, "Plugin config ID in path does not match ID in body")
+            .with_field("id", "id_mismatch")
+            .into_response());
     }
     
-    // Validate the plugin type
-    let plugin_manager = PluginManager::new(state.shared_config.clone());
-    let available_plugins = plugin_manager.available_plugins();
-    
-    if !available_plugins.contains(&updated_config.plugin_name) {
-        return Ok(Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .header("Content-Type", "application/json")
-            .body(Body::from(format!(
-                r#"{{"error":"Invalid plugin type: {}"}}"#,
-                updated_config.plugin_name
-            )))
-            .unwrap());
-    }
-    
-    // Check if plugin config exists
+    // Check if plugin config exists, then validate the plugin type, its
+    // settings and the proxy it is scoped to
     {
         let config = state.shared_config.read().await;
         
         if !config.plugin_configs.iter().any(|pc| pc.id == config_id) {
-            return Ok(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .header("Content-Type", "application/json")
-                .body(Body::from(r#"{"error":"Plugin config not found"}"#))
-                .unwrap());
+            return Ok(problem::response(StatusCode::NOT_FOUND, "Plugin config not found"));
+        }
+        
+        let errors = validation::check_plugin_config(&config, &updated_config);
+        if !errors.is_empty() {
+            return Ok(Problem::validation("plugin config", errors).into_response());
         }
     }
     
-    // Update timestamp
+    // Update timestamp and version
     updated_config.updated_at = chrono::Utc::now();
+    updated_config.version = expected_version;
     
     // Update the plugin config in the database
     match state.db_client.update_plugin_config(&updated_config).await {
         Ok(_) => {
+            updated_config.version += 1;
+            
             // Serialize the updated plugin config to JSON
             let json = serde_json::to_string(&updated_config)?;
             
@@ -217,17 +209,17 @@ pub async fn update_plugin_config(config_id: &str, req: Request<Body>, state: Ar
             Ok(Response::builder()
                 .status(StatusCode::OK)
                 .header("Content-Type", "application/json")
+                .header("ETag", etag::etag(updated_config.version))
                 .body(Body::from(json))
                 .unwrap())
         },
         Err(e) => {
+            if let Some(response) = etag::stale_response(&e) {
+                return Ok(response);
+            }
             error!("Failed to update plugin config in database: {}", e);
             
-            Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .header("Content-Type", "application/json")
-                .body(Body::from(format!(r#"{{"error":"Failed to update plugin config: {}"}}"#, e)))
-                .unwrap())
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update plugin config"))
         }
     }
 }
@@ -236,11 +228,7 @@ pub async fn update_plugin_config(config_id: &str, req: Request<Body>, state: Ar
 pub async fn delete_plugin_config(config_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
     // Check operation mode
     if state.operation_mode == OperationMode::File {
-        return Ok(Response::builder()
-            .status(StatusCode::CONFLICT)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Cannot modify config — currently running in File Mode"}"#))
-            .unwrap());
+        return Ok(problem::file_mode_response());
     }
     
     // Check if the plugin config exists
@@ -248,11 +236,7 @@ pub async fn delete_plugin_config(config_id: &str, state: Arc<AdminApiState>) ->
         let config = state.shared_config.read().await;
         
         if !config.plugin_configs.iter().any(|pc| pc.id == config_id) {
-            return Ok(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .header("Content-Type", "application/json")
-                .body(Body::from(r#"{"error":"Plugin config not found"}"#))
-                .unwrap());
+            return Ok(problem::response(StatusCode::NOT_FOUND, "Plugin config not found"));
         }
     }
     
@@ -268,11 +252,7 @@ pub async fn delete_plugin_config(config_id: &str, state: Arc<AdminApiState>) ->
         Err(e) => {
             error!("Failed to delete plugin config from database: {}", e);
             
-            Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .header("Content-Type", "application/json")
-                .body(Body::from(format!(r#"{{"error":"Failed to delete plugin config: {}"}}"#, e)))
-                .unwrap())
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete plugin config"))
         }
     }
 }
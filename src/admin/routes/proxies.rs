@@ -1,23 +1,50 @@
 use std::sync::Arc;
 use anyhow::Result;
 use hyper::{Body, Request, Response, StatusCode};
+use serde_json::Value;
 use tracing::{debug, error, info};
 
 use crate::admin::AdminApiState;
-use crate::config::data_model::Proxy;
+use crate::admin::problem::{self, Problem};
+use crate::admin::validation;
+use crate::admin::etag;
+use crate::admin::pagination::{ListQuery, create_paginated_response, invalid_query_response};
+use crate::config::data_model::{Configuration, Proxy};
+use crate::config::validation::{listen_path_owner, workspace_route_overlap};
 use crate::modes::OperationMode;
 use crate::proxy::update_manager::RouterUpdate;
 
 /// Handler for GET /proxies endpoint - lists all proxies
-pub async fn list_proxies(state: Arc<AdminApiState>) -> Result<Response<Body>> {
-    // Extract pagination parameters
-    let pagination = PaginationQuery::from_request(&Request::new(Body::empty()));
+pub async fn list_proxies(req: &Request<Body>, workspace: Option<&str>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    // Extract pagination, filter and sort parameters
+    let mut query = match ListQuery::from_request(req) {
+        Ok(query) => query,
+        Err(message) => return Ok(invalid_query_response(&message)),
+    };
+    // `plugin` filters on the name or ID of the plugin configs a proxy uses
+    let plugin_filters = query.take_filters("plugin");
     
     // Get the current configuration
     let config = state.shared_config.read().await;
+    let plugin_config_ids: Vec<Vec<&str>> = plugin_filters.iter()
+        .map(|filter| config.plugin_configs.iter()
+            .filter(|plugin_config| filter.matches_text(&plugin_config.plugin_name) || filter.matches_text(&plugin_config.id))
+            .map(|plugin_config| plugin_config.id.as_str())
+            .collect())
+        .collect();
+    
+    // Only list the proxies of the requested workspace
+    let proxies = config.proxies.iter()
+        .filter(|item| workspace.map_or(true, |workspace| item.workspace == workspace))
+        .filter(|item| plugin_config_ids.iter().all(|ids| {
+            item.plugins.iter().any(|association| ids.contains(&association.plugin_config_id.as_str()))
+        }));
     
-    // Apply pagination to the proxies
-    let (paginated_proxies, pagination_meta) = pagination.paginate(&config.proxies);
+    // Apply filters, sorting and pagination to the proxies
+    let (paginated_proxies, pagination_meta) = match query.apply(proxies) {
+        Ok(page) => page,
+        Err(message) => return Ok(invalid_query_response(&message)),
+    };
     
     // Create the paginated response
     let response = create_paginated_response(paginated_proxies, pagination_meta);
@@ -37,34 +64,33 @@ pub async fn list_proxies(state: Arc<AdminApiState>) -> Result<Response<Body>> {
 pub async fn create_proxy(req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
     // Check operation mode
     if state.operation_mode == OperationMode::File {
-        return Ok(Response::builder()
-            .status(StatusCode::CONFLICT)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Cannot modify config — currently running in File Mode"}"#))
-            .unwrap());
+        return Ok(problem::file_mode_response());
     }
     
     // Read the request body
     let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
     
     // Deserialize the proxy from JSON
-    let mut proxy = serde_json::from_slice::<Proxy>(&body_bytes)
-        .map_err(|e| anyhow::anyhow!("Invalid proxy data: {}", e))?;
+    let mut proxy = match validation::parse_entity::<Proxy>(&body_bytes, "proxies", "proxy") {
+        Ok(proxy) => proxy,
+        Err(response) => return Ok(response),
+    };
     
     // Verify listen_path uniqueness (in memory check)
     {
         let config = state.shared_config.read().await;
-        for existing_proxy in &config.proxies {
-            if existing_proxy.listen_path == proxy.listen_path {
-                return Ok(Response::builder()
-                    .status(StatusCode::CONFLICT)
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(format!(
-                        r#"{{"error":"A proxy with listen_path '{}' already exists"}}"#,
-                        proxy.listen_path
-                    )))
-                    .unwrap());
-            }
+        if let Some(existing_proxy) = listen_path_owner(&config, &proxy) {
+            return Ok(duplicate_listen_path_response(&proxy, existing_proxy));
+        }
+        
+        if let Some(response) = workspace_overlap_response(&config, &proxy) {
+            return Ok(response);
+        }
+        
+        // Check the rest of the proxy as a configuration file would be checked
+        let errors = validation::check_proxy(&config, &proxy);
+        if !errors.is_empty() {
+            return Ok(Problem::validation("proxy", errors).into_response());
         }
     }
     
@@ -98,11 +124,7 @@ pub async fn create_proxy(req: Request<Body>, state: Arc<AdminApiState>) -> Resu
         Err(e) => {
             error!("Failed to create proxy in database: {}", e);
             
-            Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .header("Content-Type", "application/json")
-                .body(Body::from(format!(r#"{{"error":"Failed to create proxy: {}"}}"#, e)))
-                .unwrap())
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create proxy"))
         }
     }
 }
@@ -131,11 +153,7 @@ pub async fn get_proxy(proxy_id: &str, state: Arc<AdminApiState>) -> Result<Resp
     
     // Return 404 if not found
     if proxy.is_none() {
-        return Ok(Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Proxy not found"}"#))
-            .unwrap());
+        return Ok(problem::response(StatusCode::NOT_FOUND, "Proxy not found"));
     }
     
     let proxy = proxy.unwrap();
@@ -147,6 +165,7 @@ pub async fn get_proxy(proxy_id: &str, state: Arc<AdminApiState>) -> Result<Resp
     Ok(Response::builder()
         .status(StatusCode::OK)
         .header("Content-Type", "application/json")
+        .header("ETag", etag::etag(proxy.version))
         .body(Body::from(json))
         .unwrap())
 }
@@ -155,61 +174,118 @@ pub async fn get_proxy(proxy_id: &str, state: Arc<AdminApiState>) -> Result<Resp
 pub async fn update_proxy(proxy_id: &str, req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
     // Check operation mode
     if state.operation_mode == OperationMode::File {
-        return Ok(Response::builder()
-            .status(StatusCode::CONFLICT)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Cannot modify config — currently running in File Mode"}"#))
-            .unwrap());
+        return Ok(problem::file_mode_response());
     }
     
+    // The version the client read, checked by the database when writing
+    let expected_version = match etag::if_match_version(&req) {
+        Ok(version) => version,
+        Err(response) => return Ok(response),
+    };
+    
     // Read the request body
     let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
     
     // Deserialize the proxy from JSON
-    let mut updated_proxy = serde_json::from_slice::<Proxy>(&body_bytes)
-        .map_err(|e| anyhow::anyhow!("Invalid proxy data: {}", e))?;
+    let updated_proxy = match validation::parse_entity::<Proxy>(&body_bytes, "proxies", "proxy") {
+        Ok(proxy) => proxy,
+        Err(response) => return Ok(response),
+    };
     
     // Ensure the ID in the path matches the ID in the body
     if updated_proxy.id != proxy_id {
-        return Ok(Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Proxy ID in path does not match ID in body"}"#))
-            .unwrap());
+        return Ok(Problem::new(StatusCode::BAD_REQUEST, "Proxy ID in path does not match ID in body")
+            .with_field("id", "id_mismatch")
+            .into_response());
     }
     
+    store_update(proxy_id, updated_proxy, expected_version, &state).await
+}
+
+/// Handler for PATCH /proxies/{id} endpoint - changes the fields of a proxy named
+/// in a JSON merge patch, such as `{"enabled": false}` to take it out of the routes
+pub async fn patch_proxy(proxy_id: &str, req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    // Check operation mode
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+    
+    // A patch only touches the fields it names, so If-Match is optional
+    let if_match = if req.headers().contains_key(hyper::header::IF_MATCH) {
+        match etag::if_match_version(&req) {
+            Ok(version) => Some(version),
+            Err(response) => return Ok(response),
+        }
+    } else {
+        None
+    };
+    
+    // Read the request body
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+    let patch = match serde_json::from_slice::<Value>(&body_bytes) {
+        Ok(patch) if patch.is_object() => patch,
+        _ => return Ok(Problem::invalid_body("The body must be a JSON object with the fields to change").into_response()),
+    };
+    
+    // Apply the patch to the proxy as it is loaded
+    let current = state.shared_config.read().await.proxies.iter().find(|p| p.id == proxy_id).cloned();
+    let current = match current {
+        Some(proxy) => proxy,
+        None => return Ok(problem::response(StatusCode::NOT_FOUND, "Proxy not found")),
+    };
+    let mut document = serde_json::to_value(&current)?;
+    merge_patch(&mut document, &patch);
+    
+    let updated_proxy = match validation::parse_entity::<Proxy>(&serde_json::to_vec(&document)?, "proxies", "proxy") {
+        Ok(proxy) => proxy,
+        Err(response) => return Ok(response),
+    };
+    if updated_proxy.id != proxy_id {
+        return Ok(Problem::new(StatusCode::BAD_REQUEST, "The ID of a proxy cannot be changed")
+            .with_field("id", "id_mismatch")
+            .into_response());
+    }
+    if updated_proxy.enabled != current.enabled {
+        info!("{} proxy {}", if updated_proxy.enabled { "Enabling" } else { "Disabling" }, proxy_id);
+    }
+    
+    store_update(proxy_id, updated_proxy, if_match.unwrap_or(current.version), &state).await
+}
+
+/// Checks and stores a changed proxy whose update was based on `expected_version`
+async fn store_update(proxy_id: &str, mut updated_proxy: Proxy, expected_version: i64, state: &AdminApiState) -> Result<Response<Body>> {
     // Verify listen_path uniqueness (in memory check)
     {
         let config = state.shared_config.read().await;
-        for existing_proxy in &config.proxies {
-            if existing_proxy.id != proxy_id && existing_proxy.listen_path == updated_proxy.listen_path {
-                return Ok(Response::builder()
-                    .status(StatusCode::CONFLICT)
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(format!(
-                        r#"{{"error":"A proxy with listen_path '{}' already exists"}}"#,
-                        updated_proxy.listen_path
-                    )))
-                    .unwrap());
-            }
+        if let Some(existing_proxy) = listen_path_owner(&config, &updated_proxy) {
+            return Ok(duplicate_listen_path_response(&updated_proxy, existing_proxy));
         }
         
         // Check if the proxy exists
         if !config.proxies.iter().any(|p| p.id == proxy_id) {
-            return Ok(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .header("Content-Type", "application/json")
-                .body(Body::from(r#"{"error":"Proxy not found"}"#))
-                .unwrap());
+            return Ok(problem::response(StatusCode::NOT_FOUND, "Proxy not found"));
+        }
+        
+        if let Some(response) = workspace_overlap_response(&config, &updated_proxy) {
+            return Ok(response);
+        }
+        
+        // Check the rest of the proxy as a configuration file would be checked
+        let errors = validation::check_proxy(&config, &updated_proxy);
+        if !errors.is_empty() {
+            return Ok(Problem::validation("proxy", errors).into_response());
         }
     }
     
-    // Update timestamp
+    // Update timestamp and version
     updated_proxy.updated_at = chrono::Utc::now();
+    updated_proxy.version = expected_version;
     
     // Update the proxy in the database
     match state.db_client.update_proxy(&updated_proxy).await {
         Ok(_) => {
+            updated_proxy.version += 1;
+            
             // Serialize the updated proxy to JSON
             let json = serde_json::to_string(&updated_proxy)?;
             
@@ -217,6 +293,7 @@ pub async fn update_proxy(proxy_id: &str, req: Request<Body>, state: Arc<AdminAp
             let response = Response::builder()
                 .status(StatusCode::OK)
                 .header("Content-Type", "application/json")
+                .header("ETag", etag::etag(updated_proxy.version))
                 .body(Body::from(json))
                 .unwrap();
             
@@ -230,13 +307,12 @@ pub async fn update_proxy(proxy_id: &str, req: Request<Body>, state: Arc<AdminAp
             Ok(response)
         },
         Err(e) => {
+            if let Some(response) = etag::stale_response(&e) {
+                return Ok(response);
+            }
             error!("Failed to update proxy in database: {}", e);
             
-            Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .header("Content-Type", "application/json")
-                .body(Body::from(format!(r#"{{"error":"Failed to update proxy: {}"}}"#, e)))
-                .unwrap())
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update proxy"))
         }
     }
 }
@@ -245,11 +321,7 @@ pub async fn update_proxy(proxy_id: &str, req: Request<Body>, state: Arc<AdminAp
 pub async fn delete_proxy(proxy_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
     // Check operation mode
     if state.operation_mode == OperationMode::File {
-        return Ok(Response::builder()
-            .status(StatusCode::CONFLICT)
-            .header("Content-Type", "application/json")
-            .body(Body::from(r#"{"error":"Cannot modify config — currently running in File Mode"}"#))
-            .unwrap());
+        return Ok(problem::file_mode_response());
     }
     
     // Check if the proxy exists
@@ -257,11 +329,7 @@ pub async fn delete_proxy(proxy_id: &str, state: Arc<AdminApiState>) -> Result<R
         let config = state.shared_config.read().await;
         
         if !config.proxies.iter().any(|p| p.id == proxy_id) {
-            return Ok(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .header("Content-Type", "application/json")
-                .body(Body::from(r#"{"error":"Proxy not found"}"#))
-                .unwrap());
+            return Ok(problem::response(StatusCode::NOT_FOUND, "Proxy not found"));
         }
     }
     
@@ -286,11 +354,41 @@ pub async fn delete_proxy(proxy_id: &str, state: Arc<AdminApiState>) -> Result<R
         Err(e) => {
             error!("Failed to delete proxy from database: {}", e);
             
-            Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .header("Content-Type", "application/json")
-                .body(Body::from(format!(r#"{{"error":"Failed to delete proxy: {}"}}"#, e)))
-                .unwrap())
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete proxy"))
         }
     }
 }
+
+/// 409 response naming the proxy that already routes the proxy's listen_path
+fn duplicate_listen_path_response(proxy: &Proxy, existing: &Proxy) -> Response<Body> {
+    let detail = format!("listen_path '{}' is already routed by proxy '{}' ('{}')", proxy.listen_path, existing.id, existing.listen_path);
+    Problem::new(StatusCode::CONFLICT, detail)
+        .with_field("listen_path", "duplicate_listen_path")
+        .into_response()
+}
+
+/// 409 response when the proxy's listen_path overlaps a route of another workspace
+fn workspace_overlap_response(config: &Configuration, proxy: &Proxy) -> Option<Response<Body>> {
+    let other = workspace_route_overlap(config, proxy)?;
+    let detail = format!("listen_path '{}' overlaps '{}' of workspace '{}'", proxy.listen_path, other.listen_path, other.workspace);
+    Some(Problem::new(StatusCode::CONFLICT, detail)
+        .with_field("listen_path", "workspace_route_overlap")
+        .into_response())
+}
+
+/// Applies a JSON merge patch (RFC 7396): objects are merged, `null` removes a
+/// field and any other value replaces it
+fn merge_patch(target: &mut Value, patch: &Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                if value.is_null() {
+                    target.remove(key);
+                } else {
+                    merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+                }
+            }
+        },
+        (target, patch) => *target = patch.clone(),
+    }
+}
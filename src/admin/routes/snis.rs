@@ -0,0 +1,208 @@
+use std::sync::Arc;
+use anyhow::Result;
+use hyper::{Body, Request, Response, StatusCode};
+use serde::Deserialize;
+use tracing::{debug, error};
+
+use crate::admin::AdminApiState;
+use crate::admin::problem;
+use crate::admin::pagination::{ListQuery, create_paginated_response, invalid_query_response};
+use crate::config::data_model::Sni;
+use crate::modes::OperationMode;
+use crate::proxy::update_manager::RouterUpdate;
+
+/// Body of POST /snis and PUT /snis/{id}
+#[derive(Debug, Deserialize)]
+struct SniRequest {
+    #[serde(default)]
+    id: Option<String>,
+    name: String,
+    certificate_id: String,
+}
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn notify_config_changed(state: &AdminApiState) {
+    if let Some(update_tx) = &state.update_tx {
+        if let Err(e) = update_tx.send(RouterUpdate::ConfigChanged) {
+            debug!("Failed to notify router update: {}", e);
+        }
+    }
+}
+
+/// Parses an SNI request body and checks it against the current configuration.
+/// Names are stored lowercase, since TLS server names are case-insensitive.
+async fn parse_sni_request(
+    req: Request<Body>,
+    sni_id: Option<&str>,
+    state: &AdminApiState,
+) -> Result<Result<SniRequest, Response<Body>>> {
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+
+    let mut request = match serde_json::from_slice::<SniRequest>(&body_bytes) {
+        Ok(request) => request,
+        Err(e) => return Ok(Err(problem::response(StatusCode::BAD_REQUEST, format!("Invalid SNI data: {}", e)))),
+    };
+    request.name = request.name.trim().to_ascii_lowercase();
+
+    if request.name.is_empty() {
+        return Ok(Err(problem::response(StatusCode::BAD_REQUEST, "SNI name must not be empty")));
+    }
+
+    let config = state.shared_config.read().await;
+
+    if !config.certificates.iter().any(|c| c.id == request.certificate_id) {
+        return Ok(Err(problem::response(StatusCode::BAD_REQUEST, format!("Certificate '{}' does not exist", request.certificate_id))));
+    }
+
+    if config.snis.iter().any(|s| s.name == request.name && Some(s.id.as_str()) != sni_id) {
+        return Ok(Err(problem::response(StatusCode::CONFLICT, format!("SNI '{}' is already mapped to a certificate", request.name))));
+    }
+
+    Ok(Ok(request))
+}
+
+/// Handler for GET /snis endpoint - lists all SNIs
+pub async fn list_snis(req: &Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let query = match ListQuery::from_request(req) {
+        Ok(query) => query,
+        Err(message) => return Ok(invalid_query_response(&message)),
+    };
+
+    let config = state.shared_config.read().await;
+    let (paginated, pagination_meta) = match query.apply(&config.snis) {
+        Ok(page) => page,
+        Err(message) => return Ok(invalid_query_response(&message)),
+    };
+    let response = create_paginated_response(paginated, pagination_meta);
+
+    Ok(json_response(StatusCode::OK, serde_json::to_string(&response)?))
+}
+
+/// Handler for POST /snis endpoint - maps a hostname to a certificate
+pub async fn create_sni(req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let request = match parse_sni_request(req, None, &state).await? {
+        Ok(request) => request,
+        Err(response) => return Ok(response),
+    };
+
+    let id = request.id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    {
+        let config = state.shared_config.read().await;
+        if config.snis.iter().any(|s| s.id == id) {
+            return Ok(problem::response(StatusCode::CONFLICT, format!("An SNI with ID '{}' already exists", id)));
+        }
+    }
+
+    let now = chrono::Utc::now();
+    let sni = Sni {
+        id,
+        name: request.name,
+        certificate_id: request.certificate_id,
+        created_at: now,
+        updated_at: now,
+    };
+
+    match state.db_client.upsert_sni(&sni).await {
+        Ok(_) => {
+            notify_config_changed(&state);
+            Ok(json_response(StatusCode::CREATED, serde_json::to_string(&sni)?))
+        },
+        Err(e) => {
+            error!("Failed to create SNI in database: {}", e);
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create SNI"))
+        }
+    }
+}
+
+/// Handler for GET /snis/{id} endpoint - gets a specific SNI
+pub async fn get_sni(sni_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let config = state.shared_config.read().await;
+
+    match config.snis.iter().find(|s| s.id == sni_id) {
+        Some(sni) => Ok(json_response(StatusCode::OK, serde_json::to_string(sni)?)),
+        None => Ok(problem::response(StatusCode::NOT_FOUND, "SNI not found")),
+    }
+}
+
+/// Handler for PUT /snis/{id} endpoint - changes an SNI's name or certificate
+pub async fn update_sni(sni_id: &str, req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let created_at = {
+        let config = state.shared_config.read().await;
+        match config.snis.iter().find(|s| s.id == sni_id) {
+            Some(existing) => existing.created_at,
+            None => return Ok(problem::response(StatusCode::NOT_FOUND, "SNI not found")),
+        }
+    };
+
+    let request = match parse_sni_request(req, Some(sni_id), &state).await? {
+        Ok(request) => request,
+        Err(response) => return Ok(response),
+    };
+
+    if request.id.as_deref().map_or(false, |id| id != sni_id) {
+        return Ok(problem::response(StatusCode::BAD_REQUEST, "SNI ID in path does not match ID in body"));
+    }
+
+    let sni = Sni {
+        id: sni_id.to_string(),
+        name: request.name,
+        certificate_id: request.certificate_id,
+        created_at,
+        updated_at: chrono::Utc::now(),
+    };
+
+    match state.db_client.upsert_sni(&sni).await {
+        Ok(_) => {
+            notify_config_changed(&state);
+            Ok(json_response(StatusCode::OK, serde_json::to_string(&sni)?))
+        },
+        Err(e) => {
+            error!("Failed to update SNI in database: {}", e);
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update SNI"))
+        }
+    }
+}
+
+/// Handler for DELETE /snis/{id} endpoint - deletes an SNI
+pub async fn delete_sni(sni_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    {
+        let config = state.shared_config.read().await;
+        if !config.snis.iter().any(|s| s.id == sni_id) {
+            return Ok(problem::response(StatusCode::NOT_FOUND, "SNI not found"));
+        }
+    }
+
+    match state.db_client.delete_sni(sni_id).await {
+        Ok(_) => {
+            notify_config_changed(&state);
+            Ok(Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty())
+                .unwrap())
+        },
+        Err(e) => {
+            error!("Failed to delete SNI from database: {}", e);
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete SNI"))
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+use anyhow::Result;
+use chrono::Utc;
+use hyper::{Body, Response, StatusCode};
+use serde_json::json;
+
+use crate::admin::AdminApiState;
+use crate::health;
+use crate::metrics;
+
+/// Handler for GET /status/detail endpoint - what this instance is running,
+/// where it listens, how busy it is and how healthy its dependencies are
+pub async fn get_status_detail(state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let (connections, requests) = metrics::active_counts();
+    let started_at = health::started_at();
+
+    let config = {
+        let config = state.shared_config.read().await;
+        json!({
+            "last_updated_at": config.last_updated_at,
+            "proxies": config.proxies.len(),
+            "consumers": config.consumers.len(),
+            "plugin_configs": config.plugin_configs.len(),
+            "certificates": config.certificates.len(),
+        })
+    };
+
+    let body = json!({
+        "build": {
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "commit": option_env!("FERRUM_BUILD_COMMIT"),
+            "profile": if cfg!(debug_assertions) { "debug" } else { "release" },
+        },
+        "mode": state.operation_mode,
+        "started_at": started_at,
+        "uptime_seconds": (Utc::now() - started_at).num_seconds(),
+        "listeners": health::listeners(),
+        "active": {
+            "connections": connections,
+            "requests": requests,
+        },
+        "config": config,
+        "health": health::report(state.operation_mode),
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .body(Body::from(serde_json::to_string(&body)?))
+        .unwrap())
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+use hyper::{Body, Request, Response, StatusCode};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::admin::AdminApiState;
+use crate::admin::problem;
+use crate::modes::OperationMode;
+use crate::proxy::tap::{self, TapSettings};
+
+/// Body of POST /proxies/{id}/tap; an empty body takes the defaults
+#[derive(Debug, Deserialize)]
+struct StartTapRequest {
+    #[serde(default = "default_count")]
+    count: usize,
+    #[serde(default)]
+    max_body_bytes: usize,
+    #[serde(default = "default_ttl_seconds")]
+    ttl_seconds: u64,
+}
+
+fn default_count() -> usize {
+    10
+}
+
+fn default_ttl_seconds() -> u64 {
+    300
+}
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn no_tap_response() -> Response<Body> {
+    problem::response(StatusCode::NOT_FOUND, "Proxy has no tap")
+}
+
+/// Handler for POST /proxies/{id}/tap endpoint - captures the proxy's next
+/// requests on this instance, replacing a tap the proxy already has
+pub async fn start_tap(proxy_id: &str, req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::ControlPlane {
+        return Ok(problem::response(StatusCode::CONFLICT, "A Control Plane does not proxy requests; taps work in Database and Hybrid modes"));
+    }
+    if !state.shared_config.read().await.proxies.iter().any(|proxy| proxy.id == proxy_id) {
+        return Ok(problem::response(StatusCode::NOT_FOUND, "Proxy not found"));
+    }
+
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+    let body: &[u8] = if body_bytes.is_empty() { b"{}" } else { &body_bytes };
+    let request: StartTapRequest = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => return Ok(problem::response(StatusCode::BAD_REQUEST, format!("Invalid tap: {}", e))),
+    };
+    if request.count == 0 || request.count > tap::MAX_COUNT {
+        return Ok(problem::response(StatusCode::BAD_REQUEST, format!("count must be between 1 and {}", tap::MAX_COUNT)));
+    }
+    if request.max_body_bytes > tap::MAX_BODY_BYTES {
+        return Ok(problem::response(StatusCode::BAD_REQUEST, format!("max_body_bytes must be at most {}", tap::MAX_BODY_BYTES)));
+    }
+    let ttl = Duration::from_secs(request.ttl_seconds);
+    if ttl.is_zero() || ttl > tap::MAX_TTL {
+        return Ok(problem::response(StatusCode::BAD_REQUEST, format!("ttl_seconds must be between 1 and {}", tap::MAX_TTL.as_secs())));
+    }
+
+    let tap = tap::start(proxy_id, TapSettings { count: request.count, max_body_bytes: request.max_body_bytes, ttl });
+    info!("Tapping the next {} requests of proxy {}", request.count, proxy_id);
+    Ok(json_response(StatusCode::CREATED, serde_json::to_string(&tap)?))
+}
+
+/// Handler for GET /proxies/{id}/tap endpoint - the proxy's tap and the
+/// requests captured so far
+pub async fn get_tap(proxy_id: &str) -> Result<Response<Body>> {
+    match tap::get(proxy_id) {
+        Some(tap) => Ok(json_response(StatusCode::OK, serde_json::to_string(&tap)?)),
+        None => Ok(no_tap_response()),
+    }
+}
+
+/// Handler for DELETE /proxies/{id}/tap endpoint - stops the tap and discards its captures
+pub async fn stop_tap(proxy_id: &str) -> Result<Response<Body>> {
+    if !tap::stop(proxy_id) {
+        return Ok(no_tap_response());
+    }
+
+    info!("Stopped the tap of proxy {}", proxy_id);
+    Ok(Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(Body::empty())
+        .unwrap())
+}
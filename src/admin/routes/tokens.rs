@@ -0,0 +1,174 @@
+use std::sync::Arc;
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use hyper::{Body, Request, Response, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::admin::AdminApiState;
+use crate::admin::auth::{self, Claims};
+use crate::admin::problem;
+use crate::config::data_model::{AdminRole, ApiToken, EntityType, TokenScope};
+use crate::modes::OperationMode;
+
+/// Lifetime of a token created without `expires_in`
+const DEFAULT_EXPIRES_IN: i64 = 30 * 24 * 60 * 60;
+
+/// Body of POST /tokens
+#[derive(Debug, Deserialize)]
+struct CreateTokenRequest {
+    name: String,
+    /// Defaults to the role of the user creating the token
+    #[serde(default)]
+    role: Option<AdminRole>,
+    #[serde(default)]
+    workspace: Option<String>,
+    #[serde(default)]
+    entity_types: Vec<EntityType>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Seconds until the token expires
+    #[serde(default)]
+    expires_in: Option<i64>,
+}
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn file_mode_response() -> Response<Body> {
+    problem::response(StatusCode::CONFLICT, "API tokens are kept in the database and cannot be managed in File Mode")
+}
+
+fn token_not_found_response() -> Response<Body> {
+    problem::response(StatusCode::NOT_FOUND, "Token not found")
+}
+
+fn database_error_response(action: &str, e: anyhow::Error) -> Response<Body> {
+    error!("Failed to {} in database: {}", action, e);
+    problem::response(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to {}", action))
+}
+
+/// Users see and delete their own tokens; admins see and delete everyone's.
+/// Other users' tokens are answered as if they did not exist.
+async fn find_token(token_id: &str, claims: &Claims, state: &AdminApiState) -> Result<Result<ApiToken, Response<Body>>> {
+    match state.db_client.get_api_token(token_id).await {
+        Ok(Some(token)) if token.username == claims.sub || claims.role() == AdminRole::Admin => Ok(Ok(token)),
+        Ok(_) => Ok(Err(token_not_found_response())),
+        Err(e) => Ok(Err(database_error_response("get token", e))),
+    }
+}
+
+/// Handler for GET /tokens endpoint - lists the API tokens of the caller, or of
+/// every user for admins
+pub async fn list_tokens(claims: &Claims, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(file_mode_response());
+    }
+
+    let username = (claims.role() != AdminRole::Admin).then_some(claims.sub.as_str());
+    match state.db_client.list_api_tokens(username).await {
+        Ok(tokens) => Ok(json_response(StatusCode::OK, json!({ "tokens": tokens }).to_string())),
+        Err(e) => Ok(database_error_response("list tokens", e)),
+    }
+}
+
+/// Handler for POST /tokens endpoint - creates a long-lived token, such as one
+/// for CI, limited to a role no higher than the caller's and optionally to a
+/// workspace, entity types and tags. The token is only returned here.
+pub async fn create_token(req: Request<Body>, claims: &Claims, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(file_mode_response());
+    }
+
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+    let request: CreateTokenRequest = match serde_json::from_slice(&body_bytes) {
+        Ok(request) => request,
+        Err(e) => return Ok(problem::response(StatusCode::BAD_REQUEST, format!("Invalid token: {}", e))),
+    };
+    if request.name.trim().is_empty() {
+        return Ok(problem::response(StatusCode::BAD_REQUEST, "name must be non-empty"));
+    }
+    let expires_in = request.expires_in.unwrap_or(DEFAULT_EXPIRES_IN);
+    if expires_in <= 0 {
+        return Ok(problem::response(StatusCode::BAD_REQUEST, "expires_in must be a positive number of seconds"));
+    }
+
+    // A token never reaches further than the token that created it
+    let role = request.role.unwrap_or_else(|| claims.role());
+    if role > claims.role() {
+        return Ok(problem::response(StatusCode::FORBIDDEN, format!("Cannot create a token with role {} from a token with role {}", role.as_str(), claims.role().as_str())));
+    }
+    let workspace = match (&claims.workspace, request.workspace) {
+        (Some(own), Some(requested)) if *own != requested => {
+            return Ok(problem::response(StatusCode::FORBIDDEN, format!("Token is limited to workspace '{}'", own)));
+        },
+        (own, requested) => requested.or_else(|| own.clone()),
+    };
+
+    let now = Utc::now();
+    let token = ApiToken {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: request.name,
+        username: claims.sub.clone(),
+        role,
+        workspace,
+        scope: TokenScope { entity_types: request.entity_types, tags: request.tags },
+        expires_at: now + Duration::seconds(expires_in),
+        created_at: now,
+    };
+    let encoded = auth::issue_api_token(&token, &state.jwt_secret)?;
+
+    match state.db_client.create_api_token(&token).await {
+        Ok(_) => {
+            info!("Created Admin API token {} ({}) for {} with role {}", token.id, token.name, token.username, token.role.as_str());
+            let mut body = serde_json::to_value(&token)?;
+            body["token"] = json!(encoded);
+            Ok(json_response(StatusCode::CREATED, body.to_string()))
+        },
+        Err(e) => Ok(database_error_response("create token", e)),
+    }
+}
+
+/// Handler for GET /tokens/{id} endpoint - gets a token's settings, without the token
+pub async fn get_token(token_id: &str, claims: &Claims, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(file_mode_response());
+    }
+
+    match find_token(token_id, claims, &state).await? {
+        Ok(token) => Ok(json_response(StatusCode::OK, serde_json::to_string(&token)?)),
+        Err(response) => Ok(response),
+    }
+}
+
+/// Handler for DELETE /tokens/{id} endpoint - revokes a token and forgets it
+pub async fn delete_token(token_id: &str, claims: &Claims, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(file_mode_response());
+    }
+
+    let token = match find_token(token_id, claims, &state).await? {
+        Ok(token) => token,
+        Err(response) => return Ok(response),
+    };
+
+    if let Err(e) = state.db_client.revoke_admin_token(&token.id, &token.username, token.expires_at).await {
+        return Ok(database_error_response("revoke token", e));
+    }
+    match state.db_client.delete_api_token(&token.id).await {
+        Ok(_) => {
+            info!("Admin API token {} of {} revoked by {}", token.id, token.username, claims.sub);
+            Ok(Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty())
+                .unwrap())
+        },
+        Err(e) => Ok(database_error_response("delete token", e)),
+    }
+}
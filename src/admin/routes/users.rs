@@ -0,0 +1,199 @@
+use std::sync::Arc;
+use anyhow::Result;
+use hyper::{Body, Request, Response, StatusCode};
+use serde::Deserialize;
+use tracing::{error, info};
+
+use crate::admin::AdminApiState;
+use crate::admin::problem;
+use crate::config::credentials;
+use crate::config::data_model::{AdminRole, AdminUser};
+use crate::modes::OperationMode;
+
+/// Body of POST /users
+#[derive(Debug, Deserialize)]
+struct CreateUserRequest {
+    username: String,
+    password: String,
+    role: AdminRole,
+}
+
+/// Body of PUT /users/{username}; fields left out are kept
+#[derive(Debug, Deserialize)]
+struct UpdateUserRequest {
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    role: Option<AdminRole>,
+}
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn file_mode_response() -> Response<Body> {
+    problem::response(StatusCode::CONFLICT, "Admin users are kept in the database and cannot be managed in File Mode")
+}
+
+fn user_not_found_response() -> Response<Body> {
+    problem::response(StatusCode::NOT_FOUND, "User not found")
+}
+
+fn database_error_response(action: &str, e: anyhow::Error) -> Response<Body> {
+    error!("Failed to {} in database: {}", action, e);
+    problem::response(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to {}", action))
+}
+
+async fn parse_body<T: serde::de::DeserializeOwned>(req: Request<Body>) -> Result<Result<T, Response<Body>>> {
+    let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+    Ok(serde_json::from_slice(&body_bytes).map_err(|e| problem::response(StatusCode::BAD_REQUEST, format!("Invalid user: {}", e))))
+}
+
+/// Whether removing a user's admin role would leave no admin to manage users
+async fn is_last_admin(state: &AdminApiState, user: &AdminUser) -> Result<bool> {
+    if user.role != AdminRole::Admin {
+        return Ok(false);
+    }
+    let users = state.db_client.list_admin_users().await?;
+    Ok(!users.iter().any(|other| other.username != user.username && other.role == AdminRole::Admin))
+}
+
+fn last_admin_response() -> Response<Body> {
+    problem::response(StatusCode::CONFLICT, "Cannot remove the last admin user")
+}
+
+/// Handler for GET /users endpoint - lists the Admin API users
+pub async fn list_users(state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(file_mode_response());
+    }
+
+    match state.db_client.list_admin_users().await {
+        Ok(users) => Ok(json_response(StatusCode::OK, serde_json::json!({ "users": users }).to_string())),
+        Err(e) => Ok(database_error_response("list users", e)),
+    }
+}
+
+/// Handler for POST /users endpoint - creates a user with a password and role
+pub async fn create_user(req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(file_mode_response());
+    }
+
+    let request: CreateUserRequest = match parse_body(req).await? {
+        Ok(request) => request,
+        Err(response) => return Ok(response),
+    };
+    if request.username.is_empty() || request.username.contains('/') {
+        return Ok(problem::response(StatusCode::BAD_REQUEST, "username must be non-empty and cannot contain '/'"));
+    }
+    if request.password.is_empty() {
+        return Ok(problem::response(StatusCode::BAD_REQUEST, "password must be non-empty"));
+    }
+
+    match state.db_client.get_admin_user(&request.username).await {
+        Ok(Some(_)) => return Ok(problem::response(StatusCode::CONFLICT, format!("User '{}' already exists", request.username))),
+        Ok(None) => {},
+        Err(e) => return Ok(database_error_response("create user", e)),
+    }
+
+    let now = chrono::Utc::now();
+    let user = AdminUser {
+        username: request.username,
+        password_hash: credentials::hash_password(&request.password, state.db_client.password_hashing())?,
+        role: request.role,
+        created_at: now,
+        updated_at: now,
+    };
+
+    match state.db_client.upsert_admin_user(&user).await {
+        Ok(_) => {
+            info!("Created Admin API user {} with role {}", user.username, user.role.as_str());
+            Ok(json_response(StatusCode::CREATED, serde_json::to_string(&user)?))
+        },
+        Err(e) => Ok(database_error_response("create user", e)),
+    }
+}
+
+/// Handler for GET /users/{username} endpoint - gets a specific user
+pub async fn get_user(username: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(file_mode_response());
+    }
+
+    match state.db_client.get_admin_user(username).await {
+        Ok(Some(user)) => Ok(json_response(StatusCode::OK, serde_json::to_string(&user)?)),
+        Ok(None) => Ok(user_not_found_response()),
+        Err(e) => Ok(database_error_response("get user", e)),
+    }
+}
+
+/// Handler for PUT /users/{username} endpoint - changes a user's password or role
+pub async fn update_user(username: &str, req: Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(file_mode_response());
+    }
+
+    let mut user = match state.db_client.get_admin_user(username).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return Ok(user_not_found_response()),
+        Err(e) => return Ok(database_error_response("update user", e)),
+    };
+
+    let request: UpdateUserRequest = match parse_body(req).await? {
+        Ok(request) => request,
+        Err(response) => return Ok(response),
+    };
+
+    if let Some(role) = request.role {
+        if role != AdminRole::Admin && is_last_admin(&state, &user).await? {
+            return Ok(last_admin_response());
+        }
+        user.role = role;
+    }
+    if let Some(password) = request.password {
+        if password.is_empty() {
+            return Ok(problem::response(StatusCode::BAD_REQUEST, "password must be non-empty"));
+        }
+        user.password_hash = credentials::hash_password(&password, state.db_client.password_hashing())?;
+    }
+    user.updated_at = chrono::Utc::now();
+
+    match state.db_client.upsert_admin_user(&user).await {
+        Ok(_) => Ok(json_response(StatusCode::OK, serde_json::to_string(&user)?)),
+        Err(e) => Ok(database_error_response("update user", e)),
+    }
+}
+
+/// Handler for DELETE /users/{username} endpoint - deletes a user
+pub async fn delete_user(username: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(file_mode_response());
+    }
+
+    match state.db_client.get_admin_user(username).await {
+        Ok(Some(user)) => {
+            if is_last_admin(&state, &user).await? {
+                return Ok(last_admin_response());
+            }
+        },
+        Ok(None) => return Ok(user_not_found_response()),
+        Err(e) => return Ok(database_error_response("delete user", e)),
+    }
+
+    match state.db_client.delete_admin_user(username).await {
+        Ok(true) => {
+            info!("Deleted Admin API user {}", username);
+            Ok(Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty())
+                .unwrap())
+        },
+        Ok(false) => Ok(user_not_found_response()),
+        Err(e) => Ok(database_error_response("delete user", e)),
+    }
+}
@@ -0,0 +1,241 @@
+use std::sync::Arc;
+use anyhow::Result;
+use hyper::{Body, Request, Response, StatusCode};
+use serde::Deserialize;
+use tracing::{debug, error, info};
+
+use crate::admin::AdminApiState;
+use crate::admin::problem;
+use crate::admin::pagination::{ListQuery, create_paginated_response, invalid_query_response};
+use crate::config::declarative::ConfigDiff;
+use crate::config::versions;
+use crate::modes::OperationMode;
+use crate::proxy::update_manager::RouterUpdate;
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn query_param(req: &Request<Body>, name: &str) -> Option<String> {
+    let query_string = req.uri().query().unwrap_or("");
+    url::form_urlencoded::parse(query_string.as_bytes())
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.to_string())
+}
+
+fn invalid_version_response(version_id: &str) -> Response<Body> {
+    problem::response(StatusCode::BAD_REQUEST, format!("Invalid version ID '{}'", version_id))
+}
+
+fn version_not_found_response(version_id: i64) -> Response<Body> {
+    problem::response(StatusCode::NOT_FOUND, format!("Configuration version {} not found", version_id))
+}
+
+/// Whether a request is recorded in the configuration history: successful
+/// writes in the modes where the Admin API changes the database
+pub fn records_version(state: &AdminApiState, method: &hyper::Method, path: &str, status: StatusCode) -> bool {
+    matches!(state.operation_mode, OperationMode::Database | OperationMode::ControlPlane | OperationMode::Hybrid)
+        && matches!(*method, hyper::Method::POST | hyper::Method::PUT | hyper::Method::PATCH | hyper::Method::DELETE)
+        && status.is_success()
+        && path != "/config/validate"
+        && !path.starts_with("/config/pins/")
+        && !path.ends_with("/promote")
+        && !path.ends_with("/tap")
+        && !path.starts_with("/nodes/")
+        && path != "/users"
+        && !path.starts_with("/users/")
+        && path != "/tokens"
+        && !path.starts_with("/tokens/")
+        && !path.starts_with("/auth/")
+        && !path.starts_with("/debug/")
+}
+
+/// Records the configuration after a change made by an Admin API request.
+/// Failures are logged; the change itself has already been made.
+pub async fn record_version(state: &AdminApiState, author: &str, method: &hyper::Method, path: &str) {
+    let description = match path.strip_prefix("/config/versions/").and_then(|rest| rest.strip_suffix("/rollback")) {
+        Some(version_id) => format!("Rollback to version {}", version_id),
+        None => format!("{} {}", method, path),
+    };
+
+    match versions::record(&state.db_client, author, &description, state.config_version_retention).await {
+        Ok(Some(version_id)) => debug!("Recorded configuration version {}: {}", version_id, description),
+        Ok(None) => debug!("Configuration unchanged by {}, no version recorded", description),
+        Err(e) => error!("Failed to record configuration version for {}: {:#}", description, e),
+    }
+}
+
+/// Handler for GET /config/versions endpoint - lists recorded versions, newest first
+pub async fn list_versions(req: &Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let query = match ListQuery::from_request_sorted(req, "-id") {
+        Ok(query) => query,
+        Err(message) => return Ok(invalid_query_response(&message)),
+    };
+
+    let versions = state.db_client.list_config_versions().await?;
+    let (paginated, pagination_meta) = match query.apply(&versions) {
+        Ok(page) => page,
+        Err(message) => return Ok(invalid_query_response(&message)),
+    };
+    let response = create_paginated_response(paginated, pagination_meta);
+
+    Ok(json_response(StatusCode::OK, serde_json::to_string(&response)?))
+}
+
+/// Handler for GET /config/versions/{id} endpoint - gets a version with its configuration
+pub async fn get_version(version_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let version_id = match version_id.parse::<i64>() {
+        Ok(version_id) => version_id,
+        Err(_) => return Ok(invalid_version_response(version_id)),
+    };
+
+    match state.db_client.get_config_version(Some(version_id)).await? {
+        Some(version) => Ok(json_response(StatusCode::OK, serde_json::to_string(&version)?)),
+        None => Ok(version_not_found_response(version_id)),
+    }
+}
+
+/// Handler for GET /config/diff endpoint - compares version `from` with version
+/// `to`, or with the current configuration when `to` is not given
+pub async fn diff_versions(req: &Request<Body>, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let mut ids = Vec::new();
+    for name in ["from", "to"] {
+        ids.push(match query_param(req, name) {
+            Some(value) => match value.parse::<i64>() {
+                Ok(version_id) => Some(version_id),
+                Err(_) => return Ok(invalid_version_response(&value)),
+            },
+            None => None,
+        });
+    }
+
+    let from_id = match ids[0] {
+        Some(from_id) => from_id,
+        None => return Ok(problem::response(StatusCode::BAD_REQUEST, "The 'from' version is required")),
+    };
+    let from = match state.db_client.get_config_version(Some(from_id)).await? {
+        Some(version) => version.config.unwrap_or_default(),
+        None => return Ok(version_not_found_response(from_id)),
+    };
+
+    let to = match ids[1] {
+        Some(to_id) => match state.db_client.get_config_version(Some(to_id)).await? {
+            Some(version) => version.config.unwrap_or_default(),
+            None => return Ok(version_not_found_response(to_id)),
+        },
+        None => state.shared_config.read().await.clone(),
+    };
+
+    Ok(json_response(
+        StatusCode::OK,
+        serde_json::json!({
+            "from": from_id,
+            "to": ids[1],
+            "changes": ConfigDiff::compute(&from, &to),
+        }).to_string(),
+    ))
+}
+
+/// Handler for POST /config/versions/{id}/rollback endpoint - restores a recorded version
+pub async fn rollback_version(version_id: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    if state.operation_mode == OperationMode::File {
+        return Ok(problem::file_mode_response());
+    }
+
+    let version_id = match version_id.parse::<i64>() {
+        Ok(version_id) => version_id,
+        Err(_) => return Ok(invalid_version_response(version_id)),
+    };
+
+    match versions::rollback(&state.db_client, version_id).await {
+        Ok(Some(diff)) => {
+            if !diff.is_empty() {
+                info!("Configuration rolled back to version {}", version_id);
+                if let Some(update_tx) = &state.update_tx {
+                    if let Err(e) = update_tx.send(RouterUpdate::ConfigChanged) {
+                        debug!("Failed to notify router update: {}", e);
+                    }
+                }
+            }
+            Ok(json_response(
+                StatusCode::OK,
+                serde_json::json!({ "version": version_id, "changes": diff }).to_string(),
+            ))
+        },
+        Ok(None) => Ok(version_not_found_response(version_id)),
+        Err(e) => {
+            error!("Failed to roll back configuration: {:#}", e);
+            Ok(problem::response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to roll back configuration"))
+        }
+    }
+}
+
+fn pins_unavailable_response() -> Response<Body> {
+    problem::response(StatusCode::CONFLICT, "Version pinning is only available in Control Plane mode")
+}
+
+/// Request body of POST /config/versions/{id}/promote
+#[derive(Debug, Deserialize)]
+struct PromoteRequest {
+    group: String,
+}
+
+/// Handler for POST /config/versions/{id}/promote endpoint - pins a Data Plane
+/// group to a recorded version, replacing the version it was pinned to
+pub async fn promote_version(version_id: &str, req: Request<Body>, author: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let group_pins = match &state.group_pins {
+        Some(group_pins) => group_pins,
+        None => return Ok(pins_unavailable_response()),
+    };
+
+    let version_id = match version_id.parse::<i64>() {
+        Ok(version_id) => version_id,
+        Err(_) => return Ok(invalid_version_response(version_id)),
+    };
+
+    let body = hyper::body::to_bytes(req.into_body()).await?;
+    let group = match serde_json::from_slice::<PromoteRequest>(&body) {
+        Ok(request) if !request.group.is_empty() => request.group,
+        _ => return Ok(problem::response(StatusCode::BAD_REQUEST, "Expected a JSON object with the Data Plane 'group' to promote the version to")),
+    };
+
+    let version = match state.db_client.get_config_version(Some(version_id)).await? {
+        Some(version) => version,
+        None => return Ok(version_not_found_response(version_id)),
+    };
+    let pinned = group_pins.pin(&group, version_id, version.config.unwrap_or_default(), author);
+    info!("Configuration version {} promoted to Data Plane group {} by {}", version_id, group, author);
+
+    Ok(json_response(StatusCode::OK, serde_json::to_string(&*pinned)?))
+}
+
+/// Handler for GET /config/pins endpoint - lists the pinned Data Plane groups
+pub async fn list_pins(state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    match &state.group_pins {
+        Some(group_pins) => {
+            let pins: Vec<_> = group_pins.list().into_iter().map(|pin| (*pin).clone()).collect();
+            Ok(json_response(StatusCode::OK, serde_json::json!({ "pins": pins }).to_string()))
+        },
+        None => Ok(pins_unavailable_response()),
+    }
+}
+
+/// Handler for DELETE /config/pins/{group} endpoint - lets a group follow the
+/// latest configuration again
+pub async fn unpin_group(group: &str, state: Arc<AdminApiState>) -> Result<Response<Body>> {
+    let group_pins = match &state.group_pins {
+        Some(group_pins) => group_pins,
+        None => return Ok(pins_unavailable_response()),
+    };
+
+    if group_pins.unpin(group) {
+        info!("Data Plane group {} unpinned", group);
+        Ok(Response::builder().status(StatusCode::NO_CONTENT).body(Body::empty()).unwrap())
+    } else {
+        Ok(problem::response(StatusCode::NOT_FOUND, format!("Data Plane group {} is not pinned", group)))
+    }
+}
@@ -0,0 +1,109 @@
+use hyper::{Body, Method, Request, Response, StatusCode, Uri};
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+use crate::admin::problem;
+use crate::config::data_model::{Configuration, EntityType, TokenScope};
+
+/// The kind of entity a path manages, or None for paths an API token with a
+/// scope may not use at all
+pub fn entity_type(path: &str) -> Option<EntityType> {
+    if path == "/proxies" || path.starts_with("/proxies/") {
+        Some(EntityType::Proxies)
+    } else if path == "/consumers" || path.starts_with("/consumers/") {
+        Some(EntityType::Consumers)
+    } else if path == "/plugins" || path == "/plugins/config" || path.starts_with("/plugins/config/")
+        || path == "/plugins/global" || path.starts_with("/plugins/global/") {
+        Some(EntityType::PluginConfigs)
+    } else {
+        None
+    }
+}
+
+/// Whether a path manages global plugins. They apply to every proxy, so a
+/// token limited to tagged entities may not manage them.
+pub fn is_global_plugin_path(path: &str) -> bool {
+    path == "/plugins/global" || path.starts_with("/plugins/global/")
+}
+
+/// Whether the entity addressed by an ID route carries every tag of the scope.
+/// Entities without them are answered as if they did not exist.
+pub fn owns_entity(config: &Configuration, path: &str, scope: &TokenScope) -> bool {
+    let has_tags = |tags: &[String]| scope.tags.iter().all(|tag| tags.contains(tag));
+    let id_after = |prefix: &str| path.strip_prefix(prefix).map(|rest| rest.split('/').next().unwrap_or(rest));
+
+    if let Some(id) = id_after("/proxies/") {
+        return config.proxies.iter().any(|p| p.id == id && has_tags(&p.tags));
+    }
+    if let Some(id) = id_after("/consumers/") {
+        return config.consumers.iter().any(|c| c.id == id && has_tags(&c.tags));
+    }
+    if let Some(id) = id_after("/plugins/config/").or_else(|| id_after("/plugins/global/")) {
+        return config.plugin_configs.iter().any(|pc| pc.id == id && has_tags(&pc.tags));
+    }
+    true
+}
+
+/// Adds the scope's tags to the entity in a create or update body, so the token
+/// can still reach what it writes. A merge patch without `tags` keeps the
+/// entity's own, which already carry them. A plugin config must be scoped to a
+/// proxy the token reaches, and is rejected with 403 otherwise.
+pub async fn scope_body(req: Request<Body>, scope: &TokenScope, shared_config: &RwLock<Configuration>) -> anyhow::Result<Result<Request<Body>, Response<Body>>> {
+    let (parts, body) = req.into_parts();
+    let body_bytes = hyper::body::to_bytes(body).await?;
+
+    let mut value: Value = match serde_json::from_slice(&body_bytes) {
+        Ok(value) => value,
+        // Left for the handler to report as invalid data
+        Err(_) => return Ok(Ok(Request::from_parts(parts, Body::from(body_bytes)))),
+    };
+
+    if let Some(object) = value.as_object_mut() {
+        if parts.uri.path().starts_with("/plugins/config") {
+            if object.get("scope").and_then(Value::as_str) == Some("global") {
+                return Ok(Err(forbidden_proxy()));
+            }
+            if let Some(proxy_id) = object.get("proxy_id").and_then(Value::as_str) {
+                if !owns_entity(&*shared_config.read().await, &format!("/proxies/{}", proxy_id), scope) {
+                    return Ok(Err(forbidden_proxy()));
+                }
+            }
+        }
+        if parts.method == Method::PATCH && !object.contains_key("tags") {
+            return Ok(Ok(Request::from_parts(parts, Body::from(body_bytes))));
+        }
+        let tags = object.entry("tags").or_insert_with(|| Value::Array(Vec::new()));
+        if tags.is_null() {
+            *tags = Value::Array(Vec::new());
+        }
+        if let Some(tags) = tags.as_array_mut() {
+            for tag in &scope.tags {
+                if !tags.iter().any(|existing| existing.as_str() == Some(tag.as_str())) {
+                    tags.push(Value::from(tag.as_str()));
+                }
+            }
+        }
+    }
+
+    Ok(Ok(Request::from_parts(parts, Body::from(serde_json::to_vec(&value)?))))
+}
+
+/// Narrows a listing to the entities carrying the scope's tags, with the same
+/// `tags=` filters a client could add itself
+pub fn scope_listing(req: Request<Body>, scope: &TokenScope) -> anyhow::Result<Request<Body>> {
+    let (mut parts, body) = req.into_parts();
+    let mut query = url::form_urlencoded::Serializer::new(parts.uri.query().unwrap_or("").to_string());
+    for tag in &scope.tags {
+        query.append_pair("tags", tag);
+    }
+    parts.uri = format!("{}?{}", parts.uri.path(), query.finish()).parse::<Uri>()?;
+    Ok(Request::from_parts(parts, body))
+}
+
+pub fn forbidden() -> Response<Body> {
+    problem::response(StatusCode::FORBIDDEN, "Token is not allowed to manage this kind of entity")
+}
+
+fn forbidden_proxy() -> Response<Body> {
+    problem::response(StatusCode::FORBIDDEN, "Token may only add plugins to proxies carrying its tags")
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use hyper::header::{self, HeaderValue};
+use hyper::{Body, Method, Request, Response, StatusCode};
+
+/// Path the management UI is served under
+pub const UI_PREFIX: &str = "/ui/";
+
+/// Largest bundle read into memory, so a misconfigured path can't exhaust it
+const MAX_BUNDLE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// One file of the bundle
+#[derive(Debug, Clone)]
+struct Asset {
+    content_type: &'static str,
+    body: Bytes,
+}
+
+/// The static files of a management UI, read from FERRUM_ADMIN_UI_PATH once at
+/// startup and served from memory under /ui/
+#[derive(Debug, Clone, Default)]
+pub struct UiBundle {
+    /// Keyed by the path relative to the bundle root, with `/` separators
+    assets: HashMap<String, Asset>,
+}
+
+impl UiBundle {
+    /// Reads every file under a directory
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut bundle = Self::default();
+        let mut total = 0;
+        bundle.add_dir(dir, "", &mut total)
+            .with_context(|| format!("Failed to read the Admin UI from {}", dir.display()))?;
+        if !bundle.assets.contains_key("index.html") {
+            anyhow::bail!("The Admin UI at {} has no index.html", dir.display());
+        }
+        Ok(bundle)
+    }
+
+    fn add_dir(&mut self, dir: &Path, prefix: &str, total: &mut u64) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let relative = format!("{}{}", prefix, name);
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                self.add_dir(&entry.path(), &format!("{}/", relative), total)?;
+            } else if file_type.is_file() {
+                let body = std::fs::read(entry.path())?;
+                *total += body.len() as u64;
+                if *total > MAX_BUNDLE_BYTES {
+                    anyhow::bail!("The Admin UI is larger than {} bytes", MAX_BUNDLE_BYTES);
+                }
+                self.assets.insert(relative, Asset {
+                    content_type: content_type(&name),
+                    body: Bytes::from(body),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    /// Serves a GET or HEAD request for a path under /ui. Paths that are not
+    /// files get index.html, so a single-page app can route them itself.
+    pub fn serve(&self, req: &Request<Body>) -> Response<Body> {
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .header(header::ALLOW, "GET, HEAD")
+                .body(Body::empty())
+                .unwrap();
+        }
+
+        let path = req.uri().path();
+        if path == "/ui" {
+            return Response::builder()
+                .status(StatusCode::MOVED_PERMANENTLY)
+                .header(header::LOCATION, UI_PREFIX)
+                .body(Body::empty())
+                .unwrap();
+        }
+        let relative = path.strip_prefix(UI_PREFIX).unwrap_or_default();
+        let relative = if relative.is_empty() { "index.html" } else { relative };
+
+        let (name, asset) = match self.assets.get_key_value(relative) {
+            Some((name, asset)) => (name.as_str(), asset),
+            // Missing files with an extension are real 404s, such as a stale script
+            None if has_extension(relative) => {
+                return Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::empty())
+                    .unwrap();
+            },
+            None => ("index.html", &self.assets["index.html"]),
+        };
+
+        // index.html names the other assets, so it is revalidated on every load
+        let cache_control = if name == "index.html" { "no-cache" } else { "public, max-age=3600" };
+        let body = if req.method() == Method::HEAD { Body::empty() } else { Body::from(asset.body.clone()) };
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, HeaderValue::from_static(asset.content_type))
+            .header(header::CONTENT_LENGTH, asset.body.len())
+            .header(header::CACHE_CONTROL, cache_control)
+            .header("X-Content-Type-Options", "nosniff")
+            .body(body)
+            .unwrap()
+    }
+}
+
+/// Whether a request is for the management UI
+pub fn is_ui_path(path: &str) -> bool {
+    path == "/ui" || path.starts_with(UI_PREFIX)
+}
+
+fn has_extension(path: &str) -> bool {
+    path.rsplit('/').next().map_or(false, |name| name.contains('.'))
+}
+
+fn content_type(name: &str) -> &'static str {
+    match name.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase()).as_deref() {
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("js") | Some("mjs") => "text/javascript; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("json") | Some("map") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("ico") => "image/x-icon",
+        Some("webp") => "image/webp",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("wasm") => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,79 @@
+use hyper::{Body, Response};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::admin::problem::{FieldError, Problem};
+use crate::config::data_model::{Configuration, Consumer, PluginConfig, Proxy};
+use crate::config::file_config;
+use crate::config::validation;
+
+/// Parses a proxy, consumer or plugin config from a request body. The body is
+/// checked against the configuration schema first, so every invalid field is
+/// reported rather than the first one serde stops at.
+pub fn parse_entity<T: DeserializeOwned>(body: &[u8], collection: &'static str, kind: &str) -> Result<T, Response<Body>> {
+    let value: Value = serde_json::from_slice(body)
+        .map_err(|e| Problem::invalid_body(format!("The body is not valid JSON: {}", e)).into_response())?;
+    if !value.is_object() {
+        return Err(Problem::invalid_body(format!("The body must be a JSON object describing the {}", kind)).into_response());
+    }
+
+    // The schema describes whole documents, so the entity is checked as the only one of its collection
+    let prefix = format!("{}[0]", collection);
+    let errors: Vec<FieldError> = file_config::schema_violations(&json!({ collection: [&value] }))
+        .into_iter()
+        .filter_map(|(path, message)| {
+            let issue = validation::ValidationIssue { code: "schema", path, message };
+            FieldError::from_issue(&issue, &prefix)
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Err(Problem::validation(kind, errors).into_response());
+    }
+
+    serde_json::from_value(value).map_err(|e| {
+        Problem::validation(kind, vec![FieldError::new("(body)", "schema", e.to_string())]).into_response()
+    })
+}
+
+/// Checks a proxy about to be stored against the other proxies and the plugin
+/// configs it uses: its listen path, tags and plugin references
+pub fn check_proxy(config: &Configuration, proxy: &Proxy) -> Vec<FieldError> {
+    let mut candidate = Configuration::default();
+    candidate.proxies = config.proxies.iter()
+        .filter(|other| other.id != proxy.id)
+        .cloned()
+        .collect();
+    candidate.proxies.push(proxy.clone());
+    candidate.plugin_configs = config.plugin_configs.iter()
+        .filter(|pc| proxy.plugins.iter().any(|association| association.plugin_config_id == pc.id))
+        .cloned()
+        .collect();
+
+    let prefix = format!("proxies[{}]", candidate.proxies.len() - 1);
+    errors_for(&candidate, &prefix)
+}
+
+/// Checks a consumer about to be stored
+pub fn check_consumer(consumer: &Consumer) -> Vec<FieldError> {
+    let mut candidate = Configuration::default();
+    candidate.consumers.push(consumer.clone());
+    errors_for(&candidate, "consumers[0]")
+}
+
+/// Checks a plugin config about to be stored: the plugin must exist and accept
+/// the settings, and a proxy it is scoped to must exist in the same workspace
+pub fn check_plugin_config(config: &Configuration, plugin_config: &PluginConfig) -> Vec<FieldError> {
+    let mut candidate = Configuration::default();
+    candidate.proxies = config.proxies.iter()
+        .filter(|proxy| plugin_config.proxy_id.as_ref() == Some(&proxy.id))
+        .cloned()
+        .collect();
+    candidate.plugin_configs.push(plugin_config.clone());
+    errors_for(&candidate, "plugin_configs[0]")
+}
+
+fn errors_for(candidate: &Configuration, prefix: &str) -> Vec<FieldError> {
+    validation::check(candidate).errors.iter()
+        .filter_map(|issue| FieldError::from_issue(issue, prefix))
+        .collect()
+}
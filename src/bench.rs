@@ -0,0 +1,373 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use anyhow::{Result, Context, bail};
+use clap::Parser;
+use hyper::{Body, Client, Request, Uri};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::config::data_model::Configuration;
+use crate::config::snapshot::ConfigurationSnapshot;
+use crate::modes::file::load_configuration_from_file;
+use crate::proxy::router::Router;
+
+/// Upper bounds (in microseconds) of the latency histogram buckets
+const HISTOGRAM_BUCKETS_US: &[u64] = &[
+    50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000, 1_000_000,
+];
+
+/// Generates synthetic traffic for the proxies in a configuration file and
+/// reports latency percentiles and a histogram.
+///
+/// Without --target the router is benchmarked in-process, which needs no
+/// backends and is suited to catching routing regressions in CI. With
+/// --target, HTTP requests are sent to a running gateway.
+#[derive(Debug, Parser)]
+#[command(name = "ferrumgw bench")]
+pub struct BenchArgs {
+    /// Configuration file (or directory) with the proxies to exercise
+    #[arg(long, env = "FERRUM_FILE_CONFIG_PATH")]
+    pub config: String,
+
+    /// Base URL of a running gateway, e.g. http://localhost:8000
+    #[arg(long)]
+    pub target: Option<String>,
+
+    /// Number of concurrent workers
+    #[arg(long, default_value_t = 16)]
+    pub concurrency: usize,
+
+    /// How long to generate traffic, in seconds
+    #[arg(long, default_value_t = 10)]
+    pub duration_secs: u64,
+
+    /// Stop after this many requests instead of running for the full duration
+    #[arg(long)]
+    pub requests: Option<u64>,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Exit with an error if the p99 latency exceeds this many milliseconds
+    #[arg(long)]
+    pub max_p99_ms: Option<f64>,
+}
+
+/// Outcome counters and latency samples collected by one worker
+#[derive(Default)]
+struct WorkerStats {
+    latencies_us: Vec<u64>,
+    success: u64,
+    client_errors: u64,
+    server_errors: u64,
+    failures: u64,
+}
+
+impl WorkerStats {
+    fn merge(&mut self, other: WorkerStats) {
+        self.latencies_us.extend(other.latencies_us);
+        self.success += other.success;
+        self.client_errors += other.client_errors;
+        self.server_errors += other.server_errors;
+        self.failures += other.failures;
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistogramBucket {
+    pub le_us: u64,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchReport {
+    pub mode: &'static str,
+    pub requests: u64,
+    pub elapsed_secs: f64,
+    pub requests_per_sec: f64,
+    pub success: u64,
+    pub client_errors: u64,
+    pub server_errors: u64,
+    pub failures: u64,
+    pub p50_us: u64,
+    pub p90_us: u64,
+    pub p99_us: u64,
+    pub p999_us: u64,
+    pub max_us: u64,
+    pub histogram: Vec<HistogramBucket>,
+}
+
+pub async fn run(args: BenchArgs) -> Result<()> {
+    if args.concurrency == 0 {
+        bail!("--concurrency must be at least 1");
+    }
+
+    let config = load_configuration_from_file(&args.config)
+        .context("Failed to load benchmark configuration")?;
+
+    let paths = request_paths(&config);
+    if paths.is_empty() {
+        bail!("Configuration {} contains no proxies to benchmark", args.config);
+    }
+
+    info!("Benchmarking {} proxies with {} workers", paths.len(), args.concurrency);
+
+    let report = match &args.target {
+        Some(target) => run_http(&args, target, paths).await?,
+        None => run_router(&args, config, paths).await?,
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+
+    if let Some(max_p99_ms) = args.max_p99_ms {
+        let p99_ms = report.p99_us as f64 / 1000.0;
+        if p99_ms > max_p99_ms {
+            bail!("p99 latency {:.3}ms exceeds the limit of {:.3}ms", p99_ms, max_p99_ms);
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds one request path per proxy that falls under its listen_path
+fn request_paths(config: &Configuration) -> Vec<String> {
+    config.proxies.iter()
+        .map(|proxy| format!("{}/bench", proxy.listen_path.trim_end_matches('/')))
+        .collect()
+}
+
+/// Routes synthetic requests through the router in-process
+async fn run_router(args: &BenchArgs, config: Configuration, paths: Vec<String>) -> Result<BenchReport> {
+    let router = Arc::new(Router::new(ConfigurationSnapshot::shared(config)));
+
+    run_workers(args, "router", paths, move |path| {
+        let router = Arc::clone(&router);
+        async move {
+            let req = Request::builder().uri(path.as_str()).body(Body::empty())?;
+            match router.route(&req) {
+                Some(proxy) => {
+                    router.construct_backend_path(&req, &proxy);
+                    Ok(200)
+                },
+                None => Ok(404),
+            }
+        }
+    }).await
+}
+
+/// Sends HTTP requests to a running gateway
+async fn run_http(args: &BenchArgs, target: &str, paths: Vec<String>) -> Result<BenchReport> {
+    let base = target.trim_end_matches('/').to_string();
+    base.parse::<Uri>().context("Invalid --target URL")?;
+
+    let client = Client::builder()
+        .pool_max_idle_per_host(args.concurrency)
+        .build_http::<Body>();
+
+    run_workers(args, "http", paths, move |path| {
+        let client = client.clone();
+        let uri = format!("{}{}", base, path);
+        async move {
+            let response = client.get(uri.parse()?).await?;
+            let status = response.status().as_u16();
+            // Drain the body so the connection can be reused
+            hyper::body::to_bytes(response.into_body()).await?;
+            Ok(status)
+        }
+    }).await
+}
+
+/// Runs `concurrency` workers cycling through the paths until the duration or
+/// request budget is exhausted, timing each call to `send`
+async fn run_workers<F, Fut>(
+    args: &BenchArgs,
+    mode: &'static str,
+    paths: Vec<String>,
+    send: F,
+) -> Result<BenchReport>
+where
+    F: Fn(String) -> Fut + Clone + Send + Sync + 'static,
+    Fut: std::future::Future<Output = Result<u16>> + Send,
+{
+    let paths = Arc::new(paths);
+    let issued = Arc::new(AtomicU64::new(0));
+    let deadline = Instant::now() + Duration::from_secs(args.duration_secs);
+    let budget = args.requests;
+    let started = Instant::now();
+
+    let mut workers = Vec::with_capacity(args.concurrency);
+    for _ in 0..args.concurrency {
+        let paths = Arc::clone(&paths);
+        let issued = Arc::clone(&issued);
+        let send = send.clone();
+
+        workers.push(tokio::spawn(async move {
+            let mut stats = WorkerStats::default();
+
+            loop {
+                let n = issued.fetch_add(1, Ordering::Relaxed);
+                if budget.map_or(false, |budget| n >= budget) || Instant::now() >= deadline {
+                    break;
+                }
+
+                let path = paths[(n as usize) % paths.len()].clone();
+                let request_start = Instant::now();
+                let result = send(path).await;
+                stats.latencies_us.push(request_start.elapsed().as_micros() as u64);
+
+                match result {
+                    Ok(status) if status < 400 => stats.success += 1,
+                    Ok(status) if status < 500 => stats.client_errors += 1,
+                    Ok(_) => stats.server_errors += 1,
+                    Err(e) => {
+                        if stats.failures == 0 {
+                            warn!("Benchmark request failed: {}", e);
+                        }
+                        stats.failures += 1;
+                    }
+                }
+            }
+
+            stats
+        }));
+    }
+
+    let mut totals = WorkerStats::default();
+    for worker in workers {
+        totals.merge(worker.await?);
+    }
+
+    Ok(build_report(mode, totals, started.elapsed()))
+}
+
+fn build_report(mode: &'static str, mut stats: WorkerStats, elapsed: Duration) -> BenchReport {
+    stats.latencies_us.sort_unstable();
+    let latencies = &stats.latencies_us;
+    let requests = latencies.len() as u64;
+
+    let mut histogram: Vec<HistogramBucket> = HISTOGRAM_BUCKETS_US.iter()
+        .map(|&le_us| HistogramBucket { le_us, count: 0 })
+        .collect();
+    histogram.push(HistogramBucket { le_us: u64::MAX, count: 0 });
+    for &latency in latencies {
+        if let Some(bucket) = histogram.iter_mut().find(|b| latency <= b.le_us) {
+            bucket.count += 1;
+        }
+    }
+
+    let elapsed_secs = elapsed.as_secs_f64();
+
+    BenchReport {
+        mode,
+        requests,
+        elapsed_secs,
+        requests_per_sec: if elapsed_secs > 0.0 { requests as f64 / elapsed_secs } else { 0.0 },
+        success: stats.success,
+        client_errors: stats.client_errors,
+        server_errors: stats.server_errors,
+        failures: stats.failures,
+        p50_us: percentile(latencies, 50.0),
+        p90_us: percentile(latencies, 90.0),
+        p99_us: percentile(latencies, 99.0),
+        p999_us: percentile(latencies, 99.9),
+        max_us: latencies.last().copied().unwrap_or(0),
+        histogram,
+    }
+}
+
+/// Nearest-rank percentile over sorted samples
+fn percentile(sorted: &[u64], pct: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.saturating_sub(1).min(sorted.len() - 1)]
+}
+
+fn print_report(report: &BenchReport) {
+    println!("Mode:        {}", report.mode);
+    println!("Requests:    {} in {:.2}s ({:.0} req/s)", report.requests, report.elapsed_secs, report.requests_per_sec);
+    println!(
+        "Outcomes:    {} ok, {} 4xx, {} 5xx, {} failed",
+        report.success, report.client_errors, report.server_errors, report.failures
+    );
+    println!(
+        "Latency:     p50 {}us, p90 {}us, p99 {}us, p99.9 {}us, max {}us",
+        report.p50_us, report.p90_us, report.p99_us, report.p999_us, report.max_us
+    );
+    println!("Histogram:");
+
+    let widest = report.histogram.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+    for bucket in report.histogram.iter().filter(|b| b.count > 0) {
+        let label = if bucket.le_us == u64::MAX {
+            "+Inf".to_string()
+        } else {
+            format!("<= {}us", bucket.le_us)
+        };
+        let bar = "#".repeat(((bucket.count * 40) / widest) as usize);
+        println!("  {:>12} {:>10} {}", label, bucket.count, bar);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(latencies_us: Vec<u64>) -> WorkerStats {
+        WorkerStats { success: latencies_us.len() as u64, latencies_us, ..WorkerStats::default() }
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        assert_eq!(percentile(&[], 50.0), 0);
+        assert_eq!(percentile(&[], 99.9), 0);
+
+        for pct in [0.0, 50.0, 99.0, 99.9, 100.0] {
+            assert_eq!(percentile(&[7], pct), 7);
+        }
+
+        let samples: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&samples, 0.0), 1);
+        assert_eq!(percentile(&samples, 50.0), 50);
+        assert_eq!(percentile(&samples, 90.0), 90);
+        assert_eq!(percentile(&samples, 99.0), 99);
+        // 99.9% of 100 samples rounds up to the last one
+        assert_eq!(percentile(&samples, 99.9), 100);
+        assert_eq!(percentile(&samples, 100.0), 100);
+    }
+
+    #[test]
+    fn test_histogram_buckets() {
+        let report = build_report("router", stats(vec![1_000_001, 50, 51, 1_000_000, u64::MAX, 0]), Duration::from_secs(1));
+        let counts: Vec<(u64, u64)> = report.histogram.iter()
+            .filter(|bucket| bucket.count > 0)
+            .map(|bucket| (bucket.le_us, bucket.count))
+            .collect();
+
+        // Bounds are inclusive; anything above the last one, up to u64::MAX, overflows
+        assert_eq!(counts, vec![(50, 2), (100, 1), (1_000_000, 1), (u64::MAX, 2)]);
+        assert_eq!(report.histogram.len(), HISTOGRAM_BUCKETS_US.len() + 1);
+        assert_eq!(report.histogram.iter().map(|bucket| bucket.count).sum::<u64>(), report.requests);
+        assert_eq!(report.max_us, u64::MAX);
+    }
+
+    #[test]
+    fn test_requests_per_sec() {
+        let report = build_report("router", stats(vec![10, 20, 30, 40]), Duration::from_millis(500));
+        assert_eq!(report.requests, 4);
+        assert_eq!(report.requests_per_sec, 8.0);
+
+        // No time elapsed reports no rate rather than infinity or NaN
+        let report = build_report("router", stats(vec![10, 20]), Duration::ZERO);
+        assert_eq!(report.requests_per_sec, 0.0);
+
+        let report = build_report("router", WorkerStats::default(), Duration::ZERO);
+        assert_eq!((report.requests, report.requests_per_sec, report.p99_us, report.max_us), (0, 0.0, 0, 0));
+    }
+}
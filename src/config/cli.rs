@@ -0,0 +1,137 @@
+use std::path::PathBuf;
+use anyhow::{Result, Context};
+use clap::{Parser, Subcommand, ValueEnum};
+use tracing::info;
+
+use super::declarative::{self, ConfigDiff};
+use super::document::Format;
+use super::file_config;
+use super::versions;
+use crate::database::cli::connect;
+
+/// Exports the configuration stored in the database as one document, or makes
+/// the database match a document, so Database mode deployments can be driven
+/// from files kept in Git.
+#[derive(Debug, Parser)]
+#[command(name = "ferrumgw config")]
+pub struct ConfigArgs {
+    /// Database type: postgres, mysql or sqlite
+    #[arg(long, env = "FERRUM_DB_TYPE", global = true)]
+    pub db_type: Option<String>,
+
+    /// Database connection URL
+    #[arg(long, env = "FERRUM_DB_URL", global = true, hide_env_values = true)]
+    pub db_url: Option<String>,
+
+    /// Configuration versions kept for rollback (0 keeps all)
+    #[arg(long, env = "FERRUM_CONFIG_VERSION_RETENTION", global = true, default_value_t = 100)]
+    pub version_retention: u64,
+
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Write the current configuration to stdout or a file
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Yaml)]
+        format: ExportFormat,
+
+        /// File to write instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// Only export the proxies, consumers and plugin configs with these tags
+        #[arg(long = "tag", value_delimiter = ',')]
+        tags: Vec<String>,
+    },
+    /// Replace the configuration with a document; entities missing from it are deleted
+    Import {
+        /// Configuration file or directory, in the File mode format
+        path: PathBuf,
+
+        /// Print the changes without applying them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Author recorded in the configuration history, the current user by default
+        #[arg(long, env = "USER", default_value = "cli")]
+        author: String,
+
+        /// Only replace the proxies, consumers and plugin configs with these tags;
+        /// the document's entities are given the tags
+        #[arg(long = "tag", value_delimiter = ',')]
+        tags: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl From<ExportFormat> for Format {
+    fn from(format: ExportFormat) -> Self {
+        match format {
+            ExportFormat::Json => Format::Json,
+            ExportFormat::Yaml => Format::Yaml,
+            ExportFormat::Toml => Format::Toml,
+        }
+    }
+}
+
+pub async fn run(args: ConfigArgs) -> Result<()> {
+    let db_client = connect(args.db_type.as_deref(), args.db_url.as_deref()).await?;
+    let current = db_client.load_full_configuration().await
+        .context("Failed to load the current configuration")?;
+
+    match args.command {
+        ConfigCommand::Export { format, output, tags } => {
+            let document = if tags.is_empty() {
+                declarative::render(&current, format.into())?
+            } else {
+                declarative::render(&declarative::select_tags(&current, &tags), format.into())?
+            };
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, document)
+                        .context(format!("Failed to write {}", path.display()))?;
+                    info!("Exported configuration to {}", path.display());
+                },
+                None => print!("{}", document),
+            }
+        },
+        ConfigCommand::Import { path, dry_run, author, tags } => {
+            // Local files may use environment references and includes, as in File mode
+            let mut desired = if path.is_dir() {
+                file_config::load_from_directory(&path)?
+            } else {
+                file_config::load_file(&path)?
+            };
+            if !tags.is_empty() {
+                desired = declarative::scope_to_tags(&current, desired, &tags);
+            }
+            declarative::validate(&desired)?;
+
+            let diff = if dry_run {
+                ConfigDiff::compute(&current, &desired)
+            } else {
+                let diff = declarative::apply(&db_client, &current, &desired).await?;
+                let description = format!("Import of {}", path.display());
+                versions::record(&db_client, &author, &description, args.version_retention).await
+                    .context("Configuration imported, but recording the version failed")?;
+                diff
+            };
+
+            println!("{}", serde_json::to_string_pretty(&serde_json::json!({
+                "dry_run": dry_run,
+                "changes": diff,
+            }))?);
+        },
+    }
+
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use tracing::warn;
+
+use super::credentials::{self, KEY_AUTH_CREDENTIAL_TYPES};
+use super::data_model::Consumer;
+
+/// Hash lookup indexes over the consumers of a configuration, so auth plugins
+/// can resolve a consumer in O(1) instead of scanning every consumer per request.
+/// Built once per configuration change as part of the ConfigurationSnapshot.
+#[derive(Debug, Default)]
+pub struct ConsumerIndex {
+    consumers: Vec<Consumer>,
+    by_id: HashMap<String, usize>,
+    by_username: HashMap<String, usize>,
+    by_custom_id: HashMap<String, usize>,
+    // Plaintext API keys, keyed by the key itself
+    by_api_key: HashMap<String, usize>,
+    // API keys stored as SHA-256 digests, keyed by the digest
+    by_api_key_digest: HashMap<String, usize>,
+    // Salted API key hashes can't be looked up directly and must be verified one by one
+    hashed_api_keys: Vec<(String, usize)>,
+    // Token subjects listed in jwt credentials
+    by_jwt_subject: HashMap<String, usize>,
+    // Expiry of the API keys that have one, keyed by the key as stored
+    api_key_expiry: HashMap<String, DateTime<Utc>>,
+}
+
+impl ConsumerIndex {
+    pub fn build(consumers: &[Consumer]) -> Self {
+        let mut index = Self {
+            consumers: consumers.to_vec(),
+            by_id: HashMap::with_capacity(consumers.len()),
+            by_username: HashMap::with_capacity(consumers.len()),
+            by_custom_id: HashMap::new(),
+            by_api_key: HashMap::new(),
+            by_api_key_digest: HashMap::new(),
+            hashed_api_keys: Vec::new(),
+            by_jwt_subject: HashMap::new(),
+            api_key_expiry: HashMap::new(),
+        };
+
+        for (position, consumer) in consumers.iter().enumerate() {
+            index.by_id.insert(consumer.id.clone(), position);
+
+            if index.by_username.insert(consumer.username.clone(), position).is_some() {
+                warn!("Duplicate consumer username '{}', the last consumer wins", consumer.username);
+            }
+
+            if let Some(custom_id) = &consumer.custom_id {
+                index.by_custom_id.insert(custom_id.clone(), position);
+            }
+
+            for (key, expires_at) in Self::api_keys(&consumer.credentials) {
+                if let Some(expires_at) = expires_at {
+                    index.api_key_expiry.insert(key.to_string(), expires_at);
+                }
+                if credentials::is_api_key_digest(key) {
+                    if index.by_api_key_digest.insert(key.to_string(), position).is_some() {
+                        warn!("API key of consumer '{}' is shared with another consumer", consumer.username);
+                    }
+                } else if credentials::is_password_hash(key) {
+                    index.hashed_api_keys.push((key.to_string(), position));
+                } else if index.by_api_key.insert(key.to_string(), position).is_some() {
+                    warn!("API key of consumer '{}' is shared with another consumer", consumer.username);
+                }
+            }
+
+            let subjects = consumer.credentials.get("jwt")
+                .and_then(|jwt| jwt.get("subjects"))
+                .and_then(|subjects| subjects.as_array());
+            for subject in subjects.into_iter().flatten().filter_map(|subject| subject.as_str()) {
+                if index.by_jwt_subject.insert(subject.to_string(), position).is_some() {
+                    warn!("JWT subject '{}' of consumer '{}' is mapped to another consumer as well", subject, consumer.username);
+                }
+            }
+        }
+
+        index
+    }
+
+    pub fn by_id(&self, id: &str) -> Option<&Consumer> {
+        self.get(self.by_id.get(id))
+    }
+
+    pub fn by_username(&self, username: &str) -> Option<&Consumer> {
+        self.get(self.by_username.get(username))
+    }
+
+    pub fn by_custom_id(&self, custom_id: &str) -> Option<&Consumer> {
+        self.get(self.by_custom_id.get(custom_id))
+    }
+
+    /// Looks up a consumer by a presented API key, stored either as is or as a
+    /// digest. Expired keys match no consumer.
+    pub fn by_api_key(&self, api_key: &str) -> Option<&Consumer> {
+        let now = Utc::now();
+        if !self.is_expired(api_key, now) {
+            if let Some(consumer) = self.get(self.by_api_key.get(api_key)) {
+                return Some(consumer);
+            }
+        }
+        if self.by_api_key_digest.is_empty() {
+            return None;
+        }
+        let digest = credentials::hash_api_key(api_key);
+        if self.is_expired(&digest, now) {
+            return None;
+        }
+        self.get(self.by_api_key_digest.get(&digest))
+    }
+
+    /// Stored bcrypt or argon2 API key hashes together with their consumers.
+    /// They are salted, so each one has to be verified individually. Expired
+    /// keys are left out.
+    pub fn hashed_api_keys(&self) -> impl Iterator<Item = (&str, &Consumer)> {
+        let now = Utc::now();
+        self.hashed_api_keys.iter()
+            .filter(move |(hash, _)| !self.is_expired(hash, now))
+            .map(move |(hash, position)| (hash.as_str(), &self.consumers[*position]))
+    }
+
+    fn is_expired(&self, stored_key: &str, now: DateTime<Utc>) -> bool {
+        self.api_key_expiry.get(stored_key).map_or(false, |expires_at| *expires_at <= now)
+    }
+
+    /// Resolves an identifier (e.g. a token claim) by ID, then custom_id, then username
+    pub fn find_by_identifier(&self, identifier: &str) -> Option<&Consumer> {
+        self.by_id(identifier)
+            .or_else(|| self.by_custom_id(identifier))
+            .or_else(|| self.by_username(identifier))
+    }
+
+    /// Looks up a consumer by a token subject listed in its jwt credential
+    pub fn by_jwt_subject(&self, subject: &str) -> Option<&Consumer> {
+        self.get(self.by_jwt_subject.get(subject))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Consumer> {
+        self.consumers.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.consumers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.consumers.is_empty()
+    }
+
+    fn get(&self, position: Option<&usize>) -> Option<&Consumer> {
+        position.and_then(|&position| self.consumers.get(position))
+    }
+
+    /// Collects the API keys stored in a consumer's credentials, with their
+    /// expiry: an `api_keys` array, the `key` field of a key-auth credential,
+    /// or its `keys` list
+    fn api_keys(credentials: &HashMap<String, Value>) -> Vec<(&str, Option<DateTime<Utc>>)> {
+        let mut keys: Vec<(&str, Option<DateTime<Utc>>)> = credentials.get("api_keys")
+            .and_then(|v| v.as_array())
+            .map(|keys| keys.iter().filter_map(|k| k.as_str()).map(|k| (k, None)).collect())
+            .unwrap_or_default();
+
+        for credential_type in KEY_AUTH_CREDENTIAL_TYPES {
+            let credential = match credentials.get(*credential_type) {
+                Some(credential) => credential,
+                None => continue,
+            };
+            if let Some(key) = credential.get("key").and_then(|k| k.as_str()) {
+                keys.push((key, credentials::api_key_expiry(credential)));
+            }
+            if let Some(listed) = credential.get("keys").and_then(|k| k.as_array()) {
+                keys.extend(listed.iter().filter_map(|entry| {
+                    let key = entry.get("key")?.as_str()?;
+                    Some((key, credentials::api_key_expiry(entry)))
+                }));
+            }
+        }
+
+        keys
+    }
+}
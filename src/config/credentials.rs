@@ -0,0 +1,308 @@
+//! Hashing of consumer credentials before they are stored
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use anyhow::{Result, anyhow};
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use argon2::password_hash::{SaltString, rand_core::OsRng};
+use subtle::ConstantTimeEq;
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use super::data_model::Consumer;
+
+const API_KEY_DIGEST_PREFIX: &str = "sha256:";
+
+/// Credential types whose `key` field holds an API key
+pub const KEY_AUTH_CREDENTIAL_TYPES: &[&str] = &["key-auth", "keyauth", "key_auth"];
+
+/// Credential types whose `password` field holds a password
+const BASIC_AUTH_CREDENTIAL_TYPES: &[&str] = &["basic-auth", "basicauth", "basic_auth"];
+
+/// Credential types managed as sub-resources of a consumer, one per auth plugin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialType {
+    /// API keys, created with a generated key that is only returned once
+    KeyAuth,
+    /// A password for basic_auth
+    BasicAuth,
+    /// Token subjects jwt_auth maps to the consumer
+    Jwt,
+    /// Subjects of OAuth2 providers oauth2_auth maps to the consumer
+    OAuth2,
+    /// Client certificate fields mtls_auth matches against
+    MtlsAuth,
+}
+
+impl CredentialType {
+    pub const ALL: [CredentialType; 5] = [Self::KeyAuth, Self::BasicAuth, Self::Jwt, Self::OAuth2, Self::MtlsAuth];
+
+    /// Parses a type name, accepting the older spellings of the key and basic auth types
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|credential_type| credential_type.storage_keys().contains(&name))
+    }
+
+    /// Key the credential is stored under in a consumer's `credentials`
+    pub fn as_str(&self) -> &'static str {
+        self.storage_keys()[0]
+    }
+
+    /// Every key the credential may be stored under, the current one first
+    pub fn storage_keys(&self) -> &'static [&'static str] {
+        match self {
+            Self::KeyAuth => &["key_auth", "key-auth", "keyauth"],
+            Self::BasicAuth => &["basic_auth", "basic-auth", "basicauth"],
+            Self::Jwt => &["jwt"],
+            Self::OAuth2 => &["oauth2"],
+            Self::MtlsAuth => &["mtls_auth"],
+        }
+    }
+
+    /// Checks a credential set with PUT. API keys are created with POST instead.
+    pub fn validate(&self, credential: &Value) -> Result<(), String> {
+        let object = credential.as_object().ok_or_else(|| "Credential must be a JSON object".to_string())?;
+        // None when the field is absent, otherwise whether it is a non-empty string
+        let string = |field: &str| object.get(field).map(|value| value.as_str().map_or(false, |value| !value.is_empty()));
+        match self {
+            Self::KeyAuth => Err("API keys are created with POST and revoked with DELETE".to_string()),
+            Self::BasicAuth => match string("password") {
+                Some(true) => Ok(()),
+                _ => Err("'password' must be a non-empty string".to_string()),
+            },
+            Self::Jwt => match object.get("subjects").and_then(Value::as_array) {
+                Some(subjects) if !subjects.is_empty() && subjects.iter().all(|subject| subject.as_str().map_or(false, |subject| !subject.is_empty())) => Ok(()),
+                _ => Err("'subjects' must be a non-empty array of strings".to_string()),
+            },
+            Self::OAuth2 => match object.get("provider_ids").and_then(Value::as_object) {
+                Some(ids) if !ids.is_empty() && ids.values().all(Value::is_string) => Ok(()),
+                _ => Err("'provider_ids' must map provider names to subjects".to_string()),
+            },
+            Self::MtlsAuth => {
+                let fields = ["subject", "san", "fingerprint_sha256"];
+                if fields.iter().any(|field| string(field) == Some(false)) {
+                    return Err("'subject', 'san' and 'fingerprint_sha256' must be non-empty strings".to_string());
+                }
+                if !fields.iter().any(|field| string(field) == Some(true)) {
+                    return Err("One of 'subject', 'san' or 'fingerprint_sha256' is required".to_string());
+                }
+                Ok(())
+            },
+        }
+    }
+
+    /// The credential without its secrets, as the Admin API shows it
+    pub fn metadata(&self, credential: &Value) -> Value {
+        let mut metadata = credential.clone();
+        if let Some(object) = metadata.as_object_mut() {
+            match self {
+                Self::KeyAuth => {
+                    object.remove("key");
+                    if let Some(Value::Array(keys)) = object.get_mut("keys") {
+                        for key in keys.iter_mut().filter_map(Value::as_object_mut) {
+                            key.remove("key");
+                        }
+                    }
+                },
+                Self::BasicAuth => {
+                    object.remove("password");
+                    object.remove("hashed_password");
+                },
+                Self::OAuth2 => {
+                    object.remove("client_secret");
+                },
+                Self::Jwt | Self::MtlsAuth => {},
+            }
+        }
+        metadata
+    }
+}
+
+/// An API key created for a consumer; `key` is the plaintext, returned once
+#[derive(Debug, Clone)]
+pub struct NewApiKey {
+    pub id: String,
+    pub key: String,
+    /// The entry stored in the consumer's `key_auth` credential
+    pub stored: Value,
+}
+
+/// Generates a random API key, or takes the one given, and the entry to store for it
+pub fn new_api_key(key: Option<String>, name: Option<String>) -> NewApiKey {
+    let key = key.unwrap_or_else(|| {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        hex::encode(bytes)
+    });
+    let id = uuid::Uuid::new_v4().to_string();
+    let mut stored = json!({
+        "id": id,
+        "key": hash_api_key(&key),
+        // Lets users tell their keys apart without the key itself
+        "prefix": key.chars().take(6).collect::<String>(),
+        "created_at": chrono::Utc::now(),
+    });
+    if let Some(name) = name {
+        stored["name"] = Value::String(name);
+    }
+    NewApiKey { id, key, stored }
+}
+
+/// When an API key entry, or a key-auth credential with a single `key`, stops
+/// being accepted; None when it does not expire or the time can't be read
+pub fn api_key_expiry(entry: &Value) -> Option<DateTime<Utc>> {
+    entry.get("expires_at")
+        .and_then(Value::as_str)
+        .and_then(|expires_at| DateTime::parse_from_rfc3339(expires_at).ok())
+        .map(|expires_at| expires_at.with_timezone(&Utc))
+}
+
+/// Algorithm passwords and client secrets are hashed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PasswordHashAlgorithm {
+    #[default]
+    Bcrypt,
+    Argon2,
+}
+
+impl FromStr for PasswordHashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bcrypt" => Ok(Self::Bcrypt),
+            "argon2" | "argon2id" => Ok(Self::Argon2),
+            other => Err(format!("Expected bcrypt or argon2. Got: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for PasswordHashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bcrypt => write!(f, "bcrypt"),
+            Self::Argon2 => write!(f, "argon2"),
+        }
+    }
+}
+
+/// Digest an API key is stored as
+pub fn hash_api_key(key: &str) -> String {
+    format!("{}{}", API_KEY_DIGEST_PREFIX, hex::encode(Sha256::digest(key.as_bytes())))
+}
+
+pub fn hash_password(password: &str, algorithm: PasswordHashAlgorithm) -> Result<String> {
+    match algorithm {
+        PasswordHashAlgorithm::Bcrypt => Ok(bcrypt::hash(password, bcrypt::DEFAULT_COST)?),
+        PasswordHashAlgorithm::Argon2 => {
+            let salt = SaltString::generate(&mut OsRng);
+            Argon2::default()
+                .hash_password(password.as_bytes(), &salt)
+                .map(|hash| hash.to_string())
+                .map_err(|e| anyhow!("Failed to hash password with argon2: {}", e))
+        },
+    }
+}
+
+pub fn is_api_key_digest(value: &str) -> bool {
+    value.starts_with(API_KEY_DIGEST_PREFIX)
+}
+
+pub fn is_password_hash(value: &str) -> bool {
+    value.starts_with("$2a$")
+        || value.starts_with("$2b$")
+        || value.starts_with("$2y$")
+        || value.starts_with("$argon2")
+}
+
+/// Checks a presented API key against a stored one, which may be a digest,
+/// a bcrypt or argon2 hash, or a plaintext key stored before hashing
+pub fn verify_api_key(key: &str, stored: &str) -> bool {
+    if is_api_key_digest(stored) {
+        hash_api_key(key).as_bytes().ct_eq(stored.as_bytes()).into()
+    } else {
+        verify_password(key, stored)
+    }
+}
+
+/// Checks a presented password against a stored bcrypt or argon2 hash, or a
+/// plaintext password stored before hashing
+pub fn verify_password(password: &str, stored: &str) -> bool {
+    if stored.starts_with("$argon2") {
+        PasswordHash::new(stored)
+            .map(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+            .unwrap_or(false)
+    } else if is_password_hash(stored) {
+        bcrypt::verify(password, stored).unwrap_or(false)
+    } else {
+        password.as_bytes().ct_eq(stored.as_bytes()).into()
+    }
+}
+
+/// Hashes the secrets of one credential in place and returns whether anything changed
+pub fn hash_credential(credential_type: &str, credential: &mut Value, algorithm: PasswordHashAlgorithm) -> Result<bool> {
+    let mut changed = false;
+    if KEY_AUTH_CREDENTIAL_TYPES.contains(&credential_type) {
+        changed |= hash_field(credential, "key", |key| Ok(hash_api_key(key)), is_api_key_digest)?;
+        if let Some(Value::Array(keys)) = credential.get_mut("keys") {
+            for key in keys.iter_mut() {
+                changed |= hash_field(key, "key", |key| Ok(hash_api_key(key)), is_api_key_digest)?;
+            }
+        }
+    } else if BASIC_AUTH_CREDENTIAL_TYPES.contains(&credential_type) {
+        changed |= hash_field(credential, "password", |password| hash_password(password, algorithm), is_password_hash)?;
+    } else if credential_type == "oauth2" {
+        changed |= hash_field(credential, "client_secret", |secret| hash_password(secret, algorithm), is_password_hash)?;
+    }
+    Ok(changed)
+}
+
+/// Hashes every secret in a consumer's credentials and returns whether anything changed
+pub fn hash_credentials(credentials: &mut HashMap<String, Value>, algorithm: PasswordHashAlgorithm) -> Result<bool> {
+    let mut changed = false;
+    for (credential_type, credential) in credentials.iter_mut() {
+        match (credential_type.as_str(), credential) {
+            // A top-level password, as read by basic-auth
+            ("password", Value::String(password)) if !is_password_hash(password) => {
+                *password = hash_password(password, algorithm)?;
+                changed = true;
+            },
+            ("api_keys", Value::Array(keys)) => {
+                for key in keys.iter_mut() {
+                    if let Value::String(key) = key {
+                        if !is_api_key_digest(key) && !is_password_hash(key) {
+                            *key = hash_api_key(key);
+                            changed = true;
+                        }
+                    }
+                }
+            },
+            (credential_type, credential) => {
+                changed |= hash_credential(credential_type, credential, algorithm)?;
+            },
+        }
+    }
+    Ok(changed)
+}
+
+/// Hashes the secrets of a consumer and returns whether anything changed
+pub fn hash_consumer(consumer: &mut Consumer, algorithm: PasswordHashAlgorithm) -> Result<bool> {
+    hash_credentials(&mut consumer.credentials, algorithm)
+}
+
+fn hash_field(
+    credential: &mut Value,
+    field: &str,
+    hash: impl Fn(&str) -> Result<String>,
+    is_hashed: impl Fn(&str) -> bool,
+) -> Result<bool> {
+    match credential.get_mut(field) {
+        Some(Value::String(value)) if !is_hashed(value) && !is_password_hash(value) => {
+            *value = hash(value)?;
+            Ok(true)
+        },
+        _ => Ok(false),
+    }
+}
@@ -1,16 +1,126 @@
 use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 use serde_json::Value;
 
+/// Workspace of proxies, consumers and plugin configs created without one
+pub const DEFAULT_WORKSPACE: &str = "default";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DatabaseType {
     Postgres,
     MySQL,
     SQLite,
+    MongoDB,
+}
+
+/// Whether connections to Postgres and MySQL use TLS and how the server is verified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DbTlsMode {
+    /// Plain connections only
+    Disable,
+    /// TLS when the server supports it, plain otherwise
+    Prefer,
+    /// TLS without verifying the server certificate
+    Require,
+    /// TLS with a server certificate signed by a trusted CA
+    VerifyCa,
+    /// As verify-ca, and the certificate must match the hostname
+    VerifyFull,
+}
+
+/// Whether a TLS listener asks clients for a certificate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientAuthMode {
+    /// No client certificate is requested
+    None,
+    /// A certificate is requested and verified if presented, but not required
+    Optional,
+    /// The handshake fails without a trusted client certificate
+    Required,
 }
 
+/// How client certificates are checked against their issuer's OCSP responder
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OcspMode {
+    Off,
+    /// Reject revoked certificates, accept when the responder can't be reached
+    Soft,
+    /// Reject revoked certificates and those whose status can't be determined
+    Hard,
+}
+
+/// TLS protocol version bounds for a listener
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum TlsVersion {
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+/// Protocol versions, cipher suites and ALPN protocols offered by a TLS listener
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsPolicy {
+    pub min_version: TlsVersion,
+    pub max_version: TlsVersion,
+    /// rustls cipher suite names, e.g. "TLS13_AES_256_GCM_SHA384". Empty uses the rustls defaults.
+    #[serde(default)]
+    pub cipher_suites: Vec<String>,
+    /// Protocols offered through ALPN, in order of preference
+    pub alpn_protocols: Vec<String>,
+}
+
+impl TlsPolicy {
+    /// Whether HTTP/2 is negotiated on this listener
+    pub fn allows_http2(&self) -> bool {
+        self.alpn_protocols.iter().any(|protocol| protocol == "h2")
+    }
+    
+    /// Whether HTTP/1.1 is negotiated on this listener
+    pub fn allows_http1(&self) -> bool {
+        self.alpn_protocols.iter().any(|protocol| protocol == "http/1.1")
+    }
+    
+    /// The same policy restricted to what QUIC supports: TLS 1.3 and the h3 protocol
+    pub fn for_quic(&self) -> Self {
+        Self {
+            min_version: TlsVersion::Tls13,
+            max_version: TlsVersion::Tls13,
+            cipher_suites: self.cipher_suites.clone(),
+            alpn_protocols: vec!["h3".to_string()],
+        }
+    }
+}
+
+impl Default for TlsPolicy {
+    fn default() -> Self {
+        Self {
+            min_version: TlsVersion::Tls12,
+            max_version: TlsVersion::Tls13,
+            cipher_suites: Vec::new(),
+            alpn_protocols: vec!["h2".to_string(), "http/1.1".to_string()],
+        }
+    }
+}
+
+/// How ACME proves control of a domain to the certificate authority
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AcmeChallengeType {
+    /// Token served from the proxy listener at /.well-known/acme-challenge/
+    #[serde(rename = "http-01")]
+    Http01,
+    /// TXT record published through an external hook command
+    #[serde(rename = "dns-01")]
+    Dns01,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
 pub enum BackendProtocol {
     Http,
     Https,
@@ -19,7 +129,7 @@ pub enum BackendProtocol {
     Grpc,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum AuthMode {
     #[serde(rename = "single")]
     Single,
@@ -33,7 +143,7 @@ impl Default for AuthMode {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum PluginScope {
     #[serde(rename = "global")]
     Global,
@@ -41,10 +151,24 @@ pub enum PluginScope {
     Proxy,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Proxy {
     pub id: String,
     pub name: Option<String>,
+    
+    /// Workspace (team) that owns the proxy
+    #[serde(default = "default_workspace")]
+    pub workspace: String,
+    /// Incremented by every update; an update must name the version it was based on
+    #[serde(default = "default_version")]
+    pub version: i64,
+    /// Labels for grouping and filtering the proxy, e.g. by team or environment
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// A disabled proxy keeps its configuration but is left out of the routes
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    
     pub listen_path: String,
     pub backend_protocol: BackendProtocol,
     pub backend_host: String,
@@ -57,6 +181,18 @@ pub struct Proxy {
     #[serde(default = "default_false")]
     pub preserve_host_header: bool,
     
+    /// Host header sent to the backend, which may use the placeholders
+    /// `{backend_host}` and `{backend_port}`; takes precedence over
+    /// preserve_host_header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_host: Option<String>,
+    
+    /// HTTP CONNECT (`http://`) or SOCKS5 (`socks5://`) proxy that backend
+    /// connections go through, overriding FERRUM_EGRESS_PROXY_URL; `direct`
+    /// connects to the backend directly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub egress_proxy: Option<String>,
+    
     pub backend_connect_timeout_ms: u64,
     pub backend_read_timeout_ms: u64,
     pub backend_write_timeout_ms: u64,
@@ -70,6 +206,15 @@ pub struct Proxy {
     pub backend_tls_server_ca_cert_path: Option<String>,
     pub dns_override: Option<String>,
     pub dns_cache_ttl_seconds: Option<u64>,
+    /// DNS servers to resolve backend_host with, in fallback order, instead of
+    /// the gateway's
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dns_resolvers: Vec<DnsResolver>,
+    
+    /// Finds the backend instances through a service registry instead of
+    /// backend_host and backend_port, which are then unused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend_discovery: Option<BackendDiscovery>,
     
     #[serde(default)]
     pub auth_mode: AuthMode,
@@ -77,31 +222,278 @@ pub struct Proxy {
     #[serde(default)]
     pub plugins: Vec<PluginAssociation>,
     
+    /// Custom bodies for gateway-generated errors, keyed by status code ("404"),
+    /// status class ("5xx") or "default"
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub error_templates: HashMap<String, ErrorTemplate>,
+    
+    /// Which requests reach the log plugins, instead of the gateway's
+    /// FERRUM_LOG_SAMPLE_* settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_sampling: Option<LogSampling>,
+    
+    /// Versions of the API, each of which can be served by its own backend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_versioning: Option<ApiVersioning>,
+    
+    /// Requests per second the proxy accepts, over all clients, before any
+    /// plugin runs; the others get 429
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_requests_per_second: Option<u64>,
+    
+    /// How long the connections to the backend are kept open and reused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend_keepalive: Option<BackendKeepalive>,
+    
+    /// Casing of the header names sent to an HTTP/1.1 backend; lowercase without
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend_header_case: Option<HeaderCase>,
+    
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Service registry that lists the backend instances of a proxy
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BackendDiscovery {
+    /// Healthy instances of a Consul service
+    Consul {
+        service: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tag: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        datacenter: Option<String>,
+    },
+    /// Keys under an etcd prefix, each holding "host:port" or {"host": ..., "port": ...}
+    Etcd {
+        prefix: String,
+    },
+    /// Hosts and ports of the SRV records of a DNS name, such as `_http._tcp.orders.internal`
+    Srv {
+        name: String,
+    },
+}
+
+/// A DNS server to resolve hostnames with instead of the system resolver
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub struct DnsResolver {
+    /// IP address of the server, with an optional port (53 for udp and tcp, 853
+    /// for tls, 443 for https)
+    pub address: String,
+    #[serde(default)]
+    pub protocol: DnsProtocol,
+    /// Name on the server's certificate, required for tls and https
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_name: Option<String>,
+    /// How long to wait for an answer before asking the next server
+    #[serde(default = "default_dns_resolver_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsProtocol {
+    #[default]
+    Udp,
+    Tcp,
+    /// DNS-over-TLS
+    Tls,
+    /// DNS-over-HTTPS
+    Https,
+}
+
+/// Which requests the log plugins see. Server errors and slow requests are
+/// always logged; of the others, one in `one_in` is, picked at random.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct LogSampling {
+    /// Log one in this many requests; 1 logs them all, 0 only errors and slow requests
+    #[serde(default = "default_log_sample_one_in")]
+    pub one_in: u64,
+    /// Always log responses with a 5xx status
+    #[serde(default = "default_true")]
+    pub errors: bool,
+    /// Always log requests that took at least this many milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slow_ms: Option<u64>,
+}
+
+impl Default for LogSampling {
+    fn default() -> Self {
+        Self {
+            one_in: default_log_sample_one_in(),
+            errors: true,
+            slow_ms: None,
+        }
+    }
+}
+
+/// Casing of header names toward HTTP/1.1 backends that treat them as case-sensitive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HeaderCase {
+    /// Each word capitalized, e.g. `Content-Type`
+    Title,
+    /// As the client sent them; headers added by the gateway are lowercase
+    Preserve,
+}
+
+impl HeaderCase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HeaderCase::Title => "title",
+            HeaderCase::Preserve => "preserve",
+        }
+    }
+    
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "title" => Some(HeaderCase::Title),
+            "preserve" => Some(HeaderCase::Preserve),
+            _ => None,
+        }
+    }
+}
+
+/// Keepalive and TCP options of the connections to a proxy's backend. Proxies
+/// with the same options and connect timeout share a connection pool.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub struct BackendKeepalive {
+    /// How long an idle connection stays in the pool; 0 keeps it until the
+    /// backend closes it
+    #[serde(default = "default_backend_idle_timeout_ms")]
+    pub idle_timeout_ms: u64,
+    /// Requests sent over a connection before it is closed, unlimited without
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_requests_per_connection: Option<u64>,
+    /// Send small writes without waiting to fill a segment (TCP_NODELAY)
+    #[serde(default = "default_true")]
+    pub tcp_nodelay: bool,
+    /// Idle seconds before TCP keepalive probes are sent (SO_KEEPALIVE),
+    /// no probes without
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp_keepalive_secs: Option<u64>,
+}
+
+impl Default for BackendKeepalive {
+    fn default() -> Self {
+        Self {
+            idle_timeout_ms: default_backend_idle_timeout_ms(),
+            max_requests_per_connection: None,
+            tcp_nodelay: true,
+            tcp_keepalive_secs: None,
+        }
+    }
+}
+
+/// How requests name the version of the API they want, and the versions a
+/// proxy serves
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ApiVersioning {
+    #[serde(default)]
+    pub source: ApiVersionSource,
+    /// Header holding the version (default `Api-Version`), or parameter of the
+    /// Accept media type holding it (default `version`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Version of the requests that name none; without one they go to the
+    /// proxy's own backend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_version: Option<String>,
+    pub versions: Vec<ApiVersion>,
+}
+
+/// Where requests name the API version
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiVersionSource {
+    /// A request header, e.g. `Api-Version: 2`
+    #[default]
+    Header,
+    /// A parameter of the Accept media type, e.g. `Accept: application/json; version=2`
+    Accept,
+    /// The first path segment after the listen path, e.g. `/orders/v2/items`
+    Path,
+}
+
+/// One version of a proxy's API. Backend settings left out are the proxy's.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ApiVersion {
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend_host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend_port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend_path: Option<String>,
+    /// Send `Deprecation: true` with the version's responses
+    #[serde(default)]
+    pub deprecated: bool,
+    /// When the version goes away, as an HTTP date sent in the Sunset header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sunset: Option<String>,
+    /// Documentation of the deprecation, sent as a `Link` with `rel="deprecation"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation_link: Option<String>,
+}
+
+/// Template for an error response generated by the gateway itself (no route,
+/// rate limited, upstream failures, ...) rather than returned by a backend.
+///
+/// The body may contain the placeholders `{{status}}`, `{{reason}}`, `{{message}}`,
+/// `{{request_id}}`, `{{error_class}}` and `{{proxy_id}}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ErrorTemplate {
+    /// Status code to send instead of the original one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    
+    #[serde(default = "default_error_content_type")]
+    pub content_type: String,
+    
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PluginAssociation {
     pub plugin_config_id: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub embedded_config: Option<Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Consumer {
     pub id: String,
+    /// Unique within the workspace
     pub username: String,
+    /// Workspace the consumer belongs to; it only authenticates to that workspace's proxies
+    #[serde(default = "default_workspace")]
+    pub workspace: String,
+    /// Incremented by every update; an update must name the version it was based on
+    #[serde(default = "default_version")]
+    pub version: i64,
+    /// Labels for grouping and filtering the consumer, e.g. by team or environment
+    #[serde(default)]
+    pub tags: Vec<String>,
     pub custom_id: Option<String>,
     pub credentials: HashMap<String, Value>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PluginConfig {
     pub id: String,
     pub plugin_name: String,
+    /// Workspace of the plugin config; only proxies of the same workspace can use it
+    #[serde(default = "default_workspace")]
+    pub workspace: String,
+    /// Incremented by every update; an update must name the version it was based on
+    #[serde(default = "default_version")]
+    pub version: i64,
+    /// Labels for grouping and filtering the plugin config, e.g. by team or environment
+    #[serde(default)]
+    pub tags: Vec<String>,
     pub config: Value,
     pub scope: PluginScope,
     pub proxy_id: Option<String>,
@@ -110,11 +502,190 @@ pub struct PluginConfig {
     pub updated_at: DateTime<Utc>,
 }
 
+/// A TLS certificate presented by the proxy listeners to clients requesting
+/// one of its SNI hostnames. `cert` and `key` hold PEM data.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Certificate {
+    pub id: String,
+    pub cert: String,
+    pub key: String,
+    /// Hostnames served with this certificate, exact ("api.example.com") or wildcard ("*.example.com")
+    #[serde(default)]
+    pub snis: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A hostname mapped to a certificate through the Admin API. Equivalent to
+/// listing the hostname in the certificate's own `snis`, but managed on its own
+/// so hostnames can be moved between certificates without re-uploading them.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Sni {
+    pub id: String,
+    /// Exact ("api.example.com") or wildcard ("*.example.com") hostname, unique across SNIs
+    pub name: String,
+    pub certificate_id: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A recorded state of the whole configuration, written after each change made
+/// through the Admin API or an import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigVersion {
+    pub id: i64,
+    /// Admin API user or CLI user that made the change
+    pub author: String,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+    /// The configuration after the change; left out of version listings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<Configuration>,
+}
+
+/// One change made through the Admin API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// Subject of the Admin API token that made the change
+    pub actor: String,
+    /// create, update, delete, import, bulk_import or rollback
+    pub action: String,
+    /// proxy, consumer, plugin_config, certificate, sni or configuration
+    pub entity_type: String,
+    pub entity_id: Option<String>,
+    /// Method and path of the request
+    pub request: String,
+    /// The entity before the change, with secrets redacted
+    pub before: Option<Value>,
+    /// The entity after the change, or the changes of a configuration import
+    pub after: Option<Value>,
+    pub source_ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Narrows an audit log listing; unset fields match every entry
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub actor: Option<String>,
+    pub action: Option<String>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Only entries older than this one, for cursor pagination
+    pub before_id: Option<i64>,
+}
+
+/// What an Admin API user may do; each role may do everything the roles
+/// before it may
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminRole {
+    /// Read the configuration, history, audit log, nodes and metrics
+    ReadOnly,
+    /// Also create, update and delete proxies, consumers, plugin configs,
+    /// certificates and SNIs
+    Operator,
+    /// Also manage users, import and roll back the configuration, and manage
+    /// Data Plane nodes
+    Admin,
+}
+
+impl AdminRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ReadOnly => "read_only",
+            Self::Operator => "operator",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for AdminRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read_only" => Ok(Self::ReadOnly),
+            "operator" => Ok(Self::Operator),
+            "admin" => Ok(Self::Admin),
+            other => Err(format!("Expected read_only, operator or admin. Got: {}", other)),
+        }
+    }
+}
+
+/// An Admin API user account
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AdminUser {
+    pub username: String,
+    /// bcrypt or argon2 hash of the password, never returned by the Admin API
+    #[serde(skip_serializing, default)]
+    pub password_hash: String,
+    pub role: AdminRole,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Kinds of entity an Admin API token can be limited to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityType {
+    Proxies,
+    Consumers,
+    /// Plugin configs, including global plugins
+    PluginConfigs,
+}
+
+/// Limits of an Admin API token beyond its role and workspace. A token with a
+/// scope can only reach the proxies, consumers and plugin configs it allows.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TokenScope {
+    /// Entity types the token may reach; empty for all three
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entity_types: Vec<EntityType>,
+    /// Tags every entity the token reaches must carry; entities it creates get them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl TokenScope {
+    pub fn is_unrestricted(&self) -> bool {
+        self.entity_types.is_empty() && self.tags.is_empty()
+    }
+
+    pub fn allows(&self, entity_type: EntityType) -> bool {
+        self.entity_types.is_empty() || self.entity_types.contains(&entity_type)
+    }
+}
+
+/// A long-lived Admin API token created through /tokens, e.g. for CI. The
+/// token itself is only returned when it is created.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ApiToken {
+    /// Also the token's `jti` claim
+    pub id: String,
+    pub name: String,
+    /// User who created the token, whose role it cannot exceed
+    pub username: String,
+    pub role: AdminRole,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    #[serde(default)]
+    pub scope: TokenScope,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Configuration {
     pub proxies: Vec<Proxy>,
     pub consumers: Vec<Consumer>,
     pub plugin_configs: Vec<PluginConfig>,
+    #[serde(default)]
+    pub certificates: Vec<Certificate>,
+    #[serde(default)]
+    pub snis: Vec<Sni>,
     pub last_updated_at: DateTime<Utc>,
 }
 
@@ -124,6 +695,8 @@ impl Default for Configuration {
             proxies: Vec::new(),
             consumers: Vec::new(),
             plugin_configs: Vec::new(),
+            certificates: Vec::new(),
+            snis: Vec::new(),
             last_updated_at: Utc::now(), // Initialize with current time
         }
     }
@@ -147,6 +720,20 @@ pub struct ConfigurationDelta {
     /// IDs of plugin configurations that were deleted
     pub deleted_plugin_config_ids: Vec<String>,
     
+    /// New or updated certificates
+    #[serde(default)]
+    pub updated_certificates: Vec<Certificate>,
+    /// IDs of certificates that were deleted
+    #[serde(default)]
+    pub deleted_certificate_ids: Vec<String>,
+    
+    /// New or updated SNIs
+    #[serde(default)]
+    pub updated_snis: Vec<Sni>,
+    /// IDs of SNIs that were deleted
+    #[serde(default)]
+    pub deleted_sni_ids: Vec<String>,
+    
     /// The timestamp of the latest change in this delta
     pub last_updated_at: DateTime<Utc>,
 }
@@ -184,6 +771,28 @@ impl ConfigurationDelta {
         }
         config.plugin_configs.retain(|p| !self.deleted_plugin_config_ids.contains(&p.id));
         
+        // Apply certificate changes
+        for certificate in &self.updated_certificates {
+            if let Some(existing) = config.certificates.iter_mut().find(|c| c.id == certificate.id) {
+                *existing = certificate.clone();
+            } else {
+                config.certificates.push(certificate.clone());
+            }
+        }
+        config.certificates.retain(|c| !self.deleted_certificate_ids.contains(&c.id));
+        
+        // Apply SNI changes. SNIs of a deleted certificate are deleted with it.
+        for sni in &self.updated_snis {
+            if let Some(existing) = config.snis.iter_mut().find(|s| s.id == sni.id) {
+                *existing = sni.clone();
+            } else {
+                config.snis.push(sni.clone());
+            }
+        }
+        config.snis.retain(|s| {
+            !self.deleted_sni_ids.contains(&s.id) && !self.deleted_certificate_ids.contains(&s.certificate_id)
+        });
+        
         // Update the last_updated_at timestamp
         if self.last_updated_at > config.last_updated_at {
             config.last_updated_at = self.last_updated_at;
@@ -197,7 +806,11 @@ impl ConfigurationDelta {
         self.updated_consumers.is_empty() &&
         self.deleted_consumer_ids.is_empty() &&
         self.updated_plugin_configs.is_empty() &&
-        self.deleted_plugin_config_ids.is_empty()
+        self.deleted_plugin_config_ids.is_empty() &&
+        self.updated_certificates.is_empty() &&
+        self.deleted_certificate_ids.is_empty() &&
+        self.updated_snis.is_empty() &&
+        self.deleted_sni_ids.is_empty()
     }
 }
 
@@ -205,6 +818,72 @@ fn default_true() -> bool {
     true
 }
 
+/// What is wrong with the format of a listen path, if anything. A listen path
+/// is absolute, has no trailing slash (which would route the same requests as
+/// the path without it), no empty, `.` or `..` segments, and none of the
+/// characters that would be taken for a query, a fragment or route syntax.
+pub fn listen_path_problem(listen_path: &str) -> Option<&'static str> {
+    if !listen_path.starts_with('/') {
+        return Some("must start with '/'");
+    }
+    if listen_path == "/" {
+        return None;
+    }
+    if listen_path.ends_with('/') {
+        return Some("must not end with '/'");
+    }
+    if listen_path[1..].split('/').any(|segment| segment.is_empty() || segment == "." || segment == "..") {
+        return Some("must not contain empty, '.' or '..' segments");
+    }
+    if listen_path.chars().any(|c| c.is_whitespace() || c.is_control() || "?#*:{}".contains(c)) {
+        return Some("must not contain whitespace or any of '?', '#', '*', ':', '{' and '}'");
+    }
+    None
+}
+
+/// Checks the format of the proxies' listen paths and that no two of them
+/// route the same paths, naming the proxy a listen path conflicts with
+pub fn validate_proxy_listen_paths(proxies: &[Proxy]) -> Result<(), String> {
+    let mut owners: HashMap<&str, &Proxy> = HashMap::new();
+    for proxy in proxies {
+        if let Some(problem) = listen_path_problem(&proxy.listen_path) {
+            return Err(format!("listen_path '{}' of proxy '{}' {}", proxy.listen_path, proxy.id, problem));
+        }
+        if let Some(other) = owners.insert(proxy.listen_path.trim_end_matches('/'), proxy) {
+            return Err(format!(
+                "listen_path '{}' of proxy '{}' is already used by proxy '{}'",
+                proxy.listen_path, proxy.id, other.id
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn default_dns_resolver_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_log_sample_one_in() -> u64 {
+    1
+}
+
+/// The idle timeout of backend connections when a proxy sets none
+pub fn default_backend_idle_timeout_ms() -> u64 {
+    30_000
+}
+
 fn default_false() -> bool {
     false
 }
+
+fn default_workspace() -> String {
+    DEFAULT_WORKSPACE.to_string()
+}
+
+fn default_version() -> i64 {
+    1
+}
+
+fn default_error_content_type() -> String {
+    "text/plain".to_string()
+}
@@ -0,0 +1,383 @@
+use std::collections::{HashMap, HashSet};
+use anyhow::{Result, Context, bail};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::info;
+
+use super::data_model::Configuration;
+use super::document::{self, Format};
+use super::file_config;
+use super::validation;
+use crate::database::{BulkChanges, DatabaseClient};
+
+/// Changes to one kind of entity, by ID
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct EntityChanges {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+impl EntityChanges {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.updated.is_empty() && self.deleted.is_empty()
+    }
+
+    /// Compares entities by ID. Timestamps and versions are ignored, so
+    /// re-importing an export reports no changes.
+    fn compute<T: Serialize>(current: &[T], desired: &[T], id: impl Fn(&T) -> &str) -> Self {
+        let current: HashMap<&str, Value> = current.iter()
+            .map(|entity| (id(entity), comparable(entity)))
+            .collect();
+
+        let mut changes = Self::default();
+        let mut seen = HashSet::new();
+        for entity in desired {
+            let entity_id = id(entity);
+            seen.insert(entity_id);
+            match current.get(entity_id) {
+                None => changes.created.push(entity_id.to_string()),
+                Some(existing) if *existing != comparable(entity) => changes.updated.push(entity_id.to_string()),
+                Some(_) => {},
+            }
+        }
+
+        changes.deleted = current.keys()
+            .filter(|entity_id| !seen.contains(*entity_id))
+            .map(|entity_id| entity_id.to_string())
+            .collect();
+        changes.deleted.sort();
+        changes
+    }
+}
+
+fn comparable<T: Serialize>(entity: &T) -> Value {
+    let mut value = serde_json::to_value(entity).unwrap_or_default();
+    if let Some(object) = value.as_object_mut() {
+        object.remove("created_at");
+        object.remove("updated_at");
+        object.remove("version");
+    }
+    value
+}
+
+/// What importing a document would change, or has changed
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigDiff {
+    pub proxies: EntityChanges,
+    pub consumers: EntityChanges,
+    pub plugin_configs: EntityChanges,
+    pub certificates: EntityChanges,
+    pub snis: EntityChanges,
+}
+
+impl ConfigDiff {
+    pub fn compute(current: &Configuration, desired: &Configuration) -> Self {
+        Self {
+            proxies: EntityChanges::compute(&current.proxies, &desired.proxies, |p| &p.id),
+            consumers: EntityChanges::compute(&current.consumers, &desired.consumers, |c| &c.id),
+            plugin_configs: EntityChanges::compute(&current.plugin_configs, &desired.plugin_configs, |p| &p.id),
+            certificates: EntityChanges::compute(&current.certificates, &desired.certificates, |c| &c.id),
+            snis: EntityChanges::compute(&current.snis, &desired.snis, |s| &s.id),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proxies.is_empty()
+            && self.consumers.is_empty()
+            && self.plugin_configs.is_empty()
+            && self.certificates.is_empty()
+            && self.snis.is_empty()
+    }
+}
+
+/// The whole configuration as one document, in the file mode format with
+/// entities ordered by ID so exports are stable under version control.
+/// The document includes credentials and private keys.
+pub fn export(config: &Configuration) -> Configuration {
+    let mut export = config.clone();
+    export.proxies.sort_by(|a, b| a.id.cmp(&b.id));
+    export.consumers.sort_by(|a, b| a.id.cmp(&b.id));
+    export.plugin_configs.sort_by(|a, b| a.id.cmp(&b.id));
+    export.certificates.sort_by(|a, b| a.id.cmp(&b.id));
+    export.snis.sort_by(|a, b| a.id.cmp(&b.id));
+    export
+}
+
+/// Serializes an export as JSON, YAML or TOML
+pub fn render(config: &Configuration, format: Format) -> Result<String> {
+    let export = export(config);
+    match format {
+        Format::Json => Ok(serde_json::to_string_pretty(&export)?),
+        Format::Yaml => Ok(serde_yaml::to_string(&export)?),
+        Format::Toml => Ok(toml::to_string_pretty(&export)?),
+    }
+}
+
+/// Parses a document to import. Environment references and includes are not
+/// resolved, since the document does not come from the gateway's own files.
+pub fn parse(content: &str, format: Format) -> Result<Configuration> {
+    let config = parse_unchecked(content, format)?;
+    validate(&config)?;
+    Ok(config)
+}
+
+/// `parse` without `validate`, for documents that are only checked once merged
+/// with the rest of the configuration
+pub fn parse_unchecked(content: &str, format: Format) -> Result<Configuration> {
+    let value = document::parse_str(content, format)?;
+    file_config::from_document(value)
+}
+
+/// Rejects a configuration with any of the errors reported by `validation::check`
+pub fn validate(config: &Configuration) -> Result<()> {
+    let report = validation::check(config);
+    if !report.valid {
+        let problems: Vec<String> = report.errors.iter()
+            .map(|issue| format!("{}: {}", issue.path, issue.message))
+            .collect();
+        bail!("Invalid configuration:\n  {}", problems.join("\n  "));
+    }
+    Ok(())
+}
+
+/// Makes the database match the desired configuration and returns what changed.
+/// Entities are written in dependency order: plugin configs, consumers and
+/// certificates before the proxies and SNIs referring to them, and removed in
+/// the reverse order. The writes are not one transaction; an import that fails
+/// part way can be retried, since it only applies what is still different.
+pub async fn apply(db_client: &DatabaseClient, current: &Configuration, desired: &Configuration) -> Result<ConfigDiff> {
+    let diff = ConfigDiff::compute(current, desired);
+    if diff.is_empty() {
+        return Ok(diff);
+    }
+
+    let now = chrono::Utc::now();
+
+    for plugin_config in &desired.plugin_configs {
+        if diff.plugin_configs.created.contains(&plugin_config.id) {
+            db_client.create_plugin_config(plugin_config).await
+                .context(format!("Failed to create plugin config {}", plugin_config.id))?;
+        } else if diff.plugin_configs.updated.contains(&plugin_config.id) {
+            let mut plugin_config = plugin_config.clone();
+            plugin_config.updated_at = now;
+            plugin_config.version = current_version(&current.plugin_configs, &plugin_config.id, |pc| (&pc.id, pc.version));
+            db_client.update_plugin_config(&plugin_config).await
+                .context(format!("Failed to update plugin config {}", plugin_config.id))?;
+        }
+    }
+
+    for consumer in &desired.consumers {
+        if diff.consumers.created.contains(&consumer.id) {
+            db_client.create_consumer(consumer).await
+                .context(format!("Failed to create consumer {}", consumer.id))?;
+        } else if diff.consumers.updated.contains(&consumer.id) {
+            let mut consumer = consumer.clone();
+            consumer.updated_at = now;
+            consumer.version = current_version(&current.consumers, &consumer.id, |c| (&c.id, c.version));
+            db_client.update_consumer(&consumer).await
+                .context(format!("Failed to update consumer {}", consumer.id))?;
+        }
+    }
+
+    for certificate in &desired.certificates {
+        if diff.certificates.created.contains(&certificate.id) || diff.certificates.updated.contains(&certificate.id) {
+            let mut certificate = certificate.clone();
+            certificate.updated_at = now;
+            db_client.upsert_certificate(&certificate).await
+                .context(format!("Failed to write certificate {}", certificate.id))?;
+        }
+    }
+
+    // SNIs and proxies that are going away release their hostnames and paths first
+    for sni_id in &diff.snis.deleted {
+        db_client.delete_sni(sni_id).await
+            .context(format!("Failed to delete SNI {}", sni_id))?;
+    }
+    for proxy_id in &diff.proxies.deleted {
+        db_client.delete_proxy(proxy_id).await
+            .context(format!("Failed to delete proxy {}", proxy_id))?;
+    }
+
+    for proxy in &desired.proxies {
+        if diff.proxies.created.contains(&proxy.id) {
+            db_client.create_proxy(proxy).await
+                .context(format!("Failed to create proxy {}", proxy.id))?;
+        } else if diff.proxies.updated.contains(&proxy.id) {
+            let mut proxy = proxy.clone();
+            proxy.updated_at = now;
+            proxy.version = current_version(&current.proxies, &proxy.id, |p| (&p.id, p.version));
+            db_client.update_proxy(&proxy).await
+                .context(format!("Failed to update proxy {}", proxy.id))?;
+        }
+    }
+
+    for sni in &desired.snis {
+        if diff.snis.created.contains(&sni.id) || diff.snis.updated.contains(&sni.id) {
+            let mut sni = sni.clone();
+            sni.updated_at = now;
+            db_client.upsert_sni(&sni).await
+                .context(format!("Failed to write SNI {}", sni.id))?;
+        }
+    }
+
+    for plugin_config_id in &diff.plugin_configs.deleted {
+        db_client.delete_plugin_config(plugin_config_id).await
+            .context(format!("Failed to delete plugin config {}", plugin_config_id))?;
+    }
+    for consumer_id in &diff.consumers.deleted {
+        db_client.delete_consumer(consumer_id).await
+            .context(format!("Failed to delete consumer {}", consumer_id))?;
+    }
+    for certificate_id in &diff.certificates.deleted {
+        db_client.delete_certificate(certificate_id).await
+            .context(format!("Failed to delete certificate {}", certificate_id))?;
+    }
+
+    info!(
+        "Imported configuration: {} proxies, {} consumers, {} plugin configs, {} certificates, {} SNIs changed",
+        diff.proxies.created.len() + diff.proxies.updated.len() + diff.proxies.deleted.len(),
+        diff.consumers.created.len() + diff.consumers.updated.len() + diff.consumers.deleted.len(),
+        diff.plugin_configs.created.len() + diff.plugin_configs.updated.len() + diff.plugin_configs.deleted.len(),
+        diff.certificates.created.len() + diff.certificates.updated.len() + diff.certificates.deleted.len(),
+        diff.snis.created.len() + diff.snis.updated.len() + diff.snis.deleted.len(),
+    );
+
+    Ok(diff)
+}
+
+/// Version of the stored entity an import overwrites. Imports replace whatever
+/// is stored, so they are based on the version last loaded rather than the
+/// one in the document.
+fn current_version<T>(current: &[T], id: &str, key: impl Fn(&T) -> (&str, i64)) -> i64 {
+    current.iter()
+        .map(key)
+        .find(|(entity_id, _)| *entity_id == id)
+        .map_or(1, |(_, version)| version)
+}
+
+/// The current configuration with the proxies, consumers and plugin configs of
+/// a bulk import added, or replacing those with the same ID
+pub fn merge(current: &Configuration, bulk: &Configuration) -> Configuration {
+    fn upsert<T: Clone>(entities: &mut Vec<T>, additions: &[T], id: impl Fn(&T) -> &str) {
+        for addition in additions {
+            match entities.iter_mut().find(|entity| id(entity) == id(addition)) {
+                Some(entity) => *entity = addition.clone(),
+                None => entities.push(addition.clone()),
+            }
+        }
+    }
+
+    let mut merged = current.clone();
+    upsert(&mut merged.proxies, &bulk.proxies, |p| &p.id);
+    upsert(&mut merged.consumers, &bulk.consumers, |c| &c.id);
+    upsert(&mut merged.plugin_configs, &bulk.plugin_configs, |p| &p.id);
+    merged
+}
+
+/// Whether an entity carries every one of `tags`
+pub fn has_tags(entity_tags: &[String], tags: &[String]) -> bool {
+    tags.iter().all(|tag| entity_tags.contains(tag))
+}
+
+/// The proxies, consumers and plugin configs carrying every one of `tags`, for
+/// exporting part of the configuration. Certificates and SNIs have no tags and
+/// are left out.
+pub fn select_tags(config: &Configuration, tags: &[String]) -> Configuration {
+    Configuration {
+        proxies: config.proxies.iter().filter(|p| has_tags(&p.tags, tags)).cloned().collect(),
+        consumers: config.consumers.iter().filter(|c| has_tags(&c.tags, tags)).cloned().collect(),
+        plugin_configs: config.plugin_configs.iter().filter(|pc| has_tags(&pc.tags, tags)).cloned().collect(),
+        certificates: Vec::new(),
+        snis: Vec::new(),
+        last_updated_at: config.last_updated_at,
+    }
+}
+
+/// The configuration an import scoped to `tags` leads to: the document's
+/// proxies, consumers and plugin configs, given any of `tags` they lack,
+/// replace the current ones carrying `tags`. Everything else is kept,
+/// including certificates and SNIs, so the result is applied with `apply`
+/// without touching entities outside the scope.
+pub fn scope_to_tags(current: &Configuration, mut desired: Configuration, tags: &[String]) -> Configuration {
+    fn add_tags(entity_tags: &mut Vec<String>, tags: &[String]) {
+        for tag in tags {
+            if !entity_tags.contains(tag) {
+                entity_tags.push(tag.clone());
+            }
+        }
+    }
+
+    let mut scoped = current.clone();
+    scoped.proxies.retain(|p| !has_tags(&p.tags, tags));
+    scoped.consumers.retain(|c| !has_tags(&c.tags, tags));
+    scoped.plugin_configs.retain(|pc| !has_tags(&pc.tags, tags));
+
+    for mut proxy in desired.proxies.drain(..) {
+        add_tags(&mut proxy.tags, tags);
+        scoped.proxies.push(proxy);
+    }
+    for mut consumer in desired.consumers.drain(..) {
+        add_tags(&mut consumer.tags, tags);
+        scoped.consumers.push(consumer);
+    }
+    for mut plugin_config in desired.plugin_configs.drain(..) {
+        add_tags(&mut plugin_config.tags, tags);
+        scoped.plugin_configs.push(plugin_config);
+    }
+    scoped
+}
+
+/// Writes the entities that differ between `current` and `merged` (see `merge`)
+/// in one database transaction and returns what changed. Unlike `apply`,
+/// nothing is deleted and a failure leaves the database untouched.
+pub async fn apply_bulk(db_client: &DatabaseClient, current: &Configuration, merged: &Configuration) -> Result<ConfigDiff> {
+    let diff = ConfigDiff::compute(current, merged);
+    let now = chrono::Utc::now();
+
+    let mut changes = BulkChanges::default();
+    for plugin_config in &merged.plugin_configs {
+        let mut plugin_config = plugin_config.clone();
+        if diff.plugin_configs.created.contains(&plugin_config.id) {
+            plugin_config.created_at = now;
+            plugin_config.updated_at = now;
+            plugin_config.version = 1;
+            changes.created_plugin_configs.push(plugin_config);
+        } else if diff.plugin_configs.updated.contains(&plugin_config.id) {
+            plugin_config.updated_at = now;
+            plugin_config.version = current_version(&current.plugin_configs, &plugin_config.id, |pc| (&pc.id, pc.version));
+            changes.updated_plugin_configs.push(plugin_config);
+        }
+    }
+    for consumer in &merged.consumers {
+        let mut consumer = consumer.clone();
+        if diff.consumers.created.contains(&consumer.id) {
+            consumer.created_at = now;
+            consumer.updated_at = now;
+            consumer.version = 1;
+            changes.created_consumers.push(consumer);
+        } else if diff.consumers.updated.contains(&consumer.id) {
+            consumer.updated_at = now;
+            consumer.version = current_version(&current.consumers, &consumer.id, |c| (&c.id, c.version));
+            changes.updated_consumers.push(consumer);
+        }
+    }
+    for proxy in &merged.proxies {
+        let mut proxy = proxy.clone();
+        if diff.proxies.created.contains(&proxy.id) {
+            proxy.created_at = now;
+            proxy.updated_at = now;
+            proxy.version = 1;
+            changes.created_proxies.push(proxy);
+        } else if diff.proxies.updated.contains(&proxy.id) {
+            proxy.updated_at = now;
+            proxy.version = current_version(&current.proxies, &proxy.id, |p| (&p.id, p.version));
+            changes.updated_proxies.push(proxy);
+        }
+    }
+
+    if !changes.is_empty() {
+        db_client.apply_bulk(&changes).await?;
+    }
+    Ok(diff)
+}
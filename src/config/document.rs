@@ -0,0 +1,282 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context, anyhow, bail};
+use serde_json::{Map, Value};
+
+/// Key of a JSON object that is replaced by the referenced file, the JSON
+/// spelling of a YAML `!include` tag
+pub const INCLUDE_KEY: &str = "!include";
+
+/// Guards against runaway include chains
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Syntax of a configuration file, chosen by extension. YAML is the fallback
+/// since it also accepts JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().map(|ext| ext.to_string_lossy().to_lowercase()).as_deref() {
+            Some("json") => Format::Json,
+            Some("toml") => Format::Toml,
+            _ => Format::Yaml,
+        }
+    }
+
+    /// Whether the file is a configuration document rather than, say, a certificate
+    pub fn is_config_path(path: &Path) -> bool {
+        path.extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .map_or(false, |ext| matches!(ext.as_str(), "json" | "yaml" | "yml" | "toml"))
+    }
+}
+
+/// A configuration file with its includes and environment references resolved
+#[derive(Debug)]
+pub struct Document {
+    pub value: Value,
+    /// Canonical paths of every file pulled in through an include
+    pub included: HashSet<PathBuf>,
+}
+
+/// Reads a YAML, JSON or TOML configuration file and resolves it:
+///
+/// - `${VAR}` and `${VAR:-default}` in string values are replaced from the
+///   environment; `$${` produces a literal `${`. A string that is exactly one
+///   reference takes the type of its value, so `backend_port: ${PORT}` is a number.
+/// - `!include path` (YAML) or `{"!include": "path"}` (JSON, TOML) is replaced by
+///   the referenced file, relative to the including file. Configuration files are
+///   inserted as data, any other file as a string, which suits PEM certificates.
+pub fn load(path: &Path) -> Result<Document> {
+    let mut included = HashSet::new();
+    let value = load_file(path, &mut Vec::new(), &mut included)?;
+    Ok(Document { value, included })
+}
+
+/// Resolves a document that is not backed by a file; includes are relative to
+/// the working directory
+pub fn resolve_str(content: &str, format: Format) -> Result<Value> {
+    let value = parse(content, format)?;
+    resolve(value, Path::new("."), &mut Vec::new(), &mut HashSet::new())
+}
+
+/// Parses a document without resolving environment references or includes,
+/// for content that does not come from the local filesystem
+pub fn parse_str(content: &str, format: Format) -> Result<Value> {
+    parse(content, format)
+}
+
+fn load_file(path: &Path, stack: &mut Vec<PathBuf>, included: &mut HashSet<PathBuf>) -> Result<Value> {
+    let canonical = fs::canonicalize(path)
+        .context(format!("Failed to read configuration file: {}", path.display()))?;
+
+    if stack.contains(&canonical) {
+        bail!("Include cycle through {}", path.display());
+    }
+    if stack.len() >= MAX_INCLUDE_DEPTH {
+        bail!("Includes nested more than {} levels deep at {}", MAX_INCLUDE_DEPTH, path.display());
+    }
+
+    let content = fs::read_to_string(&canonical)
+        .context(format!("Failed to read configuration file: {}", path.display()))?;
+    let value = parse(&content, Format::from_path(path))
+        .context(format!("Failed to parse {}", path.display()))?;
+
+    let base_dir = canonical.parent().map(Path::to_path_buf).unwrap_or_default();
+    stack.push(canonical);
+    let resolved = resolve(value, &base_dir, stack, included);
+    stack.pop();
+    resolved
+}
+
+/// Parses a document. Syntax errors carry the line and column from the parser.
+fn parse(content: &str, format: Format) -> Result<Value> {
+    match format {
+        Format::Json => serde_json::from_str(content)
+            .map_err(|e| anyhow!("Invalid JSON: {}", e)),
+        Format::Yaml => {
+            let yaml: serde_yaml::Value = serde_yaml::from_str(content)
+                .map_err(|e| anyhow!("Invalid YAML: {}", e))?;
+            yaml_to_json(yaml)
+        },
+        Format::Toml => {
+            let table: toml::Table = toml::from_str(content)
+                .map_err(|e| anyhow!("Invalid TOML: {}", e))?;
+            Ok(toml_to_json(toml::Value::Table(table)))
+        },
+    }
+}
+
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Value::from(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        // Datetimes are kept in their RFC 3339 form, as in YAML and JSON
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table.into_iter().map(|(key, value)| (key, toml_to_json(value))).collect()
+        ),
+    }
+}
+
+/// Converts YAML to JSON, turning `!include` tags into include objects
+fn yaml_to_json(yaml: serde_yaml::Value) -> Result<Value> {
+    use serde_yaml::Value as Yaml;
+
+    Ok(match yaml {
+        Yaml::Null => Value::Null,
+        Yaml::Bool(b) => Value::Bool(b),
+        Yaml::Number(n) => serde_json::to_value(n)?,
+        Yaml::String(s) => Value::String(s),
+        Yaml::Sequence(items) => Value::Array(
+            items.into_iter().map(yaml_to_json).collect::<Result<_>>()?
+        ),
+        Yaml::Mapping(mapping) => {
+            let mut object = Map::new();
+            for (key, value) in mapping {
+                let key = match key {
+                    Yaml::String(s) => s,
+                    Yaml::Number(n) => n.to_string(),
+                    Yaml::Bool(b) => b.to_string(),
+                    other => bail!("Unsupported mapping key: {:?}", other),
+                };
+                object.insert(key, yaml_to_json(value)?);
+            }
+            Value::Object(object)
+        },
+        Yaml::Tagged(tagged) => {
+            if tagged.tag != "include" {
+                bail!("Unsupported tag {}", tagged.tag);
+            }
+            match tagged.value {
+                Yaml::String(path) => {
+                    let mut object = Map::new();
+                    object.insert(INCLUDE_KEY.to_string(), Value::String(path));
+                    Value::Object(object)
+                },
+                _ => bail!("!include expects a file path"),
+            }
+        },
+    })
+}
+
+fn resolve(
+    value: Value,
+    base_dir: &Path,
+    stack: &mut Vec<PathBuf>,
+    included: &mut HashSet<PathBuf>,
+) -> Result<Value> {
+    match value {
+        Value::String(s) => interpolate(&s),
+        Value::Array(items) => Ok(Value::Array(
+            items.into_iter()
+                .map(|item| resolve(item, base_dir, stack, included))
+                .collect::<Result<_>>()?
+        )),
+        Value::Object(object) => {
+            if let Some(target) = include_target(&object) {
+                let target = match interpolate(target)? {
+                    Value::String(target) => target,
+                    other => other.to_string(),
+                };
+                return include(&base_dir.join(target), stack, included);
+            }
+
+            let mut resolved = Map::new();
+            for (key, value) in object {
+                resolved.insert(key, resolve(value, base_dir, stack, included)?);
+            }
+            Ok(Value::Object(resolved))
+        },
+        other => Ok(other),
+    }
+}
+
+fn include_target(object: &Map<String, Value>) -> Option<&str> {
+    match (object.len(), object.get(INCLUDE_KEY)) {
+        (1, Some(Value::String(path))) => Some(path),
+        _ => None,
+    }
+}
+
+fn include(path: &Path, stack: &mut Vec<PathBuf>, included: &mut HashSet<PathBuf>) -> Result<Value> {
+    let canonical = fs::canonicalize(path)
+        .context(format!("Failed to include {}", path.display()))?;
+    included.insert(canonical.clone());
+
+    if Format::is_config_path(path) {
+        load_file(&canonical, stack, included)
+    } else {
+        fs::read_to_string(&canonical)
+            .map(Value::String)
+            .context(format!("Failed to include {}", path.display()))
+    }
+}
+
+/// Substitutes environment references in a string value
+fn interpolate(s: &str) -> Result<Value> {
+    if !s.contains('$') {
+        return Ok(Value::String(s.to_string()));
+    }
+
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        let after = &rest[pos..];
+
+        if let Some(escaped) = after.strip_prefix("$${") {
+            result.push_str("${");
+            rest = escaped;
+        } else if let Some(reference) = after.strip_prefix("${") {
+            let end = reference.find('}')
+                .ok_or_else(|| anyhow!("Unterminated environment reference in \"{}\"", s))?;
+            result.push_str(&lookup(&reference[..end])?);
+            rest = &reference[end + 1..];
+        } else {
+            result.push('$');
+            rest = &after[1..];
+        }
+    }
+    result.push_str(rest);
+
+    // A lone reference is typed like a YAML scalar, so numbers and booleans can come from the environment
+    let is_lone_reference = s.starts_with("${") && s.find('}') == Some(s.len() - 1);
+    if is_lone_reference {
+        if let Ok(typed @ (serde_yaml::Value::Number(_) | serde_yaml::Value::Bool(_))) =
+            serde_yaml::from_str::<serde_yaml::Value>(&result)
+        {
+            return yaml_to_json(typed);
+        }
+    }
+
+    Ok(Value::String(result))
+}
+
+fn lookup(reference: &str) -> Result<String> {
+    let (name, default) = match reference.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (reference, None),
+    };
+
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        bail!("Invalid environment variable name \"{}\"", name);
+    }
+
+    match (env::var(name), default) {
+        (Ok(value), _) => Ok(value),
+        (Err(_), Some(default)) => Ok(default.to_string()),
+        (Err(_), None) => bail!("Environment variable {} is not set", name),
+    }
+}
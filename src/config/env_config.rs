@@ -1,13 +1,28 @@
 use std::env;
 use std::collections::HashMap;
 use std::time::Duration;
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use serde_json;
 use thiserror::Error;
 
-use super::data_model::DatabaseType;
+use super::data_model::{AcmeChallengeType, ClientAuthMode, DatabaseType, DbTlsMode, DnsResolver, ErrorTemplate, LogSampling, OcspMode, TlsPolicy, TlsVersion};
+use super::credentials::PasswordHashAlgorithm;
+use super::settings::Settings;
+use crate::dns::hosts;
+use crate::dns::upstream::parse_resolver;
+use crate::grpc::transfer::{Compression, CHUNK_OVERHEAD};
+use crate::metrics::statsd::StatsdFormat;
+use crate::proxy::bind;
+use crate::proxy::egress::EgressProxy;
+use crate::proxy::long_lived::UpdatePolicy;
 use crate::modes::OperationMode;
 
+/// Listeners bind to all IPv4 interfaces unless configured otherwise
+const UNSPECIFIED_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+
+/// Let's Encrypt production directory
+pub const DEFAULT_ACME_DIRECTORY_URL: &str = "https://acme-v02.api.letsencrypt.org/directory";
+
 #[derive(Error, Debug)]
 pub enum EnvConfigError {
     #[error("Missing required environment variable: {0}")]
@@ -18,6 +33,12 @@ pub enum EnvConfigError {
     
     #[error("Failed to parse DNS overrides: {0}")]
     DnsOverridesParseError(String),
+    
+    #[error("Failed to parse error templates: {0}")]
+    ErrorTemplatesParseError(String),
+    
+    #[error("Invalid bootstrap file {0}: {1}")]
+    BootstrapFileError(String, String),
 }
 
 #[derive(Debug, Clone)]
@@ -39,8 +60,38 @@ pub struct EnvConfig {
     pub admin_tls_cert_path: Option<String>,
     pub admin_tls_key_path: Option<String>,
     
+    /// Addresses each listener binds to, on its port
+    pub proxy_http_bind_addresses: Vec<IpAddr>,
+    pub proxy_https_bind_addresses: Vec<IpAddr>,
+    pub proxy_http3_bind_addresses: Vec<IpAddr>,
+    pub admin_http_bind_addresses: Vec<IpAddr>,
+    pub admin_https_bind_addresses: Vec<IpAddr>,
+    
+    /// Advertise the HTTP/3 listener through Alt-Svc on HTTPS responses
+    pub http3_alt_svc: bool,
+    /// How long clients may remember the advertisement, in seconds
+    pub http3_alt_svc_max_age: u64,
+    /// Accept requests sent as 0-RTT early data on the HTTP/3 listener
+    pub quic_0rtt: bool,
+    /// How long HTTP/3 connections may stay idle (0 = no timeout)
+    pub quic_idle_timeout_ms: u64,
+    /// Concurrent requests per HTTP/3 connection
+    pub quic_max_bidi_streams: u32,
+    /// Unidirectional streams per HTTP/3 connection
+    pub quic_max_uni_streams: u32,
+    
     // Security settings
     pub admin_jwt_secret: Option<String>,
+    /// Lifetime of the access tokens issued by POST /auth/login
+    pub admin_token_ttl: Duration,
+    /// Lifetime of the refresh tokens issued with them
+    pub admin_refresh_token_ttl: Duration,
+    /// Browser origins allowed to call the Admin API, `*` for any; empty disables CORS
+    pub admin_cors_allowed_origins: Vec<String>,
+    /// How long browsers may cache an Admin API preflight response
+    pub admin_cors_max_age: Duration,
+    /// Directory of a management UI served under /ui/
+    pub admin_ui_path: Option<String>,
     pub cp_grpc_jwt_secret: Option<String>,
     pub dp_grpc_auth_token: Option<String>,
     
@@ -50,30 +101,425 @@ pub struct EnvConfig {
     pub db_poll_interval: Duration,
     pub db_incremental_polling: bool,
     pub db_poll_check_interval: Duration,
+    /// Apply pending schema migrations at startup instead of refusing to start
+    pub db_auto_migrate: bool,
+    /// Connections the pool keeps open even when idle
+    pub db_min_connections: u32,
+    /// Largest pool size; None uses 10 for Postgres and MySQL and 5 for SQLite
+    pub db_max_connections: Option<u32>,
+    /// How long a query waits for a free pooled connection
+    pub db_acquire_timeout: Duration,
+    /// Server-side limit on a single statement; None leaves the server default
+    pub db_statement_timeout: Option<Duration>,
+    /// TLS mode for Postgres and MySQL; None keeps what the connection URL says
+    pub db_tls_mode: Option<DbTlsMode>,
+    /// CA certificate (PEM) the database server certificate is verified against
+    pub db_tls_ca_cert_path: Option<String>,
+    /// Read replicas that configuration loads go to; the primary takes writes
+    /// and serves reads when no replica answers
+    pub db_read_replica_urls: Vec<String>,
+    /// Attempts per configuration read, including the first
+    pub db_retry_attempts: u32,
+    /// First retry delay, doubled on each retry up to `db_retry_max_delay`
+    pub db_retry_base_delay: Duration,
+    pub db_retry_max_delay: Duration,
+    /// Failed configuration reads in a row before the database counts as down
+    pub db_circuit_breaker_threshold: u32,
+    /// How long reads fail fast once the circuit opens
+    pub db_circuit_breaker_cooldown: Duration,
+    /// How long tombstones of deleted entities are kept for delta loads (zero keeps them forever)
+    pub db_tombstone_retention: Duration,
+    /// Algorithm consumer passwords and client secrets are hashed with before they are stored
+    pub credential_hash_algorithm: PasswordHashAlgorithm,
+    /// Number of configuration versions kept for rollback (0 keeps all)
+    pub config_version_retention: u64,
     
     // File mode settings
     pub file_config_path: Option<String>,
+    /// Reload the configuration when the file changes, in addition to SIGHUP
+    pub file_watch: bool,
+    /// Quiet period after a change before the file is reloaded
+    pub file_watch_debounce_ms: u64,
+    
+    // Docker mode settings
+    /// Docker Engine API address, `unix:///path/to/docker.sock` or `tcp://host:port`
+    pub docker_host: String,
+    /// Prefix of the container labels proxies are read from
+    pub docker_label_prefix: String,
+    /// Network whose container address backends are reached at; the first one otherwise
+    pub docker_network: Option<String>,
     
     // CP/DP communication
     pub cp_grpc_listen_addr: Option<SocketAddr>,
     pub dp_cp_grpc_url: Option<String>,
+    /// Certificate and key the CP gRPC listener serves TLS with; plaintext without them
+    pub cp_grpc_tls_cert_path: Option<String>,
+    pub cp_grpc_tls_key_path: Option<String>,
+    /// CA bundle Data Plane client certificates must be issued by (mutual TLS)
+    pub cp_grpc_tls_client_ca_path: Option<String>,
+    /// Pre-shared token of each Data Plane node (node ID -> token)
+    pub cp_grpc_node_tokens: HashMap<String, String>,
+    /// Node IDs allowed to connect in addition to those with a token; empty allows any node
+    pub cp_grpc_allowed_nodes: Vec<String>,
+    /// Group whose nodes receive each configuration version first
+    pub cp_rollout_canary_group: Option<String>,
+    /// Percentages of nodes holding a new version at each rollout stage, ending
+    /// at 100; empty sends every version to all nodes at once
+    pub cp_rollout_stages: Vec<u32>,
+    /// How long each rollout stage runs before the next one starts
+    pub cp_rollout_stage_interval: Duration,
+    /// Configuration history versions Data Plane groups are pinned to at startup
+    pub cp_group_pins: HashMap<String, i64>,
+    /// Also serve the configuration to Envoy nodes over xDS
+    pub cp_xds_enabled: bool,
+    /// Address of the listener Envoy nodes are given over xDS
+    pub cp_xds_listener_addr: SocketAddr,
+    /// ID this Data Plane presents to the Control Plane; random when unset
+    pub dp_node_id: Option<String>,
+    /// Group this Data Plane belongs to for staged rollouts
+    pub dp_node_group: String,
+    /// How often the Data Plane reports its health to the Control Plane
+    pub dp_health_report_interval: Duration,
+    /// Share of 5xx responses over a report interval above which the node reports itself degraded
+    pub dp_degraded_error_rate: f64,
+    /// How often `cluster` rate limiting counters are exchanged with the Control Plane, 0 disables
+    pub dp_rate_limit_sync_interval_ms: u64,
+    /// CA bundle the Control Plane certificate is verified against
+    pub dp_grpc_tls_ca_path: Option<String>,
+    /// Client certificate and key presented to the Control Plane
+    pub dp_grpc_tls_cert_path: Option<String>,
+    pub dp_grpc_tls_key_path: Option<String>,
+    /// File the last configuration received is cached in, to boot without the Control Plane
+    pub dp_config_cache_path: Option<String>,
+    /// Longest wait between attempts to reconnect to the Control Plane
+    pub dp_reconnect_max_delay: Duration,
+    /// Compression of configuration sent from the Control Plane to Data Planes
+    pub grpc_compression: Compression,
+    /// Largest gRPC message the Control Plane and Data Planes send or accept
+    pub grpc_max_message_size: usize,
+    /// Largest piece of a snapshot sent in one chunk
+    pub cp_grpc_snapshot_chunk_size: usize,
+    /// How often an idle CP/DP connection is pinged, None disables keepalive
+    pub grpc_keepalive_interval: Option<Duration>,
+    /// How long a keepalive ping may go unanswered before the connection is closed
+    pub grpc_keepalive_timeout: Duration,
+    /// Configuration updates queued for a Data Plane before it counts as lagging
+    pub cp_grpc_subscriber_buffer: usize,
     
     // Request handling limits
     pub max_header_size_bytes: usize,
     pub max_body_size_bytes: usize,
+    /// Request headers allowed, 0 for no limit (431 beyond)
+    pub max_header_count: usize,
+    /// Bytes allowed in one request header, name and value, 0 for no limit (431 beyond)
+    pub max_single_header_bytes: usize,
+    /// Bytes allowed in the path and query of a request, 0 for no limit (414 beyond)
+    pub max_uri_length: usize,
+    
+    // Request timeouts (milliseconds, 0 disables)
+    pub request_timeout_ms: u64,
+    pub client_header_timeout_ms: u64,
+    pub client_body_timeout_ms: u64,
+    
+    // Connection-level protection (0 disables)
+    pub max_connections: usize,
+    pub max_connections_per_ip: usize,
+    pub min_transfer_rate_bytes_per_sec: u64,
+    
+    // Global templates for gateway-generated error responses
+    pub error_templates: HashMap<String, ErrorTemplate>,
+    
+    // Protocol versions, cipher suites and ALPN of the TLS listeners
+    pub proxy_tls_policy: TlsPolicy,
+    pub admin_tls_policy: TlsPolicy,
+    
+    // Client certificate verification on the TLS listeners
+    pub proxy_tls_client_auth: ClientAuthMode,
+    pub proxy_tls_client_ca_path: Option<String>,
+    pub admin_tls_client_auth: ClientAuthMode,
+    pub admin_tls_client_ca_path: Option<String>,
+    pub tls_client_crl_paths: Vec<String>,
+    pub tls_client_ocsp: OcspMode,
+    
+    // ACME certificate management
+    pub acme_enabled: bool,
+    pub acme_directory_url: String,
+    pub acme_email: Option<String>,
+    pub acme_domains: Vec<String>,
+    pub acme_challenge: AcmeChallengeType,
+    pub acme_dns_hook: Option<String>,
+    pub acme_dns_propagation_secs: u64,
+    pub acme_renew_before_days: u64,
+    pub acme_storage_path: String,
     
     // DNS caching
+    /// Longest addresses are cached, even when their records have a longer TTL
     pub dns_cache_ttl_seconds: u64,
-    pub dns_overrides: HashMap<String, String>,
+    /// Shortest addresses are cached, even when their records have a shorter TTL
+    pub dns_min_ttl: Duration,
+    /// Addresses to answer for hostnames instead of resolving them, from
+    /// FERRUM_DNS_HOSTS_FILE and FERRUM_DNS_OVERRIDES
+    pub dns_overrides: HashMap<String, Vec<String>>,
+    /// How long a failed DNS lookup is cached, doubled for consecutive failures
+    pub dns_negative_ttl: Duration,
+    /// How long expired DNS records are still served while looking them up
+    /// again fails; zero disables
+    pub dns_stale_ttl: Duration,
+    /// DNS servers to resolve backend hostnames with, in fallback order; empty
+    /// for the system resolver
+    pub dns_resolvers: Vec<DnsResolver>,
+    
+    // Service discovery
+    pub consul_addr: String,
+    pub consul_token: Option<String>,
+    pub etcd_endpoint: String,
+    
+    // Per-consumer metrics
+    /// Record metrics labeled by proxy name and consumer
+    pub metrics_consumer_labels: bool,
+    /// Proxy names or IDs to record them for; empty for every proxy
+    pub metrics_proxy_allowlist: Vec<String>,
+    /// Consumer usernames or IDs to record them for; empty for every consumer
+    pub metrics_consumer_allowlist: Vec<String>,
+    /// Most proxy and consumer label sets before requests are counted as "__other__"
+    pub metrics_max_label_sets: usize,
+    
+    // OTLP metrics export
+    /// OTLP/HTTP metrics endpoint of an OpenTelemetry collector; unset exports nothing
+    pub otlp_metrics_endpoint: Option<String>,
+    /// How often the metrics are exported
+    pub otlp_metrics_interval: Duration,
+    /// Most metrics sent in one export request
+    pub otlp_metrics_batch_size: usize,
+    /// Headers sent with every export request, such as credentials
+    pub otlp_metrics_headers: Vec<(String, String)>,
+    
+    // Metrics endpoint
+    /// Port of a listener serving only the metrics, without authentication
+    pub metrics_port: Option<u16>,
+    /// Path the metrics are served at without authentication
+    pub metrics_path: String,
+    /// Serve the metrics path on the proxy listeners
+    pub metrics_on_proxy: bool,
+    /// Serve the metrics path on the admin listeners without a token
+    pub metrics_on_admin: bool,
+    
+    // StatsD metrics
+    /// host:port of a StatsD server or Datadog agent; unset sends nothing
+    pub statsd_addr: Option<String>,
+    /// Prefix of the metric names
+    pub statsd_prefix: String,
+    pub statsd_format: StatsdFormat,
+    /// DogStatsD tags added to every metric
+    pub statsd_tags: Vec<String>,
+    
+    // Access log sampling
+    /// Which requests reach the log plugins, for proxies without log_sampling
+    pub log_sampling: LogSampling,
+    
+    /// Requests in flight from which low priority requests are shed (0 = disabled)
+    pub load_shed_max_in_flight: usize,
+    /// Requests in flight from which normal priority requests are shed too (0 = disabled)
+    pub load_shed_critical_in_flight: usize,
+    /// p99 latency of the last minute in milliseconds from which low priority
+    /// requests are shed (0 = disabled)
+    pub load_shed_p99_ms: u64,
+    /// Header setting the priority of a request, ahead of its proxy's tags
+    pub load_shed_priority_header: Option<String>,
+    
+    /// What happens to the WebSocket connections and event streams of a proxy
+    /// when an update changes or deletes it
+    pub long_lived_update_policy: UpdatePolicy,
+    /// How long drained long-lived connections stay open after the update
+    pub long_lived_drain_timeout_ms: u64,
+    
+    /// HTTP CONNECT or SOCKS5 proxy that backend connections go through, for
+    /// proxies without egress_proxy
+    pub egress_proxy_url: Option<String>,
+    /// Hosts connected to directly rather than through the egress proxy,
+    /// including their subdomains
+    pub egress_no_proxy: Vec<String>,
+    
+    /// Header telling non-gRPC backends the milliseconds left until the
+    /// request's deadline, e.g. X-Request-Timeout; None sends no header
+    pub deadline_header: Option<String>,
     
     // Pagination settings
     pub default_pagination_limit: usize,
 }
 
+impl Default for EnvConfig {
+    /// The settings used when no environment variable is set, with the proxy
+    /// listening on HTTP only. Used as the base of `from_env` and by embedders.
+    fn default() -> Self {
+        Self {
+            mode: OperationMode::File,
+            log_level: "info".to_string(),
+            proxy_http_port: Some(8000),
+            proxy_https_port: None,
+            proxy_http3_port: None,
+            proxy_tls_cert_path: None,
+            proxy_tls_key_path: None,
+            admin_http_port: None,
+            admin_https_port: None,
+            admin_http3_port: None,
+            admin_tls_cert_path: None,
+            admin_tls_key_path: None,
+            proxy_http_bind_addresses: vec![UNSPECIFIED_ADDRESS],
+            proxy_https_bind_addresses: vec![UNSPECIFIED_ADDRESS],
+            proxy_http3_bind_addresses: vec![UNSPECIFIED_ADDRESS],
+            admin_http_bind_addresses: vec![UNSPECIFIED_ADDRESS],
+            admin_https_bind_addresses: vec![UNSPECIFIED_ADDRESS],
+            http3_alt_svc: true,
+            http3_alt_svc_max_age: 86400,
+            quic_0rtt: false,
+            quic_idle_timeout_ms: 30000,
+            quic_max_bidi_streams: 100,
+            quic_max_uni_streams: 100,
+            admin_jwt_secret: None,
+            admin_token_ttl: Duration::from_secs(900),
+            admin_refresh_token_ttl: Duration::from_secs(86400),
+            admin_cors_allowed_origins: Vec::new(),
+            admin_cors_max_age: Duration::from_secs(600),
+            admin_ui_path: None,
+            cp_grpc_jwt_secret: None,
+            dp_grpc_auth_token: None,
+            db_type: None,
+            db_url: None,
+            db_poll_interval: Duration::from_secs(30),
+            db_incremental_polling: true,
+            db_poll_check_interval: Duration::from_secs(5),
+            db_auto_migrate: false,
+            db_min_connections: 0,
+            db_max_connections: None,
+            db_acquire_timeout: Duration::from_secs(30),
+            db_statement_timeout: None,
+            db_tls_mode: None,
+            db_tls_ca_cert_path: None,
+            db_read_replica_urls: Vec::new(),
+            db_retry_attempts: 3,
+            db_retry_base_delay: Duration::from_millis(200),
+            db_retry_max_delay: Duration::from_secs(5),
+            db_circuit_breaker_threshold: 3,
+            db_circuit_breaker_cooldown: Duration::from_secs(30),
+            db_tombstone_retention: Duration::from_secs(7 * 24 * 60 * 60),
+            credential_hash_algorithm: PasswordHashAlgorithm::Bcrypt,
+            config_version_retention: 100,
+            file_config_path: None,
+            file_watch: true,
+            file_watch_debounce_ms: 500,
+            docker_host: "unix:///var/run/docker.sock".to_string(),
+            docker_label_prefix: "ferrum".to_string(),
+            docker_network: None,
+            cp_grpc_listen_addr: None,
+            dp_cp_grpc_url: None,
+            cp_grpc_tls_cert_path: None,
+            cp_grpc_tls_key_path: None,
+            cp_grpc_tls_client_ca_path: None,
+            cp_grpc_node_tokens: HashMap::new(),
+            cp_grpc_allowed_nodes: Vec::new(),
+            cp_rollout_canary_group: None,
+            cp_rollout_stages: Vec::new(),
+            cp_rollout_stage_interval: Duration::from_secs(60),
+            cp_group_pins: HashMap::new(),
+            cp_xds_enabled: false,
+            cp_xds_listener_addr: SocketAddr::from(([0, 0, 0, 0], 10000)),
+            dp_node_id: None,
+            dp_node_group: "default".to_string(),
+            dp_health_report_interval: Duration::from_secs(10),
+            dp_degraded_error_rate: 0.05,
+            dp_rate_limit_sync_interval_ms: 0,
+            dp_grpc_tls_ca_path: None,
+            dp_grpc_tls_cert_path: None,
+            dp_grpc_tls_key_path: None,
+            dp_config_cache_path: None,
+            dp_reconnect_max_delay: Duration::from_secs(30),
+            grpc_compression: Compression::Gzip,
+            grpc_max_message_size: 16777216,
+            cp_grpc_snapshot_chunk_size: 1048576,
+            grpc_keepalive_interval: Some(Duration::from_secs(30)),
+            grpc_keepalive_timeout: Duration::from_secs(10),
+            cp_grpc_subscriber_buffer: 10,
+            max_header_size_bytes: 16384,
+            max_body_size_bytes: 10485760,
+            max_header_count: 100,
+            max_single_header_bytes: 8192,
+            max_uri_length: 8192,
+            request_timeout_ms: 60000,
+            client_header_timeout_ms: 30000,
+            client_body_timeout_ms: 60000,
+            max_connections: 0,
+            max_connections_per_ip: 0,
+            min_transfer_rate_bytes_per_sec: 0,
+            error_templates: HashMap::new(),
+            proxy_tls_policy: TlsPolicy::default(),
+            admin_tls_policy: TlsPolicy::default(),
+            proxy_tls_client_auth: ClientAuthMode::None,
+            proxy_tls_client_ca_path: None,
+            admin_tls_client_auth: ClientAuthMode::None,
+            admin_tls_client_ca_path: None,
+            tls_client_crl_paths: Vec::new(),
+            tls_client_ocsp: OcspMode::Off,
+            acme_enabled: false,
+            acme_directory_url: DEFAULT_ACME_DIRECTORY_URL.to_string(),
+            acme_email: None,
+            acme_domains: Vec::new(),
+            acme_challenge: AcmeChallengeType::Http01,
+            acme_dns_hook: None,
+            acme_dns_propagation_secs: 60,
+            acme_renew_before_days: 30,
+            acme_storage_path: "./acme".to_string(),
+            dns_cache_ttl_seconds: 300,
+            dns_min_ttl: Duration::from_secs(5),
+            dns_overrides: HashMap::new(),
+            dns_negative_ttl: Duration::from_secs(5),
+            dns_stale_ttl: Duration::ZERO,
+            dns_resolvers: Vec::new(),
+            consul_addr: "http://127.0.0.1:8500".to_string(),
+            consul_token: None,
+            etcd_endpoint: "http://127.0.0.1:2379".to_string(),
+            metrics_consumer_labels: false,
+            metrics_proxy_allowlist: Vec::new(),
+            metrics_consumer_allowlist: Vec::new(),
+            metrics_max_label_sets: 1000,
+            otlp_metrics_endpoint: None,
+            otlp_metrics_interval: Duration::from_secs(60),
+            otlp_metrics_batch_size: 1000,
+            otlp_metrics_headers: Vec::new(),
+            metrics_port: None,
+            metrics_path: "/metrics".to_string(),
+            metrics_on_proxy: false,
+            metrics_on_admin: false,
+            statsd_addr: None,
+            statsd_prefix: "ferrumgw".to_string(),
+            statsd_format: StatsdFormat::DogStatsd,
+            statsd_tags: Vec::new(),
+            log_sampling: LogSampling::default(),
+            load_shed_max_in_flight: 0,
+            load_shed_critical_in_flight: 0,
+            load_shed_p99_ms: 0,
+            load_shed_priority_header: None,
+            long_lived_update_policy: UpdatePolicy::Drain,
+            long_lived_drain_timeout_ms: 30000,
+            egress_proxy_url: None,
+            egress_no_proxy: Vec::new(),
+            deadline_header: None,
+            default_pagination_limit: 500,
+        }
+    }
+}
+
 impl EnvConfig {
+    /// Reads the settings from the environment and the bootstrap file named by
+    /// FERRUM_BOOTSTRAP_FILE, if any
     pub fn from_env() -> Result<Self, EnvConfigError> {
+        Self::from_settings(&Settings::from_env()?)
+    }
+    
+    /// Builds the configuration from layered settings, see `Settings`
+    pub fn from_settings(settings: &Settings) -> Result<Self, EnvConfigError> {
         // Parse mode (required)
-        let mode_str = env::var("FERRUM_MODE")
+        let mode_str = settings.var("FERRUM_MODE")
             .map_err(|_| EnvConfigError::MissingEnv("FERRUM_MODE".to_string()))?;
         
         let mode = match mode_str.as_str() {
@@ -81,33 +527,45 @@ impl EnvConfig {
             "file" => OperationMode::File,
             "cp" => OperationMode::ControlPlane,
             "dp" => OperationMode::DataPlane,
+            "docker" => OperationMode::Docker,
+            "hybrid" => OperationMode::Hybrid,
             _ => return Err(EnvConfigError::InvalidEnvValue(
                 "FERRUM_MODE".to_string(), 
-                format!("Expected one of: database, file, cp, dp. Got: {}", mode_str)
+                format!("Expected one of: database, file, cp, dp, docker, hybrid. Got: {}", mode_str)
             )),
         };
         
         // Parse log level (with default)
-        let log_level = env::var("FERRUM_LOG_LEVEL").unwrap_or_else(|_| "info".to_string());
+        let log_level = settings.var("FERRUM_LOG_LEVEL").unwrap_or_else(|_| "info".to_string());
         
         // Network ports (with defaults)
-        let proxy_http_port = Self::parse_optional_port("FERRUM_PROXY_HTTP_PORT", Some(8000))?;
-        let proxy_https_port = Self::parse_optional_port("FERRUM_PROXY_HTTPS_PORT", Some(8443))?;
-        let proxy_http3_port = Self::parse_optional_port("FERRUM_PROXY_HTTP3_PORT", Some(8444))?;
-        let admin_http_port = Self::parse_optional_port("FERRUM_ADMIN_HTTP_PORT", Some(9000))?;
-        let admin_https_port = Self::parse_optional_port("FERRUM_ADMIN_HTTPS_PORT", Some(9443))?;
-        let admin_http3_port = Self::parse_optional_port("FERRUM_ADMIN_HTTP3_PORT", Some(9444))?;
+        let proxy_http_port = Self::parse_optional_port(settings, "FERRUM_PROXY_HTTP_PORT", Some(8000))?;
+        let proxy_https_port = Self::parse_optional_port(settings, "FERRUM_PROXY_HTTPS_PORT", Some(8443))?;
+        let proxy_http3_port = Self::parse_optional_port(settings, "FERRUM_PROXY_HTTP3_PORT", Some(8444))?;
+        let admin_http_port = Self::parse_optional_port(settings, "FERRUM_ADMIN_HTTP_PORT", Some(9000))?;
+        let admin_https_port = Self::parse_optional_port(settings, "FERRUM_ADMIN_HTTPS_PORT", Some(9443))?;
+        let admin_http3_port = Self::parse_optional_port(settings, "FERRUM_ADMIN_HTTP3_PORT", Some(9444))?;
+        
+        // Bind addresses, per listener or shared by the proxy or admin listeners
+        let proxy_bind_addresses = Self::parse_bind_addresses(settings, "FERRUM_PROXY_BIND_ADDRESSES", &[UNSPECIFIED_ADDRESS])?;
+        let proxy_http_bind_addresses = Self::parse_bind_addresses(settings, "FERRUM_PROXY_HTTP_BIND_ADDRESSES", &proxy_bind_addresses)?;
+        let proxy_https_bind_addresses = Self::parse_bind_addresses(settings, "FERRUM_PROXY_HTTPS_BIND_ADDRESSES", &proxy_bind_addresses)?;
+        let proxy_http3_bind_addresses = Self::parse_bind_addresses(settings, "FERRUM_PROXY_HTTP3_BIND_ADDRESSES", &proxy_bind_addresses)?;
+        let admin_bind_addresses = Self::parse_bind_addresses(settings, "FERRUM_ADMIN_BIND_ADDRESSES", &[UNSPECIFIED_ADDRESS])?;
+        let admin_http_bind_addresses = Self::parse_bind_addresses(settings, "FERRUM_ADMIN_HTTP_BIND_ADDRESSES", &admin_bind_addresses)?;
+        let admin_https_bind_addresses = Self::parse_bind_addresses(settings, "FERRUM_ADMIN_HTTPS_BIND_ADDRESSES", &admin_bind_addresses)?;
         
         // TLS paths
-        let proxy_tls_cert_path = env::var("FERRUM_PROXY_TLS_CERT_PATH").ok();
-        let proxy_tls_key_path = env::var("FERRUM_PROXY_TLS_KEY_PATH").ok();
-        let admin_tls_cert_path = env::var("FERRUM_ADMIN_TLS_CERT_PATH").ok();
-        let admin_tls_key_path = env::var("FERRUM_ADMIN_TLS_KEY_PATH").ok();
+        let proxy_tls_cert_path = settings.var("FERRUM_PROXY_TLS_CERT_PATH").ok();
+        let proxy_tls_key_path = settings.var("FERRUM_PROXY_TLS_KEY_PATH").ok();
+        let admin_tls_cert_path = settings.var("FERRUM_ADMIN_TLS_CERT_PATH").ok();
+        let admin_tls_key_path = settings.var("FERRUM_ADMIN_TLS_KEY_PATH").ok();
         
         // JWT secrets
-        let admin_jwt_secret = env::var("FERRUM_ADMIN_JWT_SECRET").ok();
-        let cp_grpc_jwt_secret = env::var("FERRUM_CP_GRPC_JWT_SECRET").ok();
-        let dp_grpc_auth_token = env::var("FERRUM_DP_GRPC_AUTH_TOKEN").ok();
+        let admin_jwt_secret = settings.var("FERRUM_ADMIN_JWT_SECRET").ok();
+        let cp_grpc_jwt_secret = settings.var("FERRUM_CP_GRPC_JWT_SECRET").ok();
+        let dp_grpc_auth_token = settings.var("FERRUM_DP_GRPC_AUTH_TOKEN").ok();
+        let cp_grpc_node_tokens = Self::parse_node_tokens(settings)?;
         
         // Validate mode-specific configurations
         let mut config = EnvConfig {
@@ -123,22 +581,16 @@ impl EnvConfig {
             admin_http3_port,
             admin_tls_cert_path,
             admin_tls_key_path,
+            proxy_http_bind_addresses,
+            proxy_https_bind_addresses,
+            proxy_http3_bind_addresses,
+            admin_http_bind_addresses,
+            admin_https_bind_addresses,
             admin_jwt_secret,
             cp_grpc_jwt_secret,
             dp_grpc_auth_token,
-            db_type: None,
-            db_url: None,
-            db_poll_interval: Duration::from_secs(30),
-            db_incremental_polling: true,
-            db_poll_check_interval: Duration::from_secs(5),
-            file_config_path: None,
-            cp_grpc_listen_addr: None,
-            dp_cp_grpc_url: None,
-            max_header_size_bytes: 16384,
-            max_body_size_bytes: 10485760,
-            dns_cache_ttl_seconds: 300,
-            dns_overrides: HashMap::new(),
-            default_pagination_limit: 500,
+            cp_grpc_node_tokens,
+            ..Self::default()
         };
         
         match config.mode {
@@ -161,8 +613,8 @@ impl EnvConfig {
                     return Err(anyhow!("FERRUM_FILE_CONFIG_PATH is required for file mode"));
                 }
             }
-            OperationMode::ControlPlane => {
-                // For CP mode, we need database connection info and gRPC config
+            OperationMode::ControlPlane | OperationMode::Hybrid => {
+                // For CP and hybrid mode, we need database connection info and gRPC config
                 if config.db_type.is_none() {
                     return Err(anyhow!("FERRUM_DB_TYPE is required for control plane mode"));
                 }
@@ -172,8 +624,8 @@ impl EnvConfig {
                 if config.cp_grpc_listen_addr.is_none() {
                     return Err(anyhow!("FERRUM_CP_GRPC_LISTEN_ADDR is required for control plane mode"));
                 }
-                if config.cp_grpc_jwt_secret.is_none() {
-                    return Err(anyhow!("FERRUM_CP_GRPC_JWT_SECRET is required for control plane mode"));
+                if config.cp_grpc_jwt_secret.is_none() && config.cp_grpc_node_tokens.is_empty() {
+                    return Err(anyhow!("FERRUM_CP_GRPC_JWT_SECRET or FERRUM_CP_GRPC_NODE_TOKENS is required for control plane mode"));
                 }
                 // Admin JWT secret is required for admin API
                 if config.admin_jwt_secret.is_none() {
@@ -189,35 +641,43 @@ impl EnvConfig {
                     return Err(anyhow!("FERRUM_DP_GRPC_AUTH_TOKEN is required for data plane mode"));
                 }
             }
+            OperationMode::Docker => {
+                // Docker mode only needs the daemon, which has a default address
+            }
         }
         
         // Database settings
-        let db_poll_interval = Self::parse_duration_with_default("FERRUM_DB_POLL_INTERVAL", 30)?;
-        let db_incremental_polling = env::var("FERRUM_DB_INCREMENTAL_POLLING")
+        let db_poll_interval = Self::parse_duration_with_default(settings, "FERRUM_DB_POLL_INTERVAL", 30)?;
+        let db_incremental_polling = settings.var("FERRUM_DB_INCREMENTAL_POLLING")
             .map(|v| v.to_lowercase() == "true" || v == "1")
             .unwrap_or(true); // Default to true for better performance
-        let db_poll_check_interval = Self::parse_duration_with_default("FERRUM_DB_POLL_CHECK_INTERVAL", 5)?;
+        let db_poll_check_interval = Self::parse_duration_with_default(settings, "FERRUM_DB_POLL_CHECK_INTERVAL", 5)?;
         
         config.db_poll_interval = db_poll_interval;
         config.db_incremental_polling = db_incremental_polling;
         config.db_poll_check_interval = db_poll_check_interval;
+        config.db_auto_migrate = settings.var("FERRUM_DB_AUTO_MIGRATE")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+        config.read_database_connection_settings(settings)?;
         
         let (db_type, db_url) = match config.mode {
-            OperationMode::Database | OperationMode::ControlPlane => {
-                let db_type_str = env::var("FERRUM_DB_TYPE")
+            OperationMode::Database | OperationMode::ControlPlane | OperationMode::Hybrid => {
+                let db_type_str = settings.var("FERRUM_DB_TYPE")
                     .map_err(|_| EnvConfigError::MissingEnv("FERRUM_DB_TYPE".to_string()))?;
                 
                 let db_type = match db_type_str.as_str() {
                     "postgres" => DatabaseType::Postgres,
                     "mysql" => DatabaseType::MySQL,
                     "sqlite" => DatabaseType::SQLite,
+                    "mongodb" => DatabaseType::MongoDB,
                     _ => return Err(EnvConfigError::InvalidEnvValue(
                         "FERRUM_DB_TYPE".to_string(), 
-                        format!("Expected one of: postgres, mysql, sqlite. Got: {}", db_type_str)
+                        format!("Expected one of: postgres, mysql, sqlite, mongodb. Got: {}", db_type_str)
                     )),
                 };
                 
-                let db_url = env::var("FERRUM_DB_URL")
+                let db_url = settings.var("FERRUM_DB_URL")
                     .map_err(|_| EnvConfigError::MissingEnv("FERRUM_DB_URL".to_string()))?;
                 
                 (Some(db_type), Some(db_url))
@@ -231,17 +691,41 @@ impl EnvConfig {
         // File mode settings
         config.file_config_path = match config.mode {
             OperationMode::File => {
-                let path = env::var("FERRUM_FILE_CONFIG_PATH")
+                let path = settings.var("FERRUM_FILE_CONFIG_PATH")
                     .map_err(|_| EnvConfigError::MissingEnv("FERRUM_FILE_CONFIG_PATH".to_string()))?;
                 Some(path)
             },
             _ => None
         };
         
+        config.config_version_retention = Self::parse_u64_with_default(
+            settings,
+            "FERRUM_CONFIG_VERSION_RETENTION",
+            100
+        )?;
+        
+        config.file_watch = settings.var("FERRUM_FILE_WATCH")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(true);
+        
+        config.file_watch_debounce_ms = Self::parse_u64_with_default(
+            settings,
+            "FERRUM_FILE_WATCH_DEBOUNCE_MS",
+            500
+        )?;
+        
+        // Docker mode settings
+        config.docker_host = settings.var("FERRUM_DOCKER_HOST")
+            .or_else(|_| env::var("DOCKER_HOST"))
+            .unwrap_or(config.docker_host);
+        config.docker_label_prefix = settings.var("FERRUM_DOCKER_LABEL_PREFIX")
+            .unwrap_or(config.docker_label_prefix);
+        config.docker_network = settings.var("FERRUM_DOCKER_NETWORK").ok();
+        
         // CP/DP communication
         config.cp_grpc_listen_addr = match config.mode {
-            OperationMode::ControlPlane => {
-                let addr_str = env::var("FERRUM_CP_GRPC_LISTEN_ADDR")
+            OperationMode::ControlPlane | OperationMode::Hybrid => {
+                let addr_str = settings.var("FERRUM_CP_GRPC_LISTEN_ADDR")
                     .map_err(|_| EnvConfigError::MissingEnv("FERRUM_CP_GRPC_LISTEN_ADDR".to_string()))?;
                 
                 let addr = addr_str.parse::<SocketAddr>()
@@ -257,41 +741,527 @@ impl EnvConfig {
         
         config.dp_cp_grpc_url = match config.mode {
             OperationMode::DataPlane => {
-                let url = env::var("FERRUM_DP_CP_GRPC_URL")
+                let url = settings.var("FERRUM_DP_CP_GRPC_URL")
                     .map_err(|_| EnvConfigError::MissingEnv("FERRUM_DP_CP_GRPC_URL".to_string()))?;
                 Some(url)
             },
             _ => None
         };
         
+        config.cp_grpc_tls_cert_path = settings.var("FERRUM_CP_GRPC_TLS_CERT_PATH").ok();
+        config.cp_grpc_tls_key_path = settings.var("FERRUM_CP_GRPC_TLS_KEY_PATH").ok();
+        if config.cp_grpc_tls_cert_path.is_some() != config.cp_grpc_tls_key_path.is_some() {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_CP_GRPC_TLS_CERT_PATH".to_string(),
+                "FERRUM_CP_GRPC_TLS_CERT_PATH and FERRUM_CP_GRPC_TLS_KEY_PATH must be set together".to_string()
+            ));
+        }
+        config.cp_grpc_tls_client_ca_path = settings.var("FERRUM_CP_GRPC_TLS_CLIENT_CA_PATH").ok();
+        if config.cp_grpc_tls_client_ca_path.is_some() && config.cp_grpc_tls_cert_path.is_none() {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_CP_GRPC_TLS_CLIENT_CA_PATH".to_string(),
+                "Client certificates can only be verified with FERRUM_CP_GRPC_TLS_CERT_PATH set".to_string()
+            ));
+        }
+        config.cp_grpc_allowed_nodes = settings.var("FERRUM_CP_GRPC_ALLOWED_NODES")
+            .map(|v| v.split(',')
+                .map(|node| node.trim().to_string())
+                .filter(|node| !node.is_empty())
+                .collect())
+            .unwrap_or_default();
+        
+        config.cp_rollout_canary_group = settings.var("FERRUM_CP_ROLLOUT_CANARY_GROUP").ok().filter(|group| !group.is_empty());
+        config.cp_rollout_stages = Self::parse_rollout_stages(settings)?;
+        config.cp_rollout_stage_interval = Self::parse_duration_with_default(settings, "FERRUM_CP_ROLLOUT_STAGE_INTERVAL", 60)?;
+        config.cp_group_pins = match settings.var("FERRUM_CP_GROUP_PINS") {
+            Ok(json_str) => serde_json::from_str(&json_str)
+                .map_err(|e| EnvConfigError::InvalidEnvValue("FERRUM_CP_GROUP_PINS".to_string(), e.to_string()))?,
+            Err(_) => HashMap::new(),
+        };
+        config.cp_xds_enabled = settings.var("FERRUM_CP_XDS_ENABLED")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+        if let Ok(addr_str) = settings.var("FERRUM_CP_XDS_LISTENER_ADDR") {
+            config.cp_xds_listener_addr = addr_str.parse::<SocketAddr>()
+                .map_err(|_| EnvConfigError::InvalidEnvValue(
+                    "FERRUM_CP_XDS_LISTENER_ADDR".to_string(),
+                    format!("Invalid socket address: {}", addr_str)
+                ))?;
+        }
+        
+        config.dp_node_id = settings.var("FERRUM_DP_NODE_ID").ok().filter(|id| !id.is_empty());
+        config.dp_node_group = settings.var("FERRUM_DP_NODE_GROUP").ok()
+            .filter(|group| !group.is_empty())
+            .unwrap_or(config.dp_node_group);
+        config.dp_health_report_interval = Self::parse_duration_with_default(settings, "FERRUM_DP_HEALTH_REPORT_INTERVAL", 10)?;
+        if config.dp_health_report_interval.is_zero() {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_DP_HEALTH_REPORT_INTERVAL".to_string(),
+                "Must be at least 1 second".to_string()
+            ));
+        }
+        config.dp_degraded_error_rate = match settings.var("FERRUM_DP_DEGRADED_ERROR_RATE") {
+            Ok(value) => value.parse::<f64>().ok()
+                .filter(|rate| (0.0..=1.0).contains(rate))
+                .ok_or_else(|| EnvConfigError::InvalidEnvValue(
+                    "FERRUM_DP_DEGRADED_ERROR_RATE".to_string(),
+                    format!("Expected a fraction between 0 and 1. Got: {}", value)
+                ))?,
+            Err(_) => config.dp_degraded_error_rate,
+        };
+        config.dp_rate_limit_sync_interval_ms = Self::parse_u64_with_default(
+            settings,
+            "FERRUM_DP_RATE_LIMIT_SYNC_INTERVAL_MS",
+            config.dp_rate_limit_sync_interval_ms,
+        )?;
+        config.dp_grpc_tls_ca_path = settings.var("FERRUM_DP_GRPC_TLS_CA_PATH").ok();
+        config.dp_grpc_tls_cert_path = settings.var("FERRUM_DP_GRPC_TLS_CERT_PATH").ok();
+        config.dp_grpc_tls_key_path = settings.var("FERRUM_DP_GRPC_TLS_KEY_PATH").ok();
+        if config.dp_grpc_tls_cert_path.is_some() != config.dp_grpc_tls_key_path.is_some() {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_DP_GRPC_TLS_CERT_PATH".to_string(),
+                "FERRUM_DP_GRPC_TLS_CERT_PATH and FERRUM_DP_GRPC_TLS_KEY_PATH must be set together".to_string()
+            ));
+        }
+        config.dp_config_cache_path = settings.var("FERRUM_DP_CONFIG_CACHE_PATH").ok().filter(|path| !path.is_empty());
+        config.dp_reconnect_max_delay = Self::parse_duration_with_default(settings, "FERRUM_DP_RECONNECT_MAX_DELAY", 30)?;
+        if config.dp_reconnect_max_delay.is_zero() {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_DP_RECONNECT_MAX_DELAY".to_string(),
+                "Must be at least 1 second".to_string()
+            ));
+        }
+        config.grpc_compression = match settings.var("FERRUM_GRPC_COMPRESSION") {
+            Ok(value) => Compression::parse(&value).ok_or_else(|| EnvConfigError::InvalidEnvValue(
+                "FERRUM_GRPC_COMPRESSION".to_string(),
+                format!("Expected none, gzip or zstd. Got: {}", value)
+            ))?,
+            Err(_) => config.grpc_compression,
+        };
+        config.grpc_max_message_size = Self::parse_usize_with_default(settings, "FERRUM_GRPC_MAX_MESSAGE_SIZE", 16777216)?;
+        config.cp_grpc_snapshot_chunk_size = Self::parse_usize_with_default(settings, "FERRUM_CP_GRPC_SNAPSHOT_CHUNK_SIZE", 1048576)?;
+        if config.cp_grpc_snapshot_chunk_size == 0
+            || config.cp_grpc_snapshot_chunk_size + CHUNK_OVERHEAD > config.grpc_max_message_size {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_CP_GRPC_SNAPSHOT_CHUNK_SIZE".to_string(),
+                format!("Must be positive and at least {} bytes below FERRUM_GRPC_MAX_MESSAGE_SIZE", CHUNK_OVERHEAD)
+            ));
+        }
+        config.grpc_keepalive_interval = Some(Self::parse_duration_with_default(settings, "FERRUM_GRPC_KEEPALIVE_INTERVAL", 30)?)
+            .filter(|interval| !interval.is_zero());
+        config.grpc_keepalive_timeout = Self::parse_duration_with_default(settings, "FERRUM_GRPC_KEEPALIVE_TIMEOUT", 10)?;
+        if config.grpc_keepalive_timeout.is_zero() {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_GRPC_KEEPALIVE_TIMEOUT".to_string(),
+                "Must be at least 1 second".to_string()
+            ));
+        }
+        config.cp_grpc_subscriber_buffer = Self::parse_usize_with_default(settings, "FERRUM_CP_GRPC_SUBSCRIBER_BUFFER", 10)?;
+        if config.cp_grpc_subscriber_buffer == 0 {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_CP_GRPC_SUBSCRIBER_BUFFER".to_string(),
+                "Must be at least 1".to_string()
+            ));
+        }
+        
         // Request handling limits
         config.max_header_size_bytes = Self::parse_usize_with_default(
+            settings,
             "FERRUM_MAX_HEADER_SIZE_BYTES", 
             16384
         )?;
         
         config.max_body_size_bytes = Self::parse_usize_with_default(
+            settings,
             "FERRUM_MAX_BODY_SIZE_BYTES", 
             10485760
         )?;
         
+        config.max_header_count = Self::parse_usize_with_default(settings, "FERRUM_MAX_HEADER_COUNT", 100)?;
+        config.max_single_header_bytes = Self::parse_usize_with_default(settings, "FERRUM_MAX_SINGLE_HEADER_BYTES", 8192)?;
+        config.max_uri_length = Self::parse_usize_with_default(settings, "FERRUM_MAX_URI_LENGTH", 8192)?;
+        
+        // Request timeouts
+        config.request_timeout_ms = Self::parse_u64_with_default(
+            settings,
+            "FERRUM_REQUEST_TIMEOUT_MS",
+            60000
+        )?;
+        
+        config.client_header_timeout_ms = Self::parse_u64_with_default(
+            settings,
+            "FERRUM_CLIENT_HEADER_TIMEOUT_MS",
+            30000
+        )?;
+        
+        config.client_body_timeout_ms = Self::parse_u64_with_default(
+            settings,
+            "FERRUM_CLIENT_BODY_TIMEOUT_MS",
+            60000
+        )?;
+        
+        // Connection-level protection
+        config.max_connections = Self::parse_usize_with_default(
+            settings,
+            "FERRUM_MAX_CONNECTIONS",
+            0
+        )?;
+        
+        config.max_connections_per_ip = Self::parse_usize_with_default(
+            settings,
+            "FERRUM_MAX_CONNECTIONS_PER_IP",
+            0
+        )?;
+        
+        config.min_transfer_rate_bytes_per_sec = Self::parse_u64_with_default(
+            settings,
+            "FERRUM_MIN_TRANSFER_RATE_BYTES_PER_SEC",
+            0
+        )?;
+        
+        // Error templates
+        config.error_templates = match settings.var("FERRUM_ERROR_TEMPLATES") {
+            Ok(json_str) => {
+                serde_json::from_str::<HashMap<String, ErrorTemplate>>(&json_str)
+                    .map_err(|e| EnvConfigError::ErrorTemplatesParseError(e.to_string()))?
+            },
+            Err(_) => HashMap::new()
+        };
+        
+        // TLS versions, cipher suites and ALPN per listener
+        config.proxy_tls_policy = Self::parse_tls_policy(settings, "FERRUM_PROXY_TLS")?;
+        config.admin_tls_policy = Self::parse_tls_policy(settings, "FERRUM_ADMIN_TLS")?;
+        
+        // Client certificate verification
+        config.proxy_tls_client_auth = Self::parse_client_auth_mode(settings, "FERRUM_PROXY_TLS_CLIENT_AUTH")?;
+        config.proxy_tls_client_ca_path = settings.var("FERRUM_PROXY_TLS_CLIENT_CA_PATH").ok();
+        if config.proxy_tls_client_auth != ClientAuthMode::None && config.proxy_tls_client_ca_path.is_none() {
+            return Err(EnvConfigError::MissingEnv("FERRUM_PROXY_TLS_CLIENT_CA_PATH".to_string()));
+        }
+        
+        config.admin_tls_client_auth = Self::parse_client_auth_mode(settings, "FERRUM_ADMIN_TLS_CLIENT_AUTH")?;
+        config.admin_tls_client_ca_path = settings.var("FERRUM_ADMIN_TLS_CLIENT_CA_PATH").ok();
+        if config.admin_tls_client_auth != ClientAuthMode::None && config.admin_tls_client_ca_path.is_none() {
+            return Err(EnvConfigError::MissingEnv("FERRUM_ADMIN_TLS_CLIENT_CA_PATH".to_string()));
+        }
+        
+        config.admin_token_ttl = Self::parse_duration_with_default(settings, "FERRUM_ADMIN_TOKEN_TTL", 900)?;
+        config.admin_refresh_token_ttl = Self::parse_duration_with_default(settings, "FERRUM_ADMIN_REFRESH_TOKEN_TTL", 86400)?;
+        if config.admin_token_ttl.is_zero() {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_ADMIN_TOKEN_TTL".to_string(),
+                "Must be at least 1 second".to_string()
+            ));
+        }
+        if config.admin_refresh_token_ttl < config.admin_token_ttl {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_ADMIN_REFRESH_TOKEN_TTL".to_string(),
+                "Must not be shorter than FERRUM_ADMIN_TOKEN_TTL".to_string()
+            ));
+        }
+        
+        config.admin_cors_allowed_origins = settings.var("FERRUM_ADMIN_CORS_ALLOWED_ORIGINS")
+            .map(|v| v.split(',')
+                .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect())
+            .unwrap_or_default();
+        if let Some(origin) = config.admin_cors_allowed_origins.iter()
+            .find(|origin| *origin != "*" && !origin.starts_with("http://") && !origin.starts_with("https://"))
+        {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_ADMIN_CORS_ALLOWED_ORIGINS".to_string(),
+                format!("Expected '*' or origins such as https://admin.example.com. Got: {}", origin)
+            ));
+        }
+        config.admin_cors_max_age = Self::parse_duration_with_default(settings, "FERRUM_ADMIN_CORS_MAX_AGE", 600)?;
+        config.admin_ui_path = settings.var("FERRUM_ADMIN_UI_PATH").ok();
+        
+        config.tls_client_crl_paths = settings.var("FERRUM_TLS_CLIENT_CRL_PATHS")
+            .map(|v| v.split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect())
+            .unwrap_or_default();
+        
+        config.tls_client_ocsp = match settings.var("FERRUM_TLS_CLIENT_OCSP").as_deref() {
+            Ok("off") | Err(_) => OcspMode::Off,
+            Ok("soft") => OcspMode::Soft,
+            Ok("hard") => OcspMode::Hard,
+            Ok(other) => return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_TLS_CLIENT_OCSP".to_string(),
+                format!("Expected one of: off, soft, hard. Got: {}", other)
+            )),
+        };
+        
+        // ACME certificate management
+        config.acme_enabled = settings.var("FERRUM_ACME_ENABLED")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+        
+        if let Ok(url) = settings.var("FERRUM_ACME_DIRECTORY_URL") {
+            config.acme_directory_url = url;
+        }
+        
+        config.acme_email = settings.var("FERRUM_ACME_EMAIL").ok();
+        
+        config.acme_domains = settings.var("FERRUM_ACME_DOMAINS")
+            .map(|v| v.split(',')
+                .map(|d| d.trim().to_lowercase())
+                .filter(|d| !d.is_empty())
+                .collect())
+            .unwrap_or_default();
+        
+        config.acme_challenge = match settings.var("FERRUM_ACME_CHALLENGE").as_deref() {
+            Ok("http-01") | Err(_) => AcmeChallengeType::Http01,
+            Ok("dns-01") => AcmeChallengeType::Dns01,
+            Ok(other) => return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_ACME_CHALLENGE".to_string(),
+                format!("Expected one of: http-01, dns-01. Got: {}", other)
+            )),
+        };
+        
+        config.acme_dns_hook = settings.var("FERRUM_ACME_DNS_HOOK").ok();
+        
+        config.acme_dns_propagation_secs = Self::parse_u64_with_default(
+            settings,
+            "FERRUM_ACME_DNS_PROPAGATION_SECS",
+            60
+        )?;
+        
+        config.acme_renew_before_days = Self::parse_u64_with_default(
+            settings,
+            "FERRUM_ACME_RENEW_BEFORE_DAYS",
+            30
+        )?;
+        
+        if let Ok(path) = settings.var("FERRUM_ACME_STORAGE_PATH") {
+            config.acme_storage_path = path;
+        }
+        
+        if config.acme_enabled {
+            if config.acme_domains.is_empty() {
+                return Err(EnvConfigError::MissingEnv("FERRUM_ACME_DOMAINS".to_string()));
+            }
+            if config.acme_challenge == AcmeChallengeType::Dns01 && config.acme_dns_hook.is_none() {
+                return Err(EnvConfigError::MissingEnv("FERRUM_ACME_DNS_HOOK".to_string()));
+            }
+        }
+        
         // DNS caching
         config.dns_cache_ttl_seconds = Self::parse_u64_with_default(
+            settings,
             "FERRUM_DNS_CACHE_TTL_SECONDS", 
             300
         )?;
+        config.dns_min_ttl = Self::parse_duration_with_default(settings, "FERRUM_DNS_MIN_TTL_SECONDS", 5)?;
         
-        // DNS overrides
-        config.dns_overrides = match env::var("FERRUM_DNS_OVERRIDES") {
-            Ok(json_str) => {
-                serde_json::from_str::<HashMap<String, String>>(&json_str)
-                    .map_err(|e| EnvConfigError::DnsOverridesParseError(e.to_string()))?
+        // DNS overrides: a hosts file, with FERRUM_DNS_OVERRIDES taking precedence
+        // for hostnames in both
+        if let Ok(path) = settings.var("FERRUM_DNS_HOSTS_FILE") {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| EnvConfigError::InvalidEnvValue("FERRUM_DNS_HOSTS_FILE".to_string(), format!("{}: {}", path, e)))?;
+            config.dns_overrides = hosts::parse_hosts(&content)
+                .map_err(|e| EnvConfigError::InvalidEnvValue("FERRUM_DNS_HOSTS_FILE".to_string(), format!("{}: {:#}", path, e)))?;
+        }
+        if let Ok(json_str) = settings.var("FERRUM_DNS_OVERRIDES") {
+            let overrides = hosts::parse_overrides(&json_str)
+                .map_err(|e| EnvConfigError::DnsOverridesParseError(format!("{:#}", e)))?;
+            config.dns_overrides.extend(overrides);
+        }
+        
+        config.dns_negative_ttl = Self::parse_duration_with_default(settings, "FERRUM_DNS_NEGATIVE_TTL_SECONDS", 5)?;
+        config.dns_stale_ttl = Self::parse_duration_with_default(settings, "FERRUM_DNS_STALE_TTL_SECONDS", 0)?;
+        
+        // Upstream DNS servers
+        config.dns_resolvers = match settings.var("FERRUM_DNS_RESOLVERS") {
+            Ok(value) => value.split(',')
+                .map(str::trim)
+                .filter(|spec| !spec.is_empty())
+                .map(|spec| parse_resolver(spec).map_err(|e| EnvConfigError::InvalidEnvValue(
+                    "FERRUM_DNS_RESOLVERS".to_string(),
+                    format!("{:#}", e),
+                )))
+                .collect::<Result<Vec<_>, _>>()?,
+            Err(_) => Vec::new()
+        };
+        
+        // Service discovery
+        config.consul_addr = settings.var("FERRUM_CONSUL_ADDR")
+            .map(|addr| addr.trim_end_matches('/').to_string())
+            .unwrap_or(config.consul_addr);
+        config.consul_token = settings.var("FERRUM_CONSUL_TOKEN").ok();
+        config.etcd_endpoint = settings.var("FERRUM_ETCD_ENDPOINT")
+            .map(|endpoint| endpoint.trim_end_matches('/').to_string())
+            .unwrap_or(config.etcd_endpoint);
+        
+        // Per-consumer metrics
+        config.metrics_consumer_labels = settings.var("FERRUM_METRICS_CONSUMER_LABELS")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+        let parse_list = |var_name: &str| settings.var(var_name)
+            .map(|v| v.split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect())
+            .unwrap_or_default();
+        config.metrics_proxy_allowlist = parse_list("FERRUM_METRICS_PROXY_ALLOWLIST");
+        config.metrics_consumer_allowlist = parse_list("FERRUM_METRICS_CONSUMER_ALLOWLIST");
+        config.metrics_max_label_sets = Self::parse_usize_with_default(settings, "FERRUM_METRICS_MAX_LABEL_SETS", 1000)?;
+        
+        // OTLP metrics export
+        config.otlp_metrics_endpoint = settings.var("FERRUM_OTLP_METRICS_ENDPOINT").ok()
+            .map(|endpoint| endpoint.trim().to_string())
+            .filter(|endpoint| !endpoint.is_empty());
+        if let Some(endpoint) = &config.otlp_metrics_endpoint {
+            if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+                return Err(EnvConfigError::InvalidEnvValue(
+                    "FERRUM_OTLP_METRICS_ENDPOINT".to_string(),
+                    format!("'{}' is not an http:// or https:// URL", endpoint),
+                ));
+            }
+        }
+        config.otlp_metrics_interval = Self::parse_duration_with_default(settings, "FERRUM_OTLP_METRICS_INTERVAL_SECONDS", 60)?;
+        if config.otlp_metrics_interval.is_zero() {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_OTLP_METRICS_INTERVAL_SECONDS".to_string(),
+                "must be at least 1".to_string(),
+            ));
+        }
+        config.otlp_metrics_batch_size = Self::parse_usize_with_default(settings, "FERRUM_OTLP_METRICS_BATCH_SIZE", 1000)?;
+        if config.otlp_metrics_batch_size == 0 {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_OTLP_METRICS_BATCH_SIZE".to_string(),
+                "must be at least 1".to_string(),
+            ));
+        }
+        config.otlp_metrics_headers = match settings.var("FERRUM_OTLP_METRICS_HEADERS") {
+            Ok(value) => value.split(',')
+                .map(str::trim)
+                .filter(|header| !header.is_empty())
+                .map(|header| match header.split_once('=') {
+                    Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.trim().to_string())),
+                    _ => Err(EnvConfigError::InvalidEnvValue(
+                        "FERRUM_OTLP_METRICS_HEADERS".to_string(),
+                        format!("'{}' is not a name=value pair", header),
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            Err(_) => Vec::new()
+        };
+        
+        // Metrics endpoint
+        config.metrics_port = Self::parse_optional_port(settings, "FERRUM_METRICS_PORT", None)?;
+        if let Some(port) = config.metrics_port {
+            let taken = [
+                config.proxy_http_port, config.proxy_https_port,
+                config.admin_http_port, config.admin_https_port,
+            ];
+            if taken.contains(&Some(port)) {
+                return Err(EnvConfigError::InvalidEnvValue(
+                    "FERRUM_METRICS_PORT".to_string(),
+                    format!("port {} is already used by a proxy or admin listener", port),
+                ));
+            }
+        }
+        config.metrics_path = settings.var("FERRUM_METRICS_PATH").unwrap_or(config.metrics_path);
+        if !config.metrics_path.starts_with('/') {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_METRICS_PATH".to_string(),
+                format!("Expected a path starting with /. Got: {}", config.metrics_path),
+            ));
+        }
+        config.metrics_on_proxy = settings.var("FERRUM_METRICS_ON_PROXY")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+        config.metrics_on_admin = settings.var("FERRUM_METRICS_ON_ADMIN")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+        
+        // StatsD metrics
+        config.statsd_addr = settings.var("FERRUM_STATSD_ADDR").ok()
+            .map(|addr| addr.trim().to_string())
+            .filter(|addr| !addr.is_empty());
+        config.statsd_prefix = settings.var("FERRUM_STATSD_PREFIX")
+            .map(|prefix| prefix.trim_end_matches('.').to_string())
+            .unwrap_or(config.statsd_prefix);
+        config.statsd_format = match settings.var("FERRUM_STATSD_FORMAT") {
+            Ok(value) => StatsdFormat::parse(&value).ok_or_else(|| EnvConfigError::InvalidEnvValue(
+                "FERRUM_STATSD_FORMAT".to_string(),
+                format!("Expected statsd or dogstatsd. Got: {}", value),
+            ))?,
+            Err(_) => StatsdFormat::DogStatsd
+        };
+        config.statsd_tags = parse_list("FERRUM_STATSD_TAGS");
+        
+        // Access log sampling
+        config.log_sampling = LogSampling {
+            one_in: Self::parse_u64_with_default(settings, "FERRUM_LOG_SAMPLE_ONE_IN", 1)?,
+            errors: settings.var("FERRUM_LOG_SAMPLE_ERRORS")
+                .map(|v| v.to_lowercase() == "true" || v == "1")
+                .unwrap_or(true),
+            slow_ms: match settings.var("FERRUM_LOG_SAMPLE_SLOW_MS") {
+                Ok(_) => Some(Self::parse_u64_with_default(settings, "FERRUM_LOG_SAMPLE_SLOW_MS", 0)?),
+                Err(_) => None,
             },
-            Err(_) => HashMap::new()
         };
         
+        // Load shedding
+        config.load_shed_max_in_flight = Self::parse_usize_with_default(settings, "FERRUM_LOAD_SHED_MAX_IN_FLIGHT", 0)?;
+        config.load_shed_critical_in_flight = Self::parse_usize_with_default(settings, "FERRUM_LOAD_SHED_CRITICAL_IN_FLIGHT", 0)?;
+        config.load_shed_p99_ms = Self::parse_u64_with_default(settings, "FERRUM_LOAD_SHED_P99_MS", 0)?;
+        config.load_shed_priority_header = settings.var("FERRUM_LOAD_SHED_PRIORITY_HEADER").ok();
+        
+        // Long-lived connections of updated proxies
+        config.long_lived_update_policy = match settings.var("FERRUM_LONG_LIVED_UPDATE_POLICY") {
+            Ok(value) => UpdatePolicy::parse(&value).ok_or_else(|| EnvConfigError::InvalidEnvValue(
+                "FERRUM_LONG_LIVED_UPDATE_POLICY".to_string(),
+                format!("Expected drain or keep. Got: {}", value),
+            ))?,
+            Err(_) => UpdatePolicy::Drain
+        };
+        config.long_lived_drain_timeout_ms = Self::parse_u64_with_default(settings, "FERRUM_LONG_LIVED_DRAIN_TIMEOUT_MS", 30000)?;
+        
+        // Egress proxy of backend connections
+        config.egress_proxy_url = settings.var("FERRUM_EGRESS_PROXY_URL").ok()
+            .filter(|url| !url.trim().is_empty());
+        if let Some(url) = &config.egress_proxy_url {
+            EgressProxy::parse(url).map_err(|e| EnvConfigError::InvalidEnvValue(
+                "FERRUM_EGRESS_PROXY_URL".to_string(),
+                format!("{:#}", e),
+            ))?;
+        }
+        config.egress_no_proxy = parse_list("FERRUM_EGRESS_NO_PROXY");
+        
+        // Deadline propagation to backends
+        config.deadline_header = settings.var("FERRUM_DEADLINE_HEADER").ok()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty());
+        if let Some(name) = &config.deadline_header {
+            if hyper::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(EnvConfigError::InvalidEnvValue(
+                    "FERRUM_DEADLINE_HEADER".to_string(),
+                    format!("Expected a header name. Got: {}", name),
+                ));
+            }
+        }
+        
+        // HTTP/3 advertisement and QUIC transport
+        config.http3_alt_svc = settings.var("FERRUM_HTTP3_ALT_SVC")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(true);
+        config.http3_alt_svc_max_age = Self::parse_u64_with_default(settings, "FERRUM_HTTP3_ALT_SVC_MAX_AGE", 86400)?;
+        config.quic_0rtt = settings.var("FERRUM_QUIC_0RTT")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+        config.quic_idle_timeout_ms = Self::parse_u64_with_default(settings, "FERRUM_QUIC_IDLE_TIMEOUT_MS", 30000)?;
+        config.quic_max_bidi_streams = Self::parse_stream_limit(settings, "FERRUM_QUIC_MAX_BIDI_STREAMS", 100, 1)?;
+        // HTTP/3 needs a control stream and two QPACK streams
+        config.quic_max_uni_streams = Self::parse_stream_limit(settings, "FERRUM_QUIC_MAX_UNI_STREAMS", 100, 3)?;
+        
         // Pagination settings
         config.default_pagination_limit = Self::parse_usize_with_default(
+            settings,
             "FERRUM_DEFAULT_PAGINATION_LIMIT", 
             500
         )?;
@@ -299,8 +1269,76 @@ impl EnvConfig {
         Ok(config)
     }
     
-    fn parse_optional_port(var_name: &str, default: Option<u16>) -> Result<Option<u16>, EnvConfigError> {
-        match env::var(var_name) {
+    /// Reads the pool and TLS settings of database connections. Also used by the
+    /// `config` and `migrate` commands, which connect without a full configuration.
+    pub fn read_database_connection_settings(&mut self, settings: &Settings) -> Result<(), EnvConfigError> {
+        self.db_min_connections = Self::parse_u64_with_default(settings, "FERRUM_DB_MIN_CONNECTIONS", 0)? as u32;
+        self.db_max_connections = match settings.var("FERRUM_DB_MAX_CONNECTIONS") {
+            Ok(_) => Some(Self::parse_u64_with_default(settings, "FERRUM_DB_MAX_CONNECTIONS", 0)? as u32),
+            Err(_) => None,
+        };
+        if self.db_max_connections == Some(0) {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_DB_MAX_CONNECTIONS".to_string(),
+                "Must be at least 1".to_string()
+            ));
+        }
+        if self.db_max_connections.map_or(false, |max| self.db_min_connections > max) {
+            return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_DB_MIN_CONNECTIONS".to_string(),
+                "Must not be greater than FERRUM_DB_MAX_CONNECTIONS".to_string()
+            ));
+        }
+        
+        self.db_acquire_timeout = Self::parse_duration_with_default(settings, "FERRUM_DB_ACQUIRE_TIMEOUT", 30)?;
+        let statement_timeout_ms = Self::parse_u64_with_default(settings, "FERRUM_DB_STATEMENT_TIMEOUT_MS", 0)?;
+        self.db_statement_timeout = (statement_timeout_ms > 0).then(|| Duration::from_millis(statement_timeout_ms));
+        
+        self.db_tls_mode = match settings.var("FERRUM_DB_TLS_MODE").as_deref() {
+            Err(_) => None,
+            Ok("disable") => Some(DbTlsMode::Disable),
+            Ok("prefer") => Some(DbTlsMode::Prefer),
+            Ok("require") => Some(DbTlsMode::Require),
+            Ok("verify-ca") => Some(DbTlsMode::VerifyCa),
+            Ok("verify-full") => Some(DbTlsMode::VerifyFull),
+            Ok(other) => return Err(EnvConfigError::InvalidEnvValue(
+                "FERRUM_DB_TLS_MODE".to_string(),
+                format!("Expected one of: disable, prefer, require, verify-ca, verify-full. Got: {}", other)
+            )),
+        };
+        self.db_tls_ca_cert_path = settings.var("FERRUM_DB_TLS_CA_CERT_PATH").ok();
+        
+        self.db_read_replica_urls = settings.var("FERRUM_DB_READ_REPLICA_URLS")
+            .map(|v| v.split(',')
+                .map(|url| url.trim().to_string())
+                .filter(|url| !url.is_empty())
+                .collect())
+            .unwrap_or_default();
+        
+        self.db_retry_attempts = Self::parse_u64_with_default(settings, "FERRUM_DB_RETRY_ATTEMPTS", 3)? as u32;
+        self.db_retry_base_delay = Duration::from_millis(Self::parse_u64_with_default(settings, "FERRUM_DB_RETRY_BASE_DELAY_MS", 200)?);
+        self.db_retry_max_delay = Duration::from_millis(Self::parse_u64_with_default(settings, "FERRUM_DB_RETRY_MAX_DELAY_MS", 5000)?);
+        self.db_circuit_breaker_threshold = Self::parse_u64_with_default(settings, "FERRUM_DB_CIRCUIT_BREAKER_THRESHOLD", 3)? as u32;
+        self.db_circuit_breaker_cooldown = Self::parse_duration_with_default(settings, "FERRUM_DB_CIRCUIT_BREAKER_COOLDOWN", 30)?;
+        self.db_tombstone_retention = Self::parse_duration_with_default(settings, "FERRUM_DB_TOMBSTONE_RETENTION", 7 * 24 * 60 * 60)?;
+        if let Ok(algorithm) = settings.var("FERRUM_CREDENTIAL_HASH_ALGORITHM") {
+            self.credential_hash_algorithm = algorithm.parse()
+                .map_err(|e| EnvConfigError::InvalidEnvValue("FERRUM_CREDENTIAL_HASH_ALGORITHM".to_string(), e))?;
+        }
+        for (name, value) in [
+            ("FERRUM_DB_RETRY_ATTEMPTS", self.db_retry_attempts),
+            ("FERRUM_DB_CIRCUIT_BREAKER_THRESHOLD", self.db_circuit_breaker_threshold),
+        ] {
+            if value == 0 {
+                return Err(EnvConfigError::InvalidEnvValue(name.to_string(), "Must be at least 1".to_string()));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    fn parse_optional_port(settings: &Settings, var_name: &str, default: Option<u16>) -> Result<Option<u16>, EnvConfigError> {
+        match settings.var(var_name) {
             Ok(val) => {
                 let port = val.parse::<u16>()
                     .map_err(|_| EnvConfigError::InvalidEnvValue(
@@ -313,8 +1351,19 @@ impl EnvConfig {
         }
     }
     
-    fn parse_duration_with_default(var_name: &str, default_secs: u64) -> Result<Duration, EnvConfigError> {
-        match env::var(var_name) {
+    fn parse_bind_addresses(settings: &Settings, var_name: &str, default: &[IpAddr]) -> Result<Vec<IpAddr>, EnvConfigError> {
+        match settings.var(var_name) {
+            Ok(val) => bind::parse_addresses(&val)
+                .map_err(|e| EnvConfigError::InvalidEnvValue(
+                    var_name.to_string(),
+                    format!("Expected comma-separated IP addresses, {}. Got: {}", e, val)
+                )),
+            Err(_) => Ok(default.to_vec())
+        }
+    }
+    
+    fn parse_duration_with_default(settings: &Settings, var_name: &str, default_secs: u64) -> Result<Duration, EnvConfigError> {
+        match settings.var(var_name) {
             Ok(val) => {
                 let secs = val.parse::<u64>()
                     .map_err(|_| EnvConfigError::InvalidEnvValue(
@@ -327,8 +1376,8 @@ impl EnvConfig {
         }
     }
     
-    fn parse_usize_with_default(var_name: &str, default: usize) -> Result<usize, EnvConfigError> {
-        match env::var(var_name) {
+    fn parse_usize_with_default(settings: &Settings, var_name: &str, default: usize) -> Result<usize, EnvConfigError> {
+        match settings.var(var_name) {
             Ok(val) => {
                 let num = val.parse::<usize>()
                     .map_err(|_| EnvConfigError::InvalidEnvValue(
@@ -341,8 +1390,20 @@ impl EnvConfig {
         }
     }
     
-    fn parse_u64_with_default(var_name: &str, default: u64) -> Result<u64, EnvConfigError> {
-        match env::var(var_name) {
+    /// Reads a QUIC stream limit of at least `min`
+    fn parse_stream_limit(settings: &Settings, var_name: &str, default: u32, min: u32) -> Result<u32, EnvConfigError> {
+        let value = Self::parse_u64_with_default(settings, var_name, default as u64)?;
+        match u32::try_from(value) {
+            Ok(limit) if limit >= min => Ok(limit),
+            _ => Err(EnvConfigError::InvalidEnvValue(
+                var_name.to_string(),
+                format!("Expected a number from {} to {}. Got: {}", min, u32::MAX, value),
+            )),
+        }
+    }
+    
+    fn parse_u64_with_default(settings: &Settings, var_name: &str, default: u64) -> Result<u64, EnvConfigError> {
+        match settings.var(var_name) {
             Ok(val) => {
                 let num = val.parse::<u64>()
                     .map_err(|_| EnvConfigError::InvalidEnvValue(
@@ -354,4 +1415,102 @@ impl EnvConfig {
             Err(_) => Ok(default)
         }
     }
+    
+    /// Reads FERRUM_CP_ROLLOUT_STAGES, ascending percentages such as "10,50".
+    /// The last stage always covers every node.
+    fn parse_rollout_stages(settings: &Settings) -> Result<Vec<u32>, EnvConfigError> {
+        let value = match settings.var("FERRUM_CP_ROLLOUT_STAGES") {
+            Ok(value) if !value.trim().is_empty() => value,
+            _ => return Ok(Vec::new()),
+        };
+        
+        let mut stages = Vec::new();
+        for stage in value.split(',') {
+            let percentage = stage.trim().trim_end_matches('%').parse::<u32>().ok()
+                .filter(|percentage| (1..=100).contains(percentage) && stages.last().map_or(true, |last| percentage > last))
+                .ok_or_else(|| EnvConfigError::InvalidEnvValue(
+                    "FERRUM_CP_ROLLOUT_STAGES".to_string(),
+                    format!("Expected ascending percentages between 1 and 100. Got: {}", value)
+                ))?;
+            stages.push(percentage);
+        }
+        if stages.last() != Some(&100) {
+            stages.push(100);
+        }
+        Ok(stages)
+    }
+    
+    /// Reads FERRUM_CP_GRPC_NODE_TOKENS, a JSON object of node IDs to their tokens
+    fn parse_node_tokens(settings: &Settings) -> Result<HashMap<String, String>, EnvConfigError> {
+        match settings.var("FERRUM_CP_GRPC_NODE_TOKENS") {
+            Ok(json_str) => serde_json::from_str(&json_str)
+                .map_err(|e| EnvConfigError::InvalidEnvValue("FERRUM_CP_GRPC_NODE_TOKENS".to_string(), e.to_string())),
+            Err(_) => Ok(HashMap::new()),
+        }
+    }
+    
+    fn parse_client_auth_mode(settings: &Settings, var_name: &str) -> Result<ClientAuthMode, EnvConfigError> {
+        match settings.var(var_name).as_deref() {
+            Ok("none") | Err(_) => Ok(ClientAuthMode::None),
+            Ok("optional") => Ok(ClientAuthMode::Optional),
+            Ok("required") => Ok(ClientAuthMode::Required),
+            Ok(other) => Err(EnvConfigError::InvalidEnvValue(
+                var_name.to_string(),
+                format!("Expected one of: none, optional, required. Got: {}", other)
+            )),
+        }
+    }
+    
+    /// Reads the <prefix>_MIN_VERSION, _MAX_VERSION, _CIPHER_SUITES and
+    /// _ALPN_PROTOCOLS variables of a TLS listener
+    fn parse_tls_policy(settings: &Settings, prefix: &str) -> Result<TlsPolicy, EnvConfigError> {
+        let mut policy = TlsPolicy::default();
+        
+        let parse_version = |var_name: String, default: TlsVersion| match settings.var(&var_name).as_deref() {
+            Err(_) => Ok(default),
+            Ok("1.2") => Ok(TlsVersion::Tls12),
+            Ok("1.3") => Ok(TlsVersion::Tls13),
+            Ok(other) => Err(EnvConfigError::InvalidEnvValue(
+                var_name,
+                format!("Expected one of: 1.2, 1.3. Got: {}", other)
+            )),
+        };
+        policy.min_version = parse_version(format!("{}_MIN_VERSION", prefix), policy.min_version)?;
+        policy.max_version = parse_version(format!("{}_MAX_VERSION", prefix), policy.max_version)?;
+        if policy.min_version > policy.max_version {
+            return Err(EnvConfigError::InvalidEnvValue(
+                format!("{}_MIN_VERSION", prefix),
+                format!("Must not be greater than {}_MAX_VERSION", prefix)
+            ));
+        }
+        
+        let parse_list = |var_name: &str| settings.var(var_name).ok().map(|v| v.split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect::<Vec<String>>());
+        
+        // Cipher suite names are checked against rustls when the listener starts
+        if let Some(cipher_suites) = parse_list(&format!("{}_CIPHER_SUITES", prefix)) {
+            policy.cipher_suites = cipher_suites;
+        }
+        
+        let alpn_var = format!("{}_ALPN_PROTOCOLS", prefix);
+        if let Some(alpn_protocols) = parse_list(&alpn_var) {
+            if alpn_protocols.is_empty() {
+                return Err(EnvConfigError::InvalidEnvValue(
+                    alpn_var,
+                    "Expected at least one of: h2, http/1.1".to_string()
+                ));
+            }
+            if let Some(other) = alpn_protocols.iter().find(|p| *p != "h2" && *p != "http/1.1") {
+                return Err(EnvConfigError::InvalidEnvValue(
+                    alpn_var,
+                    format!("Expected one of: h2, http/1.1. Got: {}", other)
+                ));
+            }
+            policy.alpn_protocols = alpn_protocols;
+        }
+        
+        Ok(policy)
+    }
 }
@@ -1,20 +1,203 @@
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::fs;
 use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use jsonschema::{Draft, JSONSchema};
+use once_cell::sync::Lazy;
+use schemars::JsonSchema;
+use schemars::gen::SchemaSettings;
+use serde::Deserialize;
+use serde_json::{Map, Value};
 use tracing::info;
 
-use super::data_model::Configuration;
+use super::data_model::{Certificate, Configuration, Consumer, PluginConfig, Proxy, Sni};
+use super::document::{self, Format};
+
+static CONFIG_SCHEMA: Lazy<JSONSchema> = Lazy::new(|| {
+    JSONSchema::options()
+        .with_draft(Draft::Draft7)
+        .compile(&config_schema())
+        .expect("Generated configuration schema is invalid")
+});
 
 pub fn parse_json_config(content: &str) -> Result<Configuration> {
-    serde_json::from_str(content)
-        .context("Failed to parse JSON configuration")
+    parse_config(content, Format::Json)
 }
 
 pub fn parse_yaml_config(content: &str) -> Result<Configuration> {
-    serde_yaml::from_str(content)
-        .context("Failed to parse YAML configuration")
+    parse_config(content, Format::Yaml)
+}
+
+pub fn parse_toml_config(content: &str) -> Result<Configuration> {
+    parse_config(content, Format::Toml)
+}
+
+fn parse_config(content: &str, format: Format) -> Result<Configuration> {
+    let value = document::resolve_str(content, format)?;
+    Ok(parse_document(value)?.into_configuration())
+}
+
+/// Loads a single configuration file, resolving environment references and includes.
+/// The syntax follows the extension (.json, .toml, otherwise YAML).
+pub fn load_file(path: &Path) -> Result<Configuration> {
+    let document = document::load(path)?;
+    let fragment = parse_document(document.value)
+        .context(format!("Invalid configuration in {}", path.display()))?;
+    Ok(fragment.into_configuration())
+}
+
+/// Validates an already parsed configuration document against the schema
+pub fn from_document(value: Value) -> Result<Configuration> {
+    Ok(parse_document(value)?.into_configuration())
+}
+
+/// JSON Schema of a configuration document, generated from the data model.
+/// Objects reject properties the gateway does not know, so a misspelled field
+/// is reported instead of silently falling back to its default.
+pub fn config_schema() -> Value {
+    let schema = SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<ConfigFragment>();
+    let mut schema = serde_json::to_value(schema).expect("Configuration schema is serializable");
+    deny_additional_properties(&mut schema);
+    schema
+}
+
+fn deny_additional_properties(schema: &mut Value) {
+    match schema {
+        Value::Object(object) => {
+            if object.contains_key("properties") && !object.contains_key("additionalProperties") {
+                object.insert("additionalProperties".to_string(), Value::Bool(false));
+            }
+            object.values_mut().for_each(deny_additional_properties);
+        },
+        Value::Array(items) => items.iter_mut().for_each(deny_additional_properties),
+        _ => {},
+    }
+}
+
+/// Checks a document against the schema, then deserializes it
+fn parse_document(value: Value) -> Result<ConfigFragment> {
+    let violations = schema_violations(&value);
+    if !violations.is_empty() {
+        let problems: Vec<String> = violations.iter()
+            .map(|(path, message)| format!("{}: {}", path, message))
+            .collect();
+        anyhow::bail!("Configuration does not match the schema:\n  {}", problems.join("\n  "));
+    }
+    
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Schema violations of a document as (field path, message) pairs
+pub fn schema_violations(value: &Value) -> Vec<(String, String)> {
+    match CONFIG_SCHEMA.validate(value) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .map(|e| (field_path(&e.instance_path.to_string()), e.to_string()))
+            .collect(),
+    }
+}
+
+/// Turns a JSON pointer such as `/proxies/0/backend_port` into `proxies[0].backend_port`
+fn field_path(pointer: &str) -> String {
+    let mut path = String::new();
+    for segment in pointer.split('/').skip(1) {
+        let segment = segment.replace("~1", "/").replace("~0", "~");
+        if segment.parse::<usize>().is_ok() {
+            path.push_str(&format!("[{}]", segment));
+        } else {
+            if !path.is_empty() {
+                path.push('.');
+            }
+            path.push_str(&segment);
+        }
+    }
+    
+    if path.is_empty() {
+        "(document)".to_string()
+    } else {
+        path
+    }
+}
+
+/// Top-level keys of a configuration document
+const DOCUMENT_KEYS: [&str; 6] = ["proxies", "consumers", "plugin_configs", "certificates", "snis", "last_updated_at"];
+
+/// A configuration document in a configuration directory. Every section is
+/// optional, so a file can hold just the entities it is about.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+struct ConfigFragment {
+    #[serde(default)]
+    proxies: Vec<Proxy>,
+    #[serde(default)]
+    consumers: Vec<Consumer>,
+    #[serde(default)]
+    plugin_configs: Vec<PluginConfig>,
+    #[serde(default)]
+    certificates: Vec<Certificate>,
+    #[serde(default)]
+    snis: Vec<Sni>,
+    #[serde(default)]
+    last_updated_at: Option<DateTime<Utc>>,
+}
+
+impl ConfigFragment {
+    fn into_configuration(self) -> Configuration {
+        Configuration {
+            proxies: self.proxies,
+            consumers: self.consumers,
+            plugin_configs: self.plugin_configs,
+            certificates: self.certificates,
+            snis: self.snis,
+            // Use current time if no timestamp was found
+            last_updated_at: self.last_updated_at.unwrap_or_else(Utc::now),
+        }
+    }
+}
+
+/// Entity kind implied by a top-level subdirectory of a configuration directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FragmentKind {
+    Proxies,
+    Consumers,
+    PluginConfigs,
+    Certificates,
+    Snis,
 }
 
+impl FragmentKind {
+    fn section(self) -> &'static str {
+        match self {
+            Self::Proxies => "proxies",
+            Self::Consumers => "consumers",
+            Self::PluginConfigs => "plugin_configs",
+            Self::Certificates => "certificates",
+            Self::Snis => "snis",
+        }
+    }
+    
+    fn from_dir_name(name: &str) -> Option<Self> {
+        match name {
+            "proxies" => Some(Self::Proxies),
+            "consumers" => Some(Self::Consumers),
+            "plugins" | "plugin_configs" => Some(Self::PluginConfigs),
+            "certificates" => Some(Self::Certificates),
+            "snis" => Some(Self::Snis),
+            _ => None,
+        }
+    }
+}
+
+/// Loads every YAML, JSON and TOML file under a configuration directory and merges
+/// them into one configuration.
+///
+/// Files are read in lexical path order, so the result does not depend on the
+/// filesystem. Files in `proxies/`, `consumers/`, `plugins/`, `certificates/`
+/// and `snis/` may contain a single entity or a list of entities of that kind;
+/// any file may also contain a regular configuration document. Hidden files
+/// and directories are skipped. An ID defined in more than one file is an error.
 pub fn load_from_directory(dir_path: &Path) -> Result<Configuration> {
     if !dir_path.is_dir() {
         anyhow::bail!("Path is not a directory: {}", dir_path.display());
@@ -22,56 +205,110 @@ pub fn load_from_directory(dir_path: &Path) -> Result<Configuration> {
     
     info!("Loading configuration from directory: {}", dir_path.display());
     
-    let mut proxies = Vec::new();
-    let mut consumers = Vec::new();
-    let mut plugin_configs = Vec::new();
-    let mut latest_timestamp = chrono::DateTime::<chrono::Utc>::MIN_UTC;
+    let mut files = Vec::new();
+    collect_config_files(dir_path, &mut files)?;
+    files.sort();
+    
+    // Resolve every file first, since files pulled in through an include are
+    // part of the including document rather than fragments of their own
+    let mut documents = Vec::new();
+    let mut included = HashSet::new();
+    for path in &files {
+        let document = document::load(path)?;
+        included.extend(document.included.iter().cloned());
+        documents.push((path, document.value));
+    }
+    
+    let mut merged = ConfigFragment::default();
+    let mut sources: HashMap<(&'static str, String), PathBuf> = HashMap::new();
+    let mut merged_files = 0;
     
-    // Walk through all files in the directory (non-recursive)
-    for entry in fs::read_dir(dir_path)? {
-        let entry = entry?;
-        let path = entry.path();
+    for (path, value) in documents {
+        if fs::canonicalize(path).map_or(false, |canonical| included.contains(&canonical)) {
+            continue;
+        }
+        info!("Processing configuration file: {}", path.display());
+        merged_files += 1;
+        
+        let kind = path.strip_prefix(dir_path).ok()
+            .and_then(|relative| relative.components().next())
+            .and_then(|component| component.as_os_str().to_str())
+            .and_then(FragmentKind::from_dir_name);
         
-        if path.is_file() {
-            // Only process .json, .yaml, and .yml files
-            if let Some(ext) = path.extension() {
-                let ext_str = ext.to_string_lossy().to_lowercase();
-                
-                if ext_str == "json" || ext_str == "yaml" || ext_str == "yml" {
-                    info!("Processing configuration file: {}", path.display());
-                    
-                    let content = fs::read_to_string(&path)
-                        .context(format!("Failed to read file: {}", path.display()))?;
-                    
-                    let config = if ext_str == "json" {
-                        parse_json_config(&content)
-                    } else {
-                        parse_yaml_config(&content)
-                    }?;
-                    
-                    // Merge the configuration
-                    proxies.extend(config.proxies);
-                    consumers.extend(config.consumers);
-                    plugin_configs.extend(config.plugin_configs);
-                    
-                    // Update the latest timestamp
-                    if config.last_updated_at > latest_timestamp {
-                        latest_timestamp = config.last_updated_at;
-                    }
-                }
+        let fragment = parse_fragment(value, kind)
+            .context(format!("Invalid configuration file: {}", path.display()))?;
+        
+        let ids = fragment.proxies.iter().map(|p| ("proxy", &p.id))
+            .chain(fragment.consumers.iter().map(|c| ("consumer", &c.id)))
+            .chain(fragment.plugin_configs.iter().map(|p| ("plugin config", &p.id)))
+            .chain(fragment.certificates.iter().map(|c| ("certificate", &c.id)))
+            .chain(fragment.snis.iter().map(|s| ("SNI", &s.id)));
+        
+        for (entity, id) in ids {
+            if let Some(previous) = sources.insert((entity, id.clone()), path.clone()) {
+                anyhow::bail!(
+                    "Duplicate {} ID '{}' in {} and {}",
+                    entity, id, previous.display(), path.display()
+                );
             }
         }
+        
+        merged.proxies.extend(fragment.proxies);
+        merged.consumers.extend(fragment.consumers);
+        merged.plugin_configs.extend(fragment.plugin_configs);
+        merged.certificates.extend(fragment.certificates);
+        merged.snis.extend(fragment.snis);
+        merged.last_updated_at = merged.last_updated_at.max(fragment.last_updated_at);
+    }
+    
+    info!(
+        "Merged {} configuration files: {} proxies, {} consumers, {} plugin configs",
+        merged_files, merged.proxies.len(), merged.consumers.len(), merged.plugin_configs.len()
+    );
+    
+    Ok(merged.into_configuration())
+}
+
+/// Recursively collects configuration files, skipping hidden entries
+fn collect_config_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).context(format!("Failed to read directory: {}", dir.display()))? {
+        let path = entry?.path();
+        
+        let hidden = path.file_name()
+            .map_or(false, |name| name.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+        
+        if path.is_dir() {
+            collect_config_files(&path, files)?;
+        } else if Format::is_config_path(&path) {
+            files.push(path);
+        }
     }
     
-    // Use current time if no timestamp was found
-    if latest_timestamp == chrono::DateTime::<chrono::Utc>::MIN_UTC {
-        latest_timestamp = chrono::Utc::now();
+    Ok(())
+}
+
+fn parse_fragment(value: Value, kind: Option<FragmentKind>) -> Result<ConfigFragment> {
+    // An empty file contributes nothing
+    if value.is_null() {
+        return Ok(ConfigFragment::default());
     }
     
-    Ok(Configuration {
-        proxies,
-        consumers,
-        plugin_configs,
-        last_updated_at: latest_timestamp,
-    })
+    let is_document = value.as_object()
+        .map_or(false, |object| object.keys().any(|key| DOCUMENT_KEYS.contains(&key.as_str())));
+    
+    // A single entity or a list of them becomes the matching section of a document
+    let value = match kind {
+        Some(kind) if !is_document => {
+            let entities = if value.is_array() { value } else { Value::Array(vec![value]) };
+            let mut document = Map::new();
+            document.insert(kind.section().to_string(), entities);
+            Value::Object(document)
+        },
+        _ => value,
+    };
+    
+    parse_document(value)
 }
@@ -1,3 +1,12 @@
 pub mod env_config;
 pub mod data_model;
 pub mod file_config;
+pub mod snapshot;
+pub mod consumer_index;
+pub mod credentials;
+pub mod document;
+pub mod declarative;
+pub mod validation;
+pub mod versions;
+pub mod cli;
+pub mod settings;
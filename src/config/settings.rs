@@ -0,0 +1,378 @@
+//! Layered gateway settings for Ferrum Gateway
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use clap::{Arg, ArgAction, Command};
+use serde_json::Value;
+
+use super::document;
+use super::env_config::EnvConfigError;
+
+/// Environment variable naming the bootstrap file, also set with --config-file
+pub const BOOTSTRAP_FILE_VAR: &str = "FERRUM_BOOTSTRAP_FILE";
+
+/// Shown instead of the value of secret settings
+const MASK: &str = "********";
+
+/// A gateway setting
+pub struct Setting {
+    /// Environment variable name; the flag is derived from it
+    pub name: &'static str,
+    pub help: &'static str,
+    /// Value used when the setting is not given, as documented
+    pub default: Option<&'static str>,
+    /// Whether the value is hidden by print-config
+    pub secret: bool,
+}
+
+impl Setting {
+    /// The command line flag, `--proxy-http-port` for FERRUM_PROXY_HTTP_PORT
+    pub fn flag(&self) -> String {
+        self.name.trim_start_matches("FERRUM_").to_lowercase().replace('_', "-")
+    }
+}
+
+/// Every setting read by `EnvConfig`
+pub const SETTINGS: &[Setting] = &[
+    Setting { name: "FERRUM_MODE", help: "Operating mode (database, file, cp, dp, docker, hybrid)", default: None, secret: false },
+    Setting { name: "FERRUM_LOG_LEVEL", help: "Log level (error, warn, info, debug, trace)", default: Some("info"), secret: false },
+    Setting { name: "FERRUM_PROXY_HTTP_PORT", help: "HTTP port for proxy traffic", default: Some("8000"), secret: false },
+    Setting { name: "FERRUM_PROXY_HTTPS_PORT", help: "HTTPS port for proxy traffic", default: Some("8443"), secret: false },
+    Setting { name: "FERRUM_PROXY_HTTP3_PORT", help: "HTTP/3 (QUIC) port for proxy traffic", default: Some("8444"), secret: false },
+    Setting { name: "FERRUM_PROXY_TLS_CERT_PATH", help: "Path to TLS certificate for HTTPS proxy", default: None, secret: false },
+    Setting { name: "FERRUM_PROXY_TLS_KEY_PATH", help: "Path to TLS private key for HTTPS proxy", default: None, secret: false },
+    Setting { name: "FERRUM_ADMIN_HTTP_PORT", help: "HTTP port for Admin API", default: Some("9000"), secret: false },
+    Setting { name: "FERRUM_ADMIN_HTTPS_PORT", help: "HTTPS port for Admin API", default: Some("9443"), secret: false },
+    Setting { name: "FERRUM_ADMIN_HTTP3_PORT", help: "HTTP/3 (QUIC) port for Admin API", default: Some("9444"), secret: false },
+    Setting { name: "FERRUM_ADMIN_TLS_CERT_PATH", help: "Path to TLS certificate for HTTPS Admin API", default: None, secret: false },
+    Setting { name: "FERRUM_ADMIN_TLS_KEY_PATH", help: "Path to TLS private key for HTTPS Admin API", default: None, secret: false },
+    Setting { name: "FERRUM_PROXY_BIND_ADDRESSES", help: "Comma-separated IPv4 or IPv6 addresses the proxy listeners bind to", default: Some("0.0.0.0"), secret: false },
+    Setting { name: "FERRUM_PROXY_HTTP_BIND_ADDRESSES", help: "Addresses the HTTP proxy listener binds to, overriding FERRUM_PROXY_BIND_ADDRESSES", default: None, secret: false },
+    Setting { name: "FERRUM_PROXY_HTTPS_BIND_ADDRESSES", help: "Addresses the HTTPS proxy listener binds to, overriding FERRUM_PROXY_BIND_ADDRESSES", default: None, secret: false },
+    Setting { name: "FERRUM_PROXY_HTTP3_BIND_ADDRESSES", help: "Addresses the HTTP/3 proxy listener binds to, overriding FERRUM_PROXY_BIND_ADDRESSES", default: None, secret: false },
+    Setting { name: "FERRUM_ADMIN_BIND_ADDRESSES", help: "Comma-separated IPv4 or IPv6 addresses the admin listeners bind to", default: Some("0.0.0.0"), secret: false },
+    Setting { name: "FERRUM_ADMIN_HTTP_BIND_ADDRESSES", help: "Addresses the HTTP admin listener binds to, overriding FERRUM_ADMIN_BIND_ADDRESSES", default: None, secret: false },
+    Setting { name: "FERRUM_ADMIN_HTTPS_BIND_ADDRESSES", help: "Addresses the HTTPS admin listener binds to, overriding FERRUM_ADMIN_BIND_ADDRESSES", default: None, secret: false },
+    Setting { name: "FERRUM_HTTP3_ALT_SVC", help: "Advertise the HTTP/3 listener through Alt-Svc headers on HTTPS responses", default: Some("true"), secret: false },
+    Setting { name: "FERRUM_HTTP3_ALT_SVC_MAX_AGE", help: "Seconds clients may remember the HTTP/3 advertisement", default: Some("86400"), secret: false },
+    Setting { name: "FERRUM_QUIC_0RTT", help: "Accept 0-RTT early data from resuming HTTP/3 clients, for safe methods only", default: Some("false"), secret: false },
+    Setting { name: "FERRUM_QUIC_IDLE_TIMEOUT_MS", help: "How long HTTP/3 connections may stay idle (0 = no timeout)", default: Some("30000"), secret: false },
+    Setting { name: "FERRUM_QUIC_MAX_BIDI_STREAMS", help: "Concurrent requests per HTTP/3 connection", default: Some("100"), secret: false },
+    Setting { name: "FERRUM_QUIC_MAX_UNI_STREAMS", help: "Unidirectional streams per HTTP/3 connection (at least 3)", default: Some("100"), secret: false },
+    Setting { name: "FERRUM_PROXY_TLS_MIN_VERSION", help: "Minimum TLS version for the HTTPS proxy (1.2, 1.3)", default: Some("1.2"), secret: false },
+    Setting { name: "FERRUM_PROXY_TLS_MAX_VERSION", help: "Maximum TLS version for the HTTPS proxy (1.2, 1.3)", default: Some("1.3"), secret: false },
+    Setting { name: "FERRUM_PROXY_TLS_CIPHER_SUITES", help: "Comma-separated rustls cipher suite names for the HTTPS proxy", default: Some("rustls defaults"), secret: false },
+    Setting { name: "FERRUM_PROXY_TLS_ALPN_PROTOCOLS", help: "Comma-separated ALPN protocols for the HTTPS proxy (h2, http/1.1)", default: Some("h2,http/1.1"), secret: false },
+    Setting { name: "FERRUM_ADMIN_TLS_MIN_VERSION", help: "Minimum TLS version for the HTTPS Admin API", default: Some("1.2"), secret: false },
+    Setting { name: "FERRUM_ADMIN_TLS_MAX_VERSION", help: "Maximum TLS version for the HTTPS Admin API", default: Some("1.3"), secret: false },
+    Setting { name: "FERRUM_ADMIN_TLS_CIPHER_SUITES", help: "Comma-separated rustls cipher suite names for the HTTPS Admin API", default: Some("rustls defaults"), secret: false },
+    Setting { name: "FERRUM_ADMIN_TLS_ALPN_PROTOCOLS", help: "Comma-separated ALPN protocols for the HTTPS Admin API", default: Some("h2,http/1.1"), secret: false },
+    Setting { name: "FERRUM_PROXY_TLS_CLIENT_AUTH", help: "Client certificate verification on the HTTPS proxy (none, optional, required)", default: Some("none"), secret: false },
+    Setting { name: "FERRUM_PROXY_TLS_CLIENT_CA_PATH", help: "CA bundle used to verify proxy client certificates", default: None, secret: false },
+    Setting { name: "FERRUM_ADMIN_TLS_CLIENT_AUTH", help: "Client certificate verification on the HTTPS Admin API (none, optional, required)", default: Some("none"), secret: false },
+    Setting { name: "FERRUM_ADMIN_TLS_CLIENT_CA_PATH", help: "CA bundle used to verify Admin API client certificates", default: None, secret: false },
+    Setting { name: "FERRUM_TLS_CLIENT_CRL_PATHS", help: "Comma-separated CRL files (PEM or DER) checked for client certificates", default: None, secret: false },
+    Setting { name: "FERRUM_TLS_CLIENT_OCSP", help: "OCSP checking of client certificates (off, soft, hard)", default: Some("off"), secret: false },
+    Setting { name: "FERRUM_ADMIN_JWT_SECRET", help: "Secret for Admin API JWT authentication", default: None, secret: true },
+    Setting { name: "FERRUM_ADMIN_TOKEN_TTL", help: "Seconds an Admin API access token issued by /auth/login is valid", default: Some("900"), secret: false },
+    Setting { name: "FERRUM_ADMIN_REFRESH_TOKEN_TTL", help: "Seconds an Admin API refresh token is valid", default: Some("86400"), secret: false },
+    Setting { name: "FERRUM_ADMIN_CORS_ALLOWED_ORIGINS", help: "Comma-separated browser origins allowed to call the Admin API, or * for any", default: None, secret: false },
+    Setting { name: "FERRUM_ADMIN_CORS_MAX_AGE", help: "Seconds browsers may cache an Admin API preflight response", default: Some("600"), secret: false },
+    Setting { name: "FERRUM_ADMIN_UI_PATH", help: "Directory of a management UI served by the Admin API under /ui/", default: None, secret: false },
+    Setting { name: "FERRUM_CP_GRPC_JWT_SECRET", help: "Secret for CP gRPC authentication", default: None, secret: true },
+    Setting { name: "FERRUM_DP_GRPC_AUTH_TOKEN", help: "JWT or pre-shared token for DP authentication to CP", default: None, secret: true },
+    Setting { name: "FERRUM_DB_TYPE", help: "Database type (postgres, mysql, sqlite, mongodb)", default: None, secret: false },
+    Setting { name: "FERRUM_DB_URL", help: "Database connection URL", default: None, secret: true },
+    Setting { name: "FERRUM_DB_POLL_INTERVAL", help: "Interval for polling DB changes", default: Some("30"), secret: false },
+    Setting { name: "FERRUM_DB_POLL_CHECK_INTERVAL", help: "Interval for lightweight change detection", default: Some("5"), secret: false },
+    Setting { name: "FERRUM_CONFIG_VERSION_RETENTION", help: "Configuration versions kept for rollback (0 keeps all)", default: Some("100"), secret: false },
+    Setting { name: "FERRUM_DB_AUTO_MIGRATE", help: "Apply pending schema migrations at startup", default: Some("false"), secret: false },
+    Setting { name: "FERRUM_DB_MIN_CONNECTIONS", help: "Connections the pool keeps open when idle", default: Some("0"), secret: false },
+    Setting { name: "FERRUM_DB_MAX_CONNECTIONS", help: "Largest database pool size (10, or 5 for SQLite)", default: None, secret: false },
+    Setting { name: "FERRUM_DB_ACQUIRE_TIMEOUT", help: "Seconds a query waits for a pooled connection", default: Some("30"), secret: false },
+    Setting { name: "FERRUM_DB_STATEMENT_TIMEOUT_MS", help: "Server-side statement timeout in milliseconds (0 for none)", default: Some("0"), secret: false },
+    Setting { name: "FERRUM_DB_TLS_MODE", help: "Database TLS mode (disable, prefer, require, verify-ca, verify-full)", default: None, secret: false },
+    Setting { name: "FERRUM_DB_TLS_CA_CERT_PATH", help: "CA certificate the database server is verified against", default: None, secret: false },
+    Setting { name: "FERRUM_DB_READ_REPLICA_URLS", help: "Comma-separated read replica URLs configuration is loaded from", default: None, secret: true },
+    Setting { name: "FERRUM_DB_RETRY_ATTEMPTS", help: "Attempts per configuration read from the database", default: Some("3"), secret: false },
+    Setting { name: "FERRUM_DB_RETRY_BASE_DELAY_MS", help: "First retry delay in milliseconds, doubled per retry with jitter", default: Some("200"), secret: false },
+    Setting { name: "FERRUM_DB_RETRY_MAX_DELAY_MS", help: "Longest retry delay in milliseconds", default: Some("5000"), secret: false },
+    Setting { name: "FERRUM_DB_CIRCUIT_BREAKER_THRESHOLD", help: "Failed reads in a row before the database counts as down", default: Some("3"), secret: false },
+    Setting { name: "FERRUM_DB_CIRCUIT_BREAKER_COOLDOWN", help: "Seconds reads fail fast before the database is tried again", default: Some("30"), secret: false },
+    Setting { name: "FERRUM_DB_TOMBSTONE_RETENTION", help: "Seconds deleted entities are remembered for incremental polling (0 keeps them forever)", default: Some("604800"), secret: false },
+    Setting { name: "FERRUM_CREDENTIAL_HASH_ALGORITHM", help: "Hash for consumer passwords and client secrets: bcrypt or argon2", default: Some("bcrypt"), secret: false },
+    Setting { name: "FERRUM_DB_INCREMENTAL_POLLING", help: "Enable/disable incremental polling", default: Some("true"), secret: false },
+    Setting { name: "FERRUM_FILE_CONFIG_PATH", help: "Path to config file or directory", default: None, secret: false },
+    Setting { name: "FERRUM_FILE_WATCH", help: "Reload the configuration when the file changes", default: Some("true"), secret: false },
+    Setting { name: "FERRUM_FILE_WATCH_DEBOUNCE_MS", help: "Wait after the last change before reloading", default: Some("500"), secret: false },
+    Setting { name: "FERRUM_DOCKER_HOST", help: "Docker Engine API address (unix:// or tcp://), falling back to DOCKER_HOST", default: Some("unix:///var/run/docker.sock"), secret: false },
+    Setting { name: "FERRUM_DOCKER_LABEL_PREFIX", help: "Prefix of the container labels proxies are read from", default: Some("ferrum"), secret: false },
+    Setting { name: "FERRUM_DOCKER_NETWORK", help: "Network whose container address backends are reached at", default: Some("first network"), secret: false },
+    Setting { name: "FERRUM_CP_GRPC_LISTEN_ADDR", help: "Address for CP gRPC server", default: None, secret: false },
+    Setting { name: "FERRUM_DP_CP_GRPC_URL", help: "URL of CP gRPC server", default: None, secret: false },
+    Setting { name: "FERRUM_CP_GRPC_TLS_CERT_PATH", help: "Certificate (PEM) the CP gRPC server serves TLS with", default: None, secret: false },
+    Setting { name: "FERRUM_CP_GRPC_TLS_KEY_PATH", help: "Private key (PEM) of the CP gRPC server certificate", default: None, secret: false },
+    Setting { name: "FERRUM_CP_GRPC_TLS_CLIENT_CA_PATH", help: "CA bundle (PEM) data plane client certificates must be issued by", default: None, secret: false },
+    Setting { name: "FERRUM_CP_GRPC_NODE_TOKENS", help: "JSON object of data plane node IDs to their pre-shared tokens", default: None, secret: true },
+    Setting { name: "FERRUM_CP_GRPC_ALLOWED_NODES", help: "Comma-separated data plane node IDs allowed to connect", default: Some("any node"), secret: false },
+    Setting { name: "FERRUM_CP_ROLLOUT_CANARY_GROUP", help: "Data plane group that receives each configuration version first", default: None, secret: false },
+    Setting { name: "FERRUM_CP_ROLLOUT_STAGES", help: "Comma-separated percentages of data planes updated at each rollout stage", default: Some("all at once"), secret: false },
+    Setting { name: "FERRUM_CP_ROLLOUT_STAGE_INTERVAL", help: "Seconds each rollout stage runs before the next one", default: Some("60"), secret: false },
+    Setting { name: "FERRUM_CP_GROUP_PINS", help: "JSON object of data plane groups to the configuration version they are pinned to at startup", default: None, secret: false },
+    Setting { name: "FERRUM_CP_XDS_ENABLED", help: "Serve the proxies to Envoy nodes over xDS (ADS) on the CP gRPC listener", default: Some("false"), secret: false },
+    Setting { name: "FERRUM_CP_XDS_LISTENER_ADDR", help: "Address of the HTTP listener Envoy nodes are given over xDS", default: Some("0.0.0.0:10000"), secret: false },
+    Setting { name: "FERRUM_DP_NODE_ID", help: "Node ID the data plane presents to the CP", default: Some("random"), secret: false },
+    Setting { name: "FERRUM_DP_NODE_GROUP", help: "Group the data plane belongs to for staged rollouts", default: Some("default"), secret: false },
+    Setting { name: "FERRUM_DP_HEALTH_REPORT_INTERVAL", help: "Seconds between health reports to the CP", default: Some("10"), secret: false },
+    Setting { name: "FERRUM_DP_DEGRADED_ERROR_RATE", help: "Share of 5xx responses above which the data plane reports itself degraded", default: Some("0.05"), secret: false },
+    Setting { name: "FERRUM_DP_RATE_LIMIT_SYNC_INTERVAL_MS", help: "Milliseconds between exchanges of cluster rate limiting counters with the CP, 0 disables", default: Some("0"), secret: false },
+    Setting { name: "FERRUM_DP_GRPC_TLS_CA_PATH", help: "CA bundle (PEM) the CP certificate is verified against", default: Some("system roots"), secret: false },
+    Setting { name: "FERRUM_DP_GRPC_TLS_CERT_PATH", help: "Client certificate (PEM) presented to the CP", default: None, secret: false },
+    Setting { name: "FERRUM_DP_GRPC_TLS_KEY_PATH", help: "Private key (PEM) of the client certificate", default: None, secret: false },
+    Setting { name: "FERRUM_DP_CONFIG_CACHE_PATH", help: "File the last configuration received from the CP is cached in", default: None, secret: false },
+    Setting { name: "FERRUM_DP_RECONNECT_MAX_DELAY", help: "Longest wait in seconds between attempts to reconnect to the CP", default: Some("30"), secret: false },
+    Setting { name: "FERRUM_GRPC_COMPRESSION", help: "Compression of configuration sent to data planes (none, gzip or zstd)", default: Some("gzip"), secret: false },
+    Setting { name: "FERRUM_GRPC_MAX_MESSAGE_SIZE", help: "Largest CP/DP gRPC message sent or accepted, in bytes", default: Some("16777216"), secret: false },
+    Setting { name: "FERRUM_CP_GRPC_SNAPSHOT_CHUNK_SIZE", help: "Bytes of a configuration snapshot sent per streamed chunk", default: Some("1048576"), secret: false },
+    Setting { name: "FERRUM_GRPC_KEEPALIVE_INTERVAL", help: "Seconds between HTTP/2 keepalive pings on CP/DP connections (0 disables)", default: Some("30"), secret: false },
+    Setting { name: "FERRUM_GRPC_KEEPALIVE_TIMEOUT", help: "Seconds a keepalive ping may go unanswered before the connection is closed", default: Some("10"), secret: false },
+    Setting { name: "FERRUM_CP_GRPC_SUBSCRIBER_BUFFER", help: "Configuration updates queued per data plane before it counts as lagging", default: Some("10"), secret: false },
+    Setting { name: "FERRUM_MAX_HEADER_SIZE_BYTES", help: "Maximum request header size", default: Some("16384"), secret: false },
+    Setting { name: "FERRUM_MAX_BODY_SIZE_BYTES", help: "Maximum request body size", default: Some("10485760"), secret: false },
+    Setting { name: "FERRUM_MAX_HEADER_COUNT", help: "Maximum number of request headers, 0 disables (431 beyond)", default: Some("100"), secret: false },
+    Setting { name: "FERRUM_MAX_SINGLE_HEADER_BYTES", help: "Maximum size of one request header, name and value, 0 disables (431 beyond)", default: Some("8192"), secret: false },
+    Setting { name: "FERRUM_MAX_URI_LENGTH", help: "Maximum length of a request's path and query, 0 disables (414 beyond)", default: Some("8192"), secret: false },
+    Setting { name: "FERRUM_REQUEST_TIMEOUT_MS", help: "Total time allowed for a proxied request, 0 disables (504 on expiry)", default: Some("60000"), secret: false },
+    Setting { name: "FERRUM_CLIENT_HEADER_TIMEOUT_MS", help: "Time allowed for a client to send request headers, 0 disables", default: Some("30000"), secret: false },
+    Setting { name: "FERRUM_CLIENT_BODY_TIMEOUT_MS", help: "Time allowed for a client to send the request body, 0 disables (408 on expiry)", default: Some("60000"), secret: false },
+    Setting { name: "FERRUM_MAX_CONNECTIONS", help: "Maximum concurrent connections across the HTTP/HTTPS proxy listeners, 0 is unlimited", default: Some("0"), secret: false },
+    Setting { name: "FERRUM_MAX_CONNECTIONS_PER_IP", help: "Maximum concurrent connections from a single client IP, 0 is unlimited", default: Some("0"), secret: false },
+    Setting { name: "FERRUM_MIN_TRANSFER_RATE_BYTES_PER_SEC", help: "Minimum request body upload rate after a 5s grace period, 0 disables (408 when too slow)", default: Some("0"), secret: false },
+    Setting { name: "FERRUM_ACME_ENABLED", help: "Obtain and renew listener certificates over ACME", default: Some("false"), secret: false },
+    Setting { name: "FERRUM_ACME_DIRECTORY_URL", help: "ACME directory of the certificate authority", default: Some("Let's Encrypt production"), secret: false },
+    Setting { name: "FERRUM_ACME_EMAIL", help: "Contact email registered with the ACME account", default: None, secret: false },
+    Setting { name: "FERRUM_ACME_DOMAINS", help: "Comma-separated domains to issue a certificate for", default: None, secret: false },
+    Setting { name: "FERRUM_ACME_CHALLENGE", help: "Challenge type (http-01, dns-01)", default: Some("http-01"), secret: false },
+    Setting { name: "FERRUM_ACME_DNS_HOOK", help: "Command that publishes and removes DNS-01 TXT records", default: None, secret: false },
+    Setting { name: "FERRUM_ACME_DNS_PROPAGATION_SECS", help: "Time to wait for DNS-01 records to propagate", default: Some("60"), secret: false },
+    Setting { name: "FERRUM_ACME_RENEW_BEFORE_DAYS", help: "Renew certificates this many days before they expire", default: Some("30"), secret: false },
+    Setting { name: "FERRUM_ACME_STORAGE_PATH", help: "Directory for the ACME account key, and for certificates in File mode", default: Some("./acme"), secret: false },
+    Setting { name: "FERRUM_DNS_CACHE_TTL_SECONDS", help: "Longest time DNS records are cached, whatever their TTL", default: Some("300"), secret: false },
+    Setting { name: "FERRUM_DNS_MIN_TTL_SECONDS", help: "Shortest time DNS records are cached, whatever their TTL", default: Some("5"), secret: false },
+    Setting { name: "FERRUM_DNS_OVERRIDES", help: "DNS hostname overrides, each an address or a list of addresses (JSON)", default: Some("{}"), secret: false },
+    Setting { name: "FERRUM_DNS_HOSTS_FILE", help: "File of DNS hostname overrides in /etc/hosts format", default: None, secret: false },
+    Setting { name: "FERRUM_DNS_NEGATIVE_TTL_SECONDS", help: "Seconds a failed DNS lookup is cached, doubled for each consecutive failure up to 60; 0 disables", default: Some("5"), secret: false },
+    Setting { name: "FERRUM_DNS_STALE_TTL_SECONDS", help: "Seconds expired DNS records are still served while looking them up again fails; 0 disables", default: Some("0"), secret: false },
+    Setting { name: "FERRUM_DNS_RESOLVERS", help: "Comma-separated DNS servers to use instead of the system resolver, in fallback order", default: None, secret: false },
+    Setting { name: "FERRUM_CONSUL_ADDR", help: "Consul HTTP API address for backend discovery", default: Some("http://127.0.0.1:8500"), secret: false },
+    Setting { name: "FERRUM_CONSUL_TOKEN", help: "Consul ACL token", default: None, secret: true },
+    Setting { name: "FERRUM_ETCD_ENDPOINT", help: "etcd v3 HTTP endpoint for backend discovery", default: Some("http://127.0.0.1:2379"), secret: false },
+    Setting { name: "FERRUM_METRICS_CONSUMER_LABELS", help: "Record metrics labeled by proxy name and consumer", default: Some("false"), secret: false },
+    Setting { name: "FERRUM_METRICS_PROXY_ALLOWLIST", help: "Comma-separated proxy names or IDs to record per-consumer metrics for; empty for every proxy", default: None, secret: false },
+    Setting { name: "FERRUM_METRICS_CONSUMER_ALLOWLIST", help: "Comma-separated consumer usernames or IDs to record per-consumer metrics for; empty for every consumer", default: None, secret: false },
+    Setting { name: "FERRUM_METRICS_MAX_LABEL_SETS", help: "Most proxy and consumer label sets of the per-consumer metrics; later ones are counted as __other__", default: Some("1000"), secret: false },
+    Setting { name: "FERRUM_METRICS_PORT", help: "Port of a listener serving only the Prometheus metrics, without authentication", default: None, secret: false },
+    Setting { name: "FERRUM_METRICS_PATH", help: "Path the metrics are served at without authentication", default: Some("/metrics"), secret: false },
+    Setting { name: "FERRUM_METRICS_ON_PROXY", help: "Also serve the metrics path on the proxy listeners", default: Some("false"), secret: false },
+    Setting { name: "FERRUM_METRICS_ON_ADMIN", help: "Also serve the metrics path on the admin listeners without a token", default: Some("false"), secret: false },
+    Setting { name: "FERRUM_OTLP_METRICS_ENDPOINT", help: "OTLP/HTTP metrics endpoint to export metrics to, e.g. http://collector:4318/v1/metrics", default: None, secret: false },
+    Setting { name: "FERRUM_OTLP_METRICS_INTERVAL_SECONDS", help: "Seconds between OTLP metrics exports", default: Some("60"), secret: false },
+    Setting { name: "FERRUM_OTLP_METRICS_BATCH_SIZE", help: "Most metrics sent in one OTLP export request", default: Some("1000"), secret: false },
+    Setting { name: "FERRUM_OTLP_METRICS_HEADERS", help: "Comma-separated name=value headers sent with OTLP export requests", default: None, secret: true },
+    Setting { name: "FERRUM_STATSD_ADDR", help: "host:port of a StatsD server or Datadog agent to send metrics to", default: None, secret: false },
+    Setting { name: "FERRUM_STATSD_PREFIX", help: "Prefix of the StatsD metric names", default: Some("ferrumgw"), secret: false },
+    Setting { name: "FERRUM_STATSD_FORMAT", help: "StatsD line format: statsd or dogstatsd (with tags)", default: Some("dogstatsd"), secret: false },
+    Setting { name: "FERRUM_STATSD_TAGS", help: "Comma-separated DogStatsD tags added to every metric, e.g. env:prod", default: None, secret: false },
+    Setting { name: "FERRUM_LOG_SAMPLE_ONE_IN", help: "Pass one in this many requests to the log plugins; 0 passes only errors and slow requests", default: Some("1"), secret: false },
+    Setting { name: "FERRUM_LOG_SAMPLE_ERRORS", help: "Always pass responses with a 5xx status to the log plugins", default: Some("true"), secret: false },
+    Setting { name: "FERRUM_LOG_SAMPLE_SLOW_MS", help: "Always pass requests that took at least this many milliseconds to the log plugins", default: None, secret: false },
+    Setting { name: "FERRUM_LOAD_SHED_MAX_IN_FLIGHT", help: "Requests in flight from which low priority requests are shed with 503 (0 = disabled)", default: Some("0"), secret: false },
+    Setting { name: "FERRUM_LOAD_SHED_CRITICAL_IN_FLIGHT", help: "Requests in flight from which normal priority requests are shed too (0 = disabled)", default: Some("0"), secret: false },
+    Setting { name: "FERRUM_LOAD_SHED_P99_MS", help: "p99 latency of the last minute in milliseconds from which low priority requests are shed (0 = disabled)", default: Some("0"), secret: false },
+    Setting { name: "FERRUM_LOAD_SHED_PRIORITY_HEADER", help: "Header setting the priority of a request (low, normal or high)", default: None, secret: false },
+    Setting { name: "FERRUM_LONG_LIVED_UPDATE_POLICY", help: "What happens to WebSocket connections and event streams of changed or deleted proxies (drain or keep)", default: Some("drain"), secret: false },
+    Setting { name: "FERRUM_LONG_LIVED_DRAIN_TIMEOUT_MS", help: "How long drained WebSocket connections and event streams stay open after an update", default: Some("30000"), secret: false },
+    Setting { name: "FERRUM_EGRESS_PROXY_URL", help: "HTTP CONNECT (http://) or SOCKS5 (socks5://) proxy that backend connections go through", default: None, secret: true },
+    Setting { name: "FERRUM_EGRESS_NO_PROXY", help: "Comma-separated hosts, including their subdomains, connected to directly (* for all)", default: None, secret: false },
+    Setting { name: "FERRUM_DEADLINE_HEADER", help: "Header telling non-gRPC backends the milliseconds left until the request deadline, e.g. X-Request-Timeout", default: None, secret: false },
+    Setting { name: "FERRUM_ERROR_TEMPLATES", help: "Global templates for gateway-generated errors (JSON)", default: Some("{}"), secret: false },
+    Setting { name: "FERRUM_DEFAULT_PAGINATION_LIMIT", help: "Default page size for Admin API list endpoints", default: Some("500"), secret: false },
+
+];
+
+/// Where the value of a setting came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingSource {
+    File,
+    Env,
+    Cli,
+}
+
+impl fmt::Display for SettingSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingSource::File => write!(f, "file"),
+            SettingSource::Env => write!(f, "env"),
+            SettingSource::Cli => write!(f, "cli"),
+        }
+    }
+}
+
+/// Setting values merged from the bootstrap file, the environment and the
+/// command line, in increasing order of precedence
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    values: BTreeMap<String, (String, SettingSource)>,
+}
+
+impl Settings {
+    /// The bootstrap file named by FERRUM_BOOTSTRAP_FILE, if any, overlaid by
+    /// FERRUM_* environment variables
+    pub fn from_env() -> Result<Self, EnvConfigError> {
+        Self::load(None, Vec::new())
+    }
+
+    /// Parses the command line flags of every setting and layers them over the
+    /// environment and the bootstrap file. Exits with usage on invalid flags or --help.
+    pub fn from_args<I, T>(command_name: &'static str, args: I) -> Result<Self, EnvConfigError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = command(command_name).get_matches_from(args);
+
+        let bootstrap_file = matches.get_one::<String>("config-file").map(PathBuf::from);
+        let flags = SETTINGS.iter()
+            .filter_map(|setting| {
+                matches.get_one::<String>(&setting.flag())
+                    .map(|value| (setting.name.to_string(), value.clone()))
+            })
+            .collect();
+
+        Self::load(bootstrap_file, flags)
+    }
+
+    /// Layers flags over the environment over the bootstrap file, which is the
+    /// given one or else the one named by FERRUM_BOOTSTRAP_FILE
+    pub fn load(bootstrap_file: Option<PathBuf>, flags: Vec<(String, String)>) -> Result<Self, EnvConfigError> {
+        let mut settings = Self::default();
+
+        let bootstrap_file = bootstrap_file.or_else(|| env::var(BOOTSTRAP_FILE_VAR).ok().map(PathBuf::from));
+        if let Some(path) = bootstrap_file {
+            settings.read_file(&path)?;
+        }
+
+        for (name, value) in env::vars() {
+            if name.starts_with("FERRUM_") && name != BOOTSTRAP_FILE_VAR {
+                settings.set(name, value, SettingSource::Env);
+            }
+        }
+
+        for (name, value) in flags {
+            settings.set(name, value, SettingSource::Cli);
+        }
+
+        Ok(settings)
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>, source: SettingSource) {
+        self.values.insert(name.into(), (value.into(), source));
+    }
+
+    /// The value of a setting, with the same result type as `std::env::var`
+    pub fn var(&self, name: &str) -> Result<String, env::VarError> {
+        self.values.get(name)
+            .map(|(value, _)| value.clone())
+            .ok_or(env::VarError::NotPresent)
+    }
+
+    pub fn source(&self, name: &str) -> Option<SettingSource> {
+        self.values.get(name).map(|(_, source)| *source)
+    }
+
+    /// Reads a flat YAML, JSON or TOML map of settings. Keys are setting names,
+    /// with or without the FERRUM_ prefix and in any case; lists are joined with
+    /// commas and maps are kept as JSON.
+    fn read_file(&mut self, path: &Path) -> Result<(), EnvConfigError> {
+        let file_error = |message: String| EnvConfigError::BootstrapFileError(path.display().to_string(), message);
+
+        let document = document::load(path).map_err(|e| file_error(format!("{:#}", e)))?;
+        let entries = match document.value {
+            Value::Object(entries) => entries,
+            _ => return Err(file_error("expected a map of settings".to_string())),
+        };
+
+        for (key, value) in entries {
+            let mut name = key.to_uppercase().replace('-', "_");
+            if !name.starts_with("FERRUM_") {
+                name = format!("FERRUM_{}", name);
+            }
+            if !SETTINGS.iter().any(|setting| setting.name == name) {
+                return Err(file_error(format!("unknown setting {}", key)));
+            }
+
+            let value = match value {
+                Value::Null => continue,
+                Value::String(value) => value,
+                Value::Array(items) => items.iter()
+                    .map(|item| item.as_str().map(str::to_string).unwrap_or_else(|| item.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                other => other.to_string(),
+            };
+            self.set(name, value, SettingSource::File);
+        }
+
+        Ok(())
+    }
+
+    /// The effective value and source of every setting, one per line, as
+    /// printed by `ferrumgw print-config`. Secrets are masked.
+    pub fn render(&self) -> String {
+        let mut output = String::from("# Effective settings (cli > env > file > default)\n");
+        for setting in SETTINGS {
+            let line = match self.values.get(setting.name) {
+                Some((value, source)) => {
+                    let value = if setting.secret { MASK } else { value.as_str() };
+                    format!("{}={}  # {}", setting.name, value, source)
+                },
+                None => match setting.default {
+                    Some(default) => format!("# {}  (default: {})", setting.name, default),
+                    None => format!("# {}  (not set)", setting.name),
+                },
+            };
+            output.push_str(&line);
+            output.push('\n');
+        }
+        output
+    }
+}
+
+/// The command line of the gateway: a flag per setting plus --config-file
+fn command(name: &'static str) -> Command {
+    let mut command = Command::new(name)
+        .about("Ferrum Gateway. Every option can also be set with the FERRUM_* environment variable shown, or in the bootstrap file.")
+        .version(env!("CARGO_PKG_VERSION"))
+        .arg(Arg::new("config-file")
+            .long("config-file")
+            .value_name("PATH")
+            .action(ArgAction::Set)
+            .help(format!("Bootstrap file with settings (YAML, JSON or TOML) [env: {}]", BOOTSTRAP_FILE_VAR)));
+
+    for setting in SETTINGS {
+        let mut help = format!("{} [env: {}]", setting.help, setting.name);
+        if let Some(default) = setting.default {
+            help.push_str(&format!(" [default: {}]", default));
+        }
+        command = command.arg(Arg::new(setting.flag())
+            .long(setting.flag())
+            .value_name("VALUE")
+            .action(ArgAction::Set)
+            .help(help));
+    }
+
+    command
+}
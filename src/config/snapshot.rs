@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use arc_swap::ArcSwap;
+use matchit::{InsertError, Router as MatchitRouter};
+use tracing::{trace, warn};
+
+use super::consumer_index::ConsumerIndex;
+use super::data_model::{Configuration, Consumer, PluginConfig, PluginScope, Proxy};
+use crate::proxy::tls::SniCertificates;
+
+/// Snapshot handle shared between the proxy hot path and the UpdateManager.
+/// Readers load the current snapshot without locking; updates swap in a new one.
+pub type SharedSnapshot = Arc<ArcSwap<ConfigurationSnapshot>>;
+
+/// An immutable view of the configuration together with the indexes derived
+/// from it. Snapshots are never modified once built; a configuration change
+/// produces a new snapshot which is swapped in atomically.
+pub struct ConfigurationSnapshot {
+    config: Configuration,
+    // Radix tree mapping listen paths to indexes into config.proxies
+    route_tree: MatchitRouter<usize>,
+    route_count: usize,
+    proxies_by_id: HashMap<String, usize>,
+    plugin_configs_by_id: HashMap<String, usize>,
+    // Enabled global plugin configs, which run for every proxy of their workspace
+    global_plugin_configs: Vec<usize>,
+    // Shared with request contexts so auth plugins can resolve consumers;
+    // one index per workspace so consumers only authenticate to their own proxies
+    consumer_indexes: HashMap<String, Arc<ConsumerIndex>>,
+    // Listener certificates keyed by SNI hostname
+    sni_certificates: SniCertificates,
+}
+
+impl ConfigurationSnapshot {
+    /// Builds a snapshot and its lookup indexes from a configuration
+    pub fn build(config: Configuration) -> Self {
+        let mut route_tree = MatchitRouter::new();
+        let mut route_count = 0;
+        let mut proxies_by_id = HashMap::with_capacity(config.proxies.len());
+        let mut proxies_by_pattern: HashMap<String, usize> = HashMap::new();
+
+        for (index, proxy) in config.proxies.iter().enumerate() {
+            proxies_by_id.insert(proxy.id.clone(), index);
+
+            // Disabled proxies keep their configuration but get no traffic
+            if !proxy.enabled {
+                trace!("Skipping route of disabled proxy {}", proxy.id);
+                continue;
+            }
+
+            let path = Self::route_pattern(&proxy.listen_path);
+            match route_tree.insert(path.clone(), index) {
+                Ok(_) => {
+                    route_count += 1;
+                    proxies_by_pattern.insert(path, index);
+                    trace!("Added route to tree: {} -> {}", proxy.listen_path, proxy.id);
+                },
+                // The first proxy keeps the route; name it so the conflict can be fixed
+                Err(InsertError::Conflict { with }) => match proxies_by_pattern.get(&with).map(|&other| &config.proxies[other]) {
+                    Some(other) => warn!(
+                        "Failed to add route for proxy {}: listen_path '{}' conflicts with proxy {} ('{}')",
+                        proxy.id, proxy.listen_path, other.id, other.listen_path
+                    ),
+                    None => warn!("Failed to add route for proxy {}: listen_path '{}' conflicts with '{}'", proxy.id, proxy.listen_path, with),
+                },
+                Err(e) => warn!("Failed to add route for proxy {}: {}", proxy.id, e),
+            }
+        }
+
+        let plugin_configs_by_id = config.plugin_configs.iter()
+            .enumerate()
+            .map(|(index, plugin_config)| (plugin_config.id.clone(), index))
+            .collect();
+        let global_plugin_configs = config.plugin_configs.iter()
+            .enumerate()
+            .filter(|(_, plugin_config)| plugin_config.scope == PluginScope::Global && plugin_config.enabled)
+            .map(|(index, _)| index)
+            .collect();
+
+        let mut consumers_by_workspace: HashMap<&str, Vec<Consumer>> = HashMap::new();
+        for consumer in &config.consumers {
+            consumers_by_workspace.entry(consumer.workspace.as_str())
+                .or_default()
+                .push(consumer.clone());
+        }
+        let consumer_indexes = consumers_by_workspace.into_iter()
+            .map(|(workspace, consumers)| (workspace.to_string(), Arc::new(ConsumerIndex::build(&consumers))))
+            .collect();
+        let sni_certificates = SniCertificates::build(&config.certificates, &config.snis);
+
+        Self {
+            config,
+            route_tree,
+            route_count,
+            proxies_by_id,
+            plugin_configs_by_id,
+            global_plugin_configs,
+            consumer_indexes,
+            sni_certificates,
+        }
+    }
+
+    /// Creates a shared snapshot handle initialised from a configuration
+    pub fn shared(config: Configuration) -> SharedSnapshot {
+        Arc::new(ArcSwap::from_pointee(Self::build(config)))
+    }
+
+    /// The configuration this snapshot was built from
+    pub fn config(&self) -> &Configuration {
+        &self.config
+    }
+
+    /// Finds the proxy whose listen_path matches the request path
+    pub fn route(&self, path: &str) -> Option<&Proxy> {
+        self.route_tree.at(path)
+            .ok()
+            .and_then(|route_match| self.config.proxies.get(*route_match.value))
+    }
+
+    pub fn proxy_by_id(&self, id: &str) -> Option<&Proxy> {
+        self.proxies_by_id.get(id).and_then(|&index| self.config.proxies.get(index))
+    }
+
+    pub fn plugin_config_by_id(&self, id: &str) -> Option<&PluginConfig> {
+        self.plugin_configs_by_id.get(id).and_then(|&index| self.config.plugin_configs.get(index))
+    }
+
+    /// The enabled global plugin configs of a workspace, in configuration order
+    pub fn global_plugin_configs<'a>(&'a self, workspace: &'a str) -> impl Iterator<Item = &'a PluginConfig> + 'a {
+        self.global_plugin_configs.iter()
+            .filter_map(|&index| self.config.plugin_configs.get(index))
+            .filter(move |plugin_config| plugin_config.workspace == workspace)
+    }
+
+    /// Lookup indexes over the consumers of a workspace
+    pub fn consumer_index(&self, workspace: &str) -> Arc<ConsumerIndex> {
+        self.consumer_indexes.get(workspace)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Listener certificates to select from during TLS handshakes
+    pub fn sni_certificates(&self) -> &SniCertificates {
+        &self.sni_certificates
+    }
+
+    /// Number of routes in the radix tree
+    pub fn route_count(&self) -> usize {
+        self.route_count
+    }
+
+    /// Turns a listen_path into a pattern that also captures all subpaths
+    pub(crate) fn route_pattern(listen_path: &str) -> String {
+        let mut path = listen_path.to_string();
+        if !path.ends_with('*') {
+            if path.ends_with('/') {
+                path.push('*');
+            } else {
+                path.push_str("/*");
+            }
+        }
+        path
+    }
+}
+
+impl Default for ConfigurationSnapshot {
+    fn default() -> Self {
+        Self::build(Configuration::default())
+    }
+}
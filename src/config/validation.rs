@@ -0,0 +1,555 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::time::Duration;
+use matchit::{InsertError, Router as MatchitRouter};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::net::lookup_host;
+use tokio::time::timeout;
+
+use super::data_model::{listen_path_problem, ApiVersionSource, Configuration, PluginScope, Proxy};
+use super::file_config;
+use super::snapshot::ConfigurationSnapshot;
+use crate::dns::upstream;
+use crate::plugins::PluginRegistry;
+use crate::proxy::egress::EgressRoute;
+
+/// How long a backend hostname may take to resolve before it is reported
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// One problem found in a configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationIssue {
+    /// Machine readable kind of problem, such as `duplicate_listen_path`
+    pub code: &'static str,
+    /// Field the problem was found at, such as `proxies[1].listen_path`
+    pub path: String,
+    pub message: String,
+}
+
+/// Outcome of validating a configuration. Errors make the configuration
+/// unusable; warnings point at things that will probably not work as intended.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub errors: Vec<ValidationIssue>,
+    pub warnings: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    fn error(&mut self, code: &'static str, path: String, message: String) {
+        self.errors.push(ValidationIssue { code, path, message });
+        self.valid = false;
+    }
+
+    fn warning(&mut self, code: &'static str, path: String, message: String) {
+        self.warnings.push(ValidationIssue { code, path, message });
+    }
+}
+
+/// Validates a parsed document: the schema first, then the checks of `check`.
+/// The configuration is returned when the document matches the schema.
+pub fn check_document(value: Value) -> (ValidationReport, Option<Configuration>) {
+    let mut report = ValidationReport { valid: true, ..Default::default() };
+
+    let violations = file_config::schema_violations(&value);
+    if !violations.is_empty() {
+        for (path, message) in violations {
+            report.error("schema", path, message);
+        }
+        return (report, None);
+    }
+
+    match file_config::from_document(value) {
+        Ok(config) => {
+            let checked = check(&config);
+            report.errors.extend(checked.errors);
+            report.warnings.extend(checked.warnings);
+            report.valid = report.errors.is_empty();
+            (report, Some(config))
+        },
+        Err(e) => {
+            report.error("schema", "(document)".to_string(), format!("{:#}", e));
+            (report, None)
+        },
+    }
+}
+
+/// Checks what the schema cannot express: unique IDs and routes, references
+/// between entities, and plugin names and settings. Nothing is resolved or applied.
+pub fn check(config: &Configuration) -> ValidationReport {
+    let mut report = ValidationReport { valid: true, ..Default::default() };
+
+    check_unique_ids(&mut report, "proxies", "proxy", config.proxies.iter().map(|p| p.id.as_str()));
+    check_unique_ids(&mut report, "consumers", "consumer", config.consumers.iter().map(|c| c.id.as_str()));
+    check_unique_ids(&mut report, "plugin_configs", "plugin config", config.plugin_configs.iter().map(|p| p.id.as_str()));
+    check_unique_ids(&mut report, "certificates", "certificate", config.certificates.iter().map(|c| c.id.as_str()));
+    check_unique_ids(&mut report, "snis", "SNI", config.snis.iter().map(|s| s.id.as_str()));
+
+    check_tags(&mut report, "proxies", config.proxies.iter().map(|p| p.tags.as_slice()));
+    check_tags(&mut report, "consumers", config.consumers.iter().map(|c| c.tags.as_slice()));
+    check_tags(&mut report, "plugin_configs", config.plugin_configs.iter().map(|pc| pc.tags.as_slice()));
+
+    check_routes(&mut report, config);
+    check_plugins(&mut report, config);
+    check_dns_resolvers(&mut report, config);
+    check_api_versions(&mut report, config);
+    check_backend_keepalive(&mut report, config);
+    check_upstream_hosts(&mut report, config);
+    check_egress_proxies(&mut report, config);
+
+    for (index, sni) in config.snis.iter().enumerate() {
+        if !config.certificates.iter().any(|c| c.id == sni.certificate_id) {
+            report.error(
+                "unknown_certificate",
+                format!("snis[{}].certificate_id", index),
+                format!("SNI '{}' references unknown certificate '{}'", sni.name, sni.certificate_id),
+            );
+        }
+    }
+
+    report
+}
+
+fn check_unique_ids<'a>(
+    report: &mut ValidationReport,
+    collection: &str,
+    kind: &str,
+    ids: impl Iterator<Item = &'a str>,
+) {
+    let mut seen = HashSet::new();
+    for (index, id) in ids.enumerate() {
+        if !seen.insert(id) {
+            report.error(
+                "duplicate_id",
+                format!("{}[{}].id", collection, index),
+                format!("Duplicate {} ID '{}'", kind, id),
+            );
+        }
+    }
+}
+
+/// Tags are matched exactly and passed as comma-separated lists, so they must
+/// not be empty, padded with whitespace or contain commas
+fn check_tags<'a>(report: &mut ValidationReport, collection: &str, tags: impl Iterator<Item = &'a [String]>) {
+    for (index, tags) in tags.enumerate() {
+        for (tag_index, tag) in tags.iter().enumerate() {
+            if tag.is_empty() || tag.trim() != tag || tag.contains(',') {
+                report.error(
+                    "invalid_tag",
+                    format!("{}[{}].tags[{}]", collection, index, tag_index),
+                    format!("Tag '{}' must be non-empty, without commas or surrounding whitespace", tag),
+                );
+            }
+        }
+    }
+}
+
+/// Listen paths must be unique and must fit together in the route tree the
+/// proxy builds, which for instance rejects `/api` next to `/api/`
+fn check_routes(report: &mut ValidationReport, config: &Configuration) {
+    let mut route_tree = MatchitRouter::new();
+    let mut patterns: HashMap<String, usize> = HashMap::new();
+    let mut listen_paths: HashMap<&str, usize> = HashMap::new();
+
+    for (index, proxy) in config.proxies.iter().enumerate() {
+        let path = format!("proxies[{}].listen_path", index);
+
+        if let Some(problem) = listen_path_problem(&proxy.listen_path) {
+            report.error(
+                "invalid_listen_path",
+                path.clone(),
+                format!("listen_path '{}' of proxy '{}' {}", proxy.listen_path, proxy.id, problem),
+            );
+            // Paths that cannot be routed at all are not compared with the others
+            if !proxy.listen_path.starts_with('/') {
+                continue;
+            }
+        }
+
+        if let Some(&first) = listen_paths.get(proxy.listen_path.as_str()) {
+            report.error(
+                "duplicate_listen_path",
+                path,
+                format!(
+                    "listen_path '{}' of proxy '{}' is already used by proxy '{}'",
+                    proxy.listen_path, proxy.id, config.proxies[first].id
+                ),
+            );
+            continue;
+        }
+        listen_paths.insert(&proxy.listen_path, index);
+
+        let pattern = ConfigurationSnapshot::route_pattern(&proxy.listen_path);
+        match route_tree.insert(pattern.clone(), index) {
+            Ok(_) => {
+                patterns.insert(pattern, index);
+            },
+            Err(InsertError::Conflict { with }) => {
+                let other = patterns.get(&with)
+                    .map(|&other| format!("proxy '{}' ('{}')", config.proxies[other].id, config.proxies[other].listen_path))
+                    .unwrap_or(with);
+                report.error(
+                    "route_conflict",
+                    path,
+                    format!("listen_path '{}' of proxy '{}' conflicts with {}", proxy.listen_path, proxy.id, other),
+                );
+            },
+            Err(e) => {
+                report.error(
+                    "route_conflict",
+                    path,
+                    format!("listen_path '{}' of proxy '{}' cannot be routed: {}", proxy.listen_path, proxy.id, e),
+                );
+            },
+        }
+    }
+
+    check_workspace_routes(report, config);
+}
+
+/// Only the listen path checks of `check`: format, duplicates, conflicts in
+/// the route tree and overlaps between workspaces
+pub fn check_listen_paths(config: &Configuration) -> ValidationReport {
+    let mut report = ValidationReport { valid: true, ..Default::default() };
+    check_routes(&mut report, config);
+    report
+}
+
+/// Each workspace owns the paths under its listen paths: a proxy may not be
+/// nested under, or be the parent of, a listen path of another workspace
+fn check_workspace_routes(report: &mut ValidationReport, config: &Configuration) {
+    let mut owners: HashMap<&str, usize> = HashMap::new();
+    for (index, proxy) in config.proxies.iter().enumerate() {
+        owners.entry(route_prefix(&proxy.listen_path)).or_insert(index);
+    }
+
+    for (index, proxy) in config.proxies.iter().enumerate() {
+        let prefix = route_prefix(&proxy.listen_path);
+        let ancestors = prefix.match_indices('/').map(|(position, _)| &prefix[..position]);
+
+        let owner = ancestors.chain(std::iter::once(prefix))
+            .filter_map(|ancestor| owners.get(ancestor))
+            .map(|&other| &config.proxies[other])
+            .find(|other| other.workspace != proxy.workspace);
+
+        if let Some(other) = owner {
+            report.error(
+                "workspace_route_overlap",
+                format!("proxies[{}].listen_path", index),
+                format!(
+                    "listen_path '{}' of proxy '{}' (workspace '{}') overlaps '{}' of proxy '{}' in workspace '{}'",
+                    proxy.listen_path, proxy.id, proxy.workspace, other.listen_path, other.id, other.workspace
+                ),
+            );
+        }
+    }
+}
+
+/// Finds another proxy that routes the same paths as the given proxy, for
+/// checks on a single proxy such as Admin API writes
+pub fn listen_path_owner<'a>(config: &'a Configuration, proxy: &Proxy) -> Option<&'a Proxy> {
+    let prefix = route_prefix(&proxy.listen_path);
+    config.proxies.iter()
+        .find(|other| other.id != proxy.id && route_prefix(&other.listen_path) == prefix)
+}
+
+/// Finds a proxy of another workspace whose listen path overlaps the given
+/// proxy's, for checks on a single proxy such as Admin API writes
+pub fn workspace_route_overlap<'a>(config: &'a Configuration, proxy: &Proxy) -> Option<&'a Proxy> {
+    let prefix = route_prefix(&proxy.listen_path);
+    config.proxies.iter()
+        .filter(|other| other.id != proxy.id && other.workspace != proxy.workspace)
+        .find(|other| {
+            let other_prefix = route_prefix(&other.listen_path);
+            is_path_prefix(prefix, other_prefix) || is_path_prefix(other_prefix, prefix)
+        })
+}
+
+/// A listen path without its trailing slash, so `/api` and `/api/` compare equal
+/// and the root path becomes the empty string
+fn route_prefix(listen_path: &str) -> &str {
+    listen_path.trim_end_matches('*').trim_end_matches('/')
+}
+
+/// Whether `path` is `prefix` or lies under it, at a segment boundary
+fn is_path_prefix(prefix: &str, path: &str) -> bool {
+    path.strip_prefix(prefix).map_or(false, |rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Plugin configs must name a known plugin with settings it accepts, and proxies
+/// must only refer to plugin configs that exist
+/// DNS resolvers must be usable, and proxies sharing a backend host must list
+/// the same ones, since its addresses are cached once per hostname
+fn check_dns_resolvers(report: &mut ValidationReport, config: &Configuration) {
+    let mut by_host: HashMap<&str, &Proxy> = HashMap::new();
+
+    for (index, proxy) in config.proxies.iter().enumerate() {
+        for (resolver_index, resolver) in proxy.dns_resolvers.iter().enumerate() {
+            if let Err(e) = upstream::check(resolver) {
+                report.error(
+                    "invalid_dns_resolver",
+                    format!("proxies[{}].dns_resolvers[{}]", index, resolver_index),
+                    format!("{:#}", e),
+                );
+            }
+        }
+
+        if proxy.dns_override.is_some() {
+            continue;
+        }
+        match by_host.get(proxy.backend_host.as_str()) {
+            Some(other) if other.dns_resolvers != proxy.dns_resolvers => {
+                report.error(
+                    "conflicting_dns_resolvers",
+                    format!("proxies[{}].dns_resolvers", index),
+                    format!(
+                        "Proxy '{}' resolves backend host '{}' with other DNS resolvers than proxy '{}'",
+                        proxy.id, proxy.backend_host, other.id
+                    ),
+                );
+            },
+            Some(_) => {},
+            None => {
+                by_host.insert(&proxy.backend_host, proxy);
+            },
+        }
+    }
+}
+
+/// API versions must be named once, without slashes for the path source, the
+/// default version must be one of them and sunsets must be HTTP dates
+/// A connection that may carry no request at all would never be used
+fn check_backend_keepalive(report: &mut ValidationReport, config: &Configuration) {
+    for (index, proxy) in config.proxies.iter().enumerate() {
+        let max_requests = proxy.backend_keepalive.as_ref().and_then(|k| k.max_requests_per_connection);
+        if max_requests == Some(0) {
+            report.error(
+                "invalid_backend_keepalive",
+                format!("proxies[{}].backend_keepalive.max_requests_per_connection", index),
+                format!("Proxy '{}' must allow at least one request per backend connection", proxy.id),
+            );
+        }
+    }
+}
+
+/// An upstream_host is a host, optionally with a port, in which only the
+/// `{backend_host}` and `{backend_port}` placeholders are filled in
+fn check_upstream_hosts(report: &mut ValidationReport, config: &Configuration) {
+    for (index, proxy) in config.proxies.iter().enumerate() {
+        let template = match &proxy.upstream_host {
+            Some(template) => template,
+            None => continue,
+        };
+
+        let literal = template.replace("{backend_host}", "").replace("{backend_port}", "");
+        let valid = !template.is_empty()
+            && literal.chars().all(|c| c.is_ascii_alphanumeric() || "-._:[]".contains(c));
+        if !valid {
+            report.error(
+                "invalid_upstream_host",
+                format!("proxies[{}].upstream_host", index),
+                format!(
+                    "Proxy '{}' has an invalid upstream_host '{}'; expected a host with an optional port, using only the {{backend_host}} and {{backend_port}} placeholders",
+                    proxy.id, template
+                ),
+            );
+        }
+    }
+}
+
+/// An egress_proxy is `direct` or an http:// or socks5:// proxy URL
+fn check_egress_proxies(report: &mut ValidationReport, config: &Configuration) {
+    for (index, proxy) in config.proxies.iter().enumerate() {
+        if let Err(e) = EgressRoute::parse(proxy.egress_proxy.as_deref()) {
+            report.error(
+                "invalid_egress_proxy",
+                format!("proxies[{}].egress_proxy", index),
+                format!("Proxy '{}' has an invalid egress_proxy: {}", proxy.id, e),
+            );
+        }
+    }
+}
+
+fn check_api_versions(report: &mut ValidationReport, config: &Configuration) {
+    for (index, proxy) in config.proxies.iter().enumerate() {
+        let versioning = match &proxy.api_versioning {
+            Some(versioning) => versioning,
+            None => continue,
+        };
+
+        let mut seen = HashSet::new();
+        for (version_index, version) in versioning.versions.iter().enumerate() {
+            let field = format!("proxies[{}].api_versioning.versions[{}]", index, version_index);
+            if version.version.is_empty() || (versioning.source == ApiVersionSource::Path && version.version.contains('/')) {
+                report.error(
+                    "invalid_api_version",
+                    format!("{}.version", field),
+                    format!("Proxy '{}' has an invalid API version '{}'", proxy.id, version.version),
+                );
+            } else if !seen.insert(version.version.as_str()) {
+                report.error(
+                    "duplicate_api_version",
+                    format!("{}.version", field),
+                    format!("Proxy '{}' lists API version '{}' more than once", proxy.id, version.version),
+                );
+            }
+            if let Some(sunset) = &version.sunset {
+                if chrono::DateTime::parse_from_rfc2822(sunset).is_err() {
+                    report.error(
+                        "invalid_api_version_sunset",
+                        format!("{}.sunset", field),
+                        format!("Sunset '{}' is not an HTTP date, e.g. 'Sat, 01 Nov 2025 00:00:00 GMT'", sunset),
+                    );
+                }
+            }
+        }
+
+        if let Some(default_version) = &versioning.default_version {
+            if !versioning.versions.iter().any(|version| &version.version == default_version) {
+                report.error(
+                    "unknown_api_version",
+                    format!("proxies[{}].api_versioning.default_version", index),
+                    format!("Proxy '{}' has no API version '{}'", proxy.id, default_version),
+                );
+            }
+        }
+    }
+}
+
+fn check_plugins(report: &mut ValidationReport, config: &Configuration) {
+    let registry = PluginRegistry::new();
+    let known = registry.available_plugins();
+
+    for (index, plugin_config) in config.plugin_configs.iter().enumerate() {
+        if !known.contains(&plugin_config.plugin_name) {
+            report.error(
+                "unknown_plugin",
+                format!("plugin_configs[{}].plugin_name", index),
+                format!("Plugin config '{}' uses unknown plugin '{}'", plugin_config.id, plugin_config.plugin_name),
+            );
+            continue;
+        }
+
+        if let Err(e) = registry.create_plugin(&plugin_config.plugin_name, plugin_config.config.clone()) {
+            report.error(
+                "invalid_plugin_config",
+                format!("plugin_configs[{}].config", index),
+                format!("Plugin config '{}': {:#}", plugin_config.id, e),
+            );
+        }
+
+        if let Some(proxy) = plugin_config.proxy_id.as_ref().and_then(|id| config.proxies.iter().find(|p| &p.id == id)) {
+            if proxy.workspace != plugin_config.workspace {
+                report.error(
+                    "cross_workspace_plugin_config",
+                    format!("plugin_configs[{}].proxy_id", index),
+                    format!(
+                        "Plugin config '{}' (workspace '{}') is scoped to proxy '{}' of workspace '{}'",
+                        plugin_config.id, plugin_config.workspace, proxy.id, proxy.workspace
+                    ),
+                );
+            }
+        }
+
+        match (&plugin_config.scope, &plugin_config.proxy_id) {
+            (PluginScope::Proxy, Some(proxy_id)) if !config.proxies.iter().any(|p| &p.id == proxy_id) => {
+                report.error(
+                    "unknown_proxy",
+                    format!("plugin_configs[{}].proxy_id", index),
+                    format!("Plugin config '{}' is scoped to unknown proxy '{}'", plugin_config.id, proxy_id),
+                );
+            },
+            (PluginScope::Proxy, None) => {
+                report.warning(
+                    "missing_proxy_id",
+                    format!("plugin_configs[{}].proxy_id", index),
+                    format!("Plugin config '{}' has proxy scope but no proxy_id", plugin_config.id),
+                );
+            },
+            _ => {},
+        }
+    }
+
+    for (proxy_index, proxy) in config.proxies.iter().enumerate() {
+        for (plugin_index, association) in proxy.plugins.iter().enumerate() {
+            let path = format!("proxies[{}].plugins[{}]", proxy_index, plugin_index);
+            let plugin_config = config.plugin_configs.iter().find(|pc| pc.id == association.plugin_config_id);
+
+            match (plugin_config, &association.embedded_config) {
+                (None, None) => {
+                    report.error(
+                        "unknown_plugin_config",
+                        format!("{}.plugin_config_id", path),
+                        format!("Proxy '{}' references unknown plugin config '{}'", proxy.id, association.plugin_config_id),
+                    );
+                },
+                (Some(plugin_config), _) if plugin_config.workspace != proxy.workspace => {
+                    report.error(
+                        "cross_workspace_plugin_config",
+                        format!("{}.plugin_config_id", path),
+                        format!(
+                            "Proxy '{}' (workspace '{}') uses plugin config '{}' of workspace '{}'",
+                            proxy.id, proxy.workspace, plugin_config.id, plugin_config.workspace
+                        ),
+                    );
+                },
+                (Some(plugin_config), Some(embedded)) => {
+                    if known.contains(&plugin_config.plugin_name) {
+                        if let Err(e) = registry.create_plugin(&plugin_config.plugin_name, embedded.clone()) {
+                            report.error(
+                                "invalid_plugin_config",
+                                format!("{}.embedded_config", path),
+                                format!("Proxy '{}': {:#}", proxy.id, e),
+                            );
+                        }
+                    }
+                },
+                (Some(plugin_config), None) if !plugin_config.enabled => {
+                    report.warning(
+                        "disabled_plugin",
+                        format!("{}.plugin_config_id", path),
+                        format!("Proxy '{}' uses disabled plugin config '{}'", proxy.id, plugin_config.id),
+                    );
+                },
+                _ => {},
+            }
+        }
+    }
+}
+
+/// Resolves every backend hostname and warns about those that do not resolve.
+/// Proxies with a dns_override, dns_resolvers, backend_discovery or an IP address backend are skipped. A lookup
+/// failure is only a warning since the name may resolve where the gateway runs.
+pub async fn check_hostnames(report: &mut ValidationReport, config: &Configuration) {
+    let mut hostnames: Vec<&str> = config.proxies.iter()
+        .filter(|p| p.dns_override.is_none() && p.dns_resolvers.is_empty() && p.backend_discovery.is_none())
+        .filter(|p| p.backend_host.parse::<IpAddr>().is_err())
+        .map(|p| p.backend_host.as_str())
+        .collect();
+    hostnames.sort();
+    hostnames.dedup();
+
+    let lookups = hostnames.iter().map(|hostname| async move {
+        let resolved = matches!(
+            timeout(RESOLVE_TIMEOUT, lookup_host(format!("{}:0", hostname))).await,
+            Ok(Ok(mut addrs)) if addrs.next().is_some()
+        );
+        (*hostname, resolved)
+    });
+    let unresolved: HashSet<&str> = futures::future::join_all(lookups).await
+        .into_iter()
+        .filter(|(_, resolved)| !resolved)
+        .map(|(hostname, _)| hostname)
+        .collect();
+
+    for (index, proxy) in config.proxies.iter().enumerate() {
+        if unresolved.contains(proxy.backend_host.as_str()) && proxy.dns_override.is_none() && proxy.backend_discovery.is_none() {
+            report.warning(
+                "unresolved_hostname",
+                format!("proxies[{}].backend_host", index),
+                format!("Backend host '{}' of proxy '{}' does not resolve", proxy.backend_host, proxy.id),
+            );
+        }
+    }
+}
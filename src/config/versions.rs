@@ -0,0 +1,38 @@
+use anyhow::{Result, Context};
+
+use super::declarative::{self, ConfigDiff};
+use crate::database::DatabaseClient;
+
+/// Records the configuration now in the database as a new version and returns
+/// its ID. Nothing is recorded when it does not differ from the latest version,
+/// so requests that changed nothing leave no trace in the history.
+pub async fn record(db_client: &DatabaseClient, author: &str, description: &str, retain: u64) -> Result<Option<i64>> {
+    let config = db_client.load_full_configuration().await
+        .context("Failed to load the configuration to record")?;
+
+    if let Some(latest) = db_client.get_config_version(None).await? {
+        if latest.config.map_or(false, |previous| ConfigDiff::compute(&previous, &config).is_empty()) {
+            return Ok(None);
+        }
+    }
+
+    let document = serde_json::to_string(&declarative::export(&config))?;
+    let id = db_client.insert_config_version(author, description, &document, retain).await?;
+    Ok(Some(id))
+}
+
+/// Makes the database match a recorded version and returns what changed, or
+/// None when the version does not exist
+pub async fn rollback(db_client: &DatabaseClient, version_id: i64) -> Result<Option<ConfigDiff>> {
+    let version = match db_client.get_config_version(Some(version_id)).await? {
+        Some(version) => version,
+        None => return Ok(None),
+    };
+    let desired = version.config.unwrap_or_default();
+
+    let current = db_client.load_full_configuration().await
+        .context("Failed to load the current configuration")?;
+    let diff = declarative::apply(db_client, &current, &desired).await
+        .context(format!("Failed to roll back to version {}", version_id))?;
+    Ok(Some(diff))
+}
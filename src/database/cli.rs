@@ -0,0 +1,94 @@
+use anyhow::{Result, Context, bail};
+use clap::{Parser, Subcommand};
+
+use super::{DatabaseClient, DatabaseOptions, DatabaseType};
+use super::migrations::SchemaStatus;
+use crate::config::env_config::EnvConfig;
+use crate::config::settings::Settings;
+
+/// Creates or upgrades the database schema used by Database, Control Plane and
+/// Hybrid modes.
+#[derive(Debug, Parser)]
+#[command(name = "ferrumgw migrate")]
+pub struct MigrateArgs {
+    /// Database type: postgres, mysql, sqlite or mongodb
+    #[arg(long, env = "FERRUM_DB_TYPE", global = true)]
+    pub db_type: Option<String>,
+
+    /// Database connection URL
+    #[arg(long, env = "FERRUM_DB_URL", global = true, hide_env_values = true)]
+    pub db_url: Option<String>,
+
+    /// Applies the pending migrations when no command is given
+    #[command(subcommand)]
+    pub command: Option<MigrateCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateCommand {
+    /// Apply every pending migration
+    Up,
+    /// List the migrations and whether they are applied, without changing anything
+    Status,
+    /// Mark the migrations up to a version as applied without running them, for
+    /// databases set up by hand from the SQL files
+    Baseline {
+        version: i64,
+    },
+    /// Hash the plaintext API keys, passwords and client secrets of consumers
+    /// stored before credentials were hashed at write time
+    HashCredentials,
+}
+
+pub async fn run(args: MigrateArgs) -> Result<()> {
+    let db_client = connect(args.db_type.as_deref(), args.db_url.as_deref()).await?;
+
+    let status = match args.command.unwrap_or(MigrateCommand::Up) {
+        MigrateCommand::Up => db_client.migrate().await?,
+        MigrateCommand::Status => db_client.schema_status().await?,
+        MigrateCommand::Baseline { version } => db_client.baseline(version).await?,
+        MigrateCommand::HashCredentials => {
+            let rewritten = db_client.hash_stored_credentials().await?;
+            println!(
+                "Hashed the credentials of {} consumers with {}",
+                rewritten, db_client.password_hashing()
+            );
+            return Ok(());
+        },
+    };
+
+    print_status(&status);
+    Ok(())
+}
+
+fn print_status(status: &SchemaStatus) {
+    for migration in &status.migrations {
+        let state = if migration.applied { "applied" } else { "pending" };
+        println!("{:>4}  {:<8} {}", migration.version, state, migration.description);
+    }
+    match status.current_version {
+        Some(current) => println!("Schema version {} (latest {})", current, status.latest_version),
+        None => println!("No migrations applied (latest {})", status.latest_version),
+    }
+}
+
+/// Connects to the database named by `--db-type`/`--db-url` or their environment variables
+pub async fn connect(db_type: Option<&str>, db_url: Option<&str>) -> Result<DatabaseClient> {
+    let db_type = match db_type {
+        Some("postgres") => DatabaseType::Postgres,
+        Some("mysql") => DatabaseType::MySQL,
+        Some("sqlite") => DatabaseType::SQLite,
+        Some("mongodb") => DatabaseType::MongoDB,
+        Some(other) => bail!("Unsupported database type '{}', expected postgres, mysql, sqlite or mongodb", other),
+        None => bail!("--db-type or FERRUM_DB_TYPE is required"),
+    };
+    let db_url = db_url.context("--db-url or FERRUM_DB_URL is required")?;
+
+    // Pool and TLS settings come from the environment, as for the gateway itself
+    let settings = Settings::from_env()?;
+    let mut env_config = EnvConfig::default();
+    env_config.read_database_connection_settings(&settings)?;
+
+    DatabaseClient::with_options(db_type, db_url, &DatabaseOptions::from_env_config(&env_config)).await
+        .context("Failed to create database client")
+}
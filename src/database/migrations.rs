@@ -0,0 +1,217 @@
+//! Schema migrations for Ferrum Gateway
+
+use anyhow::{Result, Context, bail};
+use serde::Serialize;
+use sqlx::migrate::{Migrate, Migrator};
+use tracing::{info, warn};
+
+use super::{mongo, DatabaseClient, DatabaseType, DbPool};
+
+static POSTGRES_MIGRATIONS: Migrator = sqlx::migrate!("./migrations/postgres");
+static MYSQL_MIGRATIONS: Migrator = sqlx::migrate!("./migrations/mysql");
+static SQLITE_MIGRATIONS: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+/// One embedded migration and whether the database has it
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationState {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+}
+
+/// Where the database schema stands compared to the migrations in this binary
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaStatus {
+    /// Highest applied version, None for a database that was never migrated
+    pub current_version: Option<i64>,
+    /// Highest version this binary knows
+    pub latest_version: i64,
+    pub migrations: Vec<MigrationState>,
+}
+
+impl SchemaStatus {
+    pub fn pending(&self) -> impl Iterator<Item = &MigrationState> {
+        self.migrations.iter().filter(|migration| !migration.applied)
+    }
+
+    pub fn is_up_to_date(&self) -> bool {
+        self.pending().next().is_none()
+    }
+
+    /// The database was migrated by a newer version of the gateway
+    pub fn is_ahead(&self) -> bool {
+        self.current_version.map_or(false, |current| current > self.latest_version)
+    }
+}
+
+impl DatabaseClient {
+    fn migrator(&self) -> &'static Migrator {
+        match self.db_type {
+            DatabaseType::Postgres => &POSTGRES_MIGRATIONS,
+            DatabaseType::MySQL => &MYSQL_MIGRATIONS,
+            DatabaseType::SQLite => &SQLITE_MIGRATIONS,
+            DatabaseType::MongoDB => unreachable!("MongoDB has no SQL migrations"),
+        }
+    }
+
+    /// Applies every pending migration
+    pub async fn migrate(&self) -> Result<SchemaStatus> {
+        let result = match &*self.pool {
+            DbPool::Postgres(pool) => self.migrator().run(pool).await,
+            DbPool::MySQL(pool) => self.migrator().run(pool).await,
+            DbPool::SQLite(pool) => self.migrator().run(pool).await,
+            DbPool::MongoDB(pool) => {
+                mongo::ensure_schema(pool).await?;
+                return self.schema_status().await;
+            },
+        };
+        result.context("Failed to apply database migrations")?;
+
+        self.schema_status().await
+    }
+
+    /// Compares the applied migrations with those embedded in the binary
+    pub async fn schema_status(&self) -> Result<SchemaStatus> {
+        let applied = self.applied_versions().await?;
+
+        if let DbPool::MongoDB(_) = &*self.pool {
+            return Ok(SchemaStatus {
+                current_version: applied.iter().max().copied(),
+                latest_version: mongo::SCHEMA_VERSION,
+                migrations: vec![MigrationState {
+                    version: mongo::SCHEMA_VERSION,
+                    description: "indexes".to_string(),
+                    applied: applied.iter().any(|&version| version >= mongo::SCHEMA_VERSION),
+                }],
+            });
+        }
+
+        let migrations: Vec<MigrationState> = self.migrator().iter()
+            .map(|migration| MigrationState {
+                version: migration.version,
+                description: migration.description.to_string(),
+                applied: applied.contains(&migration.version),
+            })
+            .collect();
+
+        Ok(SchemaStatus {
+            current_version: applied.iter().max().copied(),
+            latest_version: migrations.iter().map(|m| m.version).max().unwrap_or(0),
+            migrations,
+        })
+    }
+
+    /// Records the migrations up to `version` as applied without running them,
+    /// for databases whose schema was created by hand before migrations existed
+    pub async fn baseline(&self, version: i64) -> Result<SchemaStatus> {
+        if let DbPool::MongoDB(_) = &*self.pool {
+            bail!("MongoDB has no migrations to baseline, run `ferrumgw migrate` to create its indexes");
+        }
+        if !self.migrator().iter().any(|migration| migration.version == version) {
+            bail!("Unknown schema version {}", version);
+        }
+
+        let applied = self.applied_versions().await?;
+        let migrations: Vec<_> = self.migrator().iter()
+            .filter(|migration| migration.version <= version && !applied.contains(&migration.version))
+            .collect();
+
+        const INSERT_POSTGRES: &str = "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) VALUES ($1, $2, TRUE, $3, 0)";
+        const INSERT_OTHER: &str = "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) VALUES (?, ?, TRUE, ?, 0)";
+
+        match &*self.pool {
+            DbPool::Postgres(pool) => {
+                let mut conn = pool.acquire().await?;
+                conn.ensure_migrations_table().await?;
+                for migration in &migrations {
+                    sqlx::query(INSERT_POSTGRES)
+                        .bind(migration.version)
+                        .bind(migration.description.as_ref())
+                        .bind(migration.checksum.as_ref())
+                        .execute(&mut *conn)
+                        .await?;
+                }
+            },
+            DbPool::MySQL(pool) => {
+                let mut conn = pool.acquire().await?;
+                conn.ensure_migrations_table().await?;
+                for migration in &migrations {
+                    sqlx::query(INSERT_OTHER)
+                        .bind(migration.version)
+                        .bind(migration.description.as_ref())
+                        .bind(migration.checksum.as_ref())
+                        .execute(&mut *conn)
+                        .await?;
+                }
+            },
+            DbPool::SQLite(pool) => {
+                let mut conn = pool.acquire().await?;
+                conn.ensure_migrations_table().await?;
+                for migration in &migrations {
+                    sqlx::query(INSERT_OTHER)
+                        .bind(migration.version)
+                        .bind(migration.description.as_ref())
+                        .bind(migration.checksum.as_ref())
+                        .execute(&mut *conn)
+                        .await?;
+                }
+            },
+            DbPool::MongoDB(_) => unreachable!("MongoDB is refused above"),
+        }
+
+        info!("Marked {} migrations up to version {} as applied", migrations.len(), version);
+        self.schema_status().await
+    }
+
+    /// Checks at startup that the schema matches this binary. Pending migrations
+    /// are applied when `auto_migrate` is set and refused otherwise.
+    pub async fn check_schema(&self, auto_migrate: bool) -> Result<()> {
+        let status = self.schema_status().await?;
+
+        if status.is_ahead() {
+            warn!(
+                "Database schema is at version {}, newer than the version {} this gateway knows; \
+                 an older gateway may not read every column",
+                status.current_version.unwrap_or_default(), status.latest_version
+            );
+            return Ok(());
+        }
+
+        if status.is_up_to_date() {
+            info!("Database schema is at version {}", status.latest_version);
+            return Ok(());
+        }
+
+        if !auto_migrate {
+            bail!(
+                "Database schema is at version {} but this gateway needs version {}. \
+                 Run `ferrumgw migrate` or set FERRUM_DB_AUTO_MIGRATE=true",
+                status.current_version.map_or_else(|| "none".to_string(), |v| v.to_string()),
+                status.latest_version
+            );
+        }
+
+        info!("Applying {} pending database migrations", status.pending().count());
+        let status = self.migrate().await?;
+        info!("Database schema is at version {}", status.latest_version);
+        Ok(())
+    }
+
+    async fn applied_versions(&self) -> Result<Vec<i64>> {
+        const QUERY: &str = "SELECT version FROM _sqlx_migrations WHERE success = TRUE";
+
+        let result = match &*self.pool {
+            DbPool::Postgres(pool) => sqlx::query_scalar::<_, i64>(QUERY).fetch_all(pool).await,
+            DbPool::MySQL(pool) => sqlx::query_scalar::<_, i64>(QUERY).fetch_all(pool).await,
+            DbPool::SQLite(pool) => sqlx::query_scalar::<_, i64>(QUERY).fetch_all(pool).await,
+            DbPool::MongoDB(pool) => return Ok(mongo::schema_version(pool).await?.into_iter().collect()),
+        };
+
+        match result {
+            Ok(versions) => Ok(versions),
+            // The table only exists once migrations ran
+            Err(sqlx::Error::Database(_)) => Ok(Vec::new()),
+            Err(e) => Err(e).context("Failed to read the applied database migrations"),
+        }
+    }
+}
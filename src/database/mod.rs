@@ -1,23 +1,39 @@
-use std::sync::Arc;
-use anyhow::{Result, Context};
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use anyhow::{Result, Context, anyhow};
 use sqlx::Pool;
-use sqlx::postgres::PgPoolOptions;
-use sqlx::mysql::MySqlPoolOptions;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions, PgSslMode};
+use sqlx::mysql::{MySqlConnectOptions, MySqlPoolOptions, MySqlSslMode};
 use sqlx::sqlite::SqlitePoolOptions;
-use tracing::{info, error};
+use tracing::{info, warn, error, debug};
 use chrono::{DateTime, Utc};
 
-use crate::config::data_model::{Configuration, DatabaseType, Proxy, Consumer, PluginConfig, ConfigurationDelta};
+use crate::config::credentials::{self, PasswordHashAlgorithm};
+use crate::config::env_config::EnvConfig;
+use crate::config::data_model::{Configuration, DatabaseType, DbTlsMode, Proxy, Consumer, PluginConfig, ConfigurationDelta, Certificate, Sni, ConfigVersion, AuditEntry, AuditFilter, AdminUser, ApiToken};
+use crate::health;
 
 mod postgres;
 mod mysql;
 mod sqlite;
+pub mod mongo;
+pub mod migrations;
+pub mod cli;
+pub mod resilience;
+pub mod tombstones;
+
+use resilience::{CircuitBreaker, RetryPolicy};
 
 #[derive(Debug, Clone)]
 pub enum DatabaseType {
     Postgres,
     MySQL,
     SQLite,
+    MongoDB,
 }
 
 // Add a flag to disable database features during testing
@@ -27,10 +43,21 @@ const DISABLE_DB_FEATURES: bool = true;
 #[cfg(not(test))]
 const DISABLE_DB_FEATURES: bool = false;
 
+/// How long a read replica that failed is left out before it is tried again
+const REPLICA_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
 #[derive(Debug, Clone)]
 pub struct DatabaseClient {
     db_type: DatabaseType,
+    // The primary, used for every write and for reads when no replica answers
     pool: Arc<DbPool>,
+    replicas: Arc<Vec<ReadReplica>>,
+    next_replica: Arc<AtomicUsize>,
+    retry_policy: RetryPolicy,
+    circuit_breaker: Arc<CircuitBreaker>,
+    // How long deletions stay visible to delta loads (zero keeps them forever)
+    tombstone_retention: Duration,
+    password_hashing: PasswordHashAlgorithm,
 }
 
 // Enum to hold different database connection pools
@@ -39,135 +66,392 @@ enum DbPool {
     Postgres(Pool<sqlx::Postgres>),
     MySQL(Pool<sqlx::MySql>),
     SQLite(Pool<sqlx::Sqlite>),
+    MongoDB(mongo::MongoPool),
+}
+
+/// A read replica and, after a failure, when it may be tried again
+#[derive(Debug)]
+struct ReadReplica {
+    pool: DbPool,
+    unavailable_until: Mutex<Option<Instant>>,
+}
+
+impl ReadReplica {
+    fn is_available(&self) -> bool {
+        self.unavailable_until.lock().unwrap().map_or(true, |until| Instant::now() >= until)
+    }
+
+    fn mark_unavailable(&self) {
+        *self.unavailable_until.lock().unwrap() = Some(Instant::now() + REPLICA_RETRY_INTERVAL);
+    }
+}
+
+/// An update was based on an entity version that is no longer current: the
+/// entity was changed by someone else since it was read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleVersion {
+    pub entity: &'static str,
+    pub id: String,
+    /// The version the update was based on
+    pub expected: i64,
+}
+
+impl StaleVersion {
+    pub fn new(entity: &'static str, id: &str, expected: i64) -> Self {
+        Self { entity, id: id.to_string(), expected }
+    }
+}
+
+impl fmt::Display for StaleVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The {} '{}' was changed since version {}", self.entity, self.id, self.expected)
+    }
+}
+
+impl std::error::Error for StaleVersion {}
+
+/// Entities written together by `DatabaseClient::apply_bulk`. Plugin configs
+/// are written first, then consumers, then the proxies that refer to both.
+#[derive(Debug, Clone, Default)]
+pub struct BulkChanges {
+    pub created_plugin_configs: Vec<PluginConfig>,
+    pub updated_plugin_configs: Vec<PluginConfig>,
+    pub created_consumers: Vec<Consumer>,
+    pub updated_consumers: Vec<Consumer>,
+    pub created_proxies: Vec<Proxy>,
+    pub updated_proxies: Vec<Proxy>,
+}
+
+impl BulkChanges {
+    pub fn is_empty(&self) -> bool {
+        self.created_plugin_configs.is_empty() && self.updated_plugin_configs.is_empty()
+            && self.created_consumers.is_empty() && self.updated_consumers.is_empty()
+            && self.created_proxies.is_empty() && self.updated_proxies.is_empty()
+    }
+}
+
+/// Pool and TLS settings of database connections
+#[derive(Debug, Clone)]
+pub struct DatabaseOptions {
+    pub min_connections: u32,
+    /// None uses 10 for Postgres, MySQL and MongoDB and 5 for SQLite
+    pub max_connections: Option<u32>,
+    pub acquire_timeout: Duration,
+    pub statement_timeout: Option<Duration>,
+    /// None keeps the TLS mode of the connection URL
+    pub tls_mode: Option<DbTlsMode>,
+    pub tls_ca_cert_path: Option<String>,
+    /// Replicas that configuration loads are read from
+    pub read_replica_urls: Vec<String>,
+    /// Retries of configuration reads
+    pub retry_policy: RetryPolicy,
+    /// Failed reads in a row before the database is considered down
+    pub circuit_breaker_threshold: u32,
+    pub circuit_breaker_cooldown: Duration,
+    /// How long tombstones of deleted entities are kept; zero keeps them forever
+    pub tombstone_retention: Duration,
+    /// Algorithm consumer passwords and client secrets are hashed with when written
+    pub password_hashing: PasswordHashAlgorithm,
+}
+
+impl Default for DatabaseOptions {
+    fn default() -> Self {
+        Self::from_env_config(&EnvConfig::default())
+    }
+}
+
+impl DatabaseOptions {
+    pub fn from_env_config(env_config: &EnvConfig) -> Self {
+        Self {
+            min_connections: env_config.db_min_connections,
+            max_connections: env_config.db_max_connections,
+            acquire_timeout: env_config.db_acquire_timeout,
+            statement_timeout: env_config.db_statement_timeout,
+            tls_mode: env_config.db_tls_mode,
+            tls_ca_cert_path: env_config.db_tls_ca_cert_path.clone(),
+            read_replica_urls: env_config.db_read_replica_urls.clone(),
+            retry_policy: RetryPolicy {
+                max_attempts: env_config.db_retry_attempts,
+                base_delay: env_config.db_retry_base_delay,
+                max_delay: env_config.db_retry_max_delay,
+            },
+            circuit_breaker_threshold: env_config.db_circuit_breaker_threshold,
+            circuit_breaker_cooldown: env_config.db_circuit_breaker_cooldown,
+            tombstone_retention: env_config.db_tombstone_retention,
+            password_hashing: env_config.credential_hash_algorithm,
+        }
+    }
+}
+
+/// Opens a connection pool. A lazy pool connects on first use instead of now.
+async fn connect_pool(db_type: &DatabaseType, connection_url: &str, options: &DatabaseOptions, lazy: bool) -> Result<DbPool> {
+    let pool = match db_type {
+        DatabaseType::Postgres => {
+            let mut connect_options = PgConnectOptions::from_str(connection_url)
+                .context("Invalid PostgreSQL connection URL")?;
+            if let Some(tls_mode) = options.tls_mode {
+                connect_options = connect_options.ssl_mode(match tls_mode {
+                    DbTlsMode::Disable => PgSslMode::Disable,
+                    DbTlsMode::Prefer => PgSslMode::Prefer,
+                    DbTlsMode::Require => PgSslMode::Require,
+                    DbTlsMode::VerifyCa => PgSslMode::VerifyCa,
+                    DbTlsMode::VerifyFull => PgSslMode::VerifyFull,
+                });
+            }
+            if let Some(ca_cert_path) = &options.tls_ca_cert_path {
+                connect_options = connect_options.ssl_root_cert(ca_cert_path);
+            }
+            if let Some(statement_timeout) = options.statement_timeout {
+                connect_options = connect_options.options([("statement_timeout", statement_timeout.as_millis().to_string())]);
+            }
+            
+            let pool_options = PgPoolOptions::new()
+                .min_connections(options.min_connections)
+                .max_connections(options.max_connections.unwrap_or(10))
+                .acquire_timeout(options.acquire_timeout);
+            
+            let pg_pool = if lazy {
+                pool_options.connect_lazy_with(connect_options)
+            } else {
+                pool_options.connect_with(connect_options)
+                    .await
+                    .context("Failed to connect to PostgreSQL database")?
+            };
+            DbPool::Postgres(pg_pool)
+        },
+        DatabaseType::MySQL => {
+            let mut connect_options = MySqlConnectOptions::from_str(connection_url)
+                .context("Invalid MySQL connection URL")?;
+            if let Some(tls_mode) = options.tls_mode {
+                connect_options = connect_options.ssl_mode(match tls_mode {
+                    DbTlsMode::Disable => MySqlSslMode::Disabled,
+                    DbTlsMode::Prefer => MySqlSslMode::Preferred,
+                    DbTlsMode::Require => MySqlSslMode::Required,
+                    DbTlsMode::VerifyCa => MySqlSslMode::VerifyCa,
+                    DbTlsMode::VerifyFull => MySqlSslMode::VerifyIdentity,
+                });
+            }
+            if let Some(ca_cert_path) = &options.tls_ca_cert_path {
+                connect_options = connect_options.ssl_ca(ca_cert_path);
+            }
+            
+            // MySQL has no connection option for it, so every new connection sets it for its session
+            let statement_timeout_ms = options.statement_timeout.map(|timeout| timeout.as_millis() as u64);
+            let pool_options = MySqlPoolOptions::new()
+                .min_connections(options.min_connections)
+                .max_connections(options.max_connections.unwrap_or(10))
+                .acquire_timeout(options.acquire_timeout)
+                .after_connect(move |conn, _meta| Box::pin(async move {
+                    if let Some(timeout_ms) = statement_timeout_ms {
+                        sqlx::query(&format!("SET SESSION max_execution_time = {}", timeout_ms))
+                            .execute(conn)
+                            .await?;
+                    }
+                    Ok(())
+                }));
+            
+            let mysql_pool = if lazy {
+                pool_options.connect_lazy_with(connect_options)
+            } else {
+                pool_options.connect_with(connect_options)
+                    .await
+                    .context("Failed to connect to MySQL database")?
+            };
+            DbPool::MySQL(mysql_pool)
+        },
+        DatabaseType::SQLite => {
+            if options.tls_mode.is_some() || options.statement_timeout.is_some() {
+                debug!("TLS and statement timeout settings do not apply to SQLite");
+            }
+            
+            let sqlite_pool = SqlitePoolOptions::new()
+                .min_connections(options.min_connections)
+                .max_connections(options.max_connections.unwrap_or(5))
+                .acquire_timeout(options.acquire_timeout)
+                .connect(connection_url)
+                .await
+                .context("Failed to connect to SQLite database")?;
+            DbPool::SQLite(sqlite_pool)
+        },
+        DatabaseType::MongoDB => {
+            // The driver connects on first use, so a lazy pool needs nothing more
+            let mongo_pool = mongo::connect(connection_url, options).await?;
+            if !lazy {
+                mongo::get_latest_update_timestamp(&mongo_pool).await
+                    .context("Failed to connect to MongoDB database")?;
+            }
+            DbPool::MongoDB(mongo_pool)
+        },
+    };
+    
+    Ok(pool)
 }
 
 impl DatabaseClient {
     pub async fn new(db_type: DatabaseType, connection_url: &str) -> Result<Self> {
+        Self::with_options(db_type, connection_url, &DatabaseOptions::default()).await
+    }
+    
+    pub async fn with_options(db_type: DatabaseType, connection_url: &str, options: &DatabaseOptions) -> Result<Self> {
         info!("Initializing database connection: {:?}", db_type);
         
-        let pool = match db_type {
-            DatabaseType::Postgres => {
-                let pg_pool = PgPoolOptions::new()
-                    .max_connections(10)
-                    .connect(connection_url)
-                    .await
-                    .context("Failed to connect to PostgreSQL database")?;
-                
-                Arc::new(DbPool::Postgres(pg_pool))
-            },
-            DatabaseType::MySQL => {
-                let mysql_pool = MySqlPoolOptions::new()
-                    .max_connections(10)
-                    .connect(connection_url)
-                    .await
-                    .context("Failed to connect to MySQL database")?;
-                
-                Arc::new(DbPool::MySQL(mysql_pool))
-            },
-            DatabaseType::SQLite => {
-                let sqlite_pool = SqlitePoolOptions::new()
-                    .max_connections(5)
-                    .connect(connection_url)
-                    .await
-                    .context("Failed to connect to SQLite database")?;
-                
-                Arc::new(DbPool::SQLite(sqlite_pool))
-            },
-        };
+        let pool = Arc::new(connect_pool(&db_type, connection_url, options, false).await?);
+        
+        let mut replicas = Vec::with_capacity(options.read_replica_urls.len());
+        if matches!(db_type, DatabaseType::SQLite) && !options.read_replica_urls.is_empty() {
+            warn!("Read replicas are not supported with SQLite, reading from the database file");
+        } else {
+            for url in &options.read_replica_urls {
+                // Replicas connect on first use, so one being down does not stop the gateway from starting
+                replicas.push(ReadReplica {
+                    pool: connect_pool(&db_type, url, options, true).await?,
+                    unavailable_until: Mutex::new(None),
+                });
+            }
+            if !replicas.is_empty() {
+                info!("Reading configuration from {} read replicas", replicas.len());
+            }
+        }
         
         Ok(Self {
             db_type,
             pool,
+            replicas: Arc::new(replicas),
+            next_replica: Arc::new(AtomicUsize::new(0)),
+            retry_policy: options.retry_policy,
+            circuit_breaker: Arc::new(CircuitBreaker::new(
+                options.circuit_breaker_threshold,
+                options.circuit_breaker_cooldown,
+            )),
+            tombstone_retention: options.tombstone_retention,
+            password_hashing: options.password_hashing,
         })
     }
     
+    /// Algorithm new consumer passwords and client secrets are hashed with
+    pub fn password_hashing(&self) -> PasswordHashAlgorithm {
+        self.password_hashing
+    }
+    
+    /// Whether configuration reads keep failing, so the gateway is serving the
+    /// last configuration it loaded
+    pub fn is_degraded(&self) -> bool {
+        self.circuit_breaker.is_open()
+    }
+    
+    /// Runs a configuration read, retrying failures with backoff. Reads that
+    /// still fail feed the circuit breaker, which fails reads fast while open.
+    async fn read<'a, T, F, Fut>(&'a self, read: F) -> Result<T>
+    where
+        F: Fn(&'a DbPool) -> Fut,
+        Fut: Future<Output = Result<T>> + 'a,
+    {
+        if !self.circuit_breaker.allow() {
+            return Err(anyhow!("Database circuit breaker is open, skipping the read"));
+        }
+        
+        let mut attempt = 1;
+        loop {
+            match self.read_once(&read).await {
+                Ok(value) => {
+                    self.circuit_breaker.record_success();
+                    health::record_config_source(Ok(()));
+                    return Ok(value);
+                },
+                Err(e) if attempt < self.retry_policy.max_attempts => {
+                    let delay = self.retry_policy.backoff(attempt);
+                    warn!("Database read failed (attempt {}), retrying in {:?}: {:#}", attempt, delay, e);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                },
+                Err(e) => {
+                    self.circuit_breaker.record_failure();
+                    health::record_config_source(Err(format!("{:#}", e)));
+                    return Err(e);
+                },
+            }
+        }
+    }
+    
+    /// Runs a configuration read on the read replicas, rotating through them, and
+    /// falls back to the primary when none of them answers. A replica that fails
+    /// is skipped for REPLICA_RETRY_INTERVAL.
+    async fn read_once<'a, T, F, Fut>(&'a self, read: &F) -> Result<T>
+    where
+        F: Fn(&'a DbPool) -> Fut,
+        Fut: Future<Output = Result<T>> + 'a,
+    {
+        let count = self.replicas.len();
+        let first = self.next_replica.fetch_add(1, Ordering::Relaxed);
+        
+        for offset in 0..count {
+            let replica = &self.replicas[(first + offset) % count];
+            if !replica.is_available() {
+                continue;
+            }
+            match read(&replica.pool).await {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    warn!("Read replica failed, skipping it for {:?}: {:#}", REPLICA_RETRY_INTERVAL, e);
+                    replica.mark_unavailable();
+                },
+            }
+        }
+        
+        read(&*self.pool).await
+    }
+    
     pub async fn load_full_configuration(&self) -> Result<Configuration> {
         info!("Loading full configuration from database");
         
-        match self.db_type {
-            DatabaseType::Postgres => {
-                if let DbPool::Postgres(ref pool) = *self.pool {
-                    postgres::load_full_configuration(pool).await
-                } else {
-                    unreachable!("Pool type mismatch with database type")
-                }
-            },
-            DatabaseType::MySQL => {
-                if let DbPool::MySQL(ref pool) = *self.pool {
-                    mysql::load_full_configuration(pool).await
-                } else {
-                    unreachable!("Pool type mismatch with database type")
-                }
-            },
-            DatabaseType::SQLite => {
-                if let DbPool::SQLite(ref pool) = *self.pool {
-                    sqlite::load_full_configuration(pool).await
-                } else {
-                    unreachable!("Pool type mismatch with database type")
-                }
-            },
-        }
+        self.read(|pool| async move {
+            match pool {
+                DbPool::Postgres(pool) => postgres::load_full_configuration(pool).await,
+                DbPool::MySQL(pool) => mysql::load_full_configuration(pool).await,
+                DbPool::SQLite(pool) => sqlite::load_full_configuration(pool).await,
+                DbPool::MongoDB(pool) => mongo::load_full_configuration(pool).await,
+            }
+        }).await
     }
     
     /// Load configuration changes since a specific timestamp
     pub async fn load_configuration_delta(&self, since: DateTime<Utc>) -> Result<ConfigurationDelta> {
         info!("Loading configuration delta since {}", since);
         
-        match self.db_type {
-            DatabaseType::Postgres => {
-                if let DbPool::Postgres(ref pool) = *self.pool {
-                    postgres::load_configuration_delta(pool, since).await
-                } else {
-                    unreachable!("Pool type mismatch with database type")
-                }
-            },
-            DatabaseType::MySQL => {
-                if let DbPool::MySQL(ref pool) = *self.pool {
-                    mysql::load_configuration_delta(pool, since).await
-                } else {
-                    unreachable!("Pool type mismatch with database type")
-                }
-            },
-            DatabaseType::SQLite => {
-                if let DbPool::SQLite(ref pool) = *self.pool {
-                    sqlite::load_configuration_delta(pool, since).await
-                } else {
-                    unreachable!("Pool type mismatch with database type")
-                }
-            },
+        // Deletions older than the horizon may have been purged and would be missed
+        if let Some(horizon) = self.tombstone_horizon() {
+            if since < horizon {
+                return Err(anyhow!(
+                    "Tombstones before {} may have been purged, a full configuration load is needed",
+                    horizon
+                ));
+            }
         }
+        
+        self.read(|pool| async move {
+            match pool {
+                DbPool::Postgres(pool) => postgres::load_configuration_delta(pool, since).await,
+                DbPool::MySQL(pool) => mysql::load_configuration_delta(pool, since).await,
+                DbPool::SQLite(pool) => sqlite::load_configuration_delta(pool, since).await,
+                DbPool::MongoDB(pool) => mongo::load_configuration_delta(pool, since).await,
+            }
+        }).await
     }
     
     /// Get the latest database update timestamp without fetching the data
     pub async fn get_latest_update_timestamp(&self) -> Result<DateTime<Utc>> {
-        match self.db_type {
-            DatabaseType::Postgres => {
-                if let DbPool::Postgres(ref pool) = *self.pool {
-                    postgres::get_latest_update_timestamp(pool).await
-                } else {
-                    unreachable!("Pool type mismatch with database type")
-                }
-            },
-            DatabaseType::MySQL => {
-                if let DbPool::MySQL(ref pool) = *self.pool {
-                    mysql::get_latest_update_timestamp(pool).await
-                } else {
-                    unreachable!("Pool type mismatch with database type")
-                }
-            },
-            DatabaseType::SQLite => {
-                if let DbPool::SQLite(ref pool) = *self.pool {
-                    sqlite::get_latest_update_timestamp(pool).await
-                } else {
-                    unreachable!("Pool type mismatch with database type")
-                }
-            },
-        }
+        self.read(|pool| async move {
+            match pool {
+                DbPool::Postgres(pool) => postgres::get_latest_update_timestamp(pool).await,
+                DbPool::MySQL(pool) => mysql::get_latest_update_timestamp(pool).await,
+                DbPool::SQLite(pool) => sqlite::get_latest_update_timestamp(pool).await,
+                DbPool::MongoDB(pool) => mongo::get_latest_update_timestamp(pool).await,
+            }
+        }).await
     }
     
     // Here we would implement specific CRUD methods for each entity type
     // These would be used by the Admin API to manage the configuration
-    
     pub async fn create_proxy(&self, proxy: &Proxy) -> Result<Proxy> {
         // Implementation for creating a proxy in the database
         // Each database adapter will check for listen_path uniqueness
@@ -194,6 +478,13 @@ impl DatabaseClient {
                     unreachable!("Pool type mismatch with database type")
                 }
             },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::create_proxy(pool, proxy).await
+                } else {
+                    unreachable!("Pool type mismatch with database type")
+                }
+            },
         }
     }
     
@@ -221,6 +512,13 @@ impl DatabaseClient {
                     unreachable!("Pool type mismatch with database type")
                 }
             },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::get_consumer_by_id(pool, consumer_id).await
+                } else {
+                    unreachable!("Pool type mismatch with database type")
+                }
+            },
         }
     }
     
@@ -248,6 +546,13 @@ impl DatabaseClient {
                     unreachable!("Pool type mismatch with database type")
                 }
             },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::delete_consumer(pool, consumer_id).await
+                } else {
+                    unreachable!("Pool type mismatch with database type")
+                }
+            },
         }
     }
     
@@ -271,6 +576,11 @@ impl DatabaseClient {
                      sqlite::create_plugin_config(pool, plugin_config).await
                  } else { unreachable!("Pool type mismatch") }
             },
+            DatabaseType::MongoDB => {
+                 if let DbPool::MongoDB(ref pool) = *self.pool {
+                     mongo::create_plugin_config(pool, plugin_config).await
+                 } else { unreachable!("Pool type mismatch") }
+            },
         }
     }
     
@@ -293,6 +603,11 @@ impl DatabaseClient {
                      sqlite::update_plugin_config(pool, plugin_config).await
                  } else { unreachable!("Pool type mismatch") }
             },
+            DatabaseType::MongoDB => {
+                 if let DbPool::MongoDB(ref pool) = *self.pool {
+                     mongo::update_plugin_config(pool, plugin_config).await
+                 } else { unreachable!("Pool type mismatch") }
+            },
         }
     }
     
@@ -315,6 +630,11 @@ impl DatabaseClient {
                      sqlite::delete_plugin_config(pool, config_id).await
                  } else { unreachable!("Pool type mismatch") }
             },
+            DatabaseType::MongoDB => {
+                 if let DbPool::MongoDB(ref pool) = *self.pool {
+                     mongo::delete_plugin_config(pool, config_id).await
+                 } else { unreachable!("Pool type mismatch") }
+            },
         }
     }
      
@@ -322,6 +642,9 @@ impl DatabaseClient {
     // Returns the ID of the newly created consumer
     pub async fn create_consumer(&self, consumer: &Consumer) -> Result<String> {
         info!("Creating consumer in database: {}", consumer.id);
+        let mut consumer = consumer.clone();
+        credentials::hash_consumer(&mut consumer, self.password_hashing)?;
+        let consumer = &consumer;
         match self.db_type {
             DatabaseType::Postgres => {
                 if let DbPool::Postgres(ref pool) = *self.pool {
@@ -338,12 +661,20 @@ impl DatabaseClient {
                      sqlite::create_consumer(pool, consumer).await
                  } else { unreachable!("Pool type mismatch") }
             },
+            DatabaseType::MongoDB => {
+                 if let DbPool::MongoDB(ref pool) = *self.pool {
+                     mongo::create_consumer(pool, consumer).await
+                 } else { unreachable!("Pool type mismatch") }
+            },
         }
     }
     
     // Update an existing consumer in the database
     pub async fn update_consumer(&self, consumer: &Consumer) -> Result<()> {
         info!("Updating consumer in database: {}", consumer.id);
+        let mut consumer = consumer.clone();
+        credentials::hash_consumer(&mut consumer, self.password_hashing)?;
+        let consumer = &consumer;
          match self.db_type {
             DatabaseType::Postgres => {
                 if let DbPool::Postgres(ref pool) = *self.pool {
@@ -360,6 +691,48 @@ impl DatabaseClient {
                      sqlite::update_consumer(pool, consumer).await
                  } else { unreachable!("Pool type mismatch") }
             },
+            DatabaseType::MongoDB => {
+                 if let DbPool::MongoDB(ref pool) = *self.pool {
+                     mongo::update_consumer(pool, consumer).await
+                 } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+    
+    /// Hashes the credentials of consumers stored before hashing at write time,
+    /// and returns how many consumers were rewritten
+    pub async fn hash_stored_credentials(&self) -> Result<usize> {
+        let config = self.load_full_configuration().await?;
+        let mut rewritten = 0;
+        for mut consumer in config.consumers {
+            if credentials::hash_consumer(&mut consumer, self.password_hashing)? {
+                self.update_consumer(&consumer).await
+                    .context(format!("Failed to store hashed credentials of consumer {}", consumer.id))?;
+                rewritten += 1;
+            }
+        }
+        Ok(rewritten)
+    }
+    
+    /// Writes many proxies, consumers and plugin configs in one transaction:
+    /// either all of them are stored or, on any conflict, none are
+    pub async fn apply_bulk(&self, changes: &BulkChanges) -> Result<()> {
+        info!(
+            "Applying bulk import of {} proxies, {} consumers and {} plugin configs",
+            changes.created_proxies.len() + changes.updated_proxies.len(),
+            changes.created_consumers.len() + changes.updated_consumers.len(),
+            changes.created_plugin_configs.len() + changes.updated_plugin_configs.len(),
+        );
+        let mut changes = changes.clone();
+        for consumer in changes.created_consumers.iter_mut().chain(changes.updated_consumers.iter_mut()) {
+            credentials::hash_consumer(consumer, self.password_hashing)?;
+        }
+        let changes = &changes;
+        match &*self.pool {
+            DbPool::Postgres(pool) => postgres::apply_bulk(pool, changes).await,
+            DbPool::MySQL(pool) => mysql::apply_bulk(pool, changes).await,
+            DbPool::SQLite(pool) => sqlite::apply_bulk(pool, changes).await,
+            DbPool::MongoDB(pool) => mongo::apply_bulk(pool, changes).await,
         }
     }
     
@@ -388,6 +761,13 @@ impl DatabaseClient {
                     unreachable!("Pool type mismatch with database type")
                 }
             },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::get_consumer_by_id(pool, consumer_id).await
+                } else {
+                    unreachable!("Pool type mismatch with database type")
+                }
+            },
         }
     }
      
@@ -410,6 +790,11 @@ impl DatabaseClient {
                      sqlite::get_proxy_by_id(pool, proxy_id).await
                  } else { unreachable!("Pool type mismatch") }
             },
+            DatabaseType::MongoDB => {
+                 if let DbPool::MongoDB(ref pool) = *self.pool {
+                     mongo::get_proxy_by_id(pool, proxy_id).await
+                 } else { unreachable!("Pool type mismatch") }
+            },
         }
     }
 
@@ -432,6 +817,11 @@ impl DatabaseClient {
                      sqlite::update_proxy(pool, proxy).await
                  } else { unreachable!("Pool type mismatch") }
             },
+            DatabaseType::MongoDB => {
+                 if let DbPool::MongoDB(ref pool) = *self.pool {
+                     mongo::update_proxy(pool, proxy).await
+                 } else { unreachable!("Pool type mismatch") }
+            },
         }
     }
 
@@ -454,6 +844,671 @@ impl DatabaseClient {
                      sqlite::delete_proxy(pool, proxy_id).await
                  } else { unreachable!("Pool type mismatch") }
             },
+            DatabaseType::MongoDB => {
+                 if let DbPool::MongoDB(ref pool) = *self.pool {
+                     mongo::delete_proxy(pool, proxy_id).await
+                 } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Load all listener certificates from the database
+    pub async fn load_certificates(&self) -> Result<Vec<Certificate>> {
+        debug!("Loading certificates from database");
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::load_certificates(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::load_certificates(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::load_certificates(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::load_certificates(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Get a certificate by its ID from the database
+    pub async fn get_certificate_by_id(&self, certificate_id: &str) -> Result<Certificate> {
+        info!("Getting certificate from database: {}", certificate_id);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::get_certificate_by_id(pool, certificate_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::get_certificate_by_id(pool, certificate_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::get_certificate_by_id(pool, certificate_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::get_certificate_by_id(pool, certificate_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Insert or replace a certificate in the database
+    pub async fn upsert_certificate(&self, certificate: &Certificate) -> Result<()> {
+        info!("Storing certificate in database: {}", certificate.id);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::upsert_certificate(pool, certificate).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::upsert_certificate(pool, certificate).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::upsert_certificate(pool, certificate).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::upsert_certificate(pool, certificate).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Delete a certificate and the SNIs mapped to it
+    pub async fn delete_certificate(&self, certificate_id: &str) -> Result<()> {
+        info!("Deleting certificate from database: {}", certificate_id);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::delete_certificate(pool, certificate_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::delete_certificate(pool, certificate_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::delete_certificate(pool, certificate_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::delete_certificate(pool, certificate_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
         }
     }
+
+    // Load all SNI mappings from the database
+    pub async fn load_snis(&self) -> Result<Vec<Sni>> {
+        debug!("Loading SNIs from database");
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::load_snis(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::load_snis(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::load_snis(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::load_snis(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Insert or replace an SNI in the database
+    pub async fn upsert_sni(&self, sni: &Sni) -> Result<()> {
+        info!("Storing SNI in database: {}", sni.name);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::upsert_sni(pool, sni).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::upsert_sni(pool, sni).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::upsert_sni(pool, sni).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::upsert_sni(pool, sni).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Delete an SNI from the database
+    pub async fn delete_sni(&self, sni_id: &str) -> Result<()> {
+        info!("Deleting SNI from database: {}", sni_id);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::delete_sni(pool, sni_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::delete_sni(pool, sni_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::delete_sni(pool, sni_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::delete_sni(pool, sni_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Record a configuration version, pruning versions beyond the retention
+    pub async fn insert_config_version(&self, author: &str, description: &str, document: &str, retain: u64) -> Result<i64> {
+        info!("Recording configuration version by {}", author);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::insert_config_version(pool, author, description, document, retain).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::insert_config_version(pool, author, description, document, retain).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::insert_config_version(pool, author, description, document, retain).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::insert_config_version(pool, author, description, document, retain).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // List configuration versions, newest first, without their documents
+    pub async fn list_config_versions(&self) -> Result<Vec<ConfigVersion>> {
+        debug!("Loading configuration versions from database");
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::list_config_versions(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::list_config_versions(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::list_config_versions(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::list_config_versions(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Get a configuration version with its document, or the latest one when no ID is given
+    pub async fn get_config_version(&self, version_id: Option<i64>) -> Result<Option<ConfigVersion>> {
+        debug!("Getting configuration version from database: {:?}", version_id);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::get_config_version(pool, version_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::get_config_version(pool, version_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::get_config_version(pool, version_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::get_config_version(pool, version_id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Record an Admin API change in the audit log
+    pub async fn insert_audit_entry(&self, entry: &AuditEntry) -> Result<()> {
+        debug!("Recording audit log entry: {} {}", entry.actor, entry.request);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::insert_audit_entry(pool, entry).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::insert_audit_entry(pool, entry).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::insert_audit_entry(pool, entry).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::insert_audit_entry(pool, entry).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // List audit log entries matching a filter, newest first, with the total number of matches
+    pub async fn list_audit_entries(&self, filter: &AuditFilter, limit: usize, offset: usize) -> Result<(Vec<AuditEntry>, usize)> {
+        debug!("Loading audit log entries from database: {:?}", filter);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::list_audit_entries(pool, filter, limit, offset).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::list_audit_entries(pool, filter, limit, offset).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::list_audit_entries(pool, filter, limit, offset).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::list_audit_entries(pool, filter, limit, offset).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // List the Admin API users by username
+    pub async fn list_admin_users(&self) -> Result<Vec<AdminUser>> {
+        debug!("Loading admin users from database");
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::list_admin_users(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::list_admin_users(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::list_admin_users(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::list_admin_users(pool).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Get an Admin API user by username
+    pub async fn get_admin_user(&self, username: &str) -> Result<Option<AdminUser>> {
+        debug!("Getting admin user from database: {}", username);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::get_admin_user(pool, username).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::get_admin_user(pool, username).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::get_admin_user(pool, username).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::get_admin_user(pool, username).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Insert an Admin API user, or replace the password and role of an existing one
+    pub async fn upsert_admin_user(&self, user: &AdminUser) -> Result<()> {
+        debug!("Storing admin user in database: {}", user.username);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::upsert_admin_user(pool, user).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::upsert_admin_user(pool, user).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::upsert_admin_user(pool, user).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::upsert_admin_user(pool, user).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Delete an Admin API user, returning whether it existed
+    pub async fn delete_admin_user(&self, username: &str) -> Result<bool> {
+        debug!("Deleting admin user from database: {}", username);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::delete_admin_user(pool, username).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::delete_admin_user(pool, username).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::delete_admin_user(pool, username).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::delete_admin_user(pool, username).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Revoke an Admin API token until it expires
+    pub async fn revoke_admin_token(&self, jti: &str, username: &str, expires_at: DateTime<Utc>) -> Result<()> {
+        debug!("Revoking admin token {} of {}", jti, username);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::revoke_admin_token(pool, jti, username, expires_at).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::revoke_admin_token(pool, jti, username, expires_at).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::revoke_admin_token(pool, jti, username, expires_at).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::revoke_admin_token(pool, jti, username, expires_at).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Whether an Admin API token has been revoked
+    pub async fn is_admin_token_revoked(&self, jti: &str) -> Result<bool> {
+        debug!("Checking revocation of admin token {}", jti);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::is_admin_token_revoked(pool, jti).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::is_admin_token_revoked(pool, jti).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::is_admin_token_revoked(pool, jti).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::is_admin_token_revoked(pool, jti).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Store an Admin API token created through /tokens
+    pub async fn create_api_token(&self, token: &ApiToken) -> Result<()> {
+        debug!("Storing admin API token {} of {}", token.id, token.username);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::create_api_token(pool, token).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::create_api_token(pool, token).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::create_api_token(pool, token).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::create_api_token(pool, token).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // List the Admin API tokens, of one user or of everyone
+    pub async fn list_api_tokens(&self, username: Option<&str>) -> Result<Vec<ApiToken>> {
+        debug!("Listing admin API tokens");
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::list_api_tokens(pool, username).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::list_api_tokens(pool, username).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::list_api_tokens(pool, username).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::list_api_tokens(pool, username).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Get an Admin API token by ID
+    pub async fn get_api_token(&self, id: &str) -> Result<Option<ApiToken>> {
+        debug!("Getting admin API token {}", id);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::get_api_token(pool, id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::get_api_token(pool, id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::get_api_token(pool, id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::get_api_token(pool, id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+
+    // Delete an Admin API token, returning whether it existed
+    pub async fn delete_api_token(&self, id: &str) -> Result<bool> {
+        debug!("Deleting admin API token {}", id);
+        match self.db_type {
+            DatabaseType::Postgres => {
+                if let DbPool::Postgres(ref pool) = *self.pool {
+                    postgres::delete_api_token(pool, id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MySQL => {
+                if let DbPool::MySQL(ref pool) = *self.pool {
+                    mysql::delete_api_token(pool, id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::SQLite => {
+                if let DbPool::SQLite(ref pool) = *self.pool {
+                    sqlite::delete_api_token(pool, id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+            DatabaseType::MongoDB => {
+                if let DbPool::MongoDB(ref pool) = *self.pool {
+                    mongo::delete_api_token(pool, id).await
+                } else { unreachable!("Pool type mismatch") }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn test_proxy(id: &str) -> Proxy {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "listen_path": format!("/{}", id),
+            "backend_protocol": "http",
+            "backend_host": "localhost",
+            "backend_port": 8080,
+            "backend_connect_timeout_ms": 1000,
+            "backend_read_timeout_ms": 1000,
+            "backend_write_timeout_ms": 1000,
+            "created_at": "2026-01-01T00:00:00Z",
+            "updated_at": "2026-01-01T00:00:00Z",
+        })).unwrap()
+    }
+    
+    fn sqlite_url(dir: &tempfile::TempDir, name: &str) -> String {
+        format!("sqlite://{}?mode=rwc", dir.path().join(name).display())
+    }
+    
+    async fn migrated_client(dir: &tempfile::TempDir, name: &str) -> DatabaseClient {
+        let client = DatabaseClient::new(DatabaseType::SQLite, &sqlite_url(dir, name)).await.unwrap();
+        client.migrate().await.unwrap();
+        client
+    }
+    
+    async fn replica(dir: &tempfile::TempDir, name: &str) -> ReadReplica {
+        ReadReplica {
+            pool: connect_pool(&DatabaseType::SQLite, &sqlite_url(dir, name), &DatabaseOptions::default(), true).await.unwrap(),
+            unavailable_until: Mutex::new(None),
+        }
+    }
+    
+    fn proxy_ids(config: &Configuration) -> Vec<&str> {
+        config.proxies.iter().map(|proxy| proxy.id.as_str()).collect()
+    }
+    
+    #[tokio::test]
+    async fn test_reads_fall_back_to_primary_when_replica_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut client = migrated_client(&dir, "primary.db").await;
+        client.create_proxy(&test_proxy("on-primary")).await.unwrap();
+        let replica_client = migrated_client(&dir, "replica.db").await;
+        replica_client.create_proxy(&test_proxy("on-replica")).await.unwrap();
+        
+        // SQLite ignores replica URLs, so replicas are attached directly
+        client.replicas = Arc::new(vec![replica(&dir, "replica.db").await]);
+        let config = client.load_full_configuration().await.unwrap();
+        assert_eq!(proxy_ids(&config), vec!["on-replica"]);
+        
+        // A replica without the schema fails every read
+        client.replicas = Arc::new(vec![replica(&dir, "broken.db").await]);
+        let config = client.load_full_configuration().await.unwrap();
+        assert_eq!(proxy_ids(&config), vec!["on-primary"]);
+        assert!(!client.replicas[0].is_available());
+        assert!(!client.is_degraded());
+        
+        // While it is left out, reads go to the primary without trying it
+        let config = client.load_full_configuration().await.unwrap();
+        assert_eq!(proxy_ids(&config), vec!["on-primary"]);
+    }
 }
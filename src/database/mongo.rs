@@ -0,0 +1,910 @@
+//! MongoDB configuration store
+
+use anyhow::{anyhow, bail, Context, Result};
+use bson::{doc, Bson, Document};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use mongodb::options::{
+    ClientOptions, FindOneAndUpdateOptions, FindOneOptions, FindOptions, IndexOptions, ReplaceOptions, ReturnDocument,
+};
+use mongodb::{Client, ClientSession, Collection, Database, IndexModel};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::{debug, info};
+
+use super::{BulkChanges, DatabaseOptions, StaleVersion};
+use crate::config::data_model::{
+    AdminUser, ApiToken, AuditEntry, AuditFilter, Certificate, ConfigVersion, Configuration, ConfigurationDelta, Consumer,
+    PluginConfig, Proxy, Sni,
+};
+
+/// Database used when the connection URL names none
+const DEFAULT_DATABASE: &str = "ferrumgw";
+
+/// Version of the collections and indexes created by `ensure_schema`
+pub const SCHEMA_VERSION: i64 = 1;
+
+const PROXIES: &str = "proxies";
+const CONSUMERS: &str = "consumers";
+const PLUGIN_CONFIGS: &str = "plugin_configs";
+const CERTIFICATES: &str = "certificates";
+const SNIS: &str = "snis";
+const CONFIG_VERSIONS: &str = "config_versions";
+const AUDIT_LOG: &str = "audit_log";
+const ADMIN_USERS: &str = "admin_users";
+const ADMIN_TOKEN_REVOCATIONS: &str = "admin_token_revocations";
+const ADMIN_API_TOKENS: &str = "admin_api_tokens";
+const COUNTERS: &str = "counters";
+const SCHEMA: &str = "_schema";
+
+/// Fields holding timestamps, stored as BSON dates
+const DATE_FIELDS: [&str; 4] = ["created_at", "updated_at", "deleted_at", "expires_at"];
+
+/// A MongoDB client and the database the configuration lives in
+#[derive(Debug, Clone)]
+pub struct MongoPool {
+    client: Client,
+    database: Database,
+}
+
+impl MongoPool {
+    fn collection(&self, name: &str) -> Collection<Document> {
+        self.database.collection(name)
+    }
+}
+
+/// Connects to the deployment of a `mongodb://` or `mongodb+srv://` URL. The
+/// database is the one named in the URL, or `ferrumgw`.
+pub async fn connect(connection_url: &str, options: &DatabaseOptions) -> Result<MongoPool> {
+    let mut client_options = ClientOptions::parse(connection_url)
+        .await
+        .context("Invalid MongoDB connection URL")?;
+    client_options.app_name.get_or_insert_with(|| "ferrumgw".to_string());
+    client_options.min_pool_size = Some(options.min_connections);
+    client_options.max_pool_size = Some(options.max_connections.unwrap_or(10));
+    client_options.connect_timeout = Some(options.acquire_timeout);
+    client_options.server_selection_timeout = Some(options.acquire_timeout);
+    if options.tls_mode.is_some() || options.statement_timeout.is_some() {
+        debug!("TLS and statement timeout settings do not apply to MongoDB, set them in the connection URL");
+    }
+
+    let client = Client::with_options(client_options).context("Failed to create MongoDB client")?;
+    let database = client.default_database()
+        .unwrap_or_else(|| client.database(DEFAULT_DATABASE));
+    Ok(MongoPool { client, database })
+}
+
+/// Converts an entity to the document stored for it: `id` becomes `_id` and
+/// timestamps become BSON dates
+pub fn to_document<T: Serialize>(entity: &T) -> Result<Document> {
+    let mut document = bson::to_document(entity).context("Failed to convert entity to a MongoDB document")?;
+    if let Some(id) = document.remove("id") {
+        document.insert("_id", id);
+    }
+    for field in DATE_FIELDS {
+        if let Some(Bson::String(value)) = document.get(field) {
+            let time = DateTime::parse_from_rfc3339(value)
+                .with_context(|| format!("Invalid timestamp in {}", field))?
+                .with_timezone(&Utc);
+            document.insert(field, bson::DateTime::from_chrono(time));
+        }
+    }
+    Ok(document)
+}
+
+/// Converts a stored document back to its entity
+pub fn from_document<T: DeserializeOwned>(mut document: Document) -> Result<T> {
+    if let Some(id) = document.remove("_id") {
+        document.insert("id", id);
+    }
+    for field in DATE_FIELDS {
+        if let Some(Bson::DateTime(time)) = document.get(field) {
+            let time = time.to_chrono().to_rfc3339();
+            document.insert(field, time);
+        }
+    }
+    bson::from_document(document).context("Stored MongoDB document is not valid")
+}
+
+/// A write made on its own, or inside the transaction of a bulk import
+struct Writer<'a> {
+    pool: &'a MongoPool,
+    session: Option<&'a mut ClientSession>,
+}
+
+impl<'a> Writer<'a> {
+    fn new(pool: &'a MongoPool) -> Self {
+        Self { pool, session: None }
+    }
+
+    async fn find_one(&mut self, collection: &str, filter: Document) -> Result<Option<Document>> {
+        let collection = self.pool.collection(collection);
+        let found = match self.session.as_deref_mut() {
+            Some(session) => collection.find_one_with_session(filter, None, session).await,
+            None => collection.find_one(filter, None).await,
+        };
+        Ok(found?)
+    }
+
+    async fn exists(&mut self, collection: &str, filter: Document) -> Result<bool> {
+        Ok(self.find_one(collection, filter).await?.is_some())
+    }
+
+    async fn insert(&mut self, collection: &str, document: Document) -> Result<()> {
+        let collection = self.pool.collection(collection);
+        match self.session.as_deref_mut() {
+            Some(session) => collection.insert_one_with_session(document, None, session).await?,
+            None => collection.insert_one(document, None).await?,
+        };
+        Ok(())
+    }
+
+    /// Replaces the document matching `filter` and returns whether one matched
+    async fn replace(&mut self, collection: &str, filter: Document, document: Document) -> Result<bool> {
+        let collection = self.pool.collection(collection);
+        let result = match self.session.as_deref_mut() {
+            Some(session) => collection.replace_one_with_session(filter, document, None, session).await?,
+            None => collection.replace_one(filter, document, None).await?,
+        };
+        Ok(result.matched_count > 0)
+    }
+}
+
+pub async fn load_full_configuration(pool: &MongoPool) -> Result<Configuration> {
+    info!("Loading full configuration from MongoDB database");
+
+    let configuration = Configuration {
+        proxies: load_all(pool, PROXIES, doc! {}).await?,
+        consumers: load_all(pool, CONSUMERS, doc! {}).await?,
+        plugin_configs: load_all(pool, PLUGIN_CONFIGS, doc! {}).await?,
+        certificates: load_all(pool, CERTIFICATES, doc! {}).await?,
+        snis: load_all(pool, SNIS, doc! {}).await?,
+        last_updated_at: get_latest_update_timestamp(pool).await?,
+    };
+    Ok(configuration)
+}
+
+/// Load configuration changes since a specific timestamp
+pub async fn load_configuration_delta(pool: &MongoPool, since: DateTime<Utc>) -> Result<ConfigurationDelta> {
+    info!("Loading configuration delta from MongoDB database since {}", since);
+
+    let changed = doc! { "updated_at": { "$gt": bson::DateTime::from_chrono(since) } };
+    Ok(ConfigurationDelta {
+        updated_proxies: load_all(pool, PROXIES, changed.clone()).await?,
+        deleted_proxy_ids: load_deleted_ids_since(pool, "proxy_deletions", since).await?,
+        updated_consumers: load_all(pool, CONSUMERS, changed.clone()).await?,
+        deleted_consumer_ids: load_deleted_ids_since(pool, "consumer_deletions", since).await?,
+        updated_plugin_configs: load_all(pool, PLUGIN_CONFIGS, changed.clone()).await?,
+        deleted_plugin_config_ids: load_deleted_ids_since(pool, "plugin_config_deletions", since).await?,
+        updated_certificates: load_all(pool, CERTIFICATES, changed.clone()).await?,
+        deleted_certificate_ids: load_deleted_ids_since(pool, "certificate_deletions", since).await?,
+        updated_snis: load_all(pool, SNIS, changed).await?,
+        deleted_sni_ids: load_deleted_ids_since(pool, "sni_deletions", since).await?,
+        last_updated_at: get_latest_update_timestamp(pool).await?,
+    })
+}
+
+pub async fn get_latest_update_timestamp(pool: &MongoPool) -> Result<DateTime<Utc>> {
+    debug!("Getting latest update timestamp from MongoDB database");
+
+    let options = FindOneOptions::builder()
+        .sort(doc! { "updated_at": -1 })
+        .projection(doc! { "updated_at": 1 })
+        .build();
+    let mut latest: Option<DateTime<Utc>> = None;
+    for collection in [PROXIES, CONSUMERS, PLUGIN_CONFIGS, CERTIFICATES, SNIS] {
+        let newest = pool.collection(collection)
+            .find_one(doc! {}, options.clone())
+            .await
+            .context("Failed to get latest update timestamp")?;
+        if let Some(time) = newest.as_ref().and_then(|document| document.get_datetime("updated_at").ok()) {
+            let time = time.to_chrono();
+            latest = Some(latest.map_or(time, |latest| latest.max(time)));
+        }
+    }
+
+    // If there are no documents, return the current time
+    Ok(latest.unwrap_or_else(Utc::now))
+}
+
+async fn load_all<T: DeserializeOwned>(pool: &MongoPool, collection: &str, filter: Document) -> Result<Vec<T>> {
+    let documents: Vec<Document> = pool.collection(collection)
+        .find(filter, None)
+        .await
+        .with_context(|| format!("Failed to load {} from MongoDB database", collection))?
+        .try_collect()
+        .await
+        .with_context(|| format!("Failed to load {} from MongoDB database", collection))?;
+    documents.into_iter().map(from_document).collect()
+}
+
+async fn get_by_id<T: DeserializeOwned>(pool: &MongoPool, collection: &str, id: &str) -> Result<Option<T>> {
+    pool.collection(collection)
+        .find_one(doc! { "_id": id }, None)
+        .await
+        .with_context(|| format!("Failed to load from {}", collection))?
+        .map(from_document)
+        .transpose()
+}
+
+/// IDs recorded in a tombstone collection (e.g. "sni_deletions") after the given time
+pub async fn load_deleted_ids_since(pool: &MongoPool, collection: &str, since: DateTime<Utc>) -> Result<Vec<String>> {
+    let documents: Vec<Document> = pool.collection(collection)
+        .find(doc! { "deleted_at": { "$gt": bson::DateTime::from_chrono(since) } }, None)
+        .await
+        .with_context(|| format!("Failed to fetch deleted IDs from {}", collection))?
+        .try_collect()
+        .await
+        .with_context(|| format!("Failed to fetch deleted IDs from {}", collection))?;
+
+    documents.iter()
+        .map(|document| document.get_str("id").map(str::to_string).map_err(Into::into))
+        .collect()
+}
+
+/// Deletes the tombstones of a collection recorded before `cutoff`
+pub async fn purge_tombstones(pool: &MongoPool, collection: &str, cutoff: DateTime<Utc>) -> Result<u64> {
+    let result = pool.collection(collection)
+        .delete_many(doc! { "deleted_at": { "$lt": bson::DateTime::from_chrono(cutoff) } }, None)
+        .await?;
+    Ok(result.deleted_count)
+}
+
+async fn record_deletion(writer: &mut Writer<'_>, collection: &str, id: &str) -> Result<()> {
+    writer.insert(collection, doc! { "id": id, "deleted_at": bson::DateTime::now() }).await
+        .with_context(|| format!("Failed to record deletion in {}", collection))
+}
+
+/// Stores a new version of an entity if the stored one still has the version
+/// the update was based on
+async fn replace_versioned(
+    writer: &mut Writer<'_>,
+    collection: &str,
+    entity: &'static str,
+    id: &str,
+    expected: i64,
+    mut document: Document,
+) -> Result<()> {
+    document.insert("version", expected + 1);
+    document.insert("updated_at", bson::DateTime::now());
+    if writer.replace(collection, doc! { "_id": id, "version": expected }, document).await? {
+        return Ok(());
+    }
+    if writer.exists(collection, doc! { "_id": id }).await? {
+        // The document exists, so it was changed since the caller read it
+        Err(StaleVersion::new(entity, id, expected).into())
+    } else {
+        bail!("{} with ID '{}' does not exist", capitalize(entity), id)
+    }
+}
+
+fn capitalize(entity: &str) -> String {
+    let mut chars = entity.chars();
+    chars.next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+pub async fn create_proxy(pool: &MongoPool, proxy: &Proxy) -> Result<Proxy> {
+    create_proxy_in(&mut Writer::new(pool), proxy).await
+}
+
+async fn create_proxy_in(writer: &mut Writer<'_>, proxy: &Proxy) -> Result<Proxy> {
+    info!("Creating proxy in MongoDB database: {}", proxy.id);
+
+    if writer.exists(PROXIES, doc! { "listen_path": &proxy.listen_path }).await? {
+        bail!("Proxy with listen_path '{}' already exists", proxy.listen_path);
+    }
+    writer.insert(PROXIES, to_document(proxy)?).await
+        .context("Failed to create proxy in MongoDB")?;
+
+    info!("Created proxy with ID: {}", proxy.id);
+    Ok(proxy.clone())
+}
+
+pub async fn get_proxy_by_id(pool: &MongoPool, proxy_id: &str) -> Result<Proxy> {
+    get_by_id(pool, PROXIES, proxy_id).await?
+        .ok_or_else(|| anyhow!("Proxy with ID '{}' not found", proxy_id))
+}
+
+pub async fn update_proxy(pool: &MongoPool, proxy: &Proxy) -> Result<()> {
+    update_proxy_in(&mut Writer::new(pool), proxy).await
+}
+
+async fn update_proxy_in(writer: &mut Writer<'_>, proxy: &Proxy) -> Result<()> {
+    info!("Updating proxy in MongoDB database: {}", proxy.id);
+
+    if writer.exists(PROXIES, doc! { "listen_path": &proxy.listen_path, "_id": { "$ne": &proxy.id } }).await? {
+        bail!("Another proxy with listen_path '{}' already exists", proxy.listen_path);
+    }
+    replace_versioned(writer, PROXIES, "proxy", &proxy.id, proxy.version, to_document(proxy)?).await
+}
+
+/// Delete a proxy and record its deletion
+pub async fn delete_proxy(pool: &MongoPool, proxy_id: &str) -> Result<()> {
+    info!("Deleting proxy with ID: {}", proxy_id);
+
+    let deleted = pool.collection(PROXIES)
+        .delete_one(doc! { "_id": proxy_id }, None)
+        .await
+        .context("Failed to delete proxy")?;
+    if deleted.deleted_count == 0 {
+        bail!("Proxy with ID '{}' does not exist", proxy_id);
+    }
+    record_deletion(&mut Writer::new(pool), "proxy_deletions", proxy_id).await
+}
+
+pub async fn get_consumer_by_id(pool: &MongoPool, consumer_id: &str) -> Result<Consumer> {
+    get_by_id(pool, CONSUMERS, consumer_id).await?
+        .ok_or_else(|| anyhow!("Consumer with ID '{}' not found", consumer_id))
+}
+
+pub async fn create_consumer(pool: &MongoPool, consumer: &Consumer) -> Result<String> {
+    create_consumer_in(&mut Writer::new(pool), consumer).await
+}
+
+async fn create_consumer_in(writer: &mut Writer<'_>, consumer: &Consumer) -> Result<String> {
+    info!("Creating consumer in MongoDB database: {}", consumer.id);
+
+    if writer.exists(CONSUMERS, doc! { "username": &consumer.username, "workspace": &consumer.workspace }).await? {
+        bail!("A consumer with username '{}' already exists", consumer.username);
+    }
+    if let Some(custom_id) = &consumer.custom_id {
+        if writer.exists(CONSUMERS, doc! { "custom_id": custom_id }).await? {
+            bail!("A consumer with custom_id '{}' already exists", custom_id);
+        }
+    }
+    writer.insert(CONSUMERS, to_document(consumer)?).await
+        .context("Failed to create consumer in MongoDB")?;
+
+    info!("Created consumer with ID: {}", consumer.id);
+    Ok(consumer.id.clone())
+}
+
+pub async fn update_consumer(pool: &MongoPool, consumer: &Consumer) -> Result<()> {
+    update_consumer_in(&mut Writer::new(pool), consumer).await
+}
+
+async fn update_consumer_in(writer: &mut Writer<'_>, consumer: &Consumer) -> Result<()> {
+    info!("Updating consumer in MongoDB database: {}", consumer.id);
+
+    let others = doc! { "$ne": &consumer.id };
+    if writer.exists(CONSUMERS, doc! {
+        "username": &consumer.username, "workspace": &consumer.workspace, "_id": others.clone(),
+    }).await? {
+        bail!("Another consumer with username '{}' already exists", consumer.username);
+    }
+    if let Some(custom_id) = &consumer.custom_id {
+        if writer.exists(CONSUMERS, doc! { "custom_id": custom_id, "_id": others }).await? {
+            bail!("Another consumer with custom_id '{}' already exists", custom_id);
+        }
+    }
+    replace_versioned(writer, CONSUMERS, "consumer", &consumer.id, consumer.version, to_document(consumer)?).await
+}
+
+/// Delete a consumer and record its deletion
+pub async fn delete_consumer(pool: &MongoPool, consumer_id: &str) -> Result<()> {
+    info!("Deleting consumer with ID: {}", consumer_id);
+
+    let deleted = pool.collection(CONSUMERS)
+        .delete_one(doc! { "_id": consumer_id }, None)
+        .await
+        .context("Failed to delete consumer")?;
+    if deleted.deleted_count == 0 {
+        bail!("Consumer with ID '{}' does not exist", consumer_id);
+    }
+    record_deletion(&mut Writer::new(pool), "consumer_deletions", consumer_id).await
+}
+
+pub async fn create_plugin_config(pool: &MongoPool, plugin_config: &PluginConfig) -> Result<String> {
+    create_plugin_config_in(&mut Writer::new(pool), plugin_config).await
+}
+
+async fn create_plugin_config_in(writer: &mut Writer<'_>, plugin_config: &PluginConfig) -> Result<String> {
+    info!("Creating new plugin configuration in MongoDB database: {}", plugin_config.plugin_name);
+
+    writer.insert(PLUGIN_CONFIGS, to_document(plugin_config)?).await
+        .context("Failed to create plugin configuration in MongoDB")?;
+    Ok(plugin_config.id.clone())
+}
+
+pub async fn update_plugin_config(pool: &MongoPool, plugin_config: &PluginConfig) -> Result<()> {
+    update_plugin_config_in(&mut Writer::new(pool), plugin_config).await
+}
+
+async fn update_plugin_config_in(writer: &mut Writer<'_>, plugin_config: &PluginConfig) -> Result<()> {
+    info!("Updating plugin configuration in MongoDB database: {}", plugin_config.id);
+
+    replace_versioned(
+        writer, PLUGIN_CONFIGS, "plugin config", &plugin_config.id, plugin_config.version, to_document(plugin_config)?,
+    ).await
+}
+
+/// Delete a plugin config, remove it from the proxies using it and record its deletion
+pub async fn delete_plugin_config(pool: &MongoPool, plugin_config_id: &str) -> Result<()> {
+    info!("Deleting plugin configuration with ID: {}", plugin_config_id);
+
+    let deleted = pool.collection(PLUGIN_CONFIGS)
+        .delete_one(doc! { "_id": plugin_config_id }, None)
+        .await
+        .context("Failed to delete plugin configuration")?;
+    if deleted.deleted_count == 0 {
+        bail!("Plugin configuration with ID '{}' does not exist", plugin_config_id);
+    }
+
+    // The proxies change too, so the next delta carries them without the plugin
+    pool.collection(PROXIES)
+        .update_many(
+            doc! { "plugins.plugin_config_id": plugin_config_id },
+            doc! {
+                "$pull": { "plugins": { "plugin_config_id": plugin_config_id } },
+                "$set": { "updated_at": bson::DateTime::now() },
+            },
+            None,
+        )
+        .await
+        .context("Failed to delete plugin-proxy associations")?;
+
+    record_deletion(&mut Writer::new(pool), "plugin_config_deletions", plugin_config_id).await
+}
+
+/// Writes many proxies, consumers and plugin configs in one transaction. MongoDB
+/// only has transactions on replica sets and sharded clusters.
+pub async fn apply_bulk(pool: &MongoPool, changes: &BulkChanges) -> Result<()> {
+    let mut session = pool.client.start_session(None).await.context("Failed to start MongoDB session")?;
+    session.start_transaction(None).await
+        .context("Failed to begin transaction, bulk imports need a MongoDB replica set")?;
+
+    let result = apply_bulk_in(&mut Writer { pool, session: Some(&mut session) }, changes).await;
+    match result {
+        Ok(()) => session.commit_transaction().await.context("Failed to commit transaction"),
+        Err(e) => {
+            if let Err(abort_error) = session.abort_transaction().await {
+                debug!("Failed to abort transaction: {}", abort_error);
+            }
+            Err(e)
+        },
+    }
+}
+
+async fn apply_bulk_in(writer: &mut Writer<'_>, changes: &BulkChanges) -> Result<()> {
+    for plugin_config in &changes.created_plugin_configs {
+        create_plugin_config_in(writer, plugin_config).await
+            .context(format!("Failed to create plugin config {}", plugin_config.id))?;
+    }
+    for plugin_config in &changes.updated_plugin_configs {
+        update_plugin_config_in(writer, plugin_config).await
+            .context(format!("Failed to update plugin config {}", plugin_config.id))?;
+    }
+    for consumer in &changes.created_consumers {
+        create_consumer_in(writer, consumer).await
+            .context(format!("Failed to create consumer {}", consumer.id))?;
+    }
+    for consumer in &changes.updated_consumers {
+        update_consumer_in(writer, consumer).await
+            .context(format!("Failed to update consumer {}", consumer.id))?;
+    }
+    for proxy in &changes.created_proxies {
+        create_proxy_in(writer, proxy).await
+            .context(format!("Failed to create proxy {}", proxy.id))?;
+    }
+    for proxy in &changes.updated_proxies {
+        update_proxy_in(writer, proxy).await
+            .context(format!("Failed to update proxy {}", proxy.id))?;
+    }
+    Ok(())
+}
+
+/// Load the TLS certificates served by the proxy listeners
+pub async fn load_certificates(pool: &MongoPool) -> Result<Vec<Certificate>> {
+    load_all(pool, CERTIFICATES, doc! {}).await
+}
+
+pub async fn get_certificate_by_id(pool: &MongoPool, certificate_id: &str) -> Result<Certificate> {
+    get_by_id(pool, CERTIFICATES, certificate_id).await?
+        .ok_or_else(|| anyhow!("Certificate with ID '{}' not found", certificate_id))
+}
+
+/// Insert or replace a certificate
+pub async fn upsert_certificate(pool: &MongoPool, certificate: &Certificate) -> Result<()> {
+    upsert(pool, CERTIFICATES, &certificate.id, to_document(certificate)?).await
+        .context("Failed to store certificate in MongoDB database")?;
+    info!("Stored certificate {}", certificate.id);
+    Ok(())
+}
+
+/// Delete a certificate and the SNIs mapped to it, recording their deletions
+pub async fn delete_certificate(pool: &MongoPool, certificate_id: &str) -> Result<()> {
+    let snis: Vec<Sni> = load_all(pool, SNIS, doc! { "certificate_id": certificate_id }).await?;
+    pool.collection(SNIS)
+        .delete_many(doc! { "certificate_id": certificate_id }, None)
+        .await
+        .context("Failed to delete SNIs of certificate")?;
+
+    let deleted = pool.collection(CERTIFICATES)
+        .delete_one(doc! { "_id": certificate_id }, None)
+        .await
+        .context("Failed to delete certificate")?;
+    if deleted.deleted_count == 0 {
+        return Err(anyhow!("Certificate with ID '{}' not found", certificate_id));
+    }
+
+    let mut writer = Writer::new(pool);
+    for sni in &snis {
+        record_deletion(&mut writer, "sni_deletions", &sni.id).await?;
+    }
+    record_deletion(&mut writer, "certificate_deletions", certificate_id).await?;
+
+    info!("Deleted certificate with ID: {}", certificate_id);
+    Ok(())
+}
+
+/// Load the SNI mappings managed through the Admin API
+pub async fn load_snis(pool: &MongoPool) -> Result<Vec<Sni>> {
+    load_all(pool, SNIS, doc! {}).await
+}
+
+/// Insert or replace an SNI
+pub async fn upsert_sni(pool: &MongoPool, sni: &Sni) -> Result<()> {
+    upsert(pool, SNIS, &sni.id, to_document(sni)?).await
+        .context("Failed to store SNI in MongoDB database")?;
+    info!("Stored SNI {} for certificate {}", sni.name, sni.certificate_id);
+    Ok(())
+}
+
+/// Delete an SNI by ID
+pub async fn delete_sni(pool: &MongoPool, sni_id: &str) -> Result<()> {
+    let deleted = pool.collection(SNIS)
+        .delete_one(doc! { "_id": sni_id }, None)
+        .await
+        .context("Failed to delete SNI")?;
+    if deleted.deleted_count == 0 {
+        return Err(anyhow!("SNI with ID '{}' not found", sni_id));
+    }
+    record_deletion(&mut Writer::new(pool), "sni_deletions", sni_id).await?;
+
+    info!("Deleted SNI with ID: {}", sni_id);
+    Ok(())
+}
+
+async fn upsert(pool: &MongoPool, collection: &str, id: &str, document: Document) -> Result<()> {
+    pool.collection(collection)
+        .replace_one(doc! { "_id": id }, document, ReplaceOptions::builder().upsert(true).build())
+        .await?;
+    Ok(())
+}
+
+/// Next value of a named sequence, starting at 1
+async fn next_id(pool: &MongoPool, sequence: &str) -> Result<i64> {
+    let options = FindOneAndUpdateOptions::builder()
+        .upsert(true)
+        .return_document(ReturnDocument::After)
+        .build();
+    let counter = pool.collection(COUNTERS)
+        .find_one_and_update(doc! { "_id": sequence }, doc! { "$inc": { "value": 1_i64 } }, options)
+        .await
+        .with_context(|| format!("Failed to allocate an ID for {}", sequence))?
+        .ok_or_else(|| anyhow!("Counter {} was not created", sequence))?;
+    Ok(counter.get_i64("value")?)
+}
+
+/// Record a configuration version, pruning versions beyond the retention
+pub async fn insert_config_version(pool: &MongoPool, author: &str, description: &str, document: &str, retain: u64) -> Result<i64> {
+    let id = next_id(pool, CONFIG_VERSIONS).await?;
+    pool.collection(CONFIG_VERSIONS)
+        .insert_one(doc! {
+            "_id": id,
+            "author": author,
+            "description": description,
+            "document": document,
+            "created_at": bson::DateTime::now(),
+        }, None)
+        .await
+        .context("Failed to store configuration version")?;
+
+    if retain > 0 {
+        pool.collection(CONFIG_VERSIONS)
+            .delete_many(doc! { "_id": { "$lte": id - retain as i64 } }, None)
+            .await
+            .context("Failed to prune configuration versions")?;
+    }
+
+    info!("Recorded configuration version {} by {}", id, author);
+    Ok(id)
+}
+
+/// List configuration versions, newest first, without their documents
+pub async fn list_config_versions(pool: &MongoPool) -> Result<Vec<ConfigVersion>> {
+    let options = FindOptions::builder()
+        .sort(doc! { "_id": -1 })
+        .projection(doc! { "document": 0 })
+        .build();
+    let documents: Vec<Document> = pool.collection(CONFIG_VERSIONS)
+        .find(doc! {}, options)
+        .await
+        .context("Failed to load configuration versions")?
+        .try_collect()
+        .await
+        .context("Failed to load configuration versions")?;
+    documents.into_iter().map(config_version_from_document).collect()
+}
+
+/// Get a configuration version with its document, or the latest one when no ID is given
+pub async fn get_config_version(pool: &MongoPool, version_id: Option<i64>) -> Result<Option<ConfigVersion>> {
+    let filter = match version_id {
+        Some(id) => doc! { "_id": id },
+        None => doc! {},
+    };
+    let options = FindOneOptions::builder().sort(doc! { "_id": -1 }).build();
+    pool.collection(CONFIG_VERSIONS)
+        .find_one(filter, options)
+        .await
+        .context("Failed to load configuration version")?
+        .map(config_version_from_document)
+        .transpose()
+}
+
+fn config_version_from_document(document: Document) -> Result<ConfigVersion> {
+    let config = match document.get_str("document") {
+        Ok(stored) => Some(serde_json::from_str::<Configuration>(stored).context("Stored configuration version is not valid")?),
+        Err(_) => None,
+    };
+
+    Ok(ConfigVersion {
+        id: document.get_i64("_id")?,
+        author: document.get_str("author")?.to_string(),
+        description: document.get_str("description")?.to_string(),
+        created_at: document.get_datetime("created_at")?.to_chrono(),
+        config,
+    })
+}
+
+/// Record an Admin API change in the audit log
+pub async fn insert_audit_entry(pool: &MongoPool, entry: &AuditEntry) -> Result<()> {
+    let mut entry = entry.clone();
+    entry.id = next_id(pool, AUDIT_LOG).await?;
+    pool.collection(AUDIT_LOG)
+        .insert_one(to_document(&entry)?, None)
+        .await
+        .context("Failed to store audit log entry in MongoDB database")?;
+    Ok(())
+}
+
+/// List audit log entries matching a filter, newest first, with the total number of matches
+pub async fn list_audit_entries(pool: &MongoPool, filter: &AuditFilter, limit: usize, offset: usize) -> Result<(Vec<AuditEntry>, usize)> {
+    let filter = audit_filter_document(filter);
+    let collection = pool.collection(AUDIT_LOG);
+    let total = collection.count_documents(filter.clone(), None)
+        .await
+        .context("Failed to count audit log entries in MongoDB database")?;
+
+    let options = FindOptions::builder()
+        .sort(doc! { "_id": -1 })
+        .skip(offset as u64)
+        .limit(limit as i64)
+        .build();
+    let documents: Vec<Document> = collection.find(filter, options)
+        .await
+        .context("Failed to load audit log entries from MongoDB database")?
+        .try_collect()
+        .await
+        .context("Failed to load audit log entries from MongoDB database")?;
+
+    let entries = documents.into_iter().map(from_document).collect::<Result<Vec<_>>>()?;
+    Ok((entries, total as usize))
+}
+
+fn audit_filter_document(filter: &AuditFilter) -> Document {
+    let mut document = Document::new();
+    for (field, value) in [
+        ("actor", &filter.actor),
+        ("action", &filter.action),
+        ("entity_type", &filter.entity_type),
+        ("entity_id", &filter.entity_id),
+    ] {
+        if let Some(value) = value {
+            document.insert(field, value.as_str());
+        }
+    }
+    let mut created_at = Document::new();
+    if let Some(since) = filter.since {
+        created_at.insert("$gte", bson::DateTime::from_chrono(since));
+    }
+    if let Some(until) = filter.until {
+        created_at.insert("$lt", bson::DateTime::from_chrono(until));
+    }
+    if !created_at.is_empty() {
+        document.insert("created_at", created_at);
+    }
+    if let Some(before_id) = filter.before_id {
+        document.insert("_id", doc! { "$lt": before_id });
+    }
+    document
+}
+
+/// List the Admin API users by username
+pub async fn list_admin_users(pool: &MongoPool) -> Result<Vec<AdminUser>> {
+    let options = FindOptions::builder().sort(doc! { "_id": 1 }).build();
+    pool.collection(ADMIN_USERS)
+        .find(doc! {}, options)
+        .await
+        .context("Failed to load admin users from MongoDB database")?
+        .try_collect::<Vec<_>>()
+        .await
+        .context("Failed to load admin users from MongoDB database")?
+        .into_iter()
+        .map(admin_user_from_document)
+        .collect()
+}
+
+/// Get an Admin API user by username
+pub async fn get_admin_user(pool: &MongoPool, username: &str) -> Result<Option<AdminUser>> {
+    pool.collection(ADMIN_USERS)
+        .find_one(doc! { "_id": username }, None)
+        .await
+        .context("Failed to fetch admin user from MongoDB database")?
+        .map(admin_user_from_document)
+        .transpose()
+}
+
+/// Insert an Admin API user, or replace the password and role of an existing one.
+/// Users are keyed by username; the password hash is stored, unlike in the
+/// user's JSON form.
+pub async fn upsert_admin_user(pool: &MongoPool, user: &AdminUser) -> Result<()> {
+    let document = doc! {
+        "_id": &user.username,
+        "password_hash": &user.password_hash,
+        "role": user.role.as_str(),
+        "created_at": bson::DateTime::from_chrono(user.created_at),
+        "updated_at": bson::DateTime::from_chrono(user.updated_at),
+    };
+    upsert(pool, ADMIN_USERS, &user.username, document).await
+        .context("Failed to store admin user in MongoDB database")?;
+    info!("Stored admin user {} with role {}", user.username, user.role.as_str());
+    Ok(())
+}
+
+/// Delete an Admin API user, returning whether it existed
+pub async fn delete_admin_user(pool: &MongoPool, username: &str) -> Result<bool> {
+    let deleted = pool.collection(ADMIN_USERS)
+        .delete_one(doc! { "_id": username }, None)
+        .await
+        .context("Failed to delete admin user")?;
+    Ok(deleted.deleted_count > 0)
+}
+
+fn admin_user_from_document(document: Document) -> Result<AdminUser> {
+    Ok(AdminUser {
+        username: document.get_str("_id")?.to_string(),
+        password_hash: document.get_str("password_hash")?.to_string(),
+        role: document.get_str("role")?.parse().map_err(|e: String| anyhow!("Stored admin user has an invalid role: {}", e))?,
+        created_at: document.get_datetime("created_at")?.to_chrono(),
+        updated_at: document.get_datetime("updated_at")?.to_chrono(),
+    })
+}
+
+/// Revoke an Admin API token until it expires, dropping revocations of expired tokens
+pub async fn revoke_admin_token(pool: &MongoPool, jti: &str, username: &str, expires_at: DateTime<Utc>) -> Result<()> {
+    pool.collection(ADMIN_TOKEN_REVOCATIONS)
+        .delete_many(doc! { "expires_at": { "$lt": bson::DateTime::now() } }, None)
+        .await
+        .context("Failed to purge expired admin token revocations")?;
+
+    let document = doc! {
+        "_id": jti,
+        "username": username,
+        "expires_at": bson::DateTime::from_chrono(expires_at),
+        "revoked_at": bson::DateTime::now(),
+    };
+    upsert(pool, ADMIN_TOKEN_REVOCATIONS, jti, document).await
+        .context("Failed to revoke admin token in MongoDB database")
+}
+
+/// Whether an Admin API token has been revoked
+pub async fn is_admin_token_revoked(pool: &MongoPool, jti: &str) -> Result<bool> {
+    let revocation = pool.collection(ADMIN_TOKEN_REVOCATIONS)
+        .find_one(doc! { "_id": jti }, None)
+        .await
+        .context("Failed to check admin token revocation in MongoDB database")?;
+    Ok(revocation.is_some())
+}
+
+/// Store an Admin API token created through /tokens
+pub async fn create_api_token(pool: &MongoPool, token: &ApiToken) -> Result<()> {
+    pool.collection(ADMIN_API_TOKENS)
+        .insert_one(to_document(token)?, None)
+        .await
+        .context("Failed to store admin API token in MongoDB database")?;
+    Ok(())
+}
+
+/// List the Admin API tokens, of one user or of everyone, newest first
+pub async fn list_api_tokens(pool: &MongoPool, username: Option<&str>) -> Result<Vec<ApiToken>> {
+    let filter = match username {
+        Some(username) => doc! { "username": username },
+        None => doc! {},
+    };
+    let options = FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+    pool.collection(ADMIN_API_TOKENS)
+        .find(filter, options)
+        .await
+        .context("Failed to load admin API tokens from MongoDB database")?
+        .try_collect::<Vec<_>>()
+        .await
+        .context("Failed to load admin API tokens from MongoDB database")?
+        .into_iter()
+        .map(from_document)
+        .collect()
+}
+
+/// Get an Admin API token by ID
+pub async fn get_api_token(pool: &MongoPool, id: &str) -> Result<Option<ApiToken>> {
+    pool.collection(ADMIN_API_TOKENS)
+        .find_one(doc! { "_id": id }, None)
+        .await
+        .context("Failed to fetch admin API token from MongoDB database")?
+        .map(from_document)
+        .transpose()
+}
+
+/// Delete an Admin API token, returning whether it existed
+pub async fn delete_api_token(pool: &MongoPool, id: &str) -> Result<bool> {
+    let deleted = pool.collection(ADMIN_API_TOKENS)
+        .delete_one(doc! { "_id": id }, None)
+        .await
+        .context("Failed to delete admin API token")?;
+    Ok(deleted.deleted_count > 0)
+}
+
+/// Creates the indexes the gateway relies on and records the schema version.
+/// Collections are created by MongoDB on first write.
+pub async fn ensure_schema(pool: &MongoPool) -> Result<()> {
+    let unique = || IndexOptions::builder().unique(true).build();
+    let indexes: [(&str, Document, Option<IndexOptions>); 12] = [
+        (PROXIES, doc! { "listen_path": 1 }, Some(unique())),
+        (PROXIES, doc! { "updated_at": 1 }, None),
+        (PROXIES, doc! { "tags": 1 }, None),
+        (CONSUMERS, doc! { "workspace": 1, "username": 1 }, Some(unique())),
+        (CONSUMERS, doc! { "updated_at": 1 }, None),
+        (CONSUMERS, doc! { "tags": 1 }, None),
+        (PLUGIN_CONFIGS, doc! { "updated_at": 1 }, None),
+        (CERTIFICATES, doc! { "updated_at": 1 }, None),
+        (SNIS, doc! { "name": 1 }, Some(unique())),
+        (AUDIT_LOG, doc! { "created_at": 1 }, None),
+        (ADMIN_TOKEN_REVOCATIONS, doc! { "expires_at": 1 }, None),
+        (ADMIN_API_TOKENS, doc! { "username": 1 }, None),
+    ];
+    for (collection, keys, options) in indexes {
+        let index = IndexModel::builder().keys(keys).options(options).build();
+        pool.collection(collection)
+            .create_index(index, None)
+            .await
+            .with_context(|| format!("Failed to create index on {}", collection))?;
+    }
+    for collection in super::tombstones::TOMBSTONE_TABLES {
+        let index = IndexModel::builder().keys(doc! { "deleted_at": 1 }).build();
+        pool.collection(collection)
+            .create_index(index, None)
+            .await
+            .with_context(|| format!("Failed to create index on {}", collection))?;
+    }
+
+    pool.collection(SCHEMA)
+        .replace_one(
+            doc! { "_id": "version" },
+            doc! { "_id": "version", "value": SCHEMA_VERSION },
+            ReplaceOptions::builder().upsert(true).build(),
+        )
+        .await
+        .context("Failed to record the MongoDB schema version")?;
+    Ok(())
+}
+
+/// Schema version recorded by `ensure_schema`, None for a database it never ran on
+pub async fn schema_version(pool: &MongoPool) -> Result<Option<i64>> {
+    let version = pool.collection(SCHEMA)
+        .find_one(doc! { "_id": "version" }, None)
+        .await
+        .context("Failed to read the MongoDB schema version")?;
+    Ok(version.and_then(|document| document.get_i64("value").ok()))
+}
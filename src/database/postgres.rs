@@ -1,11 +1,12 @@
-use anyhow::{Result, Context};
-use sqlx::{Pool, Postgres, Transaction};
+use anyhow::{anyhow, Result, Context};
+use sqlx::{postgres::{PgConnection, PgRow}, Pool, Postgres, QueryBuilder, Row, Transaction};
 use tracing::{info, error, debug};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use serde_json::Value;
 
-use crate::config::data_model::{Configuration, Proxy, Consumer, PluginConfig, PluginAssociation, Protocol, AuthMode};
+use super::{BulkChanges, StaleVersion};
+use crate::config::data_model::{Configuration, Proxy, Consumer, PluginConfig, PluginAssociation, Protocol, AuthMode, Certificate, Sni, ConfigVersion, AuditEntry, AuditFilter, AdminUser, ApiToken};
 
 #[cfg(test)]
 const SKIP_DELETION_TRACKING: bool = true;
@@ -34,6 +35,7 @@ pub async fn load_full_configuration(pool: &Pool<Postgres>) -> Result<Configurat
             backend_tls_server_ca_cert_path, 
             dns_override, dns_cache_ttl_seconds,
             auth_mode as "auth_mode: String",
+            error_templates, backend_discovery, dns_resolvers, log_sampling, api_versioning, max_requests_per_second, backend_keepalive, backend_header_case, upstream_host, egress_proxy, workspace, version, tags, enabled,
             created_at, updated_at
         FROM proxies
         ORDER BY created_at
@@ -66,7 +68,7 @@ pub async fn load_full_configuration(pool: &Pool<Postgres>) -> Result<Configurat
         Consumer,
         r#"
         SELECT 
-            id, username, custom_id, credentials, created_at, updated_at
+            id, username, workspace, version, tags, custom_id, credentials, created_at, updated_at
         FROM consumers
         ORDER BY created_at
         "#
@@ -80,7 +82,7 @@ pub async fn load_full_configuration(pool: &Pool<Postgres>) -> Result<Configurat
         PluginConfig,
         r#"
         SELECT 
-            id, plugin_name, config, scope, proxy_id, consumer_id, 
+            id, plugin_name, workspace, version, tags, config, scope, proxy_id, consumer_id, 
             enabled, created_at, updated_at
         FROM plugin_configs
         ORDER BY created_at
@@ -119,12 +121,17 @@ pub async fn load_full_configuration(pool: &Pool<Postgres>) -> Result<Configurat
     // Commit the transaction
     tx.commit().await.context("Failed to commit transaction")?;
     
+    let certificates = load_certificates(pool).await?;
+    let snis = load_snis(pool).await?;
+    
     // Get the latest update timestamp
     let last_updated_at = proxies
         .iter()
         .map(|p| p.updated_at)
         .chain(consumers.iter().map(|c| c.updated_at))
         .chain(plugin_configs.iter().map(|pc| pc.updated_at))
+        .chain(certificates.iter().map(|c| c.updated_at))
+        .chain(snis.iter().map(|s| s.updated_at))
         .max()
         .unwrap_or_else(Utc::now);
     
@@ -132,6 +139,8 @@ pub async fn load_full_configuration(pool: &Pool<Postgres>) -> Result<Configurat
         proxies,
         consumers,
         plugin_configs,
+        certificates,
+        snis,
         last_updated_at,
     };
     
@@ -143,10 +152,15 @@ pub async fn load_full_configuration(pool: &Pool<Postgres>) -> Result<Configurat
 
 /// Create a new proxy in the database
 pub async fn create_proxy(pool: &Pool<Postgres>, proxy: Proxy) -> Result<Proxy> {
-    info!("Creating new proxy in PostgreSQL database: {}", proxy.id);
-    
-    // Begin a transaction
     let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+    let proxy = create_proxy_in(&mut *tx, proxy).await?;
+    tx.commit().await.context("Failed to commit transaction")?;
+    Ok(proxy)
+}
+
+/// `create_proxy` on a connection or transaction the caller commits
+pub async fn create_proxy_in(tx: &mut PgConnection, proxy: Proxy) -> Result<Proxy> {
+    info!("Creating new proxy in PostgreSQL database: {}", proxy.id);
     
     // Check if listen_path already exists
     let exists = sqlx::query!(
@@ -186,9 +200,9 @@ pub async fn create_proxy(pool: &Pool<Postgres>, proxy: Proxy) -> Result<Proxy>
             backend_connect_timeout_ms, backend_read_timeout_ms, backend_write_timeout_ms,
             backend_tls_client_cert_path, backend_tls_client_key_path,
             backend_tls_verify_server_cert, backend_tls_server_ca_cert_path,
-            dns_override, dns_cache_ttl_seconds, auth_mode
+            dns_override, dns_cache_ttl_seconds, auth_mode, error_templates, backend_discovery, dns_resolvers, log_sampling, api_versioning, max_requests_per_second, backend_keepalive, backend_header_case, upstream_host, egress_proxy, workspace, tags, enabled
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31)
         RETURNING id, created_at, updated_at
         "#,
         proxy.name,
@@ -208,7 +222,20 @@ pub async fn create_proxy(pool: &Pool<Postgres>, proxy: Proxy) -> Result<Proxy>
         proxy.backend_tls_server_ca_cert_path,
         proxy.dns_override,
         proxy.dns_cache_ttl_seconds.map(|ttl| ttl as i64),
-        auth_mode_str
+        auth_mode_str,
+        serde_json::to_value(&proxy.error_templates).unwrap_or_default(),
+        proxy.backend_discovery.as_ref().map(|d| serde_json::to_value(d).unwrap_or_default()),
+        serde_json::to_value(&proxy.dns_resolvers).unwrap_or_default(),
+        proxy.log_sampling.as_ref().map(|s| serde_json::to_value(s).unwrap_or_default()),
+        proxy.api_versioning.as_ref().map(|v| serde_json::to_value(v).unwrap_or_default()),
+        proxy.max_requests_per_second.map(|rps| rps as i64),
+        proxy.backend_keepalive.as_ref().map(|k| serde_json::to_value(k).unwrap_or_default()),
+        proxy.backend_header_case.map(|c| c.as_str()),
+        proxy.upstream_host,
+        proxy.egress_proxy,
+        proxy.workspace,
+        &proxy.tags,
+        proxy.enabled
     )
     .fetch_one(&mut *tx)
     .await
@@ -232,9 +259,6 @@ pub async fn create_proxy(pool: &Pool<Postgres>, proxy: Proxy) -> Result<Proxy>
         .context("Failed to insert plugin association")?;
     }
     
-    // Commit the transaction
-    tx.commit().await.context("Failed to commit transaction")?;
-    
     // Load the newly created proxy
     let mut new_proxy = proxy.clone();
     new_proxy.id = inserted_proxy.id;
@@ -247,10 +271,15 @@ pub async fn create_proxy(pool: &Pool<Postgres>, proxy: Proxy) -> Result<Proxy>
 
 /// Update an existing proxy in the database
 pub async fn update_proxy(pool: &Pool<Postgres>, proxy: Proxy) -> Result<Proxy> {
-    info!("Updating proxy in PostgreSQL database: {}", proxy.id);
-    
-    // Begin a transaction
     let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+    let proxy = update_proxy_in(&mut *tx, proxy).await?;
+    tx.commit().await.context("Failed to commit transaction")?;
+    Ok(proxy)
+}
+
+/// `update_proxy` on a connection or transaction the caller commits
+pub async fn update_proxy_in(tx: &mut PgConnection, proxy: Proxy) -> Result<Proxy> {
+    info!("Updating proxy in PostgreSQL database: {}", proxy.id);
     
     // Check if proxy exists
     let exists = sqlx::query!(
@@ -319,9 +348,23 @@ pub async fn update_proxy(pool: &Pool<Postgres>, proxy: Proxy) -> Result<Proxy>
             dns_override = $16,
             dns_cache_ttl_seconds = $17,
             auth_mode = $18,
+            error_templates = $19,
+            backend_discovery = $20,
+            dns_resolvers = $21,
+            log_sampling = $22,
+            api_versioning = $23,
+            max_requests_per_second = $24,
+            backend_keepalive = $25,
+            backend_header_case = $26,
+            upstream_host = $27,
+            egress_proxy = $28,
+            workspace = $29,
+            tags = $30,
+            enabled = $31,
+            version = version + 1,
             updated_at = CURRENT_TIMESTAMP
-        WHERE id = $19
-        RETURNING updated_at
+        WHERE id = $32 AND version = $33
+        RETURNING updated_at, version
         "#,
         proxy.name,
         proxy.listen_path,
@@ -341,11 +384,27 @@ pub async fn update_proxy(pool: &Pool<Postgres>, proxy: Proxy) -> Result<Proxy>
         proxy.dns_override,
         proxy.dns_cache_ttl_seconds.map(|ttl| ttl as i64),
         auth_mode_str,
-        proxy.id
+        serde_json::to_value(&proxy.error_templates).unwrap_or_default(),
+        proxy.backend_discovery.as_ref().map(|d| serde_json::to_value(d).unwrap_or_default()),
+        serde_json::to_value(&proxy.dns_resolvers).unwrap_or_default(),
+        proxy.log_sampling.as_ref().map(|s| serde_json::to_value(s).unwrap_or_default()),
+        proxy.api_versioning.as_ref().map(|v| serde_json::to_value(v).unwrap_or_default()),
+        proxy.max_requests_per_second.map(|rps| rps as i64),
+        proxy.backend_keepalive.as_ref().map(|k| serde_json::to_value(k).unwrap_or_default()),
+        proxy.backend_header_case.map(|c| c.as_str()),
+        proxy.upstream_host,
+        proxy.egress_proxy,
+        proxy.workspace,
+        &proxy.tags,
+        proxy.enabled,
+        proxy.id,
+        proxy.version
     )
-    .fetch_one(&mut *tx)
+    .fetch_optional(&mut *tx)
     .await
-    .context("Failed to update proxy")?;
+    .context("Failed to update proxy")?
+    // The row exists, so it was changed since the caller read it
+    .ok_or_else(|| StaleVersion::new("proxy", &proxy.id, proxy.version))?;
     
     // Delete existing plugin associations
     sqlx::query!(
@@ -374,12 +433,10 @@ pub async fn update_proxy(pool: &Pool<Postgres>, proxy: Proxy) -> Result<Proxy>
         .context("Failed to insert plugin association")?;
     }
     
-    // Commit the transaction
-    tx.commit().await.context("Failed to commit transaction")?;
-    
     // Return the updated proxy
     let mut updated_proxy = proxy.clone();
     updated_proxy.updated_at = updated.updated_at;
+    updated_proxy.version = updated.version;
     
     info!("Updated proxy with ID: {}", updated_proxy.id);
     Ok(updated_proxy)
@@ -447,15 +504,20 @@ pub async fn delete_proxy(pool: &Pool<Postgres>, proxy_id: &str) -> Result<()> {
 
 /// Create a new consumer in the database
 pub async fn create_consumer(pool: &Pool<Postgres>, consumer: Consumer) -> Result<Consumer> {
-    info!("Creating new consumer in PostgreSQL database: {}", consumer.username);
-    
-    // Begin a transaction
     let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+    let consumer = create_consumer_in(&mut *tx, consumer).await?;
+    tx.commit().await.context("Failed to commit transaction")?;
+    Ok(consumer)
+}
+
+/// `create_consumer` on a connection or transaction the caller commits
+pub async fn create_consumer_in(tx: &mut PgConnection, consumer: Consumer) -> Result<Consumer> {
+    info!("Creating new consumer in PostgreSQL database: {}", consumer.username);
     
     // Check if username is unique
     let exists = sqlx::query!(
-        "SELECT EXISTS(SELECT 1 FROM consumers WHERE username = $1) as exists_flag",
-        consumer.username
+        "SELECT EXISTS(SELECT 1 FROM consumers WHERE username = $1 AND workspace = $2) as exists_flag",
+        consumer.username, consumer.workspace
     )
     .fetch_one(&mut *tx)
     .await
@@ -492,12 +554,14 @@ pub async fn create_consumer(pool: &Pool<Postgres>, consumer: Consumer) -> Resul
     let inserted = sqlx::query!(
         r#"
         INSERT INTO consumers (
-            username, custom_id, credentials
+            username, workspace, tags, custom_id, credentials
         )
-        VALUES ($1, $2, $3)
+        VALUES ($1, $2, $3, $4, $5)
         RETURNING id, created_at, updated_at
         "#,
         consumer.username,
+        consumer.workspace,
+        &consumer.tags,
         consumer.custom_id,
         credentials_json
     )
@@ -505,9 +569,6 @@ pub async fn create_consumer(pool: &Pool<Postgres>, consumer: Consumer) -> Resul
     .await
     .context("Failed to insert consumer")?;
     
-    // Commit the transaction
-    tx.commit().await.context("Failed to commit transaction")?;
-    
     // Load the newly created consumer
     let mut new_consumer = consumer.clone();
     new_consumer.id = inserted.id;
@@ -520,10 +581,15 @@ pub async fn create_consumer(pool: &Pool<Postgres>, consumer: Consumer) -> Resul
 
 /// Update an existing consumer in the database
 pub async fn update_consumer(pool: &Pool<Postgres>, consumer: Consumer) -> Result<Consumer> {
-    info!("Updating consumer in PostgreSQL database: {}", consumer.id);
-    
-    // Begin a transaction
     let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+    let consumer = update_consumer_in(&mut *tx, consumer).await?;
+    tx.commit().await.context("Failed to commit transaction")?;
+    Ok(consumer)
+}
+
+/// `update_consumer` on a connection or transaction the caller commits
+pub async fn update_consumer_in(tx: &mut PgConnection, consumer: Consumer) -> Result<Consumer> {
+    info!("Updating consumer in PostgreSQL database: {}", consumer.id);
     
     // Check if consumer exists
     let exists = sqlx::query!(
@@ -542,8 +608,8 @@ pub async fn update_consumer(pool: &Pool<Postgres>, consumer: Consumer) -> Resul
     
     // Check username uniqueness
     let username_exists = sqlx::query!(
-        "SELECT EXISTS(SELECT 1 FROM consumers WHERE username = $1 AND id != $2) as exists_flag",
-        consumer.username, consumer.id
+        "SELECT EXISTS(SELECT 1 FROM consumers WHERE username = $1 AND workspace = $2 AND id != $3) as exists_flag",
+        consumer.username, consumer.workspace, consumer.id
     )
     .fetch_one(&mut *tx)
     .await
@@ -582,27 +648,33 @@ pub async fn update_consumer(pool: &Pool<Postgres>, consumer: Consumer) -> Resul
         UPDATE consumers
         SET 
             username = $1,
-            custom_id = $2,
-            credentials = $3,
+            workspace = $2,
+            tags = $3,
+            custom_id = $4,
+            credentials = $5,
+            version = version + 1,
             updated_at = CURRENT_TIMESTAMP
-        WHERE id = $4
-        RETURNING updated_at
+        WHERE id = $6 AND version = $7
+        RETURNING updated_at, version
         "#,
         consumer.username,
+        consumer.workspace,
+        &consumer.tags,
         consumer.custom_id,
         credentials_json,
-        consumer.id
+        consumer.id,
+        consumer.version
     )
-    .fetch_one(&mut *tx)
+    .fetch_optional(&mut *tx)
     .await
-    .context("Failed to update consumer")?;
-    
-    // Commit the transaction
-    tx.commit().await.context("Failed to commit transaction")?;
+    .context("Failed to update consumer")?
+    // The row exists, so it was changed since the caller read it
+    .ok_or_else(|| StaleVersion::new("consumer", &consumer.id, consumer.version))?;
     
     // Return the updated consumer
     let mut updated_consumer = consumer.clone();
     updated_consumer.updated_at = updated.updated_at;
+    updated_consumer.version = updated.version;
     
     info!("Updated consumer with ID: {}", updated_consumer.id);
     Ok(updated_consumer)
@@ -674,7 +746,7 @@ pub async fn get_consumer_by_id(pool: &Pool<Postgres>, consumer_id: &str) -> Res
     let row = sqlx::query!(
         r#"
         SELECT 
-            id, username, custom_id, credentials, created_at, updated_at
+            id, username, workspace, version, tags, custom_id, credentials, created_at, updated_at
         FROM consumers
         WHERE id = $1
         "#,
@@ -699,6 +771,9 @@ pub async fn get_consumer_by_id(pool: &Pool<Postgres>, consumer_id: &str) -> Res
             Ok(Consumer {
                 id: row.id,
                 username: row.username,
+                workspace: row.workspace,
+                version: row.version,
+                tags: row.tags,
                 custom_id: row.custom_id,
                 credentials,
                 created_at: row.created_at,
@@ -711,10 +786,15 @@ pub async fn get_consumer_by_id(pool: &Pool<Postgres>, consumer_id: &str) -> Res
 
 /// Create a new plugin configuration in the database
 pub async fn create_plugin_config(pool: &Pool<Postgres>, plugin_config: PluginConfig) -> Result<PluginConfig> {
-    info!("Creating new plugin configuration in PostgreSQL database: {}", plugin_config.plugin_name);
-    
-    // Begin a transaction
     let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+    let plugin_config = create_plugin_config_in(&mut *tx, plugin_config).await?;
+    tx.commit().await.context("Failed to commit transaction")?;
+    Ok(plugin_config)
+}
+
+/// `create_plugin_config` on a connection or transaction the caller commits
+pub async fn create_plugin_config_in(tx: &mut PgConnection, plugin_config: PluginConfig) -> Result<PluginConfig> {
+    info!("Creating new plugin configuration in PostgreSQL database: {}", plugin_config.plugin_name);
     
     // Serialize config to JSON
     let config_json = serde_json::to_value(&plugin_config.config)
@@ -724,12 +804,14 @@ pub async fn create_plugin_config(pool: &Pool<Postgres>, plugin_config: PluginCo
     let inserted = sqlx::query!(
         r#"
         INSERT INTO plugin_configs (
-            plugin_name, config, scope, proxy_id, consumer_id, enabled
+            plugin_name, workspace, tags, config, scope, proxy_id, consumer_id, enabled
         )
-        VALUES ($1, $2, $3, $4, $5, $6)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
         RETURNING id, created_at, updated_at
         "#,
         plugin_config.plugin_name,
+        plugin_config.workspace,
+        &plugin_config.tags,
         config_json,
         plugin_config.scope,
         plugin_config.proxy_id,
@@ -740,9 +822,6 @@ pub async fn create_plugin_config(pool: &Pool<Postgres>, plugin_config: PluginCo
     .await
     .context("Failed to insert plugin configuration")?;
     
-    // Commit the transaction
-    tx.commit().await.context("Failed to commit transaction")?;
-    
     // Load the newly created plugin config
     let mut new_plugin_config = plugin_config.clone();
     new_plugin_config.id = inserted.id;
@@ -755,10 +834,15 @@ pub async fn create_plugin_config(pool: &Pool<Postgres>, plugin_config: PluginCo
 
 /// Update an existing plugin configuration in the database
 pub async fn update_plugin_config(pool: &Pool<Postgres>, plugin_config: PluginConfig) -> Result<PluginConfig> {
-    info!("Updating plugin configuration in PostgreSQL database: {}", plugin_config.id);
-    
-    // Begin a transaction
     let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+    let plugin_config = update_plugin_config_in(&mut *tx, plugin_config).await?;
+    tx.commit().await.context("Failed to commit transaction")?;
+    Ok(plugin_config)
+}
+
+/// `update_plugin_config` on a connection or transaction the caller commits
+pub async fn update_plugin_config_in(tx: &mut PgConnection, plugin_config: PluginConfig) -> Result<PluginConfig> {
+    info!("Updating plugin configuration in PostgreSQL database: {}", plugin_config.id);
     
     // Check if plugin config exists
     let exists = sqlx::query!(
@@ -785,33 +869,39 @@ pub async fn update_plugin_config(pool: &Pool<Postgres>, plugin_config: PluginCo
         UPDATE plugin_configs
         SET 
             plugin_name = $1,
-            config = $2,
-            scope = $3,
-            proxy_id = $4,
-            consumer_id = $5,
-            enabled = $6,
+            workspace = $2,
+            tags = $3,
+            config = $4,
+            scope = $5,
+            proxy_id = $6,
+            consumer_id = $7,
+            enabled = $8,
+            version = version + 1,
             updated_at = CURRENT_TIMESTAMP
-        WHERE id = $7
-        RETURNING updated_at
+        WHERE id = $9 AND version = $10
+        RETURNING updated_at, version
         "#,
         plugin_config.plugin_name,
+        plugin_config.workspace,
+        &plugin_config.tags,
         config_json,
         plugin_config.scope,
         plugin_config.proxy_id,
         plugin_config.consumer_id,
         plugin_config.enabled,
-        plugin_config.id
+        plugin_config.id,
+        plugin_config.version
     )
-    .fetch_one(&mut *tx)
+    .fetch_optional(&mut *tx)
     .await
-    .context("Failed to update plugin configuration")?;
-    
-    // Commit the transaction
-    tx.commit().await.context("Failed to commit transaction")?;
+    .context("Failed to update plugin configuration")?
+    // The row exists, so it was changed since the caller read it
+    .ok_or_else(|| StaleVersion::new("plugin config", &plugin_config.id, plugin_config.version))?;
     
     // Return the updated plugin config
     let mut updated_plugin_config = plugin_config.clone();
     updated_plugin_config.updated_at = updated.updated_at;
+    updated_plugin_config.version = updated.version;
     
     info!("Updated plugin configuration with ID: {}", updated_plugin_config.id);
     Ok(updated_plugin_config)
@@ -891,6 +981,40 @@ pub async fn delete_plugin_config(pool: &Pool<Postgres>, plugin_config_id: &str)
     Ok(())
 }
 
+/// Writes the entities of a bulk import in one transaction. Nothing is kept
+/// when any write fails, since the transaction is rolled back when dropped.
+pub async fn apply_bulk(pool: &Pool<Postgres>, changes: &BulkChanges) -> Result<()> {
+    let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+    
+    for plugin_config in &changes.created_plugin_configs {
+        create_plugin_config_in(&mut *tx, plugin_config.clone()).await
+            .context(format!("Failed to create plugin config {}", plugin_config.id))?;
+    }
+    for plugin_config in &changes.updated_plugin_configs {
+        update_plugin_config_in(&mut *tx, plugin_config.clone()).await
+            .context(format!("Failed to update plugin config {}", plugin_config.id))?;
+    }
+    for consumer in &changes.created_consumers {
+        create_consumer_in(&mut *tx, consumer.clone()).await
+            .context(format!("Failed to create consumer {}", consumer.id))?;
+    }
+    for consumer in &changes.updated_consumers {
+        update_consumer_in(&mut *tx, consumer.clone()).await
+            .context(format!("Failed to update consumer {}", consumer.id))?;
+    }
+    for proxy in &changes.created_proxies {
+        create_proxy_in(&mut *tx, proxy.clone()).await
+            .context(format!("Failed to create proxy {}", proxy.id))?;
+    }
+    for proxy in &changes.updated_proxies {
+        update_proxy_in(&mut *tx, proxy.clone()).await
+            .context(format!("Failed to update proxy {}", proxy.id))?;
+    }
+    
+    tx.commit().await.context("Failed to commit transaction")?;
+    Ok(())
+}
+
 /// Get the latest update timestamp from the database
 pub async fn get_latest_update_timestamp(pool: &Pool<Postgres>) -> Result<chrono::DateTime<chrono::Utc>> {
     debug!("Getting latest update timestamp from PostgreSQL database");
@@ -905,6 +1029,10 @@ pub async fn get_latest_update_timestamp(pool: &Pool<Postgres>) -> Result<chrono
             SELECT MAX(updated_at) as latest_time FROM consumers
             UNION ALL
             SELECT MAX(updated_at) as latest_time FROM plugin_configs
+            UNION ALL
+            SELECT MAX(updated_at) as latest_time FROM certificates
+            UNION ALL
+            SELECT MAX(updated_at) as latest_time FROM snis
         ) as latest_updates
         "#
     )
@@ -940,6 +1068,7 @@ pub async fn load_configuration_delta(pool: &Pool<Postgres>, since: chrono::Date
             backend_tls_server_ca_cert_path, 
             dns_override, dns_cache_ttl_seconds,
             auth_mode as "auth_mode: String",
+            error_templates, backend_discovery, dns_resolvers, log_sampling, api_versioning, max_requests_per_second, backend_keepalive, backend_header_case, upstream_host, egress_proxy, workspace, version, tags, enabled,
             created_at, updated_at
         FROM proxies
         WHERE updated_at > $1
@@ -1013,7 +1142,7 @@ pub async fn load_configuration_delta(pool: &Pool<Postgres>, since: chrono::Date
         Consumer,
         r#"
         SELECT 
-            id, username, custom_id,
+            id, username, workspace, version, tags, custom_id,
             credentials as "credentials: Value",
             created_at, updated_at
         FROM consumers
@@ -1054,7 +1183,7 @@ pub async fn load_configuration_delta(pool: &Pool<Postgres>, since: chrono::Date
         PluginConfig,
         r#"
         SELECT 
-            id, plugin_name,
+            id, plugin_name, workspace, version, tags,
             config as "config: Value",
             scope as "scope: String",
             proxy_id,
@@ -1104,6 +1233,12 @@ pub async fn load_configuration_delta(pool: &Pool<Postgres>, since: chrono::Date
         }
     };
     
+    // Load certificate and SNI changes
+    let updated_certificates = load_certificates_updated_since(pool, since).await?;
+    let deleted_certificate_ids = load_deleted_ids_since(pool, "certificate_deletions", since).await?;
+    let updated_snis = load_snis_updated_since(pool, since).await?;
+    let deleted_sni_ids = load_deleted_ids_since(pool, "sni_deletions", since).await?;
+    
     // Get the latest update timestamp
     let latest_timestamp = get_latest_update_timestamp(pool).await?;
     
@@ -1117,6 +1252,615 @@ pub async fn load_configuration_delta(pool: &Pool<Postgres>, since: chrono::Date
         deleted_consumer_ids,
         updated_plugin_configs: processed_plugin_configs,
         deleted_plugin_config_ids,
+        updated_certificates,
+        deleted_certificate_ids,
+        updated_snis,
+        deleted_sni_ids,
         last_updated_at: latest_timestamp,
     })
 }
+
+/// Load the TLS certificates served by the proxy listeners
+pub async fn load_certificates(pool: &Pool<Postgres>) -> Result<Vec<Certificate>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, cert, key, snis, created_at, updated_at
+        FROM certificates
+        ORDER BY created_at
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to load certificates from PostgreSQL database")?;
+    
+    rows.iter().map(certificate_from_row).collect()
+}
+
+/// Get a certificate by ID from the database
+pub async fn get_certificate_by_id(pool: &Pool<Postgres>, certificate_id: &str) -> Result<Certificate> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, cert, key, snis, created_at, updated_at
+        FROM certificates
+        WHERE id = $1
+        "#
+    )
+    .bind(certificate_id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch certificate from PostgreSQL database")?;
+    
+    match row {
+        Some(row) => certificate_from_row(&row),
+        None => Err(anyhow!("Certificate with ID '{}' not found", certificate_id))
+    }
+}
+
+/// Insert a certificate, or replace the PEM data and SNIs of an existing one
+pub async fn upsert_certificate(pool: &Pool<Postgres>, certificate: &Certificate) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO certificates (id, cert, key, snis, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (id) DO UPDATE SET
+            cert = EXCLUDED.cert, key = EXCLUDED.key, snis = EXCLUDED.snis, updated_at = EXCLUDED.updated_at
+        "#
+    )
+    .bind(&certificate.id)
+    .bind(&certificate.cert)
+    .bind(&certificate.key)
+    .bind(serde_json::json!(certificate.snis))
+    .bind(certificate.created_at)
+    .bind(certificate.updated_at)
+    .execute(pool)
+    .await
+    .context("Failed to store certificate in PostgreSQL database")?;
+    
+    info!("Stored certificate with ID: {}", certificate.id);
+    Ok(())
+}
+
+fn certificate_from_row(row: &PgRow) -> Result<Certificate> {
+    let snis: Value = row.try_get("snis")?;
+    let snis = serde_json::from_value::<Vec<String>>(snis).unwrap_or_default();
+    
+    Ok(Certificate {
+        id: row.try_get("id")?,
+        cert: row.try_get("cert")?,
+        key: row.try_get("key")?,
+        snis,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+/// Delete a certificate together with the SNIs mapped to it
+pub async fn delete_certificate(pool: &Pool<Postgres>, certificate_id: &str) -> Result<()> {
+    let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+    
+    let sni_ids = sqlx::query("SELECT id FROM snis WHERE certificate_id = $1")
+        .bind(certificate_id)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to fetch SNIs of certificate")?
+        .iter()
+        .map(|row| row.try_get::<String, _>("id"))
+        .collect::<Result<Vec<String>, _>>()?;
+    
+    sqlx::query("DELETE FROM snis WHERE certificate_id = $1")
+        .bind(certificate_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete SNIs of certificate")?;
+    
+    let delete_result = sqlx::query("DELETE FROM certificates WHERE id = $1")
+        .bind(certificate_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete certificate")?;
+    
+    if delete_result.rows_affected() == 0 {
+        return Err(anyhow!("Certificate with ID '{}' not found", certificate_id));
+    }
+    
+    // Track deletions for incremental updates
+    sqlx::query("INSERT INTO certificate_deletions (id, deleted_at) VALUES ($1, CURRENT_TIMESTAMP) ON CONFLICT (id) DO UPDATE SET deleted_at = CURRENT_TIMESTAMP")
+        .bind(certificate_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to track certificate deletion")?;
+    
+    for sni_id in &sni_ids {
+        sqlx::query("INSERT INTO sni_deletions (id, deleted_at) VALUES ($1, CURRENT_TIMESTAMP) ON CONFLICT (id) DO UPDATE SET deleted_at = CURRENT_TIMESTAMP")
+            .bind(sni_id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to track SNI deletion")?;
+    }
+    
+    tx.commit().await.context("Failed to commit transaction")?;
+    
+    info!("Deleted certificate with ID: {} and {} SNIs", certificate_id, sni_ids.len());
+    Ok(())
+}
+
+/// Certificates created or updated after the given time
+pub async fn load_certificates_updated_since(pool: &Pool<Postgres>, since: DateTime<Utc>) -> Result<Vec<Certificate>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, cert, key, snis, created_at, updated_at
+        FROM certificates
+        WHERE updated_at > $1
+        ORDER BY updated_at
+        "#
+    )
+    .bind(since)
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch updated certificates from PostgreSQL database")?;
+    
+    rows.iter().map(certificate_from_row).collect()
+}
+
+/// Load the SNI hostname mappings managed through the Admin API
+pub async fn load_snis(pool: &Pool<Postgres>) -> Result<Vec<Sni>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, name, certificate_id, created_at, updated_at
+        FROM snis
+        ORDER BY name
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to load SNIs from PostgreSQL database")?;
+    
+    rows.iter().map(sni_from_row).collect()
+}
+
+/// SNIs created or updated after the given time
+pub async fn load_snis_updated_since(pool: &Pool<Postgres>, since: DateTime<Utc>) -> Result<Vec<Sni>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, name, certificate_id, created_at, updated_at
+        FROM snis
+        WHERE updated_at > $1
+        ORDER BY updated_at
+        "#
+    )
+    .bind(since)
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch updated SNIs from PostgreSQL database")?;
+    
+    rows.iter().map(sni_from_row).collect()
+}
+
+/// Insert an SNI, or point an existing one at another hostname or certificate
+pub async fn upsert_sni(pool: &Pool<Postgres>, sni: &Sni) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO snis (id, name, certificate_id, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (id) DO UPDATE SET
+            name = EXCLUDED.name, certificate_id = EXCLUDED.certificate_id, updated_at = EXCLUDED.updated_at
+        "#
+    )
+    .bind(&sni.id)
+    .bind(&sni.name)
+    .bind(&sni.certificate_id)
+    .bind(sni.created_at)
+    .bind(sni.updated_at)
+    .execute(pool)
+    .await
+    .context("Failed to store SNI in PostgreSQL database")?;
+    
+    info!("Stored SNI {} for certificate {}", sni.name, sni.certificate_id);
+    Ok(())
+}
+
+/// Delete an SNI by ID
+pub async fn delete_sni(pool: &Pool<Postgres>, sni_id: &str) -> Result<()> {
+    let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+    
+    let delete_result = sqlx::query("DELETE FROM snis WHERE id = $1")
+        .bind(sni_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete SNI")?;
+    
+    if delete_result.rows_affected() == 0 {
+        return Err(anyhow!("SNI with ID '{}' not found", sni_id));
+    }
+    
+    sqlx::query("INSERT INTO sni_deletions (id, deleted_at) VALUES ($1, CURRENT_TIMESTAMP) ON CONFLICT (id) DO UPDATE SET deleted_at = CURRENT_TIMESTAMP")
+        .bind(sni_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to track SNI deletion")?;
+    
+    tx.commit().await.context("Failed to commit transaction")?;
+    
+    info!("Deleted SNI with ID: {}", sni_id);
+    Ok(())
+}
+
+/// IDs recorded in a deletion tracking table (e.g. "sni_deletions") after the given time
+pub async fn load_deleted_ids_since(pool: &Pool<Postgres>, table: &str, since: DateTime<Utc>) -> Result<Vec<String>> {
+    let rows = sqlx::query(&format!("SELECT id FROM {} WHERE deleted_at > $1", table))
+        .bind(since)
+        .fetch_all(pool)
+        .await
+        .context(format!("Failed to fetch deleted IDs from {}", table))?;
+    
+    rows.iter()
+        .map(|row| row.try_get::<String, _>("id").map_err(Into::into))
+        .collect()
+}
+
+fn sni_from_row(row: &PgRow) -> Result<Sni> {
+    Ok(Sni {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        certificate_id: row.try_get("certificate_id")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+/// Record a configuration version, keeping only the newest `retain` versions (all when 0)
+pub async fn insert_config_version(pool: &Pool<Postgres>, author: &str, description: &str, document: &str, retain: u64) -> Result<i64> {
+    let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+    
+    let id: i64 = sqlx::query(
+        r#"
+        INSERT INTO config_versions (author, description, document, created_at)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#
+    )
+    .bind(author)
+    .bind(description)
+    .bind(document)
+    .bind(Utc::now())
+    .fetch_one(&mut *tx)
+    .await
+    .context("Failed to store configuration version")?
+    .try_get("id")?;
+    
+    if retain > 0 {
+        sqlx::query("DELETE FROM config_versions WHERE id <= $1")
+            .bind(id - retain as i64)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to prune configuration versions")?;
+    }
+    
+    tx.commit().await.context("Failed to commit transaction")?;
+    
+    info!("Recorded configuration version {} by {}", id, author);
+    Ok(id)
+}
+
+/// List configuration versions, newest first, without their documents
+pub async fn list_config_versions(pool: &Pool<Postgres>) -> Result<Vec<ConfigVersion>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, author, description, created_at
+        FROM config_versions
+        ORDER BY id DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to load configuration versions from PostgreSQL database")?;
+    
+    rows.iter().map(|row| config_version_from_row(row, false)).collect()
+}
+
+/// Get a configuration version with its document, or the latest one when no ID is given
+pub async fn get_config_version(pool: &Pool<Postgres>, version_id: Option<i64>) -> Result<Option<ConfigVersion>> {
+    let row = match version_id {
+        Some(version_id) => sqlx::query(
+            "SELECT id, author, description, document, created_at FROM config_versions WHERE id = $1"
+        )
+        .bind(version_id)
+        .fetch_optional(pool)
+        .await,
+        None => sqlx::query(
+            "SELECT id, author, description, document, created_at FROM config_versions ORDER BY id DESC LIMIT 1"
+        )
+        .fetch_optional(pool)
+        .await,
+    }
+    .context("Failed to fetch configuration version from PostgreSQL database")?;
+    
+    row.map(|row| config_version_from_row(&row, true)).transpose()
+}
+
+fn config_version_from_row(row: &PgRow, with_document: bool) -> Result<ConfigVersion> {
+    let config = if with_document {
+        let document: String = row.try_get("document")?;
+        Some(serde_json::from_str::<Configuration>(&document).context("Stored configuration version is not valid")?)
+    } else {
+        None
+    };
+    
+    Ok(ConfigVersion {
+        id: row.try_get("id")?,
+        author: row.try_get("author")?,
+        description: row.try_get("description")?,
+        created_at: row.try_get("created_at")?,
+        config,
+    })
+}
+
+/// Record an Admin API change in the audit log
+pub async fn insert_audit_entry(pool: &Pool<Postgres>, entry: &AuditEntry) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (actor, action, entity_type, entity_id, request, before_json, after_json, source_ip, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#
+    )
+    .bind(&entry.actor)
+    .bind(&entry.action)
+    .bind(&entry.entity_type)
+    .bind(&entry.entity_id)
+    .bind(&entry.request)
+    .bind(entry.before.as_ref().map(Value::to_string))
+    .bind(entry.after.as_ref().map(Value::to_string))
+    .bind(&entry.source_ip)
+    .bind(entry.created_at)
+    .execute(pool)
+    .await
+    .context("Failed to store audit log entry")?;
+    
+    Ok(())
+}
+
+/// List audit log entries matching a filter, newest first, with the number of matching entries
+pub async fn list_audit_entries(pool: &Pool<Postgres>, filter: &AuditFilter, limit: usize, offset: usize) -> Result<(Vec<AuditEntry>, usize)> {
+    let mut count = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM audit_log");
+    push_audit_filter(&mut count, filter);
+    let total: i64 = count.build_query_scalar()
+        .fetch_one(pool)
+        .await
+        .context("Failed to count audit log entries in PostgreSQL database")?;
+    
+    let mut query = QueryBuilder::<Postgres>::new(
+        "SELECT id, actor, action, entity_type, entity_id, request, before_json, after_json, source_ip, created_at FROM audit_log"
+    );
+    push_audit_filter(&mut query, filter);
+    query.push(" ORDER BY id DESC LIMIT ").push_bind(limit as i64)
+        .push(" OFFSET ").push_bind(offset as i64);
+    
+    let rows = query.build()
+        .fetch_all(pool)
+        .await
+        .context("Failed to load audit log entries from PostgreSQL database")?;
+    
+    let entries = rows.iter().map(audit_entry_from_row).collect::<Result<Vec<_>>>()?;
+    Ok((entries, total as usize))
+}
+
+fn push_audit_filter(query: &mut QueryBuilder<'_, Postgres>, filter: &AuditFilter) {
+    let mut separator = " WHERE ";
+    for (column, value) in [
+        ("actor", &filter.actor),
+        ("action", &filter.action),
+        ("entity_type", &filter.entity_type),
+        ("entity_id", &filter.entity_id),
+    ] {
+        if let Some(value) = value {
+            query.push(separator).push(column).push(" = ").push_bind(value.clone());
+            separator = " AND ";
+        }
+    }
+    if let Some(since) = filter.since {
+        query.push(separator).push("created_at >= ").push_bind(since);
+        separator = " AND ";
+    }
+    if let Some(until) = filter.until {
+        query.push(separator).push("created_at < ").push_bind(until);
+        separator = " AND ";
+    }
+    if let Some(before_id) = filter.before_id {
+        query.push(separator).push("id < ").push_bind(before_id);
+    }
+}
+
+fn audit_entry_from_row(row: &PgRow) -> Result<AuditEntry> {
+    let json = |column: &str| -> Result<Option<Value>> {
+        let text: Option<String> = row.try_get(column)?;
+        text.map(|text| serde_json::from_str(&text).context("Stored audit log entry is not valid JSON"))
+            .transpose()
+    };
+    
+    Ok(AuditEntry {
+        id: row.try_get("id")?,
+        actor: row.try_get("actor")?,
+        action: row.try_get("action")?,
+        entity_type: row.try_get("entity_type")?,
+        entity_id: row.try_get("entity_id")?,
+        request: row.try_get("request")?,
+        before: json("before_json")?,
+        after: json("after_json")?,
+        source_ip: row.try_get("source_ip")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+/// List the Admin API users by username
+pub async fn list_admin_users(pool: &Pool<Postgres>) -> Result<Vec<AdminUser>> {
+    let rows = sqlx::query(
+        "SELECT username, password_hash, role, created_at, updated_at FROM admin_users ORDER BY username"
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to load admin users from PostgreSQL database")?;
+    
+    rows.iter().map(admin_user_from_row).collect()
+}
+
+/// Get an Admin API user by username
+pub async fn get_admin_user(pool: &Pool<Postgres>, username: &str) -> Result<Option<AdminUser>> {
+    let row = sqlx::query(
+        "SELECT username, password_hash, role, created_at, updated_at FROM admin_users WHERE username = $1"
+    )
+    .bind(username)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch admin user from PostgreSQL database")?;
+    
+    row.as_ref().map(admin_user_from_row).transpose()
+}
+
+/// Insert an Admin API user, or replace the password and role of an existing one
+pub async fn upsert_admin_user(pool: &Pool<Postgres>, user: &AdminUser) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO admin_users (username, password_hash, role, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (username) DO UPDATE SET
+            password_hash = EXCLUDED.password_hash, role = EXCLUDED.role, updated_at = EXCLUDED.updated_at
+        "#
+    )
+    .bind(&user.username)
+    .bind(&user.password_hash)
+    .bind(user.role.as_str())
+    .bind(user.created_at)
+    .bind(user.updated_at)
+    .execute(pool)
+    .await
+    .context("Failed to store admin user in PostgreSQL database")?;
+    
+    info!("Stored admin user {} with role {}", user.username, user.role.as_str());
+    Ok(())
+}
+
+/// Delete an Admin API user, returning whether it existed
+pub async fn delete_admin_user(pool: &Pool<Postgres>, username: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM admin_users WHERE username = $1")
+        .bind(username)
+        .execute(pool)
+        .await
+        .context("Failed to delete admin user")?;
+    
+    Ok(result.rows_affected() > 0)
+}
+
+fn admin_user_from_row(row: &PgRow) -> Result<AdminUser> {
+    let role: String = row.try_get("role")?;
+    Ok(AdminUser {
+        username: row.try_get("username")?,
+        password_hash: row.try_get("password_hash")?,
+        role: role.parse().map_err(|e: String| anyhow!("Stored admin user has an invalid role: {}", e))?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+/// Revoke an Admin API token until it expires, dropping revocations of expired tokens
+pub async fn revoke_admin_token(pool: &Pool<Postgres>, jti: &str, username: &str, expires_at: DateTime<Utc>) -> Result<()> {
+    sqlx::query("DELETE FROM admin_token_revocations WHERE expires_at < $1")
+        .bind(Utc::now())
+        .execute(pool)
+        .await
+        .context("Failed to purge expired admin token revocations")?;
+    
+    sqlx::query(
+        "INSERT INTO admin_token_revocations (jti, username, expires_at) VALUES ($1, $2, $3) ON CONFLICT (jti) DO NOTHING"
+    )
+    .bind(jti)
+    .bind(username)
+    .bind(expires_at)
+    .execute(pool)
+    .await
+    .context("Failed to revoke admin token in PostgreSQL database")?;
+    
+    Ok(())
+}
+
+/// Whether an Admin API token has been revoked
+pub async fn is_admin_token_revoked(pool: &Pool<Postgres>, jti: &str) -> Result<bool> {
+    let row = sqlx::query("SELECT jti FROM admin_token_revocations WHERE jti = $1")
+        .bind(jti)
+        .fetch_optional(pool)
+        .await
+        .context("Failed to check admin token revocation in PostgreSQL database")?;
+    
+    Ok(row.is_some())
+}
+
+/// Store an Admin API token created through /tokens
+pub async fn create_api_token(pool: &Pool<Postgres>, token: &ApiToken) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO admin_api_tokens (id, name, username, role, workspace, scope_json, expires_at, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+    )
+    .bind(&token.id)
+    .bind(&token.name)
+    .bind(&token.username)
+    .bind(token.role.as_str())
+    .bind(&token.workspace)
+    .bind(serde_json::to_string(&token.scope)?)
+    .bind(token.expires_at)
+    .bind(token.created_at)
+    .execute(pool)
+    .await
+    .context("Failed to store admin API token in PostgreSQL database")?;
+    
+    Ok(())
+}
+
+/// List the Admin API tokens, of one user or of everyone, newest first
+pub async fn list_api_tokens(pool: &Pool<Postgres>, username: Option<&str>) -> Result<Vec<ApiToken>> {
+    let rows = sqlx::query(
+        "SELECT id, name, username, role, workspace, scope_json, expires_at, created_at FROM admin_api_tokens WHERE $1::TEXT IS NULL OR username = $1 ORDER BY created_at DESC"
+    )
+    .bind(username)
+    .fetch_all(pool)
+    .await
+    .context("Failed to load admin API tokens from PostgreSQL database")?;
+    
+    rows.iter().map(api_token_from_row).collect()
+}
+
+/// Get an Admin API token by ID
+pub async fn get_api_token(pool: &Pool<Postgres>, id: &str) -> Result<Option<ApiToken>> {
+    let row = sqlx::query(
+        "SELECT id, name, username, role, workspace, scope_json, expires_at, created_at FROM admin_api_tokens WHERE id = $1"
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch admin API token from PostgreSQL database")?;
+    
+    row.as_ref().map(api_token_from_row).transpose()
+}
+
+/// Delete an Admin API token, returning whether it existed
+pub async fn delete_api_token(pool: &Pool<Postgres>, id: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM admin_api_tokens WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await
+        .context("Failed to delete admin API token")?;
+    
+    Ok(result.rows_affected() > 0)
+}
+
+fn api_token_from_row(row: &PgRow) -> Result<ApiToken> {
+    let role: String = row.try_get("role")?;
+    let scope: String = row.try_get("scope_json")?;
+    Ok(ApiToken {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        username: row.try_get("username")?,
+        role: role.parse().map_err(|e: String| anyhow!("Stored admin API token has an invalid role: {}", e))?,
+        workspace: row.try_get("workspace")?,
+        scope: serde_json::from_str(&scope).context("Stored admin API token has an invalid scope")?,
+        expires_at: row.try_get("expires_at")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
@@ -0,0 +1,95 @@
+//! Retries and circuit breaking for database reads
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use rand::Rng;
+use tracing::{info, warn};
+
+use crate::metrics;
+
+/// How often and how fast a failed read is retried
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Attempts per read, including the first one
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (starting at 1): exponential backoff
+    /// capped at `max_delay`, with full jitter so gateways do not retry in step
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(1u32 << retry.saturating_sub(1).min(16));
+        let ceiling = exponential.min(self.max_delay).as_millis() as u64;
+        Duration::from_millis(rand::thread_rng().gen_range(0..=ceiling))
+    }
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Opens after `failure_threshold` failed reads in a row and lets one trial
+/// read through per `cooldown` until a read succeeds
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Whether a read may go to the database now. After the cooldown one
+    /// caller is let through as the trial and the circuit stays open for the others.
+    pub fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.open_until {
+            Some(until) if Instant::now() < until => false,
+            Some(_) => {
+                state.open_until = Some(Instant::now() + self.cooldown);
+                true
+            },
+            None => true,
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.consecutive_failures >= self.failure_threshold {
+            info!("Database reachable again, leaving degraded mode");
+            metrics::set_config_source_degraded(false);
+        }
+        *state = BreakerState::default();
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        if state.consecutive_failures >= self.failure_threshold {
+            if state.consecutive_failures == self.failure_threshold {
+                warn!(
+                    "Database unavailable after {} failed reads, serving the last loaded configuration until it returns",
+                    state.consecutive_failures
+                );
+                metrics::set_config_source_degraded(true);
+            }
+            state.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+
+    /// The circuit is open or waiting for a trial read to succeed
+    pub fn is_open(&self) -> bool {
+        self.state.lock().unwrap().consecutive_failures >= self.failure_threshold
+    }
+}
@@ -1,13 +1,14 @@
 use anyhow::{anyhow, Result, Context};
 use chrono::{DateTime, Utc};
 use serde_json::Value;
-use sqlx::{sqlite::{SqlitePoolOptions, SqlitePool}, Pool, Sqlite, Row};
+use sqlx::{sqlite::{SqliteConnection, SqlitePoolOptions, SqlitePool, SqliteRow}, Pool, Sqlite, QueryBuilder, Row};
 use tracing::{debug, info, warn};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::config::data_model::{Configuration, Proxy, Consumer, PluginConfig, Protocol, AuthMode, ConfigurationDelta};
+use super::{BulkChanges, StaleVersion};
+use crate::config::data_model::{Configuration, Proxy, Consumer, PluginConfig, Protocol, AuthMode, ConfigurationDelta, Certificate, Sni, ConfigVersion, AuditEntry, AuditFilter, AdminUser, ApiToken};
 
 // Module-level functions for use in the DatabaseClient trait
 pub async fn load_full_configuration(pool: &Pool<Sqlite>) -> Result<Configuration> {
@@ -20,6 +21,8 @@ pub async fn load_full_configuration(pool: &Pool<Sqlite>) -> Result<Configuratio
     let proxies = load_proxies(pool).await?;
     let consumers = load_consumers(pool).await?;
     let plugin_configs = load_plugin_configs(pool).await?;
+    let certificates = load_certificates(pool).await?;
+    let snis = load_snis(pool).await?;
     
     // Create association map between proxies and plugins
     let proxy_plugin_map = load_proxy_plugin_associations(pool).await?;
@@ -44,11 +47,19 @@ pub async fn load_full_configuration(pool: &Pool<Sqlite>) -> Result<Configuratio
         proxies: proxies_with_plugins,
         consumers,
         plugin_configs,
+        certificates,
+        snis,
         last_updated_at: Utc::now(),
     })
 }
 
 pub async fn create_proxy(pool: &Pool<Sqlite>, proxy: Proxy) -> Result<Proxy> {
+    let mut conn = pool.acquire().await.context("Failed to acquire a connection")?;
+    create_proxy_in(&mut *conn, proxy).await
+}
+
+/// `create_proxy` on a connection or transaction the caller commits
+pub async fn create_proxy_in(conn: &mut SqliteConnection, proxy: Proxy) -> Result<Proxy> {
     info!("Creating proxy in SQLite database: {}", proxy.id);
     
     // Check if listen_path is unique
@@ -56,7 +67,7 @@ pub async fn create_proxy(pool: &Pool<Sqlite>, proxy: Proxy) -> Result<Proxy> {
         "SELECT COUNT(*) as count FROM proxies WHERE listen_path = ?"
     )
     .bind(&proxy.listen_path)
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
     .await
     .map_err(|e| anyhow!("Failed to check for existing proxy: {}", e))?;
     
@@ -92,14 +103,14 @@ pub async fn create_proxy(pool: &Pool<Sqlite>, proxy: Proxy) -> Result<Proxy> {
             backend_connect_timeout_ms, backend_read_timeout_ms, backend_write_timeout_ms,
             backend_tls_client_cert_path, backend_tls_client_key_path,
             backend_tls_verify_server_cert, backend_tls_server_ca_cert_path,
-            dns_override, dns_cache_ttl_seconds, auth_mode, created_at, updated_at
+            dns_override, dns_cache_ttl_seconds, auth_mode, error_templates, backend_discovery, dns_resolvers, log_sampling, api_versioning, max_requests_per_second, backend_keepalive, backend_header_case, upstream_host, egress_proxy, workspace, tags, enabled, created_at, updated_at
         ) VALUES (
             ?, ?, ?, ?, ?, ?, 
             ?, ?, ?, 
             ?, ?, ?,
             ?, ?,
             ?, ?,
-            ?, ?, ?, ?, ?
+            ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?
         )
         "#
     )
@@ -122,9 +133,22 @@ pub async fn create_proxy(pool: &Pool<Sqlite>, proxy: Proxy) -> Result<Proxy> {
     .bind(&proxy.dns_override)
     .bind(proxy.dns_cache_ttl_seconds.map(|ttl| ttl as i64))
     .bind(auth_mode)
+    .bind(serde_json::to_string(&proxy.error_templates).unwrap_or_default())
+    .bind(proxy.backend_discovery.as_ref().map(|d| serde_json::to_string(d).unwrap_or_default()))
+    .bind(serde_json::to_string(&proxy.dns_resolvers).unwrap_or_default())
+    .bind(proxy.log_sampling.as_ref().map(|s| serde_json::to_string(s).unwrap_or_default()))
+    .bind(proxy.api_versioning.as_ref().map(|v| serde_json::to_string(v).unwrap_or_default()))
+    .bind(proxy.max_requests_per_second.map(|rps| rps as i64))
+    .bind(proxy.backend_keepalive.as_ref().map(|k| serde_json::to_string(k).unwrap_or_default()))
+    .bind(proxy.backend_header_case.map(|c| c.as_str()))
+    .bind(&proxy.upstream_host)
+    .bind(&proxy.egress_proxy)
+    .bind(&proxy.workspace)
+    .bind(serde_json::to_string(&proxy.tags).unwrap_or_default())
+    .bind(proxy.enabled)
     .bind(created_at)
     .bind(updated_at)
-    .execute(pool)
+    .execute(&mut *conn)
     .await
     .map_err(|e| anyhow!("Failed to create proxy in SQLite: {}", e))?;
     
@@ -146,6 +170,7 @@ async fn load_proxies(pool: &Pool<Sqlite>) -> Result<Vec<Proxy>> {
             backend_tls_client_cert_path, backend_tls_client_key_path, backend_tls_verify_server_cert,
             backend_tls_server_ca_cert_path, dns_override, dns_cache_ttl_seconds, 
             auth_mode as "auth_mode: String",
+            error_templates, backend_discovery, dns_resolvers, log_sampling, api_versioning, max_requests_per_second, backend_keepalive, backend_header_case, upstream_host, egress_proxy, workspace, version, tags, enabled,
             created_at, updated_at
         FROM proxies
         "#
@@ -182,7 +207,7 @@ async fn load_consumers(pool: &Pool<Sqlite>) -> Result<Vec<Consumer>> {
         Consumer,
         r#"
         SELECT 
-            id, username, custom_id, credentials, created_at, updated_at
+            id, username, workspace, version, tags, custom_id, credentials, created_at, updated_at
         FROM consumers
         "#
     )
@@ -198,7 +223,7 @@ async fn load_plugin_configs(pool: &Pool<Sqlite>) -> Result<Vec<PluginConfig>> {
         PluginConfig,
         r#"
         SELECT 
-            id, plugin_name, config, 
+            id, plugin_name, workspace, version, tags, config, 
             scope, proxy_id, consumer_id, 
             enabled, created_at, updated_at
         FROM plugin_configs
@@ -236,6 +261,40 @@ async fn load_proxy_plugin_associations(pool: &Pool<Sqlite>) -> Result<HashMap<S
     Ok(proxy_plugin_map)
 }
 
+/// Writes the entities of a bulk import in one transaction. Nothing is kept
+/// when any write fails, since the transaction is rolled back when dropped.
+pub async fn apply_bulk(pool: &Pool<Sqlite>, changes: &BulkChanges) -> Result<()> {
+    let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+    
+    for plugin_config in &changes.created_plugin_configs {
+        create_plugin_config_in(&mut *tx, plugin_config.clone()).await
+            .context(format!("Failed to create plugin config {}", plugin_config.id))?;
+    }
+    for plugin_config in &changes.updated_plugin_configs {
+        update_plugin_config_in(&mut *tx, plugin_config.clone()).await
+            .context(format!("Failed to update plugin config {}", plugin_config.id))?;
+    }
+    for consumer in &changes.created_consumers {
+        create_consumer_in(&mut *tx, consumer.clone()).await
+            .context(format!("Failed to create consumer {}", consumer.id))?;
+    }
+    for consumer in &changes.updated_consumers {
+        update_consumer_in(&mut *tx, consumer.clone()).await
+            .context(format!("Failed to update consumer {}", consumer.id))?;
+    }
+    for proxy in &changes.created_proxies {
+        create_proxy_in(&mut *tx, proxy.clone()).await
+            .context(format!("Failed to create proxy {}", proxy.id))?;
+    }
+    for proxy in &changes.updated_proxies {
+        update_proxy_in(&mut *tx, proxy.clone()).await
+            .context(format!("Failed to update proxy {}", proxy.id))?;
+    }
+    
+    tx.commit().await.context("Failed to commit transaction")?;
+    Ok(())
+}
+
 /// Get a consumer by ID from the database
 pub async fn get_consumer_by_id(pool: &Pool<Sqlite>, consumer_id: &str) -> Result<Consumer> {
     info!("Fetching consumer from SQLite database by ID: {}", consumer_id);
@@ -244,7 +303,7 @@ pub async fn get_consumer_by_id(pool: &Pool<Sqlite>, consumer_id: &str) -> Resul
         Consumer,
         r#"
         SELECT 
-            id, username, custom_id, credentials, created_at, updated_at
+            id, username, workspace, version, tags, custom_id, credentials, created_at, updated_at
         FROM consumers
         WHERE id = ?
         "#,
@@ -260,6 +319,613 @@ pub async fn get_consumer_by_id(pool: &Pool<Sqlite>, consumer_id: &str) -> Resul
     }
 }
 
+/// Load the TLS certificates served by the proxy listeners
+pub async fn load_certificates(pool: &Pool<Sqlite>) -> Result<Vec<Certificate>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, cert, key, snis, created_at, updated_at
+        FROM certificates
+        ORDER BY created_at
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to load certificates from SQLite database")?;
+    
+    rows.iter().map(certificate_from_row).collect()
+}
+
+/// Get a certificate by ID from the database
+pub async fn get_certificate_by_id(pool: &Pool<Sqlite>, certificate_id: &str) -> Result<Certificate> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, cert, key, snis, created_at, updated_at
+        FROM certificates
+        WHERE id = ?
+        "#
+    )
+    .bind(certificate_id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch certificate from SQLite database")?;
+    
+    match row {
+        Some(row) => certificate_from_row(&row),
+        None => Err(anyhow!("Certificate with ID '{}' not found", certificate_id))
+    }
+}
+
+/// Insert a certificate, or replace the PEM data and SNIs of an existing one
+pub async fn upsert_certificate(pool: &Pool<Sqlite>, certificate: &Certificate) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO certificates (id, cert, key, snis, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET
+            cert = excluded.cert, key = excluded.key, snis = excluded.snis, updated_at = excluded.updated_at
+        "#
+    )
+    .bind(&certificate.id)
+    .bind(&certificate.cert)
+    .bind(&certificate.key)
+    .bind(serde_json::to_string(&certificate.snis)?)
+    .bind(certificate.created_at)
+    .bind(certificate.updated_at)
+    .execute(pool)
+    .await
+    .context("Failed to store certificate in SQLite database")?;
+    
+    info!("Stored certificate with ID: {}", certificate.id);
+    Ok(())
+}
+
+fn certificate_from_row(row: &SqliteRow) -> Result<Certificate> {
+    let snis_json: Option<String> = row.try_get("snis")?;
+    let snis = snis_json
+        .and_then(|json| serde_json::from_str::<Vec<String>>(&json).ok())
+        .unwrap_or_default();
+    
+    Ok(Certificate {
+        id: row.try_get("id")?,
+        cert: row.try_get("cert")?,
+        key: row.try_get("key")?,
+        snis,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+/// Delete a certificate together with the SNIs mapped to it
+pub async fn delete_certificate(pool: &Pool<Sqlite>, certificate_id: &str) -> Result<()> {
+    let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+    
+    let sni_ids = sqlx::query("SELECT id FROM snis WHERE certificate_id = ?")
+        .bind(certificate_id)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to fetch SNIs of certificate")?
+        .iter()
+        .map(|row| row.try_get::<String, _>("id"))
+        .collect::<Result<Vec<String>, _>>()?;
+    
+    sqlx::query("DELETE FROM snis WHERE certificate_id = ?")
+        .bind(certificate_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete SNIs of certificate")?;
+    
+    let delete_result = sqlx::query("DELETE FROM certificates WHERE id = ?")
+        .bind(certificate_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete certificate")?;
+    
+    if delete_result.rows_affected() == 0 {
+        return Err(anyhow!("Certificate with ID '{}' not found", certificate_id));
+    }
+    
+    // Track deletions for incremental updates
+    sqlx::query("INSERT INTO certificate_deletions (id, deleted_at) VALUES (?, CURRENT_TIMESTAMP) ON CONFLICT(id) DO UPDATE SET deleted_at = CURRENT_TIMESTAMP")
+        .bind(certificate_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to track certificate deletion")?;
+    
+    for sni_id in &sni_ids {
+        sqlx::query("INSERT INTO sni_deletions (id, deleted_at) VALUES (?, CURRENT_TIMESTAMP) ON CONFLICT(id) DO UPDATE SET deleted_at = CURRENT_TIMESTAMP")
+            .bind(sni_id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to track SNI deletion")?;
+    }
+    
+    tx.commit().await.context("Failed to commit transaction")?;
+    
+    info!("Deleted certificate with ID: {} and {} SNIs", certificate_id, sni_ids.len());
+    Ok(())
+}
+
+/// Certificates created or updated after the given time
+pub async fn load_certificates_updated_since(pool: &Pool<Sqlite>, since: DateTime<Utc>) -> Result<Vec<Certificate>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, cert, key, snis, created_at, updated_at
+        FROM certificates
+        WHERE updated_at > ?
+        ORDER BY updated_at
+        "#
+    )
+    .bind(since)
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch updated certificates from SQLite database")?;
+    
+    rows.iter().map(certificate_from_row).collect()
+}
+
+/// Load the SNI hostname mappings managed through the Admin API
+pub async fn load_snis(pool: &Pool<Sqlite>) -> Result<Vec<Sni>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, name, certificate_id, created_at, updated_at
+        FROM snis
+        ORDER BY name
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to load SNIs from SQLite database")?;
+    
+    rows.iter().map(sni_from_row).collect()
+}
+
+/// SNIs created or updated after the given time
+pub async fn load_snis_updated_since(pool: &Pool<Sqlite>, since: DateTime<Utc>) -> Result<Vec<Sni>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, name, certificate_id, created_at, updated_at
+        FROM snis
+        WHERE updated_at > ?
+        ORDER BY updated_at
+        "#
+    )
+    .bind(since)
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch updated SNIs from SQLite database")?;
+    
+    rows.iter().map(sni_from_row).collect()
+}
+
+/// Insert an SNI, or point an existing one at another hostname or certificate
+pub async fn upsert_sni(pool: &Pool<Sqlite>, sni: &Sni) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO snis (id, name, certificate_id, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET
+            name = excluded.name, certificate_id = excluded.certificate_id, updated_at = excluded.updated_at
+        "#
+    )
+    .bind(&sni.id)
+    .bind(&sni.name)
+    .bind(&sni.certificate_id)
+    .bind(sni.created_at)
+    .bind(sni.updated_at)
+    .execute(pool)
+    .await
+    .context("Failed to store SNI in SQLite database")?;
+    
+    info!("Stored SNI {} for certificate {}", sni.name, sni.certificate_id);
+    Ok(())
+}
+
+/// Delete an SNI by ID
+pub async fn delete_sni(pool: &Pool<Sqlite>, sni_id: &str) -> Result<()> {
+    let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+    
+    let delete_result = sqlx::query("DELETE FROM snis WHERE id = ?")
+        .bind(sni_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete SNI")?;
+    
+    if delete_result.rows_affected() == 0 {
+        return Err(anyhow!("SNI with ID '{}' not found", sni_id));
+    }
+    
+    sqlx::query("INSERT INTO sni_deletions (id, deleted_at) VALUES (?, CURRENT_TIMESTAMP) ON CONFLICT(id) DO UPDATE SET deleted_at = CURRENT_TIMESTAMP")
+        .bind(sni_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to track SNI deletion")?;
+    
+    tx.commit().await.context("Failed to commit transaction")?;
+    
+    info!("Deleted SNI with ID: {}", sni_id);
+    Ok(())
+}
+
+/// IDs recorded in a deletion tracking table (e.g. "sni_deletions") after the given time
+pub async fn load_deleted_ids_since(pool: &Pool<Sqlite>, table: &str, since: DateTime<Utc>) -> Result<Vec<String>> {
+    let rows = sqlx::query(&format!("SELECT id FROM {} WHERE deleted_at > ?", table))
+        .bind(since)
+        .fetch_all(pool)
+        .await
+        .context(format!("Failed to fetch deleted IDs from {}", table))?;
+    
+    rows.iter()
+        .map(|row| row.try_get::<String, _>("id").map_err(Into::into))
+        .collect()
+}
+
+fn sni_from_row(row: &SqliteRow) -> Result<Sni> {
+    Ok(Sni {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        certificate_id: row.try_get("certificate_id")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+/// Record a configuration version, keeping only the newest `retain` versions (all when 0)
+pub async fn insert_config_version(pool: &Pool<Sqlite>, author: &str, description: &str, document: &str, retain: u64) -> Result<i64> {
+    let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+    
+    let id = sqlx::query(
+        r#"
+        INSERT INTO config_versions (author, description, document, created_at)
+        VALUES (?, ?, ?, ?)
+        "#
+    )
+    .bind(author)
+    .bind(description)
+    .bind(document)
+    .bind(Utc::now())
+    .execute(&mut *tx)
+    .await
+    .context("Failed to store configuration version")?
+    .last_insert_rowid();
+    
+    if retain > 0 {
+        sqlx::query("DELETE FROM config_versions WHERE id <= ?")
+            .bind(id - retain as i64)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to prune configuration versions")?;
+    }
+    
+    tx.commit().await.context("Failed to commit transaction")?;
+    
+    info!("Recorded configuration version {} by {}", id, author);
+    Ok(id)
+}
+
+/// List configuration versions, newest first, without their documents
+pub async fn list_config_versions(pool: &Pool<Sqlite>) -> Result<Vec<ConfigVersion>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, author, description, created_at
+        FROM config_versions
+        ORDER BY id DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to load configuration versions from SQLite database")?;
+    
+    rows.iter().map(|row| config_version_from_row(row, false)).collect()
+}
+
+/// Get a configuration version with its document, or the latest one when no ID is given
+pub async fn get_config_version(pool: &Pool<Sqlite>, version_id: Option<i64>) -> Result<Option<ConfigVersion>> {
+    let row = match version_id {
+        Some(version_id) => sqlx::query(
+            "SELECT id, author, description, document, created_at FROM config_versions WHERE id = ?"
+        )
+        .bind(version_id)
+        .fetch_optional(pool)
+        .await,
+        None => sqlx::query(
+            "SELECT id, author, description, document, created_at FROM config_versions ORDER BY id DESC LIMIT 1"
+        )
+        .fetch_optional(pool)
+        .await,
+    }
+    .context("Failed to fetch configuration version from SQLite database")?;
+    
+    row.map(|row| config_version_from_row(&row, true)).transpose()
+}
+
+fn config_version_from_row(row: &SqliteRow, with_document: bool) -> Result<ConfigVersion> {
+    let config = if with_document {
+        let document: String = row.try_get("document")?;
+        Some(serde_json::from_str::<Configuration>(&document).context("Stored configuration version is not valid")?)
+    } else {
+        None
+    };
+    
+    Ok(ConfigVersion {
+        id: row.try_get("id")?,
+        author: row.try_get("author")?,
+        description: row.try_get("description")?,
+        created_at: row.try_get("created_at")?,
+        config,
+    })
+}
+
+/// Record an Admin API change in the audit log
+pub async fn insert_audit_entry(pool: &Pool<Sqlite>, entry: &AuditEntry) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (actor, action, entity_type, entity_id, request, before_json, after_json, source_ip, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#
+    )
+    .bind(&entry.actor)
+    .bind(&entry.action)
+    .bind(&entry.entity_type)
+    .bind(&entry.entity_id)
+    .bind(&entry.request)
+    .bind(entry.before.as_ref().map(Value::to_string))
+    .bind(entry.after.as_ref().map(Value::to_string))
+    .bind(&entry.source_ip)
+    .bind(entry.created_at)
+    .execute(pool)
+    .await
+    .context("Failed to store audit log entry")?;
+    
+    Ok(())
+}
+
+/// List audit log entries matching a filter, newest first, with the number of matching entries
+pub async fn list_audit_entries(pool: &Pool<Sqlite>, filter: &AuditFilter, limit: usize, offset: usize) -> Result<(Vec<AuditEntry>, usize)> {
+    let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM audit_log");
+    push_audit_filter(&mut count, filter);
+    let total: i64 = count.build_query_scalar()
+        .fetch_one(pool)
+        .await
+        .context("Failed to count audit log entries in SQLite database")?;
+    
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT id, actor, action, entity_type, entity_id, request, before_json, after_json, source_ip, created_at FROM audit_log"
+    );
+    push_audit_filter(&mut query, filter);
+    query.push(" ORDER BY id DESC LIMIT ").push_bind(limit as i64)
+        .push(" OFFSET ").push_bind(offset as i64);
+    
+    let rows = query.build()
+        .fetch_all(pool)
+        .await
+        .context("Failed to load audit log entries from SQLite database")?;
+    
+    let entries = rows.iter().map(audit_entry_from_row).collect::<Result<Vec<_>>>()?;
+    Ok((entries, total as usize))
+}
+
+fn push_audit_filter(query: &mut QueryBuilder<'_, Sqlite>, filter: &AuditFilter) {
+    let mut separator = " WHERE ";
+    for (column, value) in [
+        ("actor", &filter.actor),
+        ("action", &filter.action),
+        ("entity_type", &filter.entity_type),
+        ("entity_id", &filter.entity_id),
+    ] {
+        if let Some(value) = value {
+            query.push(separator).push(column).push(" = ").push_bind(value.clone());
+            separator = " AND ";
+        }
+    }
+    if let Some(since) = filter.since {
+        query.push(separator).push("created_at >= ").push_bind(since);
+        separator = " AND ";
+    }
+    if let Some(until) = filter.until {
+        query.push(separator).push("created_at < ").push_bind(until);
+        separator = " AND ";
+    }
+    if let Some(before_id) = filter.before_id {
+        query.push(separator).push("id < ").push_bind(before_id);
+    }
+}
+
+fn audit_entry_from_row(row: &SqliteRow) -> Result<AuditEntry> {
+    let json = |column: &str| -> Result<Option<Value>> {
+        let text: Option<String> = row.try_get(column)?;
+        text.map(|text| serde_json::from_str(&text).context("Stored audit log entry is not valid JSON"))
+            .transpose()
+    };
+    
+    Ok(AuditEntry {
+        id: row.try_get("id")?,
+        actor: row.try_get("actor")?,
+        action: row.try_get("action")?,
+        entity_type: row.try_get("entity_type")?,
+        entity_id: row.try_get("entity_id")?,
+        request: row.try_get("request")?,
+        before: json("before_json")?,
+        after: json("after_json")?,
+        source_ip: row.try_get("source_ip")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+/// List the Admin API users by username
+pub async fn list_admin_users(pool: &Pool<Sqlite>) -> Result<Vec<AdminUser>> {
+    let rows = sqlx::query(
+        "SELECT username, password_hash, role, created_at, updated_at FROM admin_users ORDER BY username"
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to load admin users from SQLite database")?;
+    
+    rows.iter().map(admin_user_from_row).collect()
+}
+
+/// Get an Admin API user by username
+pub async fn get_admin_user(pool: &Pool<Sqlite>, username: &str) -> Result<Option<AdminUser>> {
+    let row = sqlx::query(
+        "SELECT username, password_hash, role, created_at, updated_at FROM admin_users WHERE username = ?"
+    )
+    .bind(username)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch admin user from SQLite database")?;
+    
+    row.as_ref().map(admin_user_from_row).transpose()
+}
+
+/// Insert an Admin API user, or replace the password and role of an existing one
+pub async fn upsert_admin_user(pool: &Pool<Sqlite>, user: &AdminUser) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO admin_users (username, password_hash, role, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT (username) DO UPDATE SET
+            password_hash = excluded.password_hash, role = excluded.role, updated_at = excluded.updated_at
+        "#
+    )
+    .bind(&user.username)
+    .bind(&user.password_hash)
+    .bind(user.role.as_str())
+    .bind(user.created_at)
+    .bind(user.updated_at)
+    .execute(pool)
+    .await
+    .context("Failed to store admin user in SQLite database")?;
+    
+    info!("Stored admin user {} with role {}", user.username, user.role.as_str());
+    Ok(())
+}
+
+/// Delete an Admin API user, returning whether it existed
+pub async fn delete_admin_user(pool: &Pool<Sqlite>, username: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM admin_users WHERE username = ?")
+        .bind(username)
+        .execute(pool)
+        .await
+        .context("Failed to delete admin user")?;
+    
+    Ok(result.rows_affected() > 0)
+}
+
+fn admin_user_from_row(row: &SqliteRow) -> Result<AdminUser> {
+    let role: String = row.try_get("role")?;
+    Ok(AdminUser {
+        username: row.try_get("username")?,
+        password_hash: row.try_get("password_hash")?,
+        role: role.parse().map_err(|e: String| anyhow!("Stored admin user has an invalid role: {}", e))?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+/// Revoke an Admin API token until it expires, dropping revocations of expired tokens
+pub async fn revoke_admin_token(pool: &Pool<Sqlite>, jti: &str, username: &str, expires_at: DateTime<Utc>) -> Result<()> {
+    sqlx::query("DELETE FROM admin_token_revocations WHERE expires_at < ?")
+        .bind(Utc::now())
+        .execute(pool)
+        .await
+        .context("Failed to purge expired admin token revocations")?;
+    
+    sqlx::query(
+        "INSERT INTO admin_token_revocations (jti, username, expires_at) VALUES (?, ?, ?) ON CONFLICT (jti) DO NOTHING"
+    )
+    .bind(jti)
+    .bind(username)
+    .bind(expires_at)
+    .execute(pool)
+    .await
+    .context("Failed to revoke admin token in SQLite database")?;
+    
+    Ok(())
+}
+
+/// Whether an Admin API token has been revoked
+pub async fn is_admin_token_revoked(pool: &Pool<Sqlite>, jti: &str) -> Result<bool> {
+    let row = sqlx::query("SELECT jti FROM admin_token_revocations WHERE jti = ?")
+        .bind(jti)
+        .fetch_optional(pool)
+        .await
+        .context("Failed to check admin token revocation in SQLite database")?;
+    
+    Ok(row.is_some())
+}
+
+/// Store an Admin API token created through /tokens
+pub async fn create_api_token(pool: &Pool<Sqlite>, token: &ApiToken) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO admin_api_tokens (id, name, username, role, workspace, scope_json, expires_at, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&token.id)
+    .bind(&token.name)
+    .bind(&token.username)
+    .bind(token.role.as_str())
+    .bind(&token.workspace)
+    .bind(serde_json::to_string(&token.scope)?)
+    .bind(token.expires_at)
+    .bind(token.created_at)
+    .execute(pool)
+    .await
+    .context("Failed to store admin API token in SQLite database")?;
+    
+    Ok(())
+}
+
+/// List the Admin API tokens, of one user or of everyone, newest first
+pub async fn list_api_tokens(pool: &Pool<Sqlite>, username: Option<&str>) -> Result<Vec<ApiToken>> {
+    let rows = sqlx::query(
+        "SELECT id, name, username, role, workspace, scope_json, expires_at, created_at FROM admin_api_tokens WHERE ? IS NULL OR username = ? ORDER BY created_at DESC"
+    )
+    .bind(username)
+    .bind(username)
+    .fetch_all(pool)
+    .await
+    .context("Failed to load admin API tokens from SQLite database")?;
+    
+    rows.iter().map(api_token_from_row).collect()
+}
+
+/// Get an Admin API token by ID
+pub async fn get_api_token(pool: &Pool<Sqlite>, id: &str) -> Result<Option<ApiToken>> {
+    let row = sqlx::query(
+        "SELECT id, name, username, role, workspace, scope_json, expires_at, created_at FROM admin_api_tokens WHERE id = ?"
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch admin API token from SQLite database")?;
+    
+    row.as_ref().map(api_token_from_row).transpose()
+}
+
+/// Delete an Admin API token, returning whether it existed
+pub async fn delete_api_token(pool: &Pool<Sqlite>, id: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM admin_api_tokens WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .context("Failed to delete admin API token")?;
+    
+    Ok(result.rows_affected() > 0)
+}
+
+fn api_token_from_row(row: &SqliteRow) -> Result<ApiToken> {
+    let role: String = row.try_get("role")?;
+    let scope: String = row.try_get("scope_json")?;
+    Ok(ApiToken {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        username: row.try_get("username")?,
+        role: role.parse().map_err(|e: String| anyhow!("Stored admin API token has an invalid role: {}", e))?,
+        workspace: row.try_get("workspace")?,
+        scope: serde_json::from_str(&scope).context("Stored admin API token has an invalid scope")?,
+        expires_at: row.try_get("expires_at")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
 /// SQLite implementation of the database client
 pub struct SqliteClient {
     pool: SqlitePool,
@@ -294,6 +960,10 @@ impl SqliteClient {
             CREATE TABLE IF NOT EXISTS proxies (
                 id TEXT PRIMARY KEY,
                 name TEXT,
+                workspace TEXT NOT NULL DEFAULT 'default',
+                version INTEGER NOT NULL DEFAULT 1,
+                tags TEXT NOT NULL DEFAULT '[]',
+                enabled INTEGER NOT NULL DEFAULT 1,
                 listen_path TEXT NOT NULL UNIQUE,
                 backend_protocol TEXT NOT NULL,
                 backend_host TEXT NOT NULL,
@@ -311,10 +981,21 @@ impl SqliteClient {
                 dns_override TEXT,
                 dns_cache_ttl_seconds INTEGER,
                 auth_mode TEXT NOT NULL DEFAULT 'single',
+                error_templates TEXT,
+                backend_discovery TEXT,
+                dns_resolvers TEXT,
+                log_sampling TEXT,
+                api_versioning TEXT,
+                max_requests_per_second INTEGER,
+                backend_keepalive TEXT,
+                backend_header_case TEXT,
+                upstream_host TEXT,
+                egress_proxy TEXT,
                 created_at TEXT NOT NULL,
                 updated_at TEXT NOT NULL
             );
             CREATE INDEX IF NOT EXISTS idx_proxies_listen_path ON proxies(listen_path);
+            CREATE INDEX IF NOT EXISTS idx_proxies_workspace ON proxies(workspace);
             "#
         )
         .execute(pool)
@@ -327,6 +1008,9 @@ impl SqliteClient {
             CREATE TABLE IF NOT EXISTS consumers (
                 id TEXT PRIMARY KEY,
                 username TEXT NOT NULL UNIQUE,
+                workspace TEXT NOT NULL DEFAULT 'default',
+                version INTEGER NOT NULL DEFAULT 1,
+                tags TEXT NOT NULL DEFAULT '[]',
                 custom_id TEXT,
                 credentials TEXT,
                 created_at TEXT NOT NULL,
@@ -345,6 +1029,9 @@ impl SqliteClient {
             CREATE TABLE IF NOT EXISTS plugin_configs (
                 id TEXT PRIMARY KEY,
                 plugin_name TEXT NOT NULL,
+                workspace TEXT NOT NULL DEFAULT 'default',
+                version INTEGER NOT NULL DEFAULT 1,
+                tags TEXT NOT NULL DEFAULT '[]',
                 config TEXT NOT NULL,
                 scope TEXT NOT NULL,
                 proxy_id TEXT,
@@ -406,6 +1093,50 @@ impl SqliteClient {
         .await
         .map_err(|e| anyhow!("Failed to create consumer_deletions table: {}", e))?;
         
+        // Create certificates table
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS certificates (
+                id TEXT PRIMARY KEY,
+                cert TEXT NOT NULL,
+                key TEXT NOT NULL,
+                snis TEXT NOT NULL DEFAULT '[]',
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#
+        )
+        .execute(pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create certificates table: {}", e))?;
+        
+        // Create snis table
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS snis (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                certificate_id TEXT NOT NULL REFERENCES certificates(id) ON DELETE CASCADE,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#
+        )
+        .execute(pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create snis table: {}", e))?;
+        
+        // Create certificate and SNI deletion tracking tables
+        for table in ["certificate_deletions", "sni_deletions"] {
+            sqlx::query(&format!(
+                "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, deleted_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP)",
+                table
+            ))
+            .execute(pool)
+            .await
+            .map_err(|e| anyhow!("Failed to create {} table: {}", table, e))?;
+        }
+        
         // Create plugin_config_deletions table
         sqlx::query(
             r#"
@@ -419,6 +1150,43 @@ impl SqliteClient {
         .await
         .map_err(|e| anyhow!("Failed to create plugin_config_deletions table: {}", e))?;
         
+        // Create config_versions table
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS config_versions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                author TEXT NOT NULL,
+                description TEXT NOT NULL,
+                document TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+            "#
+        )
+        .execute(pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create config_versions table: {}", e))?;
+        
+        // Create audit_log table
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                actor TEXT NOT NULL,
+                action TEXT NOT NULL,
+                entity_type TEXT NOT NULL,
+                entity_id TEXT,
+                request TEXT NOT NULL,
+                before_json TEXT,
+                after_json TEXT,
+                source_ip TEXT,
+                created_at TEXT NOT NULL
+            );
+            "#
+        )
+        .execute(pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create audit_log table: {}", e))?;
+        
         debug!("SQLite tables created/verified");
         
         Ok(())
@@ -459,6 +1227,8 @@ impl SqliteClient {
             proxies: proxies_with_plugins,
             consumers,
             plugin_configs,
+            certificates: load_certificates(&self.pool).await?,
+            snis: load_snis(&self.pool).await?,
             last_updated_at: Utc::now(),
         })
     }
@@ -476,6 +1246,7 @@ impl SqliteClient {
                 backend_tls_client_cert_path, backend_tls_client_key_path, backend_tls_verify_server_cert,
                 backend_tls_server_ca_cert_path, dns_override, dns_cache_ttl_seconds, 
                 auth_mode as "auth_mode: String",
+                error_templates, backend_discovery, dns_resolvers, log_sampling, api_versioning, max_requests_per_second, backend_keepalive, backend_header_case, upstream_host, egress_proxy, workspace, version, tags, enabled,
                 created_at, updated_at
             FROM proxies
             "#
@@ -512,7 +1283,7 @@ impl SqliteClient {
             Consumer,
             r#"
             SELECT 
-                id, username, custom_id, credentials, created_at, updated_at
+                id, username, workspace, version, tags, custom_id, credentials, created_at, updated_at
             FROM consumers
             "#
         )
@@ -528,7 +1299,7 @@ impl SqliteClient {
             PluginConfig,
             r#"
             SELECT 
-                id, plugin_name, config, 
+                id, plugin_name, workspace, version, tags, config, 
                 scope, proxy_id, consumer_id, 
                 enabled, created_at, updated_at
             FROM plugin_configs
@@ -609,14 +1380,14 @@ impl SqliteClient {
                 backend_connect_timeout_ms, backend_read_timeout_ms, backend_write_timeout_ms,
                 backend_tls_client_cert_path, backend_tls_client_key_path,
                 backend_tls_verify_server_cert, backend_tls_server_ca_cert_path,
-                dns_override, dns_cache_ttl_seconds, auth_mode, created_at, updated_at
+                dns_override, dns_cache_ttl_seconds, auth_mode, error_templates, backend_discovery, dns_resolvers, log_sampling, api_versioning, max_requests_per_second, backend_keepalive, backend_header_case, upstream_host, egress_proxy, workspace, tags, enabled, created_at, updated_at
             ) VALUES (
                 ?, ?, ?, ?, ?, ?, 
                 ?, ?, ?, 
                 ?, ?, ?,
                 ?, ?,
                 ?, ?,
-                ?, ?, ?, ?, ?
+                ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?
             )
             "#
         )
@@ -639,6 +1410,19 @@ impl SqliteClient {
         .bind(&proxy.dns_override)
         .bind(proxy.dns_cache_ttl_seconds.map(|ttl| ttl as i64))
         .bind(auth_mode)
+        .bind(serde_json::to_string(&proxy.error_templates).unwrap_or_default())
+        .bind(proxy.backend_discovery.as_ref().map(|d| serde_json::to_string(d).unwrap_or_default()))
+        .bind(serde_json::to_string(&proxy.dns_resolvers).unwrap_or_default())
+        .bind(proxy.log_sampling.as_ref().map(|s| serde_json::to_string(s).unwrap_or_default()))
+        .bind(proxy.api_versioning.as_ref().map(|v| serde_json::to_string(v).unwrap_or_default()))
+        .bind(proxy.max_requests_per_second.map(|rps| rps as i64))
+        .bind(proxy.backend_keepalive.as_ref().map(|k| serde_json::to_string(k).unwrap_or_default()))
+        .bind(proxy.backend_header_case.map(|c| c.as_str()))
+        .bind(&proxy.upstream_host)
+        .bind(&proxy.egress_proxy)
+        .bind(&proxy.workspace)
+        .bind(serde_json::to_string(&proxy.tags).unwrap_or_default())
+        .bind(proxy.enabled)
         .bind(created_at)
         .bind(updated_at)
         .execute(&self.pool)
@@ -700,7 +1484,7 @@ impl SqliteClient {
         let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
         
         // Update the proxy
-        sqlx::query!(
+        let result = sqlx::query!(
             r#"
             UPDATE proxies
             SET 
@@ -722,8 +1506,22 @@ impl SqliteClient {
                 dns_override = ?,
                 dns_cache_ttl_seconds = ?,
                 auth_mode = ?,
+                error_templates = ?,
+                backend_discovery = ?,
+                dns_resolvers = ?,
+                log_sampling = ?,
+                api_versioning = ?,
+                max_requests_per_second = ?,
+                backend_keepalive = ?,
+                backend_header_case = ?,
+                upstream_host = ?,
+                egress_proxy = ?,
+                workspace = ?,
+                tags = ?,
+                enabled = ?,
+                version = version + 1,
                 updated_at = datetime('now')
-            WHERE id = ?
+            WHERE id = ? AND version = ?
             "#,
             proxy.name,
             proxy.listen_path,
@@ -743,12 +1541,31 @@ impl SqliteClient {
             proxy.dns_override,
             proxy.dns_cache_ttl_seconds.map(|ttl| ttl as i64),
             auth_mode_str,
-            proxy.id
+            serde_json::to_string(&proxy.error_templates).unwrap_or_default(),
+            proxy.backend_discovery.as_ref().map(|d| serde_json::to_string(d).unwrap_or_default()),
+            serde_json::to_string(&proxy.dns_resolvers).unwrap_or_default(),
+            proxy.log_sampling.as_ref().map(|s| serde_json::to_string(s).unwrap_or_default()),
+            proxy.api_versioning.as_ref().map(|v| serde_json::to_string(v).unwrap_or_default()),
+            proxy.max_requests_per_second.map(|rps| rps as i64),
+            proxy.backend_keepalive.as_ref().map(|k| serde_json::to_string(k).unwrap_or_default()),
+            proxy.backend_header_case.map(|c| c.as_str()),
+            proxy.upstream_host,
+            proxy.egress_proxy,
+            proxy.workspace,
+            serde_json::to_string(&proxy.tags).unwrap_or_default(),
+            proxy.enabled,
+            proxy.id,
+            proxy.version
         )
         .execute(&mut *tx)
         .await
         .context("Failed to update proxy")?;
         
+        if result.rows_affected() == 0 {
+            // The row exists, so it was changed since the caller read it
+            return Err(StaleVersion::new("proxy", &proxy.id, proxy.version).into());
+        }
+        
         // Delete existing plugin associations
         sqlx::query!(
             "DELETE FROM proxy_plugin_associations WHERE proxy_id = ?",
@@ -886,12 +1703,14 @@ impl SqliteClient {
         sqlx::query!(
             r#"
             INSERT INTO consumers (
-                id, username, custom_id, credentials, created_at, updated_at
+                id, username, workspace, tags, custom_id, credentials, created_at, updated_at
             )
-            VALUES (?, ?, ?, ?, datetime('now'), datetime('now'))
+            VALUES (?, ?, ?, ?, ?, ?, datetime('now'), datetime('now'))
             "#,
             id,
             consumer.username,
+            consumer.workspace,
+            serde_json::to_string(&consumer.tags).unwrap_or_default(),
             consumer.custom_id,
             credentials_json
         )
@@ -956,25 +1775,36 @@ impl SqliteClient {
             .context("Failed to serialize consumer credentials")?;
         
         // Update the consumer
-        sqlx::query!(
+        let result = sqlx::query!(
             r#"
             UPDATE consumers
             SET 
                 username = ?,
+                workspace = ?,
+                tags = ?,
                 custom_id = ?,
                 credentials = ?,
+                version = version + 1,
                 updated_at = datetime('now')
-            WHERE id = ?
+            WHERE id = ? AND version = ?
             "#,
             consumer.username,
+            consumer.workspace,
+            serde_json::to_string(&consumer.tags).unwrap_or_default(),
             consumer.custom_id,
             credentials_json,
-            consumer.id
+            consumer.id,
+            consumer.version
         )
         .execute(&self.pool)
         .await
         .context("Failed to update consumer")?;
         
+        if result.rows_affected() == 0 {
+            // The row exists, so it was changed since the caller read it
+            return Err(StaleVersion::new("consumer", &consumer.id, consumer.version).into());
+        }
+        
         info!("Updated consumer with ID: {}", consumer.id);
         Ok(())
     }
@@ -1035,13 +1865,15 @@ impl SqliteClient {
         sqlx::query!(
             r#"
             INSERT INTO plugin_configs (
-                id, plugin_name, config, scope, proxy_id, consumer_id, enabled,
+                id, plugin_name, workspace, tags, config, scope, proxy_id, consumer_id, enabled,
                 created_at, updated_at
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, datetime('now'), datetime('now'))
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now'), datetime('now'))
             "#,
             id,
             plugin_config.plugin_name,
+            plugin_config.workspace,
+            serde_json::to_string(&plugin_config.tags).unwrap_or_default(),
             config_json,
             plugin_config.scope,
             plugin_config.proxy_id,
@@ -1079,31 +1911,42 @@ impl SqliteClient {
             .context("Failed to serialize plugin configuration")?;
         
         // Update the plugin config
-        sqlx::query!(
+        let result = sqlx::query!(
             r#"
             UPDATE plugin_configs
             SET 
                 plugin_name = ?,
+                workspace = ?,
+                tags = ?,
                 config = ?,
                 scope = ?,
                 proxy_id = ?,
                 consumer_id = ?,
                 enabled = ?,
+                version = version + 1,
                 updated_at = datetime('now')
-            WHERE id = ?
+            WHERE id = ? AND version = ?
             "#,
             plugin_config.plugin_name,
+            plugin_config.workspace,
+            serde_json::to_string(&plugin_config.tags).unwrap_or_default(),
             config_json,
             plugin_config.scope,
             plugin_config.proxy_id,
             plugin_config.consumer_id,
             plugin_config.enabled,
-            plugin_config.id
+            plugin_config.id,
+            plugin_config.version
         )
         .execute(&self.pool)
         .await
         .context("Failed to update plugin configuration")?;
         
+        if result.rows_affected() == 0 {
+            // The row exists, so it was changed since the caller read it
+            return Err(StaleVersion::new("plugin config", &plugin_config.id, plugin_config.version).into());
+        }
+        
         info!("Updated plugin configuration with ID: {}", plugin_config.id);
         Ok(())
     }
@@ -1175,6 +2018,10 @@ impl SqliteClient {
                 SELECT MAX(updated_at) as latest_time FROM consumers
                 UNION ALL
                 SELECT MAX(updated_at) as latest_time FROM plugin_configs
+                UNION ALL
+                SELECT MAX(updated_at) as latest_time FROM certificates
+                UNION ALL
+                SELECT MAX(updated_at) as latest_time FROM snis
             ) as latest_updates
             "#
         )
@@ -1277,7 +2124,7 @@ impl SqliteClient {
             Consumer,
             r#"
             SELECT 
-                id, username, custom_id,
+                id, username, workspace, version, tags, custom_id,
                 credentials as "credentials: Value",
                 created_at as "created_at: DateTime<Utc>", 
                 updated_at as "updated_at: DateTime<Utc>"
@@ -1312,7 +2159,7 @@ impl SqliteClient {
             PluginConfig,
             r#"
             SELECT 
-                id, plugin_name,
+                id, plugin_name, workspace, version, tags,
                 config as "config: Value",
                 scope as "scope: String",
                 proxy_id,
@@ -1356,6 +2203,12 @@ impl SqliteClient {
         .map(|row| row.id)
         .collect::<Vec<String>>();
         
+        // Load certificate and SNI changes
+        let updated_certificates = load_certificates_updated_since(&self.pool, since).await?;
+        let deleted_certificate_ids = load_deleted_ids_since(&self.pool, "certificate_deletions", since).await?;
+        let updated_snis = load_snis_updated_since(&self.pool, since).await?;
+        let deleted_sni_ids = load_deleted_ids_since(&self.pool, "sni_deletions", since).await?;
+        
         // Get the latest update timestamp
         let latest_timestamp = self.get_latest_update_timestamp().await?;
         
@@ -1369,6 +2222,10 @@ impl SqliteClient {
             deleted_consumer_ids,
             updated_plugin_configs: processed_plugin_configs,
             deleted_plugin_config_ids,
+            updated_certificates,
+            deleted_certificate_ids,
+            updated_snis,
+            deleted_sni_ids,
             last_updated_at: latest_timestamp,
         })
     }
@@ -0,0 +1,80 @@
+//! Tombstones for incremental configuration loading
+
+use std::time::Duration;
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info};
+
+use super::{mongo, DatabaseClient, DbPool};
+
+/// The deletion tracking table of every entity type
+pub const TOMBSTONE_TABLES: [&str; 5] = [
+    "proxy_deletions",
+    "consumer_deletions",
+    "plugin_config_deletions",
+    "certificate_deletions",
+    "sni_deletions",
+];
+
+/// How often expired tombstones are purged
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+impl DatabaseClient {
+    /// Oldest time a delta load can start from without missing deletions,
+    /// or None when tombstones are kept forever
+    pub fn tombstone_horizon(&self) -> Option<DateTime<Utc>> {
+        if self.tombstone_retention.is_zero() {
+            return None;
+        }
+        chrono::Duration::from_std(self.tombstone_retention).ok()
+            .map(|retention| Utc::now() - retention)
+    }
+
+    /// Deletes the tombstones older than the retention window and returns how many were removed
+    pub async fn purge_tombstones(&self) -> Result<u64> {
+        let cutoff = match self.tombstone_horizon() {
+            Some(cutoff) => cutoff,
+            None => return Ok(0),
+        };
+
+        let mut purged = 0;
+        for table in TOMBSTONE_TABLES {
+            let postgres_query = format!("DELETE FROM {} WHERE deleted_at < $1", table);
+            let query = format!("DELETE FROM {} WHERE deleted_at < ?", table);
+            let result = match &*self.pool {
+                DbPool::Postgres(pool) => sqlx::query(&postgres_query).bind(cutoff).execute(pool).await
+                    .map(|result| result.rows_affected()),
+                DbPool::MySQL(pool) => sqlx::query(&query).bind(cutoff).execute(pool).await
+                    .map(|result| result.rows_affected()),
+                DbPool::SQLite(pool) => sqlx::query(&query).bind(cutoff).execute(pool).await
+                    .map(|result| result.rows_affected()),
+                DbPool::MongoDB(pool) => {
+                    purged += mongo::purge_tombstones(pool, table, cutoff).await
+                        .context(format!("Failed to purge tombstones from {}", table))?;
+                    continue;
+                },
+            };
+            purged += result.context(format!("Failed to purge tombstones from {}", table))?;
+        }
+        Ok(purged)
+    }
+
+    /// Purges expired tombstones every hour. Returns None when tombstones are kept forever.
+    pub fn spawn_tombstone_cleanup(&self) -> Option<JoinHandle<()>> {
+        self.tombstone_horizon()?;
+
+        let db_client = self.clone();
+        Some(tokio::spawn(async move {
+            let mut timer = tokio::time::interval(CLEANUP_INTERVAL);
+            loop {
+                timer.tick().await;
+                match db_client.purge_tombstones().await {
+                    Ok(0) => debug!("No expired tombstones to purge"),
+                    Ok(purged) => info!("Purged {} expired tombstones", purged),
+                    Err(e) => error!("Failed to purge expired tombstones: {:#}", e),
+                }
+            }
+        }))
+    }
+}
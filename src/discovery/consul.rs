@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use anyhow::{Result, Context, anyhow};
+use hyper::{Body, Request, StatusCode};
+use serde::Deserialize;
+use tracing::warn;
+
+use super::{ServiceDiscovery, Target, RETRY_INTERVAL};
+use crate::config::data_model::BackendDiscovery;
+
+/// How long Consul holds a blocking query open when nothing changes
+const BLOCKING_WAIT: &str = "5m";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ServiceEntry {
+    node: Node,
+    service: Service,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Node {
+    address: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Service {
+    #[serde(default)]
+    address: String,
+    port: u16,
+}
+
+/// Follows the passing instances of a Consul service through blocking queries,
+/// which return as soon as an instance registers, deregisters or changes health
+pub(super) async fn watch(
+    discovery: Arc<ServiceDiscovery>,
+    spec: BackendDiscovery,
+    service: String,
+    tag: Option<String>,
+    datacenter: Option<String>,
+) {
+    let mut index = 0;
+    loop {
+        match fetch(&discovery, &service, tag.as_deref(), datacenter.as_deref(), index).await {
+            Ok((targets, new_index)) => {
+                discovery.update(&spec, targets);
+                // Consul asks clients to start over when the index goes backwards
+                index = if new_index < index { 0 } else { new_index };
+            },
+            Err(e) => {
+                warn!("Failed to list instances of Consul service {}: {:#}", service, e);
+                index = 0;
+                tokio::time::sleep(RETRY_INTERVAL).await;
+            }
+        }
+    }
+}
+
+async fn fetch(
+    discovery: &ServiceDiscovery,
+    service: &str,
+    tag: Option<&str>,
+    datacenter: Option<&str>,
+    index: u64,
+) -> Result<(Vec<Target>, u64)> {
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    query.append_pair("passing", "true");
+    query.append_pair("index", &index.to_string());
+    query.append_pair("wait", BLOCKING_WAIT);
+    if let Some(tag) = tag {
+        query.append_pair("tag", tag);
+    }
+    if let Some(datacenter) = datacenter {
+        query.append_pair("dc", datacenter);
+    }
+
+    let uri = format!(
+        "{}/v1/health/service/{}?{}",
+        discovery.settings.consul_addr,
+        url::form_urlencoded::byte_serialize(service.as_bytes()).collect::<String>(),
+        query.finish()
+    );
+
+    let mut request = Request::get(uri);
+    if let Some(token) = &discovery.settings.consul_token {
+        request = request.header("X-Consul-Token", token);
+    }
+    let response = discovery.client.request(request.body(Body::empty())?).await
+        .context("Consul request failed")?;
+
+    if response.status() != StatusCode::OK {
+        return Err(anyhow!("Consul answered {}", response.status()));
+    }
+
+    let new_index = response.headers()
+        .get("X-Consul-Index")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0);
+
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    let entries: Vec<ServiceEntry> = serde_json::from_slice(&body)
+        .context("Invalid Consul health response")?;
+
+    let targets = entries.into_iter()
+        .map(|entry| Target {
+            // Services registered without an address are reached at their node's address
+            host: if entry.service.address.is_empty() { entry.node.address } else { entry.service.address },
+            port: entry.service.port,
+        })
+        .collect();
+
+    Ok((targets, new_index))
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use anyhow::{Result, Context, anyhow};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use futures::StreamExt;
+use hyper::{Body, Request, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{debug, warn};
+
+use super::{ServiceDiscovery, Target, RETRY_INTERVAL, parse_target};
+use crate::config::data_model::BackendDiscovery;
+
+#[derive(Debug, Deserialize)]
+struct RangeResponse {
+    header: ResponseHeader,
+    #[serde(default)]
+    kvs: Vec<KeyValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseHeader {
+    // int64 values are strings in the JSON gateway
+    revision: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyValue {
+    key: String,
+    #[serde(default)]
+    value: String,
+}
+
+/// Follows the keys under an etcd prefix: lists them, then holds a watch open
+/// from the listed revision and lists again on the first change
+pub(super) async fn watch(discovery: Arc<ServiceDiscovery>, spec: BackendDiscovery, prefix: String) {
+    loop {
+        let revision = match list(&discovery, &prefix).await {
+            Ok((targets, revision)) => {
+                discovery.update(&spec, targets);
+                revision
+            },
+            Err(e) => {
+                warn!("Failed to list backend instances under etcd prefix {}: {:#}", prefix, e);
+                tokio::time::sleep(RETRY_INTERVAL).await;
+                continue;
+            }
+        };
+
+        if let Err(e) = wait_for_change(&discovery, &prefix, revision + 1).await {
+            warn!("Lost the etcd watch on prefix {}: {:#}", prefix, e);
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        }
+    }
+}
+
+async fn list(discovery: &ServiceDiscovery, prefix: &str) -> Result<(Vec<Target>, i64)> {
+    let body = json!({
+        "key": BASE64.encode(prefix),
+        "range_end": BASE64.encode(range_end(prefix.as_bytes())),
+    });
+    let request = Request::post(format!("{}/v3/kv/range", discovery.settings.etcd_endpoint))
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))?;
+
+    let response = discovery.client.request(request).await.context("etcd request failed")?;
+    if response.status() != StatusCode::OK {
+        return Err(anyhow!("etcd answered {}", response.status()));
+    }
+
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    let range: RangeResponse = serde_json::from_slice(&body).context("Invalid etcd range response")?;
+    let revision = range.header.revision.parse::<i64>().context("Invalid etcd revision")?;
+
+    let mut targets = Vec::with_capacity(range.kvs.len());
+    for kv in range.kvs {
+        let key = BASE64.decode(&kv.key).map(|k| String::from_utf8_lossy(&k).into_owned()).unwrap_or(kv.key);
+        let value = BASE64.decode(&kv.value).map(|v| String::from_utf8_lossy(&v).into_owned()).unwrap_or_default();
+        match parse_target(&value) {
+            Some(target) => targets.push(target),
+            None => warn!("Ignoring etcd key {}: expected host:port or {{\"host\", \"port\"}}, got {:?}", key, value),
+        }
+    }
+
+    Ok((targets, revision))
+}
+
+/// Returns once any key under the prefix changes after `start_revision`, or when
+/// etcd ends the watch
+async fn wait_for_change(discovery: &ServiceDiscovery, prefix: &str, start_revision: i64) -> Result<()> {
+    let body = json!({
+        "create_request": {
+            "key": BASE64.encode(prefix),
+            "range_end": BASE64.encode(range_end(prefix.as_bytes())),
+            "start_revision": start_revision.to_string(),
+        }
+    });
+    let request = Request::post(format!("{}/v3/watch", discovery.settings.etcd_endpoint))
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))?;
+
+    let response = discovery.client.request(request).await.context("etcd watch request failed")?;
+    if response.status() != StatusCode::OK {
+        return Err(anyhow!("etcd answered {}", response.status()));
+    }
+
+    // The gateway streams one JSON object per watch response; the first one only confirms the watch
+    let mut stream = response.into_body();
+    let mut buffer = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        buffer.extend_from_slice(&chunk?);
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let message: serde_json::Value = match serde_json::from_slice(&line) {
+                Ok(message) => message,
+                Err(_) => continue,
+            };
+            let result = &message["result"];
+            if result["events"].as_array().map_or(false, |events| !events.is_empty()) {
+                debug!("Keys under etcd prefix {} changed", prefix);
+                return Ok(());
+            }
+            if result["canceled"].as_bool() == Some(true) || result["compact_revision"].is_string() {
+                // The revision was compacted away; listing again starts a fresh watch
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The end of the key range covering every key that starts with the prefix
+fn range_end(prefix: &[u8]) -> Vec<u8> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < 0xff {
+            end.push(last + 1);
+            return end;
+        }
+    }
+    // Every byte was 0xff: the range extends to the end of the keyspace
+    vec![0]
+}
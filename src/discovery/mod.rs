@@ -0,0 +1,187 @@
+//! Service discovery for Ferrum Gateway
+
+mod consul;
+mod etcd;
+mod srv;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use dashmap::DashMap;
+use hyper::client::HttpConnector;
+use hyper_tls::HttpsConnector;
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+
+use crate::config::data_model::{BackendDiscovery, Configuration};
+use crate::config::env_config::EnvConfig;
+use crate::dns::DnsCache;
+
+/// How long to wait before asking a registry again after a failed request
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+type RegistryClient = hyper::Client<HttpsConnector<HttpConnector>>;
+
+/// A backend instance listed by a service registry
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Target {
+    pub host: String,
+    pub port: u16,
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+/// Parses an instance address as stored in a registry: `host:port`,
+/// `[ipv6]:port` or a JSON object with `host` (or `address`) and `port`
+pub fn parse_target(value: &str) -> Option<Target> {
+    let value = value.trim();
+
+    if value.starts_with('{') {
+        let object: serde_json::Value = serde_json::from_str(value).ok()?;
+        let host = object.get("host").or_else(|| object.get("address"))?.as_str()?;
+        let port = object.get("port")?.as_u64().and_then(|port| u16::try_from(port).ok())?;
+        return Some(Target { host: host.to_string(), port });
+    }
+
+    let (host, port) = value.rsplit_once(':')?;
+    let host = host.strip_prefix('[').and_then(|h| h.strip_suffix(']')).unwrap_or(host);
+    if host.is_empty() {
+        return None;
+    }
+    Some(Target { host: host.to_string(), port: port.parse().ok()? })
+}
+
+/// Current instances of one registry entry, handed out round-robin
+#[derive(Default)]
+struct TargetSet {
+    targets: Vec<Target>,
+    next: AtomicUsize,
+}
+
+/// Where the registries are and how to authenticate to them
+#[derive(Debug, Clone)]
+pub struct DiscoverySettings {
+    pub consul_addr: String,
+    pub consul_token: Option<String>,
+    pub etcd_endpoint: String,
+}
+
+impl DiscoverySettings {
+    pub fn from_env_config(env_config: &EnvConfig) -> Self {
+        Self {
+            consul_addr: env_config.consul_addr.clone(),
+            consul_token: env_config.consul_token.clone(),
+            etcd_endpoint: env_config.etcd_endpoint.clone(),
+        }
+    }
+}
+
+/// Keeps the instance lists of every registry entry used by the configuration
+/// up to date. Each entry has a watcher task that follows the registry, so the
+/// request path only reads the latest list.
+pub struct ServiceDiscovery {
+    settings: DiscoverySettings,
+    client: RegistryClient,
+    targets: DashMap<BackendDiscovery, Arc<TargetSet>>,
+    watchers: Mutex<HashMap<BackendDiscovery, JoinHandle<()>>>,
+    dns_cache: Arc<DnsCache>,
+}
+
+impl ServiceDiscovery {
+    pub fn new(settings: DiscoverySettings, dns_cache: Arc<DnsCache>) -> Arc<Self> {
+        Arc::new(Self {
+            settings,
+            client: hyper::Client::builder().build(HttpsConnector::new()),
+            targets: DashMap::new(),
+            watchers: Mutex::new(HashMap::new()),
+            dns_cache,
+        })
+    }
+
+    /// Starts watching the registry entries the configuration uses and stops
+    /// watching those it no longer uses
+    pub fn sync(self: &Arc<Self>, config: &Configuration) {
+        let wanted: Vec<BackendDiscovery> = config.proxies.iter()
+            .filter_map(|proxy| proxy.backend_discovery.clone())
+            .collect();
+
+        let mut watchers = self.watchers.lock().unwrap();
+
+        watchers.retain(|spec, handle| {
+            let keep = wanted.contains(spec);
+            if !keep {
+                debug!("No proxy uses {:?} anymore, stopping its watcher", spec);
+                handle.abort();
+                self.targets.remove(spec);
+            }
+            keep
+        });
+
+        for spec in wanted {
+            if watchers.contains_key(&spec) {
+                continue;
+            }
+            info!("Watching {:?} for backend instances", spec);
+            let discovery = Arc::clone(self);
+            let handle = match spec.clone() {
+                BackendDiscovery::Consul { service, tag, datacenter } => tokio::spawn(
+                    consul::watch(discovery, spec.clone(), service, tag, datacenter)
+                ),
+                BackendDiscovery::Etcd { prefix } => tokio::spawn(
+                    etcd::watch(discovery, spec.clone(), prefix)
+                ),
+                BackendDiscovery::Srv { name } => tokio::spawn(
+                    srv::watch(discovery, spec.clone(), name)
+                ),
+            };
+            watchers.insert(spec, handle);
+        }
+    }
+
+    /// The next instance to send a request to, rotating through the current
+    /// instances. None until the registry has listed at least one.
+    pub fn next_target(&self, spec: &BackendDiscovery) -> Option<Target> {
+        let set = self.targets.get(spec)?.clone();
+        if set.targets.is_empty() {
+            return None;
+        }
+        let index = set.next.fetch_add(1, Ordering::Relaxed) % set.targets.len();
+        Some(set.targets[index].clone())
+    }
+
+    /// All current instances of a registry entry
+    pub fn targets(&self, spec: &BackendDiscovery) -> Vec<Target> {
+        self.targets.get(spec)
+            .map(|set| set.targets.clone())
+            .unwrap_or_default()
+    }
+
+    /// Replaces the instances of a registry entry, logging what changed
+    fn update(&self, spec: &BackendDiscovery, mut targets: Vec<Target>) {
+        targets.sort();
+        targets.dedup();
+
+        let previous = self.targets(spec);
+        if previous == targets && self.targets.contains_key(spec) {
+            return;
+        }
+
+        for target in targets.iter().filter(|t| !previous.contains(t)) {
+            info!("Backend instance {} registered for {:?}", target, spec);
+        }
+        for target in previous.iter().filter(|t| !targets.contains(t)) {
+            info!("Backend instance {} deregistered for {:?}", target, spec);
+        }
+
+        self.targets.insert(spec.clone(), Arc::new(TargetSet {
+            targets,
+            next: AtomicUsize::new(0),
+        }));
+    }
+}
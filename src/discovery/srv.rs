@@ -0,0 +1,29 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+use super::{ServiceDiscovery, Target, RETRY_INTERVAL};
+use crate::config::data_model::BackendDiscovery;
+
+/// Follows the SRV records of a name, looking them up again each time the
+/// cached answer expires. Only the records with the lowest priority are used;
+/// the others are backups that take over once those are withdrawn.
+pub(super) async fn watch(discovery: Arc<ServiceDiscovery>, spec: BackendDiscovery, name: String) {
+    loop {
+        match discovery.dns_cache.lookup_srv(&name).await {
+            Ok(records) => {
+                let priority = records.iter().map(|record| record.priority).min();
+                let targets = records.into_iter()
+                    .filter(|record| Some(record.priority) == priority)
+                    .map(|record| Target { host: record.target, port: record.port })
+                    .collect();
+                discovery.update(&spec, targets);
+                tokio::time::sleep(discovery.dns_cache.default_ttl().max(Duration::from_secs(1))).await;
+            },
+            Err(e) => {
+                warn!("Failed to resolve SRV records of {}: {:#}", name, e);
+                tokio::time::sleep(RETRY_INTERVAL).await;
+            }
+        }
+    }
+}
@@ -1,29 +1,52 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
-use anyhow::{Result, Context};
+use anyhow::{anyhow, Result, Context};
 use dashmap::DashMap;
+use hickory_resolver::TokioAsyncResolver;
+use hickory_resolver::config::{ResolverConfig, ResolverOpts};
+use serde::Serialize;
 use tokio::net::lookup_host;
 use tracing::{debug, warn, trace};
 
+use crate::config::data_model::DnsResolver;
+use crate::metrics;
+use super::upstream::UpstreamResolvers;
+
 /// A cache entry for resolved DNS records
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 struct CacheEntry {
-    /// The resolved IP address
-    ip: String,
+    /// The resolved IP addresses, in the order the resolver returned them
+    ips: Vec<String>,
+    /// Position of the next address to hand out
+    next: AtomicUsize,
     /// When this entry was created
     created_at: Instant,
-    /// Time-to-live for this entry
+    /// Time-to-live for this entry: the TTL of its records, within the cache's bounds
     ttl: Duration,
+    /// Longest TTL the entry may have, kept for when it is refreshed
+    max_ttl: Duration,
+    /// Whether a background refresh has been started
+    refreshing: AtomicBool,
 }
 
 impl CacheEntry {
+    /// Creates an entry for a static override, which never expires
+    fn permanent(ips: Vec<String>) -> Self {
+        Self::new(ips, Duration::MAX, Duration::MAX)
+    }
+    
     /// Creates a new cache entry
-    fn new(ip: String, ttl: Duration) -> Self {
+    fn new(ips: Vec<String>, ttl: Duration, max_ttl: Duration) -> Self {
         Self {
-            ip,
+            ips,
+            next: AtomicUsize::new(0),
             created_at: Instant::now(),
             ttl,
+            max_ttl,
+            refreshing: AtomicBool::new(false),
         }
     }
     
@@ -32,6 +55,11 @@ impl CacheEntry {
         self.created_at.elapsed() >= self.ttl
     }
 
+    /// Whether the entry expired less than `stale_ttl` ago, or has not expired yet
+    fn within_stale(&self, stale_ttl: Duration) -> bool {
+        self.created_at.elapsed() < self.ttl.saturating_add(stale_ttl)
+    }
+    
     /// Returns time until expiration
     fn time_until_expiry(&self) -> Duration {
         if self.is_expired() {
@@ -40,6 +68,89 @@ impl CacheEntry {
             self.ttl.saturating_sub(self.created_at.elapsed())
         }
     }
+    
+    /// The next address in turn, so requests are spread over all of them
+    fn next_ip(&self) -> String {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.ips.len();
+        self.ips[index].clone()
+    }
+    
+    /// Every address, starting with the next one in turn
+    fn rotated(&self) -> Vec<String> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % self.ips.len();
+        self.ips[start..].iter().chain(&self.ips[..start]).cloned().collect()
+    }
+    
+    /// Claims the entry's background refresh once it is within `window` of
+    /// expiring, so only the first request to see that starts one
+    fn claim_refresh(&self, window: Duration) -> bool {
+        self.time_until_expiry() <= window && !self.refreshing.swap(true, Ordering::AcqRel)
+    }
+}
+
+/// Part of an entry's TTL left when it is refreshed in the background: the last tenth
+const REFRESH_AHEAD_DIVISOR: u32 = 10;
+
+/// How long addresses are cached: the TTL of their records raised to the
+/// minimum and capped at the maximum, or the maximum when the lookup gave no TTL
+fn entry_ttl(record_ttl: Option<Duration>, min_ttl: Duration, max_ttl: Duration) -> Duration {
+    match record_ttl {
+        Some(record_ttl) => record_ttl.max(min_ttl).min(max_ttl),
+        None => max_ttl,
+    }
+}
+
+/// How the cache answered a lookup, or an entry it dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsCacheEvent {
+    /// Answered from the cache, with addresses or a cached failure
+    Hit,
+    /// Answered by asking a resolver
+    Miss,
+    /// An entry removed because it expired or was invalidated
+    Eviction,
+    /// Answered from an expired entry because looking the name up again failed
+    StaleServe,
+}
+
+/// Counts of the cache's events since it was created
+#[derive(Debug, Default)]
+struct EventCounts {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    stale_serves: AtomicU64,
+}
+
+/// Longest a failing name is answered from the cache before it is looked up again
+const MAX_NEGATIVE_TTL: Duration = Duration::from_secs(60);
+
+/// A failed lookup, answered from the cache until `retry_at`
+#[derive(Debug)]
+struct FailureEntry {
+    error: String,
+    /// Consecutive failed lookups
+    count: u32,
+    retry_at: Instant,
+}
+
+/// A host and port offering a service, from an SRV record
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SrvRecord {
+    /// Lower priorities are used first
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    /// Hostname, without the trailing dot
+    pub target: String,
+}
+
+/// Cached SRV records of a name
+#[derive(Debug)]
+struct SrvEntry {
+    records: Vec<SrvRecord>,
+    created_at: Instant,
+    ttl: Duration,
 }
 
 /// A DNS cache that provides async resolution of hostnames with TTL-based expiration
@@ -47,21 +158,119 @@ impl CacheEntry {
 pub struct DnsCache {
     /// The underlying thread-safe cache
     cache: Arc<DashMap<String, CacheEntry>>,
-    /// Default TTL for cache entries
+    /// SRV records by name
+    srv_cache: DashMap<String, SrvEntry>,
+    /// Default TTL for cache entries, and the longest a record's own TTL is honored
     default_ttl: Duration,
-    /// Static overrides (hostname -> IP) that never expire
-    overrides: HashMap<String, String>,
+    /// Shortest time addresses are cached, whatever the TTL of their records
+    min_ttl: Duration,
+    /// Static overrides (hostname -> IPs) that never expire, rotated like cached addresses
+    overrides: HashMap<String, CacheEntry>,
+    /// Names whose last lookup failed, kept for the readiness report until a
+    /// lookup succeeds and answered from here until their next lookup is due
+    failures: DashMap<String, FailureEntry>,
+    /// How long a failed lookup is cached; doubled for every consecutive failure
+    negative_ttl: Duration,
+    /// Resolver using the system's nameservers, which gives the TTLs of records
+    resolver: TokioAsyncResolver,
+    /// DNS servers to use instead of the system resolver
+    upstream: Option<Arc<UpstreamResolvers>>,
+    /// DNS servers of the proxies that have their own, by backend hostname
+    host_upstreams: DashMap<String, Arc<UpstreamResolvers>>,
+    /// Hits, misses, evictions and stale serves, also exported as metrics
+    events: EventCounts,
+    /// How long after expiring an entry is still served when looking it up
+    /// again fails; zero serves nothing stale
+    stale_ttl: Duration,
 }
 
 impl DnsCache {
     /// Creates a new DNS cache with the specified default TTL and static overrides
-    pub fn new(default_ttl_seconds: u64, overrides: HashMap<String, String>) -> Self {
+    pub fn new(default_ttl_seconds: u64, overrides: HashMap<String, Vec<String>>) -> Self {
         let default_ttl = Duration::from_secs(default_ttl_seconds);
+        let overrides = overrides.into_iter()
+            .filter(|(_, ips)| !ips.is_empty())
+            .map(|(hostname, ips)| (hostname, CacheEntry::permanent(ips)))
+            .collect();
         Self {
             cache: Arc::new(DashMap::new()),
+            srv_cache: DashMap::new(),
             default_ttl,
+            min_ttl: Duration::from_secs(5),
             overrides,
+            failures: DashMap::new(),
+            negative_ttl: Duration::from_secs(5),
+            resolver: Self::system_resolver(),
+            upstream: None,
+            host_upstreams: DashMap::new(),
+            events: EventCounts::default(),
+            stale_ttl: Duration::ZERO,
+        }
+    }
+    
+    /// Caches failed lookups for the given time, doubled for every consecutive
+    /// failure up to a minute, so a name that does not resolve is not looked up
+    /// on every request. Zero looks failing names up every time.
+    pub fn with_negative_ttl(mut self, negative_ttl: Duration) -> Self {
+        self.negative_ttl = negative_ttl;
+        self
+    }
+    
+    /// Keeps serving expired addresses and SRV records for up to the given time
+    /// while looking them up again fails, to ride out resolver outages
+    pub fn with_stale_ttl(mut self, stale_ttl: Duration) -> Self {
+        self.stale_ttl = stale_ttl;
+        self
+    }
+    
+    /// Caches addresses for at least the given time, even when their records
+    /// have a shorter TTL
+    pub fn with_min_ttl(mut self, min_ttl: Duration) -> Self {
+        self.min_ttl = min_ttl;
+        self
+    }
+    
+    /// Resolves hostnames with the given DNS servers, in fallback order, instead
+    /// of the system resolver. An empty list keeps the system resolver.
+    pub fn with_resolvers(mut self, resolvers: &[DnsResolver]) -> Result<Self> {
+        if !resolvers.is_empty() {
+            self.upstream = Some(Arc::new(UpstreamResolvers::new(resolvers)?));
+        }
+        Ok(self)
+    }
+    
+    /// Resolves a hostname with the given DNS servers from now on, or with the
+    /// gateway's when the list is empty. Addresses cached from other servers are dropped.
+    pub fn use_resolvers_for(&self, hostname: &str, resolvers: &[DnsResolver]) -> Result<()> {
+        match self.host_upstreams.get(hostname).map(|upstream| upstream.is(resolvers)) {
+            Some(true) => return Ok(()),
+            None if resolvers.is_empty() => return Ok(()),
+            _ => {}
+        }
+        
+        if resolvers.is_empty() {
+            self.host_upstreams.remove(hostname);
+        } else {
+            self.host_upstreams.insert(hostname.to_string(), Arc::new(UpstreamResolvers::new(resolvers)?));
         }
+        debug!("DNS resolvers of {} changed, dropping its cached addresses", hostname);
+        self.evict(hostname);
+        Ok(())
+    }
+    
+    /// The DNS servers to resolve a hostname with, or None for the system resolver
+    fn upstream_for(&self, hostname: &str) -> Option<Arc<UpstreamResolvers>> {
+        self.host_upstreams.get(hostname)
+            .map(|upstream| Arc::clone(upstream.value()))
+            .or_else(|| self.upstream.clone())
+    }
+    
+    /// A resolver using the nameservers of /etc/resolv.conf
+    fn system_resolver() -> TokioAsyncResolver {
+        TokioAsyncResolver::tokio_from_system_conf().unwrap_or_else(|e| {
+            warn!("Failed to read the system DNS configuration, DNS lookups will use public resolvers: {}", e);
+            TokioAsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default())
+        })
     }
     
     /// Gets the default TTL for this cache
@@ -74,122 +283,405 @@ impl DnsCache {
         self.lookup_with_ttl(hostname, self.default_ttl).await
     }
     
-    /// Lookup a hostname with a specific TTL
+    /// Lookup a hostname, caching its addresses for the TTL of their records but
+    /// no longer than `ttl`. A hostname with several addresses gives them out in
+    /// turn, which spreads requests over all of them.
     pub async fn lookup_with_ttl(&self, hostname: &str, ttl: Duration) -> Result<String> {
         // Check if there's a static override for this hostname
-        if let Some(ip) = self.overrides.get(hostname) {
+        if let Some(entry) = self.overrides.get(hostname) {
+            let ip = entry.next_ip();
             debug!("Using static DNS override for {}: {}", hostname, ip);
-            return Ok(ip.clone());
+            return Ok(ip);
         }
         
         // Check if there's a valid cache entry
-        if let Some(entry) = self.cache.get(hostname) {
-            if !entry.is_expired() {
-                trace!("DNS cache hit for {}: {}", hostname, entry.ip);
-                return Ok(entry.ip.clone());
-            }
-            
-            // Entry is expired, remove it
-            trace!("DNS cache entry for {} is expired, removing", hostname);
-            self.cache.remove(hostname);
+        if let Some(ip) = self.cached(hostname, CacheEntry::next_ip) {
+            trace!("DNS cache hit for {}: {}", hostname, ip);
+            return Ok(ip);
         }
         
         // No cache entry or expired, perform a lookup
+        let ips = self.resolve(hostname, ttl).await?;
+        Ok(self.cached(hostname, CacheEntry::next_ip).unwrap_or_else(|| ips[0].clone()))
+    }
+    
+    /// All addresses of a hostname, from cache or by performing a new lookup
+    pub async fn lookup_all_with_ttl(&self, hostname: &str, ttl: Duration) -> Result<Vec<String>> {
+        if let Some(entry) = self.overrides.get(hostname) {
+            return Ok(entry.ips.clone());
+        }
+        
+        if let Some(ips) = self.cached(hostname, |entry| entry.ips.clone()) {
+            return Ok(ips);
+        }
+        
+        self.resolve(hostname, ttl).await
+    }
+    
+    /// Every address of a hostname for a new connection to try in order,
+    /// starting with the next one in turn so connections are spread over them
+    pub async fn lookup_for_connect(&self, hostname: &str) -> Result<Vec<String>> {
+        if let Some(entry) = self.overrides.get(hostname) {
+            return Ok(entry.rotated());
+        }
+        
+        if let Some(ips) = self.cached(hostname, CacheEntry::rotated) {
+            return Ok(ips);
+        }
+        
+        self.resolve(hostname, self.default_ttl).await
+    }
+    
+    /// Reads the unexpired cache entry of a hostname, removing an expired one.
+    /// An entry in the last tenth of its TTL is refreshed in the background.
+    fn cached<T>(&self, hostname: &str, read: impl FnOnce(&CacheEntry) -> T) -> Option<T> {
+        let entry = self.cache.get(hostname)?;
+        if !entry.is_expired() {
+            let value = read(&entry);
+            if entry.claim_refresh(entry.ttl / REFRESH_AHEAD_DIVISOR) {
+                let max_ttl = entry.max_ttl;
+                drop(entry);
+                self.spawn_refresh(hostname, max_ttl);
+            }
+            self.count(DnsCacheEvent::Hit);
+            return Some(value);
+        }
+        drop(entry);
+        
+        // Entry is expired. It is kept while it may still be served stale, and
+        // otherwise removed unless a refresh has just replaced it.
+        trace!("DNS cache entry for {} is expired", hostname);
+        let stale_ttl = self.stale_ttl;
+        if self.cache.remove_if(hostname, |_, entry| !entry.within_stale(stale_ttl)).is_some() {
+            self.count(DnsCacheEvent::Eviction);
+            metrics::set_dns_cache_entries(self.cache.len());
+        }
+        None
+    }
+    
+    /// Removes the cached addresses of a hostname, counting the eviction
+    fn evict(&self, hostname: &str) -> bool {
+        let removed = self.cache.remove(hostname).is_some();
+        if removed {
+            self.count(DnsCacheEvent::Eviction);
+            metrics::set_dns_cache_entries(self.cache.len());
+        }
+        removed
+    }
+    
+    fn count(&self, event: DnsCacheEvent) {
+        self.count_many(event, 1);
+    }
+    
+    fn count_many(&self, event: DnsCacheEvent, count: u64) {
+        let counter = match event {
+            DnsCacheEvent::Hit => &self.events.hits,
+            DnsCacheEvent::Miss => &self.events.misses,
+            DnsCacheEvent::Eviction => &self.events.evictions,
+            DnsCacheEvent::StaleServe => &self.events.stale_serves,
+        };
+        counter.fetch_add(count, Ordering::Relaxed);
+        metrics::track_dns_cache_events(event, count);
+    }
+    
+    /// Looks a hostname up and caches its addresses, or the failure
+    async fn resolve(&self, hostname: &str, ttl: Duration) -> Result<Vec<String>> {
+        if let Some(e) = self.cached_failure(hostname) {
+            trace!("DNS negative cache hit for {}", hostname);
+            if let Some(ips) = self.stale(hostname) {
+                return Ok(ips);
+            }
+            self.count(DnsCacheEvent::Hit);
+            return Err(e);
+        }
+        
         debug!("DNS cache miss for {}, resolving", hostname);
-        let ip = self.perform_lookup(hostname).await?;
+        self.count(DnsCacheEvent::Miss);
+        let (ips, record_ttl) = match self.perform_lookup(hostname).await {
+            Ok(lookup) => {
+                self.failures.remove(hostname);
+                lookup
+            }
+            Err(e) => {
+                self.record_failure(hostname, &e);
+                if let Some(ips) = self.stale(hostname) {
+                    warn!("Failed to resolve {}, serving its expired addresses for up to {:?}: {:#}", hostname, self.stale_ttl, e);
+                    return Ok(ips);
+                }
+                return Err(e);
+            }
+        };
         
         // Cache the result
-        let entry = CacheEntry::new(ip.clone(), ttl);
-        self.cache.insert(hostname.to_string(), entry);
-        debug!("Cached DNS result for {}: {} (TTL: {:?})", hostname, ip, ttl);
+        let cache_ttl = entry_ttl(record_ttl, self.min_ttl, ttl);
+        self.cache.insert(hostname.to_string(), CacheEntry::new(ips.clone(), cache_ttl, ttl));
+        metrics::set_dns_cache_entries(self.cache.len());
+        debug!("Cached DNS result for {}: {:?} (TTL: {:?}, record TTL: {:?})", hostname, ips, cache_ttl, record_ttl);
+        
+        Ok(ips)
+    }
+    
+    /// Every address of an expired entry that is still within the stale window,
+    /// starting with the next one in turn
+    fn stale(&self, hostname: &str) -> Option<Vec<String>> {
+        let entry = self.cache.get(hostname)?;
+        if !entry.is_expired() || !entry.within_stale(self.stale_ttl) {
+            return None;
+        }
+        
+        trace!("Serving stale DNS entry for {}", hostname);
+        self.count(DnsCacheEvent::StaleServe);
+        Some(entry.rotated())
+    }
+    
+    /// Looks a hostname up again in the background and replaces its entry, so
+    /// requests keep being answered from the cache meanwhile. A failed refresh
+    /// keeps the entry until it expires.
+    fn spawn_refresh(&self, hostname: &str, max_ttl: Duration) {
+        let cache = Arc::clone(&self.cache);
+        let resolver = self.resolver.clone();
+        let upstream = self.upstream_for(hostname);
+        let min_ttl = self.min_ttl;
+        let hostname = hostname.to_string();
+        
+        tokio::spawn(async move {
+            match lookup_ips(&resolver, upstream.as_deref(), &hostname).await {
+                Ok((ips, record_ttl)) => {
+                    let ttl = entry_ttl(record_ttl, min_ttl, max_ttl);
+                    debug!("Refreshed DNS entry for {}: {:?} (TTL: {:?})", hostname, ips, ttl);
+                    cache.insert(hostname, CacheEntry::new(ips, ttl, max_ttl));
+                }
+                Err(e) => {
+                    warn!("DNS refresh failed for {}: {:#}", hostname, e);
+                }
+            }
+        });
+    }
+    
+    /// The error of a name's last lookup while its next lookup is not due yet
+    fn cached_failure(&self, name: &str) -> Option<anyhow::Error> {
+        let failure = self.failures.get(name)?;
+        let remaining = failure.retry_at.checked_duration_since(Instant::now())?;
+        Some(anyhow!("{} (cached, next lookup in {}s)", failure.error, remaining.as_secs() + 1))
+    }
+    
+    /// Remembers a failed lookup and backs off: every consecutive failure
+    /// doubles the time until the name is looked up again
+    fn record_failure(&self, name: &str, e: &anyhow::Error) {
+        let mut failure = self.failures.entry(name.to_string()).or_insert_with(|| FailureEntry {
+            error: String::new(),
+            count: 0,
+            retry_at: Instant::now(),
+        });
+        failure.error = format!("{:#}", e);
+        failure.count = failure.count.saturating_add(1);
+        
+        let backoff = self.negative_ttl
+            .saturating_mul(1 << (failure.count - 1).min(16))
+            .min(MAX_NEGATIVE_TTL.max(self.negative_ttl));
+        failure.retry_at = Instant::now() + backoff;
+        debug!("Lookup of {} failed {} times in a row, next lookup in {:?}", name, failure.count, backoff);
+    }
+    
+    /// Performs an actual DNS lookup, returning every A and AAAA address and
+    /// the TTL of the records, when the resolver gives one
+    async fn perform_lookup(&self, hostname: &str) -> Result<(Vec<String>, Option<Duration>)> {
+        lookup_ips(&self.resolver, self.upstream_for(hostname).as_deref(), hostname).await
+    }
+    
+    /// The SRV records of a name such as `_http._tcp.orders.internal`, by
+    /// priority and then heaviest weight first
+    pub async fn lookup_srv(&self, name: &str) -> Result<Vec<SrvRecord>> {
+        if let Some(entry) = self.srv_cache.get(name) {
+            if entry.created_at.elapsed() < entry.ttl {
+                trace!("DNS cache hit for SRV {}", name);
+                self.count(DnsCacheEvent::Hit);
+                return Ok(entry.records.clone());
+            }
+        }
+        
+        if let Some(e) = self.cached_failure(name) {
+            trace!("DNS negative cache hit for SRV {}", name);
+            if let Some(records) = self.stale_srv(name) {
+                return Ok(records);
+            }
+            self.count(DnsCacheEvent::Hit);
+            return Err(e);
+        }
+        
+        debug!("DNS cache miss for SRV {}, resolving", name);
+        self.count(DnsCacheEvent::Miss);
+        let records = match self.perform_srv_lookup(name).await {
+            Ok(records) => {
+                self.failures.remove(name);
+                records
+            }
+            Err(e) => {
+                self.record_failure(name, &e);
+                if let Some(records) = self.stale_srv(name) {
+                    warn!("Failed to resolve SRV {}, serving its expired records for up to {:?}: {:#}", name, self.stale_ttl, e);
+                    return Ok(records);
+                }
+                return Err(e);
+            }
+        };
+        
+        self.srv_cache.insert(name.to_string(), SrvEntry {
+            records: records.clone(),
+            created_at: Instant::now(),
+            ttl: self.default_ttl,
+        });
+        debug!("Cached {} SRV records for {}", records.len(), name);
         
-        Ok(ip)
+        Ok(records)
     }
     
-    /// Performs an actual DNS lookup
-    async fn perform_lookup(&self, hostname: &str) -> Result<String> {
-        // Use tokio's DNS resolver to look up the host
-        let addrs = lookup_host(format!("{}:0", hostname))
-            .await
-            .context(format!("Failed to resolve hostname: {}", hostname))?;
+    /// The records of an expired SRV entry that is still within the stale window
+    fn stale_srv(&self, name: &str) -> Option<Vec<SrvRecord>> {
+        let entry = self.srv_cache.get(name)?;
+        if entry.created_at.elapsed() >= entry.ttl.saturating_add(self.stale_ttl) {
+            return None;
+        }
         
-        // Use the first address returned
-        let addr = addrs
-            .into_iter()
-            .next()
-            .context(format!("No addresses found for hostname: {}", hostname))?;
+        trace!("Serving stale SRV records for {}", name);
+        self.count(DnsCacheEvent::StaleServe);
+        Some(entry.records.clone())
+    }
+    
+    async fn perform_srv_lookup(&self, name: &str) -> Result<Vec<SrvRecord>> {
+        let lookup = match &self.upstream {
+            Some(upstream) => upstream.lookup_srv(name).await?,
+            None => self.resolver.srv_lookup(name)
+                .await
+                .context(format!("Failed to resolve SRV records of {}", name))?,
+        };
         
-        // Extract the IP address as a string
-        let ip = addr.ip().to_string();
+        let mut records: Vec<SrvRecord> = lookup.iter()
+            .map(|srv| SrvRecord {
+                priority: srv.priority(),
+                weight: srv.weight(),
+                port: srv.port(),
+                target: srv.target().to_utf8().trim_end_matches('.').to_string(),
+            })
+            // A target of "." means the service is not offered at this name
+            .filter(|record| !record.target.is_empty())
+            .collect();
+        if records.is_empty() {
+            return Err(anyhow!("No SRV records found for {}", name));
+        }
         
-        Ok(ip)
+        records.sort_by(|a, b| a.priority.cmp(&b.priority).then(b.weight.cmp(&a.weight)));
+        Ok(records)
     }
     
-    /// Prefetch a hostname if it will expire soon
+    /// Prefetch a hostname if it will expire within a minute, for hosts that
+    /// get too few requests to be refreshed by them. `ttl` is the longest its
+    /// addresses are cached.
     pub async fn prefetch(&self, hostname: &str, ttl: u64) -> Option<String> {
         // Skip prefetch for hostnames with static overrides
         if self.overrides.contains_key(hostname) {
             return None;
         }
         
-        // Check if there's a current cache entry
-        if let Some(entry) = self.cache.get(hostname) {
-            // If entry isn't expired but will expire soon, do a prefetch
-            if !entry.is_expired() {
-                let ttl_duration = Duration::from_secs(ttl);
-                let time_to_expiry = entry.time_until_expiry();
-                
-                if time_to_expiry < Duration::from_secs(60) {
-                    debug!("Prefetching DNS entry for {} (expires in {:?})", 
-                        hostname, time_to_expiry);
-                    
-                    // Clone values we need before dropping the entry reference
-                    let current_ip = entry.ip.clone();
-                    drop(entry);
-                    
-                    // Perform lookup in the background
-                    let dns_cache = Arc::clone(&self.cache);
-                    let hostname = hostname.to_string();
-                    
-                    tokio::spawn(async move {
-                        match lookup_host(format!("{}:0", hostname)).await {
-                            Ok(addrs) => {
-                                if let Some(addr) = addrs.into_iter().next() {
-                                    let new_ip = addr.ip().to_string();
-                                    if new_ip != current_ip {
-                                        debug!("DNS prefetch: IP for {} changed from {} to {}", 
-                                            hostname, current_ip, new_ip);
-                                    }
-                                    let entry = CacheEntry::new(new_ip, ttl_duration);
-                                    dns_cache.insert(hostname, entry);
-                                }
-                            }
-                            Err(e) => {
-                                warn!("DNS prefetch failed for {}: {}", hostname, e);
-                            }
-                        }
-                    });
-                    
-                    return Some(current_ip);
-                }
-            }
+        let entry = self.cache.get(hostname)?;
+        if entry.is_expired() || !entry.claim_refresh(Duration::from_secs(60)) {
+            return None;
         }
         
-        None
+        debug!("Prefetching DNS entry for {} (expires in {:?})", hostname, entry.time_until_expiry());
+        let current_ip = entry.ips.first().cloned();
+        drop(entry);
+        
+        self.spawn_refresh(hostname, Duration::from_secs(ttl));
+        current_ip
     }
     
     /// Forces a refresh of the cache entry for a hostname
     pub async fn refresh(&self, hostname: &str) -> Result<String> {
-        // Remove any existing cache entry
-        self.cache.remove(hostname);
+        // Remove any existing cache entry, and a cached failure
+        self.evict(hostname);
+        self.failures.remove(hostname);
         
         // Perform a new lookup
         self.lookup(hostname).await
     }
     
+    /// Drops everything cached for a name: its addresses, SRV records and a
+    /// cached failure, so the next request looks it up again. Returns whether
+    /// anything was cached.
+    pub fn invalidate(&self, name: &str) -> bool {
+        let addresses = self.evict(name);
+        let srv = self.srv_cache.remove(name).is_some();
+        let failure = self.failures.remove(name).is_some();
+        addresses || srv || failure
+    }
+    
+    /// Every cached name, with what it resolved to or why it failed
+    pub fn entries(&self) -> Vec<DnsCacheEntryInfo> {
+        let mut entries: Vec<DnsCacheEntryInfo> = self.overrides.iter()
+            .map(|(hostname, entry)| DnsCacheEntryInfo {
+                name: hostname.clone(),
+                kind: DnsEntryKind::Override,
+                addresses: entry.ips.clone(),
+                srv_records: Vec::new(),
+                ttl_seconds: None,
+                expires_in_seconds: None,
+                refreshing: false,
+                error: None,
+            })
+            .collect();
+        
+        entries.extend(self.cache.iter().map(|entry| DnsCacheEntryInfo {
+            name: entry.key().clone(),
+            kind: DnsEntryKind::Addresses,
+            addresses: entry.ips.clone(),
+            srv_records: Vec::new(),
+            ttl_seconds: Some(entry.ttl.as_secs()),
+            expires_in_seconds: Some(entry.time_until_expiry().as_secs()),
+            refreshing: entry.refreshing.load(Ordering::Relaxed),
+            error: None,
+        }));
+        
+        entries.extend(self.srv_cache.iter().map(|entry| DnsCacheEntryInfo {
+            name: entry.key().clone(),
+            kind: DnsEntryKind::Srv,
+            addresses: Vec::new(),
+            srv_records: entry.records.clone(),
+            ttl_seconds: Some(entry.ttl.as_secs()),
+            expires_in_seconds: Some(entry.ttl.saturating_sub(entry.created_at.elapsed()).as_secs()),
+            refreshing: false,
+            error: None,
+        }));
+        
+        entries.extend(self.failures.iter().map(|failure| DnsCacheEntryInfo {
+            name: failure.key().clone(),
+            kind: DnsEntryKind::Failure,
+            addresses: Vec::new(),
+            srv_records: Vec::new(),
+            ttl_seconds: None,
+            expires_in_seconds: Some(failure.retry_at.saturating_duration_since(Instant::now()).as_secs()),
+            refreshing: false,
+            error: Some(failure.error.clone()),
+        }));
+        
+        entries.sort_by(|a, b| a.name.cmp(&b.name).then(a.kind.cmp(&b.kind)));
+        entries
+    }
+    
+    /// Hostnames whose last lookup failed, with the error
+    pub fn failing_hosts(&self) -> BTreeMap<String, String> {
+        self.failures.iter()
+            .map(|entry| (entry.key().clone(), entry.value().error.clone()))
+            .collect()
+    }
+    
     /// Clears the entire cache
     pub fn clear(&self) {
+        self.count_many(DnsCacheEvent::Eviction, self.cache.len() as u64);
         self.cache.clear();
+        metrics::set_dns_cache_entries(0);
+        self.srv_cache.clear();
+        self.failures.clear();
         debug!("DNS cache cleared");
     }
 
@@ -212,6 +704,10 @@ impl DnsCache {
             active_entries,
             expired_entries,
             override_entries: self.overrides.len(),
+            hits: self.events.hits.load(Ordering::Relaxed),
+            misses: self.events.misses.load(Ordering::Relaxed),
+            evictions: self.events.evictions.load(Ordering::Relaxed),
+            stale_serves: self.events.stale_serves.load(Ordering::Relaxed),
         }
     }
     
@@ -219,16 +715,22 @@ impl DnsCache {
     pub fn purge_expired(&self) -> usize {
         let mut purged = 0;
         
-        for entry in self.cache.iter() {
-            let hostname = entry.key();
-            if entry.is_expired() {
-                self.cache.remove(hostname);
+        // Removed with retain, since removing while iterating would deadlock on the
+        // shard. Entries that may still be served stale are kept.
+        let stale_ttl = self.stale_ttl;
+        self.cache.retain(|_, entry| {
+            let keep = entry.within_stale(stale_ttl);
+            if !keep {
                 purged += 1;
             }
-        }
+            keep
+        });
+        self.srv_cache.retain(|_, entry| entry.created_at.elapsed() < entry.ttl.saturating_add(stale_ttl));
         
         if purged > 0 {
             debug!("Purged {} expired DNS cache entries", purged);
+            self.count_many(DnsCacheEvent::Eviction, purged as u64);
+            metrics::set_dns_cache_entries(self.cache.len());
         }
         
         purged
@@ -236,7 +738,7 @@ impl DnsCache {
 }
 
 /// Statistics about the DNS cache
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct DnsCacheStats {
     /// Total number of entries (active + expired)
     pub total_entries: usize,
@@ -246,4 +748,94 @@ pub struct DnsCacheStats {
     pub expired_entries: usize,
     /// Number of static override entries
     pub override_entries: usize,
+    /// Lookups answered from the cache, including cached failures
+    pub hits: u64,
+    /// Lookups that had to ask a resolver
+    pub misses: u64,
+    /// Entries removed because they expired or were invalidated
+    pub evictions: u64,
+    /// Lookups answered from an expired entry because looking the name up again failed
+    pub stale_serves: u64,
+}
+
+/// What a name in the cache holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DnsEntryKind {
+    /// Addresses from a static override, which never expire
+    Override,
+    /// Resolved A and AAAA addresses
+    Addresses,
+    /// Resolved SRV records
+    Srv,
+    /// A failed lookup, answered from the cache until its next lookup is due
+    Failure,
+}
+
+/// A cached name, as listed by the Admin API
+#[derive(Debug, Clone, Serialize)]
+pub struct DnsCacheEntryInfo {
+    pub name: String,
+    pub kind: DnsEntryKind,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub srv_records: Vec<SrvRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl_seconds: Option<u64>,
+    /// Until the entry expires, or for a failure until the name is looked up again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_in_seconds: Option<u64>,
+    pub refreshing: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Every distinct A and AAAA address of a hostname and the lowest TTL of their
+/// records, from the given DNS servers or the system's nameservers
+async fn lookup_ips(
+    resolver: &TokioAsyncResolver,
+    upstream: Option<&UpstreamResolvers>,
+    hostname: &str,
+) -> Result<(Vec<String>, Option<Duration>)> {
+    let (ips, record_ttl) = match upstream {
+        Some(upstream) => {
+            let lookup = upstream.lookup_ips(hostname).await?;
+            (distinct_ips(lookup.iter()), Some(remaining_ttl(lookup.valid_until())))
+        }
+        None => match resolver.lookup_ip(hostname).await {
+            Ok(lookup) => (distinct_ips(lookup.iter()), Some(remaining_ttl(lookup.valid_until()))),
+            Err(e) => {
+                // Names only the operating system knows, such as from mDNS or
+                // other NSS sources, are looked up without a TTL
+                trace!("DNS lookup of {} failed, trying the system resolver: {}", hostname, e);
+                let addrs = lookup_host(format!("{}:0", hostname))
+                    .await
+                    .context(format!("Failed to resolve hostname: {}", hostname))?;
+                (distinct_ips(addrs.map(|addr| addr.ip())), None)
+            }
+        },
+    };
+    
+    if ips.is_empty() {
+        return Err(anyhow!("No addresses found for hostname: {}", hostname));
+    }
+    Ok((ips, record_ttl))
+}
+
+/// Time left until a lookup's records expire
+fn remaining_ttl(valid_until: Instant) -> Duration {
+    valid_until.saturating_duration_since(Instant::now())
+}
+
+/// The distinct addresses of a lookup, in the order the resolver gave them
+fn distinct_ips(addrs: impl IntoIterator<Item = IpAddr>) -> Vec<String> {
+    let mut ips: Vec<String> = Vec::new();
+    for addr in addrs {
+        let ip = addr.to_string();
+        if !ips.contains(&ip) {
+            ips.push(ip);
+        }
+    }
+    ips
 }
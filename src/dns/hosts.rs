@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+/// Addresses of an override: one address, or several to rotate through
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OverrideAddresses {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Parses the JSON map of `FERRUM_DNS_OVERRIDES`, whose values are an address
+/// or a list of addresses
+pub fn parse_overrides(json: &str) -> Result<HashMap<String, Vec<String>>> {
+    let overrides: HashMap<String, OverrideAddresses> = serde_json::from_str(json)?;
+
+    overrides.into_iter()
+        .map(|(hostname, addresses)| {
+            let addresses = match addresses {
+                OverrideAddresses::One(address) => vec![address],
+                OverrideAddresses::Many(addresses) => addresses,
+            };
+            if addresses.is_empty() {
+                return Err(anyhow!("override of {} has no addresses", hostname));
+            }
+            for address in &addresses {
+                check_address(address).with_context(|| format!("override of {}", hostname))?;
+            }
+            Ok((hostname, addresses))
+        })
+        .collect()
+}
+
+/// Parses a file in the format of /etc/hosts: an address followed by the
+/// hostnames it answers for on each line, with `#` starting a comment. A
+/// hostname on several lines gets every address, in file order.
+pub fn parse_hosts(content: &str) -> Result<HashMap<String, Vec<String>>> {
+    let mut hosts: HashMap<String, Vec<String>> = HashMap::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut fields = line.split_whitespace();
+        let address = match fields.next() {
+            Some(address) => address,
+            None => continue,
+        };
+        check_address(address).with_context(|| format!("line {}", index + 1))?;
+
+        let mut hostnames = fields.peekable();
+        if hostnames.peek().is_none() {
+            return Err(anyhow!("line {}: {} has no hostnames", index + 1, address));
+        }
+        for hostname in hostnames {
+            let addresses = hosts.entry(hostname.to_string()).or_default();
+            if !addresses.iter().any(|existing| existing == address) {
+                addresses.push(address.to_string());
+            }
+        }
+    }
+
+    Ok(hosts)
+}
+
+fn check_address(address: &str) -> Result<()> {
+    address.parse::<IpAddr>()
+        .map(|_| ())
+        .map_err(|_| anyhow!("'{}' is not an IP address", address))
+}
@@ -3,9 +3,15 @@
 // This module provides DNS resolution and caching functionality.
 
 pub mod cache;
+pub mod hosts;
+pub mod resolver;
+pub mod upstream;
 
 pub use cache::DnsCache;
 pub use cache::DnsCacheStats;
+pub use cache::DnsCacheEntryInfo;
+pub use cache::SrvRecord;
+pub use resolver::CachedResolver;
 
 use std::collections::HashSet;
 use std::sync::Arc;
@@ -64,15 +70,16 @@ pub fn start_dns_prefetch_task(
             interval.tick().await;
             
             let proxies_guard = proxies.read().await;
-            let unique_hosts: HashSet<String> = proxies_guard
+            let default_ttl = dns_cache.default_ttl().as_secs();
+            let unique_hosts: HashSet<(String, u64)> = proxies_guard
                 .iter()
-                .map(|p| p.backend_host.clone())
+                .map(|p| (p.backend_host.clone(), p.dns_cache_ttl_seconds.unwrap_or(default_ttl)))
                 .collect();
             drop(proxies_guard); // Release lock before async operations
             
-            for host in unique_hosts {
-                // Try to prefetch if entry is about to expire
-                dns_cache.prefetch(&host, 300).await; // 5-minute TTL
+            for (host, ttl) in unique_hosts {
+                // Try to prefetch if entry is about to expire, capped at the proxy's TTL
+                dns_cache.prefetch(&host, ttl).await;
             }
             
             // Purge expired entries occasionally
@@ -0,0 +1,66 @@
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use hyper::client::connect::dns::Name;
+use hyper::service::Service;
+
+use super::DnsCache;
+use crate::metrics;
+
+/// Delay before the connector starts racing the other address family, as
+/// recommended by RFC 8305
+pub const HAPPY_EYEBALLS_DELAY_MS: u64 = 250;
+
+/// Resolves backend hostnames for the HTTP connector through the DNS cache.
+/// The connector tries the addresses of the first address's family in turn and,
+/// once `HAPPY_EYEBALLS_DELAY_MS` pass without a connection, races the other
+/// family against them.
+#[derive(Clone)]
+pub struct CachedResolver {
+    dns_cache: Arc<DnsCache>,
+}
+
+impl CachedResolver {
+    pub fn new(dns_cache: Arc<DnsCache>) -> Self {
+        Self { dns_cache }
+    }
+}
+
+impl Service<Name> for CachedResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let dns_cache = Arc::clone(&self.dns_cache);
+        Box::pin(async move {
+            let started_at = Instant::now();
+            let ips = dns_cache.lookup_for_connect(name.as_str()).await;
+            metrics::track_upstream_dns_lookup(name.as_str(), started_at.elapsed());
+            let ips = ips.map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{:#}", e)))?;
+
+            // The connector sets the port from the URI
+            let addrs: Vec<SocketAddr> = connect_order(ips.iter().filter_map(|ip| ip.parse().ok()))
+                .into_iter()
+                .map(|ip| SocketAddr::new(ip, 0))
+                .collect();
+            Ok(addrs.into_iter())
+        })
+    }
+}
+
+/// Orders addresses for a new connection: IPv6 first, so dual-stack backends
+/// are reached over IPv6 where it works, keeping the order within each family
+pub fn connect_order(ips: impl IntoIterator<Item = IpAddr>) -> Vec<IpAddr> {
+    let (mut ordered, ipv4): (Vec<IpAddr>, Vec<IpAddr>) = ips.into_iter().partition(IpAddr::is_ipv6);
+    ordered.extend(ipv4);
+    ordered
+}
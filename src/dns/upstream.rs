@@ -0,0 +1,175 @@
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use anyhow::{anyhow, Context, Result};
+use hickory_resolver::TokioAsyncResolver;
+use hickory_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use hickory_resolver::error::{ResolveError, ResolveErrorKind};
+use hickory_resolver::lookup::SrvLookup;
+use hickory_resolver::lookup_ip::LookupIp;
+use tracing::debug;
+
+use crate::config::data_model::{default_dns_resolver_timeout_ms, DnsProtocol, DnsResolver};
+
+/// DNS servers asked in turn: the next one is only asked when the previous one
+/// fails or does not answer within its timeout
+pub struct UpstreamResolvers {
+    servers: Vec<(DnsResolver, TokioAsyncResolver)>,
+}
+
+impl std::fmt::Debug for UpstreamResolvers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.servers.iter().map(|(resolver, _)| resolver)).finish()
+    }
+}
+
+impl UpstreamResolvers {
+    pub fn new(resolvers: &[DnsResolver]) -> Result<Self> {
+        let servers = resolvers.iter()
+            .map(|resolver| Ok((resolver.clone(), build(resolver)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { servers })
+    }
+
+    /// Whether these are the given servers, in the same order
+    pub fn is(&self, resolvers: &[DnsResolver]) -> bool {
+        self.servers.iter().map(|(resolver, _)| resolver).eq(resolvers.iter())
+    }
+
+    /// The A and AAAA addresses of a hostname, valid until the lowest TTL of their records
+    pub async fn lookup_ips(&self, hostname: &str) -> Result<LookupIp> {
+        self.first_answer(hostname, |resolver| async move { resolver.lookup_ip(hostname).await }).await
+    }
+
+    pub async fn lookup_srv(&self, name: &str) -> Result<SrvLookup> {
+        self.first_answer(name, |resolver| async move { resolver.srv_lookup(name).await }).await
+    }
+
+    async fn first_answer<T, F, Fut>(&self, name: &str, query: F) -> Result<T>
+    where
+        F: Fn(TokioAsyncResolver) -> Fut,
+        Fut: Future<Output = Result<T, ResolveError>>,
+    {
+        let mut last_error = anyhow!("No DNS resolvers configured");
+        for (spec, resolver) in &self.servers {
+            match tokio::time::timeout(Duration::from_millis(spec.timeout_ms), query(resolver.clone())).await {
+                Ok(Ok(answer)) => return Ok(answer),
+                // An answer that the name does not exist would be the same from the next server
+                Ok(Err(e)) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                    return Err(anyhow!(e)).context(format!("Failed to resolve {}", name));
+                },
+                Ok(Err(e)) => {
+                    debug!("DNS resolver {} failed to resolve {}: {}", spec.address, name, e);
+                    last_error = anyhow!("{}: {}", spec.address, e);
+                },
+                Err(_) => {
+                    debug!("DNS resolver {} did not answer for {} within {}ms", spec.address, name, spec.timeout_ms);
+                    last_error = anyhow!("{} did not answer within {}ms", spec.address, spec.timeout_ms);
+                },
+            }
+        }
+        Err(last_error).context(format!("Failed to resolve {}", name))
+    }
+}
+
+/// Parses a resolver as written in FERRUM_DNS_RESOLVERS: an address such as
+/// `10.0.0.2`, or a URL such as `udp://10.0.0.2:53`, `tls://1.1.1.1#cloudflare-dns.com`
+/// or `https://8.8.8.8?timeout_ms=500#dns.google`, whose fragment is the
+/// name on the server's certificate
+pub fn parse_resolver(spec: &str) -> Result<DnsResolver> {
+    let spec = spec.trim();
+    let url = if spec.contains("://") {
+        url::Url::parse(spec)
+    } else {
+        url::Url::parse(&format!("udp://{}", spec))
+    }.with_context(|| format!("Invalid DNS resolver '{}'", spec))?;
+
+    let protocol = match url.scheme() {
+        "udp" => DnsProtocol::Udp,
+        "tcp" => DnsProtocol::Tcp,
+        "tls" => DnsProtocol::Tls,
+        "https" => DnsProtocol::Https,
+        other => return Err(anyhow!("Invalid DNS resolver '{}': unknown protocol '{}', expected udp, tcp, tls or https", spec, other)),
+    };
+    let host = url.host_str()
+        .ok_or_else(|| anyhow!("Invalid DNS resolver '{}': missing address", spec))?;
+    if !matches!(url.path(), "" | "/" | "/dns-query") {
+        return Err(anyhow!("Invalid DNS resolver '{}': DNS-over-HTTPS is only supported at /dns-query", spec));
+    }
+
+    let mut timeout_ms = default_dns_resolver_timeout_ms();
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "timeout_ms" => timeout_ms = value.parse()
+                .map_err(|_| anyhow!("Invalid DNS resolver '{}': timeout_ms must be a number of milliseconds", spec))?,
+            other => return Err(anyhow!("Invalid DNS resolver '{}': unknown option '{}'", spec, other)),
+        }
+    }
+
+    let resolver = DnsResolver {
+        address: match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        },
+        protocol,
+        tls_name: url.fragment().filter(|name| !name.is_empty()).map(str::to_string),
+        timeout_ms,
+    };
+    check(&resolver)?;
+    Ok(resolver)
+}
+
+/// Checks that a resolver has an IP address, the name to verify its
+/// certificate against when it uses TLS, and a timeout
+pub fn check(resolver: &DnsResolver) -> Result<()> {
+    socket_addr(resolver)?;
+    if matches!(resolver.protocol, DnsProtocol::Tls | DnsProtocol::Https) && resolver.tls_name.is_none() {
+        return Err(anyhow!("DNS resolver {} needs a tls_name to verify its certificate", resolver.address));
+    }
+    if resolver.timeout_ms == 0 {
+        return Err(anyhow!("DNS resolver {} needs a timeout_ms above 0", resolver.address));
+    }
+    Ok(())
+}
+
+fn socket_addr(resolver: &DnsResolver) -> Result<SocketAddr> {
+    if let Ok(addr) = resolver.address.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    let ip = resolver.address.trim_start_matches('[').trim_end_matches(']')
+        .parse::<IpAddr>()
+        .map_err(|_| anyhow!("DNS resolver address '{}' is not an IP address with an optional port", resolver.address))?;
+    let port = match resolver.protocol {
+        DnsProtocol::Udp | DnsProtocol::Tcp => 53,
+        DnsProtocol::Tls => 853,
+        DnsProtocol::Https => 443,
+    };
+    Ok(SocketAddr::new(ip, port))
+}
+
+fn build(resolver: &DnsResolver) -> Result<TokioAsyncResolver> {
+    check(resolver)?;
+    let addr = socket_addr(resolver)?;
+
+    let mut config = ResolverConfig::new();
+    let protocol = match resolver.protocol {
+        DnsProtocol::Udp => Protocol::Udp,
+        DnsProtocol::Tcp => Protocol::Tcp,
+        DnsProtocol::Tls => Protocol::Tls,
+        DnsProtocol::Https => Protocol::Https,
+    };
+    let mut name_server = NameServerConfig::new(addr, protocol);
+    name_server.tls_dns_name = resolver.tls_name.clone();
+    config.add_name_server(name_server);
+    // Truncated UDP answers are asked again over TCP
+    if resolver.protocol == DnsProtocol::Udp {
+        config.add_name_server(NameServerConfig::new(addr, Protocol::Tcp));
+    }
+
+    let mut opts = ResolverOpts::default();
+    opts.timeout = Duration::from_millis(resolver.timeout_ms);
+    opts.attempts = 1;
+    // Answers are cached by the DnsCache
+    opts.cache_size = 0;
+    Ok(TokioAsyncResolver::tokio(config, opts))
+}
@@ -0,0 +1,217 @@
+//! Embedded gateway for Ferrum Gateway
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use anyhow::{Result, Context, anyhow};
+use serde_json::Value;
+use tokio::sync::{RwLock, broadcast};
+use tracing::info;
+
+use crate::config::data_model::{Configuration, ConfigurationDelta, Consumer, PluginConfig, Proxy};
+use crate::config::env_config::EnvConfig;
+use crate::dns::DnsCache;
+use crate::modes::file::validate_configuration;
+use crate::plugins::{Plugin, PluginRegistry};
+use crate::proxy::{ProxyListeners, ProxyServer};
+use crate::proxy::update_manager::{RouterUpdate, UpdateManager};
+
+/// Entry point of the embedded gateway.
+///
+/// ```no_run
+/// # async fn example(proxy: ferrumgw::Proxy) -> anyhow::Result<()> {
+/// let gateway = ferrumgw::Gateway::builder()
+///     .http_port(0)
+///     .proxy(proxy)
+///     .start()
+///     .await?;
+/// println!("Listening on {}", gateway.http_addr().unwrap());
+/// gateway.shutdown();
+/// # Ok(())
+/// # }
+/// ```
+pub struct Gateway;
+
+impl Gateway {
+    pub fn builder() -> GatewayBuilder {
+        GatewayBuilder::new()
+    }
+}
+
+/// Configures an embedded gateway. Listener, TLS and limit settings come from
+/// an `EnvConfig`, which defaults to `EnvConfig::default()` (HTTP on port 8000).
+pub struct GatewayBuilder {
+    env_config: EnvConfig,
+    config: Configuration,
+    registry: PluginRegistry,
+}
+
+impl GatewayBuilder {
+    pub fn new() -> Self {
+        Self {
+            env_config: EnvConfig::default(),
+            config: Configuration::default(),
+            registry: PluginRegistry::new(),
+        }
+    }
+
+    /// Replaces all gateway settings, for instance with `EnvConfig::from_env()`
+    pub fn env_config(mut self, env_config: EnvConfig) -> Self {
+        self.env_config = env_config;
+        self
+    }
+
+    /// Port of the plain HTTP listener; 0 picks a free port, see `GatewayHandle::http_addr`
+    pub fn http_port(mut self, port: u16) -> Self {
+        self.env_config.proxy_http_port = Some(port);
+        self
+    }
+
+    /// Addresses the proxy listeners bind to, all IPv4 interfaces by default;
+    /// `vec![Ipv4Addr::LOCALHOST.into()]` accepts local clients only
+    pub fn bind_addresses(mut self, addresses: Vec<IpAddr>) -> Self {
+        self.env_config.proxy_http_bind_addresses = addresses.clone();
+        self.env_config.proxy_https_bind_addresses = addresses.clone();
+        self.env_config.proxy_http3_bind_addresses = addresses;
+        self
+    }
+
+    /// Disables the plain HTTP listener
+    pub fn without_http(mut self) -> Self {
+        self.env_config.proxy_http_port = None;
+        self
+    }
+
+    /// Enables the HTTPS listener with a default certificate and key (PEM files).
+    /// Certificates in the configuration are still selected by SNI.
+    pub fn https(mut self, port: u16, cert_path: impl Into<String>, key_path: impl Into<String>) -> Self {
+        self.env_config.proxy_https_port = Some(port);
+        self.env_config.proxy_tls_cert_path = Some(cert_path.into());
+        self.env_config.proxy_tls_key_path = Some(key_path.into());
+        self
+    }
+
+    /// Replaces the whole configuration
+    pub fn configuration(mut self, config: Configuration) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.config.proxies.push(proxy);
+        self
+    }
+
+    pub fn consumer(mut self, consumer: Consumer) -> Self {
+        self.config.consumers.push(consumer);
+        self
+    }
+
+    pub fn plugin_config(mut self, plugin_config: PluginConfig) -> Self {
+        self.config.plugin_configs.push(plugin_config);
+        self
+    }
+
+    /// Registers a custom plugin. Plugin configs with this `plugin_name` create
+    /// their instances with the factory, which receives the config's JSON settings.
+    pub fn plugin<F>(mut self, name: impl Into<String>, factory: F) -> Self
+    where
+        F: Fn(Value) -> Result<Box<dyn Plugin>> + Send + Sync + 'static,
+    {
+        self.registry.register(name, factory);
+        self
+    }
+
+    /// Validates the configuration and starts the listeners. Returns once the
+    /// listeners are bound, so requests can be sent right away.
+    pub async fn start(self) -> Result<GatewayHandle> {
+        validate_configuration(&self.config)?;
+        if self.env_config.proxy_http_port.is_none() && self.env_config.proxy_https_port.is_none() {
+            return Err(anyhow!("No listener is enabled, set an HTTP or HTTPS port"));
+        }
+
+        let dns_cache = Arc::new(DnsCache::new(
+            self.env_config.dns_cache_ttl_seconds,
+            self.env_config.dns_overrides.clone(),
+        )
+        .with_min_ttl(self.env_config.dns_min_ttl)
+        .with_negative_ttl(self.env_config.dns_negative_ttl)
+        .with_stale_ttl(self.env_config.dns_stale_ttl)
+        .with_resolvers(&self.env_config.dns_resolvers)?);
+        let shared_config = Arc::new(RwLock::new(self.config));
+
+        let proxy_server = ProxyServer::with_plugin_registry(
+            self.env_config,
+            Arc::clone(&shared_config),
+            dns_cache,
+            self.registry,
+        )?;
+        let update_manager = proxy_server.get_update_manager();
+        let listeners = proxy_server.listen().await
+            .context("Failed to start the gateway listeners")?;
+
+        info!("Embedded gateway started");
+        Ok(GatewayHandle {
+            shared_config,
+            update_manager,
+            listeners,
+        })
+    }
+}
+
+impl Default for GatewayBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A running embedded gateway. Dropping the handle leaves the gateway running;
+/// call `shutdown` to stop it.
+pub struct GatewayHandle {
+    shared_config: Arc<RwLock<Configuration>>,
+    update_manager: Arc<UpdateManager>,
+    listeners: ProxyListeners,
+}
+
+impl GatewayHandle {
+    /// Address the HTTP listener is bound to
+    pub fn http_addr(&self) -> Option<SocketAddr> {
+        self.listeners.http_addr
+    }
+
+    /// Address the HTTPS listener is bound to
+    pub fn https_addr(&self) -> Option<SocketAddr> {
+        self.listeners.https_addr
+    }
+
+    /// The configuration currently served
+    pub async fn configuration(&self) -> Configuration {
+        self.shared_config.read().await.clone()
+    }
+
+    /// Replaces the configuration. Requests arriving after this returns are
+    /// routed with the new configuration; an invalid one is rejected unchanged.
+    pub async fn update_configuration(&self, config: Configuration) -> Result<()> {
+        validate_configuration(&config)?;
+        *self.shared_config.write().await = config;
+        self.update_manager.rebuild_snapshot().await;
+        Ok(())
+    }
+
+    /// Applies a batch of changes, e.g. a proxy and its plugin configs, as one
+    /// update: requests are routed with all of them, or with none when the
+    /// result is invalid.
+    pub async fn apply_changes(&self, changes: &ConfigurationDelta) -> Result<()> {
+        self.update_manager.apply(changes).await
+    }
+
+    /// Events naming the proxies, consumers and plugin configs each update changed
+    pub fn subscribe(&self) -> broadcast::Receiver<RouterUpdate> {
+        self.update_manager.subscribe()
+    }
+
+    /// Stops accepting connections. Requests already in progress are completed.
+    pub fn shutdown(self) {
+        self.listeners.shutdown();
+        info!("Embedded gateway stopped");
+    }
+}
@@ -0,0 +1,69 @@
+//! On-disk copy of the last configuration a Data Plane received
+
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result, anyhow};
+use prost::Message;
+
+use super::delta::SyncedSnapshot;
+use super::proto::ConfigSnapshot;
+
+#[derive(Debug, Clone)]
+pub struct SnapshotCache {
+    path: PathBuf,
+}
+
+impl SnapshotCache {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The cached snapshot, or None when nothing was cached yet. A file that
+    /// cannot be decoded or fails its checksum is an error.
+    pub async fn load(&self) -> Result<Option<SyncedSnapshot>> {
+        let bytes = match tokio::fs::read(&self.path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).context(format!("Failed to read {}", self.path.display())),
+        };
+
+        let snapshot = ConfigSnapshot::decode(bytes.as_slice())
+            .context(format!("Failed to decode cached configuration {}", self.path.display()))?;
+        SyncedSnapshot::new(snapshot)
+            .map(Some)
+            .map_err(|e| anyhow!("Cached configuration {} is corrupt: {}", self.path.display(), e))
+    }
+
+    /// Replaces the cached snapshot. The file is written next to the cache and
+    /// renamed over it, so a crash never leaves a partial snapshot behind.
+    pub async fn store(&self, snapshot: &ConfigSnapshot) -> Result<()> {
+        let temp_path = self.path.with_extension("tmp");
+        write_private(&temp_path, &snapshot.encode_to_vec()).await
+            .context(format!("Failed to write {}", temp_path.display()))?;
+        tokio::fs::rename(&temp_path, &self.path).await
+            .context(format!("Failed to replace {}", self.path.display()))
+    }
+}
+
+#[cfg(unix)]
+async fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .await?;
+    file.write_all(contents).await?;
+    file.sync_all().await
+}
+
+#[cfg(not(unix))]
+async fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    tokio::fs::write(path, contents).await
+}
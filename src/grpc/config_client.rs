@@ -1,22 +1,28 @@
 use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
+use std::time::Duration;
 use anyhow::{Result, anyhow};
 use tokio::sync::mpsc;
 use tokio_stream::{Stream, wrappers::ReceiverStream};
-use tonic::{Request, Status, transport::Channel};
+use tonic::{Request, Status, codec::CompressionEncoding, transport::{Channel, ClientTlsConfig}};
 use tracing::{info, warn, error, debug};
 
 use crate::config::data_model::Configuration;
 use super::proto::{
     config_service_client::ConfigServiceClient,
-    SubscribeRequest, ConfigUpdate, GetConfigSnapshotRequest,
+    SubscribeRequest, ConfigUpdate, ConfigSnapshot, SnapshotRequest, HealthReport, ProxyMetrics, ProxyHealth,
+    RateLimitReport, RateLimitUpdate,
 };
+use super::transfer::SnapshotAssembler;
 
 /// Client for the Control Plane gRPC service
+#[derive(Clone)]
 pub struct ConfigClient {
     /// The gRPC client for the ConfigService
     client: ConfigServiceClient<Channel>,
     /// Unique ID for this data plane node
     node_id: String,
+    /// Group this node belongs to for staged rollouts
+    group: String,
     /// Authentication token for the Control Plane
     auth_token: String,
     /// Current configuration version
@@ -24,22 +30,50 @@ pub struct ConfigClient {
 }
 
 impl ConfigClient {
-    /// Connect to the Control Plane gRPC service
-    pub async fn connect(cp_url: &str, auth_token: String) -> Result<Self> {
-        // Generate a unique node ID if not provided
-        let node_id = format!("dp-{}", uuid::Uuid::new_v4());
+    /// Connect to the Control Plane gRPC service, over TLS when `tls` is given.
+    /// Messages larger than `max_message_size` bytes are refused. With a
+    /// `keepalive_interval` the connection is pinged, also while idle, and
+    /// closed when a ping goes unanswered for `keepalive_timeout`.
+    pub async fn connect(
+        cp_url: &str,
+        node_id: String,
+        group: String,
+        auth_token: String,
+        tls: Option<ClientTlsConfig>,
+        max_message_size: usize,
+        keepalive_interval: Option<Duration>,
+        keepalive_timeout: Duration,
+    ) -> Result<Self> {
+        let mut endpoint = tonic::transport::Channel::from_shared(cp_url.to_string())?
+            .http2_adaptive_window(true);
+        if let Some(interval) = keepalive_interval {
+            endpoint = endpoint
+                .http2_keep_alive_interval(interval)
+                .keep_alive_timeout(keepalive_timeout)
+                .keep_alive_while_idle(true)
+                .tcp_keepalive(Some(interval));
+        }
+        if let Some(tls) = tls {
+            endpoint = endpoint.tls_config(tls)
+                .map_err(|e| anyhow!("Invalid TLS configuration for the Control Plane connection: {}", e))?;
+        }
         
         // Connect to the gRPC service
-        let channel = tonic::transport::Channel::from_shared(cp_url.to_string())?
+        let channel = endpoint
             .connect()
             .await
             .map_err(|e| anyhow!("Failed to connect to Control Plane at {}: {}", cp_url, e))?;
         
-        let client = ConfigServiceClient::new(channel);
+        // The Control Plane decides whether to compress what it sends
+        let client = ConfigServiceClient::new(channel)
+            .max_decoding_message_size(max_message_size)
+            .max_encoding_message_size(max_message_size)
+            .accept_compressed(CompressionEncoding::Gzip);
         
         Ok(Self {
             client,
             node_id,
+            group,
             auth_token,
             config_version: Arc::new(AtomicU64::new(0)),
         })
@@ -53,6 +87,7 @@ impl ConfigClient {
         let mut request = Request::new(SubscribeRequest {
             node_id: self.node_id.clone(),
             current_version: self.config_version.load(Ordering::SeqCst),
+            group: self.group.clone(),
         });
         
         // Add authentication token as metadata
@@ -91,11 +126,13 @@ impl ConfigClient {
         Ok(ReceiverStream::new(rx))
     }
     
-    /// Get a full configuration snapshot from the Control Plane
-    pub async fn get_config_snapshot(&mut self) -> Result<Configuration> {
+    /// Get a full configuration snapshot from the Control Plane. Deltas
+    /// received afterwards apply on top of it.
+    pub async fn get_config_snapshot(&mut self) -> Result<ConfigSnapshot> {
         // Create the request with authentication
-        let mut request = Request::new(GetConfigSnapshotRequest {
+        let mut request = Request::new(SnapshotRequest {
             node_id: self.node_id.clone(),
+            group: self.group.clone(),
         });
         
         // Add authentication token as metadata
@@ -115,43 +152,106 @@ impl ConfigClient {
         // Update the config version
         self.config_version.store(snapshot.version, Ordering::SeqCst);
         
-        // Convert the proto snapshot to a domain Configuration
-        let config = snapshot.into_configuration()?;
-        
-        Ok(config)
+        Ok(snapshot)
     }
-}
-
-/// Extension trait to convert proto ConfigUpdate to domain Configuration
-impl super::proto::ConfigUpdate {
-    pub fn into_configuration(&self) -> Result<Configuration> {
-        // Convert from proto format to domain model
-        // This is a simplified implementation
-        let mut proxies = Vec::new();
-        let mut consumers = Vec::new();
-        let mut plugin_configs = Vec::new();
+    
+    /// Download a full configuration snapshot in chunks, which unlike
+    /// `get_config_snapshot` works for snapshots larger than a message
+    pub async fn stream_config_snapshot(&mut self) -> Result<ConfigSnapshot> {
+        use futures_util::TryStreamExt;
         
-        // Convert proxies
-        for proxy in &self.proxies {
-            proxies.push(proxy.try_into()?);
-        }
+        // Create the request with authentication
+        let mut request = Request::new(SnapshotRequest {
+            node_id: self.node_id.clone(),
+            group: self.group.clone(),
+        });
         
-        // Convert consumers
-        for consumer in &self.consumers {
-            consumers.push(consumer.try_into()?);
+        // Add authentication token as metadata
+        request.metadata_mut().insert(
+            "authorization", 
+            format!("Bearer {}", self.auth_token).parse().unwrap()
+        );
+        
+        let mut stream = self.client.stream_config_snapshot(request)
+            .await
+            .map_err(|e| anyhow!("Failed to get configuration snapshot: {}", e))?
+            .into_inner();
+        
+        let mut assembler = SnapshotAssembler::new();
+        while let Some(chunk) = stream.try_next().await
+            .map_err(|e| anyhow!("Configuration snapshot stream failed: {}", e))? {
+            if let Some(snapshot) = assembler.push(chunk)? {
+                // Update the config version
+                self.config_version.store(snapshot.version, Ordering::SeqCst);
+                return Ok(snapshot);
+            }
         }
         
-        // Convert plugin configs
-        for plugin_config in &self.plugin_configs {
-            plugin_configs.push(plugin_config.try_into()?);
+        Err(anyhow!("Configuration snapshot stream ended before the last chunk"))
+    }
+    
+    /// Report this node's health, the configuration version it runs and the
+    /// traffic of each proxy over the last `interval_seconds`, with the health
+    /// of the backends of each proxy
+    pub async fn report_health(
+        &mut self,
+        status: &str,
+        config_version: u64,
+        metrics: std::collections::HashMap<String, String>,
+        interval_seconds: f64,
+        proxy_metrics: Vec<ProxyMetrics>,
+        proxy_health: Vec<ProxyHealth>,
+    ) -> Result<()> {
+        let mut request = Request::new(HealthReport {
+            node_id: self.node_id.clone(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            config_version,
+            metrics,
+            status: status.to_string(),
+            interval_seconds,
+            proxy_metrics,
+            proxy_health,
+        });
+        
+        // Add authentication token as metadata
+        request.metadata_mut().insert(
+            "authorization", 
+            format!("Bearer {}", self.auth_token).parse().unwrap()
+        );
+        
+        let ack = self.client.report_health(request)
+            .await
+            .map_err(|e| anyhow!("Failed to report health: {}", e))?
+            .into_inner();
+        if !ack.success {
+            debug!("Control Plane did not record the health report: {}", ack.message);
         }
+        Ok(())
+    }
+    
+    /// Open the stream exchanging rate limiting counters with the Control
+    /// Plane. Each report sent is answered with the other nodes' counters.
+    pub async fn sync_rate_limits(
+        &mut self,
+        reports: mpsc::Receiver<RateLimitReport>,
+    ) -> Result<tonic::codec::Streaming<RateLimitUpdate>> {
+        let mut request = Request::new(ReceiverStream::new(reports));
         
-        Ok(Configuration {
-            proxies,
-            consumers,
-            plugin_configs,
-            last_updated_at: chrono::Utc::now(),
-        })
+        // Add authentication token as metadata
+        request.metadata_mut().insert(
+            "authorization", 
+            format!("Bearer {}", self.auth_token).parse().unwrap()
+        );
+        
+        let response = self.client.sync_rate_limits(request)
+            .await
+            .map_err(|e| anyhow!("Failed to synchronize rate limits: {}", e))?;
+        Ok(response.into_inner())
+    }
+    
+    /// ID this node presents to the Control Plane
+    pub fn node_id(&self) -> &str {
+        &self.node_id
     }
 }
 
@@ -163,6 +263,8 @@ impl super::proto::ConfigSnapshot {
         let mut proxies = Vec::new();
         let mut consumers = Vec::new();
         let mut plugin_configs = Vec::new();
+        let mut certificates = Vec::new();
+        let mut snis = Vec::new();
         
         // Convert proxies
         for proxy in &self.proxies {
@@ -179,10 +281,21 @@ impl super::proto::ConfigSnapshot {
             plugin_configs.push(plugin_config.try_into()?);
         }
         
+        // Convert certificates and their SNIs
+        for certificate in &self.certificates {
+            certificates.push(certificate.try_into()?);
+        }
+        
+        for sni in &self.snis {
+            snis.push(sni.try_into()?);
+        }
+        
         Ok(Configuration {
             proxies,
             consumers,
             plugin_configs,
+            certificates,
+            snis,
             last_updated_at: chrono::Utc::now(),
         })
     }
@@ -4,13 +4,15 @@ use chrono::{DateTime, Utc};
 use serde_json::{Value, json};
 
 use crate::config::data_model::{
-    Proxy, Consumer, PluginConfig, 
-    Protocol, AuthMode, Configuration
+    Proxy, Consumer, PluginConfig, Certificate, Sni,
+    Protocol, AuthMode, HeaderCase, Configuration, DEFAULT_WORKSPACE,
 };
 use super::proto::{
     Proxy as ProtoProxy, 
     Consumer as ProtoConsumer, 
     PluginConfig as ProtoPluginConfig,
+    Certificate as ProtoCertificate,
+    Sni as ProtoSni,
     Protocol as ProtoProtocol, 
     AuthMode as ProtoAuthMode,
     ConfigSnapshot as ProtoConfigSnapshot,
@@ -51,9 +53,56 @@ impl TryFrom<&ProtoProxy> for Proxy {
                 .with_timezone(&Utc)
         };
         
+        // Parse error templates JSON
+        let error_templates = if proto.error_templates.is_empty() {
+            std::collections::HashMap::new()
+        } else {
+            serde_json::from_str(&proto.error_templates)
+                .map_err(|e| anyhow!("Failed to parse proxy error templates: {}", e))?
+        };
+        
+        let backend_discovery = if proto.backend_discovery.is_empty() {
+            None
+        } else {
+            Some(serde_json::from_str(&proto.backend_discovery)
+                .map_err(|e| anyhow!("Failed to parse proxy backend discovery: {}", e))?)
+        };
+        
+        let dns_resolvers = if proto.dns_resolvers.is_empty() {
+            Vec::new()
+        } else {
+            serde_json::from_str(&proto.dns_resolvers)
+                .map_err(|e| anyhow!("Failed to parse proxy DNS resolvers: {}", e))?
+        };
+        
+        let log_sampling = if proto.log_sampling.is_empty() {
+            None
+        } else {
+            Some(serde_json::from_str(&proto.log_sampling)
+                .map_err(|e| anyhow!("Failed to parse proxy log sampling: {}", e))?)
+        };
+        
+        let api_versioning = if proto.api_versioning.is_empty() {
+            None
+        } else {
+            Some(serde_json::from_str(&proto.api_versioning)
+                .map_err(|e| anyhow!("Failed to parse proxy API versioning: {}", e))?)
+        };
+        
+        let backend_keepalive = if proto.backend_keepalive.is_empty() {
+            None
+        } else {
+            Some(serde_json::from_str(&proto.backend_keepalive)
+                .map_err(|e| anyhow!("Failed to parse proxy backend keepalive: {}", e))?)
+        };
+        
         let proxy = Proxy {
             id: proto.id.clone(),
             name: if proto.name.is_empty() { None } else { Some(proto.name.clone()) },
+            workspace: workspace_from_proto(&proto.workspace),
+            version: version_from_proto(proto.version),
+            tags: proto.tags.clone(),
+            enabled: !proto.disabled,
             listen_path: proto.listen_path.clone(),
             backend_protocol,
             backend_host: proto.backend_host.clone(),
@@ -61,6 +110,8 @@ impl TryFrom<&ProtoProxy> for Proxy {
             backend_path: if proto.backend_path.is_empty() { None } else { Some(proto.backend_path.clone()) },
             strip_listen_path: proto.strip_listen_path,
             preserve_host_header: proto.preserve_host_header,
+            upstream_host: if proto.upstream_host.is_empty() { None } else { Some(proto.upstream_host.clone()) },
+            egress_proxy: if proto.egress_proxy.is_empty() { None } else { Some(proto.egress_proxy.clone()) },
             backend_connect_timeout_ms: proto.backend_connect_timeout_ms,
             backend_read_timeout_ms: proto.backend_read_timeout_ms,
             backend_write_timeout_ms: proto.backend_write_timeout_ms,
@@ -70,8 +121,16 @@ impl TryFrom<&ProtoProxy> for Proxy {
             backend_tls_server_ca_cert_path: if proto.backend_tls_server_ca_cert_path.is_empty() { None } else { Some(proto.backend_tls_server_ca_cert_path.clone()) },
             dns_override: if proto.dns_override.is_empty() { None } else { Some(proto.dns_override.clone()) },
             dns_cache_ttl_seconds: if proto.dns_cache_ttl_seconds == 0 { None } else { Some(proto.dns_cache_ttl_seconds) },
+            dns_resolvers,
+            backend_discovery,
             auth_mode,
             plugins: Vec::new(), // Will be populated separately
+            error_templates,
+            log_sampling,
+            api_versioning,
+            max_requests_per_second: if proto.max_requests_per_second == 0 { None } else { Some(proto.max_requests_per_second) },
+            backend_keepalive,
+            backend_header_case: HeaderCase::parse(&proto.backend_header_case),
             created_at,
             updated_at,
         };
@@ -108,6 +167,8 @@ impl From<&Proxy> for ProtoProxy {
             backend_path: proxy.backend_path.clone().unwrap_or_default(),
             strip_listen_path: proxy.strip_listen_path,
             preserve_host_header: proxy.preserve_host_header,
+            upstream_host: proxy.upstream_host.clone().unwrap_or_default(),
+            egress_proxy: proxy.egress_proxy.clone().unwrap_or_default(),
             backend_connect_timeout_ms: proxy.backend_connect_timeout_ms,
             backend_read_timeout_ms: proxy.backend_read_timeout_ms,
             backend_write_timeout_ms: proxy.backend_write_timeout_ms,
@@ -121,6 +182,36 @@ impl From<&Proxy> for ProtoProxy {
             plugin_config_ids: proxy.plugins.iter().map(|p| p.plugin_config_id.clone()).collect(),
             created_at: proxy.created_at.to_rfc3339(),
             updated_at: proxy.updated_at.to_rfc3339(),
+            error_templates: if proxy.error_templates.is_empty() {
+                String::new()
+            } else {
+                serde_json::to_string(&proxy.error_templates).unwrap_or_default()
+            },
+            backend_discovery: proxy.backend_discovery.as_ref()
+                .map(|d| serde_json::to_string(d).unwrap_or_default())
+                .unwrap_or_default(),
+            workspace: proxy.workspace.clone(),
+            version: proxy.version,
+            tags: proxy.tags.clone(),
+            disabled: !proxy.enabled,
+            dns_resolvers: if proxy.dns_resolvers.is_empty() {
+                String::new()
+            } else {
+                serde_json::to_string(&proxy.dns_resolvers).unwrap_or_default()
+            },
+            log_sampling: proxy.log_sampling.as_ref()
+                .map(|s| serde_json::to_string(s).unwrap_or_default())
+                .unwrap_or_default(),
+            api_versioning: proxy.api_versioning.as_ref()
+                .map(|v| serde_json::to_string(v).unwrap_or_default())
+                .unwrap_or_default(),
+            max_requests_per_second: proxy.max_requests_per_second.unwrap_or(0),
+            backend_keepalive: proxy.backend_keepalive.as_ref()
+                .map(|k| serde_json::to_string(k).unwrap_or_default())
+                .unwrap_or_default(),
+            backend_header_case: proxy.backend_header_case
+                .map(|c| c.as_str().to_string())
+                .unwrap_or_default(),
         }
     }
 }
@@ -156,6 +247,9 @@ impl TryFrom<&ProtoConsumer> for Consumer {
         let consumer = Consumer {
             id: proto.id.clone(),
             username: proto.username.clone(),
+            workspace: workspace_from_proto(&proto.workspace),
+            version: version_from_proto(proto.version),
+            tags: proto.tags.clone(),
             custom_id: if proto.custom_id.is_empty() { None } else { Some(proto.custom_id.clone()) },
             credentials,
             created_at,
@@ -180,6 +274,9 @@ impl From<&Consumer> for ProtoConsumer {
             credentials: credentials_json,
             created_at: consumer.created_at.to_rfc3339(),
             updated_at: consumer.updated_at.to_rfc3339(),
+            workspace: consumer.workspace.clone(),
+            version: consumer.version,
+            tags: consumer.tags.clone(),
         }
     }
 }
@@ -215,6 +312,9 @@ impl TryFrom<&ProtoPluginConfig> for PluginConfig {
         let plugin_config = PluginConfig {
             id: proto.id.clone(),
             plugin_name: proto.plugin_name.clone(),
+            workspace: workspace_from_proto(&proto.workspace),
+            version: version_from_proto(proto.version),
+            tags: proto.tags.clone(),
             config,
             scope: proto.scope.clone(),
             proxy_id: if proto.proxy_id.is_empty() { None } else { Some(proto.proxy_id.clone()) },
@@ -245,6 +345,86 @@ impl From<&PluginConfig> for ProtoPluginConfig {
             enabled: plugin_config.enabled,
             created_at: plugin_config.created_at.to_rfc3339(),
             updated_at: plugin_config.updated_at.to_rfc3339(),
+            workspace: plugin_config.workspace.clone(),
+            version: plugin_config.version,
+            tags: plugin_config.tags.clone(),
+        }
+    }
+}
+
+/// Control planes predating workspaces send no workspace
+fn workspace_from_proto(workspace: &str) -> String {
+    if workspace.is_empty() { DEFAULT_WORKSPACE.to_string() } else { workspace.to_string() }
+}
+
+/// Control planes predating entity versions send 0
+fn version_from_proto(version: i64) -> i64 {
+    version.max(1)
+}
+
+/// Parses an ISO8601 timestamp, defaulting to now when it is empty
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    if value.is_empty() {
+        Ok(Utc::now())
+    } else {
+        Ok(DateTime::parse_from_rfc3339(value)?.with_timezone(&Utc))
+    }
+}
+
+/// Conversion from protobuf Certificate to domain Certificate
+impl TryFrom<&ProtoCertificate> for Certificate {
+    type Error = anyhow::Error;
+
+    fn try_from(proto: &ProtoCertificate) -> Result<Self, Self::Error> {
+        Ok(Certificate {
+            id: proto.id.clone(),
+            cert: proto.cert.clone(),
+            key: proto.key.clone(),
+            snis: proto.snis.clone(),
+            created_at: parse_timestamp(&proto.created_at)?,
+            updated_at: parse_timestamp(&proto.updated_at)?,
+        })
+    }
+}
+
+/// Conversion from domain Certificate to protobuf Certificate
+impl From<&Certificate> for ProtoCertificate {
+    fn from(certificate: &Certificate) -> Self {
+        ProtoCertificate {
+            id: certificate.id.clone(),
+            cert: certificate.cert.clone(),
+            key: certificate.key.clone(),
+            snis: certificate.snis.clone(),
+            created_at: certificate.created_at.to_rfc3339(),
+            updated_at: certificate.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Conversion from protobuf Sni to domain Sni
+impl TryFrom<&ProtoSni> for Sni {
+    type Error = anyhow::Error;
+
+    fn try_from(proto: &ProtoSni) -> Result<Self, Self::Error> {
+        Ok(Sni {
+            id: proto.id.clone(),
+            name: proto.name.clone(),
+            certificate_id: proto.certificate_id.clone(),
+            created_at: parse_timestamp(&proto.created_at)?,
+            updated_at: parse_timestamp(&proto.updated_at)?,
+        })
+    }
+}
+
+/// Conversion from domain Sni to protobuf Sni
+impl From<&Sni> for ProtoSni {
+    fn from(sni: &Sni) -> Self {
+        ProtoSni {
+            id: sni.id.clone(),
+            name: sni.name.clone(),
+            certificate_id: sni.certificate_id.clone(),
+            created_at: sni.created_at.to_rfc3339(),
+            updated_at: sni.updated_at.to_rfc3339(),
         }
     }
 }
@@ -268,7 +448,20 @@ impl From<&Configuration> for super::proto::ConfigSnapshot {
                 .iter()
                 .map(super::proto::PluginConfig::from)
                 .collect(),
-            version: config.last_updated_at.to_rfc3339(), // Use last_updated_at as version string
+            certificates: config
+                .certificates
+                .iter()
+                .map(super::proto::Certificate::from)
+                .collect(),
+            snis: config
+                .snis
+                .iter()
+                .map(super::proto::Sni::from)
+                .collect(),
+            // Set by the Control Plane when it sends the snapshot
+            version: 0,
+            created_at: config.last_updated_at.to_rfc3339(),
+            checksum: String::new(),
         }
     }
 }
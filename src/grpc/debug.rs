@@ -0,0 +1,182 @@
+//! Debugging of Data Plane nodes from the Control Plane Admin API
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use serde::Serialize;
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::config::data_model::Configuration;
+use crate::proxy::backend_health::{BackendHealth, ProxyHealth};
+use super::proto::HealthReport;
+
+/// Health reports kept per node
+pub const HEALTH_HISTORY: usize = 50;
+
+/// The configuration the Control Plane last sent a node
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveConfig {
+    pub node_id: String,
+    pub group: String,
+    /// Version the node was last sent
+    pub version: u64,
+    /// Version the node said it runs in its last health report, 0 before its first one
+    pub reported_version: u64,
+    /// Checksum of the version the node was last sent
+    pub checksum: String,
+    pub config: Configuration,
+}
+
+/// A health report received from a node
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthRecord {
+    pub node_id: String,
+    pub status: String,
+    pub config_version: u64,
+    /// When the node sent the report
+    pub timestamp: String,
+    pub metrics: BTreeMap<String, String>,
+    /// Backend health of each proxy that sent requests recently on the node
+    pub proxies: Vec<ProxyHealth>,
+}
+
+/// A proxy whose backends are not all up on a node, as of its last report
+#[derive(Debug, Clone, Serialize)]
+pub struct DegradedProxy {
+    pub node_id: String,
+    /// When the node sent the report
+    pub timestamp: String,
+    #[serde(flatten)]
+    pub health: ProxyHealth,
+}
+
+impl From<&HealthReport> for HealthRecord {
+    fn from(report: &HealthReport) -> Self {
+        Self {
+            node_id: report.node_id.clone(),
+            status: report.status.clone(),
+            config_version: report.config_version,
+            timestamp: report.timestamp.clone(),
+            metrics: report.metrics.iter().map(|(name, value)| (name.clone(), value.clone())).collect(),
+            proxies: report.proxy_health.iter()
+                .map(|proxy| ProxyHealth {
+                    proxy_id: proxy.proxy_id.clone(),
+                    status: proxy.status.clone(),
+                    backends: proxy.backends.iter()
+                        .map(|backend| BackendHealth {
+                            address: backend.address.clone(),
+                            up: backend.up,
+                            consecutive_failures: backend.consecutive_failures,
+                            last_error: Some(backend.last_error.clone()).filter(|error| !error.is_empty()),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Requests the gRPC server answers about a connected node; the reply is
+/// `None` when the node is not connected
+#[derive(Debug)]
+pub enum NodeCommand {
+    EffectiveConfig {
+        node_id: String,
+        reply: oneshot::Sender<Option<EffectiveConfig>>,
+    },
+    /// Send the node a full snapshot of the version it holds, answering with the version
+    Resync {
+        node_id: String,
+        reply: oneshot::Sender<Option<u64>>,
+    },
+}
+
+/// Shared by the Admin API and the gRPC server
+#[derive(Debug)]
+pub struct NodeDebugger {
+    commands: mpsc::Sender<NodeCommand>,
+    receiver: Mutex<Option<mpsc::Receiver<NodeCommand>>>,
+    history: Mutex<HashMap<String, VecDeque<HealthRecord>>>,
+    reports: broadcast::Sender<HealthRecord>,
+}
+
+impl Default for NodeDebugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NodeDebugger {
+    pub fn new() -> Self {
+        let (commands, receiver) = mpsc::channel(16);
+        let (reports, _) = broadcast::channel(64);
+        Self {
+            commands,
+            receiver: Mutex::new(Some(receiver)),
+            history: Mutex::new(HashMap::new()),
+            reports,
+        }
+    }
+
+    /// The commands from the Admin API, taken once by the gRPC server
+    pub fn take_commands(&self) -> Option<mpsc::Receiver<NodeCommand>> {
+        self.receiver.lock().unwrap().take()
+    }
+
+    /// Keeps a node's health report, dropping its oldest beyond `HEALTH_HISTORY`
+    pub fn record_report(&self, report: &HealthReport) {
+        let record = HealthRecord::from(report);
+        {
+            let mut history = self.history.lock().unwrap();
+            let reports = history.entry(record.node_id.clone()).or_default();
+            if reports.len() == HEALTH_HISTORY {
+                reports.pop_front();
+            }
+            reports.push_back(record.clone());
+        }
+        // Nobody following the reports is not an error
+        let _ = self.reports.send(record);
+    }
+
+    /// A node's recent health reports, oldest first
+    pub fn health_reports(&self, node_id: &str) -> Vec<HealthRecord> {
+        self.history.lock().unwrap()
+            .get(node_id)
+            .map(|reports| reports.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// The proxies that are degraded or down in the last report of each node,
+    /// by node and proxy ID
+    pub fn degraded_proxies(&self) -> Vec<DegradedProxy> {
+        let history = self.history.lock().unwrap();
+        let mut degraded: Vec<DegradedProxy> = history.values()
+            .filter_map(|reports| reports.back())
+            .flat_map(|record| record.proxies.iter()
+                .filter(|proxy| proxy.status != "healthy")
+                .map(move |proxy| DegradedProxy {
+                    node_id: record.node_id.clone(),
+                    timestamp: record.timestamp.clone(),
+                    health: proxy.clone(),
+                }))
+            .collect();
+        degraded.sort_by(|a, b| (&a.node_id, &a.health.proxy_id).cmp(&(&b.node_id, &b.health.proxy_id)));
+        degraded
+    }
+
+    /// The health reports of all nodes as they arrive
+    pub fn subscribe(&self) -> broadcast::Receiver<HealthRecord> {
+        self.reports.subscribe()
+    }
+
+    pub async fn effective_config(&self, node_id: &str) -> Option<EffectiveConfig> {
+        let (reply, answer) = oneshot::channel();
+        self.commands.send(NodeCommand::EffectiveConfig { node_id: node_id.to_string(), reply }).await.ok()?;
+        answer.await.ok().flatten()
+    }
+
+    pub async fn resync(&self, node_id: &str) -> Option<u64> {
+        let (reply, answer) = oneshot::channel();
+        self.commands.send(NodeCommand::Resync { node_id: node_id.to_string(), reply }).await.ok()?;
+        answer.await.ok().flatten()
+    }
+}
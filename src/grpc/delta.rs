@@ -0,0 +1,238 @@
+//! Incremental configuration updates from the Control Plane to Data Planes
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use prost::Message;
+use sha2::{Digest, Sha256};
+
+use super::proto::{
+    config_update, Certificate, ConfigDelta, ConfigSnapshot, ConfigUpdate, Consumer, PluginConfig, Proxy, Sni,
+    UpdateType,
+};
+
+/// An entity of a snapshot, identified by its ID
+trait Entity: Message + Clone + PartialEq {
+    fn id(&self) -> &str;
+}
+
+macro_rules! impl_entity {
+    ($($entity:ty),*) => {
+        $(impl Entity for $entity {
+            fn id(&self) -> &str {
+                &self.id
+            }
+        })*
+    };
+}
+
+impl_entity!(Proxy, Consumer, PluginConfig, Certificate, Sni);
+
+/// SHA-256 over the entities of a snapshot, each kind ordered by ID. The
+/// version and timestamp are left out, so equal configurations have equal checksums.
+pub fn checksum(snapshot: &ConfigSnapshot) -> String {
+    let mut hasher = Sha256::new();
+    hash_entities(&mut hasher, &snapshot.proxies);
+    hash_entities(&mut hasher, &snapshot.consumers);
+    hash_entities(&mut hasher, &snapshot.plugin_configs);
+    hash_entities(&mut hasher, &snapshot.certificates);
+    hash_entities(&mut hasher, &snapshot.snis);
+    hex::encode(hasher.finalize())
+}
+
+fn hash_entities<T: Entity>(hasher: &mut Sha256, entities: &[T]) {
+    let mut sorted: Vec<&T> = entities.iter().collect();
+    sorted.sort_by(|a, b| a.id().cmp(b.id()));
+
+    hasher.update((sorted.len() as u64).to_be_bytes());
+    for entity in sorted {
+        let encoded = entity.encode_to_vec();
+        hasher.update((encoded.len() as u64).to_be_bytes());
+        hasher.update(&encoded);
+    }
+}
+
+/// The entities added or changed in `current`, and the IDs of those removed
+fn diff<T: Entity>(previous: &[T], current: &[T]) -> (Vec<T>, Vec<String>) {
+    let before: HashMap<&str, &T> = previous.iter().map(|entity| (entity.id(), entity)).collect();
+    let upserts = current.iter()
+        .filter(|entity| before.get(entity.id()) != Some(entity))
+        .cloned()
+        .collect();
+
+    let kept: HashSet<&str> = current.iter().map(Entity::id).collect();
+    let removed = previous.iter()
+        .filter(|entity| !kept.contains(entity.id()))
+        .map(|entity| entity.id().to_string())
+        .collect();
+    (upserts, removed)
+}
+
+fn apply<T: Entity>(entities: &mut Vec<T>, upserts: &[T], removed: &[String]) {
+    for entity in upserts {
+        match entities.iter_mut().find(|existing| existing.id() == entity.id()) {
+            Some(existing) => *existing = entity.clone(),
+            None => entities.push(entity.clone()),
+        }
+    }
+    entities.retain(|entity| !removed.iter().any(|id| id == entity.id()));
+}
+
+/// What changed between two snapshots
+pub fn compute_delta(previous: &ConfigSnapshot, current: &ConfigSnapshot) -> ConfigDelta {
+    let (upsert_proxies, remove_proxy_ids) = diff(&previous.proxies, &current.proxies);
+    let (upsert_consumers, remove_consumer_ids) = diff(&previous.consumers, &current.consumers);
+    let (upsert_plugin_configs, remove_plugin_config_ids) = diff(&previous.plugin_configs, &current.plugin_configs);
+    let (upsert_certificates, remove_certificate_ids) = diff(&previous.certificates, &current.certificates);
+    let (upsert_snis, remove_sni_ids) = diff(&previous.snis, &current.snis);
+
+    ConfigDelta {
+        upsert_proxies,
+        remove_proxy_ids,
+        upsert_consumers,
+        remove_consumer_ids,
+        upsert_plugin_configs,
+        remove_plugin_config_ids,
+        upsert_certificates,
+        remove_certificate_ids,
+        upsert_snis,
+        remove_sni_ids,
+    }
+}
+
+pub fn apply_delta(snapshot: &mut ConfigSnapshot, delta: &ConfigDelta) {
+    apply(&mut snapshot.proxies, &delta.upsert_proxies, &delta.remove_proxy_ids);
+    apply(&mut snapshot.consumers, &delta.upsert_consumers, &delta.remove_consumer_ids);
+    apply(&mut snapshot.plugin_configs, &delta.upsert_plugin_configs, &delta.remove_plugin_config_ids);
+    apply(&mut snapshot.certificates, &delta.upsert_certificates, &delta.remove_certificate_ids);
+    apply(&mut snapshot.snis, &delta.upsert_snis, &delta.remove_sni_ids);
+}
+
+pub fn is_empty(delta: &ConfigDelta) -> bool {
+    delta.upsert_proxies.is_empty() && delta.remove_proxy_ids.is_empty()
+        && delta.upsert_consumers.is_empty() && delta.remove_consumer_ids.is_empty()
+        && delta.upsert_plugin_configs.is_empty() && delta.remove_plugin_config_ids.is_empty()
+        && delta.upsert_certificates.is_empty() && delta.remove_certificate_ids.is_empty()
+        && delta.upsert_snis.is_empty() && delta.remove_sni_ids.is_empty()
+}
+
+/// Sets the version of a snapshot and the checksum of its entities
+pub fn stamp(snapshot: &mut ConfigSnapshot, version: u64) {
+    snapshot.version = version;
+    snapshot.checksum = checksum(snapshot);
+}
+
+/// Stamps a snapshot with its version and checksum and wraps it in a full update
+pub fn full_update(snapshot: &mut ConfigSnapshot, version: u64) -> ConfigUpdate {
+    stamp(snapshot, version);
+    ConfigUpdate {
+        update_type: UpdateType::Full as i32,
+        version,
+        updated_at: snapshot.created_at.clone(),
+        checksum: snapshot.checksum.clone(),
+        base_version: 0,
+        update: Some(config_update::Update::FullSnapshot(snapshot.clone())),
+    }
+}
+
+/// The delta update taking a node from one stamped snapshot to another
+pub fn update_between(previous: &ConfigSnapshot, current: &ConfigSnapshot) -> ConfigUpdate {
+    ConfigUpdate {
+        update_type: UpdateType::Delta as i32,
+        version: current.version,
+        updated_at: current.created_at.clone(),
+        checksum: current.checksum.clone(),
+        base_version: previous.version,
+        update: Some(config_update::Update::Delta(compute_delta(previous, current))),
+    }
+}
+
+/// Stamps `current` with the version after `previous` and returns the delta
+/// update between them, or None when no entity changed
+pub fn delta_update(previous: &ConfigSnapshot, current: &mut ConfigSnapshot) -> Option<ConfigUpdate> {
+    if is_empty(&compute_delta(previous, current)) {
+        return None;
+    }
+
+    stamp(current, previous.version + 1);
+    Some(update_between(previous, current))
+}
+
+/// Why a Data Plane could not apply an update and needs a full snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncError {
+    /// The delta applies on top of another version than the one held
+    VersionGap { held: u64, base: u64 },
+    /// Applying the update did not produce the Control Plane's configuration
+    ChecksumMismatch { expected: String, actual: String },
+    /// The update carried neither a snapshot nor a delta
+    Empty,
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::VersionGap { held, base } => write!(f, "delta is based on version {} but version {} is held", base, held),
+            Self::ChecksumMismatch { expected, actual } => write!(f, "checksum {} does not match {}", actual, expected),
+            Self::Empty => write!(f, "update has no content"),
+        }
+    }
+}
+
+impl std::error::Error for SyncError {}
+
+/// The configuration a Data Plane holds, as the Control Plane sent it
+#[derive(Debug, Clone, Default)]
+pub struct SyncedSnapshot {
+    snapshot: ConfigSnapshot,
+}
+
+impl SyncedSnapshot {
+    /// Starts from a full snapshot, verifying its checksum when it has one
+    pub fn new(snapshot: ConfigSnapshot) -> Result<Self, SyncError> {
+        verify(&snapshot, &snapshot.checksum)?;
+        Ok(Self { snapshot })
+    }
+
+    pub fn version(&self) -> u64 {
+        self.snapshot.version
+    }
+
+    pub fn snapshot(&self) -> &ConfigSnapshot {
+        &self.snapshot
+    }
+
+    /// Applies a full or delta update. When the update cannot be applied the
+    /// held snapshot is left as it was.
+    pub fn apply(&mut self, update: &ConfigUpdate) -> Result<(), SyncError> {
+        let mut snapshot = match &update.update {
+            Some(config_update::Update::FullSnapshot(snapshot)) => snapshot.clone(),
+            Some(config_update::Update::Delta(delta)) => {
+                if update.base_version != self.snapshot.version {
+                    return Err(SyncError::VersionGap { held: self.snapshot.version, base: update.base_version });
+                }
+                let mut snapshot = self.snapshot.clone();
+                apply_delta(&mut snapshot, delta);
+                snapshot
+            },
+            None => return Err(SyncError::Empty),
+        };
+        verify(&snapshot, &update.checksum)?;
+
+        snapshot.version = update.version;
+        snapshot.created_at = update.updated_at.clone();
+        snapshot.checksum = update.checksum.clone();
+        self.snapshot = snapshot;
+        Ok(())
+    }
+}
+
+fn verify(snapshot: &ConfigSnapshot, expected: &str) -> Result<(), SyncError> {
+    if expected.is_empty() {
+        return Ok(());
+    }
+    let actual = checksum(snapshot);
+    if actual != expected {
+        return Err(SyncError::ChecksumMismatch { expected: expected.to_string(), actual });
+    }
+    Ok(())
+}
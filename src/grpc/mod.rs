@@ -1,6 +1,15 @@
 // Add this at the top of the file
 pub mod proto;
 pub mod conversions;
+pub mod delta;
+pub mod security;
+pub mod cache;
+pub mod rollout;
+pub mod transfer;
+pub mod xds;
+pub mod ratelimit;
+pub mod pinning;
+pub mod debug;
 
 use proto::*;
 use tokio::sync::{mpsc, RwLock};
@@ -21,7 +30,7 @@ pub mod config_client;
 // Import the proto types
 use proto::config_service_server::{ConfigService, ConfigServiceServer};
 
-use crate::config::data_model::{Configuration, Proxy, Consumer, PluginConfig};
+use crate::config::data_model::{Configuration, Proxy, Consumer, PluginConfig, Certificate, Sni};
 use crate::config::cache::ConfigCache;
 
 // Control Plane implementation
@@ -170,6 +179,15 @@ impl ConfigService for ConfigServiceImpl {
         Ok(Response::new(snapshot))
     }
     
+    type StreamConfigSnapshotStream = tokio_stream::wrappers::ReceiverStream<Result<SnapshotChunk, Status>>;
+    
+    async fn stream_config_snapshot(
+        &self,
+        _request: Request<SnapshotRequest>,
+    ) -> Result<Response<Self::StreamConfigSnapshotStream>, Status> {
+        Err(Status::unimplemented("Snapshot streaming is served by the Control Plane mode"))
+    }
+    
     async fn report_health(
         &self,
         request: Request<HealthReport>,
@@ -188,6 +206,15 @@ impl ConfigService for ConfigServiceImpl {
         
         Ok(Response::new(ack))
     }
+    
+    type SyncRateLimitsStream = tokio_stream::wrappers::ReceiverStream<Result<RateLimitUpdate, Status>>;
+    
+    async fn sync_rate_limits(
+        &self,
+        _request: Request<tonic::Streaming<RateLimitReport>>,
+    ) -> Result<Response<Self::SyncRateLimitsStream>, Status> {
+        Err(Status::unimplemented("Rate limit synchronization is served by the Control Plane mode"))
+    }
 }
 
 // Data Plane gRPC client
@@ -269,6 +296,7 @@ impl DataPlaneClient {
             config_version: current_version,
             metrics,
             status: status.to_string(),
+            ..Default::default()
         };
         
         self.client.report_health(report).await
@@ -297,6 +325,21 @@ impl DataPlaneClient {
             }
         }
         
+        // Convert certificates before the SNIs that point at them
+        for proto_certificate in snapshot.certificates {
+            match Certificate::try_from(&proto_certificate) {
+                Ok(certificate) => config.certificates.push(certificate),
+                Err(e) => warn!("Failed to convert certificate: {}", e),
+            }
+        }
+        
+        for proto_sni in snapshot.snis {
+            match Sni::try_from(&proto_sni) {
+                Ok(sni) => config.snis.push(sni),
+                Err(e) => warn!("Failed to convert SNI: {}", e),
+            }
+        }
+        
         // Convert proxies and link to plugin configs
         for proto_proxy in snapshot.proxies {
             match Proxy::try_from(&proto_proxy) {
@@ -0,0 +1,60 @@
+//! Configuration versions pinned per Data Plane group
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::config::data_model::Configuration;
+
+/// A version of the configuration history a group is pinned to
+#[derive(Debug, Clone, Serialize)]
+pub struct PinnedVersion {
+    pub group: String,
+    /// ID of the version in the configuration history
+    pub version_id: i64,
+    pub pinned_at: DateTime<Utc>,
+    /// Who pinned it, the `sub` of the Admin JWT
+    pub pinned_by: String,
+    #[serde(skip)]
+    pub config: Arc<Configuration>,
+}
+
+/// The pinned groups, shared by the Admin API and the gRPC server
+#[derive(Debug, Default)]
+pub struct GroupPins {
+    pins: RwLock<BTreeMap<String, Arc<PinnedVersion>>>,
+}
+
+impl GroupPins {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pins a group to a version, replacing the version it was pinned to
+    pub fn pin(&self, group: &str, version_id: i64, config: Configuration, pinned_by: &str) -> Arc<PinnedVersion> {
+        let pinned = Arc::new(PinnedVersion {
+            group: group.to_string(),
+            version_id,
+            pinned_at: Utc::now(),
+            pinned_by: pinned_by.to_string(),
+            config: Arc::new(config),
+        });
+        self.pins.write().unwrap().insert(group.to_string(), Arc::clone(&pinned));
+        pinned
+    }
+
+    /// Lets a group follow the latest configuration again, returning whether it was pinned
+    pub fn unpin(&self, group: &str) -> bool {
+        self.pins.write().unwrap().remove(group).is_some()
+    }
+
+    pub fn get(&self, group: &str) -> Option<Arc<PinnedVersion>> {
+        self.pins.read().unwrap().get(group).cloned()
+    }
+
+    /// All pins, by group
+    pub fn list(&self) -> Vec<Arc<PinnedVersion>> {
+        self.pins.read().unwrap().values().cloned().collect()
+    }
+}
@@ -0,0 +1,510 @@
+//! Envoy xDS v3 messages and the Aggregated Discovery Service
+
+use std::collections::HashMap;
+use prost_types::{Any, Duration};
+
+pub const LISTENER_TYPE_URL: &str = "type.googleapis.com/envoy.config.listener.v3.Listener";
+pub const ROUTE_CONFIGURATION_TYPE_URL: &str = "type.googleapis.com/envoy.config.route.v3.RouteConfiguration";
+pub const CLUSTER_TYPE_URL: &str = "type.googleapis.com/envoy.config.cluster.v3.Cluster";
+pub const CLUSTER_LOAD_ASSIGNMENT_TYPE_URL: &str = "type.googleapis.com/envoy.config.endpoint.v3.ClusterLoadAssignment";
+pub const HTTP_CONNECTION_MANAGER_TYPE_URL: &str =
+    "type.googleapis.com/envoy.extensions.filters.network.http_connection_manager.v3.HttpConnectionManager";
+pub const ROUTER_TYPE_URL: &str = "type.googleapis.com/envoy.extensions.filters.http.router.v3.Router";
+pub const UPSTREAM_TLS_CONTEXT_TYPE_URL: &str =
+    "type.googleapis.com/envoy.extensions.transport_sockets.tls.v3.UpstreamTlsContext";
+pub const HTTP_PROTOCOL_OPTIONS_TYPE_URL: &str = "type.googleapis.com/envoy.extensions.upstreams.http.v3.HttpProtocolOptions";
+
+/// envoy.config.core.v3.Node
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Node {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub cluster: ::prost::alloc::string::String,
+}
+/// google.rpc.Status, as sent by Envoy when it rejects a response
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+    #[prost(string, tag = "2")]
+    pub message: ::prost::alloc::string::String,
+}
+/// envoy.service.discovery.v3.DiscoveryRequest
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiscoveryRequest {
+    /// Version of the last response the client accepted
+    #[prost(string, tag = "1")]
+    pub version_info: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub node: ::core::option::Option<Node>,
+    /// Requested resources, empty for all resources of the type
+    #[prost(string, repeated, tag = "3")]
+    pub resource_names: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, tag = "4")]
+    pub type_url: ::prost::alloc::string::String,
+    /// Nonce of the response this request acknowledges or rejects
+    #[prost(string, tag = "5")]
+    pub response_nonce: ::prost::alloc::string::String,
+    /// Set when the client rejected the response
+    #[prost(message, optional, tag = "6")]
+    pub error_detail: ::core::option::Option<RpcStatus>,
+}
+/// envoy.service.discovery.v3.DiscoveryResponse
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiscoveryResponse {
+    #[prost(string, tag = "1")]
+    pub version_info: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "2")]
+    pub resources: ::prost::alloc::vec::Vec<Any>,
+    #[prost(string, tag = "4")]
+    pub type_url: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub nonce: ::prost::alloc::string::String,
+}
+/// envoy.config.core.v3.SocketAddress
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SocketAddress {
+    #[prost(string, tag = "2")]
+    pub address: ::prost::alloc::string::String,
+    #[prost(uint32, optional, tag = "3")]
+    pub port_value: ::core::option::Option<u32>,
+}
+/// envoy.config.core.v3.Address
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Address {
+    #[prost(message, optional, tag = "1")]
+    pub socket_address: ::core::option::Option<SocketAddress>,
+}
+/// envoy.config.core.v3.TransportSocket
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TransportSocket {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "3")]
+    pub typed_config: ::core::option::Option<Any>,
+}
+/// envoy.extensions.transport_sockets.tls.v3.UpstreamTlsContext
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpstreamTlsContext {
+    #[prost(string, tag = "2")]
+    pub sni: ::prost::alloc::string::String,
+}
+/// envoy.config.core.v3.Http2ProtocolOptions
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Http2ProtocolOptions {}
+/// envoy.extensions.upstreams.http.v3.HttpProtocolOptions.ExplicitHttpConfig
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExplicitHttpConfig {
+    #[prost(message, optional, tag = "2")]
+    pub http2_protocol_options: ::core::option::Option<Http2ProtocolOptions>,
+}
+/// envoy.extensions.upstreams.http.v3.HttpProtocolOptions
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HttpProtocolOptions {
+    #[prost(message, optional, tag = "3")]
+    pub explicit_http_config: ::core::option::Option<ExplicitHttpConfig>,
+}
+/// envoy.config.endpoint.v3.Endpoint
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Endpoint {
+    #[prost(message, optional, tag = "1")]
+    pub address: ::core::option::Option<Address>,
+}
+/// envoy.config.endpoint.v3.LbEndpoint
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LbEndpoint {
+    #[prost(message, optional, tag = "1")]
+    pub endpoint: ::core::option::Option<Endpoint>,
+}
+/// envoy.config.endpoint.v3.LocalityLbEndpoints
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LocalityLbEndpoints {
+    #[prost(message, repeated, tag = "2")]
+    pub lb_endpoints: ::prost::alloc::vec::Vec<LbEndpoint>,
+}
+/// envoy.config.endpoint.v3.ClusterLoadAssignment
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ClusterLoadAssignment {
+    #[prost(string, tag = "1")]
+    pub cluster_name: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "2")]
+    pub endpoints: ::prost::alloc::vec::Vec<LocalityLbEndpoints>,
+}
+/// envoy.config.cluster.v3.Cluster
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Cluster {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(enumeration = "DiscoveryType", optional, tag = "2")]
+    pub r#type: ::core::option::Option<i32>,
+    #[prost(message, optional, tag = "4")]
+    pub connect_timeout: ::core::option::Option<Duration>,
+    #[prost(message, optional, tag = "24")]
+    pub transport_socket: ::core::option::Option<TransportSocket>,
+    #[prost(message, optional, tag = "33")]
+    pub load_assignment: ::core::option::Option<ClusterLoadAssignment>,
+    #[prost(map = "string, message", tag = "36")]
+    pub typed_extension_protocol_options: HashMap<::prost::alloc::string::String, Any>,
+}
+/// envoy.config.cluster.v3.Cluster.DiscoveryType
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum DiscoveryType {
+    Static = 0,
+    StrictDns = 1,
+}
+/// envoy.config.route.v3.RouteMatch
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RouteMatch {
+    #[prost(string, optional, tag = "1")]
+    pub prefix: ::core::option::Option<::prost::alloc::string::String>,
+}
+/// type.matcher.v3.RegexMatcher
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RegexMatcher {
+    #[prost(string, tag = "2")]
+    pub regex: ::prost::alloc::string::String,
+}
+/// type.matcher.v3.RegexMatchAndSubstitute
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RegexMatchAndSubstitute {
+    #[prost(message, optional, tag = "1")]
+    pub pattern: ::core::option::Option<RegexMatcher>,
+    #[prost(string, tag = "2")]
+    pub substitution: ::prost::alloc::string::String,
+}
+/// envoy.config.route.v3.RouteAction
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RouteAction {
+    #[prost(string, optional, tag = "1")]
+    pub cluster: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, tag = "5")]
+    pub prefix_rewrite: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "6")]
+    pub host_rewrite_literal: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(message, optional, tag = "8")]
+    pub timeout: ::core::option::Option<Duration>,
+    #[prost(message, optional, tag = "32")]
+    pub regex_rewrite: ::core::option::Option<RegexMatchAndSubstitute>,
+}
+/// envoy.config.route.v3.Route
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Route {
+    #[prost(message, optional, tag = "1")]
+    pub r#match: ::core::option::Option<RouteMatch>,
+    #[prost(message, optional, tag = "2")]
+    pub route: ::core::option::Option<RouteAction>,
+    #[prost(string, tag = "14")]
+    pub name: ::prost::alloc::string::String,
+}
+/// envoy.config.route.v3.VirtualHost
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VirtualHost {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "2")]
+    pub domains: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(message, repeated, tag = "3")]
+    pub routes: ::prost::alloc::vec::Vec<Route>,
+}
+/// envoy.config.route.v3.RouteConfiguration
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RouteConfiguration {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "2")]
+    pub virtual_hosts: ::prost::alloc::vec::Vec<VirtualHost>,
+}
+/// envoy.config.core.v3.AggregatedConfigSource
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AggregatedConfigSource {}
+/// envoy.config.core.v3.ConfigSource
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConfigSource {
+    #[prost(message, optional, tag = "3")]
+    pub ads: ::core::option::Option<AggregatedConfigSource>,
+    /// envoy.config.core.v3.ApiVersion, 2 is V3
+    #[prost(int32, tag = "6")]
+    pub resource_api_version: i32,
+}
+/// envoy.extensions.filters.network.http_connection_manager.v3.Rds
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Rds {
+    #[prost(message, optional, tag = "1")]
+    pub config_source: ::core::option::Option<ConfigSource>,
+    #[prost(string, tag = "2")]
+    pub route_config_name: ::prost::alloc::string::String,
+}
+/// envoy.extensions.filters.network.http_connection_manager.v3.HttpFilter
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HttpFilter {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "4")]
+    pub typed_config: ::core::option::Option<Any>,
+}
+/// envoy.extensions.filters.network.http_connection_manager.v3.HttpConnectionManager.UpgradeConfig
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpgradeConfig {
+    #[prost(string, tag = "1")]
+    pub upgrade_type: ::prost::alloc::string::String,
+}
+/// envoy.extensions.filters.network.http_connection_manager.v3.HttpConnectionManager
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HttpConnectionManager {
+    #[prost(string, tag = "2")]
+    pub stat_prefix: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "3")]
+    pub rds: ::core::option::Option<Rds>,
+    #[prost(message, repeated, tag = "5")]
+    pub http_filters: ::prost::alloc::vec::Vec<HttpFilter>,
+    #[prost(message, repeated, tag = "23")]
+    pub upgrade_configs: ::prost::alloc::vec::Vec<UpgradeConfig>,
+}
+/// envoy.config.listener.v3.Filter
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Filter {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "4")]
+    pub typed_config: ::core::option::Option<Any>,
+}
+/// envoy.config.listener.v3.FilterChain
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FilterChain {
+    #[prost(message, repeated, tag = "3")]
+    pub filters: ::prost::alloc::vec::Vec<Filter>,
+}
+/// envoy.config.listener.v3.Listener
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Listener {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub address: ::core::option::Option<Address>,
+    #[prost(message, repeated, tag = "3")]
+    pub filter_chains: ::prost::alloc::vec::Vec<FilterChain>,
+}
+/// Generated server implementations.
+pub mod aggregated_discovery_service_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with AggregatedDiscoveryServiceServer.
+    #[async_trait]
+    pub trait AggregatedDiscoveryService: Send + Sync + 'static {
+        /// Server streaming response type for the StreamAggregatedResources method.
+        type StreamAggregatedResourcesStream: futures_core::Stream<
+                Item = std::result::Result<super::DiscoveryResponse, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// All resource types over one stream, in the state-of-the-world variant
+        async fn stream_aggregated_resources(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::DiscoveryRequest>>,
+        ) -> std::result::Result<
+            tonic::Response<Self::StreamAggregatedResourcesStream>,
+            tonic::Status,
+        >;
+    }
+    /// Envoy's Aggregated Discovery Service
+    #[derive(Debug)]
+    pub struct AggregatedDiscoveryServiceServer<T: AggregatedDiscoveryService> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: AggregatedDiscoveryService> AggregatedDiscoveryServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for AggregatedDiscoveryServiceServer<T>
+    where
+        T: AggregatedDiscoveryService,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/envoy.service.discovery.v3.AggregatedDiscoveryService/StreamAggregatedResources" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamAggregatedResourcesSvc<T: AggregatedDiscoveryService>(pub Arc<T>);
+                    impl<
+                        T: AggregatedDiscoveryService,
+                    > tonic::server::StreamingService<super::DiscoveryRequest>
+                    for StreamAggregatedResourcesSvc<T> {
+                        type Response = super::DiscoveryResponse;
+                        type ResponseStream = T::StreamAggregatedResourcesStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<tonic::Streaming<super::DiscoveryRequest>>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).stream_aggregated_resources(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = StreamAggregatedResourcesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        Ok(
+                            http::Response::builder()
+                                .status(200)
+                                .header("grpc-status", "12")
+                                .header("content-type", "application/grpc")
+                                .body(empty_body())
+                                .unwrap(),
+                        )
+                    })
+                }
+            }
+        }
+    }
+    impl<T: AggregatedDiscoveryService> Clone for AggregatedDiscoveryServiceServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    impl<T: AggregatedDiscoveryService> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(Arc::clone(&self.0))
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: AggregatedDiscoveryService> tonic::server::NamedService for AggregatedDiscoveryServiceServer<T> {
+        const NAME: &'static str = "envoy.service.discovery.v3.AggregatedDiscoveryService";
+    }
+}
@@ -8,6 +8,9 @@ pub struct SubscribeRequest {
     /// Current configuration version (0 if none)
     #[prost(uint64, tag = "2")]
     pub current_version: u64,
+    /// Group the node belongs to, used by staged rollouts
+    #[prost(string, tag = "3")]
+    pub group: ::prost::alloc::string::String,
 }
 /// Request to get a full configuration snapshot
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -16,6 +19,31 @@ pub struct SnapshotRequest {
     /// Data Plane node identifier
     #[prost(string, tag = "1")]
     pub node_id: ::prost::alloc::string::String,
+    /// Group the node belongs to, used by staged rollouts
+    #[prost(string, tag = "2")]
+    pub group: ::prost::alloc::string::String,
+}
+/// Part of a configuration snapshot streamed by StreamConfigSnapshot. The
+/// chunks' data, concatenated in order, is the encoded ConfigSnapshot after
+/// compression with the chunk encoding.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SnapshotChunk {
+    /// Version of the snapshot
+    #[prost(uint64, tag = "1")]
+    pub version: u64,
+    /// Position of this chunk, starting at 0
+    #[prost(uint32, tag = "2")]
+    pub index: u32,
+    /// Number of chunks of the snapshot
+    #[prost(uint32, tag = "3")]
+    pub total: u32,
+    /// Compression of the encoded snapshot: "identity" or "zstd"
+    #[prost(string, tag = "4")]
+    pub encoding: ::prost::alloc::string::String,
+    /// This chunk's part of the compressed snapshot
+    #[prost(bytes = "vec", tag = "5")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
 }
 /// Configuration update message sent from CP to DP
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -30,6 +58,12 @@ pub struct ConfigUpdate {
     /// Timestamp of this update (ISO8601 string)
     #[prost(string, tag = "5")]
     pub updated_at: ::prost::alloc::string::String,
+    /// Checksum of the configuration once the update is applied (see ConfigSnapshot.checksum)
+    #[prost(string, tag = "6")]
+    pub checksum: ::prost::alloc::string::String,
+    /// Version a delta applies on top of; 0 for a full snapshot
+    #[prost(uint64, tag = "7")]
+    pub base_version: u64,
     /// Full configuration or delta update based on update_type
     #[prost(oneof = "config_update::Update", tags = "2, 3")]
     pub update: ::core::option::Option<config_update::Update>,
@@ -65,6 +99,16 @@ pub struct ConfigSnapshot {
     /// Timestamp of this snapshot (ISO8601 string)
     #[prost(string, tag = "5")]
     pub created_at: ::prost::alloc::string::String,
+    /// All TLS certificates
+    #[prost(message, repeated, tag = "6")]
+    pub certificates: ::prost::alloc::vec::Vec<Certificate>,
+    /// All SNI to certificate mappings
+    #[prost(message, repeated, tag = "7")]
+    pub snis: ::prost::alloc::vec::Vec<Sni>,
+    /// SHA-256 over the entities ordered by ID, so a Data Plane can verify the
+    /// configuration it built from deltas (empty when not computed)
+    #[prost(string, tag = "8")]
+    pub checksum: ::prost::alloc::string::String,
 }
 /// Delta configuration update
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -90,6 +134,20 @@ pub struct ConfigDelta {
     pub remove_plugin_config_ids: ::prost::alloc::vec::Vec<
         ::prost::alloc::string::String,
     >,
+    /// Added or modified certificates
+    #[prost(message, repeated, tag = "7")]
+    pub upsert_certificates: ::prost::alloc::vec::Vec<Certificate>,
+    /// IDs of removed certificates
+    #[prost(string, repeated, tag = "8")]
+    pub remove_certificate_ids: ::prost::alloc::vec::Vec<
+        ::prost::alloc::string::String,
+    >,
+    /// Added or modified SNIs
+    #[prost(message, repeated, tag = "9")]
+    pub upsert_snis: ::prost::alloc::vec::Vec<Sni>,
+    /// IDs of removed SNIs
+    #[prost(string, repeated, tag = "10")]
+    pub remove_sni_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 /// Proxy configuration
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -161,6 +219,48 @@ pub struct Proxy {
     /// Last update timestamp (ISO8601 string)
     #[prost(string, tag = "22")]
     pub updated_at: ::prost::alloc::string::String,
+    /// Custom error templates (JSON-encoded map of status key to template)
+    #[prost(string, tag = "23")]
+    pub error_templates: ::prost::alloc::string::String,
+    /// Service registry of the backend instances (JSON-encoded, empty for a static backend)
+    #[prost(string, tag = "24")]
+    pub backend_discovery: ::prost::alloc::string::String,
+    /// Workspace that owns the proxy
+    #[prost(string, tag = "25")]
+    pub workspace: ::prost::alloc::string::String,
+    /// Version of the proxy, incremented by every update
+    #[prost(int64, tag = "26")]
+    pub version: i64,
+    /// Labels for grouping and filtering
+    #[prost(string, repeated, tag = "27")]
+    pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Left out of the routes; false for older Control Planes, which had no toggle
+    #[prost(bool, tag = "28")]
+    pub disabled: bool,
+    /// DNS servers for the backend host in fallback order (JSON-encoded, empty for the gateway's)
+    #[prost(string, tag = "29")]
+    pub dns_resolvers: ::prost::alloc::string::String,
+    /// Access log sampling of the proxy (JSON-encoded, empty for the gateway's)
+    #[prost(string, tag = "30")]
+    pub log_sampling: ::prost::alloc::string::String,
+    /// Versions of the API and their backends (JSON-encoded, empty without versioning)
+    #[prost(string, tag = "31")]
+    pub api_versioning: ::prost::alloc::string::String,
+    /// Requests per second the proxy accepts, 0 for no limit
+    #[prost(uint64, tag = "32")]
+    pub max_requests_per_second: u64,
+    /// Keepalive and TCP options of the backend connections (JSON-encoded, empty for the defaults)
+    #[prost(string, tag = "33")]
+    pub backend_keepalive: ::prost::alloc::string::String,
+    /// Casing of backend request header names: title, preserve, or empty for lowercase
+    #[prost(string, tag = "34")]
+    pub backend_header_case: ::prost::alloc::string::String,
+    /// Template of the Host header sent to the backend, empty for none
+    #[prost(string, tag = "35")]
+    pub upstream_host: ::prost::alloc::string::String,
+    /// URL of the egress proxy of backend connections or "direct", empty for the gateway default
+    #[prost(string, tag = "36")]
+    pub egress_proxy: ::prost::alloc::string::String,
 }
 /// Consumer configuration
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -184,6 +284,15 @@ pub struct Consumer {
     /// Last update timestamp (ISO8601 string)
     #[prost(string, tag = "6")]
     pub updated_at: ::prost::alloc::string::String,
+    /// Workspace of the consumer
+    #[prost(string, tag = "7")]
+    pub workspace: ::prost::alloc::string::String,
+    /// Version of the consumer, incremented by every update
+    #[prost(int64, tag = "8")]
+    pub version: i64,
+    /// Labels for grouping and filtering
+    #[prost(string, repeated, tag = "9")]
+    pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 /// Plugin configuration
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -216,6 +325,58 @@ pub struct PluginConfig {
     /// Last update timestamp (ISO8601 string)
     #[prost(string, tag = "9")]
     pub updated_at: ::prost::alloc::string::String,
+    /// Workspace of the plugin configuration
+    #[prost(string, tag = "10")]
+    pub workspace: ::prost::alloc::string::String,
+    /// Version of the plugin configuration, incremented by every update
+    #[prost(int64, tag = "11")]
+    pub version: i64,
+    /// Labels for grouping and filtering
+    #[prost(string, repeated, tag = "12")]
+    pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// TLS certificate served by the proxy listeners
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Certificate {
+    /// Unique identifier
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    /// PEM encoded certificate chain
+    #[prost(string, tag = "2")]
+    pub cert: ::prost::alloc::string::String,
+    /// PEM encoded private key
+    #[prost(string, tag = "3")]
+    pub key: ::prost::alloc::string::String,
+    /// Hostnames the certificate is served for
+    #[prost(string, repeated, tag = "4")]
+    pub snis: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Creation timestamp (ISO8601 string)
+    #[prost(string, tag = "5")]
+    pub created_at: ::prost::alloc::string::String,
+    /// Last update timestamp (ISO8601 string)
+    #[prost(string, tag = "6")]
+    pub updated_at: ::prost::alloc::string::String,
+}
+/// Hostname mapped to a certificate
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Sni {
+    /// Unique identifier
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    /// Hostname, may start with "*." for a wildcard
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    /// ID of the certificate served for this hostname
+    #[prost(string, tag = "3")]
+    pub certificate_id: ::prost::alloc::string::String,
+    /// Creation timestamp (ISO8601 string)
+    #[prost(string, tag = "4")]
+    pub created_at: ::prost::alloc::string::String,
+    /// Last update timestamp (ISO8601 string)
+    #[prost(string, tag = "5")]
+    pub updated_at: ::prost::alloc::string::String,
 }
 /// Health report from Data Plane to Control Plane
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -239,6 +400,66 @@ pub struct HealthReport {
     /// Node status: "healthy", "degraded", "unhealthy"
     #[prost(string, tag = "5")]
     pub status: ::prost::alloc::string::String,
+    /// Seconds of traffic covered by proxy_metrics
+    #[prost(double, tag = "6")]
+    pub interval_seconds: f64,
+    /// Traffic of each proxy since the previous report
+    #[prost(message, repeated, tag = "7")]
+    pub proxy_metrics: ::prost::alloc::vec::Vec<ProxyMetrics>,
+    /// Backend health of each proxy that sent requests recently
+    #[prost(message, repeated, tag = "8")]
+    pub proxy_health: ::prost::alloc::vec::Vec<ProxyHealth>,
+}
+/// Traffic of one proxy on a Data Plane node over a report interval
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProxyMetrics {
+    /// Proxy identifier
+    #[prost(string, tag = "1")]
+    pub proxy_id: ::prost::alloc::string::String,
+    /// Requests completed
+    #[prost(uint64, tag = "2")]
+    pub requests: u64,
+    /// Requests completed per second
+    #[prost(double, tag = "3")]
+    pub requests_per_second: f64,
+    /// Estimated 99th percentile of the request duration in milliseconds
+    #[prost(double, tag = "4")]
+    pub p99_latency_ms: f64,
+    /// Responses by status code
+    #[prost(map = "string, uint64", tag = "5")]
+    pub status_codes: ::std::collections::HashMap<::prost::alloc::string::String, u64>,
+}
+/// Health of the backends of one proxy on a Data Plane node
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProxyHealth {
+    /// Proxy identifier
+    #[prost(string, tag = "1")]
+    pub proxy_id: ::prost::alloc::string::String,
+    /// "healthy", "degraded" (some backends down) or "down" (all backends down)
+    #[prost(string, tag = "2")]
+    pub status: ::prost::alloc::string::String,
+    /// Backends the proxy sent requests to recently
+    #[prost(message, repeated, tag = "3")]
+    pub backends: ::prost::alloc::vec::Vec<BackendHealth>,
+}
+/// Health of one backend, from the outcomes of the requests sent to it
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BackendHealth {
+    /// host:port of the backend
+    #[prost(string, tag = "1")]
+    pub address: ::prost::alloc::string::String,
+    /// False after consecutive failed requests
+    #[prost(bool, tag = "2")]
+    pub up: bool,
+    /// Failed requests since the last one that succeeded
+    #[prost(uint32, tag = "3")]
+    pub consecutive_failures: u32,
+    /// Error class of the last failed request, empty after a success
+    #[prost(string, tag = "4")]
+    pub last_error: ::prost::alloc::string::String,
 }
 /// Acknowledgment of health report
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -251,6 +472,40 @@ pub struct HealthAck {
     #[prost(string, tag = "2")]
     pub message: ::prost::alloc::string::String,
 }
+/// Requests counted for a rate limiting key in one window
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RateLimitCounter {
+    /// Rate limiting key, scoped to the proxy
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    /// Length of the window in seconds
+    #[prost(uint64, tag = "2")]
+    pub window_seconds: u64,
+    /// Start of the window in seconds since the Unix epoch, a multiple of its length
+    #[prost(uint64, tag = "3")]
+    pub window_start: u64,
+    /// Requests counted
+    #[prost(uint64, tag = "4")]
+    pub count: u64,
+}
+/// Requests a Data Plane counted since its previous report
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RateLimitReport {
+    /// Data Plane node identifier
+    #[prost(string, tag = "1")]
+    pub node_id: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "2")]
+    pub counters: ::prost::alloc::vec::Vec<RateLimitCounter>,
+}
+/// Requests all other Data Planes counted in the current windows
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RateLimitUpdate {
+    #[prost(message, repeated, tag = "1")]
+    pub counters: ::prost::alloc::vec::Vec<RateLimitCounter>,
+}
 /// Types of configuration updates
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
@@ -424,6 +679,38 @@ pub mod config_service_client {
                 );
             self.inner.unary(req, path, codec).await
         }
+        /// Get the same snapshot split into chunks, for configurations larger than
+        /// the maximum gRPC message size
+        pub async fn stream_config_snapshot(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SnapshotRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::SnapshotChunk>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/ferrumgw.config.ConfigService/StreamConfigSnapshot",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "ferrumgw.config.ConfigService",
+                        "StreamConfigSnapshot",
+                    ),
+                );
+            self.inner.server_streaming(req, path, codec).await
+        }
         /// Send health status from Data Plane to Control Plane
         pub async fn report_health(
             &mut self,
@@ -449,6 +736,35 @@ pub mod config_service_client {
                 );
             self.inner.unary(req, path, codec).await
         }
+        /// Exchange rate limiting counters: each node reports the requests it counted
+        /// since its last report and receives the counts of all other nodes
+        pub async fn sync_rate_limits(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::RateLimitReport>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::RateLimitUpdate>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/ferrumgw.config.ConfigService/SyncRateLimits",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("ferrumgw.config.ConfigService", "SyncRateLimits"),
+                );
+            self.inner.streaming(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -479,11 +795,41 @@ pub mod config_service_server {
             &self,
             request: tonic::Request<super::SnapshotRequest>,
         ) -> std::result::Result<tonic::Response<super::ConfigSnapshot>, tonic::Status>;
+        /// Server streaming response type for the StreamConfigSnapshot method.
+        type StreamConfigSnapshotStream: futures_core::Stream<
+                Item = std::result::Result<super::SnapshotChunk, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Get the same snapshot split into chunks, for configurations larger than
+        /// the maximum gRPC message size
+        async fn stream_config_snapshot(
+            &self,
+            request: tonic::Request<super::SnapshotRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::StreamConfigSnapshotStream>,
+            tonic::Status,
+        >;
         /// Send health status from Data Plane to Control Plane
         async fn report_health(
             &self,
             request: tonic::Request<super::HealthReport>,
         ) -> std::result::Result<tonic::Response<super::HealthAck>, tonic::Status>;
+        /// Server streaming response type for the SyncRateLimits method.
+        type SyncRateLimitsStream: futures_core::Stream<
+                Item = std::result::Result<super::RateLimitUpdate, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Exchange rate limiting counters: each node reports the requests it counted
+        /// since its last report and receives the counts of all other nodes
+        async fn sync_rate_limits(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::RateLimitReport>>,
+        ) -> std::result::Result<
+            tonic::Response<Self::SyncRateLimitsStream>,
+            tonic::Status,
+        >;
     }
     /// Control Plane / Data Plane configuration service
     #[derive(Debug)]
@@ -658,6 +1004,53 @@ pub mod config_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/ferrumgw.config.ConfigService/StreamConfigSnapshot" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamConfigSnapshotSvc<T: ConfigService>(pub Arc<T>);
+                    impl<
+                        T: ConfigService,
+                    > tonic::server::ServerStreamingService<super::SnapshotRequest>
+                    for StreamConfigSnapshotSvc<T> {
+                        type Response = super::SnapshotChunk;
+                        type ResponseStream = T::StreamConfigSnapshotStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SnapshotRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).stream_config_snapshot(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = StreamConfigSnapshotSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/ferrumgw.config.ConfigService/ReportHealth" => {
                     #[allow(non_camel_case_types)]
                     struct ReportHealthSvc<T: ConfigService>(pub Arc<T>);
@@ -704,6 +1097,53 @@ pub mod config_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/ferrumgw.config.ConfigService/SyncRateLimits" => {
+                    #[allow(non_camel_case_types)]
+                    struct SyncRateLimitsSvc<T: ConfigService>(pub Arc<T>);
+                    impl<
+                        T: ConfigService,
+                    > tonic::server::StreamingService<super::RateLimitReport>
+                    for SyncRateLimitsSvc<T> {
+                        type Response = super::RateLimitUpdate;
+                        type ResponseStream = T::SyncRateLimitsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<tonic::Streaming<super::RateLimitReport>>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).sync_rate_limits(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SyncRateLimitsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
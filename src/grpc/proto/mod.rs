@@ -5,13 +5,17 @@ pub mod ferrumgw {
     }
 }
 
+// Envoy xDS messages served to Envoy sidecars, written by hand
+pub mod envoy;
+
 // Re-export all the important types from the generated code
 pub use self::ferrumgw::config::{
     // Messages
     ConfigSnapshot, ConfigUpdate, ConfigDelta,
-    Proxy, Consumer, PluginConfig,
-    SubscribeRequest, SnapshotRequest,
-    HealthReport, HealthAck,
+    Proxy, Consumer, PluginConfig, Certificate, Sni,
+    SubscribeRequest, SnapshotRequest, SnapshotChunk,
+    HealthReport, HealthAck, ProxyMetrics, ProxyHealth, BackendHealth,
+    RateLimitCounter, RateLimitReport, RateLimitUpdate,
     
     // Enums
     UpdateType, Protocol, AuthMode,
@@ -0,0 +1,72 @@
+//! Synchronization of `cluster` rate limiting counters between Data Planes
+
+use std::collections::HashMap;
+
+use crate::plugins::rate_limiting::cluster::{Window, WindowCount};
+use super::proto::{RateLimitCounter, RateLimitReport, RateLimitUpdate};
+
+/// Requests counted per key and window by each Data Plane
+#[derive(Debug, Default)]
+pub struct RateLimitAggregator {
+    windows: HashMap<Window, HashMap<String, u64>>,
+}
+
+impl RateLimitAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the requests a node counted since its previous report, and
+    /// forgets the windows that have ended
+    pub fn record(&mut self, report: &RateLimitReport, now: u64) {
+        self.windows.retain(|window, _| !window.has_ended(now));
+        for count in counts(&report.counters) {
+            *self.windows.entry(count.window)
+                .or_default()
+                .entry(report.node_id.clone())
+                .or_default() += count.count;
+        }
+    }
+
+    /// What all nodes but the given one counted in the windows still running
+    pub fn others(&self, node_id: &str, now: u64) -> RateLimitUpdate {
+        let counters = self.windows.iter()
+            .filter(|(window, _)| !window.has_ended(now))
+            .map(|(window, nodes)| (window, nodes.iter().filter(|(node, _)| *node != node_id).map(|(_, count)| count).sum::<u64>()))
+            .filter(|(_, count)| *count > 0)
+            .map(|(window, count)| counter(window, count))
+            .collect();
+        RateLimitUpdate { counters }
+    }
+}
+
+/// The report of the requests a node counted since its previous one
+pub fn report(node_id: &str, counts: Vec<WindowCount>) -> RateLimitReport {
+    RateLimitReport {
+        node_id: node_id.to_string(),
+        counters: counts.into_iter().map(|count| counter(&count.window, count.count)).collect(),
+    }
+}
+
+pub fn counts(counters: &[RateLimitCounter]) -> Vec<WindowCount> {
+    counters.iter()
+        .filter(|counter| counter.window_seconds > 0)
+        .map(|counter| WindowCount {
+            window: Window {
+                key: counter.key.clone(),
+                window_seconds: counter.window_seconds,
+                window_start: counter.window_start,
+            },
+            count: counter.count,
+        })
+        .collect()
+}
+
+fn counter(window: &Window, count: u64) -> RateLimitCounter {
+    RateLimitCounter {
+        key: window.key.clone(),
+        window_seconds: window.window_seconds,
+        window_start: window.window_start,
+        count,
+    }
+}
@@ -0,0 +1,162 @@
+//! Staged rollout of configuration versions to Data Plane groups
+
+use std::collections::{BTreeMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::config::env_config::EnvConfig;
+
+/// Status a Data Plane reports when it is serving normally
+pub const HEALTHY: &str = "healthy";
+
+/// How configuration versions are rolled out
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RolloutPolicy {
+    /// Group that receives each version before any other node
+    pub canary_group: Option<String>,
+    /// Percentages of all nodes that hold the new version at each stage after
+    /// the canary one, ascending and ending at 100
+    pub stages: Vec<u32>,
+    /// How long each stage runs before the next one starts
+    pub stage_interval: Duration,
+}
+
+impl RolloutPolicy {
+    pub fn from_env_config(config: &EnvConfig) -> Self {
+        Self {
+            canary_group: config.cp_rollout_canary_group.clone(),
+            stages: config.cp_rollout_stages.clone(),
+            stage_interval: config.cp_rollout_stage_interval,
+        }
+    }
+
+    /// Whether versions are rolled out in stages rather than to all nodes at once
+    pub fn is_staged(&self) -> bool {
+        self.canary_group.is_some() || !self.stages.is_empty()
+    }
+
+    fn stage_count(&self) -> usize {
+        self.canary_group.is_some() as usize + self.stages.len()
+    }
+}
+
+/// What the Control Plane knows about a connected Data Plane node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeStatus {
+    pub group: String,
+    /// Version last sent to the node
+    pub version: u64,
+    /// Version and status of the node's last health report
+    pub reported_version: u64,
+    pub health: String,
+}
+
+impl NodeStatus {
+    pub fn new(group: impl Into<String>, version: u64) -> Self {
+        Self { group: group.into(), version, reported_version: 0, health: HEALTHY.to_string() }
+    }
+}
+
+/// Result of checking a rollout on a timer tick
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RolloutStep {
+    /// The current stage is still running
+    Wait,
+    /// The next stage started
+    Advanced,
+    /// Every stage ran, so the target is now the stable version
+    Completed,
+    /// A node running the target reported a problem
+    Halted(String),
+}
+
+/// A version being rolled out stage by stage
+#[derive(Debug, Clone)]
+pub struct Rollout {
+    policy: RolloutPolicy,
+    target: u64,
+    stage: usize,
+    stage_started_at: Instant,
+    halted: Option<String>,
+}
+
+impl Rollout {
+    pub fn new(policy: RolloutPolicy, target: u64, now: Instant) -> Self {
+        Self { policy, target, stage: 0, stage_started_at: now, halted: None }
+    }
+
+    pub fn target(&self) -> u64 {
+        self.target
+    }
+
+    /// Zero-based stage and the number of stages
+    pub fn stage(&self) -> (usize, usize) {
+        (self.stage, self.policy.stage_count())
+    }
+
+    /// Why the rollout halted, if it did
+    pub fn halted(&self) -> Option<&str> {
+        self.halted.as_deref()
+    }
+
+    /// The nodes that should hold the target at the current stage: those
+    /// already holding it, the canary group, and the first nodes by ID up to
+    /// the stage's percentage. Nothing more is selected once halted.
+    pub fn selected(&self, nodes: &BTreeMap<String, NodeStatus>) -> HashSet<String> {
+        let mut selected: HashSet<String> = nodes.iter()
+            .filter(|(_, node)| node.version == self.target)
+            .map(|(node_id, _)| node_id.clone())
+            .collect();
+        if self.halted.is_some() {
+            return selected;
+        }
+
+        let is_canary = |node: &NodeStatus| self.policy.canary_group.as_deref() == Some(node.group.as_str());
+        let canary_stages = self.policy.canary_group.is_some() as usize;
+        selected.extend(nodes.iter()
+            .filter(|(_, node)| is_canary(node))
+            .map(|(node_id, _)| node_id.clone()));
+
+        if self.stage >= canary_stages {
+            let percentage = self.policy.stages[self.stage - canary_stages] as usize;
+            let wanted = (nodes.len() * percentage + 99) / 100;
+            // Canary nodes count towards the percentage
+            let mut ordered: Vec<&String> = nodes.keys().collect();
+            ordered.sort_by_key(|node_id| !is_canary(&nodes[*node_id]));
+            for node_id in ordered {
+                if selected.len() >= wanted {
+                    break;
+                }
+                selected.insert(node_id.clone());
+            }
+        }
+        selected
+    }
+
+    /// Halts when a node running the target reports a problem, otherwise
+    /// starts the next stage once the current one has run its interval
+    pub fn tick(&mut self, nodes: &BTreeMap<String, NodeStatus>, now: Instant) -> RolloutStep {
+        if self.halted.is_some() {
+            return RolloutStep::Wait;
+        }
+
+        let unhealthy = nodes.iter().find(|(_, node)| {
+            node.version == self.target && node.reported_version == self.target && node.health != HEALTHY
+        });
+        if let Some((node_id, node)) = unhealthy {
+            let reason = format!("node {} reported {} on version {}", node_id, node.health, self.target);
+            self.halted = Some(reason.clone());
+            return RolloutStep::Halted(reason);
+        }
+
+        if now.duration_since(self.stage_started_at) < self.policy.stage_interval {
+            return RolloutStep::Wait;
+        }
+        self.stage += 1;
+        self.stage_started_at = now;
+        if self.stage >= self.policy.stage_count() {
+            RolloutStep::Completed
+        } else {
+            RolloutStep::Advanced
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Authentication of Data Plane nodes by the Control Plane
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use anyhow::{Context, Result};
+use subtle::ConstantTimeEq;
+use tonic::transport::{Certificate, ClientTlsConfig, Identity, ServerTlsConfig};
+
+use crate::config::env_config::EnvConfig;
+use crate::proxy::client_auth::ClientCertificate;
+
+/// Why a Data Plane node was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeAuthError {
+    /// The request carried no bearer token
+    MissingToken,
+    /// The token is neither the node's pre-shared token nor a valid JWT for it
+    InvalidToken(String),
+    /// The node ID is not allowlisted
+    UnknownNode(String),
+    /// The client certificate was issued to another node
+    CertificateMismatch { node_id: String, subject: String },
+}
+
+impl fmt::Display for NodeAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingToken => write!(f, "missing bearer token"),
+            Self::InvalidToken(reason) => write!(f, "invalid token: {}", reason),
+            Self::UnknownNode(node_id) => write!(f, "unknown Data Plane node {}", node_id),
+            Self::CertificateMismatch { node_id, subject } => {
+                write!(f, "client certificate {} was not issued to node {}", subject, node_id)
+            },
+        }
+    }
+}
+
+impl std::error::Error for NodeAuthError {}
+
+impl From<NodeAuthError> for tonic::Status {
+    fn from(error: NodeAuthError) -> Self {
+        match error {
+            NodeAuthError::UnknownNode(_) | NodeAuthError::CertificateMismatch { .. } => {
+                tonic::Status::permission_denied(error.to_string())
+            },
+            _ => tonic::Status::unauthenticated(error.to_string()),
+        }
+    }
+}
+
+/// What a gRPC request presents to authenticate: the `authorization`
+/// metadata value and the client certificate verified during the handshake
+#[derive(Debug, Clone, Default)]
+pub struct RequestCredentials {
+    pub authorization: Option<String>,
+    pub certificate: Option<ClientCertificate>,
+}
+
+impl RequestCredentials {
+    pub fn from_request<T>(request: &tonic::Request<T>) -> Result<Self, tonic::Status> {
+        let authorization = request.metadata().get("authorization")
+            .map(|value| value.to_str()
+                .map(str::to_string)
+                .map_err(|_| tonic::Status::unauthenticated("Invalid authorization header")))
+            .transpose()?;
+
+        // The chain was verified against the client CA during the handshake
+        let certificate = match request.peer_certs().as_deref().and_then(|chain| chain.first()) {
+            Some(certificate) => Some(ClientCertificate::from_der(certificate.get_ref())
+                .map_err(|e| tonic::Status::unauthenticated(e.to_string()))?),
+            None => None,
+        };
+        Ok(Self { authorization, certificate })
+    }
+}
+
+/// Checks the credentials of Data Plane nodes
+#[derive(Debug, Clone, Default)]
+pub struct NodeAuthenticator {
+    jwt_secret: Option<String>,
+    /// Pre-shared token of each node (node ID -> token)
+    node_tokens: HashMap<String, String>,
+    /// Node IDs allowed to connect, including those with a pre-shared token.
+    /// Empty when any node with a valid JWT for itself may connect.
+    allowed_nodes: HashSet<String>,
+}
+
+impl NodeAuthenticator {
+    pub fn new(
+        jwt_secret: Option<String>,
+        node_tokens: HashMap<String, String>,
+        allowed_nodes: impl IntoIterator<Item = String>,
+    ) -> Self {
+        let mut allowed_nodes: HashSet<String> = allowed_nodes.into_iter().collect();
+        allowed_nodes.extend(node_tokens.keys().cloned());
+        Self { jwt_secret, node_tokens, allowed_nodes }
+    }
+
+    pub fn from_env_config(config: &EnvConfig) -> Self {
+        Self::new(
+            config.cp_grpc_jwt_secret.clone(),
+            config.cp_grpc_node_tokens.clone(),
+            config.cp_grpc_allowed_nodes.iter().cloned(),
+        )
+    }
+
+    /// Whether node IDs are checked against an allowlist
+    pub fn restricts_nodes(&self) -> bool {
+        !self.allowed_nodes.is_empty()
+    }
+
+    /// Authenticates a request from `node_id` carrying the `authorization`
+    /// metadata value and the client certificate verified during the handshake
+    pub fn authenticate(
+        &self,
+        node_id: &str,
+        authorization: Option<&str>,
+        certificate: Option<&ClientCertificate>,
+    ) -> Result<(), NodeAuthError> {
+        if self.restricts_nodes() && !self.allowed_nodes.contains(node_id) {
+            return Err(NodeAuthError::UnknownNode(node_id.to_string()));
+        }
+
+        let token = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .filter(|token| !token.is_empty())
+            .ok_or(NodeAuthError::MissingToken)?;
+
+        match self.node_tokens.get(node_id) {
+            Some(expected) => {
+                if !bool::from(token.as_bytes().ct_eq(expected.as_bytes())) {
+                    return Err(NodeAuthError::InvalidToken("token does not match the node's token".to_string()));
+                }
+            },
+            None => {
+                let secret = self.jwt_secret.as_deref()
+                    .ok_or_else(|| NodeAuthError::InvalidToken("no token is configured for the node".to_string()))?;
+                let subject = validate_jwt_token(token, secret).map_err(NodeAuthError::InvalidToken)?;
+                if subject != node_id {
+                    return Err(NodeAuthError::InvalidToken(format!("token was issued to {}", subject)));
+                }
+            },
+        }
+
+        if let Some(certificate) = certificate {
+            let names_node = certificate.common_name() == Some(node_id)
+                || certificate.dns_names.iter().any(|name| name == node_id);
+            if !names_node {
+                return Err(NodeAuthError::CertificateMismatch {
+                    node_id: node_id.to_string(),
+                    subject: certificate.subject.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Authenticates `node_id` with the credentials its request carried
+    pub fn authenticate_credentials(&self, node_id: &str, credentials: &RequestCredentials) -> Result<(), NodeAuthError> {
+        self.authenticate(node_id, credentials.authorization.as_deref(), credentials.certificate.as_ref())
+    }
+}
+
+/// Validates a JWT against the provided secret and returns its subject
+pub fn validate_jwt_token(token: &str, secret: &str) -> Result<String, String> {
+    use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
+    use serde::{Serialize, Deserialize};
+
+    // Define the claims we expect in the JWT token
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Claims {
+        sub: String,          // Subject (the data plane node ID)
+        exp: usize,           // Expiration time (as UTC timestamp)
+        iat: Option<usize>,   // Issued at (as UTC timestamp)
+        #[serde(default)]
+        role: String,         // Optional role claim
+    }
+
+    let decoding_key = DecodingKey::from_secret(secret.as_bytes());
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_required_spec_claims(&["sub", "exp"]);
+
+    match decode::<Claims>(token, &decoding_key, &validation) {
+        Ok(token_data) => {
+            if !token_data.claims.role.is_empty() && token_data.claims.role != "data_plane" {
+                return Err(format!("Invalid role: {}", token_data.claims.role));
+            }
+            Ok(token_data.claims.sub)
+        },
+        Err(err) => Err(format!("Token validation error: {}", err)),
+    }
+}
+
+/// TLS settings of the Control Plane gRPC listener, or None for plaintext.
+/// With a client CA, Data Plane nodes must present a certificate it issued.
+pub fn server_tls_config(config: &EnvConfig) -> Result<Option<ServerTlsConfig>> {
+    let (cert_path, key_path) = match (&config.cp_grpc_tls_cert_path, &config.cp_grpc_tls_key_path) {
+        (Some(cert_path), Some(key_path)) => (cert_path, key_path),
+        _ => return Ok(None),
+    };
+
+    let mut tls = ServerTlsConfig::new().identity(Identity::from_pem(read_pem(cert_path)?, read_pem(key_path)?));
+    if let Some(ca_path) = &config.cp_grpc_tls_client_ca_path {
+        tls = tls.client_ca_root(Certificate::from_pem(read_pem(ca_path)?));
+    }
+    Ok(Some(tls))
+}
+
+/// TLS settings for connecting to the Control Plane, or None for plaintext.
+/// `https://` URLs use TLS even without a CA, verifying the Control Plane
+/// against the system roots.
+pub fn client_tls_config(config: &EnvConfig, cp_url: &str) -> Result<Option<ClientTlsConfig>> {
+    let has_tls_settings = config.dp_grpc_tls_ca_path.is_some() || config.dp_grpc_tls_cert_path.is_some();
+    if !has_tls_settings && !cp_url.starts_with("https://") {
+        return Ok(None);
+    }
+
+    let mut tls = ClientTlsConfig::new();
+    if let Some(ca_path) = &config.dp_grpc_tls_ca_path {
+        tls = tls.ca_certificate(Certificate::from_pem(read_pem(ca_path)?));
+    }
+    if let (Some(cert_path), Some(key_path)) = (&config.dp_grpc_tls_cert_path, &config.dp_grpc_tls_key_path) {
+        tls = tls.identity(Identity::from_pem(read_pem(cert_path)?, read_pem(key_path)?));
+    }
+    Ok(Some(tls))
+}
+
+fn read_pem(path: &str) -> Result<Vec<u8>> {
+    fs::read(path).context(format!("Failed to read PEM file: {}", path))
+}
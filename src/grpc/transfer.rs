@@ -0,0 +1,181 @@
+//! Transfer of large configurations between the Control Plane and Data Planes
+
+use std::fmt;
+use std::time::Duration;
+use anyhow::{Context, Result, anyhow};
+use prost::Message;
+use tonic::codec::CompressionEncoding;
+
+use crate::config::env_config::EnvConfig;
+use super::proto::{ConfigSnapshot, SnapshotChunk};
+
+/// Room left in a message for the chunk fields around its data
+pub const CHUNK_OVERHEAD: usize = 1024;
+
+/// How configuration messages are compressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    None,
+    /// gRPC message compression for all calls
+    #[default]
+    Gzip,
+    /// zstd over streamed snapshots only
+    Zstd,
+}
+
+impl Compression {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "none" => Some(Self::None),
+            "gzip" => Some(Self::Gzip),
+            "zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Compression gRPC applies to the messages the Control Plane sends
+    pub fn grpc_encoding(&self) -> Option<CompressionEncoding> {
+        match self {
+            Self::Gzip => Some(CompressionEncoding::Gzip),
+            _ => None,
+        }
+    }
+
+    /// Encoding of streamed snapshot chunks
+    fn chunk_encoding(&self) -> &'static str {
+        match self {
+            Self::Zstd => "zstd",
+            _ => "identity",
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Gzip => write!(f, "gzip"),
+            Self::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+/// Message size, compression and connection settings of the CP/DP channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferSettings {
+    pub compression: Compression,
+    /// Largest gRPC message sent or accepted, in bytes
+    pub max_message_size: usize,
+    /// Largest data of a streamed snapshot chunk, in bytes
+    pub chunk_size: usize,
+    /// Interval of HTTP/2 keepalive pings, None when disabled
+    pub keepalive_interval: Option<Duration>,
+    pub keepalive_timeout: Duration,
+    /// Updates queued per subscribed node before newer ones are coalesced
+    pub subscriber_buffer: usize,
+}
+
+impl TransferSettings {
+    pub fn from_env_config(config: &EnvConfig) -> Self {
+        Self {
+            compression: config.grpc_compression,
+            max_message_size: config.grpc_max_message_size,
+            chunk_size: config.cp_grpc_snapshot_chunk_size,
+            keepalive_interval: config.grpc_keepalive_interval,
+            keepalive_timeout: config.grpc_keepalive_timeout,
+            subscriber_buffer: config.cp_grpc_subscriber_buffer,
+        }
+    }
+}
+
+impl Default for TransferSettings {
+    fn default() -> Self {
+        Self::from_env_config(&EnvConfig::default())
+    }
+}
+
+/// Encodes a stamped snapshot, compresses it and splits it into chunks of at
+/// most `chunk_size` bytes of data
+pub fn split_snapshot(snapshot: &ConfigSnapshot, compression: Compression, chunk_size: usize) -> Result<Vec<SnapshotChunk>> {
+    let encoded = snapshot.encode_to_vec();
+    let data = match compression {
+        Compression::Zstd => zstd::stream::encode_all(encoded.as_slice(), 0)
+            .context("Failed to compress the configuration snapshot")?,
+        _ => encoded,
+    };
+
+    let parts: Vec<&[u8]> = if data.is_empty() {
+        vec![&[]]
+    } else {
+        data.chunks(chunk_size.max(1)).collect()
+    };
+    let total = parts.len() as u32;
+    Ok(parts.into_iter()
+        .enumerate()
+        .map(|(index, part)| SnapshotChunk {
+            version: snapshot.version,
+            index: index as u32,
+            total,
+            encoding: compression.chunk_encoding().to_string(),
+            data: part.to_vec(),
+        })
+        .collect())
+}
+
+/// Joins the chunks of a streamed snapshot back together
+#[derive(Debug, Default)]
+pub struct SnapshotAssembler {
+    version: u64,
+    total: u32,
+    encoding: String,
+    received: u32,
+    data: Vec<u8>,
+}
+
+impl SnapshotAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the next chunk and returns the snapshot once the last one arrived.
+    /// Chunks must arrive in order and belong to the same snapshot.
+    pub fn push(&mut self, chunk: SnapshotChunk) -> Result<Option<ConfigSnapshot>> {
+        if chunk.index != self.received {
+            return Err(anyhow!("Expected snapshot chunk {} but received chunk {}", self.received, chunk.index));
+        }
+        if self.received == 0 {
+            if chunk.total == 0 {
+                return Err(anyhow!("Snapshot chunk announces no chunks"));
+            }
+            self.version = chunk.version;
+            self.total = chunk.total;
+            self.encoding = chunk.encoding;
+        } else if chunk.version != self.version || chunk.total != self.total || chunk.encoding != self.encoding {
+            return Err(anyhow!("Snapshot chunk {} belongs to another snapshot", chunk.index));
+        }
+
+        self.data.extend_from_slice(&chunk.data);
+        self.received += 1;
+        if self.received < self.total {
+            return Ok(None);
+        }
+
+        let encoded = match self.encoding.as_str() {
+            "" | "identity" => std::mem::take(&mut self.data),
+            "zstd" => zstd::stream::decode_all(self.data.as_slice())
+                .context("Failed to decompress the configuration snapshot")?,
+            other => return Err(anyhow!("Unsupported snapshot encoding {}", other)),
+        };
+        let snapshot = ConfigSnapshot::decode(encoded.as_slice())
+            .context("Failed to decode the configuration snapshot")?;
+        if snapshot.version != self.version {
+            return Err(anyhow!("Snapshot chunks announced version {} but carried version {}", self.version, snapshot.version));
+        }
+        Ok(Some(snapshot))
+    }
+
+    /// Whether every chunk was received
+    pub fn is_complete(&self) -> bool {
+        self.total > 0 && self.received == self.total
+    }
+}
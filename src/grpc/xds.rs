@@ -0,0 +1,370 @@
+//! xDS view of the gateway configuration for Envoy sidecars
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use prost::Message;
+use prost_types::Any;
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{debug, info, warn};
+
+use crate::config::data_model::{BackendProtocol, Configuration, Proxy};
+use crate::proxy::handler;
+use super::proto::envoy::{
+    self, aggregated_discovery_service_server::AggregatedDiscoveryService, Address, AggregatedConfigSource,
+    Cluster, ClusterLoadAssignment, ConfigSource, DiscoveryRequest, DiscoveryResponse, DiscoveryType, Endpoint,
+    ExplicitHttpConfig, Filter, FilterChain, Http2ProtocolOptions, HttpConnectionManager, HttpFilter,
+    HttpProtocolOptions, LbEndpoint, Listener, LocalityLbEndpoints, Rds, RegexMatchAndSubstitute, RegexMatcher,
+    Route, RouteAction, RouteConfiguration, RouteMatch, SocketAddress, TransportSocket, UpgradeConfig,
+    UpstreamTlsContext, VirtualHost,
+};
+use super::security::{NodeAuthenticator, RequestCredentials};
+
+/// Name of the listener Envoy receives traffic on
+pub const LISTENER_NAME: &str = "ferrumgw_http";
+/// Name of the route configuration holding a route per proxy
+pub const ROUTE_CONFIGURATION_NAME: &str = "ferrumgw";
+
+/// envoy.config.core.v3.ApiVersion.V3
+const API_VERSION_V3: i32 = 2;
+
+/// The Envoy resources of one configuration version, by type URL
+#[derive(Debug, Clone, Default)]
+pub struct XdsSnapshot {
+    version: u64,
+    resources: HashMap<&'static str, Vec<(String, Any)>>,
+}
+
+impl XdsSnapshot {
+    /// Translates the enabled proxies of a configuration; Envoy listens on `listener_addr`
+    pub fn build(config: &Configuration, version: u64, listener_addr: SocketAddr) -> Self {
+        let listener = listener(listener_addr);
+        let routes = route_configuration(&config.proxies);
+        let clusters = config.proxies.iter()
+            .filter(|proxy| proxy.enabled)
+            .map(|proxy| (proxy.id.clone(), any(envoy::CLUSTER_TYPE_URL, &cluster(proxy))))
+            .collect();
+
+        let resources = HashMap::from([
+            (envoy::LISTENER_TYPE_URL, vec![(LISTENER_NAME.to_string(), any(envoy::LISTENER_TYPE_URL, &listener))]),
+            (envoy::ROUTE_CONFIGURATION_TYPE_URL, vec![
+                (ROUTE_CONFIGURATION_NAME.to_string(), any(envoy::ROUTE_CONFIGURATION_TYPE_URL, &routes)),
+            ]),
+            (envoy::CLUSTER_TYPE_URL, clusters),
+        ]);
+        Self { version, resources }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// The resources of a type, only the named ones when names are given
+    pub fn resources(&self, type_url: &str, names: &[String]) -> Vec<Any> {
+        self.resources.get(type_url)
+            .map(|resources| resources.iter()
+                .filter(|(name, _)| names.is_empty() || names.contains(name))
+                .map(|(_, resource)| resource.clone())
+                .collect())
+            .unwrap_or_default()
+    }
+}
+
+fn any<M: Message>(type_url: &str, message: &M) -> Any {
+    Any { type_url: type_url.to_string(), value: message.encode_to_vec() }
+}
+
+fn duration_ms(ms: u64) -> prost_types::Duration {
+    prost_types::Duration { seconds: (ms / 1000) as i64, nanos: ((ms % 1000) * 1_000_000) as i32 }
+}
+
+/// A listener on `addr` whose routes come from the route configuration over ADS
+fn listener(addr: SocketAddr) -> Listener {
+    let connection_manager = HttpConnectionManager {
+        stat_prefix: "ferrumgw".to_string(),
+        rds: Some(Rds {
+            config_source: Some(ConfigSource { ads: Some(AggregatedConfigSource {}), resource_api_version: API_VERSION_V3 }),
+            route_config_name: ROUTE_CONFIGURATION_NAME.to_string(),
+        }),
+        http_filters: vec![HttpFilter {
+            name: "envoy.filters.http.router".to_string(),
+            typed_config: Some(Any { type_url: envoy::ROUTER_TYPE_URL.to_string(), value: Vec::new() }),
+        }],
+        upgrade_configs: vec![UpgradeConfig { upgrade_type: "websocket".to_string() }],
+    };
+
+    Listener {
+        name: LISTENER_NAME.to_string(),
+        address: Some(socket_address(&addr.ip().to_string(), addr.port())),
+        filter_chains: vec![FilterChain {
+            filters: vec![Filter {
+                name: "envoy.filters.network.http_connection_manager".to_string(),
+                typed_config: Some(any(envoy::HTTP_CONNECTION_MANAGER_TYPE_URL, &connection_manager)),
+            }],
+        }],
+    }
+}
+
+/// A route per proxy, longest listen path first as the gateway matches them
+fn route_configuration(proxies: &[Proxy]) -> RouteConfiguration {
+    let mut ordered: Vec<&Proxy> = proxies.iter().filter(|proxy| proxy.enabled).collect();
+    ordered.sort_by(|a, b| b.listen_path.len().cmp(&a.listen_path.len()).then_with(|| a.id.cmp(&b.id)));
+
+    RouteConfiguration {
+        name: ROUTE_CONFIGURATION_NAME.to_string(),
+        virtual_hosts: vec![VirtualHost {
+            name: "ferrumgw".to_string(),
+            domains: vec!["*".to_string()],
+            routes: ordered.into_iter().map(route).collect(),
+        }],
+    }
+}
+
+fn route(proxy: &Proxy) -> Route {
+    let backend_path = proxy.backend_path.as_deref().unwrap_or("").trim_end_matches('/');
+    let mut action = RouteAction {
+        cluster: Some(proxy.id.clone()),
+        host_rewrite_literal: match &proxy.upstream_host {
+            Some(_) => Some(handler::upstream_host_header(proxy, &proxy.backend_host, proxy.backend_port, None)),
+            None => (!proxy.preserve_host_header).then(|| proxy.backend_host.clone()),
+        },
+        timeout: (proxy.backend_read_timeout_ms > 0).then(|| duration_ms(proxy.backend_read_timeout_ms)),
+        ..Default::default()
+    };
+
+    // Same paths as the gateway builds: the rest of the path after the listen
+    // path, or the full path, appended to the backend path
+    if proxy.strip_listen_path {
+        action.regex_rewrite = Some(RegexMatchAndSubstitute {
+            pattern: Some(RegexMatcher {
+                regex: format!("^{}/?", escape_regex(proxy.listen_path.trim_end_matches('/'))),
+            }),
+            substitution: format!("{}/", backend_path),
+        });
+    } else if !backend_path.is_empty() {
+        action.prefix_rewrite = format!("{}{}", backend_path, proxy.listen_path);
+    }
+
+    Route {
+        name: proxy.name.clone().unwrap_or_else(|| proxy.id.clone()),
+        r#match: Some(RouteMatch { prefix: Some(proxy.listen_path.clone()) }),
+        route: Some(action),
+    }
+}
+
+/// A cluster with the backend as its only endpoint, resolved by Envoy unless
+/// it is an IP address. Proxies using service discovery are exported with
+/// their backend host.
+fn cluster(proxy: &Proxy) -> Cluster {
+    let host = proxy.dns_override.as_deref().unwrap_or(&proxy.backend_host);
+    let discovery_type = if host.parse::<IpAddr>().is_ok() { DiscoveryType::Static } else { DiscoveryType::StrictDns };
+
+    let mut cluster = Cluster {
+        name: proxy.id.clone(),
+        r#type: Some(discovery_type as i32),
+        connect_timeout: Some(duration_ms(proxy.backend_connect_timeout_ms.max(1))),
+        load_assignment: Some(ClusterLoadAssignment {
+            cluster_name: proxy.id.clone(),
+            endpoints: vec![LocalityLbEndpoints {
+                lb_endpoints: vec![LbEndpoint {
+                    endpoint: Some(Endpoint { address: Some(socket_address(host, proxy.backend_port)) }),
+                }],
+            }],
+        }),
+        ..Default::default()
+    };
+
+    if matches!(proxy.backend_protocol, BackendProtocol::Https | BackendProtocol::Wss) {
+        let tls = UpstreamTlsContext { sni: proxy.backend_host.clone() };
+        cluster.transport_socket = Some(TransportSocket {
+            name: "envoy.transport_sockets.tls".to_string(),
+            typed_config: Some(any(envoy::UPSTREAM_TLS_CONTEXT_TYPE_URL, &tls)),
+        });
+    }
+    if proxy.backend_protocol == BackendProtocol::Grpc {
+        let options = HttpProtocolOptions {
+            explicit_http_config: Some(ExplicitHttpConfig { http2_protocol_options: Some(Http2ProtocolOptions {}) }),
+        };
+        cluster.typed_extension_protocol_options.insert(
+            "envoy.extensions.upstreams.http.v3.HttpProtocolOptions".to_string(),
+            any(envoy::HTTP_PROTOCOL_OPTIONS_TYPE_URL, &options),
+        );
+    }
+    cluster
+}
+
+fn socket_address(address: &str, port: u16) -> Address {
+    Address {
+        socket_address: Some(SocketAddress { address: address.to_string(), port_value: Some(port as u32) }),
+    }
+}
+
+/// Escapes the characters RE2 treats as operators
+fn escape_regex(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+    for c in literal.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// What an Envoy node subscribed to on its stream, and what it was sent
+#[derive(Debug, Default)]
+pub struct AdsSession {
+    subscriptions: HashMap<String, Subscription>,
+    nonce: u64,
+}
+
+#[derive(Debug, Default)]
+struct Subscription {
+    names: Vec<String>,
+    /// Version last sent, whether the node accepted it or not
+    sent_version: Option<u64>,
+    nonce: String,
+}
+
+impl AdsSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The response to a request, if one is due: for a new subscription, a
+    /// change of the requested names, or a version the node was not sent yet.
+    /// Acknowledgements, rejections and requests answering an older response
+    /// get none; a rejected version is not sent again.
+    pub fn on_request(&mut self, request: &DiscoveryRequest, snapshot: &XdsSnapshot) -> Option<DiscoveryResponse> {
+        let subscription = self.subscriptions.entry(request.type_url.clone()).or_default();
+        if !request.response_nonce.is_empty() && request.response_nonce != subscription.nonce {
+            return None;
+        }
+
+        let names_changed = subscription.names != request.resource_names;
+        subscription.names = request.resource_names.clone();
+        if request.error_detail.is_some() || (!names_changed && subscription.sent_version == Some(snapshot.version())) {
+            return None;
+        }
+        Some(self.respond(&request.type_url, snapshot))
+    }
+
+    /// The responses due once the configuration changed, one per subscribed type
+    pub fn on_snapshot(&mut self, snapshot: &XdsSnapshot) -> Vec<DiscoveryResponse> {
+        let stale: Vec<String> = self.subscriptions.iter()
+            .filter(|(_, subscription)| subscription.sent_version != Some(snapshot.version()))
+            .map(|(type_url, _)| type_url.clone())
+            .collect();
+        stale.iter().map(|type_url| self.respond(type_url, snapshot)).collect()
+    }
+
+    fn respond(&mut self, type_url: &str, snapshot: &XdsSnapshot) -> DiscoveryResponse {
+        self.nonce += 1;
+        let subscription = self.subscriptions.entry(type_url.to_string()).or_default();
+        subscription.sent_version = Some(snapshot.version());
+        subscription.nonce = self.nonce.to_string();
+
+        DiscoveryResponse {
+            version_info: snapshot.version().to_string(),
+            resources: snapshot.resources(type_url, &subscription.names),
+            type_url: type_url.to_string(),
+            nonce: subscription.nonce.clone(),
+        }
+    }
+}
+
+/// The Aggregated Discovery Service served to Envoy nodes
+#[derive(Debug)]
+pub struct AggregatedDiscovery {
+    listener_addr: SocketAddr,
+    auth: NodeAuthenticator,
+    snapshots: watch::Sender<Arc<XdsSnapshot>>,
+}
+
+impl AggregatedDiscovery {
+    pub fn new(listener_addr: SocketAddr, auth: NodeAuthenticator) -> Self {
+        let (snapshots, _) = watch::channel(Arc::new(XdsSnapshot::default()));
+        Self { listener_addr, auth, snapshots }
+    }
+
+    /// Translates a new configuration and sends it to the connected nodes
+    pub fn publish(&self, config: &Configuration) {
+        let version = self.snapshots.borrow().version() + 1;
+        self.snapshots.send_replace(Arc::new(XdsSnapshot::build(config, version, self.listener_addr)));
+        debug!("Published xDS resources v{} for {} proxies", version, config.proxies.len());
+    }
+}
+
+#[tonic::async_trait]
+impl AggregatedDiscoveryService for AggregatedDiscovery {
+    type StreamAggregatedResourcesStream = ReceiverStream<Result<DiscoveryResponse, Status>>;
+
+    async fn stream_aggregated_resources(
+        &self,
+        request: Request<Streaming<DiscoveryRequest>>,
+    ) -> Result<Response<Self::StreamAggregatedResourcesStream>, Status> {
+        let credentials = RequestCredentials::from_request(&request)?;
+        let mut requests = request.into_inner();
+
+        // The node ID only arrives with the first request
+        let first = requests.message().await?
+            .ok_or_else(|| Status::invalid_argument("Stream closed before the first discovery request"))?;
+        let node_id = first.node.as_ref().map(|node| node.id.clone()).unwrap_or_default();
+        if let Err(e) = self.auth.authenticate_credentials(&node_id, &credentials) {
+            warn!("Refused Envoy node {}: {}", node_id, e);
+            return Err(e.into());
+        }
+        info!("Envoy node {} connected for xDS resources", node_id);
+
+        let (tx, rx) = mpsc::channel(16);
+        let mut snapshots = self.snapshots.subscribe();
+        tokio::spawn(async move {
+            let mut session = AdsSession::new();
+            let mut next = Some(first);
+            loop {
+                let responses = match next.take() {
+                    Some(request) => {
+                        if let Some(error) = &request.error_detail {
+                            warn!("Envoy node {} rejected {} v{}: {}",
+                                node_id, request.type_url, request.version_info, error.message);
+                        }
+                        let snapshot = snapshots.borrow().clone();
+                        session.on_request(&request, &snapshot).into_iter().collect()
+                    },
+                    None => tokio::select! {
+                        request = requests.message() => match request {
+                            Ok(Some(request)) => {
+                                next = Some(request);
+                                continue;
+                            },
+                            Ok(None) => break,
+                            Err(e) => {
+                                debug!("xDS stream of Envoy node {} failed: {}", node_id, e);
+                                break;
+                            },
+                        },
+                        changed = snapshots.changed() => {
+                            if changed.is_err() {
+                                break;
+                            }
+                            let snapshot = snapshots.borrow().clone();
+                            session.on_snapshot(&snapshot)
+                        },
+                    },
+                };
+
+                for response in responses {
+                    if tx.send(Ok(response)).await.is_err() {
+                        info!("Envoy node {} disconnected", node_id);
+                        return;
+                    }
+                }
+            }
+            info!("Envoy node {} disconnected", node_id);
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, Weak};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+use crate::dns::DnsCache;
+use crate::modes::OperationMode;
+
+lazy_static! {
+    static ref STATE: Mutex<HealthState> = Mutex::new(HealthState::default());
+    static ref STARTED_AT: DateTime<Utc> = Utc::now();
+}
+
+/// How ready this instance, or one part of it, is to take traffic. An
+/// instance that has never loaded a configuration is not ready; one that keeps
+/// serving its last configuration while the source is unreachable is degraded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Ready,
+    /// Serving, but something needs attention
+    Degraded,
+    /// Should not receive traffic
+    NotReady,
+}
+
+/// Reads and failures of a configuration source or connection
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SourceState {
+    pub reachable: bool,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_failure_at: Option<DateTime<Utc>>,
+    /// Why the last attempt failed, while the source is unreachable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl SourceState {
+    fn record(&mut self, result: Result<(), String>) {
+        let now = Utc::now();
+        match result {
+            Ok(()) => {
+                self.reachable = true;
+                self.last_success_at = Some(now);
+                self.error = None;
+            },
+            Err(error) => {
+                self.reachable = false;
+                self.last_failure_at = Some(now);
+                self.error = Some(error);
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct HealthState {
+    config_loaded_at: Option<DateTime<Utc>>,
+    config_source: Option<SourceState>,
+    control_plane: Option<SourceState>,
+    dns_cache: Option<Weak<DnsCache>>,
+    listeners: BTreeMap<&'static str, SocketAddr>,
+}
+
+/// One part of the readiness report
+#[derive(Debug, Clone, Serialize)]
+pub struct Check<T: Serialize> {
+    pub status: Status,
+    #[serde(flatten)]
+    pub detail: T,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigDetail {
+    /// When a configuration was last swapped in for the request path
+    pub loaded_at: Option<DateTime<Utc>>,
+    /// The database or file the configuration is read from; None before the first read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceState>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DnsDetail {
+    pub entries: usize,
+    pub expired_entries: usize,
+    pub override_entries: usize,
+    /// Backend hosts whose last lookup failed, with the error
+    pub failing_hosts: BTreeMap<String, String>,
+}
+
+/// What GET /health returns
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub status: Status,
+    pub mode: OperationMode,
+    pub started_at: DateTime<Utc>,
+    pub config: Check<ConfigDetail>,
+    /// The Data Plane's connection to its Control Plane
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub control_plane: Option<Check<SourceState>>,
+    /// None when this instance does not proxy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns: Option<Check<DnsDetail>>,
+}
+
+impl HealthReport {
+    /// Whether orchestrators should send traffic to this instance
+    pub fn is_ready(&self) -> bool {
+        self.status != Status::NotReady
+    }
+}
+
+/// Starts the uptime clock reported by the Admin API
+pub fn record_start() {
+    lazy_static::initialize(&STARTED_AT);
+}
+
+/// When this instance started
+pub fn started_at() -> DateTime<Utc> {
+    *STARTED_AT
+}
+
+/// Records an address this instance accepts connections on, such as `proxy_http`
+pub fn record_listener(name: &'static str, addr: SocketAddr) {
+    STATE.lock().unwrap().listeners.insert(name, addr);
+}
+
+/// The addresses this instance accepts connections on, by listener
+pub fn listeners() -> BTreeMap<&'static str, SocketAddr> {
+    STATE.lock().unwrap().listeners.clone()
+}
+
+/// Records that a configuration was swapped in for the request path
+pub fn record_config_loaded() {
+    STATE.lock().unwrap().config_loaded_at = Some(Utc::now());
+}
+
+/// Records a read of the database or file the configuration comes from
+pub fn record_config_source(result: Result<(), String>) {
+    STATE.lock().unwrap().config_source.get_or_insert_with(SourceState::default).record(result);
+}
+
+/// Records a Data Plane connecting to, or losing, its Control Plane
+pub fn record_control_plane(result: Result<(), String>) {
+    STATE.lock().unwrap().control_plane.get_or_insert_with(SourceState::default).record(result);
+}
+
+/// Includes a DNS cache in the report for as long as it is in use
+pub fn register_dns_cache(dns_cache: &Arc<DnsCache>) {
+    STATE.lock().unwrap().dns_cache = Some(Arc::downgrade(dns_cache));
+}
+
+/// The DNS cache in use, if any
+pub fn dns_cache() -> Option<Arc<DnsCache>> {
+    STATE.lock().unwrap().dns_cache.as_ref().and_then(Weak::upgrade)
+}
+
+/// The readiness of this instance
+pub fn report(mode: OperationMode) -> HealthReport {
+    let state = STATE.lock().unwrap();
+
+    let config_status = match (&state.config_loaded_at, &state.config_source) {
+        (None, _) => Status::NotReady,
+        (Some(_), Some(source)) if !source.reachable => Status::Degraded,
+        (Some(_), _) => Status::Ready,
+    };
+    let config = Check {
+        status: config_status,
+        detail: ConfigDetail {
+            loaded_at: state.config_loaded_at,
+            source: state.config_source.clone(),
+        },
+    };
+
+    // Without its Control Plane a Data Plane serves the configuration it holds
+    let control_plane = state.control_plane.clone().map(|connection| Check {
+        status: if connection.reachable { Status::Ready } else { Status::Degraded },
+        detail: connection,
+    });
+
+    let dns = state.dns_cache.as_ref().and_then(Weak::upgrade).map(|dns_cache| {
+        let stats = dns_cache.stats();
+        let failing_hosts = dns_cache.failing_hosts();
+        Check {
+            status: if failing_hosts.is_empty() { Status::Ready } else { Status::Degraded },
+            detail: DnsDetail {
+                entries: stats.total_entries,
+                expired_entries: stats.expired_entries,
+                override_entries: stats.override_entries,
+                failing_hosts,
+            },
+        }
+    });
+
+    let status = [Some(config.status), control_plane.as_ref().map(|c| c.status), dns.as_ref().map(|d| d.status)]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or(Status::Ready);
+
+    HealthReport {
+        status,
+        mode,
+        started_at: started_at(),
+        config,
+        control_plane,
+        dns,
+    }
+}
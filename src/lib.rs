@@ -7,14 +7,21 @@ pub mod modes;
 pub mod grpc;
 pub mod metrics;
 pub mod utils;
+pub mod bench;
+pub mod acme;
+pub mod discovery;
+pub mod dns;
+pub mod gateway;
+pub mod health;
 
 // Re-export important types and functions for easier access
 pub use config::data_model::{
     Configuration, Proxy, Consumer, PluginConfig, 
     Protocol, AuthMode, 
 };
-pub use proxy::handler::ProxyHandler;
+pub use proxy::handler::{ProxyHandler, RequestContext};
 pub use proxy::router::Router;
 pub use database::DatabaseClient;
 pub use plugins::Plugin;
 pub use plugins::PluginManager;
+pub use gateway::{Gateway, GatewayBuilder, GatewayHandle};
@@ -10,17 +10,84 @@ mod proxy;
 mod admin;
 mod utils;
 mod metrics;
+mod bench;
+mod acme;
+mod discovery;
+mod dns;
+mod health;
 
-use config::env_config::EnvConfig;
+use clap::Parser;
+
+use config::env_config::{EnvConfig, EnvConfigError};
+use config::settings::Settings;
 use modes::OperationMode;
 
 #[tokio::main]
 async fn main() {
+    // `ferrumgw config-schema` prints the JSON Schema of file mode configuration
+    if env::args().nth(1).as_deref() == Some("config-schema") {
+        initialize_logging(env::var("FERRUM_LOG_LEVEL").ok());
+        match serde_json::to_string_pretty(&config::file_config::config_schema()) {
+            Ok(schema) => println!("{}", schema),
+            Err(e) => {
+                error!("Failed to generate configuration schema: {}", e);
+                exit(1);
+            }
+        }
+        return;
+    }
+    
+    // `ferrumgw config export|import` syncs the database with a declarative document
+    if env::args().nth(1).as_deref() == Some("config") {
+        initialize_logging(env::var("FERRUM_LOG_LEVEL").ok());
+        let args = config::cli::ConfigArgs::parse_from(env::args().skip(1));
+        if let Err(e) = config::cli::run(args).await {
+            error!("Configuration command failed: {:#}", e);
+            exit(1);
+        }
+        return;
+    }
+    
+    // `ferrumgw migrate` creates or upgrades the database schema
+    if env::args().nth(1).as_deref() == Some("migrate") {
+        initialize_logging(env::var("FERRUM_LOG_LEVEL").ok());
+        let args = database::cli::MigrateArgs::parse_from(env::args().skip(1));
+        if let Err(e) = database::cli::run(args).await {
+            error!("Migration failed: {:#}", e);
+            exit(1);
+        }
+        return;
+    }
+    
+    // `ferrumgw bench` runs the load generator instead of the gateway
+    if env::args().nth(1).as_deref() == Some("bench") {
+        initialize_logging(env::var("FERRUM_LOG_LEVEL").ok());
+        let args = bench::BenchArgs::parse_from(env::args().skip(1));
+        if let Err(e) = bench::run(args).await {
+            error!("Benchmark failed: {}", e);
+            exit(1);
+        }
+        return;
+    }
+    
+    // `ferrumgw print-config` shows the effective settings and where each one comes from
+    if env::args().nth(1).as_deref() == Some("print-config") {
+        let settings = load_settings(Settings::from_args("ferrumgw print-config", env::args().skip(1)));
+        print!("{}", settings.render());
+        if let Err(e) = EnvConfig::from_settings(&settings) {
+            eprintln!("Invalid configuration: {}", e);
+            exit(1);
+        }
+        return;
+    }
+    
+    // Load settings from command line flags, environment and bootstrap file
+    let settings = load_settings(Settings::from_args("ferrumgw", env::args()));
+    
     // Initialize logging
-    initialize_logging();
+    initialize_logging(settings.var("FERRUM_LOG_LEVEL").ok());
     
-    // Load environment configuration
-    let env_config = match EnvConfig::from_env() {
+    let env_config = match EnvConfig::from_settings(&settings) {
         Ok(config) => config,
         Err(e) => {
             error!("Failed to load environment configuration: {}", e);
@@ -29,6 +96,18 @@ async fn main() {
     };
     
     info!("Starting Ferrum Gateway v{}", env!("CARGO_PKG_VERSION"));
+    health::record_start();
+    plugins::log_sampling::start(&env_config);
+    proxy::load_shedding::start(&env_config);
+    proxy::long_lived::start(&env_config);
+    proxy::egress::start(&env_config);
+    proxy::deadline::start(&env_config);
+    metrics::endpoint::start(&env_config);
+    metrics::otlp::start(&env_config);
+    if let Err(e) = metrics::statsd::start(&env_config) {
+        error!("Failed to start the StatsD sink: {:#}", e);
+        exit(1);
+    }
     info!("Operation mode: {}", env_config.mode);
     
     // Initialize the gateway based on operation mode
@@ -37,6 +116,8 @@ async fn main() {
         OperationMode::File => modes::file::run(env_config).await,
         OperationMode::ControlPlane => modes::control_plane::run(env_config).await,
         OperationMode::DataPlane => modes::data_plane::run(env_config).await,
+        OperationMode::Docker => modes::docker::run(env_config).await,
+        OperationMode::Hybrid => modes::control_plane::run_hybrid(env_config).await,
     };
     
     // Handle result
@@ -48,16 +129,35 @@ async fn main() {
     info!("Ferrum Gateway shut down successfully");
 }
 
-fn initialize_logging() {
-    let log_level = env::var("FERRUM_LOG_LEVEL").unwrap_or_else(|_| "info".to_string());
+fn load_settings(settings: Result<Settings, EnvConfigError>) -> Settings {
+    match settings {
+        Ok(settings) => settings,
+        Err(e) => {
+            // Logging is configured by these settings, so report on stderr directly
+            eprintln!("Failed to load settings: {}", e);
+            exit(1);
+        }
+    }
+}
+
+fn initialize_logging(log_level: Option<String>) {
+    let log_level = log_level.unwrap_or_else(|| "info".to_string());
     
     let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| {
             tracing_subscriber::EnvFilter::new(format!("ferrumgw={}", log_level))
         });
     
-    tracing_subscriber::fmt()
+    // Configuration commands print documents on stdout, so their logs go to stderr
+    let logs_to_stderr = matches!(env::args().nth(1).as_deref(), Some("config") | Some("config-schema"));
+    
+    let subscriber = tracing_subscriber::fmt()
         .with_env_filter(env_filter)
-        .with_target(true)
-        .init();
+        .with_target(true);
+    
+    if logs_to_stderr {
+        subscriber.with_writer(std::io::stderr).init();
+    } else {
+        subscriber.init();
+    }
 }
@@ -0,0 +1,155 @@
+//! Per-consumer traffic of each proxy, labeled by name
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use lazy_static::lazy_static;
+use prometheus::{register_counter_vec, register_histogram_vec, CounterVec, HistogramVec};
+
+use crate::config::env_config::EnvConfig;
+
+/// Label value of the proxy and consumer of requests past the label set limit
+pub const OTHER: &str = "__other__";
+
+lazy_static! {
+    static ref CONSUMER_REQUESTS: CounterVec = register_counter_vec!(
+        "ferrumgw_consumer_requests_total",
+        "Requests of each consumer to each proxy; the consumer is empty for unauthenticated requests",
+        &["proxy", "consumer"]
+    ).unwrap();
+
+    static ref CONSUMER_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "ferrumgw_consumer_request_duration_seconds",
+        "Request duration in seconds of each consumer on each proxy",
+        &["proxy", "consumer"],
+        vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]
+    ).unwrap();
+
+    static ref CONSUMER_REQUEST_BYTES: CounterVec = register_counter_vec!(
+        "ferrumgw_consumer_request_bytes_total",
+        "Request body bytes each consumer sent to each proxy",
+        &["proxy", "consumer"]
+    ).unwrap();
+
+    static ref CONSUMER_RESPONSE_BYTES: CounterVec = register_counter_vec!(
+        "ferrumgw_consumer_response_bytes_total",
+        "Response body bytes each consumer received from each proxy",
+        &["proxy", "consumer"]
+    ).unwrap();
+
+    static ref STATE: Mutex<LabelSets> = Mutex::new(LabelSets::default());
+}
+
+/// Whether the metrics are recorded, so requests skip the lock when they are not
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Which requests the per-consumer metrics are recorded for
+#[derive(Debug, Clone, Default)]
+pub struct ConsumerMetricsSettings {
+    pub enabled: bool,
+    /// Proxy names or IDs to record; empty records every proxy
+    pub proxies: Vec<String>,
+    /// Consumer usernames or IDs to record; empty records every consumer and
+    /// unauthenticated requests
+    pub consumers: Vec<String>,
+    /// Most proxy and consumer label sets
+    pub max_label_sets: usize,
+}
+
+impl ConsumerMetricsSettings {
+    pub fn from_env_config(env_config: &EnvConfig) -> Self {
+        Self {
+            enabled: env_config.metrics_consumer_labels,
+            proxies: env_config.metrics_proxy_allowlist.clone(),
+            consumers: env_config.metrics_consumer_allowlist.clone(),
+            max_label_sets: env_config.metrics_max_label_sets,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct LabelSets {
+    settings: ConsumerMetricsSettings,
+    seen: HashSet<(String, String)>,
+}
+
+impl LabelSets {
+    /// The labels to record a request under, or None when it is not recorded
+    fn labels(&mut self, proxy: Labeled, consumer: Option<Labeled>) -> Option<(String, String)> {
+        let allowed = |allowlist: &[String], entity: &Labeled| {
+            allowlist.is_empty() || allowlist.iter().any(|name| name == entity.id || Some(name.as_str()) == entity.name)
+        };
+        if !allowed(&self.settings.proxies, &proxy) {
+            return None;
+        }
+        match &consumer {
+            Some(consumer) if !allowed(&self.settings.consumers, consumer) => return None,
+            None if !self.settings.consumers.is_empty() => return None,
+            _ => {},
+        }
+
+        let labels = (proxy.label().to_string(), consumer.map_or("", |consumer| consumer.label()).to_string());
+        if self.seen.contains(&labels) {
+            return Some(labels);
+        }
+        if self.seen.len() >= self.settings.max_label_sets {
+            return Some((OTHER.to_string(), OTHER.to_string()));
+        }
+        self.seen.insert(labels.clone());
+        Some(labels)
+    }
+}
+
+/// A proxy or consumer: labeled by its name, or its ID when it has none
+#[derive(Debug, Clone, Copy)]
+pub struct Labeled<'a> {
+    pub id: &'a str,
+    pub name: Option<&'a str>,
+}
+
+impl Labeled<'_> {
+    fn label(&self) -> &str {
+        self.name.filter(|name| !name.is_empty()).unwrap_or(self.id)
+    }
+}
+
+/// Applies the settings, and forgets the label sets recorded so far
+pub fn configure(settings: ConsumerMetricsSettings) {
+    let mut state = STATE.lock().unwrap();
+    ENABLED.store(settings.enabled, Ordering::Relaxed);
+    state.settings = settings;
+    state.seen.clear();
+}
+
+/// Records a request of a consumer, or an unauthenticated one, to a proxy
+pub fn record(proxy: Labeled, consumer: Option<Labeled>, duration: Duration, request_bytes: u64) {
+    let labels = match labels(proxy, consumer) {
+        Some(labels) => labels,
+        None => return,
+    };
+
+    let labels = [labels.0.as_str(), labels.1.as_str()];
+    CONSUMER_REQUESTS.with_label_values(&labels).inc();
+    CONSUMER_REQUEST_DURATION.with_label_values(&labels).observe(duration.as_secs_f64());
+    CONSUMER_REQUEST_BYTES.with_label_values(&labels).inc_by(request_bytes as f64);
+}
+
+/// Records the response body bytes sent for a request, once they have been sent
+pub fn record_response_bytes(proxy: Labeled, consumer: Option<Labeled>, bytes: u64) {
+    if let Some((proxy, consumer)) = labels(proxy, consumer) {
+        CONSUMER_RESPONSE_BYTES.with_label_values(&[&proxy, &consumer]).inc_by(bytes as f64);
+    }
+}
+
+fn labels(proxy: Labeled, consumer: Option<Labeled>) -> Option<(String, String)> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    STATE.lock().unwrap().labels(proxy, consumer)
+}
+
+/// Proxy and consumer label sets recorded since the metrics were configured
+pub fn label_sets() -> usize {
+    STATE.lock().unwrap().seen.len()
+}
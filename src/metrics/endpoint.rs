@@ -0,0 +1,130 @@
+//! Where Prometheus can scrape the metrics without an Admin API token
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use anyhow::Result;
+use arc_swap::ArcSwapOption;
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+use crate::config::env_config::EnvConfig;
+use crate::health;
+use super::fleet;
+
+static SETTINGS: ArcSwapOption<MetricsEndpointSettings> = ArcSwapOption::const_empty();
+
+/// Where the unauthenticated metrics endpoint is served
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsEndpointSettings {
+    /// Port of the dedicated metrics listener, if any
+    pub port: Option<u16>,
+    /// Path of the metrics on every listener that serves them
+    pub path: String,
+    /// Also answer the path on the proxy listeners, before any proxy can claim it
+    pub on_proxy: bool,
+    /// Also answer the path on the admin listeners without a token
+    pub on_admin: bool,
+}
+
+impl MetricsEndpointSettings {
+    pub fn from_env_config(env_config: &EnvConfig) -> Self {
+        Self {
+            port: env_config.metrics_port,
+            path: env_config.metrics_path.clone(),
+            on_proxy: env_config.metrics_on_proxy,
+            on_admin: env_config.metrics_on_admin,
+        }
+    }
+}
+
+/// Applies the settings and starts the dedicated listener, if one is configured
+pub fn start(env_config: &EnvConfig) {
+    let settings = MetricsEndpointSettings::from_env_config(env_config);
+
+    if let Some(port) = settings.port {
+        let path = settings.path.clone();
+        tokio::spawn(async move {
+            if let Err(e) = run_server(port, path).await {
+                error!("Metrics server error: {}", e);
+            }
+        });
+    }
+    SETTINGS.store(Some(Arc::new(settings)));
+}
+
+async fn run_server(port: u16, path: String) -> Result<()> {
+    let addr = format!("0.0.0.0:{}", port).parse::<SocketAddr>()?;
+    let listener = TcpListener::bind(addr).await?;
+    health::record_listener("metrics", listener.local_addr()?);
+    info!("Serving metrics on {}{}", addr, path);
+
+    let path: Arc<str> = path.into();
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                error!("Failed to accept metrics connection: {}", e);
+                continue;
+            }
+        };
+
+        let path = Arc::clone(&path);
+        tokio::spawn(async move {
+            let service = service_fn(move |req: Request<Body>| {
+                let path = Arc::clone(&path);
+                async move {
+                    if req.method() == Method::GET && req.uri().path() == &*path {
+                        Ok::<_, hyper::Error>(metrics_response().await)
+                    } else {
+                        Ok(Response::builder()
+                            .status(StatusCode::NOT_FOUND)
+                            .body(Body::from("Not Found"))
+                            .unwrap())
+                    }
+                }
+            });
+            if let Err(e) = Http::new().serve_connection(stream, service).await {
+                error!("Error serving metrics connection: {}", e);
+            }
+        });
+    }
+}
+
+/// The metrics, when the request is for the metrics path on a proxy listener
+pub async fn proxy_response(req: &Request<Body>) -> Option<Response<Body>> {
+    match SETTINGS.load().as_ref() {
+        Some(settings) if settings.on_proxy && is_metrics_request(settings, req) => Some(metrics_response().await),
+        _ => None,
+    }
+}
+
+/// The metrics, when the request is for the metrics path on an admin listener
+pub async fn admin_response(req: &Request<Body>) -> Option<Response<Body>> {
+    match SETTINGS.load().as_ref() {
+        Some(settings) if settings.on_admin && is_metrics_request(settings, req) => Some(metrics_response().await),
+        _ => None,
+    }
+}
+
+fn is_metrics_request(settings: &MetricsEndpointSettings, req: &Request<Body>) -> bool {
+    req.method() == Method::GET && req.uri().path() == settings.path
+}
+
+/// The metrics in the Prometheus text format, with the fleet metrics of a
+/// Control Plane up to date
+pub async fn metrics_response() -> Response<Body> {
+    fleet::refresh();
+    match super::metrics_handler(Request::new(Body::empty())).await {
+        Ok(response) => response,
+        Err(e) => {
+            error!("Failed to encode metrics: {}", e);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::empty())
+                .unwrap()
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Fleet-level traffic metrics aggregated by the Control Plane
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use lazy_static::lazy_static;
+use prometheus::{register_counter_vec, register_gauge_vec, CounterVec, GaugeVec};
+use serde::Serialize;
+
+use crate::grpc::proto::HealthReport;
+
+/// A report is stale after this many of the node's report intervals
+const STALE_INTERVALS: f64 = 3.0;
+/// Reports never turn stale sooner than this
+const MIN_STALE_AFTER: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    static ref FLEET_REQUESTS_PER_SECOND: GaugeVec = register_gauge_vec!(
+        "ferrumgw_fleet_requests_per_second",
+        "Requests per second for each proxy summed over all reporting Data Plane nodes",
+        &["proxy_id"]
+    ).unwrap();
+
+    static ref FLEET_P99_LATENCY: GaugeVec = register_gauge_vec!(
+        "ferrumgw_fleet_p99_latency_seconds",
+        "Highest p99 request duration for each proxy among the reporting Data Plane nodes",
+        &["proxy_id"]
+    ).unwrap();
+
+    static ref FLEET_RESPONSES: CounterVec = register_counter_vec!(
+        "ferrumgw_fleet_responses_total",
+        "Responses reported by Data Plane nodes for each proxy, by status code",
+        &["proxy_id", "status_code"]
+    ).unwrap();
+
+    static ref FLEET_NODES: GaugeVec = register_gauge_vec!(
+        "ferrumgw_fleet_nodes",
+        "Data Plane nodes by the status of their last health report",
+        &["status"]
+    ).unwrap();
+
+    static ref FLEET: Mutex<FleetMetrics> = Mutex::new(FleetMetrics::default());
+}
+
+/// Traffic of one proxy over a report interval
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProxyStats {
+    pub proxy_id: String,
+    pub requests: u64,
+    pub requests_per_second: f64,
+    pub p99_latency_ms: f64,
+    pub status_codes: BTreeMap<String, u64>,
+}
+
+/// The last health report of a Data Plane node
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeMetrics {
+    pub node_id: String,
+    pub status: String,
+    pub config_version: u64,
+    /// When the node sent the report
+    pub timestamp: String,
+    pub interval_seconds: f64,
+    pub proxies: Vec<ProxyStats>,
+    #[serde(skip)]
+    received_at: Instant,
+}
+
+impl NodeMetrics {
+    fn is_stale(&self, now: Instant) -> bool {
+        let stale_after = Duration::from_secs_f64(self.interval_seconds.max(0.0) * STALE_INTERVALS).max(MIN_STALE_AFTER);
+        now.duration_since(self.received_at) > stale_after
+    }
+}
+
+/// The latest report of every Data Plane node
+#[derive(Debug, Default)]
+pub struct FleetMetrics {
+    nodes: BTreeMap<String, NodeMetrics>,
+}
+
+impl FleetMetrics {
+    /// Keeps a node's report in place of its previous one
+    pub fn record(&mut self, report: &HealthReport, now: Instant) {
+        let mut proxies: Vec<ProxyStats> = report.proxy_metrics.iter()
+            .map(|proxy| ProxyStats {
+                proxy_id: proxy.proxy_id.clone(),
+                requests: proxy.requests,
+                requests_per_second: proxy.requests_per_second,
+                p99_latency_ms: proxy.p99_latency_ms,
+                status_codes: proxy.status_codes.iter().map(|(code, count)| (code.clone(), *count)).collect(),
+            })
+            .collect();
+        proxies.sort_by(|a, b| a.proxy_id.cmp(&b.proxy_id));
+
+        self.nodes.insert(report.node_id.clone(), NodeMetrics {
+            node_id: report.node_id.clone(),
+            status: report.status.clone(),
+            config_version: report.config_version,
+            timestamp: report.timestamp.clone(),
+            interval_seconds: report.interval_seconds,
+            proxies,
+            received_at: now,
+        });
+    }
+
+    pub fn node(&self, node_id: &str) -> Option<&NodeMetrics> {
+        self.nodes.get(node_id)
+    }
+
+    /// Traffic of each proxy across the nodes whose report is not stale:
+    /// requests, rates and status codes are summed, p99 is the highest
+    pub fn proxies(&self, now: Instant) -> Vec<ProxyStats> {
+        let mut proxies: BTreeMap<&str, ProxyStats> = BTreeMap::new();
+        for node in self.nodes.values().filter(|node| !node.is_stale(now)) {
+            for proxy in &node.proxies {
+                let fleet = proxies.entry(proxy.proxy_id.as_str()).or_insert_with(|| ProxyStats {
+                    proxy_id: proxy.proxy_id.clone(),
+                    ..Default::default()
+                });
+                fleet.requests += proxy.requests;
+                fleet.requests_per_second += proxy.requests_per_second;
+                fleet.p99_latency_ms = fleet.p99_latency_ms.max(proxy.p99_latency_ms);
+                for (code, count) in &proxy.status_codes {
+                    *fleet.status_codes.entry(code.clone()).or_default() += count;
+                }
+            }
+        }
+        proxies.into_values().collect()
+    }
+
+    /// Number of nodes by reported status, with stale nodes counted as "stale"
+    pub fn node_counts(&self, now: Instant) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for node in self.nodes.values() {
+            let status = if node.is_stale(now) { "stale" } else { node.status.as_str() };
+            *counts.entry(status.to_string()).or_default() += 1;
+        }
+        counts
+    }
+}
+
+// Record a Data Plane health report in the fleet metrics
+pub fn record_report(report: &HealthReport) {
+    for proxy in &report.proxy_metrics {
+        for (code, count) in &proxy.status_codes {
+            FLEET_RESPONSES.with_label_values(&[&proxy.proxy_id, code]).inc_by(*count as f64);
+        }
+    }
+    FLEET.lock().unwrap().record(report, Instant::now());
+    refresh();
+}
+
+// The last report of a Data Plane node
+pub fn node_metrics(node_id: &str) -> Option<NodeMetrics> {
+    FLEET.lock().unwrap().node(node_id).cloned()
+}
+
+// The traffic of a proxy summed over the reporting Data Plane nodes
+pub fn proxy_stats(proxy_id: &str) -> Option<ProxyStats> {
+    FLEET.lock().unwrap().proxies(Instant::now()).into_iter().find(|proxy| proxy.proxy_id == proxy_id)
+}
+
+// Recompute the fleet gauges, so nodes that stopped reporting drop out
+pub fn refresh() {
+    let now = Instant::now();
+    let fleet = FLEET.lock().unwrap();
+
+    FLEET_REQUESTS_PER_SECOND.reset();
+    FLEET_P99_LATENCY.reset();
+    for proxy in fleet.proxies(now) {
+        FLEET_REQUESTS_PER_SECOND.with_label_values(&[&proxy.proxy_id]).set(proxy.requests_per_second);
+        FLEET_P99_LATENCY.with_label_values(&[&proxy.proxy_id]).set(proxy.p99_latency_ms / 1000.0);
+    }
+
+    FLEET_NODES.reset();
+    for (status, count) in fleet.node_counts(now) {
+        FLEET_NODES.with_label_values(&[&status]).set(count as f64);
+    }
+}
@@ -0,0 +1,205 @@
+//! Latency percentiles of the last minute, overall and by proxy
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+use super::recent::WINDOW_SECONDS;
+
+/// Linear sub-buckets per power of two, as a power of two; 3 keeps every
+/// bucket within 1/16 of its midpoint
+const SUB_BUCKET_BITS: u32 = 3;
+
+/// Seconds covered by one slot of the window
+const SLOT_SECONDS: u64 = 5;
+
+const SLOTS: usize = (WINDOW_SECONDS / SLOT_SECONDS) as usize;
+
+lazy_static! {
+    static ref STARTED: Instant = Instant::now();
+    static ref LATENCIES: Mutex<RecentLatencies> = Mutex::new(RecentLatencies::default());
+}
+
+/// A histogram of latencies with buckets of bounded relative width
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    buckets: BTreeMap<u32, u64>,
+    count: u64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, duration: Duration) {
+        let micros = duration.as_micros().min(u64::MAX as u128) as u64;
+        *self.buckets.entry(bucket_index(micros)).or_default() += 1;
+        self.count += 1;
+    }
+
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (index, count) in &other.buckets {
+            *self.buckets.entry(*index).or_default() += count;
+        }
+        self.count += other.count;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The latency in milliseconds below which the given fraction of the
+    /// requests fall, or 0 without requests
+    pub fn quantile_ms(&self, quantile: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in &self.buckets {
+            seen += count;
+            if seen >= rank {
+                let (low, high) = bucket_range(*index);
+                return (low + high) as f64 / 2.0 / 1000.0;
+            }
+        }
+        0.0
+    }
+
+    pub fn percentiles(&self) -> LatencyPercentiles {
+        LatencyPercentiles {
+            requests: self.count,
+            p50: self.quantile_ms(0.5),
+            p95: self.quantile_ms(0.95),
+            p99: self.quantile_ms(0.99),
+        }
+    }
+}
+
+/// Values below 2^SUB_BUCKET_BITS microseconds get a bucket each; above,
+/// each power of two is split into 2^SUB_BUCKET_BITS buckets
+fn bucket_index(micros: u64) -> u32 {
+    let sub_buckets = 1u64 << SUB_BUCKET_BITS;
+    if micros < sub_buckets {
+        return micros as u32;
+    }
+    let magnitude = 63 - micros.leading_zeros();
+    let shift = magnitude - SUB_BUCKET_BITS;
+    let sub_bucket = (micros >> shift) & (sub_buckets - 1);
+    ((shift + 1) << SUB_BUCKET_BITS) + sub_bucket as u32
+}
+
+/// The microseconds a bucket covers, as a half-open range
+fn bucket_range(index: u32) -> (u64, u64) {
+    let sub_buckets = 1u64 << SUB_BUCKET_BITS;
+    if (index as u64) < sub_buckets {
+        return (index as u64, index as u64 + 1);
+    }
+    let shift = (index >> SUB_BUCKET_BITS) - 1;
+    let sub_bucket = index as u64 & (sub_buckets - 1);
+    let low = (sub_buckets + sub_bucket) << shift;
+    (low, low + (1 << shift))
+}
+
+/// Request count and latency percentiles in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct LatencyPercentiles {
+    pub requests: u64,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+/// Latencies of one slot of the window
+#[derive(Debug, Clone, Default)]
+struct Slot {
+    start: u64,
+    histogram: LatencyHistogram,
+}
+
+/// The last minute of latencies of the gateway or one proxy
+#[derive(Debug, Clone)]
+struct RecentHistogram {
+    slots: Vec<Slot>,
+    last_second: u64,
+}
+
+impl Default for RecentHistogram {
+    fn default() -> Self {
+        Self {
+            slots: vec![Slot::default(); SLOTS],
+            last_second: 0,
+        }
+    }
+}
+
+impl RecentHistogram {
+    fn record(&mut self, second: u64, duration: Duration) {
+        let start = second - second % SLOT_SECONDS;
+        let slot = &mut self.slots[((second / SLOT_SECONDS) % SLOTS as u64) as usize];
+        if slot.start != start || slot.histogram.count() == 0 {
+            *slot = Slot { start, ..Default::default() };
+        }
+        slot.histogram.record(duration);
+        self.last_second = self.last_second.max(second);
+    }
+
+    /// The slots that end within the minute before `second`
+    fn window(&self, second: u64) -> LatencyHistogram {
+        let mut histogram = LatencyHistogram::default();
+        for slot in &self.slots {
+            if slot.histogram.count() > 0 && slot.start <= second && second - slot.start < WINDOW_SECONDS {
+                histogram.merge(&slot.histogram);
+            }
+        }
+        histogram
+    }
+}
+
+/// Latencies of the last minute, over all proxies and of each proxy that
+/// served requests in it
+#[derive(Debug, Default)]
+pub struct RecentLatencies {
+    overall: RecentHistogram,
+    proxies: HashMap<String, RecentHistogram>,
+    pruned_at: u64,
+}
+
+impl RecentLatencies {
+    pub fn record(&mut self, second: u64, proxy_id: &str, duration: Duration) {
+        if second != self.pruned_at {
+            self.proxies.retain(|_, histogram| second.saturating_sub(histogram.last_second) < WINDOW_SECONDS);
+            self.pruned_at = second;
+        }
+
+        self.overall.record(second, duration);
+        self.proxies.entry(proxy_id.to_string()).or_default().record(second, duration);
+    }
+
+    pub fn overall(&self, second: u64) -> LatencyPercentiles {
+        self.overall.window(second).percentiles()
+    }
+
+    pub fn proxies(&self, second: u64) -> HashMap<String, LatencyPercentiles> {
+        self.proxies.iter()
+            .map(|(proxy_id, histogram)| (proxy_id.clone(), histogram.window(second).percentiles()))
+            .filter(|(_, percentiles)| percentiles.requests > 0)
+            .collect()
+    }
+}
+
+fn current_second() -> u64 {
+    STARTED.elapsed().as_secs()
+}
+
+// Track how long a request of a proxy took
+pub fn record(proxy_id: &str, duration: Duration) {
+    LATENCIES.lock().unwrap().record(current_second(), proxy_id, duration);
+}
+
+// Latency percentiles over all proxies and of each proxy, over the last minute
+pub fn last_minute() -> (LatencyPercentiles, HashMap<String, LatencyPercentiles>) {
+    let latencies = LATENCIES.lock().unwrap();
+    let second = current_second();
+    (latencies.overall(second), latencies.proxies(second))
+}
@@ -1,16 +1,26 @@
 use anyhow::Result;
-use prometheus::{register_counter_vec, register_histogram_vec, register_int_counter, register_int_gauge};
-use prometheus::{Counter, CounterVec, Histogram, HistogramVec, IntCounter, IntGauge};
+use prometheus::{register_counter_vec, register_gauge_vec, register_histogram_vec, register_int_counter, register_int_gauge, register_int_gauge_vec};
+use prometheus::{Counter, CounterVec, GaugeVec, Histogram, HistogramVec, IntCounter, IntGauge, IntGaugeVec};
 use prometheus::Encoder;
+use prometheus::core::Collector;
 use prometheus::TextEncoder;
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use tokio::sync::RwLock;
 
 use crate::config::data_model::Configuration;
+use crate::dns::cache::DnsCacheEvent;
+
+pub mod consumers;
+pub mod endpoint;
+pub mod fleet;
+pub mod latency;
+pub mod otlp;
+pub mod recent;
+pub mod statsd;
 
 /// MetricsCollector manages all the metrics collection for the Ferrum Gateway
 pub struct MetricsCollector {
@@ -35,6 +45,11 @@ lazy_static! {
         "Current number of active requests being processed"
     ).unwrap();
 
+    static ref PROXY_CONNECTIONS_ACTIVE: IntGauge = register_int_gauge!(
+        "ferrumgw_proxy_connections_active",
+        "Current number of open client connections on the HTTP and HTTPS listeners"
+    ).unwrap();
+
     // Request metrics by proxy
     static ref PROXY_REQUESTS_BY_PROXY: CounterVec = register_counter_vec!(
         "ferrumgw_proxy_requests_by_proxy",
@@ -49,6 +64,13 @@ lazy_static! {
         &["status_code"]
     ).unwrap();
 
+    // Status code metrics by proxy
+    static ref PROXY_RESPONSES: CounterVec = register_counter_vec!(
+        "ferrumgw_proxy_responses_total",
+        "Responses returned for each proxy, by status code",
+        &["proxy_id", "status_code"]
+    ).unwrap();
+
     // Latency metrics
     static ref PROXY_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
         "ferrumgw_proxy_request_duration_seconds",
@@ -68,13 +90,50 @@ lazy_static! {
     static ref AUTH_SUCCESSES: CounterVec = register_counter_vec!(
         "ferrumgw_auth_successes",
         "Number of successful authentications",
-        &["plugin_name"]
+        &["plugin_name", "proxy_id"]
     ).unwrap();
 
     static ref AUTH_FAILURES: CounterVec = register_counter_vec!(
         "ferrumgw_auth_failures",
-        "Number of failed authentications",
-        &["plugin_name"]
+        "Number of failed authentications, by reason: missing_credentials, invalid_credentials, unknown_consumer or validation_error",
+        &["plugin_name", "proxy_id", "reason"]
+    ).unwrap();
+
+    static ref AUTH_CACHE_LOOKUPS: CounterVec = register_counter_vec!(
+        "ferrumgw_auth_cache_lookups_total",
+        "Lookups in the token and JWKS caches of the auth plugins, by cache and result (hit or miss)",
+        &["cache", "result"]
+    ).unwrap();
+
+    // Rate limiting metrics
+    static ref RATE_LIMIT_REJECTIONS: CounterVec = register_counter_vec!(
+        "ferrumgw_rate_limit_rejections_total",
+        "Requests rejected by the rate limiting plugin, by the window whose limit was reached and whether the key was a consumer or an IP address",
+        &["proxy_id", "window", "key_type"]
+    ).unwrap();
+
+    static ref BANDWIDTH_THROTTLED: CounterVec = register_counter_vec!(
+        "ferrumgw_bandwidth_throttled_seconds_total",
+        "Time response bodies were held back by the bandwidth limiting plugin",
+        &["proxy_id"]
+    ).unwrap();
+
+    static ref LOAD_SHED_REQUESTS: CounterVec = register_counter_vec!(
+        "ferrumgw_load_shed_requests_total",
+        "Requests answered with 503 by load shedding, by the priority of the request",
+        &["proxy_id", "priority"]
+    ).unwrap();
+
+    static ref PROXY_RATE_CAPPED: CounterVec = register_counter_vec!(
+        "ferrumgw_proxy_rate_capped_total",
+        "Requests answered with 429 because their proxy reached its max_requests_per_second",
+        &["proxy_id"]
+    ).unwrap();
+
+    static ref LONG_LIVED_DRAINED: CounterVec = register_counter_vec!(
+        "ferrumgw_long_lived_connections_drained_total",
+        "WebSocket connections and event streams drained because a configuration update changed or deleted their proxy",
+        &["proxy_id", "kind"]
     ).unwrap();
 
     // Plugin metrics
@@ -84,6 +143,132 @@ lazy_static! {
         &["plugin_name", "hook_name"],
         vec![0.0001, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5]
     ).unwrap();
+
+    // Timeout metrics
+    static ref PROXY_TIMEOUTS: CounterVec = register_counter_vec!(
+        "ferrumgw_proxy_timeouts_total",
+        "Number of requests that hit a timeout, by reason",
+        &["proxy_id", "reason"]
+    ).unwrap();
+
+    // Upstream failure metrics
+    static ref UPSTREAM_ERRORS: CounterVec = register_counter_vec!(
+        "ferrumgw_upstream_errors_total",
+        "Number of failed backend requests, by error class",
+        &["proxy_id", "error_class"]
+    ).unwrap();
+
+    // Connection limit metrics
+    static ref CONNECTIONS_REJECTED: CounterVec = register_counter_vec!(
+        "ferrumgw_connections_rejected_total",
+        "Number of connections refused by the listener connection limits",
+        &["reason"]
+    ).unwrap();
+
+    // Workspace metrics
+    static ref WORKSPACE_REQUESTS: CounterVec = register_counter_vec!(
+        "ferrumgw_workspace_requests_total",
+        "Number of proxied requests by workspace and status code",
+        &["workspace", "status_code"]
+    ).unwrap();
+
+    // Configuration source metrics
+    static ref CONFIG_SOURCE_DEGRADED: IntGauge = register_int_gauge!(
+        "ferrumgw_config_source_degraded",
+        "1 while the database cannot be read and the last loaded configuration is served"
+    ).unwrap();
+
+    // Data Plane subscriber metrics of the Control Plane
+    static ref GRPC_SUBSCRIBER_LAG: IntGaugeVec = register_int_gauge_vec!(
+        "ferrumgw_grpc_subscriber_lag_versions",
+        "Configuration versions between the one queued for a Data Plane node and the one it should hold",
+        &["node_id"]
+    ).unwrap();
+
+    static ref GRPC_SUBSCRIBER_QUEUED: IntGaugeVec = register_int_gauge_vec!(
+        "ferrumgw_grpc_subscriber_queued_updates",
+        "Configuration updates queued for a Data Plane node and not yet sent",
+        &["node_id"]
+    ).unwrap();
+
+    static ref GRPC_COALESCED_UPDATES: CounterVec = register_counter_vec!(
+        "ferrumgw_grpc_coalesced_updates_total",
+        "Configuration updates held back from a Data Plane node whose queue was full, to be sent as one later",
+        &["node_id"]
+    ).unwrap();
+
+    // DNS cache metrics
+    static ref DNS_CACHE_HITS: IntCounter = register_int_counter!(
+        "ferrumgw_dns_cache_hits_total",
+        "DNS lookups answered from the cache, including cached failures"
+    ).unwrap();
+
+    static ref DNS_CACHE_MISSES: IntCounter = register_int_counter!(
+        "ferrumgw_dns_cache_misses_total",
+        "DNS lookups that had to ask a resolver"
+    ).unwrap();
+
+    static ref DNS_CACHE_EVICTIONS: IntCounter = register_int_counter!(
+        "ferrumgw_dns_cache_evictions_total",
+        "DNS cache entries removed because they expired or were invalidated"
+    ).unwrap();
+
+    static ref DNS_CACHE_STALE_SERVES: IntCounter = register_int_counter!(
+        "ferrumgw_dns_cache_stale_serves_total",
+        "DNS lookups answered from an expired entry because looking the name up again failed"
+    ).unwrap();
+
+    static ref DNS_CACHE_ENTRIES: IntGauge = register_int_gauge!(
+        "ferrumgw_dns_cache_entries",
+        "Hostnames whose addresses are in the DNS cache"
+    ).unwrap();
+
+    // Bandwidth metrics
+    static ref PROXY_BYTES: CounterVec = register_counter_vec!(
+        "ferrumgw_proxy_bytes_total",
+        "Body bytes of each proxy's requests and responses, by direction: client_received, backend_sent, backend_received or client_sent",
+        &["proxy_id", "direction"]
+    ).unwrap();
+
+    // Upstream connection metrics
+    static ref UPSTREAM_CONNECTIONS_ACTIVE: IntGaugeVec = register_int_gauge_vec!(
+        "ferrumgw_upstream_connections_active",
+        "Open connections to each backend host, in use or idle in the pool",
+        &["backend_host"]
+    ).unwrap();
+
+    static ref UPSTREAM_REQUESTS_IN_FLIGHT: IntGaugeVec = register_int_gauge_vec!(
+        "ferrumgw_upstream_requests_in_flight",
+        "Requests sent to each backend host that are waiting for its response",
+        &["backend_host"]
+    ).unwrap();
+
+    static ref UPSTREAM_POOL_SATURATION: GaugeVec = register_gauge_vec!(
+        "ferrumgw_upstream_pool_saturation",
+        "Requests in flight to each backend host over the idle connections the pool keeps for it",
+        &["backend_host"]
+    ).unwrap();
+
+    static ref UPSTREAM_CONNECT_DURATION: HistogramVec = register_histogram_vec!(
+        "ferrumgw_upstream_connect_duration_seconds",
+        "Time to open a TCP connection to each backend host",
+        &["backend_host"],
+        vec![0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]
+    ).unwrap();
+
+    static ref UPSTREAM_TLS_HANDSHAKE_DURATION: HistogramVec = register_histogram_vec!(
+        "ferrumgw_upstream_tls_handshake_duration_seconds",
+        "Time of the TLS handshake with each backend host",
+        &["backend_host"],
+        vec![0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]
+    ).unwrap();
+
+    static ref UPSTREAM_DNS_DURATION: HistogramVec = register_histogram_vec!(
+        "ferrumgw_upstream_dns_duration_seconds",
+        "Time to resolve each backend host when connecting, including DNS cache hits",
+        &["backend_host"],
+        vec![0.00001, 0.0001, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0]
+    ).unwrap();
 }
 
 impl MetricsCollector {
@@ -133,15 +318,6 @@ impl MetricsCollector {
         PROXY_BACKEND_DURATION.with_label_values(&[proxy_id]).observe(duration_seconds);
     }
     
-    // Track authentication result
-    pub fn track_auth_result(&self, plugin_name: &str, success: bool) {
-        if success {
-            AUTH_SUCCESSES.with_label_values(&[plugin_name]).inc();
-        } else {
-            AUTH_FAILURES.with_label_values(&[plugin_name]).inc();
-        }
-    }
-    
     // Track plugin execution duration
     pub fn track_plugin_execution(&self, plugin_name: &str, hook_name: &str, duration_ns: u64) {
         let duration_seconds = duration_ns as f64 / 1_000_000_000.0;
@@ -258,6 +434,297 @@ impl MetricsCollector {
     }
 }
 
+// Track a request that hit a timeout. Reasons are one of: client_header, client_body,
+// client_min_rate, request_total, backend_connect, backend_read. Timeouts that happen before a proxy
+// is matched are recorded with an empty proxy_id.
+pub fn track_timeout(proxy_id: &str, reason: &str) {
+    PROXY_TIMEOUTS.with_label_values(&[proxy_id, reason]).inc();
+}
+
+// Track a failed backend request. Error classes are one of: dns_failure, connect_refused,
+// connect_timeout, tls_failure, upstream_timeout, upstream_body_error, upstream_error.
+pub fn track_upstream_error(proxy_id: &str, error_class: &str) {
+    UPSTREAM_ERRORS.with_label_values(&[proxy_id, error_class]).inc();
+}
+
+// Track a connection refused by the listener limits (max_connections, max_connections_per_ip)
+pub fn track_connection_rejected(reason: &str) {
+    CONNECTIONS_REJECTED.with_label_values(&[reason]).inc();
+}
+
+// Track a response served by a proxy of the given workspace
+pub fn track_workspace_request(workspace: &str, status_code: u16) {
+    WORKSPACE_REQUESTS.with_label_values(&[workspace, &status_code.to_string()]).inc();
+}
+
+// Track a response returned for a proxy and how long the request took overall
+pub fn track_proxy_response(proxy_id: &str, status_code: u16, duration: Duration) {
+    let status_code = status_code.to_string();
+    PROXY_STATUS_CODES.with_label_values(&[&status_code]).inc();
+    PROXY_RESPONSES.with_label_values(&[proxy_id, &status_code]).inc();
+    PROXY_REQUEST_DURATION.with_label_values(&[proxy_id]).observe(duration.as_secs_f64());
+    
+    let tags = [("proxy", proxy_id), ("status_code", status_code.as_str())];
+    statsd::count("requests", 1, &tags);
+    statsd::timing("request.duration", duration, &tags);
+}
+
+// Track how long a plugin took to run one of its hooks for a request
+pub fn track_plugin_duration(plugin_name: &str, hook_name: &str, duration: Duration) {
+    PLUGIN_EXEC_DURATION.with_label_values(&[plugin_name, hook_name]).observe(duration.as_secs_f64());
+    statsd::timing("plugin.duration", duration, &[("plugin", plugin_name), ("hook", hook_name)]);
+}
+
+// Record how far a subscribed Data Plane node is behind and how many updates wait in its queue
+pub fn set_subscriber_lag(node_id: &str, lag_versions: u64, queued: usize) {
+    GRPC_SUBSCRIBER_LAG.with_label_values(&[node_id]).set(lag_versions as i64);
+    GRPC_SUBSCRIBER_QUEUED.with_label_values(&[node_id]).set(queued as i64);
+}
+
+// Track an update held back from a Data Plane node because its queue was full
+pub fn track_coalesced_update(node_id: &str) {
+    GRPC_COALESCED_UPDATES.with_label_values(&[node_id]).inc();
+}
+
+// Forget the subscriber metrics of a Data Plane node that disconnected
+pub fn remove_subscriber(node_id: &str) {
+    let _ = GRPC_SUBSCRIBER_LAG.remove_label_values(&[node_id]);
+    let _ = GRPC_SUBSCRIBER_QUEUED.remove_label_values(&[node_id]);
+    let _ = GRPC_COALESCED_UPDATES.remove_label_values(&[node_id]);
+}
+
+// Record a client connection accepted by the HTTP or HTTPS listener
+pub fn track_connection_opened() {
+    PROXY_CONNECTIONS_ACTIVE.inc();
+}
+
+// Record a client connection closing
+pub fn track_connection_closed() {
+    PROXY_CONNECTIONS_ACTIVE.dec();
+}
+
+// Client connections and proxied requests currently open
+pub fn active_counts() -> (i64, i64) {
+    (PROXY_CONNECTIONS_ACTIVE.get(), PROXY_REQUESTS_ACTIVE.get())
+}
+
+// Track how the DNS cache answered lookups, or entries it dropped
+pub fn track_dns_cache_events(event: DnsCacheEvent, count: u64) {
+    let counter = match event {
+        DnsCacheEvent::Hit => &*DNS_CACHE_HITS,
+        DnsCacheEvent::Miss => &*DNS_CACHE_MISSES,
+        DnsCacheEvent::Eviction => &*DNS_CACHE_EVICTIONS,
+        DnsCacheEvent::StaleServe => &*DNS_CACHE_STALE_SERVES,
+    };
+    counter.inc_by(count);
+}
+
+// Record how many hostnames the DNS cache holds
+pub fn set_dns_cache_entries(entries: usize) {
+    DNS_CACHE_ENTRIES.set(entries as i64);
+}
+
+// Track a request an auth plugin identified a consumer for
+pub fn track_auth_success(plugin_name: &str, proxy_id: &str) {
+    AUTH_SUCCESSES.with_label_values(&[plugin_name, proxy_id]).inc();
+}
+
+// Track a request an auth plugin could not identify a consumer for
+pub fn track_auth_failure(plugin_name: &str, proxy_id: &str, reason: &str) {
+    AUTH_FAILURES.with_label_values(&[plugin_name, proxy_id, reason]).inc();
+}
+
+// Track a lookup in a token or JWKS cache of an auth plugin
+pub fn track_auth_cache_lookup(cache: &str, hit: bool) {
+    AUTH_CACHE_LOOKUPS.with_label_values(&[cache, if hit { "hit" } else { "miss" }]).inc();
+}
+
+// Track a request the rate limiting plugin rejected
+pub fn track_rate_limit_rejection(proxy_id: &str, window: &str, key_type: &str) {
+    RATE_LIMIT_REJECTIONS.with_label_values(&[proxy_id, window, key_type]).inc();
+}
+
+// Track the time a response body was held back to stay within its bandwidth limit
+pub fn track_bandwidth_throttled(proxy_id: &str, held_back: Duration) {
+    BANDWIDTH_THROTTLED.with_label_values(&[proxy_id]).inc_by(held_back.as_secs_f64());
+}
+
+// Track a request shed under overload
+pub fn track_load_shed(proxy_id: &str, priority: &str) {
+    LOAD_SHED_REQUESTS.with_label_values(&[proxy_id, priority]).inc();
+}
+
+// Track a request beyond its proxy's request rate cap
+pub fn track_proxy_rate_capped(proxy_id: &str) {
+    PROXY_RATE_CAPPED.with_label_values(&[proxy_id]).inc();
+}
+
+// Track a long-lived connection drained after its proxy changed. Kinds are
+// websocket and event_stream
+pub fn track_long_lived_drained(proxy_id: &str, kind: &str) {
+    LONG_LIVED_DRAINED.with_label_values(&[proxy_id, kind]).inc();
+}
+
+// Track body bytes a proxy transferred. Directions are one of: client_received,
+// backend_sent, backend_received, client_sent.
+pub fn track_proxy_bytes(proxy_id: &str, direction: &str, bytes: u64) {
+    PROXY_BYTES.with_label_values(&[proxy_id, direction]).inc_by(bytes as f64);
+}
+
+// Track a connection opened to a backend host
+pub fn track_upstream_connection_opened(backend_host: &str) {
+    UPSTREAM_CONNECTIONS_ACTIVE.with_label_values(&[backend_host]).inc();
+}
+
+// Track a connection to a backend host that was closed
+pub fn track_upstream_connection_closed(backend_host: &str) {
+    UPSTREAM_CONNECTIONS_ACTIVE.with_label_values(&[backend_host]).dec();
+}
+
+// Track how long opening a TCP connection to a backend host took
+pub fn track_upstream_connect(backend_host: &str, duration: Duration) {
+    UPSTREAM_CONNECT_DURATION.with_label_values(&[backend_host]).observe(duration.as_secs_f64());
+}
+
+// Track how long the TLS handshake with a backend host took
+pub fn track_upstream_tls_handshake(backend_host: &str, duration: Duration) {
+    UPSTREAM_TLS_HANDSHAKE_DURATION.with_label_values(&[backend_host]).observe(duration.as_secs_f64());
+}
+
+// Track how long resolving a backend host for a new connection took
+pub fn track_upstream_dns_lookup(backend_host: &str, duration: Duration) {
+    UPSTREAM_DNS_DURATION.with_label_values(&[backend_host]).observe(duration.as_secs_f64());
+}
+
+/// A request waiting for a backend's response, counted in flight until dropped
+pub struct UpstreamRequest {
+    backend_host: String,
+    pool_size: usize,
+}
+
+impl UpstreamRequest {
+    fn update(&self, change: i64) {
+        let in_flight = UPSTREAM_REQUESTS_IN_FLIGHT.with_label_values(&[&self.backend_host]);
+        in_flight.add(change);
+        let saturation = in_flight.get() as f64 / self.pool_size.max(1) as f64;
+        UPSTREAM_POOL_SATURATION.with_label_values(&[&self.backend_host]).set(saturation);
+    }
+}
+
+impl Drop for UpstreamRequest {
+    fn drop(&mut self) {
+        self.update(-1);
+    }
+}
+
+// Track a request sent to a backend host whose client keeps `pool_size` idle connections
+pub fn start_upstream_request(backend_host: &str, pool_size: usize) -> UpstreamRequest {
+    let request = UpstreamRequest { backend_host: backend_host.to_string(), pool_size };
+    request.update(1);
+    request
+}
+
+// Record whether the gateway is serving its last loaded configuration because the database is down
+pub fn set_config_source_degraded(degraded: bool) {
+    CONFIG_SOURCE_DEGRADED.set(degraded as i64);
+}
+
+// Responses returned since startup, and how many of them were 5xx errors
+pub fn response_counts() -> (u64, u64) {
+    let mut total = 0.0;
+    let mut server_errors = 0.0;
+    for family in PROXY_STATUS_CODES.collect() {
+        for metric in family.get_metric() {
+            let count = metric.get_counter().get_value();
+            total += count;
+            if metric.get_label().iter().any(|label| label.get_value().starts_with('5')) {
+                server_errors += count;
+            }
+        }
+    }
+    (total as u64, server_errors as u64)
+}
+
+/// Traffic of one proxy since startup, or between two readings
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProxyTraffic {
+    pub requests: u64,
+    /// Upper bound in seconds and cumulative count of each request duration bucket
+    pub latency_buckets: Vec<(f64, u64)>,
+    pub status_codes: BTreeMap<String, u64>,
+}
+
+impl ProxyTraffic {
+    /// The traffic between an earlier reading and this one
+    pub fn since(&self, earlier: &ProxyTraffic) -> ProxyTraffic {
+        let earlier_bucket = |upper: f64| earlier.latency_buckets.iter()
+            .find(|(bound, _)| *bound == upper)
+            .map(|(_, count)| *count)
+            .unwrap_or(0);
+        ProxyTraffic {
+            requests: self.requests.saturating_sub(earlier.requests),
+            latency_buckets: self.latency_buckets.iter()
+                .map(|(upper, count)| (*upper, count.saturating_sub(earlier_bucket(*upper))))
+                .collect(),
+            status_codes: self.status_codes.iter()
+                .map(|(code, count)| (code.clone(), count.saturating_sub(earlier.status_codes.get(code).copied().unwrap_or(0))))
+                .filter(|(_, count)| *count > 0)
+                .collect(),
+        }
+    }
+
+    /// Estimated quantile of the request duration in seconds, interpolated
+    /// linearly within its bucket as Prometheus' histogram_quantile does.
+    /// Durations beyond the last bucket are reported as its upper bound.
+    pub fn latency_quantile(&self, quantile: f64) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        let rank = quantile * self.requests as f64;
+        let (mut lower, mut below) = (0.0, 0u64);
+        for (upper, count) in &self.latency_buckets {
+            if *count as f64 >= rank {
+                let in_bucket = (*count - below) as f64;
+                if in_bucket == 0.0 {
+                    return *upper;
+                }
+                return lower + (upper - lower) * (rank - below as f64) / in_bucket;
+            }
+            (lower, below) = (*upper, *count);
+        }
+        lower
+    }
+}
+
+// Traffic of every proxy that served requests since startup, by proxy ID
+pub fn proxy_traffic() -> HashMap<String, ProxyTraffic> {
+    let mut traffic: HashMap<String, ProxyTraffic> = HashMap::new();
+    let label = |metric: &prometheus::proto::Metric, name: &str| metric.get_label().iter()
+        .find(|label| label.get_name() == name)
+        .map(|label| label.get_value().to_string())
+        .unwrap_or_default();
+
+    for family in PROXY_REQUEST_DURATION.collect() {
+        for metric in family.get_metric() {
+            let histogram = metric.get_histogram();
+            let proxy = traffic.entry(label(metric, "proxy_id")).or_default();
+            proxy.requests = histogram.get_sample_count();
+            proxy.latency_buckets = histogram.get_bucket().iter()
+                .map(|bucket| (bucket.get_upper_bound(), bucket.get_cumulative_count()))
+                .collect();
+        }
+    }
+    for family in PROXY_RESPONSES.collect() {
+        for metric in family.get_metric() {
+            traffic.entry(label(metric, "proxy_id"))
+                .or_default()
+                .status_codes
+                .insert(label(metric, "status_code"), metric.get_counter().get_value() as u64);
+        }
+    }
+    traffic
+}
+
 // Start a background task to periodically update RPS metrics
 pub async fn start_metrics_updater(metrics: Arc<MetricsCollector>) {
     tokio::spawn(async move {
@@ -0,0 +1,210 @@
+//! Export of the gateway's metrics to an OpenTelemetry collector
+
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use hyper::client::HttpConnector;
+use hyper::{Body, Method, Request};
+use hyper_tls::HttpsConnector;
+use prometheus::proto::{LabelPair, Metric, MetricFamily, MetricType};
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+use crate::config::env_config::EnvConfig;
+use crate::health;
+
+/// Longest an export request may take
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// OTLP `AGGREGATION_TEMPORALITY_CUMULATIVE`
+const CUMULATIVE: u8 = 2;
+
+/// Where and how often the metrics are exported
+#[derive(Debug, Clone)]
+pub struct OtlpSettings {
+    pub endpoint: String,
+    pub interval: Duration,
+    pub batch_size: usize,
+    pub headers: Vec<(String, String)>,
+}
+
+impl OtlpSettings {
+    /// The export settings, or None when no endpoint is configured
+    pub fn from_env_config(env_config: &EnvConfig) -> Option<Self> {
+        env_config.otlp_metrics_endpoint.as_ref().map(|endpoint| Self {
+            endpoint: endpoint.clone(),
+            interval: env_config.otlp_metrics_interval,
+            batch_size: env_config.otlp_metrics_batch_size,
+            headers: env_config.otlp_metrics_headers.clone(),
+        })
+    }
+}
+
+/// Starts exporting the metrics when an OTLP endpoint is configured
+pub fn start(env_config: &EnvConfig) {
+    if let Some(settings) = OtlpSettings::from_env_config(env_config) {
+        info!("Exporting metrics to {} every {}s", settings.endpoint, settings.interval.as_secs());
+        tokio::spawn(export_loop(settings));
+    }
+}
+
+async fn export_loop(settings: OtlpSettings) {
+    let client: hyper::Client<HttpsConnector<HttpConnector>> = hyper::Client::builder().build(HttpsConnector::new());
+    let mut interval = tokio::time::interval(settings.interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick completes at once; the first export waits an interval
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+        let batches = encode(&prometheus::gather(), health::started_at(), Utc::now(), settings.batch_size);
+        for batch in batches {
+            match tokio::time::timeout(EXPORT_TIMEOUT, send(&client, &settings, batch)).await {
+                Ok(Ok(())) => {},
+                Ok(Err(e)) => warn!("Failed to export metrics to {}: {:#}", settings.endpoint, e),
+                Err(_) => warn!("Exporting metrics to {} timed out", settings.endpoint),
+            }
+        }
+        debug!("Exported metrics to {}", settings.endpoint);
+    }
+}
+
+async fn send(client: &hyper::Client<HttpsConnector<HttpConnector>>, settings: &OtlpSettings, batch: Value) -> Result<()> {
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri(&settings.endpoint)
+        .header("Content-Type", "application/json");
+    for (name, value) in &settings.headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    let request = request.body(Body::from(batch.to_string()))?;
+
+    let response = client.request(request).await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap_or_default();
+        return Err(anyhow!("collector answered {}: {}", status, String::from_utf8_lossy(&body)));
+    }
+    Ok(())
+}
+
+/// Encodes gathered metric families as OTLP `ExportMetricsServiceRequest`
+/// JSON documents of at most `batch_size` metrics each
+pub fn encode(families: &[MetricFamily], started_at: DateTime<Utc>, now: DateTime<Utc>, batch_size: usize) -> Vec<Value> {
+    let start = unix_nanos(started_at);
+    let time = unix_nanos(now);
+    let metrics: Vec<Value> = families.iter()
+        .filter_map(|family| encode_family(family, &start, &time))
+        .collect();
+
+    metrics.chunks(batch_size.max(1))
+        .map(|metrics| json!({
+            "resourceMetrics": [{
+                "resource": {
+                    "attributes": [
+                        attribute("service.name", "ferrumgw"),
+                        attribute("service.version", env!("CARGO_PKG_VERSION")),
+                    ],
+                },
+                "scopeMetrics": [{
+                    "scope": { "name": "ferrumgw", "version": env!("CARGO_PKG_VERSION") },
+                    "metrics": metrics,
+                }],
+            }],
+        }))
+        .collect()
+}
+
+fn encode_family(family: &MetricFamily, start: &str, time: &str) -> Option<Value> {
+    let points = |value: &dyn Fn(&Metric) -> Value| -> Vec<Value> {
+        family.get_metric().iter()
+            .map(|metric| {
+                let mut point = value(metric);
+                point["attributes"] = attributes(metric.get_label());
+                point["startTimeUnixNano"] = json!(start);
+                point["timeUnixNano"] = json!(time);
+                point
+            })
+            .collect()
+    };
+
+    let data = match family.get_field_type() {
+        MetricType::COUNTER => json!({
+            "sum": {
+                "dataPoints": points(&|metric| json!({ "asDouble": metric.get_counter().get_value() })),
+                "aggregationTemporality": CUMULATIVE,
+                "isMonotonic": true,
+            },
+        }),
+        MetricType::GAUGE | MetricType::UNTYPED => json!({
+            "gauge": {
+                "dataPoints": points(&|metric| json!({ "asDouble": gauge_value(family.get_field_type(), metric) })),
+            },
+        }),
+        MetricType::HISTOGRAM => json!({
+            "histogram": {
+                "dataPoints": points(&histogram_point),
+                "aggregationTemporality": CUMULATIVE,
+            },
+        }),
+        // The gateway registers no summaries
+        MetricType::SUMMARY => return None,
+    };
+
+    let mut metric = json!({
+        "name": family.get_name(),
+        "description": family.get_help(),
+    });
+    if let (Value::Object(metric), Value::Object(data)) = (&mut metric, data) {
+        metric.extend(data);
+    }
+    Some(metric)
+}
+
+fn gauge_value(metric_type: MetricType, metric: &Metric) -> f64 {
+    match metric_type {
+        MetricType::UNTYPED => metric.get_untyped().get_value(),
+        _ => metric.get_gauge().get_value(),
+    }
+}
+
+/// A histogram data point. Prometheus counts each bucket cumulatively and
+/// leaves out +Inf, while OTLP counts each bucket on its own and ends with
+/// the overflow bucket above the last bound.
+fn histogram_point(metric: &Metric) -> Value {
+    let histogram = metric.get_histogram();
+    let mut bounds = Vec::new();
+    let mut counts = Vec::new();
+    let mut below = 0;
+    for bucket in histogram.get_bucket() {
+        if bucket.get_upper_bound().is_infinite() {
+            continue;
+        }
+        bounds.push(bucket.get_upper_bound());
+        counts.push(bucket.get_cumulative_count().saturating_sub(below).to_string());
+        below = bucket.get_cumulative_count();
+    }
+    counts.push(histogram.get_sample_count().saturating_sub(below).to_string());
+
+    json!({
+        "count": histogram.get_sample_count().to_string(),
+        "sum": histogram.get_sample_sum(),
+        "bucketCounts": counts,
+        "explicitBounds": bounds,
+    })
+}
+
+fn attributes(labels: &[LabelPair]) -> Value {
+    labels.iter()
+        .map(|label| attribute(label.get_name(), label.get_value()))
+        .collect()
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+/// OTLP encodes 64-bit integers as JSON strings
+fn unix_nanos(time: DateTime<Utc>) -> String {
+    time.timestamp_nanos_opt().unwrap_or_default().max(0).to_string()
+}
@@ -0,0 +1,143 @@
+//! Traffic of each proxy and consumer over the last minute
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use lazy_static::lazy_static;
+
+use super::ProxyTraffic;
+
+/// Seconds of traffic kept
+pub const WINDOW_SECONDS: u64 = 60;
+
+/// Upper bounds in seconds of the latency buckets, as in the request duration histogram
+const LATENCY_BOUNDS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+lazy_static! {
+    static ref STARTED: Instant = Instant::now();
+    static ref RECENT: Mutex<RecentMetrics> = Mutex::new(RecentMetrics::default());
+}
+
+/// Responses of one second
+#[derive(Debug, Clone, Default)]
+struct Slot {
+    second: u64,
+    requests: u64,
+    /// Requests per latency bucket, not cumulative
+    latency_buckets: [u64; LATENCY_BOUNDS.len()],
+    status_codes: BTreeMap<u16, u64>,
+}
+
+/// The last minute of one proxy's or consumer's traffic
+#[derive(Debug, Clone)]
+pub struct RecentTraffic {
+    slots: Vec<Slot>,
+    last_second: u64,
+}
+
+impl Default for RecentTraffic {
+    fn default() -> Self {
+        Self {
+            slots: vec![Slot::default(); WINDOW_SECONDS as usize],
+            last_second: 0,
+        }
+    }
+}
+
+impl RecentTraffic {
+    pub fn record(&mut self, second: u64, status_code: u16, duration: Duration) {
+        let slot = &mut self.slots[(second % WINDOW_SECONDS) as usize];
+        if slot.second != second || slot.requests == 0 {
+            *slot = Slot { second, ..Default::default() };
+        }
+        slot.requests += 1;
+        *slot.status_codes.entry(status_code).or_default() += 1;
+        let seconds = duration.as_secs_f64();
+        if let Some(bucket) = LATENCY_BOUNDS.iter().position(|bound| seconds <= *bound) {
+            slot.latency_buckets[bucket] += 1;
+        }
+        self.last_second = self.last_second.max(second);
+    }
+
+    fn is_idle(&self, second: u64) -> bool {
+        second.saturating_sub(self.last_second) >= WINDOW_SECONDS
+    }
+
+    /// The traffic of the window ending at `second`, with cumulative latency
+    /// buckets so quantiles can be estimated from it
+    pub fn window(&self, second: u64) -> ProxyTraffic {
+        let mut traffic = ProxyTraffic::default();
+        let mut buckets = [0u64; LATENCY_BOUNDS.len()];
+        for slot in &self.slots {
+            if slot.requests == 0 || slot.second > second || second - slot.second >= WINDOW_SECONDS {
+                continue;
+            }
+            traffic.requests += slot.requests;
+            for (total, count) in buckets.iter_mut().zip(slot.latency_buckets) {
+                *total += count;
+            }
+            for (code, count) in &slot.status_codes {
+                *traffic.status_codes.entry(code.to_string()).or_default() += count;
+            }
+        }
+        let mut cumulative = 0;
+        traffic.latency_buckets = LATENCY_BOUNDS.iter().zip(buckets)
+            .map(|(bound, count)| {
+                cumulative += count;
+                (*bound, cumulative)
+            })
+            .collect();
+        traffic
+    }
+}
+
+/// The recent traffic of every proxy and consumer that served requests in the last minute
+#[derive(Debug, Default)]
+pub struct RecentMetrics {
+    proxies: HashMap<String, RecentTraffic>,
+    consumers: HashMap<String, RecentTraffic>,
+    pruned_at: u64,
+}
+
+impl RecentMetrics {
+    pub fn record(&mut self, second: u64, proxy_id: &str, consumer_id: Option<&str>, status_code: u16, duration: Duration) {
+        // Routes and consumers without traffic for a minute are forgotten
+        if second != self.pruned_at {
+            self.proxies.retain(|_, traffic| !traffic.is_idle(second));
+            self.consumers.retain(|_, traffic| !traffic.is_idle(second));
+            self.pruned_at = second;
+        }
+
+        self.proxies.entry(proxy_id.to_string()).or_default().record(second, status_code, duration);
+        if let Some(consumer_id) = consumer_id {
+            self.consumers.entry(consumer_id.to_string()).or_default().record(second, status_code, duration);
+        }
+    }
+
+    pub fn proxy(&self, proxy_id: &str, second: u64) -> ProxyTraffic {
+        self.proxies.get(proxy_id).map(|traffic| traffic.window(second)).unwrap_or_default()
+    }
+
+    pub fn consumer(&self, consumer_id: &str, second: u64) -> ProxyTraffic {
+        self.consumers.get(consumer_id).map(|traffic| traffic.window(second)).unwrap_or_default()
+    }
+}
+
+fn current_second() -> u64 {
+    STARTED.elapsed().as_secs()
+}
+
+// Track a response for the recent traffic of its proxy and authenticated consumer
+pub fn record(proxy_id: &str, consumer_id: Option<&str>, status_code: u16, duration: Duration) {
+    RECENT.lock().unwrap().record(current_second(), proxy_id, consumer_id, status_code, duration);
+}
+
+// Traffic of a proxy over the last minute
+pub fn proxy(proxy_id: &str) -> ProxyTraffic {
+    RECENT.lock().unwrap().proxy(proxy_id, current_second())
+}
+
+// Traffic of a consumer over the last minute
+pub fn consumer(consumer_id: &str) -> ProxyTraffic {
+    RECENT.lock().unwrap().consumer(consumer_id, current_second())
+}
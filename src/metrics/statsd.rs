@@ -0,0 +1,129 @@
+//! StatsD and DogStatsD sink
+
+use std::net::UdpSocket;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{Context, Result};
+use arc_swap::ArcSwapOption;
+use tracing::info;
+
+use crate::config::env_config::EnvConfig;
+
+static SINK: ArcSwapOption<StatsdSink> = ArcSwapOption::const_empty();
+
+/// Line format of the datagrams
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsdFormat {
+    /// Plain StatsD, which has no tags
+    Statsd,
+    /// DogStatsD, with tags after `|#`
+    DogStatsd,
+}
+
+impl StatsdFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "statsd" => Some(StatsdFormat::Statsd),
+            "dogstatsd" | "datadog" => Some(StatsdFormat::DogStatsd),
+            _ => None,
+        }
+    }
+}
+
+/// Where the metrics are sent and how they are named
+#[derive(Debug, Clone)]
+pub struct StatsdSettings {
+    /// host:port of the StatsD server
+    pub addr: String,
+    /// Prepended to every metric name, joined with a dot
+    pub prefix: String,
+    pub format: StatsdFormat,
+    /// Tags added to every metric, as `name:value` or bare names
+    pub tags: Vec<String>,
+}
+
+impl StatsdSettings {
+    /// The sink settings, or None when no StatsD address is configured
+    pub fn from_env_config(env_config: &EnvConfig) -> Option<Self> {
+        env_config.statsd_addr.as_ref().map(|addr| Self {
+            addr: addr.clone(),
+            prefix: env_config.statsd_prefix.clone(),
+            format: env_config.statsd_format,
+            tags: env_config.statsd_tags.clone(),
+        })
+    }
+}
+
+struct StatsdSink {
+    socket: UdpSocket,
+    settings: StatsdSettings,
+}
+
+impl StatsdSink {
+    fn send(&self, name: &str, value: &str, kind: &str, tags: &[(&str, &str)]) {
+        let line = format_line(&self.settings, name, value, kind, tags);
+        let _ = self.socket.send(line.as_bytes());
+    }
+}
+
+/// Formats one metric line, such as `ferrumgw.requests:1|c|#proxy:orders`
+pub fn format_line(settings: &StatsdSettings, name: &str, value: &str, kind: &str, tags: &[(&str, &str)]) -> String {
+    let mut line = if settings.prefix.is_empty() {
+        format!("{}:{}|{}", name, value, kind)
+    } else {
+        format!("{}.{}:{}|{}", settings.prefix, name, value, kind)
+    };
+
+    if settings.format == StatsdFormat::DogStatsd && (!settings.tags.is_empty() || !tags.is_empty()) {
+        let tags = settings.tags.iter()
+            .cloned()
+            .chain(tags.iter().map(|(name, value)| format!("{}:{}", name, sanitize(value))));
+        line.push_str("|#");
+        line.push_str(&tags.collect::<Vec<_>>().join(","));
+    }
+    line
+}
+
+/// Tag values cannot contain the separators of the line format
+fn sanitize(value: &str) -> String {
+    value.replace([',', '|', '#', '\n'], "_")
+}
+
+/// Starts sending metrics when a StatsD address is configured. Fails when the
+/// address does not resolve.
+pub fn start(env_config: &EnvConfig) -> Result<()> {
+    match StatsdSettings::from_env_config(env_config) {
+        Some(settings) => configure(settings),
+        None => Ok(()),
+    }
+}
+
+/// Sends metrics to the given server from now on, instead of any configured before
+pub fn configure(settings: StatsdSettings) -> Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:0").context("failed to open a UDP socket for StatsD")?;
+    socket.connect(&settings.addr).with_context(|| format!("failed to resolve StatsD address {}", settings.addr))?;
+    socket.set_nonblocking(true)?;
+
+    info!("Sending metrics to StatsD at {}", settings.addr);
+    SINK.store(Some(Arc::new(StatsdSink { socket, settings })));
+    Ok(())
+}
+
+/// Stops sending metrics
+pub fn disable() {
+    SINK.store(None);
+}
+
+/// Adds to a counter
+pub fn count(name: &str, value: u64, tags: &[(&str, &str)]) {
+    if let Some(sink) = SINK.load().as_ref() {
+        sink.send(name, &value.to_string(), "c", tags);
+    }
+}
+
+/// Records a duration in milliseconds
+pub fn timing(name: &str, duration: Duration, tags: &[(&str, &str)]) {
+    if let Some(sink) = SINK.load().as_ref() {
+        sink.send(name, &format!("{:.3}", duration.as_secs_f64() * 1000.0), "ms", tags);
+    }
+}
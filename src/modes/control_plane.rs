@@ -6,14 +6,45 @@ use anyhow::{Result, Context};
 use tracing::{info, warn, error, debug};
 
 use crate::config::env_config::EnvConfig;
-use crate::config::data_model::Configuration;
-use crate::database::DatabaseClient;
+use crate::config::data_model::{AcmeChallengeType, Configuration};
+use crate::database::{DatabaseClient, DatabaseOptions};
 use crate::admin::AdminServer;
+use crate::modes::database::{apply_delta, notify_config_changed};
+use crate::proxy::ProxyServer;
 use crate::dns::{self, DnsCache}; // Add DNS module
+use crate::acme::AcmeManager;
+use crate::acme::storage::DatabaseAcmeStorage;
+use crate::grpc::security::{self, NodeAuthenticator};
+use crate::grpc::rollout::RolloutPolicy;
+use crate::grpc::transfer::TransferSettings;
+use crate::grpc::xds::AggregatedDiscovery;
+use crate::grpc::pinning::GroupPins;
+use crate::grpc::debug::NodeDebugger;
+use crate::health;
 use chrono::Utc;
 
 pub async fn run(config: EnvConfig) -> Result<()> {
     info!("Starting Ferrum Gateway in Control Plane mode");
+    serve(config, false).await
+}
+
+/// Hybrid mode: the control plane, which also proxies traffic itself with the
+/// configuration it serves to data plane nodes
+pub async fn run_hybrid(config: EnvConfig) -> Result<()> {
+    info!("Starting Ferrum Gateway in Hybrid mode");
+    check_proxy_listeners(&config)?;
+    serve(config, true).await
+}
+
+/// Hybrid mode proxies traffic, so it needs at least one proxy listener
+pub fn check_proxy_listeners(config: &EnvConfig) -> Result<()> {
+    if config.proxy_http_port.is_none() && config.proxy_https_port.is_none() {
+        anyhow::bail!("Hybrid mode needs FERRUM_PROXY_HTTP_PORT or FERRUM_PROXY_HTTPS_PORT");
+    }
+    Ok(())
+}
+
+async fn serve(config: EnvConfig, serve_traffic: bool) -> Result<()> {
     
     // Get database configuration
     let db_type_str = config.db_type.clone().context("Database type must be set in Control Plane mode")?;
@@ -24,25 +55,38 @@ pub async fn run(config: EnvConfig) -> Result<()> {
         crate::config::data_model::DatabaseType::Postgres => crate::database::DatabaseType::Postgres,
         crate::config::data_model::DatabaseType::MySQL => crate::database::DatabaseType::MySQL,
         crate::config::data_model::DatabaseType::SQLite => crate::database::DatabaseType::SQLite,
+        crate::config::data_model::DatabaseType::MongoDB => crate::database::DatabaseType::MongoDB,
     };
     
     // Set up database client
-    let db_client = DatabaseClient::new(db_type, &db_url)
+    let db_client = DatabaseClient::with_options(db_type, &db_url, &DatabaseOptions::from_env_config(&config))
         .await
         .context("Failed to create database client")?;
     
+    // Refuse to run against a schema this version does not match
+    db_client.check_schema(config.db_auto_migrate).await?;
+    
+    // Purge tombstones that incremental polling no longer needs
+    let _tombstone_cleanup = db_client.spawn_tombstone_cleanup();
+    
     // Get DNS cache configuration
     let dns_ttl = config.dns_cache_ttl_seconds;
     let dns_overrides = config.dns_overrides.clone();
     
     // Create DNS cache - Control Plane can benefit from DNS caching for health checks
-    let dns_cache = Arc::new(DnsCache::new(dns_ttl, dns_overrides));
+    let dns_cache = Arc::new(DnsCache::new(dns_ttl, dns_overrides)
+        .with_min_ttl(config.dns_min_ttl)
+        .with_negative_ttl(config.dns_negative_ttl)
+        .with_stale_ttl(config.dns_stale_ttl)
+        .with_resolvers(&config.dns_resolvers)?);
     
     // Create shared configuration
     let shared_config = Arc::new(RwLock::new(Configuration {
         proxies: Vec::new(),
         consumers: Vec::new(),
         plugin_configs: Vec::new(),
+        certificates: Vec::new(),
+        snis: Vec::new(),
         last_updated_at: Utc::now(),
     }));
     
@@ -52,6 +96,7 @@ pub async fn run(config: EnvConfig) -> Result<()> {
         .context("Failed to load initial configuration from database")?;
     
     *shared_config.write().await = initial_config.clone();
+    health::record_config_loaded();
     
     // Warm up DNS cache for health checks and service discovery
     if !initial_config.proxies.is_empty() {
@@ -69,6 +114,52 @@ pub async fn run(config: EnvConfig) -> Result<()> {
         );
     }
     
+    // In hybrid mode the node proxies requests with the same configuration
+    let update_manager = if serve_traffic {
+        info!("Starting proxy server");
+        let proxy_server = ProxyServer::new(
+            config.clone(),
+            Arc::clone(&shared_config),
+            Arc::clone(&dns_cache),
+        )?;
+        let update_manager = proxy_server.get_update_manager();
+        
+        tokio::spawn(async move {
+            if let Err(e) = proxy_server.start().await {
+                error!("Proxy server error: {}", e);
+            }
+        });
+        
+        Some(update_manager)
+    } else {
+        None
+    };
+    
+    // Start ACME certificate management. A plain control plane has no proxy
+    // listener to answer HTTP-01 challenges from, so only DNS-01 is supported.
+    let _acme_handle = if config.acme_enabled {
+        if !serve_traffic && config.acme_challenge != AcmeChallengeType::Dns01 {
+            anyhow::bail!("FERRUM_ACME_CHALLENGE must be dns-01 in Control Plane mode");
+        }
+        let storage = DatabaseAcmeStorage::new(db_client.clone(), &config.acme_storage_path)
+            .context("Failed to initialize ACME storage")?;
+        Some(AcmeManager::new(&config, Arc::new(storage)).spawn())
+    } else {
+        None
+    };
+    
+    // Pin the Data Plane groups configured at startup to their versions
+    let group_pins = Arc::new(GroupPins::new());
+    for (group, version_id) in &config.cp_group_pins {
+        let version = db_client.get_config_version(Some(*version_id))
+            .await?
+            .with_context(|| format!("Configuration version {} pinned for group {} not found", version_id, group))?;
+        group_pins.pin(group, *version_id, version.config.unwrap_or_default(), "FERRUM_CP_GROUP_PINS");
+        info!("Data Plane group {} pinned to configuration version {}", group, version_id);
+    }
+    
+    let node_debugger = Arc::new(NodeDebugger::new());
+    
     // Start admin server
     info!("Starting admin server");
     let admin_jwt_secret = config.admin_jwt_secret.clone()
@@ -79,7 +170,9 @@ pub async fn run(config: EnvConfig) -> Result<()> {
         Arc::clone(&shared_config),
         db_client.clone(),
         admin_jwt_secret,
-    )?;
+    )?
+    .with_group_pins(Arc::clone(&group_pins))
+    .with_node_debugger(Arc::clone(&node_debugger));
     
     let admin_handle = tokio::spawn(async move {
         if let Err(e) = admin_server.start().await {
@@ -89,17 +182,30 @@ pub async fn run(config: EnvConfig) -> Result<()> {
     
     // Start gRPC server for Data Plane nodes
     info!("Starting gRPC server for Data Plane nodes");
-    let cp_grpc_jwt_secret = config.cp_grpc_jwt_secret.clone()
-        .context("CP gRPC JWT secret must be set in Control Plane mode")?;
+    let node_auth = NodeAuthenticator::from_env_config(&config);
+    let grpc_tls = security::server_tls_config(&config)
+        .context("Failed to load the CP gRPC TLS certificates")?;
+    if grpc_tls.is_none() {
+        warn!("The gRPC server for Data Plane nodes is not using TLS, set FERRUM_CP_GRPC_TLS_CERT_PATH and FERRUM_CP_GRPC_TLS_KEY_PATH to encrypt it");
+    }
     
     let cp_grpc_listen_addr = config.cp_grpc_listen_addr
         .context("CP gRPC listen address must be set in Control Plane mode")?;
+    health::record_listener("cp_grpc", cp_grpc_listen_addr);
     
-    let grpc_server = crate::modes::control_plane::grpc::GrpcServer::new(
+    let mut grpc_server = crate::modes::control_plane::grpc::GrpcServer::new(
         cp_grpc_listen_addr,
-        cp_grpc_jwt_secret,
+        node_auth.clone(),
+        grpc_tls,
+        RolloutPolicy::from_env_config(&config),
+        TransferSettings::from_env_config(&config),
         Arc::clone(&shared_config),
-    )?;
+    )?
+    .with_group_pins(group_pins)
+    .with_node_debugger(node_debugger);
+    if config.cp_xds_enabled {
+        grpc_server = grpc_server.with_xds(AggregatedDiscovery::new(config.cp_xds_listener_addr, node_auth));
+    }
     
     let grpc_handle = tokio::spawn(async move {
         if let Err(e) = grpc_server.start().await {
@@ -111,7 +217,6 @@ pub async fn run(config: EnvConfig) -> Result<()> {
     let poll_interval = config.db_poll_interval;
     let poll_check_interval = config.db_poll_check_interval;
     let use_incremental_polling = config.db_incremental_polling;
-    let dns_cache_for_polling: Arc<crate::dns::cache::DnsCache> = Arc::clone(&dns_cache);
     let config_service_clone = Arc::clone(&shared_config);
     
     let _polling_handle = tokio::spawn(async move {
@@ -142,59 +247,21 @@ pub async fn run(config: EnvConfig) -> Result<()> {
                                                     delta.updated_consumers.len() + delta.deleted_consumer_ids.len(),
                                                     delta.updated_plugin_configs.len() + delta.deleted_plugin_config_ids.len());
                                                 
-                                                // Apply the delta to the shared configuration
-                                                {
-                                                    let mut config = config_service_clone.write().await;
-                                                    delta.apply_to(&mut *config);
+                                                // Apply the delta to the shared configuration as one update
+                                                if let Err(e) = apply_delta(&delta, &config_service_clone, update_manager.as_deref()).await {
+                                                    error!("{}", e);
+                                                    
+                                                    // Fallback to full config load on next poll
+                                                    poll_timer.reset();
+                                                    continue;
                                                 }
                                                 
-                                                // Convert the delta to a proto delta for data plane subscribers
-                                                // This requires building a ConfigDelta proto from our delta struct
-                                                let proto_delta = crate::proto::ConfigDelta {
-                                                    upsert_proxies: delta.updated_proxies.iter().map(crate::proto::Proxy::from).collect(),
-                                                    remove_proxy_ids: delta.deleted_proxy_ids.clone(),
-                                                    upsert_consumers: delta.updated_consumers.iter().map(crate::proto::Consumer::from).collect(),
-                                                    remove_consumer_ids: delta.deleted_consumer_ids.clone(),
-                                                    upsert_plugin_configs: delta.updated_plugin_configs.iter().map(crate::proto::PluginConfig::from).collect(),
-                                                    remove_plugin_config_ids: delta.deleted_plugin_config_ids.clone(),
-                                                };
-                                                
-                                                // Create a ConfigUpdate with delta
-                                                let update = crate::proto::ConfigUpdate {
-                                                    update_type: crate::proto::UpdateType::Delta as i32,
-                                                    version: last_update_timestamp.timestamp_millis(),
-                                                    updated_at: delta.last_updated_at.to_rfc3339(),
-                                                    update: Some(crate::proto::config_update::Update::Delta(proto_delta)),
-                                                };
-                                                
-                                                // Send the delta update to all DP subscribers
-                                                if let Err(e) = grpc_server.push_config_update(update).await {
-                                                    error!("Failed to push delta config update to subscribers: {}", e);
-                                                }
+                                                // The gRPC server sends data plane nodes what changed
+                                                // since the snapshot it last sent them
                                                 
                                                 // Update our tracking timestamp
                                                 last_update_timestamp = delta.last_updated_at;
                                                 
-                                                // Check for new backend hosts that need DNS resolution
-                                                let new_hosts = delta.updated_proxies.iter()
-                                                    .filter(|p| p.dns_override.is_none())
-                                                    .map(|p| p.backend_host.clone())
-                                                    .collect::<Vec<_>>();
-                                                
-                                                if !new_hosts.is_empty() {
-                                                    // Warm up DNS cache for new hosts in background
-                                                    for hostname in new_hosts {
-                                                        let dns_cache: Arc<crate::dns::cache::DnsCache> = Arc::clone(&dns_cache_for_polling);
-                                                        tokio::spawn(async move {
-                                                            if let Err(e) = dns_cache.resolve(&hostname).await {
-                                                                warn!("DNS warmup failed for host {}: {}", hostname, e);
-                                                            } else {
-                                                                debug!("DNS warmup successful for host {}", hostname);
-                                                            }
-                                                        });
-                                                    }
-                                                }
-                                                
                                                 info!("Configuration updated and propagated successfully using incremental update");
                                             } else {
                                                 debug!("Incremental update returned empty delta");
@@ -243,31 +310,14 @@ pub async fn run(config: EnvConfig) -> Result<()> {
                                     let mut config = config_service_clone.write().await;
                                     *config = new_config.clone();
                                 }
+                                notify_config_changed(update_manager.as_deref());
                                 
-                                // Create full snapshot for data plane nodes
-                                let snapshot = crate::proto::ConfigSnapshot::from(&new_config);
-                                
-                                // Create a config update with full snapshot
-                                let update = crate::proto::ConfigUpdate {
-                                    update_type: crate::proto::UpdateType::Full as i32,
-                                    version: new_config.last_updated_at.timestamp_millis(),
-                                    updated_at: new_config.last_updated_at.to_rfc3339(),
-                                    update: Some(crate::proto::config_update::Update::FullSnapshot(snapshot)),
-                                };
-                                
-                                // Push full config update to subscribers
-                                if let Err(e) = grpc_server.push_config_update(update).await {
-                                    error!("Failed to push full config update to subscribers: {}", e);
-                                }
+                                // The gRPC server sends data plane nodes only the entities
+                                // that changed, after a full reload too
                                 
                                 // Update our tracking timestamp
                                 last_update_timestamp = new_config.last_updated_at;
                                 
-                                // After updating the configuration, warm up DNS cache for any new hosts
-                                if let Err(e) = dns::warm_up_dns_cache(&dns_cache_for_polling, &new_config.proxies).await {
-                                    warn!("DNS cache warmup failed: {}", e);
-                                }
-                                
                                 info!("Configuration updated and propagated successfully with full refresh");
                             } else {
                                 debug!("Full configuration refresh found no changes");
@@ -283,15 +333,17 @@ pub async fn run(config: EnvConfig) -> Result<()> {
     });
     
     // Wait for shutdown signal
-    tokio::spawn(async {
-        let (_tx, _rx) = tokio::sync::oneshot::channel::<()>();
-        let _ = _rx.await;
-        info!("Received shutdown signal, stopping Control Plane");
-        // Implement graceful shutdown logic here
-    });
+    tokio::signal::ctrl_c().await
+        .context("Failed to listen for ctrl-c signal")?;
     
     info!("Shutdown signal received, stopping services");
     
+    if serve_traffic {
+        // Allow in-flight requests to complete
+        info!("Waiting for in-flight requests to complete...");
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+    
     // The rest of the tasks will be cleaned up when the main function returns
     Ok(())
 }
@@ -299,179 +351,450 @@ pub async fn run(config: EnvConfig) -> Result<()> {
 pub mod grpc {
     use std::sync::{Arc, Mutex};
     use std::net::SocketAddr;
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap, HashSet};
     use tokio::sync::{RwLock, mpsc};
+    use tokio::sync::mpsc::error::TrySendError;
     use tokio_stream::{Stream, wrappers::ReceiverStream};
     use anyhow::{Result, anyhow};
-    use tonic::{transport::Server, Request, Response, Status};
+    use tonic::{codec::CompressionEncoding, transport::{Server, ServerTlsConfig}, Request, Response, Status};
     use tracing::{info, warn, error, debug};
     use chrono::Utc;
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
     
     use crate::config::data_model::{Configuration, Proxy, Consumer, PluginConfig};
     use crate::grpc::proto::{
         config_service_server::{ConfigService, ConfigServiceServer},
-        SubscribeRequest, ConfigUpdate, ConfigSnapshot, GetConfigSnapshotRequest,
+        SubscribeRequest, ConfigUpdate, ConfigSnapshot, SnapshotRequest, SnapshotChunk, HealthReport, HealthAck,
+        RateLimitReport, RateLimitUpdate,
     };
-    use crate::grpc::conversions;
+    use crate::grpc::debug::{EffectiveConfig, NodeCommand, NodeDebugger};
+    use crate::grpc::delta;
+    use crate::grpc::pinning::GroupPins;
+    use crate::grpc::ratelimit::RateLimitAggregator;
+    use crate::grpc::rollout::{self, NodeStatus, Rollout, RolloutPolicy, RolloutStep};
+    use crate::grpc::security::{NodeAuthenticator, RequestCredentials};
+    use crate::grpc::transfer::{self, TransferSettings};
+    use crate::grpc::xds::AggregatedDiscovery;
+    use crate::grpc::proto::envoy::aggregated_discovery_service_server::AggregatedDiscoveryServiceServer;
+    use crate::metrics;
+    use crate::plugins::rate_limiting::cluster;
+    
+    /// A connected Data Plane node
+    #[derive(Debug, Clone)]
+    struct Client {
+        sender: mpsc::Sender<Result<ConfigUpdate, Status>>,
+        status: NodeStatus,
+    }
+    
+    /// The configuration versions built for Data Plane nodes
+    #[derive(Debug, Default)]
+    struct SyncState {
+        /// Snapshots, stamped with version and checksum, of the versions nodes
+        /// hold plus the latest and stable ones. Deltas are computed between them.
+        versions: BTreeMap<u64, ConfigSnapshot>,
+        /// Newest version, 0 until the first one is built
+        latest: u64,
+        /// Version nodes outside a running rollout hold
+        stable: u64,
+        rollout: Option<Rollout>,
+        /// History version each pinned group is pinned to, and the version built for it
+        pinned: BTreeMap<String, (i64, u64)>,
+        /// Versions built for pinned groups rather than from the latest configuration
+        pin_versions: HashSet<u64>,
+        /// Last version number handed out
+        built: u64,
+        /// Nodes whose last update was held back because their queue was full
+        lagging: HashSet<String>,
+        /// Nodes to send a full snapshot in place of their next delta
+        resync: HashSet<String>,
+    }
+    
+    impl SyncState {
+        fn next_version(&mut self) -> u64 {
+            self.built += 1;
+            self.built
+        }
+    }
     
     /// State shared between all connected DP clients
     #[derive(Debug)]
     struct SharedState {
-        /// The current configuration version (incremented on each change)
-        version: std::sync::atomic::AtomicU64,
+        /// Holding the lock while sending keeps each node's updates in order
+        sync: tokio::sync::Mutex<SyncState>,
         /// The shared configuration that all nodes access
         shared_config: Arc<RwLock<Configuration>>,
-        /// Connected Data Plane clients (client_id -> sender)
-        clients: Mutex<HashMap<String, mpsc::Sender<Result<ConfigUpdate, Status>>>>,
-        /// JWT secret for authenticating Data Plane nodes
-        jwt_secret: String,
+        /// Connected Data Plane clients (node_id -> client)
+        clients: Mutex<HashMap<String, Client>>,
+        /// Checks the tokens and certificates of Data Plane nodes
+        auth: NodeAuthenticator,
+        rollout_policy: RolloutPolicy,
+        transfer: TransferSettings,
+        /// Requests counted by each node for `cluster` rate limits
+        rate_limits: Mutex<RateLimitAggregator>,
+        /// Groups pinned to a version of the configuration history
+        pins: Option<Arc<GroupPins>>,
+        /// Answers the Admin API's questions about nodes
+        debugger: Option<Arc<NodeDebugger>>,
     }
     
     impl SharedState {
-        /// Broadcasts a configuration update to all connected Data Plane nodes
+        /// Builds a new version when an entity changed and sends it to the
+        /// nodes that should run it: all of them, or the first rollout stage
         async fn broadcast_update(&self) -> Result<()> {
-            let config = self.shared_config.read().await;
-            let version = self.version.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            let mut current = ConfigSnapshot::from(&*self.shared_config.read().await);
+            let mut sync = self.sync.lock().await;
             
-            // Create a ConfigUpdate from the current configuration
-            let update = self.create_config_update(&config, version)?;
+            if let Some(latest) = sync.versions.get(&sync.latest) {
+                if delta::is_empty(&delta::compute_delta(latest, &current)) {
+                    debug!("Configuration changed without changing any entity, nothing to broadcast");
+                    return Ok(());
+                }
+            }
             
-            // Get a copy of the clients hashmap
-            let clients = self.clients.lock().unwrap().clone();
+            let version = sync.next_version();
+            delta::stamp(&mut current, version);
+            sync.versions.insert(version, current);
+            sync.latest = version;
             
-            // Drop the lock to avoid holding it during potentially slow broadcast
-            drop(config);
+            // The first version has nothing to roll out over
+            if self.rollout_policy.is_staged() && sync.stable != 0 {
+                let rollout = Rollout::new(self.rollout_policy.clone(), version, Instant::now());
+                info!("Rolling out configuration v{} in {} stages, other nodes stay on v{}",
+                    version, rollout.stage().1, sync.stable);
+                sync.rollout = Some(rollout);
+            } else {
+                sync.stable = version;
+                sync.rollout = None;
+            }
             
-            if clients.is_empty() {
-                debug!("No connected Data Plane nodes to broadcast configuration update to");
-                return Ok(());
+            self.dispatch(&mut sync).await;
+            Ok(())
+        }
+        
+        /// Halts the running rollout when a node on the new version reports a
+        /// problem, or starts its next stage when the current one has run
+        async fn check_rollout(&self) {
+            let mut sync = self.sync.lock().await;
+            let nodes = self.rollout_nodes(&sync);
+            let (step, target) = match sync.rollout.as_mut() {
+                Some(rollout) => (rollout.tick(&nodes, Instant::now()), rollout.target()),
+                None => return,
+            };
+            
+            match step {
+                RolloutStep::Wait => {},
+                RolloutStep::Advanced => {
+                    if let Some((stage, stages)) = sync.rollout.as_ref().map(Rollout::stage) {
+                        info!("Configuration v{} rollout entering stage {} of {}", target, stage + 1, stages);
+                    }
+                    self.dispatch(&mut sync).await;
+                },
+                RolloutStep::Completed => {
+                    info!("Configuration v{} rolled out to all Data Plane nodes", target);
+                    sync.stable = target;
+                    sync.rollout = None;
+                    self.dispatch(&mut sync).await;
+                },
+                RolloutStep::Halted(reason) => {
+                    error!("Halted the rollout of configuration v{}: {}. Nodes that did not receive it stay on v{} until the configuration changes again",
+                        target, reason, sync.stable);
+                },
+            }
+        }
+        
+        /// Builds a version for each group pinned to another history version
+        /// than before, and sends the nodes of groups whose pin changed the
+        /// version they should now hold
+        async fn apply_pins(&self) {
+            let pins = match &self.pins {
+                Some(pins) => pins.list(),
+                None => return,
+            };
+            let mut sync = self.sync.lock().await;
+            let mut changed = false;
+            
+            for pin in &pins {
+                if sync.pinned.get(&pin.group).map(|(version_id, _)| *version_id) == Some(pin.version_id) {
+                    continue;
+                }
+                let mut snapshot = ConfigSnapshot::from(&*pin.config);
+                let version = sync.next_version();
+                delta::stamp(&mut snapshot, version);
+                sync.versions.insert(version, snapshot);
+                sync.pin_versions.insert(version);
+                sync.pinned.insert(pin.group.clone(), (pin.version_id, version));
+                info!("Data Plane group {} pinned to configuration history version {} (v{})", pin.group, pin.version_id, version);
+                changed = true;
+            }
+            
+            let unpinned: Vec<String> = sync.pinned.keys()
+                .filter(|group| !pins.iter().any(|pin| &pin.group == *group))
+                .cloned()
+                .collect();
+            for group in unpinned {
+                sync.pinned.remove(&group);
+                info!("Data Plane group {} follows the latest configuration again", group);
+                changed = true;
+            }
+            
+            if changed {
+                self.dispatch(&mut sync).await;
             }
+        }
+        
+        /// The nodes staged rollouts apply to: those of groups that are not pinned
+        fn rollout_nodes(&self, sync: &SyncState) -> BTreeMap<String, NodeStatus> {
+            let mut nodes = self.node_statuses();
+            nodes.retain(|_, node| !sync.pinned.contains_key(&node.group));
+            nodes
+        }
+        
+        /// Sends each connected node the version it should hold, if it holds another
+        async fn dispatch(&self, sync: &mut SyncState) {
+            let nodes = self.rollout_nodes(sync);
+            let selected = sync.rollout.as_ref().map(|rollout| (rollout.target(), rollout.selected(&nodes)));
             
-            info!("Broadcasting configuration update (v{}) to {} Data Plane nodes", 
-                version, clients.len());
+            // Get a copy of the clients
+            let clients: Vec<(String, Client)> = self.clients.lock().unwrap()
+                .iter()
+                .map(|(node_id, client)| (node_id.clone(), client.clone()))
+                .collect();
             
+            if clients.is_empty() {
+                debug!("No connected Data Plane nodes to send configuration v{} to", sync.latest);
+            }
+            
+            let mut updated = 0;
             let mut disconnected_clients = Vec::new();
+            sync.lagging.clear();
             
-            // Send the update to each client
-            for (client_id, sender) in clients.iter() {
-                if let Err(_) = sender.send(Ok(update.clone())).await {
-                    // Client is disconnected, mark for removal
-                    warn!("Data Plane node {} is disconnected, will remove", client_id);
-                    disconnected_clients.push(client_id.clone());
-                } else {
-                    debug!("Sent configuration update to Data Plane node {}", client_id);
+            for (node_id, client) in clients {
+                let held = client.status.version;
+                let desired = match (sync.pinned.get(&client.status.group), &selected) {
+                    (Some((_, pinned)), _) => *pinned,
+                    (None, Some((target, selected))) if selected.contains(&node_id) => *target,
+                    // Leaving a pin, the node joins the nodes outside the rollout
+                    (None, Some(_)) if sync.pin_versions.contains(&held) => sync.stable,
+                    // Not part of the rollout yet, keeps what it has
+                    (None, Some(_)) => held,
+                    (None, None) => sync.latest,
+                };
+                let resync = sync.resync.contains(&node_id);
+                if desired == held && !resync {
+                    metrics::set_subscriber_lag(&node_id, 0, queued_updates(&client.sender));
+                    continue;
+                }
+                
+                // A node that does not keep up is not waited for. Its update is
+                // held back, and once its queue drains it gets a single delta
+                // from the version it holds to the one it should hold by then.
+                let update = Self::update_between(sync, if resync { 0 } else { held }, desired);
+                match client.sender.try_send(Ok(update)) {
+                    Ok(()) => {
+                        debug!("Sent configuration v{} to Data Plane node {}", desired, node_id);
+                        if let Some(client) = self.clients.lock().unwrap().get_mut(&node_id) {
+                            client.status.version = desired;
+                        }
+                        sync.resync.remove(&node_id);
+                        metrics::set_subscriber_lag(&node_id, 0, queued_updates(&client.sender));
+                        updated += 1;
+                    },
+                    Err(TrySendError::Full(_)) => {
+                        debug!("Update queue of Data Plane node {} is full, holding back configuration v{}", node_id, desired);
+                        metrics::track_coalesced_update(&node_id);
+                        metrics::set_subscriber_lag(&node_id, desired.saturating_sub(held), queued_updates(&client.sender));
+                        sync.lagging.insert(node_id);
+                    },
+                    Err(TrySendError::Closed(_)) => {
+                        // Client is disconnected, mark for removal
+                        warn!("Data Plane node {} is disconnected, will remove", node_id);
+                        disconnected_clients.push(node_id);
+                    },
                 }
             }
             
+            if updated > 0 {
+                info!("Sent configuration v{} to {} Data Plane nodes",
+                    selected.as_ref().map(|(target, _)| *target).unwrap_or(sync.latest), updated);
+            }
+            if !sync.lagging.is_empty() {
+                warn!("{} Data Plane nodes are lagging behind, their updates will be coalesced", sync.lagging.len());
+            }
+            
             // Remove disconnected clients
             if !disconnected_clients.is_empty() {
                 let mut clients = self.clients.lock().unwrap();
-                for client_id in disconnected_clients {
-                    clients.remove(&client_id);
+                for node_id in &disconnected_clients {
+                    clients.remove(node_id);
+                    sync.resync.remove(node_id);
+                    metrics::remove_subscriber(node_id);
                 }
                 info!("Removed {} disconnected Data Plane nodes, {} nodes remain connected", 
                     disconnected_clients.len(), clients.len());
             }
             
-            Ok(())
+            self.prune_versions(sync);
         }
         
-        /// Creates a ConfigUpdate message from the current configuration
-        fn create_config_update(&self, config: &Configuration, version: u64) -> Result<ConfigUpdate> {
-            // Convert domain models to proto messages
-            let mut proxies = Vec::new();
-            let mut consumers = Vec::new();
-            let mut plugin_configs = Vec::new();
-            
-            for proxy in &config.proxies {
-                proxies.push(conversions::From::from(proxy));
+        /// Retries the nodes whose updates were held back because their queue was full
+        async fn retry_lagging(&self) {
+            let mut sync = self.sync.lock().await;
+            if !sync.lagging.is_empty() || !sync.resync.is_empty() {
+                self.dispatch(&mut sync).await;
             }
-            
-            for consumer in &config.consumers {
-                consumers.push(conversions::From::from(consumer));
-            }
-            
-            for plugin_config in &config.plugin_configs {
-                plugin_configs.push(conversions::From::from(plugin_config));
+        }
+        
+        /// A delta from the version a node holds to another, or the full
+        /// snapshot when the node holds none
+        fn update_between(sync: &SyncState, from: u64, to: u64) -> ConfigUpdate {
+            let target = &sync.versions[&to];
+            match sync.versions.get(&from) {
+                Some(previous) => delta::update_between(previous, target),
+                None => delta::full_update(&mut target.clone(), to),
             }
-            
-            Ok(ConfigUpdate {
-                version,
-                proxies,
-                consumers,
-                plugin_configs,
-                timestamp: Utc::now().to_rfc3339(),
-                update_type: 0, // 0 = Full update
-                deleted_ids: Vec::new(), // None for full update
-            })
         }
         
-        /// Creates a ConfigSnapshot message from the current configuration
-        async fn create_config_snapshot(&self) -> Result<ConfigSnapshot> {
-            let config = self.shared_config.read().await;
-            let version = self.version.load(std::sync::atomic::Ordering::SeqCst);
-            
-            // Convert domain models to proto messages
-            let mut proxies = Vec::new();
-            let mut consumers = Vec::new();
-            let mut plugin_configs = Vec::new();
-            
-            for proxy in &config.proxies {
-                proxies.push(conversions::From::from(proxy));
+        /// The version to give a node asking for a full snapshot: the one it
+        /// was last sent, or where a new node stands in the rollout. Builds the
+        /// first version when none was built yet.
+        async fn version_for(&self, sync: &mut SyncState, node_id: &str, group: &str) -> u64 {
+            if sync.latest == 0 {
+                let mut snapshot = ConfigSnapshot::from(&*self.shared_config.read().await);
+                let version = sync.next_version();
+                delta::stamp(&mut snapshot, version);
+                sync.versions.insert(version, snapshot);
+                sync.latest = version;
+                sync.stable = version;
             }
             
-            for consumer in &config.consumers {
-                consumers.push(conversions::From::from(consumer));
+            if let Some((_, pinned)) = sync.pinned.get(group) {
+                return *pinned;
             }
             
-            for plugin_config in &config.plugin_configs {
-                plugin_configs.push(conversions::From::from(plugin_config));
+            let mut nodes = self.rollout_nodes(sync);
+            if let Some(node) = nodes.get(node_id) {
+                if sync.versions.contains_key(&node.version) {
+                    return node.version;
+                }
             }
             
-            Ok(ConfigSnapshot {
-                version,
-                proxies,
-                consumers,
-                plugin_configs,
-                timestamp: Utc::now().to_rfc3339(),
+            match &sync.rollout {
+                Some(rollout) => {
+                    nodes.insert(node_id.to_string(), NodeStatus::new(group, sync.stable));
+                    if rollout.selected(&nodes).contains(node_id) {
+                        rollout.target()
+                    } else {
+                        sync.stable
+                    }
+                },
+                None => sync.latest,
+            }
+        }
+        
+        /// Drops the snapshots of versions no node holds any more
+        fn prune_versions(&self, sync: &mut SyncState) {
+            let mut in_use: HashSet<u64> = self.clients.lock().unwrap()
+                .values()
+                .map(|client| client.status.version)
+                .collect();
+            in_use.extend([sync.latest, sync.stable]);
+            in_use.extend(sync.pinned.values().map(|(_, version)| *version));
+            sync.versions.retain(|version, _| in_use.contains(version));
+            let versions = &sync.versions;
+            sync.pin_versions.retain(|version| versions.contains_key(version));
+        }
+        
+        /// Answers a request of the Admin API about a node
+        async fn handle_command(&self, command: NodeCommand) {
+            match command {
+                NodeCommand::EffectiveConfig { node_id, reply } => {
+                    let _ = reply.send(self.effective_config(&node_id).await);
+                },
+                NodeCommand::Resync { node_id, reply } => {
+                    let _ = reply.send(self.resync(&node_id).await);
+                },
+            }
+        }
+        
+        /// The configuration a connected node was last sent
+        async fn effective_config(&self, node_id: &str) -> Option<EffectiveConfig> {
+            let sync = self.sync.lock().await;
+            let status = self.clients.lock().unwrap().get(node_id)?.status.clone();
+            let snapshot = sync.versions.get(&status.version)?;
+            let config = match snapshot.into_configuration() {
+                Ok(config) => config,
+                Err(e) => {
+                    error!("Failed to convert configuration v{} of Data Plane node {}: {}", status.version, node_id, e);
+                    return None;
+                }
+            };
+            Some(EffectiveConfig {
+                node_id: node_id.to_string(),
+                group: status.group,
+                version: status.version,
+                reported_version: status.reported_version,
+                checksum: snapshot.checksum.clone(),
+                config,
             })
         }
         
-        /// Registers a new Data Plane client
-        async fn register_client(&self, client_id: String, sender: mpsc::Sender<Result<ConfigUpdate, Status>>) {
-            info!("Registering new Data Plane node with ID {}", client_id);
+        /// Sends a connected node the full snapshot of the version it should
+        /// hold, replacing whatever its deltas made of it. A lagging node gets
+        /// it once its queue drains.
+        async fn resync(&self, node_id: &str) -> Option<u64> {
+            let mut sync = self.sync.lock().await;
+            if !self.clients.lock().unwrap().contains_key(node_id) {
+                return None;
+            }
+            sync.resync.insert(node_id.to_string());
+            self.dispatch(&mut sync).await;
+            let version = self.clients.lock().unwrap().get(node_id)?.status.version;
+            if !sync.resync.contains(node_id) {
+                info!("Sent the full configuration v{} to Data Plane node {} for a forced resync", version, node_id);
+            }
+            Some(version)
+        }
+        
+        fn node_statuses(&self) -> BTreeMap<String, NodeStatus> {
+            self.clients.lock().unwrap()
+                .iter()
+                .map(|(node_id, client)| (node_id.clone(), client.status.clone()))
+                .collect()
+        }
+        
+        /// Registers a new Data Plane client holding `status.version`
+        async fn register_client(&self, node_id: String, sender: mpsc::Sender<Result<ConfigUpdate, Status>>, status: NodeStatus) {
+            info!("Registering new Data Plane node with ID {} in group {} on configuration v{}",
+                node_id, status.group, status.version);
             
             // Add the client to the map
-            self.clients.lock().unwrap().insert(client_id, sender);
+            let client_count = {
+                let mut clients = self.clients.lock().unwrap();
+                clients.insert(node_id, Client { sender, status });
+                clients.len()
+            };
             
             // Log the total number of connected clients
-            let client_count = self.clients.lock().unwrap().len();
             info!("Total connected Data Plane nodes: {}", client_count);
         }
         
-        /// Validates a JWT token from a Data Plane node
-        fn validate_jwt(&self, auth_token: &str) -> Result<(), Status> {
-            // First check if token has the Bearer prefix
-            let token = if let Some(stripped) = auth_token.strip_prefix("Bearer ") {
-                stripped
-            } else {
-                return Err(Status::unauthenticated("Invalid authorization format"));
-            };
-            
-            // Validate JWT token
-            match validate_jwt_token(token, &self.jwt_secret) {
-                Ok(_) => {
-                    // Token is valid, allow the request to proceed
-                    debug!("Successfully validated JWT token for data plane connection");
+        /// Authenticates a request from a Data Plane node
+        fn authenticate<T>(&self, request: &Request<T>, node_id: &str) -> Result<(), Status> {
+            let credentials = RequestCredentials::from_request(request)?;
+            self.authenticate_credentials(&credentials, node_id)
+        }
+        
+        fn authenticate_credentials(&self, credentials: &RequestCredentials, node_id: &str) -> Result<(), Status> {
+            match self.auth.authenticate_credentials(node_id, credentials) {
+                Ok(()) => {
+                    debug!("Authenticated Data Plane node {}", node_id);
+                    Ok(())
                 },
                 Err(e) => {
-                    warn!("JWT validation failed: {}", e);
-                    return Err(Status::unauthenticated(format!("Invalid token: {}", e)));
+                    warn!("Refused Data Plane node {}: {}", node_id, e);
+                    Err(e.into())
                 }
             }
-            
-            Ok(())
         }
     }
     
@@ -490,45 +813,32 @@ pub mod grpc {
             &self,
             request: Request<SubscribeRequest>,
         ) -> Result<Response<Self::SubscribeConfigUpdatesStream>, Status> {
-            let req = request.into_inner();
-            let node_id = req.node_id;
-            
-            // Get auth token from metadata
-            let auth_token = match request.metadata().get("authorization") {
-                Some(t) => match t.to_str() {
-                    Ok(s) => s,
-                    Err(_) => return Err(Status::unauthenticated("Invalid authorization header")),
-                },
-                None => return Err(Status::unauthenticated("Missing authorization header")),
-            };
+            let node_id = request.get_ref().node_id.clone();
+            let group = group_or_default(&request.get_ref().group);
             
-            // Validate the JWT
-            self.state.validate_jwt(auth_token)?;
+            // Check the node's token, allowlisting and client certificate
+            self.state.authenticate(&request, &node_id)?;
             
             // Create channel for configuration updates
-            let (tx, rx) = mpsc::channel(10);
+            let (tx, rx) = mpsc::channel(self.state.transfer.subscriber_buffer);
             
-            // Register this client
-            self.state.register_client(node_id.clone(), tx.clone()).await;
-            
-            // Send initial full configuration update
-            let config = self.state.shared_config.read().await;
-            let version = self.state.version.load(std::sync::atomic::Ordering::SeqCst);
-            
-            match self.state.create_config_update(&config, version) {
-                Ok(update) => {
-                    // Send initial update
-                    if let Err(e) = tx.send(Ok(update)).await {
-                        error!("Failed to send initial configuration update to Data Plane node {}: {}", node_id, e);
-                        return Err(Status::internal("Failed to send initial configuration"));
-                    }
-                },
-                Err(e) => {
-                    error!("Failed to create initial configuration update: {}", e);
-                    return Err(Status::internal("Failed to create initial configuration update"));
-                }
+            // Send the snapshot later deltas are based on before any delta
+            // can reach the new client. A node that just streamed that
+            // snapshot gets an empty delta confirming it instead.
+            let current_version = request.get_ref().current_version;
+            let mut sync = self.state.sync.lock().await;
+            let version = self.state.version_for(&mut sync, &node_id, &group).await;
+            let base = if current_version == version { version } else { 0 };
+            let update = SharedState::update_between(&sync, base, version);
+            if let Err(e) = tx.send(Ok(update)).await {
+                error!("Failed to send initial configuration update to Data Plane node {}: {}", node_id, e);
+                return Err(Status::internal("Failed to send initial configuration"));
             }
             
+            // Register this client
+            self.state.register_client(node_id.clone(), tx.clone(), NodeStatus::new(group, version)).await;
+            drop(sync);
+            
             // Return the stream
             Ok(Response::new(ReceiverStream::new(rx)))
         }
@@ -536,32 +846,21 @@ pub mod grpc {
         /// Get a configuration snapshot
         async fn get_config_snapshot(
             &self,
-            request: Request<GetConfigSnapshotRequest>,
+            request: Request<SnapshotRequest>,
         ) -> Result<Response<ConfigSnapshot>, Status> {
-            let req = request.into_inner();
-            let node_id = req.node_id;
-            
-            // Get auth token from metadata
-            let auth_token = match request.metadata().get("authorization") {
-                Some(t) => match t.to_str() {
-                    Ok(s) => s,
-                    Err(_) => return Err(Status::unauthenticated("Invalid authorization header")),
-                },
-                None => return Err(Status::unauthenticated("Missing authorization header")),
-            };
+            let node_id = request.get_ref().node_id.clone();
+            let group = group_or_default(&request.get_ref().group);
             
-            // Validate the JWT
-            self.state.validate_jwt(auth_token)?;
+            // Check the node's token, allowlisting and client certificate
+            self.state.authenticate(&request, &node_id)?;
             
             info!("Data Plane node {} requested configuration snapshot", node_id);
             
-            // Create and return the snapshot
-            let snapshot = match self.state.create_config_snapshot().await {
-                Ok(snapshot) => snapshot,
-                Err(e) => {
-                    error!("Failed to create configuration snapshot: {}", e);
-                    return Err(Status::internal("Failed to create configuration snapshot"));
-                }
+            // The version the node should hold, so the deltas that follow apply to it
+            let snapshot = {
+                let mut sync = self.state.sync.lock().await;
+                let version = self.state.version_for(&mut sync, &node_id, &group).await;
+                sync.versions[&version].clone()
             };
             
             info!("Sending configuration snapshot to Data Plane node {} (v{})", 
@@ -569,83 +868,226 @@ pub mod grpc {
             
             Ok(Response::new(snapshot))
         }
-    }
-    
-    /// Validate a JWT token against the provided secret
-    fn validate_jwt_token(token: &str, secret: &str) -> Result<(), String> {
-        use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
-        use serde::{Serialize, Deserialize};
         
-        // Define the claims we expect in the JWT token
-        #[derive(Debug, Serialize, Deserialize)]
-        struct Claims {
-            sub: String,          // Subject (typically the data plane node ID)
-            exp: usize,           // Expiration time (as UTC timestamp)
-            iat: Option<usize>,   // Issued at (as UTC timestamp)
-            #[serde(default)]
-            role: String,         // Optional role claim
-        }
+        /// Snapshot chunks, for snapshots larger than a message
+        type StreamConfigSnapshotStream = tokio_stream::Iter<std::vec::IntoIter<Result<SnapshotChunk, Status>>>;
         
-        // Create a decoding key from the secret
-        let decoding_key = DecodingKey::from_secret(secret.as_bytes());
+        /// Stream a configuration snapshot in chunks
+        async fn stream_config_snapshot(
+            &self,
+            request: Request<SnapshotRequest>,
+        ) -> Result<Response<Self::StreamConfigSnapshotStream>, Status> {
+            let node_id = request.get_ref().node_id.clone();
+            let group = group_or_default(&request.get_ref().group);
+            
+            // Check the node's token, allowlisting and client certificate
+            self.state.authenticate(&request, &node_id)?;
+            
+            // The version the node should hold, so the deltas that follow apply to it
+            let snapshot = {
+                let mut sync = self.state.sync.lock().await;
+                let version = self.state.version_for(&mut sync, &node_id, &group).await;
+                sync.versions[&version].clone()
+            };
+            
+            let chunks = transfer::split_snapshot(&snapshot, self.state.transfer.compression, self.state.transfer.chunk_size)
+                .map_err(|e| Status::internal(e.to_string()))?;
+            info!("Streaming configuration snapshot v{} to Data Plane node {} in {} chunks ({} compression)",
+                snapshot.version, node_id, chunks.len(), self.state.transfer.compression);
+            
+            Ok(Response::new(tokio_stream::iter(chunks.into_iter().map(Ok).collect::<Vec<_>>())))
+        }
         
-        // Create a Validation object
-        let mut validation = Validation::new(Algorithm::HS256);
+        /// Record a node's health, which staged rollouts halt on, and the
+        /// traffic it reports for the fleet metrics
+        async fn report_health(
+            &self,
+            request: Request<HealthReport>,
+        ) -> Result<Response<HealthAck>, Status> {
+            let node_id = request.get_ref().node_id.clone();
+            
+            // Check the node's token, allowlisting and client certificate
+            self.state.authenticate(&request, &node_id)?;
+            let report = request.into_inner();
+            metrics::fleet::record_report(&report);
+            if let Some(debugger) = &self.state.debugger {
+                debugger.record_report(&report);
+            }
+            
+            let subscribed = match self.state.clients.lock().unwrap().get_mut(&node_id) {
+                Some(client) => {
+                    client.status.reported_version = report.config_version;
+                    client.status.health = report.status.clone();
+                    true
+                },
+                None => false,
+            };
+            
+            if report.status == rollout::HEALTHY {
+                debug!("Data Plane node {} is healthy on configuration v{}", node_id, report.config_version);
+            } else {
+                warn!("Data Plane node {} reported {} on configuration v{} ({:?})",
+                    node_id, report.status, report.config_version, report.metrics);
+            }
+            
+            Ok(Response::new(HealthAck {
+                success: subscribed,
+                message: if subscribed { String::new() } else { "Node is not subscribed to configuration updates".to_string() },
+            }))
+        }
         
-        // We require the subject claim
-        validation.set_required_spec_claims(&["sub", "exp"]);
+        type SyncRateLimitsStream = ReceiverStream<Result<RateLimitUpdate, Status>>;
         
-        // Decode and validate the token
-        match decode::<Claims>(token, &decoding_key, &validation) {
-            Ok(token_data) => {
-                // Additional custom validation can be performed here
-                // For example, check if the role is "data_plane"
-                if !token_data.claims.role.is_empty() && token_data.claims.role != "data_plane" {
-                    return Err(format!("Invalid role: {}", token_data.claims.role));
+        /// Answer each node's rate limiting counters with those of all other nodes
+        async fn sync_rate_limits(
+            &self,
+            request: Request<tonic::Streaming<RateLimitReport>>,
+        ) -> Result<Response<Self::SyncRateLimitsStream>, Status> {
+            let credentials = RequestCredentials::from_request(&request)?;
+            let mut reports = request.into_inner();
+            
+            // The node ID only arrives with the first report
+            let first = reports.message().await?
+                .ok_or_else(|| Status::invalid_argument("Stream closed before the first rate limit report"))?;
+            let node_id = first.node_id.clone();
+            self.state.authenticate_credentials(&credentials, &node_id)?;
+            debug!("Data Plane node {} is synchronizing rate limits", node_id);
+            
+            let (tx, rx) = mpsc::channel(4);
+            let state = Arc::clone(&self.state);
+            tokio::spawn(async move {
+                let mut next = Some(first);
+                while let Some(report) = next.take() {
+                    // Reports name their node, but only the authenticated one is counted
+                    if report.node_id != node_id {
+                        let _ = tx.send(Err(Status::invalid_argument("Rate limit reports must keep the node ID of the stream"))).await;
+                        return;
+                    }
+                    let now = cluster::unix_now();
+                    let update = {
+                        let mut rate_limits = state.rate_limits.lock().unwrap();
+                        rate_limits.record(&report, now);
+                        rate_limits.others(&node_id, now)
+                    };
+                    if tx.send(Ok(update)).await.is_err() {
+                        break;
+                    }
+                    next = match reports.message().await {
+                        Ok(report) => report,
+                        Err(e) => {
+                            debug!("Rate limit stream of Data Plane node {} failed: {}", node_id, e);
+                            None
+                        },
+                    };
                 }
-                
-                Ok(())
-            },
-            Err(err) => Err(format!("Token validation error: {}", err)),
+                debug!("Data Plane node {} stopped synchronizing rate limits", node_id);
+            });
+            
+            Ok(Response::new(ReceiverStream::new(rx)))
         }
     }
     
+    /// Updates waiting in a node's queue
+    fn queued_updates(sender: &mpsc::Sender<Result<ConfigUpdate, Status>>) -> usize {
+        sender.max_capacity() - sender.capacity()
+    }
+    
+    /// Nodes that do not name a group belong to the default one
+    fn group_or_default(group: &str) -> String {
+        if group.is_empty() { "default".to_string() } else { group.to_string() }
+    }
+    
     #[derive(Debug)]
     pub struct GrpcServer {
         addr: SocketAddr,
-        jwt_secret: String,
+        auth: NodeAuthenticator,
+        tls: Option<ServerTlsConfig>,
+        rollout_policy: RolloutPolicy,
+        transfer: TransferSettings,
         shared_config: Arc<RwLock<Configuration>>,
+        /// Serves the configuration to Envoy nodes as well
+        xds: Option<Arc<AggregatedDiscovery>>,
+        pins: Option<Arc<GroupPins>>,
+        debugger: Option<Arc<NodeDebugger>>,
     }
     
     impl GrpcServer {
         pub fn new(
             addr: SocketAddr,
-            jwt_secret: String,
+            auth: NodeAuthenticator,
+            tls: Option<ServerTlsConfig>,
+            rollout_policy: RolloutPolicy,
+            transfer: TransferSettings,
             shared_config: Arc<RwLock<Configuration>>,
         ) -> Result<Self> {
             Ok(Self {
                 addr,
-                jwt_secret,
+                auth,
+                tls,
+                rollout_policy,
+                transfer,
                 shared_config,
+                xds: None,
+                pins: None,
+                debugger: None,
             })
         }
         
+        /// Also serve Envoy's Aggregated Discovery Service on the listener
+        pub fn with_xds(mut self, xds: AggregatedDiscovery) -> Self {
+            self.xds = Some(Arc::new(xds));
+            self
+        }
+        
+        /// Serve pinned groups the version of the configuration history they
+        /// are pinned to
+        pub fn with_group_pins(mut self, pins: Arc<GroupPins>) -> Self {
+            self.pins = Some(pins);
+            self
+        }
+        
+        /// Answer the Admin API's requests to inspect and resync nodes
+        pub fn with_node_debugger(mut self, debugger: Arc<NodeDebugger>) -> Self {
+            self.debugger = Some(debugger);
+            self
+        }
+        
         pub async fn start(self) -> Result<()> {
             // Create the shared state
             let state = Arc::new(SharedState {
-                version: std::sync::atomic::AtomicU64::new(1),
+                sync: tokio::sync::Mutex::new(SyncState::default()),
                 shared_config: self.shared_config.clone(),
                 clients: Mutex::new(HashMap::new()),
-                jwt_secret: self.jwt_secret,
+                auth: self.auth,
+                rollout_policy: self.rollout_policy,
+                transfer: self.transfer,
+                rate_limits: Mutex::new(RateLimitAggregator::new()),
+                pins: self.pins.clone(),
+                debugger: self.debugger.clone(),
             });
+            state.apply_pins().await;
+            
+            if let Some(mut commands) = self.debugger.as_ref().and_then(|debugger| debugger.take_commands()) {
+                let command_state = Arc::clone(&state);
+                tokio::spawn(async move {
+                    while let Some(command) = commands.recv().await {
+                        command_state.handle_command(command).await;
+                    }
+                });
+            }
             
             // Create the service implementation
             let service = ConfigServiceImpl {
                 state: Arc::clone(&state),
             };
             
+            if let Some(xds) = &self.xds {
+                xds.publish(&*self.shared_config.read().await);
+            }
+            
             // Set up config change watcher to broadcast updates
             let config_watch_state = Arc::clone(&state);
+            let config_watch_xds = self.xds.clone();
             let config_watch_handle = tokio::spawn(async move {
                 let mut last_updated_at = Utc::now();
                 let mut interval = tokio::time::interval(Duration::from_secs(1));
@@ -666,15 +1108,46 @@ pub mod grpc {
                         if let Err(e) = config_watch_state.broadcast_update().await {
                             error!("Failed to broadcast configuration update: {}", e);
                         }
+                        if let Some(xds) = &config_watch_xds {
+                            xds.publish(&*config_watch_state.shared_config.read().await);
+                        }
                     }
+                    
+                    config_watch_state.apply_pins().await;
+                    config_watch_state.check_rollout().await;
+                    config_watch_state.retry_lagging().await;
                 }
             });
             
             // Build the gRPC server
-            info!("Starting gRPC server at {}", self.addr);
-            let server = Server::builder()
-                .add_service(ConfigServiceServer::new(service))
-                .serve(self.addr);
+            info!("Starting gRPC server at {} ({})", self.addr,
+                if self.tls.is_some() { "TLS" } else { "plaintext" });
+            // Keepalive pings notice Data Planes that vanished without closing
+            // the connection; adaptive windows let large snapshots fill the link
+            let mut builder = Server::builder()
+                .http2_keepalive_interval(self.transfer.keepalive_interval)
+                .http2_keepalive_timeout(Some(self.transfer.keepalive_timeout))
+                .tcp_keepalive(self.transfer.keepalive_interval)
+                .http2_adaptive_window(Some(true));
+            if let Some(tls) = self.tls {
+                builder = builder.tls_config(tls)
+                    .map_err(|e| anyhow!("Invalid gRPC TLS configuration: {}", e))?;
+            }
+            let mut service = ConfigServiceServer::new(service)
+                .max_decoding_message_size(self.transfer.max_message_size)
+                .max_encoding_message_size(self.transfer.max_message_size)
+                .accept_compressed(CompressionEncoding::Gzip);
+            if let Some(encoding) = self.transfer.compression.grpc_encoding() {
+                service = service.send_compressed(encoding);
+            }
+            let mut router = builder.add_service(service);
+            if let Some(xds) = self.xds {
+                info!("Serving xDS resources to Envoy nodes on the gRPC listener");
+                router = router.add_service(AggregatedDiscoveryServiceServer::from_arc(xds)
+                    .max_decoding_message_size(self.transfer.max_message_size)
+                    .max_encoding_message_size(self.transfer.max_message_size));
+            }
+            let server = router.serve(self.addr);
             
             // Start the server
             server.await
@@ -683,46 +1156,5 @@ pub mod grpc {
             // This should never be reached
             Ok(())
         }
-        
-        pub async fn push_config_update(&self, update: crate::proto::ConfigUpdate) -> Result<(), Status> {
-            // Get a copy of the clients hashmap
-            let clients = self.shared_config.read().await.clients.lock().unwrap().clone();
-            
-            // Drop the lock to avoid holding it during potentially slow broadcast
-            drop(self.shared_config);
-            
-            if clients.is_empty() {
-                debug!("No connected Data Plane nodes to broadcast configuration update to");
-                return Ok(());
-            }
-            
-            info!("Broadcasting configuration update (v{}) to {} Data Plane nodes", 
-                update.version, clients.len());
-            
-            let mut disconnected_clients = Vec::new();
-            
-            // Send the update to each client
-            for (client_id, sender) in clients.iter() {
-                if let Err(_) = sender.send(Ok(update.clone())).await {
-                    // Client is disconnected, mark for removal
-                    warn!("Data Plane node {} is disconnected, will remove", client_id);
-                    disconnected_clients.push(client_id.clone());
-                } else {
-                    debug!("Sent configuration update to Data Plane node {}", client_id);
-                }
-            }
-            
-            // Remove disconnected clients
-            if !disconnected_clients.is_empty() {
-                let mut clients = self.shared_config.write().await.clients.lock().unwrap();
-                for client_id in disconnected_clients {
-                    clients.remove(&client_id);
-                }
-                info!("Removed {} disconnected Data Plane nodes, {} nodes remain connected", 
-                    disconnected_clients.len(), clients.len());
-            }
-            
-            Ok(())
-        }
     }
 }
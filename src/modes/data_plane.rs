@@ -1,8 +1,11 @@
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use std::cmp::min;
 use tokio::sync::{RwLock, mpsc};
 use tokio_stream::StreamExt;
+use tonic::transport::ClientTlsConfig;
 use rand::Rng;
 use anyhow::{Result, Context, anyhow};
 use tracing::{info, warn, error, debug};
@@ -10,8 +13,19 @@ use tracing::{info, warn, error, debug};
 use crate::config::env_config::EnvConfig;
 use crate::config::data_model::Configuration;
 use crate::proxy::ProxyServer;
+use crate::proxy::backend_health;
+use crate::proxy::update_manager::UpdateManager;
 use crate::grpc::config_client::ConfigClient;
+use crate::grpc::delta::SyncedSnapshot;
+use crate::grpc::security;
+use crate::grpc::rollout;
+use crate::grpc::ratelimit;
+use crate::plugins::rate_limiting::cluster;
+use crate::grpc::cache::SnapshotCache;
+use crate::grpc::proto::{BackendHealth, ProxyHealth, ProxyMetrics};
 use crate::dns::{self, DnsCache};
+use crate::metrics;
+use crate::health;
 
 pub async fn run(config: EnvConfig) -> Result<()> {
     info!("Starting Ferrum Gateway in Data Plane mode");
@@ -23,11 +37,41 @@ pub async fn run(config: EnvConfig) -> Result<()> {
     let grpc_auth_token = config.dp_grpc_auth_token.clone()
         .context("gRPC Auth Token must be set in Data Plane mode")?;
     
+    let grpc_tls = security::client_tls_config(&config, &cp_grpc_url)
+        .context("Failed to load the Control Plane TLS certificates")?;
+    if grpc_tls.is_none() {
+        warn!("Connecting to the Control Plane without TLS, use an https:// URL to encrypt the connection");
+    }
+    
+    let connection = ControlPlaneConnection {
+        url: cp_grpc_url,
+        // A stable node ID lets the Control Plane allowlist this node
+        node_id: config.dp_node_id.clone()
+            .unwrap_or_else(|| format!("dp-{}", uuid::Uuid::new_v4())),
+        group: config.dp_node_group.clone(),
+        auth_token: grpc_auth_token,
+        tls: grpc_tls,
+        health_report_interval: config.dp_health_report_interval,
+        degraded_error_rate: config.dp_degraded_error_rate,
+        max_message_size: config.grpc_max_message_size,
+        keepalive_interval: config.grpc_keepalive_interval,
+        keepalive_timeout: config.grpc_keepalive_timeout,
+        rate_limit_sync_interval: Some(config.dp_rate_limit_sync_interval_ms)
+            .filter(|ms| *ms > 0)
+            .map(Duration::from_millis),
+    };
+    
+    if config.acme_enabled {
+        warn!("FERRUM_ACME_ENABLED is ignored in Data Plane mode, certificates are issued by the Control Plane or a database-mode node");
+    }
+    
     // Initialize with empty configuration (will be populated by CP)
     let initial_config = Configuration {
         proxies: Vec::new(),
         consumers: Vec::new(),
         plugin_configs: Vec::new(),
+        certificates: Vec::new(),
+        snis: Vec::new(),
         last_updated_at: chrono::Utc::now(),
     };
     
@@ -36,7 +80,11 @@ pub async fn run(config: EnvConfig) -> Result<()> {
     let dns_overrides = config.dns_overrides.clone();
     
     // Create DNS cache
-    let dns_cache = Arc::new(DnsCache::new(dns_ttl, dns_overrides));
+    let dns_cache = Arc::new(DnsCache::new(dns_ttl, dns_overrides)
+        .with_min_ttl(config.dns_min_ttl)
+        .with_negative_ttl(config.dns_negative_ttl)
+        .with_stale_ttl(config.dns_stale_ttl)
+        .with_resolvers(&config.dns_resolvers)?);
     
     // Create shared configuration
     let shared_config = Arc::new(RwLock::new(initial_config));
@@ -61,6 +109,28 @@ pub async fn run(config: EnvConfig) -> Result<()> {
         Arc::clone(&shared_config),
         Arc::clone(&dns_cache),
     )?;
+    let update_manager = proxy_server.get_update_manager();
+    
+    // Serve the configuration cached by the previous run until the Control
+    // Plane is reached, so a restart does not depend on it being up
+    let snapshot_cache = config.dp_config_cache_path.as_ref().map(SnapshotCache::new);
+    if let Some(cache) = &snapshot_cache {
+        match cache.load().await {
+            Ok(Some(synced)) => {
+                info!("Loaded cached configuration version {} from {}", synced.version(), cache.path().display());
+                match install_snapshot(&synced, &shared_config, &update_manager, None).await {
+                    Ok(()) => {
+                        if let Err(e) = dns::warm_up_dns_cache(&dns_cache, &shared_config.read().await.proxies).await {
+                            warn!("DNS cache warmup for cached proxies failed: {}", e);
+                        }
+                    },
+                    Err(e) => warn!("Cannot serve the cached configuration: {}", e),
+                }
+            },
+            Ok(None) => info!("No cached configuration at {}, waiting for the Control Plane", cache.path().display()),
+            Err(e) => warn!("Ignoring cached configuration: {}", e),
+        }
+    }
     
     let _proxy_handle = tokio::spawn(async move {
         if let Err(e) = proxy_server.start().await {
@@ -75,38 +145,52 @@ pub async fn run(config: EnvConfig) -> Result<()> {
     let shared_config_clone = Arc::clone(&shared_config);
     let dns_cache_for_grpc: Arc<crate::dns::cache::DnsCache> = Arc::clone(&dns_cache);
     
+    let max_retry_delay = config.dp_reconnect_max_delay;
+    
     let _grpc_client_handle = tokio::spawn(async move {
-        let mut connection_retry_delay = Duration::from_secs(1);
-        const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
         const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+        let mut connection_retry_delay = MIN_RETRY_DELAY;
         
+        // The last configuration received keeps being served while disconnected
         loop {
-            info!("Connecting to Control Plane at {}", cp_grpc_url);
+            info!("Connecting to Control Plane at {} as node {} in group {}",
+                connection.url, connection.node_id, connection.group);
+            let connected_at = Instant::now();
             
             match connect_to_control_plane(
-                &cp_grpc_url, 
-                &grpc_auth_token, 
+                &connection,
                 shared_config_clone.clone(),
+                Arc::clone(&update_manager),
                 dns_cache_for_grpc.clone(),
+                snapshot_cache.clone(),
                 reconnect_notify_tx.clone()
             ).await {
                 Ok(()) => {
                     info!("Connection to Control Plane closed normally, reconnecting immediately");
+                    health::record_control_plane(Err("Configuration stream closed".to_string()));
                     // If the connection closed normally, reset the retry delay
                     connection_retry_delay = MIN_RETRY_DELAY;
                 },
                 Err(e) => {
                     error!("Control Plane connection error: {}", e);
+                    health::record_control_plane(Err(format!("{:#}", e)));
                     
-                    // Notify about connection loss
-                    if let Err(e) = reconnect_notify_tx.send(()).await {
-                        error!("Failed to send reconnection notification: {}", e);
+                    // Notify about connection loss. Nothing may be reading the
+                    // notifications, so a full channel must not block reconnecting.
+                    if let Err(mpsc::error::TrySendError::Closed(_)) = reconnect_notify_tx.try_send(()) {
+                        error!("Failed to send reconnection notification: channel closed");
                     }
                     
-                    // Apply exponential backoff with jitter for reconnect attempts
-                    let jitter = std::time::Duration::from_millis(
-                        (rand::random::<f32>() * 500.0) as u64
-                    );
+                    // A connection that stayed up for a while was healthy, so
+                    // the next failure starts the backoff over
+                    if connected_at.elapsed() > max_retry_delay {
+                        connection_retry_delay = MIN_RETRY_DELAY;
+                    }
+                    
+                    // Apply exponential backoff with up to 50% jitter, so data
+                    // planes that lost the Control Plane together do not
+                    // reconnect in lockstep
+                    let jitter = connection_retry_delay.mul_f64(rand::thread_rng().gen_range(0.0..0.5));
                     
                     let retry_delay = connection_retry_delay.saturating_add(jitter);
                     info!("Retrying connection in {:?}", retry_delay);
@@ -115,7 +199,7 @@ pub async fn run(config: EnvConfig) -> Result<()> {
                     // Increase retry delay with exponential backoff
                     connection_retry_delay = min(
                         connection_retry_delay.saturating_mul(2), 
-                        MAX_RETRY_DELAY
+                        max_retry_delay
                     );
                 }
             }
@@ -136,85 +220,119 @@ pub async fn run(config: EnvConfig) -> Result<()> {
     Ok(())
 }
 
+/// How this Data Plane reaches and identifies itself to the Control Plane
+struct ControlPlaneConnection {
+    url: String,
+    node_id: String,
+    group: String,
+    auth_token: String,
+    tls: Option<ClientTlsConfig>,
+    health_report_interval: Duration,
+    degraded_error_rate: f64,
+    max_message_size: usize,
+    keepalive_interval: Option<Duration>,
+    keepalive_timeout: Duration,
+    /// How often `cluster` rate limiting counters are exchanged, if at all
+    rate_limit_sync_interval: Option<Duration>,
+}
+
+/// Aborts a background task when dropped
+struct AbortOnDrop(tokio::task::JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
 /// Connect to the control plane and start receiving configuration updates
 async fn connect_to_control_plane(
-    cp_url: &str,
-    auth_token: &str, 
+    connection: &ControlPlaneConnection,
     shared_config: Arc<RwLock<Configuration>>,
+    update_manager: Arc<UpdateManager>,
     dns_cache: Arc<crate::dns::cache::DnsCache>,
+    snapshot_cache: Option<SnapshotCache>,
     reconnect_notify: mpsc::Sender<()>,
 ) -> Result<()> {
     // Connect to the Control Plane gRPC service
-    info!("Connecting to Control Plane gRPC service at {}", cp_url);
-    let mut client = ConfigClient::connect(cp_url, auth_token.to_string()).await?;
+    info!("Connecting to Control Plane gRPC service at {}", connection.url);
+    let mut client = ConfigClient::connect(
+        &connection.url,
+        connection.node_id.clone(),
+        connection.group.clone(),
+        connection.auth_token.clone(),
+        connection.tls.clone(),
+        connection.max_message_size,
+        connection.keepalive_interval,
+        connection.keepalive_timeout,
+    ).await?;
     
     // First, get a full configuration snapshot
     info!("Requesting initial configuration snapshot");
-    match client.get_config_snapshot().await {
-        Ok(snapshot) => {
-            info!("Received initial configuration with {} proxies, {} consumers, and {} plugin configs",
-                snapshot.proxies.len(), snapshot.consumers.len(), snapshot.plugin_configs.len());
-            
-            // Update shared configuration
-            {
-                let mut config = shared_config.write().await;
-                *config = snapshot;
-            }
-            
-            // Warm up DNS cache for all backend hosts
-            if let Err(e) = dns::warm_up_dns_cache(&dns_cache, &shared_config.read().await.proxies).await {
-                warn!("DNS cache warmup for initial proxies failed: {}", e);
-            }
-            
-            info!("Initial configuration loaded successfully");
-        },
+    let mut synced = match fetch_snapshot(&mut client).await {
+        Ok(synced) => synced,
         Err(e) => {
             error!("Failed to get initial configuration snapshot: {}", e);
             return Err(anyhow!("Failed to retrieve initial configuration: {}", e));
         }
+    };
+    {
+        let snapshot = synced.snapshot();
+        info!("Received initial configuration version {} with {} proxies, {} consumers, and {} plugin configs",
+            snapshot.version, snapshot.proxies.len(), snapshot.consumers.len(), snapshot.plugin_configs.len());
+    }
+    install_snapshot(&synced, &shared_config, &update_manager, snapshot_cache.as_ref()).await?;
+    
+    // Warm up DNS cache for all backend hosts
+    if let Err(e) = dns::warm_up_dns_cache(&dns_cache, &shared_config.read().await.proxies).await {
+        warn!("DNS cache warmup for initial proxies failed: {}", e);
     }
     
+    info!("Initial configuration loaded successfully");
+    
     // Now subscribe to ongoing configuration updates
     info!("Subscribing to configuration updates");
     let mut stream = client.subscribe().await?;
+    health::record_control_plane(Ok(()));
+    
+    // Report health while subscribed, so staged rollouts can halt on this node
+    let applied_version = Arc::new(AtomicU64::new(synced.version()));
+    let _health_reporter = AbortOnDrop(tokio::spawn(report_health(
+        client.clone(),
+        Arc::clone(&applied_version),
+        connection.health_report_interval,
+        connection.degraded_error_rate,
+    )));
+    let _rate_limit_sync = connection.rate_limit_sync_interval
+        .map(|interval| AbortOnDrop(tokio::spawn(sync_rate_limits(client.clone(), interval))));
     
     // Process configuration updates
     while let Some(update) = stream.next().await {
         match update {
             Ok(config_update) => {
-                info!("Received configuration update from Control Plane (version: {})", config_update.version);
+                // Confirms the snapshot just streamed
+                if config_update.base_version == synced.version() && config_update.version == synced.version() {
+                    debug!("Control Plane confirmed configuration version {}", synced.version());
+                    continue;
+                }
                 
-                // Convert proto message to domain model
-                let updated_config = match config_update.into_configuration() {
-                    Ok(config) => config,
-                    Err(e) => {
-                        error!("Failed to convert configuration update: {}", e);
-                        continue;
-                    }
-                };
+                info!("Received configuration update from Control Plane (version: {})", config_update.version);
                 
-                // Apply update to shared configuration
-                {
-                    let mut config = shared_config.write().await;
-                    let old_proxies_count = config.proxies.len();
-                    
-                    // Update configuration
-                    *config = updated_config;
-                    
-                    // Warm up DNS cache with new configuration
-                    drop(config); // Release the write lock
-                    
-                    let config_read = shared_config.read().await;
-                    // Only warm up if there are proxies and we've actually added new ones
-                    if !config_read.proxies.is_empty() && config_read.proxies.len() > old_proxies_count {
-                        if let Err(e) = dns::warm_up_dns_cache(&dns_cache, &config_read.proxies).await {
-                            warn!("DNS cache warmup for new proxies failed: {}", e);
-                        }
-                    }
-                    drop(config_read);
+                // Deltas apply on top of the version held. When one cannot be
+                // applied, start over from a full snapshot.
+                if let Err(e) = synced.apply(&config_update) {
+                    warn!("Cannot apply configuration update v{}: {}, requesting a full snapshot",
+                        config_update.version, e);
+                    synced = fetch_snapshot(&mut client).await
+                        .context("Failed to resynchronize configuration with the Control Plane")?;
                 }
                 
-                info!("Configuration updated successfully");
+                // Swap in a new snapshot for the request path; the proxy server
+                // resolves the backends of added and changed proxies
+                install_snapshot(&synced, &shared_config, &update_manager, snapshot_cache.as_ref()).await?;
+                
+                applied_version.store(synced.version(), Ordering::SeqCst);
+                info!("Configuration updated successfully to version {}", synced.version());
             },
             Err(e) => {
                 error!("Error receiving configuration update: {}", e);
@@ -226,3 +344,163 @@ async fn connect_to_control_plane(
     info!("Configuration update stream ended");
     Ok(())
 }
+
+/// Report health periodically: degraded when more than `degraded_error_rate`
+/// of the responses since the last report were 5xx errors. Each report also
+/// carries the traffic of every proxy since the previous one, and the health
+/// of the backends of every proxy.
+async fn report_health(
+    mut client: ConfigClient,
+    applied_version: Arc<AtomicU64>,
+    interval: Duration,
+    degraded_error_rate: f64,
+) {
+    // Too few requests say nothing about the configuration
+    const MIN_REQUESTS: u64 = 20;
+    
+    let mut timer = tokio::time::interval(interval);
+    timer.tick().await;
+    let (mut last_total, mut last_errors) = metrics::response_counts();
+    let mut last_traffic = metrics::proxy_traffic();
+    let mut last_report = Instant::now();
+    
+    loop {
+        timer.tick().await;
+        let (total, errors) = metrics::response_counts();
+        let requests = total.saturating_sub(last_total);
+        let server_errors = errors.saturating_sub(last_errors);
+        (last_total, last_errors) = (total, errors);
+        
+        let error_rate = if requests == 0 { 0.0 } else { server_errors as f64 / requests as f64 };
+        let status = if requests >= MIN_REQUESTS && error_rate > degraded_error_rate {
+            "degraded"
+        } else {
+            rollout::HEALTHY
+        };
+        
+        let report = HashMap::from([
+            ("requests".to_string(), requests.to_string()),
+            ("server_errors".to_string(), server_errors.to_string()),
+            ("error_rate".to_string(), format!("{:.4}", error_rate)),
+        ]);
+        
+        let traffic = metrics::proxy_traffic();
+        let elapsed = last_report.elapsed().as_secs_f64();
+        last_report = Instant::now();
+        let proxies = proxy_metrics(&traffic, &last_traffic, elapsed);
+        last_traffic = traffic;
+        
+        let health = proxy_health(backend_health::report());
+        
+        if let Err(e) = client.report_health(status, applied_version.load(Ordering::SeqCst), report, elapsed, proxies, health).await {
+            debug!("{}", e);
+        }
+    }
+}
+
+/// Exchange `cluster` rate limiting counters with the Control Plane every
+/// interval while subscribed
+async fn sync_rate_limits(mut client: ConfigClient, interval: Duration) {
+    let counters = cluster::counters();
+    let (tx, rx) = mpsc::channel(4);
+    
+    // The Control Plane answers the stream once the first report names this node
+    let first = ratelimit::report(client.node_id(), counters.take_unreported(cluster::unix_now()));
+    if tx.send(first).await.is_err() {
+        return;
+    }
+    let mut updates = match client.sync_rate_limits(rx).await {
+        Ok(updates) => updates,
+        Err(e) => {
+            warn!("{}, cluster rate limits only count this node's requests", e);
+            return;
+        },
+    };
+    
+    let mut timer = tokio::time::interval(interval);
+    timer.tick().await;
+    loop {
+        tokio::select! {
+            _ = timer.tick() => {
+                let report = ratelimit::report(client.node_id(), counters.take_unreported(cluster::unix_now()));
+                if tx.send(report).await.is_err() {
+                    break;
+                }
+            },
+            update = updates.message() => match update {
+                Ok(Some(update)) => counters.apply_remote(&ratelimit::counts(&update.counters), cluster::unix_now()),
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("Rate limit synchronization with the Control Plane failed: {}", e);
+                    break;
+                },
+            },
+        }
+    }
+    debug!("Stopped synchronizing rate limits until the next connection");
+}
+
+/// The traffic of each proxy that served requests between two readings
+fn proxy_metrics(
+    traffic: &HashMap<String, metrics::ProxyTraffic>,
+    earlier: &HashMap<String, metrics::ProxyTraffic>,
+    elapsed_seconds: f64,
+) -> Vec<ProxyMetrics> {
+    traffic.iter()
+        .map(|(proxy_id, total)| (proxy_id, total.since(&earlier.get(proxy_id).cloned().unwrap_or_default())))
+        .filter(|(_, interval)| interval.requests > 0)
+        .map(|(proxy_id, interval)| ProxyMetrics {
+            proxy_id: proxy_id.clone(),
+            requests: interval.requests,
+            requests_per_second: if elapsed_seconds > 0.0 { interval.requests as f64 / elapsed_seconds } else { 0.0 },
+            p99_latency_ms: interval.latency_quantile(0.99) * 1000.0,
+            status_codes: interval.status_codes.into_iter().collect(),
+        })
+        .collect()
+}
+
+/// The backend health of each proxy, as sent to the Control Plane
+fn proxy_health(report: Vec<backend_health::ProxyHealth>) -> Vec<ProxyHealth> {
+    report.into_iter()
+        .map(|proxy| ProxyHealth {
+            proxy_id: proxy.proxy_id,
+            status: proxy.status,
+            backends: proxy.backends.into_iter()
+                .map(|backend| BackendHealth {
+                    address: backend.address,
+                    up: backend.up,
+                    consecutive_failures: backend.consecutive_failures,
+                    last_error: backend.last_error.unwrap_or_default(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Fetch a full snapshot from the Control Plane and verify its checksum
+async fn fetch_snapshot(client: &mut ConfigClient) -> Result<SyncedSnapshot> {
+    let snapshot = client.stream_config_snapshot().await?;
+    SyncedSnapshot::new(snapshot).map_err(|e| anyhow!("Invalid configuration snapshot: {}", e))
+}
+
+/// Convert the held snapshot and make it the active configuration, then
+/// write it to the on-disk cache
+async fn install_snapshot(
+    synced: &SyncedSnapshot,
+    shared_config: &RwLock<Configuration>,
+    update_manager: &UpdateManager,
+    snapshot_cache: Option<&SnapshotCache>,
+) -> Result<()> {
+    let configuration = synced.snapshot().into_configuration()?;
+    *shared_config.write().await = configuration;
+    health::record_config_loaded();
+    update_manager.notify_config_changed()?;
+    
+    if let Some(cache) = snapshot_cache {
+        // Failing to cache only matters for the next restart
+        if let Err(e) = cache.store(synced.snapshot()).await {
+            warn!("Failed to cache configuration version {}: {}", synced.version(), e);
+        }
+    }
+    Ok(())
+}
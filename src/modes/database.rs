@@ -6,11 +6,15 @@ use tracing::{info, warn, error, debug};
 use chrono::Utc;
 
 use crate::config::env_config::EnvConfig;
-use crate::config::data_model::Configuration;
-use crate::database::DatabaseClient;
+use crate::config::data_model::{Configuration, ConfigurationDelta};
+use crate::database::{DatabaseClient, DatabaseOptions};
 use crate::proxy::ProxyServer;
+use crate::proxy::update_manager::UpdateManager;
 use crate::admin::AdminServer;
 use crate::dns::{self, DnsCache};
+use crate::acme::AcmeManager;
+use crate::acme::storage::DatabaseAcmeStorage;
+use crate::health;
 
 pub async fn run(config: EnvConfig) -> Result<()> {
     info!("Starting Ferrum Gateway in Database mode");
@@ -24,25 +28,38 @@ pub async fn run(config: EnvConfig) -> Result<()> {
         crate::config::data_model::DatabaseType::Postgres => crate::database::DatabaseType::Postgres,
         crate::config::data_model::DatabaseType::MySQL => crate::database::DatabaseType::MySQL,
         crate::config::data_model::DatabaseType::SQLite => crate::database::DatabaseType::SQLite,
+        crate::config::data_model::DatabaseType::MongoDB => crate::database::DatabaseType::MongoDB,
     };
     
     // Set up database client
-    let db_client = DatabaseClient::new(db_type, &db_url)
+    let db_client = DatabaseClient::with_options(db_type, &db_url, &DatabaseOptions::from_env_config(&config))
         .await
         .context("Failed to create database client")?;
     
+    // Refuse to run against a schema this version does not match
+    db_client.check_schema(config.db_auto_migrate).await?;
+    
+    // Purge tombstones that incremental polling no longer needs
+    let _tombstone_cleanup = db_client.spawn_tombstone_cleanup();
+    
     // Get DNS cache configuration
     let dns_ttl = config.dns_cache_ttl_seconds;
     let dns_overrides = config.dns_overrides.clone();
     
     // Create DNS cache
-    let dns_cache: Arc<crate::dns::cache::DnsCache> = Arc::new(DnsCache::new(dns_ttl, dns_overrides));
+    let dns_cache: Arc<crate::dns::cache::DnsCache> = Arc::new(DnsCache::new(dns_ttl, dns_overrides)
+        .with_min_ttl(config.dns_min_ttl)
+        .with_negative_ttl(config.dns_negative_ttl)
+        .with_stale_ttl(config.dns_stale_ttl)
+        .with_resolvers(&config.dns_resolvers)?);
     
     // Create shared configuration
     let shared_config = Arc::new(RwLock::new(Configuration {
         proxies: Vec::new(),
         consumers: Vec::new(),
         plugin_configs: Vec::new(),
+        certificates: Vec::new(),
+        snis: Vec::new(),
         last_updated_at: Utc::now(),
     }));
     
@@ -56,6 +73,7 @@ pub async fn run(config: EnvConfig) -> Result<()> {
         let mut config_write = shared_config.write().await;
         *config_write = initial_config;
     }
+    health::record_config_loaded();
     
     // Validate listen_path uniqueness
     validate_listen_path_uniqueness(&*shared_config.read().await)?;
@@ -113,13 +131,14 @@ pub async fn run(config: EnvConfig) -> Result<()> {
     });
     
     // Start proxy server if ports are configured
-    let _proxy_server = if config.proxy_http_port.is_some() || config.proxy_https_port.is_some() {
+    let update_manager = if config.proxy_http_port.is_some() || config.proxy_https_port.is_some() {
         info!("Starting proxy server");
         let proxy_server = ProxyServer::new(
             config.clone(),
             shared_config.clone(),
             Arc::clone(&dns_cache),
         )?;
+        let update_manager = proxy_server.get_update_manager();
         
         tokio::spawn(async move {
             if let Err(e) = proxy_server.start().await {
@@ -127,12 +146,21 @@ pub async fn run(config: EnvConfig) -> Result<()> {
             }
         });
         
-        Some(proxy_server)
+        Some(update_manager)
     } else {
         warn!("No proxy HTTP/HTTPS ports configured, proxy server will not be started");
         None
     };
     
+    // Start ACME certificate management, sharing issued certificates through the database
+    let _acme_handle = if config.acme_enabled {
+        let storage = DatabaseAcmeStorage::new(db_client.clone(), &config.acme_storage_path)
+            .context("Failed to initialize ACME storage")?;
+        Some(AcmeManager::new(&config, Arc::new(storage)).spawn())
+    } else {
+        None
+    };
+    
     // Start admin server if ports are configured
     let _admin_server = if config.admin_http_port.is_some() || config.admin_https_port.is_some() {
         info!("Starting admin server");
@@ -162,7 +190,6 @@ pub async fn run(config: EnvConfig) -> Result<()> {
     let poll_interval = config.db_poll_interval;
     let poll_check_interval = config.db_poll_check_interval;
     let use_incremental_polling = config.db_incremental_polling;
-    let dns_cache_for_polling: Arc<crate::dns::cache::DnsCache> = Arc::clone(&dns_cache);
     let shared_config_clone = Arc::clone(&shared_config);
     
     let _polling_handle = tokio::spawn(async move {
@@ -190,35 +217,18 @@ pub async fn run(config: EnvConfig) -> Result<()> {
                                                     delta.updated_consumers.len() + delta.deleted_consumer_ids.len(),
                                                     delta.updated_plugin_configs.len() + delta.deleted_plugin_config_ids.len());
                                                 
-                                                // Apply the delta to the shared configuration
-                                                {
-                                                    let mut config = shared_config_clone.write().await;
-                                                    delta.apply_to(&mut *config);
+                                                // Apply the delta to the shared configuration as one update
+                                                if let Err(e) = apply_delta(&delta, &shared_config_clone, update_manager.as_deref()).await {
+                                                    error!("{}", e);
+                                                    
+                                                    // Fallback to full config load on next poll
+                                                    poll_timer.reset();
+                                                    continue;
                                                 }
                                                 
                                                 // Update our tracking timestamp
                                                 last_update_timestamp = delta.last_updated_at;
                                                 
-                                                // Check for new backend hosts that need DNS resolution
-                                                let new_hosts = delta.updated_proxies.iter()
-                                                    .filter(|p| p.dns_override.is_none())
-                                                    .map(|p| p.backend_host.clone())
-                                                    .collect::<Vec<_>>();
-                                                
-                                                if !new_hosts.is_empty() {
-                                                    // Warm up DNS cache for new hosts in background
-                                                    for hostname in new_hosts {
-                                                        let dns_cache: Arc<crate::dns::cache::DnsCache> = Arc::clone(&dns_cache_for_polling);
-                                                        tokio::spawn(async move {
-                                                            if let Err(e) = dns_cache.resolve(&hostname).await {
-                                                                warn!("DNS warmup failed for host {}: {}", hostname, e);
-                                                            } else {
-                                                                debug!("DNS warmup successful for host {}", hostname);
-                                                            }
-                                                        });
-                                                    }
-                                                }
-                                                
                                                 info!("Configuration updated successfully using incremental update");
                                             } else {
                                                 debug!("Incremental update returned empty delta");
@@ -260,15 +270,11 @@ pub async fn run(config: EnvConfig) -> Result<()> {
                                     let mut config = shared_config_clone.write().await;
                                     *config = new_config;
                                 }
+                                notify_config_changed(update_manager.as_deref());
                                 
                                 // Update our tracking timestamp
                                 last_update_timestamp = new_config.last_updated_at;
                                 
-                                // After updating the configuration, warm up DNS cache for any new hosts
-                                if let Err(e) = dns::warm_up_dns_cache(&dns_cache_for_polling, &new_config.proxies).await {
-                                    warn!("DNS cache warmup failed: {}", e);
-                                }
-                                
                                 info!("Configuration updated successfully with full refresh");
                             } else {
                                 debug!("Full configuration refresh found no changes");
@@ -297,6 +303,36 @@ pub async fn run(config: EnvConfig) -> Result<()> {
     Ok(())
 }
 
+/// Applies a delta loaded from the database as one update: with a proxy server
+/// running, its update manager swaps in a snapshot holding all of the changes.
+/// A delta that would leave the configuration invalid is not applied at all.
+pub(crate) async fn apply_delta(
+    delta: &ConfigurationDelta,
+    shared_config: &RwLock<Configuration>,
+    update_manager: Option<&UpdateManager>,
+) -> Result<()> {
+    match update_manager {
+        Some(update_manager) => update_manager.apply(delta).await?,
+        None => {
+            let mut config = shared_config.write().await;
+            *config = crate::proxy::update_manager::apply_delta(&config, delta)?;
+        }
+    }
+    health::record_config_loaded();
+    Ok(())
+}
+
+/// Records that a configuration change was loaded and asks the proxy server,
+/// if one is running, to rebuild its configuration snapshot
+pub(crate) fn notify_config_changed(update_manager: Option<&UpdateManager>) {
+    health::record_config_loaded();
+    if let Some(update_manager) = update_manager {
+        if let Err(e) = update_manager.notify_config_changed() {
+            error!("Failed to notify configuration change: {}", e);
+        }
+    }
+}
+
 fn validate_listen_path_uniqueness(config: &Configuration) -> Result<()> {
     let mut seen_paths = std::collections::HashSet::new();
     
@@ -0,0 +1,495 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{Result, Context, anyhow, bail};
+use chrono::Utc;
+use futures::StreamExt;
+use hyper::{Body, Response, StatusCode};
+use hyper::client::HttpConnector;
+use hyperlocal::{UnixClientExt, UnixConnector};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tokio::sync::{mpsc, RwLock};
+use tracing::{info, warn, error, debug};
+
+use crate::config::env_config::EnvConfig;
+use crate::config::data_model::{Configuration, PluginAssociation, PluginConfig, PluginScope, Proxy, DEFAULT_WORKSPACE};
+use crate::config::declarative::ConfigDiff;
+use crate::proxy::ProxyServer;
+use crate::proxy::update_manager::UpdateManager;
+use crate::dns::{self, DnsCache};
+use crate::acme::AcmeManager;
+use crate::acme::storage::FileAcmeStorage;
+use crate::modes::file::validate_configuration;
+use crate::health;
+
+/// Quiet period after a container event before the containers are listed again,
+/// so that a `docker compose up` of several services causes a single reload
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// How long to wait before reconnecting to the event stream of the daemon
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Container events after which the set of routable containers may have changed
+const CONTAINER_EVENTS: &[&str] = &["start", "die", "destroy", "pause", "unpause", "rename", "update"];
+
+pub async fn run(config: EnvConfig) -> Result<()> {
+    info!("Starting Ferrum Gateway in Docker mode");
+
+    let labels = LabelSettings {
+        prefix: config.docker_label_prefix.clone(),
+        network: config.docker_network.clone(),
+    };
+    let docker = Arc::new(DockerClient::new(&config.docker_host)?);
+
+    // Load initial configuration
+    info!("Reading proxies from the labels of containers on {}", config.docker_host);
+    let initial_config = load_configuration(&docker, &labels).await
+        .context("Failed to read containers from the Docker daemon")?;
+    validate_configuration(&initial_config)?;
+    info!("Found {} proxies on running containers", initial_config.proxies.len());
+
+    // Create DNS cache
+    let dns_cache = Arc::new(DnsCache::new(config.dns_cache_ttl_seconds, config.dns_overrides.clone())
+        .with_min_ttl(config.dns_min_ttl)
+        .with_negative_ttl(config.dns_negative_ttl)
+        .with_stale_ttl(config.dns_stale_ttl)
+        .with_resolvers(&config.dns_resolvers)?);
+    if let Err(e) = dns::warm_up_dns_cache(&dns_cache, &initial_config.proxies).await {
+        warn!("DNS cache warmup failed: {}", e);
+    }
+
+    // Create shared configuration
+    let shared_config = Arc::new(RwLock::new(initial_config));
+    health::record_config_loaded();
+
+    // Start proxy server with the configuration
+    info!("Starting proxy server");
+    let proxy_server = ProxyServer::new(
+        config.clone(),
+        Arc::clone(&shared_config),
+        Arc::clone(&dns_cache),
+    )?;
+    let update_manager = proxy_server.get_update_manager();
+
+    let _proxy_handle = tokio::spawn(async move {
+        if let Err(e) = proxy_server.start().await {
+            error!("Proxy server error: {}", e);
+        }
+    });
+
+    // Start ACME certificate management, keeping issued certificates on disk
+    let _acme_handle = if config.acme_enabled {
+        let storage = FileAcmeStorage::new(&config.acme_storage_path)
+            .context("Failed to initialize ACME storage")?;
+        Some(AcmeManager::new(&config, Arc::new(storage)).spawn())
+    } else {
+        None
+    };
+
+    // Container events request a reload, which a single task applies one at a time
+    let (reload_tx, reload_rx) = mpsc::channel(1);
+    let _events_handle = tokio::spawn(watch_events(Arc::clone(&docker), labels.clone(), reload_tx));
+    let _reload_handle = tokio::spawn(run_reload_loop(
+        reload_rx,
+        docker,
+        labels,
+        Arc::clone(&shared_config),
+        update_manager,
+    ));
+
+    // Wait for shutdown signal
+    tokio::signal::ctrl_c().await
+        .context("Failed to listen for ctrl-c signal")?;
+
+    info!("Shutdown signal received, stopping services");
+
+    // Allow in-flight requests to complete
+    info!("Waiting for in-flight requests to complete...");
+    tokio::time::sleep(Duration::from_secs(5)).await;
+
+    info!("Shutdown complete");
+    Ok(())
+}
+
+/// How proxies are read from container labels
+#[derive(Debug, Clone)]
+pub struct LabelSettings {
+    /// Label prefix, `ferrum` for labels like `ferrum.listen_path`
+    pub prefix: String,
+    /// Network whose address backends are reached at
+    pub network: Option<String>,
+}
+
+/// A running container as listed by the Docker Engine API
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Container {
+    pub id: String,
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    #[serde(default)]
+    pub ports: Vec<ContainerPort>,
+    #[serde(default)]
+    pub network_settings: NetworkSettings,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerPort {
+    pub private_port: u16,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct NetworkSettings {
+    #[serde(default)]
+    pub networks: HashMap<String, ContainerNetwork>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ContainerNetwork {
+    #[serde(rename = "IPAddress", default)]
+    pub ip_address: String,
+}
+
+impl Container {
+    /// The container name without Docker's leading slash, or its short ID
+    pub fn name(&self) -> String {
+        self.names.first()
+            .map(|name| name.trim_start_matches('/').to_string())
+            .unwrap_or_else(|| self.id.chars().take(12).collect())
+    }
+
+    /// The container's address on the given network, or on the first network
+    /// by name that gave it one
+    fn address(&self, network: Option<&str>) -> Option<String> {
+        let networks = &self.network_settings.networks;
+        match network {
+            Some(network) => networks.get(network)
+                .map(|n| n.ip_address.clone())
+                .filter(|ip| !ip.is_empty()),
+            None => {
+                let mut names: Vec<&String> = networks.keys().collect();
+                names.sort();
+                names.into_iter()
+                    .map(|name| networks[name].ip_address.clone())
+                    .find(|ip| !ip.is_empty())
+            }
+        }
+    }
+}
+
+/// Builds the configuration from the labels of the given containers. Containers
+/// with invalid labels are skipped with a warning so one bad container does not
+/// take the routes of the others down.
+pub fn configuration_from_containers(containers: &[Container], labels: &LabelSettings) -> Configuration {
+    let mut config = Configuration::default();
+
+    for container in containers {
+        match proxies_from_container(container, labels) {
+            Ok((proxies, plugin_configs)) => {
+                config.proxies.extend(proxies);
+                config.plugin_configs.extend(plugin_configs);
+            },
+            Err(e) => warn!("Ignoring container {}: {:#}", container.name(), e),
+        }
+    }
+
+    config.proxies.sort_by(|a, b| a.id.cmp(&b.id));
+    config.plugin_configs.sort_by(|a, b| a.id.cmp(&b.id));
+    config
+}
+
+/// Reads the proxies of one container. Labels directly under the prefix
+/// (`ferrum.listen_path`) describe a proxy named after the container, and
+/// `ferrum.proxies.<name>.<field>` labels describe further proxies. Any proxy
+/// field can be set this way; values are read as JSON where they parse as JSON.
+pub fn proxies_from_container(container: &Container, labels: &LabelSettings) -> Result<(Vec<Proxy>, Vec<PluginConfig>)> {
+    let prefix = format!("{}.", labels.prefix);
+    if container.labels.get(&format!("{}enable", prefix)).map(String::as_str) != Some("true") {
+        return Ok((Vec::new(), Vec::new()));
+    }
+
+    // Labels by proxy name, "" being the proxy named after the container
+    let mut groups: BTreeMap<&str, BTreeMap<&str, &str>> = BTreeMap::new();
+    for (key, value) in &container.labels {
+        let key = match key.strip_prefix(&prefix) {
+            Some(key) if key != "enable" => key,
+            _ => continue,
+        };
+        let (group, field) = match key.strip_prefix("proxies.").and_then(|rest| rest.split_once('.')) {
+            Some((group, field)) => (group, field),
+            None => ("", key),
+        };
+        groups.entry(group).or_default().insert(field, value);
+    }
+
+    if groups.is_empty() {
+        bail!("{}enable is set but no proxy is described by its labels", prefix);
+    }
+
+    let name = container.name();
+    let address = container.address(labels.network.as_deref())
+        .ok_or_else(|| match &labels.network {
+            Some(network) => anyhow!("it has no address on network {}", network),
+            None => anyhow!("it has no network address"),
+        })?;
+
+    let mut proxies = Vec::new();
+    let mut plugin_configs = Vec::new();
+    for (group, fields) in groups {
+        let id = if group.is_empty() { format!("docker-{}", name) } else { format!("docker-{}-{}", name, group) };
+        let (proxy, plugins) = build_proxy(container, &id, &address, &fields)
+            .with_context(|| format!("invalid labels for proxy {}", id))?;
+        proxies.push(proxy);
+        plugin_configs.extend(plugins);
+    }
+
+    Ok((proxies, plugin_configs))
+}
+
+fn build_proxy(
+    container: &Container,
+    id: &str,
+    address: &str,
+    fields: &BTreeMap<&str, &str>,
+) -> Result<(Proxy, Vec<PluginConfig>)> {
+    let now = Utc::now();
+
+    let mut object = Map::new();
+    object.insert("id".to_string(), Value::from(id));
+    object.insert("name".to_string(), Value::from(container.name()));
+    object.insert("backend_protocol".to_string(), Value::from("http"));
+    object.insert("backend_host".to_string(), Value::from(address));
+    object.insert("backend_connect_timeout_ms".to_string(), Value::from(5000));
+    object.insert("backend_read_timeout_ms".to_string(), Value::from(60000));
+    object.insert("backend_write_timeout_ms".to_string(), Value::from(60000));
+    object.insert("created_at".to_string(), serde_json::to_value(now)?);
+    object.insert("updated_at".to_string(), serde_json::to_value(now)?);
+
+    // A container exposing a single port needs no port label
+    let mut exposed: Vec<u16> = container.ports.iter().map(|p| p.private_port).collect();
+    exposed.sort();
+    exposed.dedup();
+    if let [port] = exposed.as_slice() {
+        object.insert("backend_port".to_string(), Value::from(*port));
+    }
+
+    let mut plugins = Vec::new();
+    for (&field, &value) in fields {
+        if let Some(plugin_name) = field.strip_prefix("plugins.") {
+            let plugin_config: Value = serde_json::from_str(value)
+                .with_context(|| format!("plugin {} needs a JSON object as its configuration", plugin_name))?;
+            plugins.push(PluginConfig {
+                id: format!("{}-{}", id, plugin_name),
+                plugin_name: plugin_name.to_string(),
+                workspace: DEFAULT_WORKSPACE.to_string(),
+                version: 1,
+                tags: Vec::new(),
+                config: plugin_config,
+                scope: PluginScope::Proxy,
+                proxy_id: Some(id.to_string()),
+                enabled: true,
+                created_at: now,
+                updated_at: now,
+            });
+            continue;
+        }
+
+        let field = if field == "port" { "backend_port" } else { field };
+        let value = serde_json::from_str(value).unwrap_or_else(|_| Value::from(value));
+        object.insert(field.to_string(), value);
+    }
+
+    if !object.contains_key("listen_path") {
+        bail!("listen_path is required");
+    }
+    if !object.contains_key("backend_port") {
+        bail!("port is required since the container exposes {} ports", exposed.len());
+    }
+
+    let mut proxy: Proxy = serde_json::from_value(Value::Object(object))?;
+
+    // Fields that did not make it into the proxy are most likely misspelled
+    let known = serde_json::to_value(&proxy)?;
+    for &field in fields.keys() {
+        if field != "port" && !field.starts_with("plugins.") && known.get(field).is_none() {
+            warn!("Ignoring unknown label field {} on proxy {}", field, id);
+        }
+    }
+
+    // A `workspace` label moves the proxy's plugin configs along with it
+    for plugin in &mut plugins {
+        plugin.workspace = proxy.workspace.clone();
+    }
+    proxy.plugins.extend(plugins.iter().map(|plugin| PluginAssociation {
+        plugin_config_id: plugin.id.clone(),
+        embedded_config: None,
+    }));
+
+    Ok((proxy, plugins))
+}
+
+/// Lists the containers that opted in and builds the configuration from their labels
+async fn load_configuration(docker: &DockerClient, labels: &LabelSettings) -> Result<Configuration> {
+    let filters = serde_json::json!({ "label": [format!("{}.enable=true", labels.prefix)] });
+    let response = docker.get(&format!("/containers/json?filters={}", encode(&filters))).await?;
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    let containers: Vec<Container> = serde_json::from_slice(&body)
+        .context("Invalid container list from the Docker daemon")?;
+
+    Ok(configuration_from_containers(&containers, labels))
+}
+
+/// Follows container events and requests a reload for each relevant one. A
+/// reload is also requested after reconnecting, since events may have been missed.
+async fn watch_events(docker: Arc<DockerClient>, labels: LabelSettings, reload_tx: mpsc::Sender<()>) {
+    let filters = serde_json::json!({
+        "type": ["container"],
+        "event": CONTAINER_EVENTS,
+        "label": [format!("{}.enable=true", labels.prefix)],
+    });
+    let path = format!("/events?filters={}", encode(&filters));
+    let mut reconnecting = false;
+
+    loop {
+        let response = match docker.get(&path).await {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Failed to follow Docker events: {:#}", e);
+                tokio::time::sleep(RECONNECT_INTERVAL).await;
+                reconnecting = true;
+                continue;
+            }
+        };
+
+        if reconnecting {
+            info!("Reconnected to Docker events");
+            let _ = reload_tx.try_send(());
+            reconnecting = false;
+        }
+
+        // The daemon sends one JSON object per event
+        let mut stream = response.into_body();
+        let mut buffer = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    warn!("Docker event stream failed: {}", e);
+                    break;
+                }
+            };
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                if let Ok(event) = serde_json::from_slice::<Value>(&line) {
+                    debug!(
+                        "Container {} event: {}",
+                        event["Actor"]["Attributes"]["name"].as_str().unwrap_or("?"),
+                        event["Action"].as_str().unwrap_or("?")
+                    );
+                    // A full channel already holds a pending reload
+                    let _ = reload_tx.try_send(());
+                }
+            }
+        }
+
+        tokio::time::sleep(RECONNECT_INTERVAL).await;
+        reconnecting = true;
+    }
+}
+
+async fn run_reload_loop(
+    mut reload_rx: mpsc::Receiver<()>,
+    docker: Arc<DockerClient>,
+    labels: LabelSettings,
+    shared_config: Arc<RwLock<Configuration>>,
+    update_manager: Arc<UpdateManager>,
+) {
+    while reload_rx.recv().await.is_some() {
+        // Let a burst of events settle into one reload
+        tokio::time::sleep(RELOAD_DEBOUNCE).await;
+        while reload_rx.try_recv().is_ok() {}
+
+        match reload_configuration(&docker, &labels, &shared_config, &update_manager).await {
+            Ok(()) => health::record_config_source(Ok(())),
+            Err(e) => {
+                error!("Docker configuration reload failed, keeping the last good configuration: {:#}", e);
+                health::record_config_source(Err(format!("{:#}", e)));
+            }
+        }
+    }
+}
+
+/// Lists the containers again and applies the resulting configuration when it
+/// differs from the running one
+async fn reload_configuration(
+    docker: &DockerClient,
+    labels: &LabelSettings,
+    shared_config: &RwLock<Configuration>,
+    update_manager: &UpdateManager,
+) -> Result<()> {
+    let new_config = load_configuration(docker, labels).await?;
+    validate_configuration(&new_config)?;
+
+    let diff = ConfigDiff::compute(&*shared_config.read().await, &new_config);
+    if diff.is_empty() {
+        debug!("Container change does not affect any proxy");
+        return Ok(());
+    }
+
+    for id in &diff.proxies.created {
+        info!("Adding proxy {} from container labels", id);
+    }
+    for id in &diff.proxies.updated {
+        info!("Updating proxy {} from container labels", id);
+    }
+    for id in &diff.proxies.deleted {
+        info!("Removing proxy {}, its container is gone", id);
+    }
+
+    *shared_config.write().await = new_config;
+    health::record_config_loaded();
+    update_manager.notify_config_changed()?;
+
+    Ok(())
+}
+
+fn encode(filters: &Value) -> String {
+    url::form_urlencoded::byte_serialize(filters.to_string().as_bytes()).collect()
+}
+
+/// Client for the Docker Engine API over its unix socket or TCP
+enum DockerClient {
+    Unix(hyper::Client<UnixConnector>, PathBuf),
+    Tcp(hyper::Client<HttpConnector>, String),
+}
+
+impl DockerClient {
+    fn new(docker_host: &str) -> Result<Self> {
+        if let Some(socket) = docker_host.strip_prefix("unix://") {
+            Ok(DockerClient::Unix(hyper::Client::unix(), PathBuf::from(socket)))
+        } else if let Some(addr) = docker_host.strip_prefix("tcp://") {
+            Ok(DockerClient::Tcp(hyper::Client::new(), format!("http://{}", addr.trim_end_matches('/'))))
+        } else {
+            Err(anyhow!("Unsupported Docker host {}, expected unix:// or tcp://", docker_host))
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<Response<Body>> {
+        let response = match self {
+            DockerClient::Unix(client, socket) => client.get(hyperlocal::Uri::new(socket, path).into()).await,
+            DockerClient::Tcp(client, base) => client.get(format!("{}{}", base, path).parse()?).await,
+        }.context("Docker daemon request failed")?;
+
+        if response.status() != StatusCode::OK {
+            return Err(anyhow!("Docker daemon answered {} for {}", response.status(), path));
+        }
+        Ok(response)
+    }
+}
@@ -1,16 +1,21 @@
 use std::sync::Arc;
 use std::path::Path;
-use std::fs;
 use std::time::Duration;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
 use anyhow::{Result, Context};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use tracing::{info, warn, error, debug};
 
 use crate::config::env_config::EnvConfig;
 use crate::config::data_model::Configuration;
 use crate::proxy::ProxyServer;
+use crate::proxy::update_manager::UpdateManager;
 use crate::config::file_config;
+use crate::config::validation;
 use crate::dns::{self, DnsCache};
+use crate::acme::AcmeManager;
+use crate::acme::storage::FileAcmeStorage;
+use crate::health;
 
 pub async fn run(config: EnvConfig) -> Result<()> {
     info!("Starting Ferrum Gateway in File mode");
@@ -25,18 +30,23 @@ pub async fn run(config: EnvConfig) -> Result<()> {
     let initial_config = load_configuration_from_file(config_path)
         .context("Failed to load initial configuration from file")?;
     
-    // Validate listen_path uniqueness
-    validate_listen_path_uniqueness(&initial_config)?;
+    // Validate the configuration before serving it
+    validate_configuration(&initial_config)?;
     
     // Get DNS cache configuration
     let dns_ttl = config.dns_cache_ttl_seconds;
     let dns_overrides = config.dns_overrides.clone();
     
     // Create DNS cache
-    let dns_cache: Arc<crate::dns::cache::DnsCache> = Arc::new(DnsCache::new(dns_ttl, dns_overrides));
+    let dns_cache: Arc<crate::dns::cache::DnsCache> = Arc::new(DnsCache::new(dns_ttl, dns_overrides)
+        .with_min_ttl(config.dns_min_ttl)
+        .with_negative_ttl(config.dns_negative_ttl)
+        .with_stale_ttl(config.dns_stale_ttl)
+        .with_resolvers(&config.dns_resolvers)?);
     
     // Create shared configuration
     let shared_config = Arc::new(RwLock::new(initial_config));
+    health::record_config_loaded();
     
     // Load all proxies from config for DNS cache initialization
     {
@@ -65,6 +75,7 @@ pub async fn run(config: EnvConfig) -> Result<()> {
         Arc::clone(&shared_config),
         Arc::clone(&dns_cache),
     )?;
+    let update_manager = proxy_server.get_update_manager();
     
     let _proxy_handle = tokio::spawn(async move {
         if let Err(e) = proxy_server.start().await {
@@ -72,107 +83,54 @@ pub async fn run(config: EnvConfig) -> Result<()> {
         }
     });
     
-    // Set up signal handling for config reloading
-    let shared_config_clone = Arc::clone(&shared_config);
-    let config_path_clone = config_path.clone();
-    let dns_cache_for_reload = Arc::clone(&dns_cache);
+    // Start ACME certificate management, keeping issued certificates on disk
+    let _acme_handle = if config.acme_enabled {
+        let storage = FileAcmeStorage::new(&config.acme_storage_path)
+            .context("Failed to initialize ACME storage")?;
+        Some(AcmeManager::new(&config, Arc::new(storage)).spawn())
+    } else {
+        None
+    };
     
-    let _reload_handle = tokio::spawn(async move {
-        #[cfg(unix)]
-        {
-            use tokio::signal::unix::{signal, SignalKind};
-            
-            // Set up SIGHUP handler for config reload
-            let mut sighup = signal(SignalKind::hangup()).unwrap();
-            
-            loop {
-                // Wait for SIGHUP
-                sighup.recv().await;
-                info!("Received SIGHUP, reloading configuration from {}", config_path_clone);
-                
-                // Reload configuration
-                match load_configuration_from_file(&config_path_clone) {
-                    Ok(new_config) => {
-                        // Validate listen_path uniqueness
-                        if let Err(e) = validate_listen_path_uniqueness(&new_config) {
-                            error!("Configuration validation failed during reload: {}", e);
-                            continue;
-                        }
-                        
-                        // Update shared configuration
-                        let mut config = shared_config_clone.write().await;
-                        *config = new_config;
-                        info!("Configuration reloaded successfully");
-                        
-                        // Warm up DNS cache with new configuration
-                        drop(config); // Release the write lock
-                        {
-                            let config_read = shared_config_clone.read().await;
-                            if !config_read.proxies.is_empty() {
-                                // Warm up DNS cache
-                                if let Err(e) = dns::warm_up_dns_cache(&dns_cache_for_reload, &config_read.proxies).await {
-                                    warn!("DNS cache warmup failed: {}", e);
-                                }
-                            }
-                        }
-                    },
-                    Err(e) => {
-                        error!("Failed to reload configuration: {}", e);
-                    }
-                }
-            }
-        }
+    // Reloads are requested by SIGHUP and by changes to the configuration file,
+    // and applied one at a time by a single task
+    let (reload_tx, reload_rx) = mpsc::channel(1);
+    
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
         
-        #[cfg(not(unix))]
-        {
-            // On non-unix platforms, we can't use SIGHUP, so we'll periodically check for changes
-            let mut interval = tokio::time::interval(Duration::from_secs(30));
-            let mut last_modified = get_last_modified(&config_path_clone).unwrap_or_else(|_| std::time::SystemTime::now());
-            
-            loop {
-                interval.tick().await;
-                
-                // Check if file was modified
-                if let Ok(modified) = get_last_modified(&config_path_clone) {
-                    if modified > last_modified {
-                        info!("Configuration file changed, reloading from {}", config_path_clone);
-                        last_modified = modified;
-                        
-                        // Reload configuration
-                        match load_configuration_from_file(&config_path_clone) {
-                            Ok(new_config) => {
-                                // Validate listen_path uniqueness
-                                if let Err(e) = validate_listen_path_uniqueness(&new_config) {
-                                    error!("Configuration validation failed during reload: {}", e);
-                                    continue;
-                                }
-                                
-                                // Update shared configuration
-                                let mut config = shared_config_clone.write().await;
-                                *config = new_config;
-                                info!("Configuration reloaded successfully");
-                                
-                                // Warm up DNS cache with new configuration
-                                drop(config); // Release the write lock
-                                {
-                                    let config_read = shared_config_clone.read().await;
-                                    if !config_read.proxies.is_empty() {
-                                        // Warm up DNS cache
-                                        if let Err(e) = dns::warm_up_dns_cache(&dns_cache_for_reload, &config_read.proxies).await {
-                                            warn!("DNS cache warmup failed: {}", e);
-                                        }
-                                    }
-                                }
-                            },
-                            Err(e) => {
-                                error!("Failed to reload configuration: {}", e);
-                            }
-                        }
-                    }
-                }
+        let mut sighup = signal(SignalKind::hangup())
+            .context("Failed to install SIGHUP handler")?;
+        let reload_tx = reload_tx.clone();
+        tokio::spawn(async move {
+            while sighup.recv().await.is_some() {
+                info!("Received SIGHUP, reloading configuration");
+                let _ = reload_tx.try_send(ReloadTrigger::Signal);
+            }
+        });
+    }
+    
+    // The watcher stops when dropped, so it is held until shutdown
+    let _watcher = if config.file_watch {
+        match watch_configuration(config_path, reload_tx.clone()) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                warn!("Failed to watch {} for changes, reload with SIGHUP instead: {:#}", config_path, e);
+                None
             }
         }
-    });
+    } else {
+        None
+    };
+    
+    let _reload_handle = tokio::spawn(run_reload_loop(
+        reload_rx,
+        config_path.clone(),
+        Duration::from_millis(config.file_watch_debounce_ms),
+        Arc::clone(&shared_config),
+        update_manager,
+    ));
 
     // Wait for shutdown signal
     tokio::signal::ctrl_c().await
@@ -188,79 +146,216 @@ pub async fn run(config: EnvConfig) -> Result<()> {
     Ok(())
 }
 
-#[cfg(not(unix))]
-fn get_last_modified(path: &str) -> Result<std::time::SystemTime> {
-    let path = Path::new(path);
-    if path.is_dir() {
-        // Find the most recently modified file in the directory
-        let mut latest = std::time::SystemTime::UNIX_EPOCH;
-        
-        for entry in std::fs::read_dir(path)? {
-            let entry = entry?;
-            let metadata = entry.metadata()?;
-            if metadata.is_file() {
-                let modified = metadata.modified()?;
-                if modified > latest {
-                    latest = modified;
-                }
+/// What caused a configuration reload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReloadTrigger {
+    Signal,
+    FileChanged,
+}
+
+/// Watches the configuration file, or every file under a configuration directory.
+/// A single file is watched through its parent directory, since editors and
+/// config management tools usually replace files by renaming over them.
+fn watch_configuration(config_path: &str, reload_tx: mpsc::Sender<ReloadTrigger>) -> Result<RecommendedWatcher> {
+    let path = Path::new(config_path);
+    let (watch_path, target, mode) = if path.is_dir() {
+        (path.to_path_buf(), None, RecursiveMode::Recursive)
+    } else {
+        let parent = path.parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        (parent.to_path_buf(), path.file_name().map(|name| name.to_os_string()), RecursiveMode::NonRecursive)
+    };
+    
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+        let event = match result {
+            Ok(event) => event,
+            Err(e) => {
+                warn!("Configuration watch error: {}", e);
+                return;
             }
+        };
+        
+        if matches!(event.kind, EventKind::Access(_) | EventKind::Other) {
+            return;
         }
         
-        if latest == std::time::SystemTime::UNIX_EPOCH {
-            Err(anyhow::anyhow!("No files found in directory"))
-        } else {
-            Ok(latest)
+        let relevant = match &target {
+            Some(name) => event.paths.iter().any(|p| p.file_name() == Some(name.as_os_str())),
+            None => true,
+        };
+        
+        if relevant {
+            debug!("Configuration change detected: {:?}", event);
+            // A full channel already holds a pending reload
+            let _ = reload_tx.try_send(ReloadTrigger::FileChanged);
+        }
+    }).context("Failed to create configuration file watcher")?;
+    
+    watcher.watch(&watch_path, mode)
+        .context(format!("Failed to watch {}", watch_path.display()))?;
+    
+    info!("Watching {} for configuration changes", config_path);
+    Ok(watcher)
+}
+
+async fn run_reload_loop(
+    mut reload_rx: mpsc::Receiver<ReloadTrigger>,
+    config_path: String,
+    debounce: Duration,
+    shared_config: Arc<RwLock<Configuration>>,
+    update_manager: Arc<UpdateManager>,
+) {
+    while let Some(trigger) = reload_rx.recv().await {
+        if trigger == ReloadTrigger::FileChanged {
+            // Wait for the writer to finish; further events in the meantime are absorbed
+            tokio::time::sleep(debounce).await;
+            while reload_rx.try_recv().is_ok() {}
+            info!("Configuration file changed, reloading from {}", config_path);
+        }
+        
+        match reload_configuration(&config_path, &shared_config, &update_manager).await {
+            Ok(()) => health::record_config_source(Ok(())),
+            Err(e) => {
+                error!("Configuration reload failed, keeping the last good configuration: {:#}", e);
+                health::record_config_source(Err(format!("{:#}", e)));
+            }
         }
-    } else {
-        // Just get the modification time of the file
-        let metadata = std::fs::metadata(path)?;
-        metadata.modified().map_err(|e| e.into())
     }
 }
 
-fn load_configuration_from_file(config_path: &str) -> Result<Configuration> {
+/// Loads and validates the configuration, then swaps it in and rebuilds the
+/// request path snapshot. Nothing is applied unless the whole file is valid.
+async fn reload_configuration(
+    config_path: &str,
+    shared_config: &RwLock<Configuration>,
+    update_manager: &UpdateManager,
+) -> Result<()> {
+    let new_config = load_configuration_from_file(config_path)?;
+    validate_configuration(&new_config)?;
+    
+    let summary = format!(
+        "{} proxies, {} consumers, {} plugin configs",
+        new_config.proxies.len(), new_config.consumers.len(), new_config.plugin_configs.len()
+    );
+    *shared_config.write().await = new_config;
+    health::record_config_loaded();
+    
+    update_manager.notify_config_changed()?;
+    info!("Configuration reloaded successfully ({})", summary);
+    
+    Ok(())
+}
+
+pub(crate) fn load_configuration_from_file(config_path: &str) -> Result<Configuration> {
     let path = Path::new(config_path);
     
     if path.is_dir() {
         // If it's a directory, load all config files from it
-        return file_config::load_from_directory(path);
+        file_config::load_from_directory(path)
     } else {
-        // Read file content
-        let content = fs::read_to_string(path)
-            .context(format!("Failed to read configuration file: {}", config_path))?;
-        
-        // Parse based on file extension
-        if let Some(extension) = path.extension() {
-            if extension == "json" {
-                return file_config::parse_json_config(&content);
-            } else if extension == "yaml" || extension == "yml" {
-                return file_config::parse_yaml_config(&content);
-            }
-        }
-        
-        // If no extension or unrecognized, try both formats
-        if let Ok(config) = file_config::parse_json_config(&content) {
-            return Ok(config);
-        }
-        if let Ok(config) = file_config::parse_yaml_config(&content) {
-            return Ok(config);
-        }
-        
-        anyhow::bail!("Unsupported configuration file format, expected JSON or YAML")
+        file_config::load_file(path)
+            .context(format!("Failed to load configuration file: {}", config_path))
     }
 }
 
-fn validate_listen_path_uniqueness(config: &Configuration) -> Result<()> {
-    let mut seen_paths = std::collections::HashSet::new();
+pub(crate) fn validate_configuration(config: &Configuration) -> Result<()> {
+    validate_listen_paths(config)?;
     
+    // Plugins attached by ID must exist in the same configuration
     for proxy in &config.proxies {
-        if !seen_paths.insert(&proxy.listen_path) {
-            return Err(anyhow::anyhow!(
-                "Duplicate listen_path detected: {}. All paths must be unique.", 
-                proxy.listen_path
-            ));
+        for association in &proxy.plugins {
+            if association.embedded_config.is_none()
+                && !config.plugin_configs.iter().any(|pc| pc.id == association.plugin_config_id)
+            {
+                return Err(anyhow::anyhow!(
+                    "Proxy {} references unknown plugin config {}",
+                    proxy.id, association.plugin_config_id
+                ));
+            }
         }
     }
     
     Ok(())
 }
+
+fn validate_listen_paths(config: &Configuration) -> Result<()> {
+    let report = validation::check_listen_paths(config);
+    if report.errors.is_empty() {
+        return Ok(());
+    }
+    
+    let problems: Vec<String> = report.errors.iter()
+        .map(|issue| format!("{}: {}", issue.path, issue.message))
+        .collect();
+    Err(anyhow::anyhow!("Invalid listen paths: {}", problems.join("; ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::future::Future;
+    use crate::config::snapshot::ConfigurationSnapshot;
+    use crate::discovery::{DiscoverySettings, ServiceDiscovery};
+    use crate::modes::OperationMode;
+    
+    fn config_with_proxy(id: &str, listen_path: &str) -> String {
+        format!(
+            "proxies:\n  - id: \"{}\"\n    listen_path: \"{}\"\n    backend_protocol: \"http\"\n    backend_host: \"example.com\"\n    backend_port: 80\n",
+            id, listen_path
+        )
+    }
+    
+    async fn wait_until<F, Fut>(mut condition: F) -> bool
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = bool>,
+    {
+        for _ in 0..100 {
+            if condition().await {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        false
+    }
+    
+    #[tokio::test]
+    async fn test_file_change_reloads_configuration() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ferrum.yaml");
+        std::fs::write(&path, config_with_proxy("first", "/first")).unwrap();
+        let config_path = path.to_str().unwrap().to_string();
+        
+        let initial = load_configuration_from_file(&config_path).unwrap();
+        let shared_config = Arc::new(RwLock::new(initial.clone()));
+        let snapshot = ConfigurationSnapshot::shared(initial);
+        let dns_cache = Arc::new(DnsCache::new(300, HashMap::new()));
+        let discovery = ServiceDiscovery::new(DiscoverySettings::from_env_config(&EnvConfig::default()), dns_cache);
+        let update_manager = Arc::new(UpdateManager::new(Arc::clone(&shared_config), Arc::clone(&snapshot), discovery));
+        
+        let (reload_tx, reload_rx) = mpsc::channel(1);
+        let _watcher = watch_configuration(&config_path, reload_tx).unwrap();
+        tokio::spawn(run_reload_loop(
+            reload_rx,
+            config_path.clone(),
+            Duration::from_millis(50),
+            Arc::clone(&shared_config),
+            update_manager,
+        ));
+        
+        // Changing the file swaps in a snapshot of the new configuration
+        std::fs::write(&path, config_with_proxy("second", "/second")).unwrap();
+        assert!(wait_until(|| async { snapshot.load().proxy_by_id("second").is_some() }).await);
+        assert!(snapshot.load().proxy_by_id("first").is_none());
+        
+        // A file that does not parse is reported and the last good configuration stays
+        std::fs::write(&path, "proxies: [").unwrap();
+        assert!(wait_until(|| async {
+            health::report(OperationMode::File).config.detail.source.map_or(false, |source| !source.reachable)
+        }).await);
+        assert!(snapshot.load().proxy_by_id("second").is_some());
+        assert_eq!(shared_config.read().await.proxies[0].id, "second");
+    }
+}
@@ -5,6 +5,7 @@ pub mod database;
 pub mod file;
 pub mod control_plane;
 pub mod data_plane;
+pub mod docker;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OperationMode {
@@ -12,6 +13,8 @@ pub enum OperationMode {
     File,
     ControlPlane,
     DataPlane,
+    Docker,
+    Hybrid,
 }
 
 impl fmt::Display for OperationMode {
@@ -21,6 +24,8 @@ impl fmt::Display for OperationMode {
             OperationMode::File => write!(f, "File Mode"),
             OperationMode::ControlPlane => write!(f, "Control Plane Mode"),
             OperationMode::DataPlane => write!(f, "Data Plane Mode"),
+            OperationMode::Docker => write!(f, "Docker Mode"),
+            OperationMode::Hybrid => write!(f, "Hybrid Mode"),
         }
     }
 }
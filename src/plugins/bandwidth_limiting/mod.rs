@@ -0,0 +1,282 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use dashmap::DashMap;
+use hyper::body::HttpBody;
+use hyper::{Body, Response, StatusCode};
+use serde::{Serialize, Deserialize};
+use tracing::debug;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::metrics;
+use crate::plugins::Plugin;
+use crate::proxy::handler::RequestContext;
+
+/// Largest piece of a response body sent at once, so that large chunks are
+/// spread out rather than sent in one go after a long pause
+const MAX_PIECE_BYTES: usize = 16 * 1024;
+
+/// How often the buckets of clients that went quiet are dropped
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Configuration for the bandwidth limiting plugin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BandwidthLimitingConfig {
+    /// Whether each consumer has its own budget or all responses of the proxy share one
+    #[serde(default)]
+    pub limit_by: BandwidthLimitBy,
+
+    /// The limit of consumers without a tier, or of the proxy
+    #[serde(flatten)]
+    pub limit: BandwidthLimit,
+
+    /// Limits by consumer tag, e.g. `plan:gold`, for consumers with one of the tags
+    #[serde(default)]
+    pub tiers: HashMap<String, BandwidthLimit>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BandwidthLimit {
+    /// Sustained response body rate
+    pub bytes_per_second: u64,
+
+    /// Bytes that can be sent at once after a quiet period, one second's worth by default
+    #[serde(default)]
+    pub burst_bytes: Option<u64>,
+}
+
+impl BandwidthLimit {
+    fn burst(&self) -> u64 {
+        self.burst_bytes.unwrap_or(self.bytes_per_second)
+    }
+
+    fn validate(&self, name: &str) -> Result<()> {
+        if self.bytes_per_second == 0 {
+            return Err(anyhow!("{}: bytes_per_second must be greater than 0", name));
+        }
+        if self.burst_bytes == Some(0) {
+            return Err(anyhow!("{}: burst_bytes must be greater than 0", name));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BandwidthLimitBy {
+    /// Each consumer, or client IP address for anonymous requests
+    #[default]
+    Consumer,
+    /// All clients of the proxy together
+    Proxy,
+}
+
+/// A token bucket of bytes. Senders reserve bytes before sending them and the
+/// balance may go negative, so responses sharing a budget take turns.
+#[derive(Debug)]
+pub struct ByteBucket {
+    limit: BandwidthLimit,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl ByteBucket {
+    pub fn new(limit: BandwidthLimit, now: Instant) -> Self {
+        Self { limit, tokens: limit.burst() as f64, refilled_at: now }
+    }
+
+    /// Reserves `bytes` and returns how long to wait before sending them
+    pub fn reserve(&mut self, bytes: usize, now: Instant) -> Duration {
+        let rate = self.limit.bytes_per_second as f64;
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(self.limit.burst() as f64);
+        self.refilled_at = self.refilled_at.max(now);
+
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
+
+    /// Whether the bucket has refilled to its burst by `now`, in which case a
+    /// new bucket would behave the same
+    pub fn is_full(&self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens + elapsed * self.limit.bytes_per_second as f64 >= self.limit.burst() as f64
+    }
+
+    /// Size of the pieces bodies are sent in, no larger than the burst
+    pub fn piece_bytes(&self) -> usize {
+        (self.limit.burst() as usize).clamp(1, MAX_PIECE_BYTES)
+    }
+}
+
+/// Passes a response body on no faster than its bucket allows. Trailers are
+/// forwarded, and the time the body was held back is recorded for the proxy.
+pub fn throttle_body(mut body: Body, bucket: Arc<Mutex<ByteBucket>>, proxy_id: String) -> Body {
+    let (mut sender, throttled) = Body::channel();
+    tokio::spawn(async move {
+        let mut held_back = Duration::ZERO;
+        let mut complete = true;
+        'body: while let Some(chunk) = body.data().await {
+            let mut chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(_) => {
+                    sender.abort();
+                    complete = false;
+                    break;
+                }
+            };
+            while !chunk.is_empty() {
+                let (piece_len, wait) = {
+                    let mut bucket = bucket.lock().unwrap();
+                    let piece_len = chunk.len().min(bucket.piece_bytes());
+                    (piece_len, bucket.reserve(piece_len, Instant::now()))
+                };
+                if !wait.is_zero() {
+                    held_back += wait;
+                    tokio::time::sleep(wait).await;
+                }
+                if sender.send_data(chunk.split_to(piece_len)).await.is_err() {
+                    complete = false;
+                    break 'body;
+                }
+            }
+        }
+        if complete {
+            if let Ok(Some(trailers)) = body.trailers().await {
+                let _ = sender.send_trailers(trailers).await;
+            }
+        }
+        if !held_back.is_zero() {
+            metrics::track_bandwidth_throttled(&proxy_id, held_back);
+        }
+    });
+    throttled
+}
+
+/// Plugin limiting the rate at which response bodies are sent to clients
+pub struct BandwidthLimitingPlugin {
+    config: BandwidthLimitingConfig,
+    buckets: DashMap<String, Arc<Mutex<ByteBucket>>>,
+    last_sweep: Mutex<Instant>,
+}
+
+impl BandwidthLimitingPlugin {
+    pub fn new(config_json: serde_json::Value) -> Result<Self> {
+        let config: BandwidthLimitingConfig = serde_json::from_value(config_json)
+            .context("Invalid bandwidth_limiting configuration")?;
+
+        config.limit.validate("bandwidth_limiting")?;
+        for (tag, limit) in &config.tiers {
+            limit.validate(&format!("bandwidth_limiting tier '{}'", tag))?;
+        }
+
+        Ok(Self {
+            config,
+            buckets: DashMap::new(),
+            last_sweep: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// The key of the budget a response is sent from and its limit
+    fn budget(&self, ctx: &RequestContext) -> (String, BandwidthLimit) {
+        match self.config.limit_by {
+            BandwidthLimitBy::Proxy => (format!("proxy:{}", ctx.proxy.id), self.config.limit),
+            BandwidthLimitBy::Consumer => match &ctx.consumer {
+                Some(consumer) => {
+                    let tier = ctx.consumers.by_id(&consumer.id)
+                        .and_then(|consumer| consumer.tags.iter().find_map(|tag| self.config.tiers.get(tag)));
+                    (format!("consumer:{}", consumer.id), tier.copied().unwrap_or(self.config.limit))
+                },
+                None => (format!("ip:{}", ctx.client_addr.ip()), self.config.limit),
+            },
+        }
+    }
+
+    fn bucket(&self, key: String, limit: BandwidthLimit) -> Arc<Mutex<ByteBucket>> {
+        self.sweep(Instant::now());
+
+        let mut entry = self.buckets.entry(key)
+            .or_insert_with(|| Arc::new(Mutex::new(ByteBucket::new(limit, Instant::now()))));
+        // A consumer that moved to another tier starts over with a full bucket
+        if entry.lock().unwrap().limit != limit {
+            *entry = Arc::new(Mutex::new(ByteBucket::new(limit, Instant::now())));
+        }
+        Arc::clone(&entry)
+    }
+
+    /// Drops the buckets that refilled and have no body in flight, at most once
+    /// per SWEEP_INTERVAL. Client addresses come and go, so their buckets would
+    /// otherwise pile up.
+    fn sweep(&self, now: Instant) {
+        {
+            let mut last_sweep = self.last_sweep.lock().unwrap();
+            if now.saturating_duration_since(*last_sweep) < SWEEP_INTERVAL {
+                return;
+            }
+            *last_sweep = now;
+        }
+
+        self.buckets.retain(|_, bucket| Arc::strong_count(bucket) > 1 || !bucket.lock().unwrap().is_full(now));
+    }
+}
+
+#[async_trait]
+impl Plugin for BandwidthLimitingPlugin {
+    fn name(&self) -> &'static str {
+        "bandwidth_limiting"
+    }
+
+    async fn after_proxy(&self, resp: &mut Response<Body>, ctx: &mut RequestContext) -> Result<()> {
+        // Upgraded connections and empty bodies have nothing to throttle
+        if resp.status() == StatusCode::SWITCHING_PROTOCOLS || resp.body().is_end_stream() {
+            return Ok(());
+        }
+
+        let (key, limit) = self.budget(ctx);
+        debug!("Limiting response of proxy {} to {} bytes/s for {}", ctx.proxy.id, limit.bytes_per_second, key);
+
+        let bucket = self.bucket(key, limit);
+        let body = std::mem::replace(resp.body_mut(), Body::empty());
+        *resp.body_mut() = throttle_body(body, bucket, ctx.proxy.id.clone());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sweep_drops_idle_buckets() {
+        let plugin = BandwidthLimitingPlugin::new(serde_json::json!({ "bytes_per_second": 1000 })).unwrap();
+        let limit = plugin.config.limit;
+        let start = Instant::now();
+
+        let in_flight = plugin.bucket("ip:192.0.2.1".to_string(), limit);
+        plugin.bucket("ip:192.0.2.2".to_string(), limit);
+        // Bytes for the next 1000 seconds were sent ahead of the rate
+        plugin.bucket("ip:192.0.2.3".to_string(), limit).lock().unwrap().reserve(1_000_000, start);
+        assert_eq!(plugin.buckets.len(), 3);
+
+        // Nothing is dropped before the sweep interval
+        plugin.sweep(start + Duration::from_secs(1));
+        assert_eq!(plugin.buckets.len(), 3);
+
+        // The refilled bucket without a body in flight goes, the others stay
+        plugin.sweep(start + SWEEP_INTERVAL + Duration::from_secs(1));
+        assert_eq!(plugin.buckets.len(), 2);
+        assert!(plugin.buckets.contains_key("ip:192.0.2.1"));
+        assert!(!plugin.buckets.contains_key("ip:192.0.2.2"));
+        assert!(plugin.buckets.contains_key("ip:192.0.2.3"));
+
+        drop(in_flight);
+        plugin.sweep(start + SWEEP_INTERVAL * 2 + Duration::from_secs(2));
+        assert_eq!(plugin.buckets.len(), 1);
+    }
+}
@@ -4,8 +4,9 @@ use hyper::{Body, Request, Response, header, StatusCode};
 use serde::{Serialize, Deserialize};
 use tracing::{debug, warn, info};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
-use bcrypt::verify;
 
+use crate::config::credentials;
+use crate::metrics;
 use crate::plugins::Plugin;
 use crate::proxy::handler::{RequestContext, Consumer};
 
@@ -92,29 +93,26 @@ impl BasicAuthPlugin {
     
     /// Authenticate a user based on username and password
     async fn authenticate_user(&self, username: &str, password: &str, ctx: &RequestContext) -> Option<Consumer> {
-        // Look up the user in the shared configuration
-        if let Some(active_config) = &ctx.proxy.active_config {
-            // Try to find the consumer by username
-            if let Some(consumer) = active_config.consumers.iter().find(|c| c.username == username) {
-                // Check if the consumer has password credentials
-                if let Some(credentials) = &consumer.credentials {
-                    // Look for password in credentials
-                    if let Some(stored_password) = credentials.get("password").and_then(|p| p.as_str()) {
-                        // Verify the password
-                        if verify_password(password, stored_password) {
-                            debug!("Authentication successful for user: {}", username);
-                            return Some(consumer.clone());
-                        }
-                    }
-                    
-                    // Look for hashed password in credentials
-                    if let Some(hashed_password) = credentials.get("hashed_password").and_then(|p| p.as_str()) {
-                        // Verify the password against the hash
-                        if verify_password_hash(password, hashed_password) {
-                            debug!("Authentication successful for user: {} (using hashed password)", username);
-                            return Some(consumer.clone());
-                        }
-                    }
+        // Look up the consumer by username through the consumer index
+        if let Some(consumer) = ctx.consumers.by_username(username) {
+            let stored = &consumer.credentials;
+            
+            // Look for password in credentials, set through the basic_auth credential or directly
+            let basic_auth = stored.get("basic_auth").and_then(|credential| credential.get("password"));
+            if let Some(stored_password) = basic_auth.or_else(|| stored.get("password")).and_then(|p| p.as_str()) {
+                // Verify the password
+                if credentials::verify_password(password, stored_password) {
+                    debug!("Authentication successful for user: {}", username);
+                    return Some(Consumer::from(consumer));
+                }
+            }
+            
+            // Look for hashed password in credentials
+            if let Some(hashed_password) = stored.get("hashed_password").and_then(|p| p.as_str()) {
+                // Verify the password against the hash
+                if credentials::verify_password(password, hashed_password) {
+                    debug!("Authentication successful for user: {} (using hashed password)", username);
+                    return Some(Consumer::from(consumer));
                 }
             }
         }
@@ -142,6 +140,7 @@ impl Plugin for BasicAuthPlugin {
             Some(creds) => creds,
             None => {
                 debug!("No Basic authentication credentials found in request");
+                metrics::track_auth_failure(self.name(), &ctx.proxy.id, "missing_credentials");
                 
                 // In multi-auth mode, we continue even if this auth method failed
                 if ctx.proxy.auth_mode == crate::config::data_model::AuthMode::Multi {
@@ -157,6 +156,7 @@ impl Plugin for BasicAuthPlugin {
             Some(consumer) => consumer,
             None => {
                 warn!("Basic authentication failed for user '{}'", username);
+                metrics::track_auth_failure(self.name(), &ctx.proxy.id, "invalid_credentials");
                 
                 // In multi-auth mode, we continue even if this auth method failed
                 if ctx.proxy.auth_mode == crate::config::data_model::AuthMode::Multi {
@@ -169,48 +169,9 @@ impl Plugin for BasicAuthPlugin {
         
         // Set the consumer in the context
         ctx.consumer = Some(consumer);
+        metrics::track_auth_success(self.name(), &ctx.proxy.id);
         debug!("Consumer identified by Basic authentication: {}", username);
         
         Ok(true)
     }
 }
-
-// Verify a plaintext password against another plaintext password
-// This is only for development/testing and should be removed in production
-fn verify_password(provided: &str, stored: &str) -> bool {
-    provided == stored
-}
-
-// Verify a password against a hash
-fn verify_password_hash(password: &str, hash: &str) -> bool {
-    #[cfg(feature = "bcrypt")]
-    {
-        bcrypt::verify(password, hash).unwrap_or(false)
-    }
-    
-    #[cfg(all(feature = "argon2", not(feature = "bcrypt")))]
-    {
-        use argon2::{Argon2, PasswordHash, PasswordVerifier};
-        
-        match PasswordHash::new(hash) {
-            Ok(parsed_hash) => {
-                Argon2::default()
-                    .verify_password(password.as_bytes(), &parsed_hash)
-                    .is_ok()
-            }
-            Err(_) => false,
-        }
-    }
-    
-    #[cfg(not(any(feature = "bcrypt", feature = "argon2")))]
-    {
-        // Fallback implementation when neither bcrypt nor argon2 features are enabled
-        // This is insecure and should only be used for development/testing
-        let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        std::hash::Hash::hash_slice(password.as_bytes(), &mut hasher);
-        let simple_hash = format!("{:x}", std::hash::Hasher::finish(&hasher));
-        
-        // Only use this for testing!
-        hash == simple_hash
-    }
-}
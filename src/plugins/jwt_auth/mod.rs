@@ -5,6 +5,7 @@ use serde::{Serialize, Deserialize};
 use tracing::{debug, warn, info};
 use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm, TokenData};
 
+use crate::metrics;
 use crate::plugins::Plugin;
 use crate::proxy::handler::{RequestContext, Consumer};
 
@@ -256,22 +257,14 @@ impl JwtAuthPlugin {
         // Look up the consumer in the shared configuration
         let consumer_id_str = consumer_id.to_string();
         
-        // Find the consumer in the proxy's active configuration
-        if let Some(consumers) = ctx.proxy.active_config.as_ref().map(|cfg| &cfg.consumers) {
-            // Try to find the consumer by ID first
-            if let Some(consumer) = consumers.iter().find(|c| c.id == consumer_id_str) {
-                return Some(consumer.clone());
-            }
-            
-            // If not found by ID, try by custom_id
-            if let Some(consumer) = consumers.iter().find(|c| c.custom_id.as_ref().map_or(false, |cid| cid == &consumer_id_str)) {
-                return Some(consumer.clone());
-            }
-            
-            // If not found by custom_id, try by username
-            if let Some(consumer) = consumers.iter().find(|c| c.username == consumer_id_str) {
-                return Some(consumer.clone());
-            }
+        // Resolve the consumer by ID, then custom_id, then username
+        if let Some(consumer) = ctx.consumers.find_by_identifier(&consumer_id_str) {
+            return Some(Consumer::from(consumer));
+        }
+        
+        // Then by the subjects mapped to consumers in their jwt credentials
+        if let Some(consumer) = ctx.consumers.by_jwt_subject(&consumer_id_str) {
+            return Some(Consumer::from(consumer));
         }
         
         debug!("Consumer with ID '{}' not found in active configuration", consumer_id);
@@ -297,6 +290,7 @@ impl Plugin for JwtAuthPlugin {
             Some(token) => token,
             None => {
                 debug!("No JWT token found in request");
+                metrics::track_auth_failure(self.name(), &ctx.proxy.id, "missing_credentials");
                 
                 // In multi-auth mode, we continue even if this auth method failed
                 if ctx.proxy.auth_mode == crate::config::data_model::AuthMode::Multi {
@@ -312,6 +306,7 @@ impl Plugin for JwtAuthPlugin {
             Ok(claims) => claims,
             Err(e) => {
                 warn!("JWT token validation failed: {}", e);
+                metrics::track_auth_failure(self.name(), &ctx.proxy.id, "invalid_credentials");
                 
                 // In multi-auth mode, we continue even if this auth method failed
                 if ctx.proxy.auth_mode == crate::config::data_model::AuthMode::Multi {
@@ -327,6 +322,7 @@ impl Plugin for JwtAuthPlugin {
             Some(consumer) => consumer,
             None => {
                 warn!("No consumer found for JWT token");
+                metrics::track_auth_failure(self.name(), &ctx.proxy.id, "unknown_consumer");
                 
                 // In multi-auth mode, we continue even if this auth method failed
                 if ctx.proxy.auth_mode == crate::config::data_model::AuthMode::Multi {
@@ -339,6 +335,7 @@ impl Plugin for JwtAuthPlugin {
         
         // Set the consumer in the context
         ctx.consumer = Some(consumer);
+        metrics::track_auth_success(self.name(), &ctx.proxy.id);
         debug!("Consumer identified by JWT token: {}", ctx.consumer.as_ref().unwrap().username);
         
         Ok(true)
@@ -3,8 +3,9 @@ use async_trait::async_trait;
 use hyper::{Body, Request, Response, header, StatusCode};
 use serde::{Serialize, Deserialize};
 use tracing::{debug, warn, info};
-use bcrypt::verify;
 
+use crate::config::credentials;
+use crate::metrics;
 use crate::plugins::Plugin;
 use crate::proxy::handler::{RequestContext, Consumer};
 
@@ -126,25 +127,18 @@ impl KeyAuthPlugin {
     
     /// Find a consumer based on the API key
     async fn find_consumer_by_key(&self, api_key: &str, ctx: &RequestContext) -> Option<Consumer> {
-        // Look up the API key in the shared configuration
-        if let Some(active_config) = &ctx.proxy.active_config {
-            // Iterate through all consumers to find one with matching API key
-            for consumer in &active_config.consumers {
-                // Check if the consumer has an API key credential
-                if let Some(credentials) = &consumer.credentials {
-                    // Check for API key entries
-                    if let Some(keys) = credentials.get("api_keys").and_then(|v| v.as_array()) {
-                        // Look through all keys
-                        for key in keys {
-                            if let Some(key_str) = key.as_str() {
-                                // If using hashed keys, we would verify the hash here instead of direct comparison
-                                if key_str == api_key || (self.config.hash_keys && verify_key_hash(api_key, key_str)) {
-                                    debug!("Found consumer {} using API key authentication", consumer.username);
-                                    return Some(consumer.clone());
-                                }
-                            }
-                        }
-                    }
+        // Plaintext keys are resolved directly through the consumer index
+        if let Some(consumer) = ctx.consumers.by_api_key(api_key) {
+            debug!("Found consumer {} using API key authentication", consumer.username);
+            return Some(Consumer::from(consumer));
+        }
+        
+        // Hashed keys are salted, so each stored hash has to be verified
+        if self.config.hash_keys {
+            for (key_hash, consumer) in ctx.consumers.hashed_api_keys() {
+                if credentials::verify_api_key(api_key, key_hash) {
+                    debug!("Found consumer {} using hashed API key authentication", consumer.username);
+                    return Some(Consumer::from(consumer));
                 }
             }
         }
@@ -172,6 +166,7 @@ impl Plugin for KeyAuthPlugin {
             Some(key) => key,
             None => {
                 debug!("No API key found in request");
+                metrics::track_auth_failure(self.name(), &ctx.proxy.id, "missing_credentials");
                 
                 // In multi-auth mode, we continue even if this auth method failed
                 if ctx.proxy.auth_mode == crate::config::data_model::AuthMode::Multi {
@@ -187,6 +182,7 @@ impl Plugin for KeyAuthPlugin {
             Some(consumer) => consumer,
             None => {
                 warn!("No consumer found for API key");
+                metrics::track_auth_failure(self.name(), &ctx.proxy.id, "invalid_credentials");
                 
                 // In multi-auth mode, we continue even if this auth method failed
                 if ctx.proxy.auth_mode == crate::config::data_model::AuthMode::Multi {
@@ -199,36 +195,9 @@ impl Plugin for KeyAuthPlugin {
         
         // Set the consumer in the context
         ctx.consumer = Some(consumer);
+        metrics::track_auth_success(self.name(), &ctx.proxy.id);
         debug!("Consumer identified by API key: {}", ctx.consumer.as_ref().unwrap().username);
         
         Ok(true)
     }
 }
-
-/// Verify a key against a hash (for implementations that hash API keys)
-fn verify_key_hash(plain_key: &str, hash: &str) -> bool {
-    // In a production system, we would use bcrypt, argon2, or similar
-    // Here's a simplified example using a bcrypt-like approach:
-    
-    #[cfg(feature = "argon2")]
-    {
-        use argon2::{Argon2, PasswordHash, PasswordVerifier};
-        
-        match PasswordHash::new(hash) {
-            Ok(parsed_hash) => {
-                Argon2::default()
-                    .verify_password(plain_key.as_bytes(), &parsed_hash)
-                    .is_ok()
-            }
-            Err(_) => false,
-        }
-    }
-    
-    #[cfg(not(feature = "argon2"))]
-    {
-        // Fallback for when argon2 feature is not enabled
-        // This would be a simplified and insecure comparison 
-        // (should only be used for development/testing)
-        hash.starts_with(&format!("hash_{}", plain_key))
-    }
-}
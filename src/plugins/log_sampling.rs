@@ -0,0 +1,44 @@
+//! Access log sampling
+
+use std::sync::Arc;
+use arc_swap::ArcSwapOption;
+
+use crate::config::data_model::{LogSampling, Proxy};
+use crate::config::env_config::EnvConfig;
+
+static GLOBAL: ArcSwapOption<LogSampling> = ArcSwapOption::const_empty();
+
+/// Applies the gateway's sampling settings to proxies without their own
+pub fn start(env_config: &EnvConfig) {
+    configure(env_config.log_sampling.clone());
+}
+
+/// Samples proxies without their own settings with these from now on
+pub fn configure(sampling: LogSampling) {
+    GLOBAL.store(Some(Arc::new(sampling)));
+}
+
+/// Whether a request of the proxy is passed to the log plugins
+pub fn should_log(proxy: &Proxy, status: u16, latency_ms: u64) -> bool {
+    match &proxy.log_sampling {
+        Some(sampling) => keeps(sampling, status, latency_ms, rand::random()),
+        None => match GLOBAL.load().as_ref() {
+            Some(sampling) => keeps(sampling, status, latency_ms, rand::random()),
+            None => true,
+        },
+    }
+}
+
+/// Whether the settings keep a request, given a random draw for the one in N
+pub fn keeps(sampling: &LogSampling, status: u16, latency_ms: u64, draw: u64) -> bool {
+    if sampling.errors && status >= 500 {
+        return true;
+    }
+    if sampling.slow_ms.map_or(false, |slow_ms| latency_ms >= slow_ms) {
+        return true;
+    }
+    match sampling.one_in {
+        0 => false,
+        one_in => draw % one_in == 0,
+    }
+}
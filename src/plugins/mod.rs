@@ -1,8 +1,9 @@
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Instant;
 use anyhow::{Result, Context};
 use async_trait::async_trait;
+use dashmap::DashMap;
 use hyper::{Body, Request, Response};
 use tracing::{debug, info, error, warn};
 use tokio::spawn;
@@ -10,7 +11,9 @@ use tokio::time::sleep;
 use tokio::select;
 
 use crate::proxy::handler::RequestContext;
-use crate::config::data_model::{PluginConfig, Proxy, Configuration};
+use crate::config::data_model::{PluginConfig, Proxy};
+use crate::config::snapshot::SharedSnapshot;
+use crate::metrics;
 
 // Import plugin implementations
 mod stdout_logging;
@@ -20,10 +23,13 @@ mod oauth2_auth;
 mod jwt_auth;
 mod key_auth;
 mod basic_auth;
+mod mtls_auth;
 mod access_control;
 mod request_transformer;
 mod response_transformer;
-mod rate_limiting;
+pub mod rate_limiting;
+pub mod bandwidth_limiting;
+pub mod log_sampling;
 
 /// A trait that defines the interface for all plugins
 #[async_trait]
@@ -66,15 +72,18 @@ pub trait Plugin: Send + Sync {
     }
 }
 
+/// Creates a plugin instance from its JSON configuration
+pub type PluginFactory = Box<dyn Fn(serde_json::Value) -> Result<Box<dyn Plugin>> + Send + Sync>;
+
 /// Registry of available plugin factories
 pub struct PluginRegistry {
-    factories: HashMap<String, Box<dyn Fn(serde_json::Value) -> Result<Box<dyn Plugin>> + Send + Sync>>,
+    factories: HashMap<String, PluginFactory>,
 }
 
 impl PluginRegistry {
     /// Creates a new plugin registry with all built-in plugins registered
     pub fn new() -> Self {
-        let mut factories = HashMap::new();
+        let mut factories: HashMap<String, PluginFactory> = HashMap::new();
         
         // Register all standard plugins
         factories.insert(
@@ -107,6 +116,11 @@ impl PluginRegistry {
             Box::new(|config| Ok(Box::new(basic_auth::BasicAuthPlugin::new(config)?) as Box<dyn Plugin>))
         );
         
+        factories.insert(
+            "mtls_auth".to_string(),
+            Box::new(|config| Ok(Box::new(mtls_auth::MtlsAuthPlugin::new(config)?) as Box<dyn Plugin>))
+        );
+        
         factories.insert(
             "oauth2_auth".to_string(),
             Box::new(|config| Ok(Box::new(oauth2_auth::OAuth2Plugin::new(config)?) as Box<dyn Plugin>))
@@ -132,9 +146,23 @@ impl PluginRegistry {
             Box::new(|config| Ok(Box::new(rate_limiting::RateLimitingPlugin::new(config)?) as Box<dyn Plugin>))
         );
         
+        factories.insert(
+            "bandwidth_limiting".to_string(),
+            Box::new(|config| Ok(Box::new(bandwidth_limiting::BandwidthLimitingPlugin::new(config)?) as Box<dyn Plugin>))
+        );
+        
         Self { factories }
     }
     
+    /// Registers a plugin under a name, replacing a built-in plugin of the same name.
+    /// Plugin configs refer to it by this name like to any built-in plugin.
+    pub fn register<F>(&mut self, name: impl Into<String>, factory: F)
+    where
+        F: Fn(serde_json::Value) -> Result<Box<dyn Plugin>> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.into(), Box::new(factory));
+    }
+    
     /// Creates a plugin instance from a plugin name and configuration
     pub fn create_plugin(&self, name: &str, config: serde_json::Value) -> Result<Box<dyn Plugin>> {
         match self.factories.get(name) {
@@ -152,19 +180,32 @@ impl PluginRegistry {
 /// Manager for plugin instances and execution
 pub struct PluginManager {
     registry: PluginRegistry,
-    // Cached plugins for better performance
-    global_plugins: Arc<RwLock<Vec<Box<dyn Plugin>>>>,
-    // Shared configuration for looking up plugin configs
-    shared_config: Arc<RwLock<Configuration>>,
+    // Configuration snapshot for looking up plugin configs without locking
+    snapshot: SharedSnapshot,
+    // Plugin instances by plugin config ID, or by proxy and plugin config ID for
+    // configs embedded in a proxy, reused while their configuration is unchanged
+    instances: DashMap<String, CachedPlugin>,
+}
+
+/// A plugin instance and the configuration it was created from
+struct CachedPlugin {
+    plugin_name: String,
+    config: serde_json::Value,
+    plugin: Arc<dyn Plugin>,
 }
 
 impl PluginManager {
     /// Creates a new plugin manager
-    pub fn new(shared_config: Arc<RwLock<Configuration>>) -> Self {
+    pub fn new(snapshot: SharedSnapshot) -> Self {
+        Self::with_registry(snapshot, PluginRegistry::new())
+    }
+    
+    /// Creates a plugin manager creating its plugins from the given registry
+    pub fn with_registry(snapshot: SharedSnapshot, registry: PluginRegistry) -> Self {
         Self {
-            registry: PluginRegistry::new(),
-            global_plugins: Arc::new(RwLock::new(Vec::new())),
-            shared_config,
+            registry,
+            snapshot,
+            instances: DashMap::new(),
         }
     }
     
@@ -178,6 +219,47 @@ impl PluginManager {
         self.registry.create_plugin(plugin_name, config)
     }
     
+    /// Creates the plugins a proxy runs, so that its first request does not wait for them
+    pub async fn warm_proxy(&self, proxy: &Proxy) -> Result<usize> {
+        Ok(self.get_active_plugins_for_proxy(proxy).await?.len())
+    }
+    
+    /// Creates the plugin of a plugin config ahead of the first request through it
+    pub fn warm_plugin_config(&self, plugin_config: &PluginConfig) -> Result<()> {
+        self.instance(&plugin_config.id, &plugin_config.plugin_name, &plugin_config.config)
+            .map(|_| ())
+    }
+    
+    /// Drops the plugin instances of deleted plugin configs and proxies
+    pub fn evict(&self, plugin_config_ids: &[String], proxy_ids: &[String]) {
+        self.instances.retain(|key, _| {
+            let owner = key.split_once('/').map_or(key.as_str(), |(proxy_id, _)| proxy_id);
+            !plugin_config_ids.contains(key) && !proxy_ids.iter().any(|proxy_id| proxy_id == owner)
+        });
+    }
+    
+    /// Plugin instances currently held
+    pub fn instance_count(&self) -> usize {
+        self.instances.len()
+    }
+    
+    /// The plugin created from a configuration, reused while the configuration is unchanged
+    fn instance(&self, key: &str, plugin_name: &str, config: &serde_json::Value) -> Result<Arc<dyn Plugin>> {
+        if let Some(cached) = self.instances.get(key) {
+            if cached.plugin_name == plugin_name && &cached.config == config {
+                return Ok(Arc::clone(&cached.plugin));
+            }
+        }
+        
+        let plugin: Arc<dyn Plugin> = Arc::from(self.registry.create_plugin(plugin_name, config.clone())?);
+        self.instances.insert(key.to_string(), CachedPlugin {
+            plugin_name: plugin_name.to_string(),
+            config: config.clone(),
+            plugin: Arc::clone(&plugin),
+        });
+        Ok(plugin)
+    }
+    
     /// Runs the pre-proxy plugin pipeline on a request
     /// Returns the (possibly modified) request and a boolean indicating whether to continue
     pub async fn run_pre_proxy_plugins(
@@ -193,7 +275,10 @@ impl PluginManager {
         // Execute on_request_received phase
         debug!("Executing on_request_received phase for {} plugins", active_plugins.len());
         for plugin in &active_plugins {
-            match plugin.on_request_received(&mut req, ctx).await {
+            let started_at = Instant::now();
+            let result = plugin.on_request_received(&mut req, ctx).await;
+            metrics::track_plugin_duration(plugin.name(), "on_request_received", started_at.elapsed());
+            match result {
                 Ok(true) => continue, // Continue to next plugin
                 Ok(false) => {
                     debug!("Plugin {} rejected request in on_request_received phase", plugin.name());
@@ -209,7 +294,10 @@ impl PluginManager {
         // Execute authenticate phase
         debug!("Executing authenticate phase for {} plugins", active_plugins.len());
         for plugin in &active_plugins {
-            match plugin.authenticate(&mut req, ctx).await {
+            let started_at = Instant::now();
+            let result = plugin.authenticate(&mut req, ctx).await;
+            metrics::track_plugin_duration(plugin.name(), "authenticate", started_at.elapsed());
+            match result {
                 Ok(true) => continue, // Continue to next plugin
                 Ok(false) => {
                     debug!("Plugin {} rejected request in authenticate phase", plugin.name());
@@ -225,7 +313,10 @@ impl PluginManager {
         // Execute authorize phase
         debug!("Executing authorize phase for {} plugins", active_plugins.len());
         for plugin in &active_plugins {
-            match plugin.authorize(&mut req, ctx).await {
+            let started_at = Instant::now();
+            let result = plugin.authorize(&mut req, ctx).await;
+            metrics::track_plugin_duration(plugin.name(), "authorize", started_at.elapsed());
+            match result {
                 Ok(true) => continue, // Continue to next plugin
                 Ok(false) => {
                     debug!("Plugin {} rejected request in authorize phase", plugin.name());
@@ -241,7 +332,10 @@ impl PluginManager {
         // Execute before_proxy phase
         debug!("Executing before_proxy phase for {} plugins", active_plugins.len());
         for plugin in &active_plugins {
-            match plugin.before_proxy(&mut req, ctx).await {
+            let started_at = Instant::now();
+            let result = plugin.before_proxy(&mut req, ctx).await;
+            metrics::track_plugin_duration(plugin.name(), "before_proxy", started_at.elapsed());
+            match result {
                 Ok(true) => continue, // Continue to next plugin
                 Ok(false) => {
                     debug!("Plugin {} rejected request in before_proxy phase", plugin.name());
@@ -273,7 +367,10 @@ impl PluginManager {
         // Execute after_proxy phase
         debug!("Executing after_proxy phase for {} plugins", active_plugins.len());
         for plugin in &active_plugins {
-            match plugin.after_proxy(&mut resp, ctx).await {
+            let started_at = Instant::now();
+            let result = plugin.after_proxy(&mut resp, ctx).await;
+            metrics::track_plugin_duration(plugin.name(), "after_proxy", started_at.elapsed());
+            match result {
                 Ok(()) => continue, // Continue to next plugin
                 Err(e) => {
                     error!("Error in plugin {} during after_proxy: {}", plugin.name(), e);
@@ -295,6 +392,11 @@ impl PluginManager {
     ) -> Result<()> {
         let proxy = &ctx.proxy;
         
+        // Sampled out requests are not logged at all
+        if !log_sampling::should_log(proxy, resp.status().as_u16(), ctx.latency.total) {
+            return Ok(());
+        }
+        
         // Get all relevant plugins for this proxy
         let active_plugins = self.get_active_plugins_for_proxy(proxy).await?;
         
@@ -309,7 +411,10 @@ impl PluginManager {
             let ctx = ctx.clone();
             
             let task = spawn(async move {
-                if let Err(e) = p.log(&req, &resp, &ctx).await {
+                let started_at = Instant::now();
+                let result = p.log(&req, &resp, &ctx).await;
+                metrics::track_plugin_duration(p.name(), "log", started_at.elapsed());
+                if let Err(e) = result {
                     warn!("Error in plugin {} during log phase: {}", p.name(), e);
                 }
             });
@@ -331,15 +436,22 @@ impl PluginManager {
     }
     
     /// Get all active plugins for a proxy
-    async fn get_active_plugins_for_proxy(&self, proxy: &Proxy) -> Result<Vec<Box<dyn Plugin>>> {
+    async fn get_active_plugins_for_proxy(&self, proxy: &Proxy) -> Result<Vec<Arc<dyn Plugin>>> {
         // Get shared configuration
-        let mut plugins: Vec<Box<dyn Plugin>> = Vec::new();
+        let mut plugins: Vec<Arc<dyn Plugin>> = Vec::new();
         
-        // Get global plugins from cache
+        // Global plugins of the proxy's workspace run first, unless the proxy
+        // lists the same plugin config itself
         {
-            let global_plugins = self.global_plugins.read().await;
-            for plugin in global_plugins.iter() {
-                plugins.push(self.registry.create_plugin(plugin.name(), serde_json::json!({}))?);
+            let snapshot = self.snapshot.load();
+            for plugin_config in snapshot.global_plugin_configs(&proxy.workspace) {
+                if proxy.plugins.iter().any(|association| association.plugin_config_id == plugin_config.id) {
+                    continue;
+                }
+                match self.instance(&plugin_config.id, &plugin_config.plugin_name, &plugin_config.config) {
+                    Ok(plugin) => plugins.push(plugin),
+                    Err(e) => warn!("Skipping global plugin config {}: {}", plugin_config.id, e),
+                }
             }
         }
         
@@ -350,7 +462,8 @@ impl PluginManager {
                 if let Ok(config_copy) = serde_json::to_value(config) {
                     if let Ok(plugin_config) = self.get_plugin_config_by_id(&plugin_association.plugin_config_id).await {
                         // Create the plugin with its configuration
-                        if let Ok(plugin) = self.registry.create_plugin(&plugin_config.plugin_name, config_copy) {
+                        let key = format!("{}/{}", proxy.id, plugin_association.plugin_config_id);
+                        if let Ok(plugin) = self.instance(&key, &plugin_config.plugin_name, &config_copy) {
                             plugins.push(plugin);
                         }
                     }
@@ -359,7 +472,7 @@ impl PluginManager {
                 // Otherwise, look up the plugin config by ID
                 if let Ok(plugin_config) = self.get_plugin_config_by_id(&plugin_association.plugin_config_id).await {
                     // Create the plugin with its configuration
-                    if let Ok(plugin) = self.registry.create_plugin(&plugin_config.plugin_name, plugin_config.config.clone()) {
+                    if let Ok(plugin) = self.instance(&plugin_config.id, &plugin_config.plugin_name, &plugin_config.config) {
                         plugins.push(plugin);
                     }
                 }
@@ -371,12 +484,9 @@ impl PluginManager {
     
     /// Get plugin config by ID from the shared configuration
     async fn get_plugin_config_by_id(&self, id: &str) -> Result<PluginConfig> {
-        // First check in-memory configuration
-        {
-            let config = self.shared_config.read().await;
-            if let Some(plugin_config) = config.plugin_configs.iter().find(|pc| pc.id == id) {
-                return Ok(plugin_config.clone());
-            }
+        // First check the in-memory configuration snapshot
+        if let Some(plugin_config) = self.snapshot.load().plugin_config_by_id(id) {
+            return Ok(plugin_config.clone());
         }
         
         // If not found in memory, check database if available
@@ -0,0 +1,131 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use hyper::{Body, Request};
+use serde::{Serialize, Deserialize};
+use tracing::{debug, warn};
+
+use crate::config::data_model::AuthMode;
+use crate::metrics;
+use crate::plugins::Plugin;
+use crate::proxy::client_auth::ClientCertificate;
+use crate::proxy::handler::{RequestContext, Consumer};
+
+/// Configuration for the client certificate authentication plugin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MtlsAuthConfig {
+    /// Which part of the verified certificate identifies the consumer
+    #[serde(default = "default_match_field")]
+    pub match_field: MatchField,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchField {
+    /// Subject CN matched against consumer username or custom_id
+    CommonName,
+    /// Full subject DN matched against credentials.mtls_auth.subject
+    Subject,
+    /// Any SAN (DNS, email, URI or IP) matched against credentials.mtls_auth.san
+    San,
+    /// SHA-256 fingerprint matched against credentials.mtls_auth.fingerprint_sha256
+    Fingerprint,
+}
+
+fn default_match_field() -> MatchField {
+    MatchField::CommonName
+}
+
+impl Default for MtlsAuthConfig {
+    fn default() -> Self {
+        Self {
+            match_field: default_match_field(),
+        }
+    }
+}
+
+/// Authenticates consumers by the client certificate verified on the listener
+pub struct MtlsAuthPlugin {
+    config: MtlsAuthConfig,
+}
+
+impl MtlsAuthPlugin {
+    pub fn new(config_json: serde_json::Value) -> Result<Self> {
+        let config = serde_json::from_value(config_json)
+            .unwrap_or_else(|_| MtlsAuthConfig::default());
+
+        Ok(Self { config })
+    }
+
+    /// Find the consumer the certificate belongs to
+    fn find_consumer(&self, certificate: &ClientCertificate, ctx: &RequestContext) -> Option<Consumer> {
+        if self.config.match_field == MatchField::CommonName {
+            let common_name = certificate.common_name()?;
+            return ctx.consumers.find_by_identifier(common_name).map(Consumer::from);
+        }
+
+        let (field, candidates) = match self.config.match_field {
+            MatchField::Subject => ("subject", vec![certificate.subject.clone()]),
+            MatchField::Fingerprint => ("fingerprint_sha256", vec![certificate.fingerprint_sha256.clone()]),
+            _ => {
+                let mut sans = certificate.dns_names.clone();
+                sans.extend(certificate.emails.iter().cloned());
+                sans.extend(certificate.uris.iter().cloned());
+                sans.extend(certificate.ip_addresses.iter().map(|ip| ip.to_string()));
+                ("san", sans)
+            },
+        };
+
+        ctx.consumers.iter()
+            .find(|consumer| {
+                consumer.credentials.get("mtls_auth")
+                    .and_then(|credential| credential.get(field))
+                    .and_then(|value| value.as_str())
+                    .map(|expected| candidates.iter().any(|candidate| candidate.eq_ignore_ascii_case(expected)))
+                    .unwrap_or(false)
+            })
+            .map(Consumer::from)
+    }
+}
+
+#[async_trait]
+impl Plugin for MtlsAuthPlugin {
+    fn name(&self) -> &'static str {
+        "mtls_auth"
+    }
+
+    async fn authenticate(&self, _req: &mut Request<Body>, ctx: &mut RequestContext) -> Result<bool> {
+        // Skip if a consumer is already identified (multi-auth mode)
+        if ctx.consumer.is_some() {
+            debug!("Consumer already identified, skipping client certificate authentication");
+            return Ok(true);
+        }
+
+        let consumer = match ctx.client_certificate.clone() {
+            Some(certificate) => self.find_consumer(&certificate, ctx),
+            None => {
+                debug!("No verified client certificate on this connection");
+                None
+            }
+        };
+
+        match consumer {
+            Some(consumer) => {
+                debug!("Consumer identified by client certificate: {}", consumer.username);
+                ctx.consumer = Some(consumer);
+                metrics::track_auth_success(self.name(), &ctx.proxy.id);
+                Ok(true)
+            },
+            None => {
+                if ctx.client_certificate.is_some() {
+                    warn!("No consumer found for client certificate");
+                    metrics::track_auth_failure(self.name(), &ctx.proxy.id, "unknown_consumer");
+                } else {
+                    metrics::track_auth_failure(self.name(), &ctx.proxy.id, "missing_credentials");
+                }
+
+                // In multi-auth mode, we continue even if this auth method failed
+                Ok(ctx.proxy.auth_mode == AuthMode::Multi)
+            }
+        }
+    }
+}
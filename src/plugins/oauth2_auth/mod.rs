@@ -10,7 +10,9 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::metrics;
 use crate::plugins::Plugin;
+use crate::proxy::egress::{EgressConnector, EgressRoute};
 use crate::proxy::handler::{RequestContext, Consumer};
 
 /// Configuration for the OAuth2 authentication plugin
@@ -52,6 +54,10 @@ pub struct OAuth2AuthConfig {
     
     /// OAuth provider name
     pub provider_name: String,
+    
+    /// Egress proxy of introspection and JWKS requests, overriding
+    /// FERRUM_EGRESS_PROXY_URL; `direct` connects directly
+    pub egress_proxy: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -95,6 +101,7 @@ impl Default for OAuth2AuthConfig {
             use_cache: default_true(),
             cache_ttl_seconds: default_cache_ttl(),
             provider_name: "oauth2".to_string(),
+            egress_proxy: None,
         }
     }
 }
@@ -102,7 +109,7 @@ impl Default for OAuth2AuthConfig {
 /// OAuth2 authentication plugin
 pub struct OAuth2AuthPlugin {
     config: OAuth2AuthConfig,
-    http_client: hyper::Client<HttpsConnector<HttpConnector>>,
+    http_client: hyper::Client<HttpsConnector<EgressConnector<HttpConnector>>>,
     // Cache for JWKS keys
     jwks_cache: Arc<RwLock<HashMap<String, JwksCache>>>,
     // Cache for validated tokens
@@ -154,12 +161,18 @@ impl OAuth2AuthPlugin {
             },
         }
         
-        // Create HTTP client for introspection and JWKS requests
+        let egress = EgressRoute::parse(config.egress_proxy.as_deref())
+            .context("OAuth2 plugin configuration error: invalid egress_proxy")?;
+        
+        // Create HTTP client for introspection and JWKS requests, going through
+        // the egress proxy if there is one
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
         let https = hyper_rustls::HttpsConnectorBuilder::new()
             .with_native_roots()
             .https_only()
             .enable_http1()
-            .build();
+            .wrap_connector(EgressConnector::new(http, egress));
         
         let http_client = hyper::Client::builder()
             .pool_idle_timeout(Duration::from_secs(90))
@@ -199,11 +212,13 @@ impl OAuth2AuthPlugin {
                 if now < cache_entry.expires_at {
                     // Token is in cache and not expired
                     debug!("OAuth2 token validation cache hit");
+                    metrics::track_auth_cache_lookup("oauth2_token", true);
                     return Ok(Some(cache_entry.claims.clone()));
                 }
                 // Token expired, will need to validate again
                 debug!("OAuth2 token in cache but expired");
             }
+            metrics::track_auth_cache_lookup("oauth2_token", false);
         }
         
         // Token not in cache or cache disabled, validate according to mode
@@ -385,6 +400,7 @@ impl OAuth2AuthPlugin {
                         let jwks_key = &cache.keys[&kid];
                         if jwks_key.algorithm == algorithm {
                             debug!("JWKS key found in cache: {}", kid);
+                            metrics::track_auth_cache_lookup("oauth2_jwks", true);
                             return self.validate_token_with_key(token, &jwks_key.decoding_key, algorithm);
                         } else {
                             debug!("JWKS key algorithm mismatch, refreshing");
@@ -403,6 +419,8 @@ impl OAuth2AuthPlugin {
             };
             
             if should_fetch_jwks {
+                metrics::track_auth_cache_lookup("oauth2_jwks", false);
+                
                 // Fetch JWKS and update cache
                 debug!("Fetching JWKS from {}", jwks_uri);
                 let keys = self.fetch_jwks(jwks_uri).await?;
@@ -562,36 +580,22 @@ impl OAuth2AuthPlugin {
         // Look up the consumer in the shared configuration
         let consumer_id_str = consumer_id.to_string();
         
-        // Find the consumer in the proxy's active configuration
-        if let Some(consumers) = ctx.proxy.active_config.as_ref().map(|cfg| &cfg.consumers) {
-            // Try to find the consumer by ID first
-            if let Some(consumer) = consumers.iter().find(|c| c.id == consumer_id_str) {
-                return Some(consumer.clone());
-            }
-            
-            // If not found by ID, try by custom_id
-            if let Some(consumer) = consumers.iter().find(|c| c.custom_id.as_ref().map_or(false, |cid| cid == &consumer_id_str)) {
-                return Some(consumer.clone());
-            }
-            
-            // If not found by custom_id, try by username
-            if let Some(consumer) = consumers.iter().find(|c| c.username == consumer_id_str) {
-                return Some(consumer.clone());
-            }
-            
-            // Special case for OAuth - look for OAuth provider associations
-            if let Some(consumer) = consumers.iter().find(|c| {
-                if let Some(credentials) = &c.credentials {
-                    if let Some(oauth_ids) = credentials.get("oauth_provider_ids").and_then(|v| v.as_object()) {
-                        if let Some(provider_id) = oauth_ids.get(&self.config.provider_name) {
-                            return provider_id.as_str().map_or(false, |id| id == consumer_id_str);
-                        }
-                    }
-                }
-                false
-            }) {
-                return Some(consumer.clone());
-            }
+        // Resolve the consumer by ID, then custom_id, then username
+        if let Some(consumer) = ctx.consumers.find_by_identifier(&consumer_id_str) {
+            return Some(Consumer::from(consumer));
+        }
+        
+        // Special case for OAuth - look for OAuth provider associations, set
+        // through the oauth2 credential or the older oauth_provider_ids entry
+        if let Some(consumer) = ctx.consumers.iter().find(|c| {
+            c.credentials.get("oauth2").and_then(|oauth2| oauth2.get("provider_ids"))
+                .or_else(|| c.credentials.get("oauth_provider_ids"))
+                .and_then(|v| v.as_object())
+                .and_then(|oauth_ids| oauth_ids.get(&self.config.provider_name))
+                .and_then(|provider_id| provider_id.as_str())
+                .map_or(false, |id| id == consumer_id_str)
+        }) {
+            return Some(Consumer::from(consumer));
         }
         
         debug!("Consumer with OAuth ID '{}' not found in active configuration", consumer_id);
@@ -617,6 +621,7 @@ impl Plugin for OAuth2AuthPlugin {
             Some(token) => token,
             None => {
                 debug!("No OAuth2 token found in request");
+                metrics::track_auth_failure(self.name(), &ctx.proxy.id, "missing_credentials");
                 
                 // In multi-auth mode, we continue even if this auth method failed
                 if ctx.proxy.auth_mode == crate::config::data_model::AuthMode::Multi {
@@ -632,6 +637,7 @@ impl Plugin for OAuth2AuthPlugin {
             Ok(Some(claims)) => claims,
             Ok(None) => {
                 debug!("OAuth2 token validation failed");
+                metrics::track_auth_failure(self.name(), &ctx.proxy.id, "invalid_credentials");
                 
                 // In multi-auth mode, we continue even if this auth method failed
                 if ctx.proxy.auth_mode == crate::config::data_model::AuthMode::Multi {
@@ -642,6 +648,7 @@ impl Plugin for OAuth2AuthPlugin {
             },
             Err(e) => {
                 warn!("OAuth2 token validation error: {}", e);
+                metrics::track_auth_failure(self.name(), &ctx.proxy.id, "validation_error");
                 
                 // In multi-auth mode, we continue even if this auth method failed
                 if ctx.proxy.auth_mode == crate::config::data_model::AuthMode::Multi {
@@ -657,6 +664,7 @@ impl Plugin for OAuth2AuthPlugin {
             Some(consumer) => consumer,
             None => {
                 warn!("No consumer found for OAuth2 token");
+                metrics::track_auth_failure(self.name(), &ctx.proxy.id, "unknown_consumer");
                 
                 // In multi-auth mode, we continue even if this auth method failed
                 if ctx.proxy.auth_mode == crate::config::data_model::AuthMode::Multi {
@@ -669,6 +677,7 @@ impl Plugin for OAuth2AuthPlugin {
         
         // Set the consumer in the context
         ctx.consumer = Some(consumer);
+        metrics::track_auth_success(self.name(), &ctx.proxy.id);
         debug!("Consumer identified by OAuth2 token: {}", ctx.consumer.as_ref().unwrap().username);
         
         Ok(true)
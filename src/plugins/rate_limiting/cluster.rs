@@ -0,0 +1,153 @@
+//! Rate limiting counters shared by all Data Planes
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref COUNTERS: ClusterCounters = ClusterCounters::new();
+}
+
+/// How often, in seconds, the windows that have ended are dropped
+const SWEEP_INTERVAL: u64 = 60;
+
+/// The counters of this node, shared by all rate_limiting plugin instances
+pub fn counters() -> &'static ClusterCounters {
+    &COUNTERS
+}
+
+/// Seconds since the Unix epoch
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Start of the window of the given length containing `now`
+pub fn window_start(window_seconds: u64, now: u64) -> u64 {
+    now - now % window_seconds.max(1)
+}
+
+/// A rate limiting key in one window
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Window {
+    pub key: String,
+    pub window_seconds: u64,
+    pub window_start: u64,
+}
+
+impl Window {
+    pub fn current(key: &str, window_seconds: u64, now: u64) -> Self {
+        Self { key: key.to_string(), window_seconds, window_start: window_start(window_seconds, now) }
+    }
+
+    pub fn has_ended(&self, now: u64) -> bool {
+        self.window_start + self.window_seconds <= now
+    }
+}
+
+/// Requests counted in a window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowCount {
+    pub window: Window,
+    pub count: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counter {
+    /// Requests counted by this node
+    local: u64,
+    /// Part of `local` not reported to the Control Plane yet
+    unreported: u64,
+    /// Requests counted by the other nodes, as of the last synchronization
+    remote: u64,
+}
+
+/// Requests counted per key and window by this node and the others
+#[derive(Debug, Default)]
+pub struct ClusterCounters {
+    counters: Mutex<HashMap<Window, Counter>>,
+    last_sweep: Mutex<u64>,
+}
+
+impl ClusterCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests counted for a key in the current window by all nodes
+    pub fn count(&self, key: &str, window_seconds: u64, now: u64) -> u64 {
+        let counters = self.counters.lock().unwrap();
+        counters.get(&Window::current(key, window_seconds, now))
+            .map(|counter| counter.local + counter.remote)
+            .unwrap_or(0)
+    }
+
+    /// Counts a request for a key in the current window
+    pub fn record(&self, key: &str, window_seconds: u64, now: u64) {
+        let mut counters = self.counters.lock().unwrap();
+        let counter = counters.entry(Window::current(key, window_seconds, now)).or_default();
+        counter.local += 1;
+        counter.unreported += 1;
+        self.sweep(&mut counters, now);
+    }
+
+    /// Drops the windows that have ended, at most once per SWEEP_INTERVAL.
+    /// Only Data Planes report their counts, so elsewhere nothing else would.
+    fn sweep(&self, counters: &mut HashMap<Window, Counter>, now: u64) {
+        let mut last_sweep = self.last_sweep.lock().unwrap();
+        if now.saturating_sub(*last_sweep) < SWEEP_INTERVAL {
+            return;
+        }
+        *last_sweep = now;
+        counters.retain(|window, _| !window.has_ended(now));
+    }
+
+    /// Takes the requests counted since the last call, and forgets the
+    /// windows that have ended
+    pub fn take_unreported(&self, now: u64) -> Vec<WindowCount> {
+        let mut counters = self.counters.lock().unwrap();
+        counters.retain(|window, _| !window.has_ended(now));
+        counters.iter_mut()
+            .filter(|(_, counter)| counter.unreported > 0)
+            .map(|(window, counter)| WindowCount {
+                window: window.clone(),
+                count: std::mem::take(&mut counter.unreported),
+            })
+            .collect()
+    }
+
+    /// Replaces the counts of the other nodes with those from the Control Plane
+    pub fn apply_remote(&self, counts: &[WindowCount], now: u64) {
+        let mut counters = self.counters.lock().unwrap();
+        for count in counts.iter().filter(|count| !count.window.has_ended(now)) {
+            counters.entry(count.window.clone()).or_default().remote = count.count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_drops_ended_windows() {
+        let counters = ClusterCounters::new();
+        let start = 1_700_000_000;
+
+        counters.record("ip:192.0.2.1", 10, start);
+        counters.record("ip:192.0.2.2", 3600, start);
+        assert_eq!(counters.counters.lock().unwrap().len(), 2);
+
+        // Nothing is dropped before the sweep interval
+        counters.record("ip:192.0.2.3", 10, start + 20);
+        assert_eq!(counters.counters.lock().unwrap().len(), 3);
+
+        // The ended windows go without a synchronization, the open one stays
+        counters.record("ip:192.0.2.3", 10, start + SWEEP_INTERVAL + 20);
+        let windows = counters.counters.lock().unwrap();
+        assert_eq!(windows.len(), 2);
+        assert!(windows.keys().any(|window| window.key == "ip:192.0.2.2"));
+        assert!(windows.keys().all(|window| window.key != "ip:192.0.2.1"));
+        assert_eq!(windows.get(&Window::current("ip:192.0.2.3", 10, start + SWEEP_INTERVAL + 20)).unwrap().local, 1);
+    }
+}
@@ -9,8 +9,12 @@ use tokio::sync::Mutex;
 use std::time::{Duration, Instant};
 use dashmap::DashMap;
 
+use crate::metrics;
 use crate::plugins::Plugin;
 use crate::proxy::handler::RequestContext;
+use crate::proxy::error_pages::gateway_error_response;
+
+pub mod cluster;
 
 /// Configuration for the rate limiting plugin
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +38,10 @@ pub struct RateLimitingConfig {
     /// Whether to add X-RateLimit headers to responses
     #[serde(default = "default_true")]
     pub add_headers: bool,
+    
+    /// Whether requests are counted by this node only or by all Data Planes
+    #[serde(default)]
+    pub policy: RateLimitPolicy,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -51,6 +59,16 @@ impl Default for LimitBy {
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitPolicy {
+    /// Count the requests this node handles
+    #[default]
+    Local,
+    /// Count the requests of all Data Planes, synchronized through the Control Plane
+    Cluster,
+}
+
 fn default_true() -> bool {
     true
 }
@@ -63,6 +81,7 @@ impl Default for RateLimitingConfig {
             requests_per_minute: 0,
             requests_per_hour: 0,
             add_headers: true,
+            policy: RateLimitPolicy::default(),
         }
     }
 }
@@ -276,6 +295,41 @@ impl RateLimitingPlugin {
         }
     }
     
+    /// Check the limits against the requests all nodes counted for a key in
+    /// the current windows
+    fn check_cluster_limits(&self, key: &str, now: u64) -> RateLimitCheckResult {
+        let mut result = RateLimitCheckResult {
+            limit_exceeded: false,
+            window_unit: None,
+            remaining: HashMap::new(),
+        };
+        
+        for (unit, window_seconds, limit) in self.windows() {
+            if limit == 0 {
+                continue;
+            }
+            let used = cluster::counters().count(key, window_seconds, now);
+            let remaining = (limit as u64).saturating_sub(used) as u32;
+            result.remaining.insert(unit.to_string(), remaining);
+            
+            if remaining == 0 && !result.limit_exceeded {
+                result.limit_exceeded = true;
+                result.window_unit = Some(unit.to_string());
+            }
+        }
+        
+        result
+    }
+    
+    /// The configured limits with their window unit and length in seconds
+    fn windows(&self) -> [(&'static str, u64, u32); 3] {
+        [
+            ("second", 1, self.config.requests_per_second),
+            ("minute", 60, self.config.requests_per_minute),
+            ("hour", 3600, self.config.requests_per_hour),
+        ]
+    }
+    
     /// Add rate limit headers to the response
     fn add_rate_limit_headers(&self, resp: &mut Response<Body>, check_result: &RateLimitCheckResult) {
         if !self.config.add_headers {
@@ -349,12 +403,17 @@ impl Plugin for RateLimitingPlugin {
         let key = self.get_rate_limit_key(ctx);
         
         // Check if rate limit is exceeded
-        let check_result = self.state.check_limits(
-            &key,
-            self.config.requests_per_second,
-            self.config.requests_per_minute,
-            self.config.requests_per_hour,
-        );
+        let now = cluster::unix_now();
+        let check_result = match self.config.policy {
+            RateLimitPolicy::Local => self.state.check_limits(
+                &key,
+                self.config.requests_per_second,
+                self.config.requests_per_minute,
+                self.config.requests_per_hour,
+            ),
+            // Cluster counters are shared by all proxies, so the key is scoped to this one
+            RateLimitPolicy::Cluster => self.check_cluster_limits(&format!("{}:{}", ctx.proxy.id, key), now),
+        };
         
         if check_result.limit_exceeded {
             // Rate limit exceeded
@@ -363,12 +422,19 @@ impl Plugin for RateLimitingPlugin {
                 key,
                 check_result.window_unit.as_deref().unwrap_or("unknown")
             );
+            let key_type = if key.starts_with("consumer:") { "consumer" } else { "ip" };
+            metrics::track_rate_limit_rejection(
+                &ctx.proxy.id,
+                check_result.window_unit.as_deref().unwrap_or("unknown"),
+                key_type,
+            );
             
             // Create a 429 response
-            let mut response = Response::builder()
-                .status(StatusCode::TOO_MANY_REQUESTS)
-                .body(Body::from("Rate limit exceeded"))
-                .unwrap();
+            let mut response = gateway_error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate_limited",
+                "Rate limit exceeded",
+            );
             
             // Add rate limit headers
             self.add_rate_limit_headers(&mut response, &check_result);
@@ -380,12 +446,22 @@ impl Plugin for RateLimitingPlugin {
         }
         
         // Record the request
-        self.state.record_request(
-            &key,
-            self.config.requests_per_second,
-            self.config.requests_per_minute,
-            self.config.requests_per_hour,
-        );
+        match self.config.policy {
+            RateLimitPolicy::Local => self.state.record_request(
+                &key,
+                self.config.requests_per_second,
+                self.config.requests_per_minute,
+                self.config.requests_per_hour,
+            ),
+            RateLimitPolicy::Cluster => {
+                let key = format!("{}:{}", ctx.proxy.id, key);
+                for (_, window_seconds, limit) in self.windows() {
+                    if limit > 0 {
+                        cluster::counters().record(&key, window_seconds, now);
+                    }
+                }
+            },
+        }
         
         Ok(true)
     }
@@ -59,6 +59,8 @@ struct TransactionSummary {
     latency_gateway_processing_ms: u64,
     latency_backend_ttfb_ms: u64,
     latency_backend_total_ms: u64,
+    request_bytes: u64,
+    response_bytes: Option<u64>,
     user_agent: Option<String>,
 }
 
@@ -109,6 +111,8 @@ impl Plugin for StdoutLoggingPlugin {
             latency_gateway_processing_ms: ctx.latency.gateway_processing.as_millis() as u64,
             latency_backend_ttfb_ms: ctx.latency.backend_ttfb.as_millis() as u64,
             latency_backend_total_ms: ctx.latency.backend_total.as_millis() as u64,
+            request_bytes: ctx.request_bytes,
+            response_bytes: crate::proxy::body::declared_size(resp),
             user_agent,
         };
         
@@ -0,0 +1,102 @@
+//! Versions of a proxy's API served by different backends
+
+use hyper::header::{HeaderValue, ACCEPT};
+use hyper::{Body, Request, Response};
+
+use crate::config::data_model::{ApiVersion, ApiVersionSource, ApiVersioning, Proxy};
+
+/// Header holding the version, with the header source and no `name`
+pub const DEFAULT_VERSION_HEADER: &str = "Api-Version";
+
+/// Accept media type parameter holding the version, with the accept source and no `name`
+pub const DEFAULT_ACCEPT_PARAMETER: &str = "version";
+
+/// The version a request names, if any. With the path source, only a first
+/// segment that is one of the versions counts, so unversioned paths still work.
+pub fn requested_version(versioning: &ApiVersioning, req: &Request<Body>, listen_path: &str) -> Option<String> {
+    match versioning.source {
+        ApiVersionSource::Header => {
+            let name = versioning.name.as_deref().unwrap_or(DEFAULT_VERSION_HEADER);
+            req.headers().get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        },
+        ApiVersionSource::Accept => {
+            let name = versioning.name.as_deref().unwrap_or(DEFAULT_ACCEPT_PARAMETER);
+            req.headers().get_all(ACCEPT).iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .flat_map(|media_type| media_type.split(';').skip(1))
+                .filter_map(|parameter| parameter.split_once('='))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.trim().trim_matches('"').to_string())
+                .filter(|value| !value.is_empty())
+        },
+        ApiVersionSource::Path => {
+            let rest = req.uri().path().strip_prefix(listen_path.trim_end_matches('/'))?;
+            let segment = rest.trim_start_matches('/').split('/').next()?;
+            versioning.versions.iter()
+                .find(|version| version.version == segment)
+                .map(|version| version.version.clone())
+        },
+    }
+}
+
+/// The version that serves a request: the one it names, else the default
+/// version. `Ok(None)` leaves the request to the proxy's own backend, and
+/// `Err` holds a named version the proxy does not have.
+pub fn select<'a>(versioning: &'a ApiVersioning, req: &Request<Body>, listen_path: &str) -> Result<Option<&'a ApiVersion>, String> {
+    let requested = requested_version(versioning, req, listen_path);
+    let wanted = match requested.as_deref().or(versioning.default_version.as_deref()) {
+        Some(wanted) => wanted,
+        None => return Ok(None),
+    };
+    versioning.versions.iter()
+        .find(|version| version.version == wanted)
+        .map(Some)
+        .ok_or_else(|| wanted.to_string())
+}
+
+/// The proxy as it serves a version: with the version's backend, and with
+/// the path source, the version segment as part of the listen path
+pub fn apply(proxy: &Proxy, version: &ApiVersion) -> Proxy {
+    let mut versioned = proxy.clone();
+    if let Some(host) = &version.backend_host {
+        versioned.backend_host = host.clone();
+        versioned.backend_discovery = None;
+    }
+    if let Some(port) = version.backend_port {
+        versioned.backend_port = port;
+    }
+    if let Some(path) = &version.backend_path {
+        versioned.backend_path = Some(path.clone());
+    }
+
+    let path_source = proxy.api_versioning.as_ref()
+        .map_or(false, |versioning| versioning.source == ApiVersionSource::Path);
+    if path_source {
+        versioned.listen_path = format!("{}/{}", proxy.listen_path.trim_end_matches('/'), version.version);
+    }
+    versioned
+}
+
+/// Announces the deprecation and removal of a version on its responses
+pub fn add_deprecation_headers(version: &ApiVersion, response: &mut Response<Body>) {
+    if !version.deprecated && version.sunset.is_none() {
+        return;
+    }
+
+    let headers = response.headers_mut();
+    if version.deprecated {
+        headers.insert("Deprecation", HeaderValue::from_static("true"));
+    }
+    if let Some(sunset) = version.sunset.as_deref().and_then(|sunset| HeaderValue::from_str(sunset).ok()) {
+        headers.insert("Sunset", sunset);
+    }
+    if let Some(link) = &version.deprecation_link {
+        if let Ok(value) = HeaderValue::from_str(&format!("<{}>; rel=\"deprecation\"", link)) {
+            headers.append("Link", value);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Passive health of the backends of each proxy
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+/// Consecutive failed requests after which a backend is down
+pub const DOWN_AFTER_FAILURES: u32 = 3;
+
+/// Backends no request was sent to for this long are forgotten, so that
+/// removed proxies and deregistered instances drop out of the reports
+const FORGET_AFTER: Duration = Duration::from_secs(600);
+
+lazy_static! {
+    static ref BACKENDS: Mutex<BackendHealthTracker> = Mutex::new(BackendHealthTracker::default());
+}
+
+#[derive(Debug, Clone)]
+struct BackendState {
+    consecutive_failures: u32,
+    last_error: Option<String>,
+    last_request: Instant,
+}
+
+/// Health of one backend of a proxy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BackendHealth {
+    /// host:port of the backend
+    pub address: String,
+    pub up: bool,
+    pub consecutive_failures: u32,
+    /// Error class of the last failed request, while failures are consecutive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Health of a proxy's backends: `healthy` when all are up, `degraded` when
+/// some are down and `down` when all are
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProxyHealth {
+    pub proxy_id: String,
+    pub status: String,
+    pub backends: Vec<BackendHealth>,
+}
+
+/// Outcomes of the recent requests to each backend, by proxy
+#[derive(Debug, Default)]
+pub struct BackendHealthTracker {
+    backends: HashMap<(String, String), BackendState>,
+}
+
+impl BackendHealthTracker {
+    pub fn record_success(&mut self, proxy_id: &str, address: &str, now: Instant) {
+        self.backends.insert((proxy_id.to_string(), address.to_string()), BackendState {
+            consecutive_failures: 0,
+            last_error: None,
+            last_request: now,
+        });
+    }
+
+    pub fn record_failure(&mut self, proxy_id: &str, address: &str, error_class: &str, now: Instant) {
+        let state = self.backends.entry((proxy_id.to_string(), address.to_string()))
+            .or_insert(BackendState { consecutive_failures: 0, last_error: None, last_request: now });
+        state.consecutive_failures += 1;
+        state.last_error = Some(error_class.to_string());
+        state.last_request = now;
+    }
+
+    /// The health of every proxy with recently used backends, by proxy ID
+    pub fn report(&mut self, now: Instant) -> Vec<ProxyHealth> {
+        self.backends.retain(|_, state| now.duration_since(state.last_request) < FORGET_AFTER);
+
+        let mut proxies: HashMap<&str, Vec<BackendHealth>> = HashMap::new();
+        for ((proxy_id, address), state) in &self.backends {
+            proxies.entry(proxy_id).or_default().push(BackendHealth {
+                address: address.clone(),
+                up: state.consecutive_failures < DOWN_AFTER_FAILURES,
+                consecutive_failures: state.consecutive_failures,
+                last_error: state.last_error.clone(),
+            });
+        }
+
+        let mut report: Vec<ProxyHealth> = proxies.into_iter()
+            .map(|(proxy_id, mut backends)| {
+                backends.sort_by(|a, b| a.address.cmp(&b.address));
+                let down = backends.iter().filter(|backend| !backend.up).count();
+                let status = match down {
+                    0 => "healthy",
+                    down if down == backends.len() => "down",
+                    _ => "degraded",
+                };
+                ProxyHealth { proxy_id: proxy_id.to_string(), status: status.to_string(), backends }
+            })
+            .collect();
+        report.sort_by(|a, b| a.proxy_id.cmp(&b.proxy_id));
+        report
+    }
+}
+
+// Track a request that reached a backend of a proxy
+pub fn record_success(proxy_id: &str, address: &str) {
+    BACKENDS.lock().unwrap().record_success(proxy_id, address, Instant::now());
+}
+
+// Track a request that failed to reach a backend of a proxy
+pub fn record_failure(proxy_id: &str, address: &str, error_class: &str) {
+    BACKENDS.lock().unwrap().record_failure(proxy_id, address, error_class, Instant::now());
+}
+
+// The health of the backends of every proxy that sent requests recently
+pub fn report() -> Vec<ProxyHealth> {
+    BACKENDS.lock().unwrap().report(Instant::now())
+}
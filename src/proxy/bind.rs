@@ -0,0 +1,68 @@
+//! Listener bind addresses
+
+use std::io;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use anyhow::{anyhow, Context, Result};
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::TcpListener;
+
+/// Pending connections queued per TCP listener, as with `TcpListener::bind`
+const LISTEN_BACKLOG: i32 = 1024;
+
+/// Reads a comma-separated list of addresses; IPv6 addresses may be in brackets
+pub fn parse_addresses(value: &str) -> Result<Vec<IpAddr>> {
+    let addresses = value.split(',')
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .map(|address| {
+            address.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>()
+                .map_err(|_| anyhow!("'{}' is not an IP address", address))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if addresses.is_empty() {
+        return Err(anyhow!("expected at least one IP address"));
+    }
+    Ok(addresses)
+}
+
+/// Binds a TCP listener to each address. With port 0 the first listener
+/// picks a free port and the others bind to the same one.
+pub fn tcp_listeners(addresses: &[IpAddr], port: u16) -> Result<Vec<TcpListener>> {
+    let v6_only = addresses.iter().any(IpAddr::is_ipv4);
+    let mut listeners: Vec<TcpListener> = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        let port = match listeners.first() {
+            Some(first) if port == 0 => first.local_addr()?.port(),
+            _ => port,
+        };
+        let addr = SocketAddr::new(*address, port);
+        let listener = tcp_listener(addr, v6_only).with_context(|| format!("Failed to bind {}", addr))?;
+        listeners.push(listener);
+    }
+    Ok(listeners)
+}
+
+/// Binds a TCP listener, IPv6-only if `v6_only` is set
+pub fn tcp_listener(addr: SocketAddr, v6_only: bool) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(v6_only)?;
+    }
+    // Restarted listeners can rebind while old connections are in TIME_WAIT
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(LISTEN_BACKLOG)?;
+    TcpListener::from_std(socket.into())
+}
+
+/// Binds the UDP socket of a QUIC listener, IPv6-only if `v6_only` is set
+pub fn udp_socket(addr: SocketAddr, v6_only: bool) -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(v6_only)?;
+    }
+    socket.bind(&addr.into())?;
+    Ok(socket.into())
+}
@@ -0,0 +1,224 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use hyper::body::{HttpBody, Sender};
+use hyper::{Body, Request, Response};
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+/// Passes a body on while counting its bytes, and calls `on_end` with the
+/// count once it has been sent, failed or been dropped by the client.
+/// Trailers are forwarded, so gRPC statuses reach the client.
+pub fn count_body<F>(mut body: Body, on_end: F) -> Body
+where
+    F: FnOnce(u64) + Send + 'static,
+{
+    if body.is_end_stream() {
+        on_end(0);
+        return body;
+    }
+
+    let (mut sender, counted) = Body::channel();
+    tokio::spawn(async move {
+        let mut bytes = 0;
+        let mut complete = true;
+        while let Some(chunk) = body.data().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(_) => {
+                    sender.abort();
+                    complete = false;
+                    break;
+                }
+            };
+            let len = chunk.len() as u64;
+            if sender.send_data(chunk).await.is_err() {
+                complete = false;
+                break;
+            }
+            bytes += len;
+        }
+        if complete {
+            if let Ok(Some(trailers)) = body.trailers().await {
+                let _ = sender.send_trailers(trailers).await;
+            }
+        }
+        on_end(bytes);
+    });
+    counted
+}
+
+/// The size of a response body as known before it is sent: its Content-Length,
+/// or the length of a body held in memory
+pub fn declared_size(response: &Response<Body>) -> Option<u64> {
+    response.headers().get(hyper::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .or_else(|| response.body().size_hint().exact())
+}
+
+/// The size of a request body as known before it is read: its Content-Length,
+/// or the length of a body held in memory
+pub fn declared_request_size(request: &Request<Body>) -> Option<u64> {
+    request.headers().get(hyper::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .or_else(|| request.body().size_hint().exact())
+}
+
+/// Why the gateway stopped passing a request body on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyLimitError {
+    /// The body grew past the maximum body size
+    TooLarge,
+    /// The client did not send the body within the client body timeout
+    Timeout,
+    /// The client sent the body below the minimum transfer rate
+    TooSlow,
+    /// Reading the body from the client failed
+    Client,
+}
+
+/// Limits a request body is held to while it is read from the client
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BodyLimits {
+    /// Bytes allowed in the body (0 disables)
+    pub max_bytes: u64,
+    /// Time the client may spend sending the body
+    pub timeout: Option<Duration>,
+    /// Minimum transfer rate in bytes per second (0 disables)
+    pub min_rate: u64,
+    /// How long the client may send the body before the minimum rate applies
+    pub min_rate_grace_period: Duration,
+    /// Leading bytes of the body to keep, for a tap (0 keeps none)
+    pub capture_bytes: usize,
+}
+
+/// How far a limited request body has been read from the client
+#[derive(Debug, Default)]
+pub struct BodyProgress {
+    received: AtomicU64,
+    complete: AtomicBool,
+    captured: Mutex<Vec<u8>>,
+}
+
+impl BodyProgress {
+    /// Bytes read from the client so far
+    pub fn received(&self) -> u64 {
+        self.received.load(Ordering::Acquire)
+    }
+
+    /// Whether the client sent the whole body
+    pub fn is_complete(&self) -> bool {
+        self.complete.load(Ordering::Acquire)
+    }
+
+    /// The leading bytes of the body, up to the limit's `capture_bytes`
+    pub fn captured(&self) -> Vec<u8> {
+        self.captured.lock().unwrap().clone()
+    }
+}
+
+/// A request body passed on as the client sends it
+pub struct LimitedBody {
+    pub body: Body,
+    pub progress: Arc<BodyProgress>,
+    /// Gets the limit the client crossed; closed without one when the body
+    /// ended or its reader went away
+    pub failure: oneshot::Receiver<BodyLimitError>,
+}
+
+/// Passes a request body on chunk by chunk, checking the limits as each chunk
+/// arrives rather than once the body is read. When the client crosses one, the
+/// reason is sent on `failure` and the passed on body ends with an error, so the
+/// backend does not take a truncated body for a whole one. Only the time spent
+/// waiting for the client counts towards the timeout and the minimum rate.
+/// Trailers are forwarded, and `on_end` gets the bytes read from the client.
+pub fn limit_body<F>(mut body: Body, limits: BodyLimits, on_end: F) -> LimitedBody
+where
+    F: FnOnce(u64) + Send + 'static,
+{
+    let progress = Arc::new(BodyProgress::default());
+    let (failure_tx, failure) = oneshot::channel();
+    if body.is_end_stream() {
+        progress.complete.store(true, Ordering::Release);
+        on_end(0);
+        return LimitedBody { body, progress, failure };
+    }
+
+    let (mut sender, limited) = Body::channel();
+    let read = Arc::clone(&progress);
+    tokio::spawn(async move {
+        match forward_limited(&mut body, &mut sender, &limits, &read).await {
+            Ok(()) => read.complete.store(true, Ordering::Release),
+            Err(Some(error)) => {
+                // Reported before the backend sees the error, so the client gets
+                // the limit's response rather than a backend failure
+                let _ = failure_tx.send(error);
+                sender.abort();
+            },
+            Err(None) => {},
+        }
+        on_end(read.received());
+    });
+    LimitedBody { body: limited, progress, failure }
+}
+
+/// Forwards the chunks and trailers of `body`. Fails with the crossed limit,
+/// or with None when the reader of the passed on body went away.
+async fn forward_limited(
+    body: &mut Body,
+    sender: &mut Sender,
+    limits: &BodyLimits,
+    progress: &BodyProgress,
+) -> Result<(), Option<BodyLimitError>> {
+    let mut waited = Duration::ZERO;
+    let mut received = 0u64;
+
+    loop {
+        // The time left before the client body timeout or, at the current
+        // count, the minimum rate is crossed
+        let mut deadline = limits.timeout.map(|timeout| (timeout, BodyLimitError::Timeout));
+        if limits.min_rate > 0 {
+            let allowed = Duration::from_secs_f64(received as f64 / limits.min_rate as f64)
+                .max(limits.min_rate_grace_period);
+            if deadline.map_or(true, |(timeout, _)| allowed < timeout) {
+                deadline = Some((allowed, BodyLimitError::TooSlow));
+            }
+        }
+
+        let wait_started = Instant::now();
+        let chunk = match deadline {
+            Some((allowed, error)) => tokio::time::timeout(allowed.saturating_sub(waited), body.data())
+                .await
+                .map_err(|_| Some(error))?,
+            None => body.data().await,
+        };
+        waited += wait_started.elapsed();
+
+        let chunk = match chunk {
+            Some(Ok(chunk)) => chunk,
+            Some(Err(_)) => return Err(Some(BodyLimitError::Client)),
+            None => break,
+        };
+        received += chunk.len() as u64;
+        if limits.max_bytes > 0 && received > limits.max_bytes {
+            return Err(Some(BodyLimitError::TooLarge));
+        }
+
+        progress.received.store(received, Ordering::Release);
+        if limits.capture_bytes > 0 {
+            let mut captured = progress.captured.lock().unwrap();
+            let keep = (limits.capture_bytes - captured.len().min(limits.capture_bytes)).min(chunk.len());
+            captured.extend_from_slice(&chunk[..keep]);
+        }
+        sender.send_data(chunk).await.map_err(|_| None)?;
+    }
+
+    // HTTP/2 clients may end the body with trailers, which go to the backend too
+    match body.trailers().await {
+        Ok(Some(trailers)) => sender.send_trailers(trailers).await.map_err(|_| None),
+        Ok(None) => Ok(()),
+        Err(_) => Err(Some(BodyLimitError::Client)),
+    }
+}
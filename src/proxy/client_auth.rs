@@ -0,0 +1,189 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use anyhow::{anyhow, Context, Result};
+use sha2::{Digest, Sha256};
+use tokio_rustls::rustls::{Certificate, RootCertStore};
+use tokio_rustls::rustls::server::{
+    AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient, ClientCertVerifier,
+    UnparsedCertRevocationList,
+};
+use tracing::info;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::FromDer;
+
+use crate::config::data_model::{ClientAuthMode, OcspMode};
+use super::ocsp::OcspChecker;
+
+/// Identity of a verified client certificate. Attached to requests on mTLS
+/// listeners and exposed to plugins through the RequestContext.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCertificate {
+    /// Subject distinguished name, e.g. "CN=client.example.com, O=Example"
+    pub subject: String,
+    pub issuer: String,
+    /// Serial number as colon separated hex
+    pub serial: String,
+    /// Subject Alternative Names by type
+    pub dns_names: Vec<String>,
+    pub emails: Vec<String>,
+    pub uris: Vec<String>,
+    pub ip_addresses: Vec<IpAddr>,
+    /// SHA-256 of the DER encoded certificate, lowercase hex
+    pub fingerprint_sha256: String,
+}
+
+impl ClientCertificate {
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let (_, certificate) = X509Certificate::from_der(der)
+            .map_err(|e| anyhow!("Failed to parse client certificate: {}", e))?;
+
+        let mut identity = Self {
+            subject: certificate.subject().to_string(),
+            issuer: certificate.issuer().to_string(),
+            serial: certificate.raw_serial_as_string(),
+            dns_names: Vec::new(),
+            emails: Vec::new(),
+            uris: Vec::new(),
+            ip_addresses: Vec::new(),
+            fingerprint_sha256: hex::encode(Sha256::digest(der)),
+        };
+
+        if let Ok(Some(sans)) = certificate.subject_alternative_name() {
+            for name in &sans.value.general_names {
+                match name {
+                    GeneralName::DNSName(dns_name) => identity.dns_names.push(dns_name.to_string()),
+                    GeneralName::RFC822Name(email) => identity.emails.push(email.to_string()),
+                    GeneralName::URI(uri) => identity.uris.push(uri.to_string()),
+                    GeneralName::IPAddress(bytes) => {
+                        let ip = match bytes.len() {
+                            4 => <[u8; 4]>::try_from(*bytes).ok().map(IpAddr::from),
+                            16 => <[u8; 16]>::try_from(*bytes).ok().map(IpAddr::from),
+                            _ => None,
+                        };
+                        identity.ip_addresses.extend(ip);
+                    },
+                    _ => {},
+                }
+            }
+        }
+
+        Ok(identity)
+    }
+
+    /// The subject's common name, if it has one
+    pub fn common_name(&self) -> Option<&str> {
+        self.subject.split(", ")
+            .find_map(|component| component.strip_prefix("CN="))
+    }
+}
+
+/// Client certificate verification for a TLS listener. Chain and CRL checks
+/// happen during the handshake; OCSP is checked right after it, since it
+/// needs a network round trip.
+pub struct ClientAuth {
+    verifier: Arc<dyn ClientCertVerifier>,
+    ocsp: Option<OcspChecker>,
+}
+
+impl ClientAuth {
+    /// Builds the verifier for a listener, or None when client certificates are not requested
+    pub fn new(
+        mode: ClientAuthMode,
+        ca_path: Option<&str>,
+        crl_paths: &[String],
+        ocsp_mode: OcspMode,
+    ) -> Result<Option<Arc<Self>>> {
+        if mode == ClientAuthMode::None {
+            return Ok(None);
+        }
+
+        let ca_path = ca_path.context("A client CA bundle is required to verify client certificates")?;
+        let ca_certs = load_certificates(ca_path)?;
+
+        let mut roots = RootCertStore::empty();
+        for certificate in &ca_certs {
+            roots.add(certificate).context(format!("Invalid CA certificate in {}", ca_path))?;
+        }
+
+        let crls = load_crls(crl_paths)?;
+        let verifier = match mode {
+            ClientAuthMode::Required => AllowAnyAuthenticatedClient::new(roots)
+                .with_crls(crls)
+                .map_err(|e| anyhow!("Invalid certificate revocation list: {:?}", e))?
+                .boxed(),
+            _ => AllowAnyAnonymousOrAuthenticatedClient::new(roots)
+                .with_crls(crls)
+                .map_err(|e| anyhow!("Invalid certificate revocation list: {:?}", e))?
+                .boxed(),
+        };
+
+        let ocsp = (ocsp_mode != OcspMode::Off).then(|| OcspChecker::new(ocsp_mode, ca_certs));
+
+        info!(
+            "Client certificate verification {:?} with CA bundle {} ({} CRLs, OCSP {:?})",
+            mode, ca_path, crl_paths.len(), ocsp_mode
+        );
+
+        Ok(Some(Arc::new(Self { verifier, ocsp })))
+    }
+
+    pub fn verifier(&self) -> Arc<dyn ClientCertVerifier> {
+        Arc::clone(&self.verifier)
+    }
+
+    /// Runs the post-handshake checks on the chain the client presented and
+    /// returns its identity. The chain has already been verified by rustls.
+    pub async fn authorize(&self, peer_certificates: Option<&[Certificate]>) -> Result<Option<Arc<ClientCertificate>>> {
+        let chain = match peer_certificates {
+            Some(chain) if !chain.is_empty() => chain,
+            _ => return Ok(None),
+        };
+
+        if let Some(ocsp) = &self.ocsp {
+            ocsp.check(chain).await?;
+        }
+
+        ClientCertificate::from_der(&chain[0].0).map(|identity| Some(Arc::new(identity)))
+    }
+}
+
+fn load_certificates(path: &str) -> Result<Vec<Certificate>> {
+    let pem = std::fs::read(path).context(format!("Failed to read CA bundle: {}", path))?;
+    let certificates: Vec<Certificate> = rustls_pemfile::certs(&mut pem.as_slice())
+        .context(format!("Failed to parse CA bundle: {}", path))?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+
+    if certificates.is_empty() {
+        anyhow::bail!("No certificates found in CA bundle: {}", path);
+    }
+    Ok(certificates)
+}
+
+/// Loads CRLs from PEM ("X509 CRL") or DER files
+fn load_crls(paths: &[String]) -> Result<Vec<UnparsedCertRevocationList>> {
+    let mut crls = Vec::new();
+
+    for path in paths {
+        let data = std::fs::read(path).context(format!("Failed to read CRL: {}", path))?;
+
+        let pem_crls: Vec<UnparsedCertRevocationList> = rustls_pemfile::read_all(&mut data.as_slice())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|item| match item {
+                rustls_pemfile::Item::Crl(der) => Some(UnparsedCertRevocationList(der)),
+                _ => None,
+            })
+            .collect();
+
+        if pem_crls.is_empty() {
+            crls.push(UnparsedCertRevocationList(data));
+        } else {
+            crls.extend(pem_crls);
+        }
+    }
+
+    Ok(crls)
+}
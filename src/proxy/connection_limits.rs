@@ -0,0 +1,117 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use dashmap::DashMap;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config::env_config::EnvConfig;
+use crate::metrics;
+
+/// Why a new connection was refused by the ConnectionLimiter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionRejection {
+    /// The listener-wide connection limit has been reached
+    MaxConnections,
+    /// The client IP already holds its maximum number of connections
+    MaxConnectionsPerIp,
+}
+
+impl ConnectionRejection {
+    /// Label used for metrics
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionRejection::MaxConnections => "max_connections",
+            ConnectionRejection::MaxConnectionsPerIp => "max_connections_per_ip",
+        }
+    }
+}
+
+/// Tracks open connections across the proxy listeners and enforces the global
+/// and per-IP connection limits. A limit of 0 means unlimited.
+pub struct ConnectionLimiter {
+    global: Option<Arc<Semaphore>>,
+    max_per_ip: usize,
+    per_ip: Arc<DashMap<IpAddr, usize>>,
+}
+
+/// Held for the lifetime of an accepted connection; releases its slots on drop
+pub struct ConnectionGuard {
+    _permit: Option<OwnedSemaphorePermit>,
+    ip: IpAddr,
+    per_ip: Option<Arc<DashMap<IpAddr, usize>>>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(max_connections: usize, max_per_ip: usize) -> Self {
+        Self {
+            global: if max_connections > 0 {
+                Some(Arc::new(Semaphore::new(max_connections)))
+            } else {
+                None
+            },
+            max_per_ip,
+            per_ip: Arc::new(DashMap::new()),
+        }
+    }
+
+    pub fn from_env_config(env_config: &EnvConfig) -> Self {
+        Self::new(env_config.max_connections, env_config.max_connections_per_ip)
+    }
+
+    /// Tries to reserve a slot for a new connection from the given IP
+    pub fn try_acquire(&self, ip: IpAddr) -> Result<ConnectionGuard, ConnectionRejection> {
+        // Reserve the global slot first so a rejected per-IP check doesn't leak it
+        let permit = match &self.global {
+            Some(semaphore) => match Arc::clone(semaphore).try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => return Err(ConnectionRejection::MaxConnections),
+            },
+            None => None,
+        };
+
+        if self.max_per_ip == 0 {
+            metrics::track_connection_opened();
+            return Ok(ConnectionGuard {
+                _permit: permit,
+                ip,
+                per_ip: None,
+            });
+        }
+
+        // The entry guard holds the shard lock, so check-and-increment is atomic
+        let mut count = self.per_ip.entry(ip).or_insert(0);
+        if *count >= self.max_per_ip {
+            return Err(ConnectionRejection::MaxConnectionsPerIp);
+        }
+        *count += 1;
+
+        metrics::track_connection_opened();
+        Ok(ConnectionGuard {
+            _permit: permit,
+            ip,
+            per_ip: Some(Arc::clone(&self.per_ip)),
+        })
+    }
+
+    /// Number of connections currently held by an IP (only tracked when a per-IP limit is set)
+    pub fn connections_for(&self, ip: &IpAddr) -> usize {
+        self.per_ip.get(ip).map(|count| *count).unwrap_or(0)
+    }
+
+    /// Number of IPs currently holding connections (only tracked when a per-IP limit is set)
+    pub fn tracked_ips(&self) -> usize {
+        self.per_ip.len()
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        metrics::track_connection_closed();
+        if let Some(per_ip) = &self.per_ip {
+            // Remove the entry once the last connection from this IP closes
+            per_ip.remove_if_mut(&self.ip, |_, count| {
+                *count = count.saturating_sub(1);
+                *count == 0
+            });
+        }
+    }
+}
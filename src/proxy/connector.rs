@@ -0,0 +1,177 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use hyper::client::connect::{CaptureConnection, Connected, Connection};
+use hyper::service::Service;
+use hyper::{Response, Uri};
+use hyper_rustls::MaybeHttpsStream;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::metrics;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Wraps the TCP connector of the backend client: times each connection and
+/// counts it as active until it is closed, by backend host
+#[derive(Clone)]
+pub struct MeteredConnector<C> {
+    inner: C,
+}
+
+impl<C> MeteredConnector<C> {
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+}
+
+impl<C> Service<Uri> for MeteredConnector<C>
+where
+    C: Service<Uri> + Send,
+    C::Response: AsyncRead + AsyncWrite + Connection + Unpin + Send + 'static,
+    C::Future: Send + 'static,
+    C::Error: Into<BoxError>,
+{
+    type Response = MeteredStream<C::Response>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let backend_host = uri.host().unwrap_or_default().to_string();
+        let connecting = self.inner.call(uri);
+        Box::pin(async move {
+            let started_at = Instant::now();
+            let stream = connecting.await.map_err(Into::into)?;
+            metrics::track_upstream_connect(&backend_host, started_at.elapsed());
+            Ok(MeteredStream::new(stream, backend_host))
+        })
+    }
+}
+
+/// A backend connection, counted in the active connections until it is dropped
+pub struct MeteredStream<T> {
+    inner: T,
+    backend_host: String,
+    /// When the TCP connection was established, which starts the TLS handshake
+    connected_at: Instant,
+    uses: ConnectionUses,
+}
+
+impl<T> MeteredStream<T> {
+    fn new(inner: T, backend_host: String) -> Self {
+        metrics::track_upstream_connection_opened(&backend_host);
+        Self { inner, backend_host, connected_at: Instant::now(), uses: ConnectionUses::default() }
+    }
+}
+
+impl<T> Drop for MeteredStream<T> {
+    fn drop(&mut self) {
+        metrics::track_upstream_connection_closed(&self.backend_host);
+    }
+}
+
+impl<T: Connection> Connection for MeteredStream<T> {
+    fn connected(&self) -> Connected {
+        // Every response received over the connection carries its use count
+        self.inner.connected().extra(self.uses.clone())
+    }
+}
+
+/// The responses received over a backend connection so far, shared by all of them
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionUses(Arc<AtomicU64>);
+
+impl ConnectionUses {
+    /// Counts one more response and returns the count
+    pub fn record(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+/// Keeps the connection a response came over out of the pool once it has
+/// carried `max_requests`, so the next request opens a new one
+pub fn limit_connection_uses<B>(response: &Response<B>, connection: &CaptureConnection, max_requests: u64) {
+    let used_up = response.extensions().get::<ConnectionUses>()
+        .map_or(false, |uses| uses.record() >= max_requests);
+    if used_up {
+        if let Some(connected) = connection.connection_metadata().as_ref() {
+            connected.poison();
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for MeteredStream<T> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for MeteredStream<T> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(mut self: Pin<&mut Self>, cx: &mut Context<'_>, bufs: &[io::IoSlice<'_>]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+/// Wraps the HTTPS connector of the backend client to time TLS handshakes,
+/// from the end of the TCP connect to the end of the handshake
+#[derive(Clone)]
+pub struct HandshakeTimer<C> {
+    inner: C,
+}
+
+impl<C> HandshakeTimer<C> {
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+}
+
+impl<C, T> Service<Uri> for HandshakeTimer<C>
+where
+    C: Service<Uri, Response = MaybeHttpsStream<MeteredStream<T>>> + Send,
+    C::Future: Send + 'static,
+    C::Error: Into<BoxError>,
+    T: Send + 'static,
+{
+    type Response = C::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connecting = self.inner.call(uri);
+        Box::pin(async move {
+            let stream = connecting.await.map_err(Into::into)?;
+            if let MaybeHttpsStream::Https(tls) = &stream {
+                let tcp = tls.get_ref().0;
+                metrics::track_upstream_tls_handshake(&tcp.backend_host, tcp.connected_at.elapsed());
+            }
+            Ok(stream)
+        })
+    }
+}
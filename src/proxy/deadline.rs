@@ -0,0 +1,138 @@
+//! Request deadline propagation
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use arc_swap::ArcSwapOption;
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::config::env_config::EnvConfig;
+
+static SETTINGS: ArcSwapOption<DeadlineSettings> = ArcSwapOption::const_empty();
+
+/// Time left for a gRPC call (gRPC over HTTP/2 protocol)
+pub const GRPC_TIMEOUT_HEADER: HeaderName = HeaderName::from_static("grpc-timeout");
+
+/// grpc-timeout values have at most 8 digits
+const GRPC_TIMEOUT_MAX_DIGITS: u128 = 99_999_999;
+
+/// The moment the listener's total request timeout expires, carried in the
+/// request extensions when FERRUM_REQUEST_TIMEOUT_MS is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestDeadline(pub Instant);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeadlineSettings {
+    /// Header telling backends the milliseconds left, None to send only grpc-timeout
+    pub header: Option<HeaderName>,
+}
+
+impl DeadlineSettings {
+    pub fn from_env_config(env_config: &EnvConfig) -> Self {
+        Self {
+            header: env_config.deadline_header.as_deref()
+                .and_then(|name| HeaderName::from_bytes(name.as_bytes()).ok()),
+        }
+    }
+}
+
+/// Applies the deadline header from the environment
+pub fn start(env_config: &EnvConfig) {
+    SETTINGS.store(Some(Arc::new(DeadlineSettings::from_env_config(env_config))));
+}
+
+/// The deadline of a backend request taken at `start`, or None when neither
+/// the proxy, the listener nor the client limit the time it may take
+pub fn backend_deadline(
+    settings: &DeadlineSettings,
+    budget: Option<Duration>,
+    start: Instant,
+    headers: &HeaderMap,
+    request_deadline: Option<RequestDeadline>,
+    grpc: bool,
+) -> Option<Instant> {
+    let client_timeout = if grpc {
+        headers.get(GRPC_TIMEOUT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_grpc_timeout)
+    } else {
+        settings.header.as_ref()
+            .and_then(|header| headers.get(header))
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_millis)
+    };
+
+    [budget, client_timeout].into_iter()
+        .flatten()
+        .map(|timeout| start + timeout)
+        .chain(request_deadline.map(|deadline| deadline.0))
+        .min()
+}
+
+/// The deadline of a backend request, with the configured deadline header
+pub fn deadline_for(
+    budget: Option<Duration>,
+    start: Instant,
+    headers: &HeaderMap,
+    request_deadline: Option<RequestDeadline>,
+    grpc: bool,
+) -> Option<Instant> {
+    let settings = SETTINGS.load_full().unwrap_or_default();
+    backend_deadline(&settings, budget, start, headers, request_deadline, grpc)
+}
+
+/// Tells the backend how much time is left until the deadline, replacing the
+/// values the client sent
+pub fn propagate(headers: &mut HeaderMap, deadline: Instant, grpc: bool) {
+    let settings = SETTINGS.load_full().unwrap_or_default();
+    set_headers(&settings, headers, deadline.saturating_duration_since(Instant::now()), grpc);
+}
+
+/// Sets `grpc-timeout` for gRPC backends and the configured header for the others
+pub fn set_headers(settings: &DeadlineSettings, headers: &mut HeaderMap, remaining: Duration, grpc: bool) {
+    if grpc {
+        headers.insert(GRPC_TIMEOUT_HEADER, grpc_timeout_value(remaining));
+    } else if let Some(header) = &settings.header {
+        headers.insert(header.clone(), HeaderValue::from(remaining.as_millis() as u64));
+    }
+}
+
+/// Encodes a timeout as grpc-timeout, in the finest unit that fits in 8 digits.
+/// Rounding down keeps the backend's deadline ahead of the gateway's.
+pub fn grpc_timeout_value(timeout: Duration) -> HeaderValue {
+    let units: [(u128, char); 6] = [
+        (timeout.as_nanos(), 'n'),
+        (timeout.as_micros(), 'u'),
+        (timeout.as_millis(), 'm'),
+        (timeout.as_secs() as u128, 'S'),
+        (timeout.as_secs() as u128 / 60, 'M'),
+        (timeout.as_secs() as u128 / 3600, 'H'),
+    ];
+    let (value, unit) = units.iter()
+        .find(|(value, _)| *value <= GRPC_TIMEOUT_MAX_DIGITS)
+        .copied()
+        .unwrap_or((GRPC_TIMEOUT_MAX_DIGITS, 'H'));
+    HeaderValue::from_str(&format!("{}{}", value, unit)).expect("grpc-timeout value is valid")
+}
+
+/// Reads a grpc-timeout value such as `100m` or `5S`
+pub fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if value.len() < 2 || value.len() > 9 || !value.is_char_boundary(value.len() - 1) {
+        return None;
+    }
+    let (amount, unit) = value.split_at(value.len() - 1);
+    if !amount.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let amount: u64 = amount.parse().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(amount * 3600)),
+        "M" => Some(Duration::from_secs(amount * 60)),
+        "S" => Some(Duration::from_secs(amount)),
+        "m" => Some(Duration::from_millis(amount)),
+        "u" => Some(Duration::from_micros(amount)),
+        "n" => Some(Duration::from_nanos(amount)),
+        _ => None,
+    }
+}
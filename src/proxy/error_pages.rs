@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use hyper::{Body, Response, StatusCode, header};
+use tracing::warn;
+
+use crate::config::data_model::{ErrorTemplate, Proxy};
+
+/// Marker stored in the extensions of responses generated by the gateway itself,
+/// so they can be told apart from backend responses when applying error templates.
+#[derive(Debug, Clone)]
+pub struct GatewayError {
+    /// Short machine readable classification, e.g. "no_route" or "upstream_timeout"
+    pub error_class: &'static str,
+    /// Human readable description used as the default body
+    pub message: String,
+}
+
+/// Header identifying the class of a gateway-generated error
+pub const GATEWAY_ERROR_HEADER: &str = "x-gateway-error";
+
+/// Longest client-supplied request ID that is reused
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Builds a plain-text gateway error response tagged with a GatewayError marker
+/// and an X-Gateway-Error header carrying the error class
+pub fn gateway_error_response(status: StatusCode, error_class: &'static str, message: &str) -> Response<Body> {
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .header(GATEWAY_ERROR_HEADER, error_class)
+        .body(Body::from(message.to_string()))
+        .unwrap();
+
+    response.extensions_mut().insert(GatewayError {
+        error_class,
+        message: message.to_string(),
+    });
+
+    response
+}
+
+/// Renders gateway-generated errors using the per-proxy templates, falling back
+/// to the global templates configured through FERRUM_ERROR_TEMPLATES.
+pub struct ErrorPages {
+    global_templates: HashMap<String, ErrorTemplate>,
+}
+
+impl ErrorPages {
+    pub fn new(global_templates: HashMap<String, ErrorTemplate>) -> Self {
+        Self { global_templates }
+    }
+
+    /// Applies a matching template to a gateway-generated response. Backend
+    /// responses (without a GatewayError marker) are returned untouched.
+    pub fn apply(&self, response: Response<Body>, proxy: Option<&Proxy>, request_id: &str) -> Response<Body> {
+        let gateway_error = match response.extensions().get::<GatewayError>() {
+            Some(gateway_error) => gateway_error.clone(),
+            None => return response,
+        };
+
+        let status = response.status();
+
+        // Per-proxy templates take precedence over the global ones
+        let template = proxy
+            .and_then(|p| Self::find_template(&p.error_templates, status))
+            .or_else(|| Self::find_template(&self.global_templates, status));
+
+        let template = match template {
+            Some(template) => template,
+            None => return response,
+        };
+
+        let final_status = match template.status.map(StatusCode::from_u16) {
+            Some(Ok(code)) => code,
+            Some(Err(_)) => {
+                warn!("Ignoring invalid status code in error template: {:?}", template.status);
+                status
+            },
+            None => status,
+        };
+
+        let body = Self::render(
+            &template.body,
+            &template.content_type,
+            status,
+            &gateway_error,
+            request_id,
+            proxy.map(|p| p.id.as_str()).unwrap_or(""),
+        );
+
+        // Keep the original headers (e.g. rate limit headers) but replace the body
+        let (mut parts, _) = response.into_parts();
+        parts.status = final_status;
+        parts.headers.remove(header::CONTENT_LENGTH);
+        if let Ok(content_type) = template.content_type.parse() {
+            parts.headers.insert(header::CONTENT_TYPE, content_type);
+        }
+
+        Response::from_parts(parts, Body::from(body))
+    }
+
+    /// Looks up a template by exact status code, then by status class, then "default"
+    fn find_template(templates: &HashMap<String, ErrorTemplate>, status: StatusCode) -> Option<&ErrorTemplate> {
+        if templates.is_empty() {
+            return None;
+        }
+
+        let code = status.as_u16();
+        templates.get(&code.to_string())
+            .or_else(|| templates.get(&format!("{}xx", code / 100)))
+            .or_else(|| templates.get("default"))
+    }
+
+    /// Substitutes the supported placeholders in a template body in one pass,
+    /// so placeholders inside substituted values are left as they are. Values
+    /// are escaped for HTML and JSON templates, since the message and request
+    /// ID can come from outside the gateway.
+    fn render(
+        template: &str,
+        content_type: &str,
+        status: StatusCode,
+        gateway_error: &GatewayError,
+        request_id: &str,
+        proxy_id: &str,
+    ) -> String {
+        let value = |name: &str| match name {
+            "status" => Some(status.as_str()),
+            "reason" => Some(status.canonical_reason().unwrap_or("")),
+            "message" => Some(gateway_error.message.as_str()),
+            "request_id" => Some(request_id),
+            "error_class" => Some(gateway_error.error_class),
+            "proxy_id" => Some(proxy_id),
+            _ => None,
+        };
+        let content_type = content_type.to_ascii_lowercase();
+
+        let mut body = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            body.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let placeholder = after.find("}}")
+                .and_then(|end| value(&after[..end]).map(|value| (end, value)));
+            match placeholder {
+                Some((end, value)) => {
+                    body.push_str(&escape(value, &content_type));
+                    rest = &after[end + 2..];
+                },
+                None => {
+                    body.push_str("{{");
+                    rest = after;
+                },
+            }
+        }
+        body.push_str(rest);
+        body
+    }
+}
+
+/// Escapes a template value for the template's content type: HTML entities
+/// for HTML and XML, the inside of a string literal for JSON
+fn escape(value: &str, content_type: &str) -> String {
+    if content_type.contains("json") {
+        let quoted = serde_json::to_string(value).unwrap_or_default();
+        quoted[1..quoted.len() - 1].to_string()
+    } else if content_type.contains("html") || content_type.contains("xml") {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&#39;"),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    } else {
+        value.to_string()
+    }
+}
+
+/// Whether a client's X-Request-Id can be reused as the request ID: 1 to 128
+/// letters, digits, dots, underscores and dashes. Anything else is replaced by
+/// a generated ID, so it never reaches headers, logs or error pages.
+pub fn is_valid_request_id(id: &str) -> bool {
+    (1..=MAX_REQUEST_ID_LEN).contains(&id.len())
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'_' | b'-'))
+}
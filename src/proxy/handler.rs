@@ -1,65 +1,189 @@
 use std::sync::Arc;
 use std::net::SocketAddr;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
 use anyhow::{Result, Context};
 use tracing::{info, warn, error, debug, trace};
 use hyper::{Body, Request, Response, StatusCode, Uri, header};
 use hyper::client::HttpConnector;
 use hyper_rustls::HttpsConnector;
 use http::uri::Scheme;
+use dashmap::DashMap;
+use thiserror::Error;
 
-use crate::config::data_model::{Configuration, Proxy, BackendProtocol};
+use crate::config::data_model::{Proxy, BackendProtocol, BackendKeepalive, HeaderCase};
+use crate::config::snapshot::SharedSnapshot;
+use crate::config::consumer_index::ConsumerIndex;
+use crate::config::data_model;
 use crate::proxy::router::Router;
+use crate::proxy::client_auth::ClientCertificate;
 use crate::dns::DnsCache; // Updated import from the dns module
+use crate::dns::resolver::{CachedResolver, HAPPY_EYEBALLS_DELAY_MS};
+use crate::discovery::ServiceDiscovery;
 use crate::plugins::PluginManager;
 use crate::proxy::websocket::handle_websocket;
+use crate::proxy::connector::{self, HandshakeTimer, MeteredConnector};
+use crate::proxy::egress::{EgressConnector, EgressRoute};
+use crate::proxy::backend_health;
+use crate::proxy::api_version;
+use crate::proxy::hop_by_hop;
+use crate::proxy::body;
+use crate::proxy::deadline::{self, RequestDeadline};
+use crate::metrics;
+use crate::proxy::error_pages::gateway_error_response;
 
-type HttpClient = hyper::Client<HttpsConnector<HttpConnector>>;
+type HttpClient = hyper::Client<HandshakeTimer<HttpsConnector<MeteredConnector<EgressConnector<HttpConnector<CachedResolver>>>>>>;
+
+/// Idle connections the backend client keeps open to each backend
+const POOL_MAX_IDLE_PER_HOST: usize = 32;
+
+/// TCP connect timeout of proxies that set none
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The settings of a proxy that belong to the backend client's connector and
+/// pool rather than to a request. Proxies with the same options share a client.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct ClientOptions {
+    /// TCP connect timeout, 0 for the default
+    connect_timeout_ms: u64,
+    keepalive: BackendKeepalive,
+    /// Speak HTTP/2 to plaintext backends too (h2c), as gRPC backends expect
+    http2_only: bool,
+    header_case: Option<HeaderCase>,
+    egress: EgressRoute,
+}
+
+impl ClientOptions {
+    fn for_proxy(proxy: &Proxy) -> Self {
+        Self {
+            connect_timeout_ms: proxy.backend_connect_timeout_ms,
+            keepalive: proxy.backend_keepalive.clone().unwrap_or_default(),
+            http2_only: proxy.backend_protocol == BackendProtocol::Grpc,
+            header_case: proxy.backend_header_case,
+            // Validation rejects egress proxies that do not parse
+            egress: EgressRoute::parse(proxy.egress_proxy.as_deref()).unwrap_or_default(),
+        }
+    }
+}
 
 /// The ProxyHandler is responsible for forwarding requests to the appropriate
 /// backend service and processing the response.
 pub struct ProxyHandler {
-    shared_config: Arc<RwLock<Configuration>>,
+    snapshot: SharedSnapshot,
+    router: Router,
     plugin_manager: Arc<PluginManager>,
     dns_cache: Arc<DnsCache>,
+    discovery: Arc<ServiceDiscovery>,
     http_client: HttpClient,
+    // Clients of the proxies whose client options are not the defaults
+    tuned_clients: DashMap<ClientOptions, HttpClient>,
 }
 
 impl ProxyHandler {
     pub fn new(
-        shared_config: Arc<RwLock<Configuration>>,
+        snapshot: SharedSnapshot,
         plugin_manager: Arc<PluginManager>,
         dns_cache: Arc<DnsCache>,
+        discovery: Arc<ServiceDiscovery>,
     ) -> Self {
+        let http_client = Self::build_http_client(&dns_cache, &ClientOptions::default());
+        
+        Self {
+            router: Router::new(Arc::clone(&snapshot)),
+            snapshot,
+            plugin_manager,
+            dns_cache,
+            discovery,
+            http_client,
+            tuned_clients: DashMap::new(),
+        }
+    }
+    
+    /// The configuration snapshot requests are currently handled with
+    pub fn snapshot(&self) -> &SharedSnapshot {
+        &self.snapshot
+    }
+    
+    /// Builds a hyper client with the given options
+    fn build_http_client(dns_cache: &Arc<DnsCache>, options: &ClientOptions) -> HttpClient {
+        let keepalive = &options.keepalive;
+        let connect_timeout = match options.connect_timeout_ms {
+            0 => DEFAULT_CONNECT_TIMEOUT,
+            ms => Duration::from_millis(ms),
+        };
+        
         // Create a custom DNS resolver that will use our cache
-        let mut http = hyper::client::HttpConnector::new();
-        http.set_nodelay(true);
+        let mut http = HttpConnector::new_with_resolver(CachedResolver::new(Arc::clone(dns_cache)));
+        http.set_nodelay(keepalive.tcp_nodelay);
+        http.set_keepalive(keepalive.tcp_keepalive_secs.map(Duration::from_secs));
         http.enforce_http(false); // Allow HTTPS and other schemes
-        http.set_connect_timeout(Some(Duration::from_secs(10)));
+        http.set_connect_timeout(Some(connect_timeout));
+        // Dual-stack backends: race IPv4 when IPv6 does not connect quickly
+        http.set_happy_eyeballs_timeout(Some(Duration::from_millis(HAPPY_EYEBALLS_DELAY_MS)));
         
-        // Create a HTTPS connector with our custom DNS and TLS config
+        // Create a HTTPS connector with our custom DNS and TLS config, tunnelling
+        // through the egress proxy of the options, if any
         let https = hyper_rustls::HttpsConnectorBuilder::new()
             .with_native_roots()
-            .https_only()
+            .https_or_http()
             .enable_http1()
             .enable_http2()
-            .wrap_connector(http);
+            .wrap_connector(MeteredConnector::new(EgressConnector::new(http, options.egress.clone())));
         
-        // Create a hyper client with the HTTPS connector
-        let http_client = hyper::Client::builder()
-            .pool_idle_timeout(Duration::from_secs(30))
-            .pool_max_idle_per_host(32)
-            .build(https);
+        // Create a hyper client with the HTTPS connector; an idle timeout of 0
+        // leaves idle connections open until the backend closes them
+        let idle_timeout = match keepalive.idle_timeout_ms {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        };
+        hyper::Client::builder()
+            .http2_only(options.http2_only)
+            .http1_title_case_headers(options.header_case == Some(HeaderCase::Title))
+            .http1_preserve_header_case(options.header_case == Some(HeaderCase::Preserve))
+            .pool_idle_timeout(idle_timeout)
+            .pool_max_idle_per_host(POOL_MAX_IDLE_PER_HOST)
+            .build(HandshakeTimer::new(https))
+    }
+    
+    /// Returns the client to use for a proxy, honouring its backend_connect_timeout_ms,
+    /// backend_keepalive, backend_header_case and egress_proxy, and speaking HTTP/2
+    /// to gRPC backends. Other proxies use the default client.
+    fn client_for(&self, proxy: &Proxy) -> HttpClient {
+        let options = ClientOptions::for_proxy(proxy);
+        if options == ClientOptions::default() {
+            return self.http_client.clone();
+        }
         
-        Self {
-            shared_config,
-            plugin_manager,
-            dns_cache,
-            http_client,
+        self.tuned_clients
+            .entry(options.clone())
+            .or_insert_with(|| Self::build_http_client(&self.dns_cache, &options))
+            .clone()
+    }
+    
+    /// The time allowed for writing the request to the backend and receiving the
+    /// response headers. Returns None when both write and read timeouts are disabled (0).
+    fn backend_response_timeout(proxy: &Proxy) -> Option<Duration> {
+        let total_ms = proxy.backend_write_timeout_ms.saturating_add(proxy.backend_read_timeout_ms);
+        if total_ms == 0 {
+            None
+        } else {
+            Some(Duration::from_millis(total_ms))
         }
     }
     
+    /// Builds the response for a failed backend exchange and records it in metrics
+    fn upstream_error_response(&self, proxy: &Proxy, kind: UpstreamError) -> Response<Body> {
+        metrics::track_upstream_error(&proxy.id, kind.as_str());
+        
+        // Timeouts are additionally counted with the other timeout reasons
+        match kind {
+            UpstreamError::ConnectTimeout => metrics::track_timeout(&proxy.id, "backend_connect"),
+            UpstreamError::ResponseTimeout => metrics::track_timeout(&proxy.id, "backend_read"),
+            _ => {}
+        }
+        
+        gateway_error_response(kind.status(), kind.as_str(), &kind.to_string())
+    }
+    
     /// Handles a request by forwarding it to the appropriate backend service
     /// and processing the response through the plugin pipeline.
     pub async fn handle(
@@ -70,12 +194,35 @@ impl ProxyHandler {
     ) -> Result<Response<Body>> {
         let start_time = Instant::now();
         
+        // Serve the API version the request asks for with the version's backend
+        let version = match &proxy.api_versioning {
+            Some(versioning) => match api_version::select(versioning, &req, &proxy.listen_path) {
+                Ok(version) => version.cloned(),
+                Err(requested) => {
+                    debug!("Proxy {} has no API version '{}'", proxy.id, requested);
+                    return Ok(gateway_error_response(
+                        StatusCode::BAD_REQUEST,
+                        "unsupported_api_version",
+                        &format!("API version '{}' is not supported", requested),
+                    ));
+                }
+            },
+            None => None,
+        };
+        let proxy = match &version {
+            Some(version) => api_version::apply(&proxy, version),
+            None => proxy,
+        };
+        
         // Create a context for this request
         let mut context = RequestContext {
             proxy: proxy.clone(),
             client_addr,
             consumer: None,
+            consumers: self.snapshot.load().consumer_index(&proxy.workspace),
+            client_certificate: req.extensions().get::<Arc<ClientCertificate>>().cloned(),
             latency: Default::default(),
+            request_bytes: body::declared_request_size(&req).unwrap_or(0),
         };
         
         // Check for WebSocket upgrade request
@@ -83,26 +230,48 @@ impl ProxyHandler {
             debug!("Handling WebSocket upgrade request for path: {}", req.uri().path());
             return handle_websocket(req, context, req.uri().clone()).await;
         }
-
+        
+        let mut response = self.run_pipeline(req, &proxy, &mut context, start_time).await?;
+        
+        // Lets the listener attribute the response to the authenticated consumer
+        if let Some(consumer) = &context.consumer {
+            response.extensions_mut().insert(consumer.clone());
+        }
+        response.extensions_mut().insert(context.latency);
+        if let Some(version) = &version {
+            api_version::add_deprecation_headers(version, &mut response);
+        }
+        
+        Ok(response)
+    }
+    
+    /// Runs the plugin phases around the backend request
+    async fn run_pipeline(
+        &self,
+        req: Request<Body>,
+        proxy: &Proxy,
+        context: &mut RequestContext,
+        start_time: Instant,
+    ) -> Result<Response<Body>> {
         // Run pre-proxy plugins (authentication, access control, etc.)
-        let (modified_req, should_continue) = match self.plugin_manager.run_pre_proxy_plugins(req, &mut context).await {
+        let (mut modified_req, should_continue) = match self.plugin_manager.run_pre_proxy_plugins(req, context).await {
             Ok((modified_req, true)) => (modified_req, true),
-            Ok((modified_req, false)) => {
-                // Plugin indicated that we should not continue with the proxy
-                let rejection_response = Response::builder()
-                    .status(StatusCode::FORBIDDEN)
-                    .body(Body::from("Request rejected by plugin"))
-                    .unwrap();
+            Ok((mut modified_req, false)) => {
+                // Plugin indicated that we should not continue with the proxy. Plugins
+                // may leave their own response (e.g. a 429) in the request extensions.
+                let rejection_response = modified_req.extensions_mut()
+                    .remove::<Response<Body>>()
+                    .unwrap_or_else(|| gateway_error_response(StatusCode::FORBIDDEN, "plugin_rejected", "Request rejected by plugin"));
                 
                 // Run post-proxy plugins with the rejection response
-                let response = self.plugin_manager.run_post_proxy_plugins(rejection_response, &mut context).await
+                let response = self.plugin_manager.run_post_proxy_plugins(rejection_response, context).await
                     .unwrap_or_else(|e| {
                         error!("Error in post-proxy plugins: {}", e);
                         rejection_response
                     });
                 
                 // Always run logging phase
-                if let Err(e) = self.plugin_manager.run_log_plugins(&modified_req, &response, &context).await {
+                if let Err(e) = self.plugin_manager.run_log_plugins(&modified_req, &response, context).await {
                     error!("Error in logging plugins: {}", e);
                 }
                 
@@ -112,13 +281,10 @@ impl ProxyHandler {
                 // Plugin error
                 error!("Error in pre-proxy plugins: {}", e);
                 
-                let error_response = Response::builder()
-                    .status(StatusCode::INTERNAL_SERVER_ERROR)
-                    .body(Body::from("Internal server error in request processing"))
-                    .unwrap();
+                let error_response = gateway_error_response(StatusCode::INTERNAL_SERVER_ERROR, "plugin_error", "Internal server error in request processing");
                 
                 // Try to run logging phase even for errors
-                if let Err(log_err) = self.plugin_manager.run_log_plugins(&req, &error_response, &context).await {
+                if let Err(log_err) = self.plugin_manager.run_log_plugins(&req, &error_response, context).await {
                     error!("Error in logging plugins: {}", log_err);
                 }
                 
@@ -128,32 +294,49 @@ impl ProxyHandler {
         
         if !should_continue {
             // This shouldn't happen based on the match above, but just in case
-            let error_response = Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(Body::from("Internal server error: plugin chain inconsistency"))
-                .unwrap();
+            let error_response = gateway_error_response(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", "Internal server error: plugin chain inconsistency");
             
             // Run logging phase
-            if let Err(e) = self.plugin_manager.run_log_plugins(&modified_req, &error_response, &context).await {
+            if let Err(e) = self.plugin_manager.run_log_plugins(&modified_req, &error_response, context).await {
                 error!("Error in logging plugins: {}", e);
             }
             
             return Ok(error_response);
         }
         
-        // Resolve the backend host to an IP address
-        let backend_ip = match self.resolve_backend_host(&proxy).await {
-            Ok(ip) => ip,
+        // Pick the backend instance: the next one listed by the service registry
+        // for proxies with backend_discovery, otherwise backend_host:backend_port
+        let (backend_host, backend_port) = match &proxy.backend_discovery {
+            Some(spec) => match self.discovery.next_target(spec) {
+                Some(target) => (target.host, target.port),
+                None => {
+                    warn!("No backend instances are registered for proxy {}", proxy.id);
+                    
+                    let response = self.upstream_error_response(&proxy, UpstreamError::NoTargets);
+                    
+                    // Run logging phase
+                    if let Err(log_err) = self.plugin_manager.run_log_plugins(&modified_req, &response, context).await {
+                        error!("Error in logging plugins: {}", log_err);
+                    }
+                    
+                    return Ok(response);
+                }
+            },
+            None => (proxy.backend_host.clone(), proxy.backend_port),
+        };
+        
+        // Resolve the backend host, unless it is an address already
+        let backend_addr = match self.resolve_backend_host(&proxy, &backend_host).await {
+            Ok(addr) => addr,
             Err(e) => {
-                error!("Failed to resolve backend host {}: {}", proxy.backend_host, e);
+                error!("Failed to resolve backend host {}: {}", backend_host, e);
                 
-                let response = Response::builder()
-                    .status(StatusCode::BAD_GATEWAY)
-                    .body(Body::from("Failed to resolve backend host"))
-                    .unwrap();
+                backend_health::record_failure(&proxy.id, &format!("{}:{}", backend_host, backend_port), UpstreamError::DnsFailure.as_str());
+                
+                let response = self.upstream_error_response(&proxy, UpstreamError::DnsFailure);
                 
                 // Run logging phase
-                if let Err(log_err) = self.plugin_manager.run_log_plugins(&modified_req, &response, &context).await {
+                if let Err(log_err) = self.plugin_manager.run_log_plugins(&modified_req, &response, context).await {
                     error!("Error in logging plugins: {}", log_err);
                 }
                 
@@ -161,23 +344,17 @@ impl ProxyHandler {
             }
         };
         
-        // Create a router instance for path construction
-        let router = Router::new(Arc::clone(&self.shared_config));
-        
         // Build the backend URI
-        let backend_path = router.construct_backend_path(&modified_req, &proxy);
-        let backend_uri = match self.build_backend_uri(&proxy, &backend_ip, &backend_path, &modified_req) {
+        let backend_path = self.router.construct_backend_path(&modified_req, &proxy);
+        let backend_uri = match self.build_backend_uri(&proxy, &backend_addr, backend_port, &backend_path, &modified_req) {
             Ok(uri) => uri,
             Err(e) => {
                 error!("Failed to build backend URI: {}", e);
                 
-                let response = Response::builder()
-                    .status(StatusCode::INTERNAL_SERVER_ERROR)
-                    .body(Body::from("Failed to build backend URI"))
-                    .unwrap();
+                let response = gateway_error_response(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", "Failed to build backend URI");
                 
                 // Run logging phase
-                if let Err(log_err) = self.plugin_manager.run_log_plugins(&modified_req, &response, &context).await {
+                if let Err(log_err) = self.plugin_manager.run_log_plugins(&modified_req, &response, context).await {
                     error!("Error in logging plugins: {}", log_err);
                 }
                 
@@ -185,19 +362,36 @@ impl ProxyHandler {
             }
         };
         
-        // Prepare the outgoing request to the backend
-        let (backend_req, outgoing_body) = match self.prepare_backend_request(modified_req.clone(), &proxy, backend_uri) {
-            Ok(result) => result,
+        // The deadline of the backend request, counting the time the request
+        // already spent in the gateway
+        let grpc = proxy.backend_protocol == BackendProtocol::Grpc;
+        let backend_deadline = deadline::deadline_for(
+            Self::backend_response_timeout(&proxy),
+            start_time,
+            modified_req.headers(),
+            modified_req.extensions().get::<RequestDeadline>().copied(),
+            grpc,
+        );
+        
+        // Prepare the outgoing request to the backend. The body goes to the
+        // backend, trailers included; the log plugins see the request without it.
+        let request_body = std::mem::replace(modified_req.body_mut(), Body::empty());
+        let mut backend_req = match self.prepare_backend_request(&modified_req, &proxy, (backend_host.as_str(), backend_port), backend_uri, request_body) {
+            Ok(mut backend_req) => {
+                // The casing of the client's header names travels in the request
+                // extensions, which cannot be copied one by one
+                if proxy.backend_header_case == Some(HeaderCase::Preserve) {
+                    *backend_req.extensions_mut() = std::mem::take(modified_req.extensions_mut());
+                }
+                backend_req
+            },
             Err(e) => {
                 error!("Failed to prepare backend request: {}", e);
                 
-                let response = Response::builder()
-                    .status(StatusCode::INTERNAL_SERVER_ERROR)
-                    .body(Body::from("Failed to prepare backend request"))
-                    .unwrap();
+                let response = gateway_error_response(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", "Failed to prepare backend request");
                 
                 // Run logging phase
-                if let Err(log_err) = self.plugin_manager.run_log_plugins(&modified_req, &response, &context).await {
+                if let Err(log_err) = self.plugin_manager.run_log_plugins(&modified_req, &response, context).await {
                     error!("Error in logging plugins: {}", log_err);
                 }
                 
@@ -205,31 +399,83 @@ impl ProxyHandler {
             }
         };
         
+        // Tell the backend how much time is left
+        if let Some(backend_deadline) = backend_deadline {
+            deadline::propagate(backend_req.headers_mut(), backend_deadline, grpc);
+        }
+        
         // Record time before making backend request
         let backend_start = Instant::now();
         
-        // Send the request to the backend
-        let resp = match self.http_client.request(backend_req).await {
-            Ok(resp) => {
+        // Send the request to the backend, bounded by the proxy's write + read timeouts
+        let client = self.client_for(&proxy);
+        let connection = hyper::client::connect::capture_connection(&mut backend_req);
+        let backend_request_bytes = body::declared_request_size(&backend_req).unwrap_or(0);
+        let in_flight = metrics::start_upstream_request(&backend_host, POOL_MAX_IDLE_PER_HOST);
+        let outcome = match Self::backend_response_timeout(&proxy) {
+            Some(limit) => tokio::time::timeout(limit, client.request(backend_req)).await.ok(),
+            None => Some(client.request(backend_req).await),
+        };
+        drop(in_flight);
+        
+        let result = match outcome {
+            Some(Ok(resp)) => Ok(resp),
+            Some(Err(e)) => {
+                let kind = UpstreamError::classify(&e);
+                warn!(
+                    "Error sending request to backend {}:{} ({}): {}",
+                    backend_host,
+                    backend_port,
+                    kind.as_str(),
+                    e
+                );
+                Err(kind)
+            },
+            None => {
+                warn!(
+                    "Backend {}:{} did not respond within {}ms",
+                    backend_host,
+                    backend_port,
+                    proxy.backend_write_timeout_ms + proxy.backend_read_timeout_ms
+                );
+                Err(UpstreamError::ResponseTimeout)
+            }
+        };
+        
+        // Passive health of the backend, reported to the Control Plane
+        let backend_address = format!("{}:{}", backend_host, backend_port);
+        match &result {
+            Ok(_) => backend_health::record_success(&proxy.id, &backend_address),
+            Err(kind) => backend_health::record_failure(&proxy.id, &backend_address, kind.as_str()),
+        }
+        
+        let resp = match result {
+            Ok(mut resp) => {
+                hop_by_hop::strip_response(resp.headers_mut());
+                
+                // Retire the connection once it carried the proxy's max requests
+                if let Some(max_requests) = proxy.backend_keepalive.as_ref().and_then(|k| k.max_requests_per_connection) {
+                    connector::limit_connection_uses(&resp, &connection, max_requests);
+                }
+                
                 // Record backend response time
                 context.latency.backend_ttfb = backend_start.elapsed().as_millis() as u64;
                 context.latency.backend_total = backend_start.elapsed().as_millis() as u64;
+                
+                // The listener counts the bytes of the body as the backend's
+                metrics::track_proxy_bytes(&proxy.id, "backend_sent", backend_request_bytes);
+                resp.extensions_mut().insert(BackendResponse);
                 resp
             },
-            Err(e) => {
-                error!("Error sending request to backend: {}", e);
-                
-                let response = Response::builder()
-                    .status(StatusCode::BAD_GATEWAY)
-                    .body(Body::from(format!("Error sending request to backend: {}", e)))
-                    .unwrap();
+            Err(kind) => {
+                let response = self.upstream_error_response(&proxy, kind);
                 
                 // Record backend failure
                 context.latency.backend_ttfb = 0;
                 context.latency.backend_total = backend_start.elapsed().as_millis() as u64;
                 
                 // Run logging phase
-                if let Err(log_err) = self.plugin_manager.run_log_plugins(&modified_req, &response, &context).await {
+                if let Err(log_err) = self.plugin_manager.run_log_plugins(&modified_req, &response, context).await {
                     error!("Error in logging plugins: {}", log_err);
                 }
                 
@@ -238,16 +484,13 @@ impl ProxyHandler {
         };
         
         // Process the backend response through post-proxy plugins
-        let processed_resp = match self.plugin_manager.run_post_proxy_plugins(resp, &mut context).await {
+        let processed_resp = match self.plugin_manager.run_post_proxy_plugins(resp, context).await {
             Ok(resp) => resp,
             Err(e) => {
                 error!("Error in post-proxy plugins: {}", e);
                 
                 // If post-proxy plugins fail, return a server error
-                let error_response = Response::builder()
-                    .status(StatusCode::INTERNAL_SERVER_ERROR)
-                    .body(Body::from("Error processing backend response"))
-                    .unwrap();
+                let error_response = gateway_error_response(StatusCode::INTERNAL_SERVER_ERROR, "plugin_error", "Error processing backend response");
                 
                 error_response
             }
@@ -258,10 +501,10 @@ impl ProxyHandler {
         context.latency.gateway_processing = context.latency.total - context.latency.backend_total;
         
         // Log request summary
-        self.log_request_summary(&context, &modified_req, &processed_resp);
+        self.log_request_summary(context, &modified_req, &processed_resp);
         
         // Run logging phase plugins
-        if let Err(e) = self.plugin_manager.run_log_plugins(&modified_req, &processed_resp, &context).await {
+        if let Err(e) = self.plugin_manager.run_log_plugins(&modified_req, &processed_resp, context).await {
             error!("Error in logging plugins: {}", e);
         }
         
@@ -269,98 +512,91 @@ impl ProxyHandler {
         Ok(processed_resp)
     }
     
-    /// Resolves a backend hostname to an IP address using the DNS cache
-    async fn resolve_backend_host(&self, proxy: &Proxy) -> Result<String> {
+    /// Gives the host to connect to: an IP address as is, the proxy's DNS
+    /// override, or a hostname once the DNS cache holds its addresses. The
+    /// connector then tries every IPv6 and IPv4 address of the hostname.
+    async fn resolve_backend_host(&self, proxy: &Proxy, host: &str) -> Result<String> {
+        // Registries often list instances by IP address already
+        if host.parse::<std::net::IpAddr>().is_ok() {
+            return Ok(host.to_string());
+        }
+        
         // Check if there's a DNS override for this proxy
         if let Some(ref ip) = proxy.dns_override {
-            return Ok(ip.clone());
+            if proxy.backend_discovery.is_none() {
+                return Ok(ip.clone());
+            }
         }
         
-        // Otherwise resolve the hostname using the DNS cache
+        // Otherwise resolve the hostname using the DNS cache, with the proxy's
+        // own DNS servers if it has any
+        self.dns_cache.use_resolvers_for(host, &proxy.dns_resolvers)?;
         let ttl = proxy.dns_cache_ttl_seconds
             .map(Duration::from_secs)
             .unwrap_or_else(|| self.dns_cache.default_ttl());
         
-        self.dns_cache.lookup_with_ttl(&proxy.backend_host, ttl).await // Using lookup_with_ttl instead of resolve_with_ttl
+        // Resolved here rather than by the connector, so a failure is reported as a
+        // DNS failure and the proxy's TTL applies
+        self.dns_cache.lookup_all_with_ttl(host, ttl).await?;
+        Ok(host.to_string())
     }
     
     /// Builds the backend URI for the request
-    fn build_backend_uri(&self, proxy: &Proxy, backend_ip: &str, backend_path: &str, original_req: &Request<Body>) -> Result<Uri> {
-        // Determine the scheme based on the backend protocol
-        let scheme = match proxy.backend_protocol {
-            BackendProtocol::Http => Scheme::HTTP,
-            BackendProtocol::Https => Scheme::HTTPS,
-            BackendProtocol::Ws => Scheme::HTTP,
-            BackendProtocol::Wss => Scheme::HTTPS,
-            BackendProtocol::Grpc => Scheme::HTTP,
-        };
-        
+    fn build_backend_uri(&self, proxy: &Proxy, backend_addr: &str, backend_port: u16, backend_path: &str, original_req: &Request<Body>) -> Result<Uri> {
         // Preserve the query string from the original request
-        let query = original_req.uri().query().map(|q| format!("?{}", q)).unwrap_or_default();
-        
-        // Construct the backend URI
-        let uri_str = format!(
-            "{}://{}:{}{}{}",
-            scheme,
-            backend_ip,
-            proxy.backend_port,
-            backend_path,
-            query
-        );
-        
-        uri_str.parse::<Uri>().context("Failed to parse backend URI")
+        backend_uri(proxy, backend_addr, backend_port, backend_path, original_req.uri().query())
     }
     
-    /// Prepares the outgoing request to the backend
+    /// Prepares the outgoing request to the backend picked for it
     fn prepare_backend_request(
         &self,
-        original_req: Request<Body>,
+        original_req: &Request<Body>,
         proxy: &Proxy,
+        (backend_host, backend_port): (&str, u16),
         backend_uri: Uri,
-    ) -> Result<(Request<Body>, Body)> {
-        let (parts, body) = original_req.into_parts();
-        
+        body: Body,
+    ) -> Result<Request<Body>> {
         // Create a new request with the backend URI
         let mut req_builder = Request::builder()
             .uri(backend_uri)
-            .method(parts.method);
+            .method(original_req.method().clone());
         
-        // Copy all headers from the original request
-        for (name, value) in parts.headers.iter() {
-            // Skip the Host header - it will be set based on the backend URI
-            if name.as_str().to_lowercase() != "host" {
-                req_builder = req_builder.header(name, value);
-            }
+        // Copy the headers of the original request, except the hop-by-hop ones
+        let mut headers = original_req.headers().clone();
+        hop_by_hop::strip_request(&mut headers);
+        // Host is set based on the backend below. Expect is answered by the
+        // gateway, which sends 100 Continue to the client as it reads the body,
+        // before the backend is involved.
+        headers.remove(header::HOST);
+        headers.remove(header::EXPECT);
+        if let Some(backend_headers) = req_builder.headers_mut() {
+            backend_headers.extend(headers);
         }
         
-        // Set Host header to the backend host
-        let host = format!("{}:{}", 
-            proxy.backend_host,
-            proxy.backend_port
-        );
-        req_builder = req_builder.header("Host", host);
+        // Set the Host header; HTTP/2 clients send the host as the URI authority
+        let client_host = original_req.headers().get(header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .or_else(|| original_req.uri().authority().map(|authority| authority.as_str()));
+        req_builder = req_builder.header("Host", upstream_host_header(proxy, backend_host, backend_port, client_host));
         
         // Set X-Forwarded headers
-        let forwarded_for = match parts.headers.get("X-Forwarded-For") {
+        let forwarded_for = match original_req.headers().get("X-Forwarded-For") {
             Some(forwarded_for) => {
                 let mut forwarded = forwarded_for.to_str()?.to_string();
                 forwarded.push_str(", ");
-                forwarded.push_str(&parts.extensions.get::<SocketAddr>().map(|addr| addr.ip().to_string()).unwrap_or_else(|| "unknown".to_string()));
+                forwarded.push_str(&original_req.extensions().get::<SocketAddr>().map(|addr| addr.ip().to_string()).unwrap_or_else(|| "unknown".to_string()));
                 forwarded
             },
-            None => parts.extensions.get::<SocketAddr>().map(|addr| addr.ip().to_string()).unwrap_or_else(|| "unknown".to_string()),
+            None => original_req.extensions().get::<SocketAddr>().map(|addr| addr.ip().to_string()).unwrap_or_else(|| "unknown".to_string()),
         };
         
         req_builder = req_builder.header("X-Forwarded-For", forwarded_for);
-        req_builder = req_builder.header("X-Forwarded-Proto", parts.uri.scheme_str().unwrap_or("http"));
-        req_builder = req_builder.header("X-Forwarded-Host", parts.uri.host().unwrap_or("unknown"));
+        req_builder = req_builder.header("X-Forwarded-Proto", original_req.uri().scheme_str().unwrap_or("http"));
+        req_builder = req_builder.header("X-Forwarded-Host", original_req.uri().host().unwrap_or("unknown"));
         
-        // Create the final request with an empty body for now
-        // We'll return the original body separately
-        let backend_req = req_builder
-            .body(Body::empty())?;
+        let backend_req = req_builder.body(body)?;
         
-        Ok((backend_req, body))
+        Ok(backend_req)
     }
     
     /// Processes the backend response before returning it to the client
@@ -404,6 +640,91 @@ impl ProxyHandler {
     }
 }
 
+/// Classification of failures while talking to a backend, used to pick the
+/// status code, the X-Gateway-Error header value and the metrics label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum UpstreamError {
+    #[error("Failed to resolve backend host")]
+    DnsFailure,
+    #[error("No backend instances are registered")]
+    NoTargets,
+    #[error("Backend refused the connection")]
+    ConnectRefused,
+    #[error("Timed out connecting to backend")]
+    ConnectTimeout,
+    #[error("TLS handshake with backend failed")]
+    TlsFailure,
+    #[error("Backend response timed out")]
+    ResponseTimeout,
+    #[error("Backend closed the connection or sent an invalid response")]
+    BodyError,
+    #[error("Error sending request to backend")]
+    Other,
+}
+
+impl UpstreamError {
+    /// Status code returned to the client for this failure
+    pub fn status(&self) -> StatusCode {
+        match self {
+            UpstreamError::ConnectRefused | UpstreamError::NoTargets => StatusCode::SERVICE_UNAVAILABLE,
+            UpstreamError::ConnectTimeout | UpstreamError::ResponseTimeout => StatusCode::GATEWAY_TIMEOUT,
+            UpstreamError::DnsFailure
+            | UpstreamError::TlsFailure
+            | UpstreamError::BodyError
+            | UpstreamError::Other => StatusCode::BAD_GATEWAY,
+        }
+    }
+    
+    /// Stable identifier used for the X-Gateway-Error header and metrics labels
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpstreamError::DnsFailure => "dns_failure",
+            UpstreamError::NoTargets => "no_backend_targets",
+            UpstreamError::ConnectRefused => "connect_refused",
+            UpstreamError::ConnectTimeout => "connect_timeout",
+            UpstreamError::TlsFailure => "tls_failure",
+            UpstreamError::ResponseTimeout => "upstream_timeout",
+            UpstreamError::BodyError => "upstream_body_error",
+            UpstreamError::Other => "upstream_error",
+        }
+    }
+    
+    /// Classifies a hyper client error by inspecting its kind and source chain
+    pub fn classify(err: &hyper::Error) -> Self {
+        if err.is_connect() {
+            let mut source = std::error::Error::source(err);
+            while let Some(cause) = source {
+                if cause.downcast_ref::<rustls::Error>().is_some() {
+                    return UpstreamError::TlsFailure;
+                }
+                if let Some(io_err) = cause.downcast_ref::<std::io::Error>() {
+                    match io_err.kind() {
+                        std::io::ErrorKind::ConnectionRefused => return UpstreamError::ConnectRefused,
+                        std::io::ErrorKind::TimedOut => return UpstreamError::ConnectTimeout,
+                        _ => {}
+                    }
+                    // hyper-rustls wraps handshake failures in an io::Error
+                    if io_err.get_ref().map_or(false, |inner| inner.is::<rustls::Error>()) {
+                        return UpstreamError::TlsFailure;
+                    }
+                }
+                source = cause.source();
+            }
+            return UpstreamError::Other;
+        }
+        
+        if err.is_timeout() {
+            return UpstreamError::ResponseTimeout;
+        }
+        
+        if err.is_incomplete_message() || err.is_body_write_aborted() || err.is_parse() || err.is_closed() {
+            return UpstreamError::BodyError;
+        }
+        
+        UpstreamError::Other
+    }
+}
+
 /// A struct to represent a consumer context for a request
 #[derive(Debug, Clone)]
 pub struct Consumer {
@@ -412,8 +733,23 @@ pub struct Consumer {
     pub custom_id: Option<String>,
 }
 
+impl From<&data_model::Consumer> for Consumer {
+    fn from(consumer: &data_model::Consumer) -> Self {
+        Self {
+            id: consumer.id.clone(),
+            username: consumer.username.clone(),
+            custom_id: consumer.custom_id.clone(),
+        }
+    }
+}
+
+/// Marks a response that came from the backend, rather than from the gateway
+/// or a plugin
+#[derive(Debug, Clone, Copy)]
+pub struct BackendResponse;
+
 /// A struct to track latency metrics for a request
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct LatencyMetrics {
     /// Total request processing time
     pub total: u64,
@@ -425,6 +761,58 @@ pub struct LatencyMetrics {
     pub backend_total: u64,
 }
 
+/// The URI of a backend request: the backend host or address, the path built
+/// from the proxy's listen and backend paths, and the client's query string
+pub fn backend_uri(proxy: &Proxy, backend_host: &str, backend_port: u16, backend_path: &str, query: Option<&str>) -> Result<Uri> {
+    // Determine the scheme based on the backend protocol
+    let scheme = match proxy.backend_protocol {
+        BackendProtocol::Http => Scheme::HTTP,
+        BackendProtocol::Https => Scheme::HTTPS,
+        BackendProtocol::Ws => Scheme::HTTP,
+        BackendProtocol::Wss => Scheme::HTTPS,
+        BackendProtocol::Grpc => Scheme::HTTP,
+    };
+    
+    let query = query.map(|q| format!("?{}", q)).unwrap_or_default();
+    
+    // IPv6 addresses need brackets in a URI authority
+    let backend_host = if backend_host.contains(':') {
+        format!("[{}]", backend_host)
+    } else {
+        backend_host.to_string()
+    };
+    
+    // Construct the backend URI
+    let uri_str = format!(
+        "{}://{}:{}{}{}",
+        scheme,
+        backend_host,
+        backend_port,
+        backend_path,
+        query
+    );
+    
+    uri_str.parse::<Uri>().context("Failed to parse backend URI")
+}
+
+/// The Host header sent to a proxy's backend: its upstream_host with the
+/// placeholders filled in, the client's Host with preserve_host_header, or else
+/// the address of the backend the request goes to, which backend discovery may
+/// have picked instead of backend_host
+pub fn upstream_host_header(proxy: &Proxy, backend_host: &str, backend_port: u16, client_host: Option<&str>) -> String {
+    if let Some(template) = &proxy.upstream_host {
+        return template
+            .replace("{backend_host}", backend_host)
+            .replace("{backend_port}", &backend_port.to_string());
+    }
+    match client_host {
+        Some(host) if proxy.preserve_host_header => host.to_string(),
+        // IPv6 addresses need brackets, as in a URI authority
+        _ if backend_host.contains(':') => format!("[{}]:{}", backend_host, backend_port),
+        _ => format!("{}:{}", backend_host, backend_port),
+    }
+}
+
 /// A context object for a single request through the gateway
 pub struct RequestContext {
     /// The proxy configuration that matched this request
@@ -433,6 +821,12 @@ pub struct RequestContext {
     pub client_addr: SocketAddr,
     /// The authenticated consumer (if any)
     pub consumer: Option<Consumer>,
+    /// Consumer lookup indexes from the configuration snapshot the request started with
+    pub consumers: Arc<ConsumerIndex>,
+    /// The verified client certificate, on listeners with client certificate verification
+    pub client_certificate: Option<Arc<ClientCertificate>>,
     /// Latency metrics for the request
     pub latency: LatencyMetrics,
+    /// Body bytes received from the client
+    pub request_bytes: u64,
 }
@@ -0,0 +1,142 @@
+//! Load shedding by request priority
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use arc_swap::ArcSwapOption;
+use hyper::{Body, Request};
+
+use crate::config::data_model::Proxy;
+use crate::config::env_config::EnvConfig;
+use crate::metrics;
+
+static SETTINGS: ArcSwapOption<LoadSheddingSettings> = ArcSwapOption::const_empty();
+
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+/// p99 latency of the last minute in milliseconds, refreshed every second
+static P99_MS: AtomicU64 = AtomicU64::new(0);
+
+/// Proxy tag giving its requests a priority, e.g. `priority:low`
+pub const PRIORITY_TAG_PREFIX: &str = "priority:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+impl Priority {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "low" => Some(Priority::Low),
+            "normal" => Some(Priority::Normal),
+            "high" => Some(Priority::High),
+            _ => None,
+        }
+    }
+
+    /// Label used for metrics
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+        }
+    }
+}
+
+/// When requests are shed; a limit of 0 is disabled
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadSheddingSettings {
+    /// Requests in flight from which low priority requests are shed
+    pub max_in_flight: usize,
+    /// Requests in flight from which normal priority requests are shed too
+    pub critical_in_flight: usize,
+    /// p99 latency of the last minute from which low priority requests are shed
+    pub p99_ms: u64,
+    /// Header whose value (low, normal or high) sets the priority of a request
+    pub priority_header: Option<String>,
+}
+
+impl LoadSheddingSettings {
+    pub fn from_env_config(env_config: &EnvConfig) -> Self {
+        Self {
+            max_in_flight: env_config.load_shed_max_in_flight,
+            critical_in_flight: env_config.load_shed_critical_in_flight,
+            p99_ms: env_config.load_shed_p99_ms,
+            priority_header: env_config.load_shed_priority_header.clone(),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.max_in_flight > 0 || self.critical_in_flight > 0 || self.p99_ms > 0
+    }
+}
+
+/// Applies the settings and, with a latency threshold, starts following the p99
+pub fn start(env_config: &EnvConfig) {
+    let settings = LoadSheddingSettings::from_env_config(env_config);
+
+    if settings.p99_ms > 0 {
+        tokio::spawn(async {
+            let mut timer = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                timer.tick().await;
+                let (overall, _) = metrics::latency::last_minute();
+                P99_MS.store(overall.p99 as u64, Ordering::Relaxed);
+            }
+        });
+    }
+    SETTINGS.store(Some(Arc::new(settings)));
+}
+
+/// The priority of a request: the priority header's, else its proxy's tag's,
+/// else normal
+pub fn priority(settings: &LoadSheddingSettings, proxy: &Proxy, req: &Request<Body>) -> Priority {
+    let from_header = settings.priority_header.as_deref()
+        .and_then(|name| req.headers().get(name))
+        .and_then(|value| value.to_str().ok())
+        .and_then(Priority::parse);
+    from_header
+        .or_else(|| proxy.tags.iter()
+            .filter_map(|tag| tag.strip_prefix(PRIORITY_TAG_PREFIX))
+            .find_map(Priority::parse))
+        .unwrap_or(Priority::Normal)
+}
+
+/// Whether a request of the priority is shed at the current load
+pub fn sheds(settings: &LoadSheddingSettings, priority: Priority, in_flight: usize, p99_ms: u64) -> bool {
+    let critical = settings.critical_in_flight > 0 && in_flight >= settings.critical_in_flight;
+    let overloaded = critical
+        || (settings.max_in_flight > 0 && in_flight >= settings.max_in_flight)
+        || (settings.p99_ms > 0 && p99_ms >= settings.p99_ms);
+    match priority {
+        Priority::Low => overloaded,
+        Priority::Normal => critical,
+        Priority::High => false,
+    }
+}
+
+/// A request counted in flight until it is dropped
+pub struct InFlight(());
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Counts the request in flight, or returns its priority when it is shed
+pub fn admit(proxy: &Proxy, req: &Request<Body>) -> Result<InFlight, Priority> {
+    if let Some(settings) = SETTINGS.load().as_ref().filter(|settings| settings.enabled()) {
+        let priority = priority(settings, proxy, req);
+        if sheds(settings, priority, IN_FLIGHT.load(Ordering::Relaxed), P99_MS.load(Ordering::Relaxed)) {
+            metrics::track_load_shed(&proxy.id, priority.as_str());
+            return Err(priority);
+        }
+    }
+    IN_FLIGHT.fetch_add(1, Ordering::Relaxed);
+    Ok(InFlight(()))
+}
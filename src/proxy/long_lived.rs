@@ -0,0 +1,200 @@
+//! Long-lived connections across configuration updates
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use arc_swap::ArcSwapOption;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use hyper::body::HttpBody;
+use hyper::Body;
+use lazy_static::lazy_static;
+use tokio::sync::Notify;
+use tracing::{debug, info};
+
+use crate::config::data_model::{Configuration, Proxy};
+use crate::config::env_config::EnvConfig;
+use crate::metrics;
+
+static SETTINGS: ArcSwapOption<LongLivedSettings> = ArcSwapOption::const_empty();
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+lazy_static! {
+    static ref CONNECTIONS: DashMap<u64, Tracked> = DashMap::new();
+}
+
+/// What happens to the long-lived connections of a changed or deleted proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdatePolicy {
+    Drain,
+    Keep,
+}
+
+impl UpdatePolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "drain" => Some(UpdatePolicy::Drain),
+            "keep" => Some(UpdatePolicy::Keep),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LongLivedSettings {
+    pub policy: UpdatePolicy,
+    /// How long drained connections stay open after the update
+    pub drain_timeout: Duration,
+}
+
+impl LongLivedSettings {
+    pub fn from_env_config(env_config: &EnvConfig) -> Self {
+        Self {
+            policy: env_config.long_lived_update_policy,
+            drain_timeout: Duration::from_millis(env_config.long_lived_drain_timeout_ms),
+        }
+    }
+}
+
+impl Default for LongLivedSettings {
+    fn default() -> Self {
+        Self { policy: UpdatePolicy::Drain, drain_timeout: Duration::from_secs(30) }
+    }
+}
+
+/// Applies the update policy from the environment
+pub fn start(env_config: &EnvConfig) {
+    SETTINGS.store(Some(Arc::new(LongLivedSettings::from_env_config(env_config))));
+}
+
+/// The revision of a proxy a connection was routed with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub proxy_id: String,
+    pub version: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Route {
+    pub fn of(proxy: &Proxy) -> Self {
+        Self { proxy_id: proxy.id.clone(), version: proxy.version, updated_at: proxy.updated_at }
+    }
+
+    /// Whether the configuration changed or deleted the proxy
+    pub fn outdated_by(&self, config: &Configuration) -> bool {
+        !config.proxies.iter()
+            .any(|proxy| proxy.id == self.proxy_id && proxy.version == self.version && proxy.updated_at == self.updated_at)
+    }
+}
+
+struct Tracked {
+    route: Route,
+    kind: &'static str,
+    close: Arc<Notify>,
+    draining: bool,
+}
+
+/// A WebSocket connection or event stream, tracked until it is dropped
+pub struct LongLivedConnection {
+    id: u64,
+    close: Arc<Notify>,
+}
+
+impl LongLivedConnection {
+    pub fn register(route: Route, kind: &'static str) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let close = Arc::new(Notify::new());
+        CONNECTIONS.insert(id, Tracked { route, kind, close: Arc::clone(&close), draining: false });
+        Self { id, close }
+    }
+
+    /// Resolves once a configuration update drained the connection
+    pub async fn closing(&self) {
+        self.close.notified().await
+    }
+}
+
+impl Drop for LongLivedConnection {
+    fn drop(&mut self) {
+        CONNECTIONS.remove(&self.id);
+    }
+}
+
+/// Long-lived connections currently open
+pub fn open_connections() -> usize {
+    CONNECTIONS.len()
+}
+
+/// Applies the update policy to the connections the configuration outdated,
+/// returning how many of them are drained
+pub fn apply_update(settings: &LongLivedSettings, config: &Configuration) -> usize {
+    let mut drained = 0;
+    let mut kept = 0;
+    for mut connection in CONNECTIONS.iter_mut() {
+        if connection.draining || !connection.route.outdated_by(config) {
+            continue;
+        }
+        if settings.policy == UpdatePolicy::Keep {
+            kept += 1;
+            continue;
+        }
+
+        connection.draining = true;
+        drained += 1;
+        metrics::track_long_lived_drained(&connection.route.proxy_id, connection.kind);
+        let close = Arc::clone(&connection.close);
+        let drain_timeout = settings.drain_timeout;
+        tokio::spawn(async move {
+            tokio::time::sleep(drain_timeout).await;
+            close.notify_one();
+        });
+    }
+
+    if drained > 0 {
+        info!("Draining {} long-lived connections of changed proxies within {:?}", drained, settings.drain_timeout);
+    }
+    if kept > 0 {
+        debug!("Keeping {} long-lived connections of changed proxies until they close", kept);
+    }
+    drained
+}
+
+/// Applies the configured update policy after a configuration change
+pub fn on_config_changed(config: &Configuration) {
+    if CONNECTIONS.is_empty() {
+        return;
+    }
+    let settings = SETTINGS.load_full().unwrap_or_default();
+    apply_update(&settings, config);
+}
+
+/// Passes an event stream on until it ends or its connection is drained,
+/// which ends the body early
+pub fn drainable_body(mut body: Body, connection: LongLivedConnection) -> Body {
+    let (mut sender, drainable) = Body::channel();
+    tokio::spawn(async move {
+        loop {
+            let chunk = tokio::select! {
+                chunk = body.data() => chunk,
+                _ = connection.closing() => return,
+            };
+            match chunk {
+                Some(Ok(chunk)) => {
+                    if sender.send_data(chunk).await.is_err() {
+                        return;
+                    }
+                },
+                Some(Err(_)) => {
+                    sender.abort();
+                    return;
+                },
+                None => break,
+            }
+        }
+        if let Ok(Some(trailers)) = body.trailers().await {
+            let _ = sender.send_trailers(trailers).await;
+        }
+    });
+    drainable
+}
@@ -1,44 +1,171 @@
 use std::sync::Arc;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
 use std::collections::HashMap;
 use std::time::Duration;
 use tokio::sync::RwLock;
 use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
 use anyhow::{Result, Context};
 use tracing::{info, warn, error, debug};
 use hyper::server::conn::Http;
 use hyper::service::{service_fn, make_service_fn};
 use hyper::{Body, Request, Response, StatusCode};
-use quinn::{ServerConfig as QuinnServerConfig, Endpoint, TransportConfig};
-use h3_quinn::quinn::{self, ClientConfig};
+use hyper::header::HeaderValue;
+use quinn::{ServerConfig as QuinnServerConfig, Endpoint};
+use h3_quinn::quinn;
 use h3::server::{Connection as H3Connection, Builder as H3ServerBuilder};
 use h3_quinn::server::Connection as H3QuinnConnection;
 use h3::quic::SendStream;
 use futures::{StreamExt, TryStreamExt};
-use bytes::Bytes;
 use tokio::io::AsyncWriteExt;
 use std::pin::Pin;
 use std::task::{Context as TaskContext, Poll};
 
 use crate::config::env_config::EnvConfig;
-use crate::config::data_model::{Configuration, Proxy, BackendProtocol};
+use crate::config::data_model::{Configuration, Proxy, BackendProtocol, TlsPolicy, TlsVersion};
+use crate::config::snapshot::{ConfigurationSnapshot, SharedSnapshot};
 use crate::proxy::router::Router;
-use crate::proxy::handler::ProxyHandler;
-use crate::plugins::PluginManager;
+use crate::proxy::handler::{ProxyHandler, UpstreamError};
+use crate::plugins::{PluginManager, PluginRegistry};
 use crate::proxy::update_manager::UpdateManager;
 use crate::dns::cache::DnsCache; // Add correct import for DNS cache
+use crate::discovery::{DiscoverySettings, ServiceDiscovery};
+use crate::metrics;
+use crate::metrics::consumers::{ConsumerMetricsSettings, Labeled};
+use crate::acme;
+use crate::proxy::connection_limits::ConnectionLimiter;
+use crate::proxy::error_pages::{ErrorPages, GatewayError, gateway_error_response};
+use crate::utils::generate_id;
+use crate::proxy::tls::SniCertResolver;
+use crate::proxy::client_auth::{ClientAuth, ClientCertificate};
+use crate::proxy::quic::{Handshake, QuicSettings};
+use crate::health;
 
-mod router;
-mod handler;
-mod tls;
+pub mod router;
+pub mod handler;
+pub mod tls;
+pub mod client_auth;
+mod ocsp;
 mod websocket;
-mod update_manager;
+pub mod update_manager;
+pub mod connection_limits;
+mod connector;
+pub mod body;
+pub mod error_pages;
+pub mod tap;
+pub mod backend_health;
+pub mod api_version;
+pub mod hop_by_hop;
+pub mod load_shedding;
+pub mod long_lived;
+pub mod warmer;
+pub mod rate_cap;
+pub mod egress;
+pub mod bind;
+pub mod quic;
+pub mod deadline;
+
+/// Header used to correlate gateway-generated errors with logs
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// How long a client may send a request body before the minimum transfer rate is enforced
+const MIN_TRANSFER_RATE_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Request handling limits shared by all proxy listeners
+#[derive(Debug, Clone, Copy)]
+pub struct RequestLimits {
+    pub max_header_size: usize,
+    pub max_body_size: usize,
+    /// Headers allowed in a request (0 disables)
+    pub max_header_count: usize,
+    /// Bytes allowed in one request header, name and value (0 disables)
+    pub max_single_header_size: usize,
+    /// Bytes allowed in the path and query of a request (0 disables)
+    pub max_uri_length: usize,
+    /// Total time allowed for a request, including reading the body and proxying it
+    pub request_timeout: Option<Duration>,
+    /// Time allowed for a client to send the request headers
+    pub client_header_timeout: Option<Duration>,
+    /// Time allowed for a client to send the request body
+    pub client_body_timeout: Option<Duration>,
+    /// Minimum request body transfer rate in bytes per second (0 disables)
+    pub min_transfer_rate: u64,
+}
+
+impl RequestLimits {
+    pub fn from_env_config(env_config: &EnvConfig) -> Self {
+        Self {
+            max_header_size: env_config.max_header_size_bytes,
+            max_body_size: env_config.max_body_size_bytes,
+            max_header_count: env_config.max_header_count,
+            max_single_header_size: env_config.max_single_header_bytes,
+            max_uri_length: env_config.max_uri_length,
+            request_timeout: Self::timeout_from_ms(env_config.request_timeout_ms),
+            client_header_timeout: Self::timeout_from_ms(env_config.client_header_timeout_ms),
+            client_body_timeout: Self::timeout_from_ms(env_config.client_body_timeout_ms),
+            min_transfer_rate: env_config.min_transfer_rate_bytes_per_sec,
+        }
+    }
+    
+    /// Answers a request whose URI or headers exceed the limits: 414 for the
+    /// URI, 431 for the headers
+    pub fn check_head(&self, req: &Request<Body>) -> Option<Response<Body>> {
+        let uri_length = req.uri().path_and_query().map_or(0, |path_and_query| path_and_query.as_str().len());
+        if self.max_uri_length > 0 && uri_length > self.max_uri_length {
+            return Some(gateway_error_response(StatusCode::URI_TOO_LONG, "uri_too_long", "URI Too Long"));
+        }
+        
+        if self.max_header_count > 0 && req.headers().len() > self.max_header_count {
+            return Some(gateway_error_response(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE, "too_many_headers", "Request Header Fields Too Large"));
+        }
+        
+        if self.max_single_header_size > 0 {
+            let too_large = req.headers().iter()
+                .any(|(name, value)| name.as_str().len() + value.len() > self.max_single_header_size);
+            if too_large {
+                return Some(gateway_error_response(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE, "header_too_large", "Request Header Fields Too Large"));
+            }
+        }
+        
+        None
+    }
+    
+    /// Converts a millisecond setting into a timeout, treating 0 as disabled
+    fn timeout_from_ms(ms: u64) -> Option<Duration> {
+        if ms == 0 {
+            None
+        } else {
+            Some(Duration::from_millis(ms))
+        }
+    }
+}
+
+/// The listeners started by `ProxyServer::listen`
+#[derive(Default)]
+pub struct ProxyListeners {
+    /// Bound address of the HTTP listener, with the actual port when port 0 was
+    /// requested; the first one when it binds to several addresses
+    pub http_addr: Option<SocketAddr>,
+    /// Bound address of the HTTPS listener, the first one when there are several
+    pub https_addr: Option<SocketAddr>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl ProxyListeners {
+    /// Stops accepting connections. Connections already accepted are served to completion.
+    pub fn shutdown(&self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
 
 pub struct ProxyServer {
     env_config: EnvConfig,
-    shared_config: Arc<RwLock<Configuration>>,
+    snapshot: SharedSnapshot,
     plugin_manager: Arc<PluginManager>,
     dns_cache: Arc<DnsCache>,
+    discovery: Arc<ServiceDiscovery>,
     update_manager: Arc<UpdateManager>,
 }
 
@@ -48,18 +175,47 @@ impl ProxyServer {
         shared_config: Arc<RwLock<Configuration>>,
         dns_cache: Arc<DnsCache>,
     ) -> Result<Self> {
+        Self::with_plugin_registry(env_config, shared_config, dns_cache, PluginRegistry::new())
+    }
+    
+    /// Creates a proxy server whose plugins come from the given registry, which
+    /// may hold plugins registered by an embedding application
+    pub fn with_plugin_registry(
+        env_config: EnvConfig,
+        shared_config: Arc<RwLock<Configuration>>,
+        dns_cache: Arc<DnsCache>,
+        registry: PluginRegistry,
+    ) -> Result<Self> {
+        // The request path reads from an immutable snapshot which the update
+        // manager rebuilds whenever the shared configuration changes
+        let snapshot = ConfigurationSnapshot::shared(Configuration::default());
+        
+        // Failing backend lookups show up in the readiness report
+        health::register_dns_cache(&dns_cache);
+        metrics::consumers::configure(ConsumerMetricsSettings::from_env_config(&env_config));
+        
         // Initialize the plugin manager
-        let plugin_manager = Arc::new(PluginManager::new());
+        let plugin_manager = Arc::new(PluginManager::with_registry(Arc::clone(&snapshot), registry));
+        
+        // Backend instances of proxies that use a service registry
+        let discovery = ServiceDiscovery::new(DiscoverySettings::from_env_config(&env_config), Arc::clone(&dns_cache));
+        
+        // Initialize the update manager
+        let update_manager = Arc::new(UpdateManager::new(
+            Arc::clone(&shared_config),
+            Arc::clone(&snapshot),
+            Arc::clone(&discovery),
+        ));
         
-        // Initialize the router and update manager
-        let router = Arc::new(Router::new(Arc::clone(&shared_config)));
-        let update_manager = Arc::new(UpdateManager::new(Arc::clone(&router)));
+        // Resolve new backends and create new plugins as updates are swapped in
+        warmer::start(Arc::clone(&snapshot), update_manager.subscribe(), Arc::clone(&dns_cache), Arc::clone(&plugin_manager));
         
         Ok(Self {
             env_config,
-            shared_config,
+            snapshot,
             plugin_manager,
             dns_cache,
+            discovery,
             update_manager,
         })
     }
@@ -78,62 +234,125 @@ impl ProxyServer {
             return Ok(());
         }
         
+        let _listeners = self.listen().await?;
+        
+        // Prevent the function from returning (server runs in background tasks)
+        loop {
+            tokio::time::sleep(Duration::from_secs(60 * 60)).await; // Sleep for an hour
+        }
+    }
+    
+    /// Binds the enabled listeners and serves them in background tasks. Bind
+    /// failures are returned; the listeners run until they are shut down.
+    pub async fn listen(self) -> Result<ProxyListeners> {
+        let mut listeners = ProxyListeners::default();
+        
+        // Build the initial snapshot before accepting any traffic
+        self.update_manager.rebuild_snapshot().await;
+        
+        // Connection limits are shared by the TCP listeners so that the global
+        // and per-IP caps apply across HTTP and HTTPS together
+        let connection_limiter = Arc::new(ConnectionLimiter::from_env_config(&self.env_config));
+        
+        // Error templates configured globally; proxies can override them individually
+        let error_pages = Arc::new(ErrorPages::new(self.env_config.error_templates.clone()));
+        
+        // Client certificate verification shared by the HTTPS and HTTP/3 listeners
+        let client_auth = ClientAuth::new(
+            self.env_config.proxy_tls_client_auth,
+            self.env_config.proxy_tls_client_ca_path.as_deref(),
+            &self.env_config.tls_client_crl_paths,
+            self.env_config.tls_client_ocsp,
+        ).context("Failed to configure client certificate verification")?;
+        
         // Start HTTP server if enabled
         if let Some(http_port) = self.env_config.proxy_http_port {
-            let addr = format!("0.0.0.0:{}", http_port).parse::<SocketAddr>()?;
-            let shared_config = Arc::clone(&self.shared_config);
-            let plugin_manager = Arc::clone(&self.plugin_manager);
-            let dns_cache = Arc::clone(&self.dns_cache);
-            let max_header_size = self.env_config.max_header_size_bytes;
-            let max_body_size = self.env_config.max_body_size_bytes;
-            
-            info!("Starting HTTP/1.1 and HTTP/2 server on {}", addr);
-            
-            tokio::spawn(async move {
-                if let Err(e) = Self::run_http_server(
-                    addr, 
-                    shared_config, 
-                    plugin_manager,
-                    dns_cache,
-                    max_header_size,
-                    max_body_size,
-                ).await {
-                    error!("HTTP server error: {}", e);
+            let bound = bind::tcp_listeners(&self.env_config.proxy_http_bind_addresses, http_port)
+                .context("Failed to bind HTTP listener")?;
+            for listener in bound {
+                let addr = listener.local_addr()?;
+                // The first address stands for the listener
+                if listeners.http_addr.is_none() {
+                    listeners.http_addr = Some(addr);
+                    health::record_listener("proxy_http", addr);
                 }
-            });
-        }
-        
-        // Start HTTPS server if enabled
-        if let Some(https_port) = self.env_config.proxy_https_port {
-            if let (Some(cert_path), Some(key_path)) = (
-                &self.env_config.proxy_tls_cert_path,
-                &self.env_config.proxy_tls_key_path,
-            ) {
-                let addr = format!("0.0.0.0:{}", https_port).parse::<SocketAddr>()?;
-                let shared_config = Arc::clone(&self.shared_config);
+                let snapshot = Arc::clone(&self.snapshot);
                 let plugin_manager = Arc::clone(&self.plugin_manager);
                 let dns_cache = Arc::clone(&self.dns_cache);
-                let max_header_size = self.env_config.max_header_size_bytes;
-                let max_body_size = self.env_config.max_body_size_bytes;
-                let cert_path = cert_path.clone();
-                let key_path = key_path.clone();
+                let discovery = Arc::clone(&self.discovery);
+                let limits = RequestLimits::from_env_config(&self.env_config);
+                let connection_limiter = Arc::clone(&connection_limiter);
+                let error_pages = Arc::clone(&error_pages);
                 
-                info!("Starting HTTPS (HTTP/1.1 and HTTP/2 over TLS) server on {}", addr);
+                info!("Starting HTTP/1.1 and HTTP/2 server on {}", addr);
                 
-                tokio::spawn(async move {
-                    if let Err(e) = Self::run_https_server(
-                        addr,
-                        cert_path,
-                        key_path,
-                        shared_config,
+                listeners.tasks.push(tokio::spawn(async move {
+                    if let Err(e) = Self::run_http_server(
+                        listener,
+                        snapshot, 
                         plugin_manager,
                         dns_cache,
-                        max_header_size,
-                        max_body_size,
+                        discovery,
+                        limits,
+                        connection_limiter,
+                        error_pages,
                     ).await {
-                        error!("HTTPS server error: {}", e);
+                        error!("HTTP server error: {}", e);
                     }
-                });
+                }));
+            }
+        }
+        
+        // Start HTTPS server if enabled, advertising the HTTP/3 listener
+        if let Some(https_port) = self.env_config.proxy_https_port {
+            let alt_svc = quic::alt_svc_header(&self.env_config);
+            if let (Some(cert_path), Some(key_path)) = (
+                &self.env_config.proxy_tls_cert_path,
+                &self.env_config.proxy_tls_key_path,
+            ) {
+                let bound = bind::tcp_listeners(&self.env_config.proxy_https_bind_addresses, https_port)
+                    .context("Failed to bind HTTPS listener")?;
+                for listener in bound {
+                    let addr = listener.local_addr()?;
+                    if listeners.https_addr.is_none() {
+                        listeners.https_addr = Some(addr);
+                        health::record_listener("proxy_https", addr);
+                    }
+                    let snapshot = Arc::clone(&self.snapshot);
+                    let plugin_manager = Arc::clone(&self.plugin_manager);
+                    let dns_cache = Arc::clone(&self.dns_cache);
+                    let discovery = Arc::clone(&self.discovery);
+                    let limits = RequestLimits::from_env_config(&self.env_config);
+                    let connection_limiter = Arc::clone(&connection_limiter);
+                    let error_pages = Arc::clone(&error_pages);
+                    let tls_policy = self.env_config.proxy_tls_policy.clone();
+                    let client_auth = client_auth.clone();
+                    let cert_path = cert_path.clone();
+                    let key_path = key_path.clone();
+                    let alt_svc = alt_svc.clone();
+                    
+                    info!("Starting HTTPS server on {} (ALPN: {})", addr, tls_policy.alpn_protocols.join(", "));
+                    
+                    listeners.tasks.push(tokio::spawn(async move {
+                        if let Err(e) = Self::run_https_server(
+                            listener,
+                            cert_path,
+                            key_path,
+                            snapshot,
+                            plugin_manager,
+                            dns_cache,
+                            discovery,
+                            limits,
+                            connection_limiter,
+                            error_pages,
+                            tls_policy,
+                            client_auth,
+                            alt_svc,
+                        ).await {
+                            error!("HTTPS server error: {}", e);
+                        }
+                    }));
+                }
             } else {
                 warn!("HTTPS port is enabled but TLS certificate and/or key path is not provided. HTTPS server will not start.");
             }
@@ -141,33 +360,54 @@ impl ProxyServer {
         
         // Start HTTP/3 server if enabled
         if let Some(http3_port) = self.env_config.proxy_http3_port {
-            if let (Some(cert_path), Some(key_path)) = (
+            if self.env_config.proxy_tls_policy.max_version < TlsVersion::Tls13 {
+                warn!("HTTP/3 requires TLS 1.3 but FERRUM_PROXY_TLS_MAX_VERSION is 1.2. HTTP/3 server will not start.");
+            } else if let (Some(cert_path), Some(key_path)) = (
                 &self.env_config.proxy_tls_cert_path,
                 &self.env_config.proxy_tls_key_path,
             ) {
-                let addr = format!("0.0.0.0:{}", http3_port).parse::<SocketAddr>()?;
-                let shared_config = Arc::clone(&self.shared_config);
-                let plugin_manager = Arc::clone(&self.plugin_manager);
-                let dns_cache = Arc::clone(&self.dns_cache);
-                let max_body_size = self.env_config.max_body_size_bytes;
-                let cert_path = cert_path.clone();
-                let key_path = key_path.clone();
-                
-                info!("Starting HTTP/3 server on {}", addr);
-                
-                tokio::spawn(async move {
-                    if let Err(e) = Self::run_http3_server(
-                        addr,
-                        cert_path,
-                        key_path,
-                        shared_config,
-                        plugin_manager,
-                        dns_cache,
-                        max_body_size,
-                    ).await {
-                        error!("HTTP/3 server error: {}", e);
-                    }
-                });
+                let addresses = &self.env_config.proxy_http3_bind_addresses;
+                let v6_only = addresses.iter().any(IpAddr::is_ipv4);
+                if let Some(first) = addresses.first() {
+                    health::record_listener("proxy_http3", SocketAddr::new(*first, http3_port));
+                }
+                for address in addresses {
+                    let addr = SocketAddr::new(*address, http3_port);
+                    let socket = bind::udp_socket(addr, v6_only)
+                        .with_context(|| format!("Failed to bind HTTP/3 listener on {}", addr))?;
+                    let snapshot = Arc::clone(&self.snapshot);
+                    let plugin_manager = Arc::clone(&self.plugin_manager);
+                    let dns_cache = Arc::clone(&self.dns_cache);
+                    let discovery = Arc::clone(&self.discovery);
+                    let limits = RequestLimits::from_env_config(&self.env_config);
+                    let error_pages = Arc::clone(&error_pages);
+                    let tls_policy = self.env_config.proxy_tls_policy.for_quic();
+                    let client_auth = client_auth.clone();
+                    let cert_path = cert_path.clone();
+                    let key_path = key_path.clone();
+                    let quic_settings = quic::QuicSettings::from_env_config(&self.env_config);
+                    
+                    info!("Starting HTTP/3 server on {}", addr);
+                    
+                    listeners.tasks.push(tokio::spawn(async move {
+                        if let Err(e) = Self::run_http3_server(
+                            socket,
+                            cert_path,
+                            key_path,
+                            snapshot,
+                            plugin_manager,
+                            dns_cache,
+                            discovery,
+                            limits,
+                            error_pages,
+                            tls_policy,
+                            client_auth,
+                            quic_settings,
+                        ).await {
+                            error!("HTTP/3 server error: {}", e);
+                        }
+                    }));
+                }
             } else {
                 warn!("HTTP/3 port is enabled but TLS certificate and/or key path is not provided. HTTP/3 server will not start.");
             }
@@ -176,31 +416,28 @@ impl ProxyServer {
         // Perform DNS warmup for all backend hostnames
         self.warmup_dns_cache().await;
         
-        // Prevent the function from returning (server runs in background tasks)
-        loop {
-            tokio::time::sleep(Duration::from_secs(60 * 60)).await; // Sleep for an hour
-        }
+        Ok(listeners)
     }
     
     async fn run_http_server(
-        addr: SocketAddr,
-        shared_config: Arc<RwLock<Configuration>>,
+        listener: TcpListener,
+        snapshot: SharedSnapshot,
         plugin_manager: Arc<PluginManager>,
         dns_cache: Arc<DnsCache>,
-        max_header_size: usize,
-        max_body_size: usize,
+        discovery: Arc<ServiceDiscovery>,
+        limits: RequestLimits,
+        connection_limiter: Arc<ConnectionLimiter>,
+        error_pages: Arc<ErrorPages>,
     ) -> Result<()> {
-        // Create TCP listener
-        let listener = TcpListener::bind(addr).await?;
-        
         // Create the router
-        let router = Arc::new(Router::new(Arc::clone(&shared_config)));
+        let router = Arc::new(Router::new(Arc::clone(&snapshot)));
         
         // Create the handler
         let handler = Arc::new(ProxyHandler::new(
-            Arc::clone(&shared_config),
+            Arc::clone(&snapshot),
             Arc::clone(&plugin_manager),
             Arc::clone(&dns_cache),
+            Arc::clone(&discovery),
         ));
         
         // Accept and serve connections
@@ -213,26 +450,36 @@ impl ProxyServer {
                 }
             };
             
+            // Enforce connection limits before doing any work for this connection
+            let connection_guard = match connection_limiter.try_acquire(remote_addr.ip()) {
+                Ok(guard) => guard,
+                Err(rejection) => {
+                    debug!("Rejecting connection from {}: {}", remote_addr, rejection.as_str());
+                    metrics::track_connection_rejected(rejection.as_str());
+                    continue;
+                }
+            };
+            
             // Clone the necessary components for the connection handler
             let router_clone = Arc::clone(&router);
             let handler_clone = Arc::clone(&handler);
+            let error_pages_clone = Arc::clone(&error_pages);
             
             // Configure HTTP server with appropriate limits
-            let http = Http::new()
-                .max_buf_size(max_header_size)
-                .http1_only(false)
-                .http2_only(false)
-                .http1_keep_alive(true)
-                .http2_keep_alive_interval(Some(Duration::from_secs(30)));
+            let http = Self::build_http(&limits);
             
             // Spawn a task to serve the connection
             tokio::spawn(async move {
+                // Hold the connection slot until the connection closes
+                let _connection_guard = connection_guard;
+                
                 if let Err(e) = http
                     .serve_connection(
                         stream,
                         service_fn(move |req| {
                             let router = Arc::clone(&router_clone);
                             let handler = Arc::clone(&handler_clone);
+                            let error_pages = Arc::clone(&error_pages_clone);
                             let remote_addr = remote_addr;
                             
                             async move {
@@ -241,44 +488,57 @@ impl ProxyServer {
                                     router, 
                                     handler, 
                                     remote_addr,
-                                    max_body_size,
+                                    limits,
+                                    error_pages,
                                 ).await
                             }
                         }),
                     )
                     .await
                 {
-                    error!("Error serving connection: {}", e);
+                    if e.is_timeout() {
+                        metrics::track_timeout("", "client_header");
+                        debug!("Client {} timed out sending request headers", remote_addr);
+                    } else {
+                        error!("Error serving connection: {}", e);
+                    }
                 }
             });
         }
     }
     
     async fn run_https_server(
-        addr: SocketAddr,
+        listener: TcpListener,
         cert_path: String,
         key_path: String,
-        shared_config: Arc<RwLock<Configuration>>,
+        snapshot: SharedSnapshot,
         plugin_manager: Arc<PluginManager>,
         dns_cache: Arc<DnsCache>,
-        max_header_size: usize,
-        max_body_size: usize,
+        discovery: Arc<ServiceDiscovery>,
+        limits: RequestLimits,
+        connection_limiter: Arc<ConnectionLimiter>,
+        error_pages: Arc<ErrorPages>,
+        tls_policy: TlsPolicy,
+        client_auth: Option<Arc<ClientAuth>>,
+        alt_svc: Option<HeaderValue>,
     ) -> Result<()> {
-        // Load TLS configuration
-        let tls_config = tls::load_server_config(&cert_path, &key_path)
-            .context("Failed to load TLS configuration")?;
-        
-        // Create TCP listener
-        let listener = TcpListener::bind(addr).await?;
+        // The configured certificate is the default; certificates from the
+        // configuration are selected per connection by SNI
+        let default_cert = tls::load_certified_key_files(&cert_path, &key_path)
+            .context("Failed to load TLS certificate")?;
+        let resolver = Arc::new(SniCertResolver::new(Arc::clone(&snapshot), Some(default_cert)));
+        let tls_config = tls::build_sni_server_config(resolver, &tls_policy, client_auth.as_deref())
+            .context("Failed to build TLS configuration")?;
         
         // Create the router
-        let router = Arc::new(Router::new(Arc::clone(&shared_config)));
+        let router = Arc::new(Router::new(Arc::clone(&snapshot)));
         
         // Create the handler
         let handler = Arc::new(ProxyHandler::new(
-            Arc::clone(&shared_config),
+            Arc::clone(&snapshot),
             Arc::clone(&plugin_manager),
             Arc::clone(&dns_cache),
+            Arc::clone(&discovery),
         ));
         
         // Accept and serve connections
@@ -291,10 +551,23 @@ impl ProxyServer {
                 }
             };
             
+            // Enforce connection limits before doing any work for this connection
+            let connection_guard = match connection_limiter.try_acquire(remote_addr.ip()) {
+                Ok(guard) => guard,
+                Err(rejection) => {
+                    debug!("Rejecting connection from {}: {}", remote_addr, rejection.as_str());
+                    metrics::track_connection_rejected(rejection.as_str());
+                    continue;
+                }
+            };
+            
             // Clone the necessary components for the connection handler
             let router_clone = Arc::clone(&router);
             let handler_clone = Arc::clone(&handler);
+            let error_pages_clone = Arc::clone(&error_pages);
+            let client_auth = client_auth.clone();
             let tls_config = tls_config.clone();
+            let alt_svc = alt_svc.clone();
             
             // Perform TLS handshake
             let tls_stream = match tls::accept_connection(stream, tls_config).await {
@@ -305,84 +578,119 @@ impl ProxyServer {
                 }
             };
             
-            // Configure HTTP server with appropriate limits
-            let http = Http::new()
-                .max_buf_size(max_header_size)
-                .http1_only(false)
-                .http2_only(false)
-                .http1_keep_alive(true)
-                .http2_keep_alive_interval(Some(Duration::from_secs(30)));
+            // Configure HTTP server with appropriate limits, serving only the
+            // protocols offered through ALPN
+            let mut http = Self::build_http(&limits);
+            http.http1_only(!tls_policy.allows_http2())
+                .http2_only(!tls_policy.allows_http1());
             
             // Spawn a task to serve the connection
             tokio::spawn(async move {
+                // Hold the connection slot until the connection closes
+                let _connection_guard = connection_guard;
+                
+                // Check revocation and read the identity of the client certificate
+                let client_certificate = match &client_auth {
+                    Some(client_auth) => match client_auth.authorize(tls_stream.get_ref().1.peer_certificates()).await {
+                        Ok(client_certificate) => client_certificate,
+                        Err(e) => {
+                            warn!("Rejecting client certificate from {}: {:#}", remote_addr, e);
+                            metrics::track_connection_rejected("client_certificate");
+                            return;
+                        }
+                    },
+                    None => None,
+                };
+                
                 if let Err(e) = http
                     .serve_connection(
                         tls_stream,
-                        service_fn(move |req| {
+                        service_fn(move |mut req| {
                             let router = Arc::clone(&router_clone);
                             let handler = Arc::clone(&handler_clone);
+                            let error_pages = Arc::clone(&error_pages_clone);
                             let remote_addr = remote_addr;
+                            let alt_svc = alt_svc.clone();
+                            
+                            if let Some(client_certificate) = &client_certificate {
+                                req.extensions_mut().insert(Arc::clone(client_certificate));
+                            }
                             
                             async move {
-                                Self::handle_request(
+                                let mut response = Self::handle_request(
                                     req, 
                                     router, 
                                     handler, 
                                     remote_addr,
-                                    max_body_size,
-                                ).await
+                                    limits,
+                                    error_pages,
+                                ).await?;
+                                quic::advertise(&mut response, alt_svc.as_ref());
+                                Ok::<_, hyper::Error>(response)
                             }
                         }),
                     )
                     .await
                 {
-                    error!("Error serving TLS connection: {}", e);
+                    if e.is_timeout() {
+                        metrics::track_timeout("", "client_header");
+                        debug!("Client {} timed out sending request headers", remote_addr);
+                    } else {
+                        error!("Error serving TLS connection: {}", e);
+                    }
                 }
             });
         }
     }
     
     async fn run_http3_server(
-        addr: SocketAddr,
+        socket: UdpSocket,
         cert_path: String,
         key_path: String,
-        shared_config: Arc<RwLock<Configuration>>,
+        snapshot: SharedSnapshot,
         plugin_manager: Arc<PluginManager>,
         dns_cache: Arc<DnsCache>,
-        max_body_size: usize,
+        discovery: Arc<ServiceDiscovery>,
+        limits: RequestLimits,
+        error_pages: Arc<ErrorPages>,
+        tls_policy: TlsPolicy,
+        client_auth: Option<Arc<ClientAuth>>,
+        quic_settings: QuicSettings,
     ) -> Result<()> {
-        // Load TLS configuration for QUIC
-        let tls_config = tls::load_server_config(&cert_path, &key_path)
-            .context("Failed to load TLS configuration for HTTP/3")?;
+        // Load TLS configuration for QUIC, selecting certificates by SNI like the HTTPS listener
+        let default_cert = tls::load_certified_key_files(&cert_path, &key_path)
+            .context("Failed to load TLS certificate for HTTP/3")?;
+        let resolver = Arc::new(SniCertResolver::new(Arc::clone(&snapshot), Some(default_cert)));
+        let tls_config = tls::build_sni_server_config(resolver, &tls_policy, client_auth.as_deref())
+            .context("Failed to build TLS configuration for HTTP/3")?;
         
         // Create the router
-        let router = Arc::new(Router::new(Arc::clone(&shared_config)));
+        let router = Arc::new(Router::new(Arc::clone(&snapshot)));
         
         // Create the handler
         let handler = Arc::new(ProxyHandler::new(
-            Arc::clone(&shared_config),
+            Arc::clone(&snapshot),
             Arc::clone(&plugin_manager),
             Arc::clone(&dns_cache),
+            Arc::clone(&discovery),
         ));
         
         // Configure and build the QUIC server
         let mut server_config = QuinnServerConfig::default();
         
-        // Set up the TLS configuration for QUIC
-        let mut client_config = ClientConfig::default();
-        client_config.crypto = tls_config.clone();
-        client_config.crypto.alpn_protocols = vec![b"h3".to_vec()]; // HTTP/3 protocol identifier
+        // Client certificates are only known once the handshake completes, so
+        // listeners verifying them do not accept early data
+        let accept_0rtt = quic_settings.enable_0rtt && client_auth.is_none();
+        server_config.crypto = if accept_0rtt {
+            quic::accept_early_data(&tls_config)
+        } else {
+            tls_config
+        };
         
         // Configure the transport
-        let mut transport_config = TransportConfig::default();
-        transport_config.keep_alive_interval(Some(Duration::from_secs(10)));
-        transport_config.max_concurrent_uni_streams(100u32.into());
+        server_config.transport = Arc::new(quic_settings.transport_config());
         
-        server_config.crypto = tls_config.clone();
-        server_config.transport = Arc::new(transport_config);
-        
-        // Bind to UDP socket
-        let socket = UdpSocket::bind(addr)?;
+        let addr = socket.local_addr()?;
         let endpoint = Endpoint::new(
             Default::default(),
             Some(server_config),
@@ -395,44 +703,76 @@ impl ProxyServer {
         let h3_server = H3ServerBuilder::new().build();
         
         // Accept connections
-        while let Some(conn) = endpoint.accept().await {
-            match conn.await {
-                Ok(new_conn) => {
-                    // Clone references for this connection
-                    let router_clone = Arc::clone(&router);
-                    let handler_clone = Arc::clone(&handler);
-                    let h3_server = h3_server.clone();
-                    
-                    // Get the remote address
-                    let remote_addr = new_conn.remote_address();
-                    
-                    tokio::spawn(async move {
-                        // Create an H3 connection from the QUIC connection
-                        match H3QuinnConnection::new(h3_server, new_conn).await {
-                            Ok(h3_conn) => {
-                                debug!("New HTTP/3 connection from {}", remote_addr);
-                                
-                                // Process requests on this connection
-                                if let Err(e) = Self::process_h3_requests(
-                                    h3_conn,
-                                    router_clone,
-                                    handler_clone,
-                                    remote_addr,
-                                    max_body_size,
-                                ).await {
-                                    error!("Error processing HTTP/3 requests: {}", e);
-                                }
-                            }
-                            Err(e) => {
-                                error!("Failed to establish HTTP/3 connection: {}", e);
-                            }
+        while let Some(connecting) = endpoint.accept().await {
+            // Clone references for this connection
+            let router_clone = Arc::clone(&router);
+            let handler_clone = Arc::clone(&handler);
+            let error_pages_clone = Arc::clone(&error_pages);
+            let h3_server = h3_server.clone();
+            let client_auth = client_auth.clone();
+            
+            // Complete the handshake in the connection's task so that slow
+            // clients do not hold up the others
+            tokio::spawn(async move {
+                let connected = if accept_0rtt {
+                    match connecting.into_0rtt() {
+                        Ok((new_conn, accepted)) => Ok((new_conn, Handshake::pending(accepted))),
+                        Err(connecting) => connecting.await.map(|new_conn| (new_conn, Handshake::completed())),
+                    }
+                } else {
+                    connecting.await.map(|new_conn| (new_conn, Handshake::completed()))
+                };
+                let (new_conn, handshake) = match connected {
+                    Ok(connected) => connected,
+                    Err(e) => {
+                        error!("Connection failed: {}", e);
+                        return;
+                    }
+                };
+                
+                // Get the remote address
+                let remote_addr = new_conn.remote_address();
+                
+                // Certificates the client presented during the QUIC handshake
+                let peer_certificates = new_conn.peer_identity()
+                    .and_then(|identity| identity.downcast::<Vec<rustls::Certificate>>().ok());
+                
+                let client_certificate = match &client_auth {
+                    Some(client_auth) => match client_auth.authorize(peer_certificates.as_deref().map(Vec::as_slice)).await {
+                        Ok(client_certificate) => client_certificate,
+                        Err(e) => {
+                            warn!("Rejecting client certificate from {}: {:#}", remote_addr, e);
+                            metrics::track_connection_rejected("client_certificate");
+                            return;
                         }
-                    });
-                }
-                Err(e) => {
-                    error!("Connection failed: {}", e);
+                    },
+                    None => None,
+                };
+                
+                // Create an H3 connection from the QUIC connection
+                match H3QuinnConnection::new(h3_server, new_conn).await {
+                    Ok(h3_conn) => {
+                        debug!("New HTTP/3 connection from {}", remote_addr);
+                        
+                        // Process requests on this connection
+                        if let Err(e) = Self::process_h3_requests(
+                            h3_conn,
+                            router_clone,
+                            handler_clone,
+                            remote_addr,
+                            limits,
+                            error_pages_clone,
+                            client_certificate,
+                            handshake,
+                        ).await {
+                            error!("Error processing HTTP/3 requests: {}", e);
+                        }
+                    }
+                    Err(e) => {
+                        error!("Failed to establish HTTP/3 connection: {}", e);
+                    }
                 }
-            }
+            });
         }
         
         Ok(())
@@ -443,28 +783,51 @@ impl ProxyServer {
         router: Arc<Router>,
         handler: Arc<ProxyHandler>,
         remote_addr: SocketAddr,
-        max_body_size: usize,
+        limits: RequestLimits,
+        error_pages: Arc<ErrorPages>,
+        client_certificate: Option<Arc<ClientCertificate>>,
+        handshake: Handshake,
     ) -> Result<()> {
         // Process each request in the connection
         while let Some(request) = h3_conn.accept().await? {
             // Clone references for this request
             let router_clone = Arc::clone(&router);
             let handler_clone = Arc::clone(&handler);
+            let error_pages_clone = Arc::clone(&error_pages);
+            let client_certificate = client_certificate.clone();
+            let handshake = handshake.clone();
             
             // Process the request in a separate task
             tokio::spawn(async move {
                 // Convert HTTP/3 request to hyper Request
                 let (parts, body) = request.into_parts();
-                let hyper_req = Request::from_parts(parts, Body::wrap_stream(body));
+                let mut hyper_req = Request::from_parts(parts, Body::wrap_stream(body));
+                if let Some(client_certificate) = client_certificate {
+                    hyper_req.extensions_mut().insert(client_certificate);
+                }
                 
-                // Handle the request using our existing handler
-                match Self::handle_request(
-                    hyper_req,
-                    router_clone,
-                    handler_clone,
-                    remote_addr,
-                    max_body_size,
-                ).await {
+                // Early data can be replayed, so until the handshake completes
+                // only requests that are safe to repeat are served
+                let early_data = !handshake.is_complete();
+                let response = if early_data && !quic::replay_safe(hyper_req.method()) {
+                    Ok(quic::too_early_response())
+                } else {
+                    if early_data {
+                        hyper_req.headers_mut().insert(quic::EARLY_DATA_HEADER, HeaderValue::from_static("1"));
+                    }
+                    
+                    // Handle the request using our existing handler
+                    Self::handle_request(
+                        hyper_req,
+                        router_clone,
+                        handler_clone,
+                        remote_addr,
+                        limits,
+                        error_pages_clone,
+                    ).await
+                };
+                
+                match response {
                     Ok(hyper_resp) => {
                         // Convert hyper Response back to HTTP/3 response
                         let (parts, body) = hyper_resp.into_parts();
@@ -479,25 +842,37 @@ impl ProxyServer {
                                 let mut buffer = Vec::new();
                                 
                                 // Process the hyper body stream
-                                let mut body_stream = body.into_data_stream();
+                                let mut body = body;
+                                let mut complete = true;
                                 
                                 // Read and forward chunks from hyper body to h3 send_stream
-                                while let Some(chunk_result) = body_stream.next().await {
+                                while let Some(chunk_result) = hyper::body::HttpBody::data(&mut body).await {
                                     match chunk_result {
                                         Ok(chunk) => {
                                             // Write chunk to send stream
                                             if let Err(e) = send_stream.write_chunk(chunk).await {
                                                 error!("Failed to write HTTP/3 body chunk: {}", e);
+                                                complete = false;
                                                 break;
                                             }
                                         }
                                         Err(e) => {
                                             error!("Error reading response body chunk: {}", e);
+                                            complete = false;
                                             break;
                                         }
                                     }
                                 }
                                 
+                                // Trailers, such as the grpc-status of gRPC responses, follow the body
+                                if complete {
+                                    if let Ok(Some(trailers)) = hyper::body::HttpBody::trailers(&mut body).await {
+                                        if let Err(e) = send_stream.send_trailers(trailers).await {
+                                            error!("Failed to send HTTP/3 trailers: {}", e);
+                                        }
+                                    }
+                                }
+                                
                                 // Finish the stream
                                 if let Err(e) = send_stream.finish().await {
                                     error!("Failed to finish HTTP/3 response: {}", e);
@@ -529,65 +904,330 @@ impl ProxyServer {
         Ok(())
     }
     
+    /// Builds the hyper connection settings for a TCP/TLS listener
+    fn build_http(limits: &RequestLimits) -> Http {
+        let mut http = Http::new();
+        http.max_buf_size(limits.max_header_size)
+            .http2_max_header_list_size(limits.max_header_size as u32)
+            .http1_only(false)
+            .http2_only(false)
+            .http1_keep_alive(true)
+            // Recorded for proxies with backend_header_case: preserve
+            .http1_preserve_header_case(true)
+            .http2_keep_alive_interval(Some(Duration::from_secs(30)));
+        
+        // Drop connections that are too slow to send their request headers
+        if let Some(header_timeout) = limits.client_header_timeout {
+            http.http1_header_read_timeout(header_timeout);
+        }
+        
+        http
+    }
+    
     async fn handle_request(
         req: Request<Body>,
         router: Arc<Router>,
         handler: Arc<ProxyHandler>,
         remote_addr: SocketAddr,
-        max_body_size: usize,
+        limits: RequestLimits,
+        error_pages: Arc<ErrorPages>,
     ) -> Result<Response<Body>, hyper::Error> {
+        // Reuse the client's request ID if provided so errors can be correlated end to
+        // end, unless it carries characters that would need escaping
+        let request_id = req.headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| error_pages::is_valid_request_id(value))
+            .map(|value| value.to_string())
+            .unwrap_or_else(generate_id);
+        
+        // Answer pending ACME HTTP-01 challenges before any proxy can claim the path
+        if let Some(key_authorization) = acme::challenge_response(req.uri().path()) {
+            return Ok(Response::builder()
+                .header(hyper::header::CONTENT_TYPE, "text/plain")
+                .body(Body::from(key_authorization))
+                .unwrap());
+        }
+        
+        // Serve the metrics to scrapers when they are exposed on the proxy listeners
+        if let Some(response) = metrics::endpoint::proxy_response(&req).await {
+            return Ok(response);
+        }
+        
+        // Match the request to a proxy configuration
+        let proxy_config = router.route(&req);
+        
+        let response = Self::proxy_request(req, proxy_config.clone(), handler, remote_addr, limits, &request_id).await;
+        
+        // Render gateway-generated errors through the configured templates
+        let mut response = error_pages.apply(response, proxy_config.as_ref(), &request_id);
+        if response.extensions().get::<GatewayError>().is_some() {
+            if let Ok(value) = request_id.parse() {
+                response.headers_mut().insert(REQUEST_ID_HEADER, value);
+            }
+        }
+        
+        Ok(response)
+    }
+    
+    /// Enforces the request limits and forwards the request to the matched proxy
+    async fn proxy_request(
+        mut req: Request<Body>,
+        proxy_config: Option<Proxy>,
+        handler: Arc<ProxyHandler>,
+        remote_addr: SocketAddr,
+        limits: RequestLimits,
+        request_id: &str,
+    ) -> Response<Body> {
+        // Refuse oversized URIs and headers on every listener, routed or not
+        if let Some(response) = limits.check_head(&req) {
+            return response;
+        }
+        
+        let proxy_config = match proxy_config {
+            Some(proxy_config) => proxy_config,
+            None => {
+                // No matching proxy found
+                debug!("No matching proxy for path: {}", req.uri().path());
+                
+                return gateway_error_response(StatusCode::NOT_FOUND, "no_route", "Not Found");
+            }
+        };
+        
         // Check request body size (if Content-Length is provided)
         if let Some(length) = req.headers().get(hyper::header::CONTENT_LENGTH) {
             if let Ok(size) = length.to_str().unwrap_or("0").parse::<usize>() {
-                if max_body_size > 0 && size > max_body_size {
-                    return Ok(Response::builder()
-                        .status(StatusCode::PAYLOAD_TOO_LARGE)
-                        .body(Body::from("Request body too large"))
-                        .unwrap());
+                if limits.max_body_size > 0 && size > limits.max_body_size {
+                    return gateway_error_response(StatusCode::PAYLOAD_TOO_LARGE, "body_too_large", "Request body too large");
                 }
             }
         }
         
-        // Match the request to a proxy configuration
-        match router.route(&req).await {
-            Some(proxy_config) => {
-                // Handle the request with the matched proxy
-                match handler.handle(req, proxy_config, remote_addr).await {
-                    Ok(response) => Ok(response),
-                    Err(e) => {
-                        error!("Proxy handler error: {}", e);
+        // Enforce the proxy's request rate cap before anything else is spent on the request
+        if !rate_cap::admit(&proxy_config) {
+            debug!("Proxy {} reached its request rate cap", proxy_config.id);
+            let mut response = gateway_error_response(StatusCode::TOO_MANY_REQUESTS, "proxy_rate_limited", "Too Many Requests");
+            response.headers_mut().insert(hyper::header::RETRY_AFTER, hyper::header::HeaderValue::from_static("1"));
+            return response;
+        }
+        
+        // Turn away low priority requests under overload, before any plugin runs
+        let _in_flight = match load_shedding::admit(&proxy_config, &req) {
+            Ok(in_flight) => in_flight,
+            Err(priority) => {
+                debug!("Shed {} priority request to proxy {}", priority.as_str(), proxy_config.id);
+                let mut response = gateway_error_response(StatusCode::SERVICE_UNAVAILABLE, "load_shed", "Service overloaded, retry later");
+                response.headers_mut().insert(hyper::header::RETRY_AFTER, hyper::header::HeaderValue::from_static("1"));
+                return response;
+            }
+        };
+        
+        let proxy_id = proxy_config.id.clone();
+        let proxy_name = proxy_config.name.clone();
+        let workspace = proxy_config.workspace.clone();
+        let route = long_lived::Route::of(&proxy_config);
+        let started_at = std::time::Instant::now();
+        
+        // Backends are told the time left of the total request timeout
+        if let Some(request_timeout) = limits.request_timeout {
+            req.extensions_mut().insert(deadline::RequestDeadline(started_at + request_timeout));
+        }
+        
+        // A tap on the proxy captures this request and its response
+        let tap_slot = tap::claim(&proxy_id);
+        let tapped_request = tap_slot.as_ref().map(|_| {
+            let credential_params = tap::credential_query_params(&handler.snapshot().load(), &proxy_config);
+            (req.method().to_string(), tap::capture_uri(req.uri(), &credential_params), tap::capture_headers(req.headers()))
+        });
+        
+        // The client body streams to the backend, held to the body limits as it
+        // passes rather than read into memory first
+        let body_limits = body::BodyLimits {
+            max_bytes: limits.max_body_size as u64,
+            timeout: limits.client_body_timeout,
+            min_rate: limits.min_transfer_rate,
+            min_rate_grace_period: MIN_TRANSFER_RATE_GRACE_PERIOD,
+            capture_bytes: tap_slot.as_ref().map_or(0, |slot| slot.max_body_bytes),
+        };
+        let received_by = proxy_id.clone();
+        let (parts, client_body) = req.into_parts();
+        let limited = body::limit_body(client_body, body_limits, move |bytes| {
+            metrics::track_proxy_bytes(&received_by, "client_received", bytes);
+        });
+        let body_progress = limited.progress;
+        let mut body_failure = limited.failure;
+        let req = Request::from_parts(parts, limited.body);
+        
+        // Read the client body and forward the request, as one unit of work
+        // so that the total request timeout covers both
+        let work = async {
+            let handled = tokio::select! {
+                biased;
+                Ok(error) = &mut body_failure => Err(error),
+                result = handler.handle(req, proxy_config, remote_addr) => Ok(result),
+            };
+            // A backend failure caused by the client's body is answered as the body's failure
+            let handled = match handled {
+                Ok(result) => match body_failure.try_recv() {
+                    Ok(error) => Err(error),
+                    Err(_) => Ok(result),
+                },
+                Err(error) => Err(error),
+            };
+            
+            // Handle the request with the matched proxy
+            match handled {
+                Ok(Ok(response)) => response,
+                Ok(Err(e)) => {
+                    // Upstream failures carry their own classification; anything else is a gateway bug
+                    if let Some(kind) = e.downcast_ref::<UpstreamError>() {
+                        warn!("Upstream failure for proxy {}: {}", proxy_id, kind);
+                        metrics::track_upstream_error(&proxy_id, kind.as_str());
                         
-                        // Return an internal server error
-                        Ok(Response::builder()
-                            .status(StatusCode::INTERNAL_SERVER_ERROR)
-                            .body(Body::from("Internal Server Error"))
-                            .unwrap())
+                        return gateway_error_response(kind.status(), kind.as_str(), &kind.to_string());
                     }
+                    
+                    error!("Proxy handler error: {}", e);
+                    
+                    // Return an internal server error
+                    gateway_error_response(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", "Internal Server Error")
+                },
+                Err(error) => Self::body_limit_response(error, &proxy_id),
+            }
+        };
+        
+        let response = match limits.request_timeout {
+            Some(request_timeout) => match tokio::time::timeout(request_timeout, work).await {
+                Ok(response) => response,
+                Err(_) => {
+                    warn!("Request to proxy {} exceeded the total request timeout of {:?}", proxy_id, request_timeout);
+                    metrics::track_timeout(&proxy_id, "request_total");
+                    
+                    gateway_error_response(StatusCode::GATEWAY_TIMEOUT, "request_timeout", "Gateway Timeout")
                 }
             },
-            None => {
-                // No matching proxy found
-                debug!("No matching proxy for path: {}", req.uri().path());
-                
-                Ok(Response::builder()
-                    .status(StatusCode::NOT_FOUND)
-                    .body(Body::from("Not Found"))
-                    .unwrap())
+            None => work.await,
+        };
+        
+        let request_bytes = body_progress.received();
+        metrics::track_workspace_request(&workspace, response.status().as_u16());
+        metrics::track_proxy_response(&proxy_id, response.status().as_u16(), started_at.elapsed());
+        let consumer = response.extensions().get::<handler::Consumer>();
+        metrics::recent::record(&proxy_id, consumer.map(|consumer| consumer.id.as_str()), response.status().as_u16(), started_at.elapsed());
+        metrics::latency::record(&proxy_id, started_at.elapsed());
+        metrics::consumers::record(
+            Labeled { id: &proxy_id, name: proxy_name.as_deref() },
+            consumer.map(|consumer| Labeled { id: &consumer.id, name: Some(&consumer.username) }),
+            started_at.elapsed(),
+            request_bytes,
+        );
+        let consumer = consumer.map(|consumer| (consumer.id.clone(), consumer.username.clone()));
+        
+        let response = match (tap_slot, tapped_request) {
+            (Some(slot), Some((method, uri, request_headers))) => {
+                let (parts, body) = response.into_parts();
+                let (body, response_body) = match slot.max_body_bytes {
+                    0 => (body, None),
+                    max_bytes => tap::capture_body(body, &parts.headers, max_bytes).await,
+                };
+                let latency = parts.extensions.get::<handler::LatencyMetrics>().copied();
+                let capture = tap::Capture {
+                    captured_at: chrono::Utc::now(),
+                    request_id: request_id.to_string(),
+                    client_addr: remote_addr,
+                    method,
+                    uri,
+                    request_headers,
+                    request_body: (slot.max_body_bytes > 0).then(|| tap::capture_request_body(&body_progress, slot.max_body_bytes)),
+                    status: parts.status.as_u16(),
+                    response_headers: tap::capture_headers(&parts.headers),
+                    response_body,
+                    consumer_id: parts.extensions.get::<handler::Consumer>().map(|consumer| consumer.id.clone()),
+                    timings: tap::CaptureTimings {
+                        total_ms: started_at.elapsed().as_millis() as u64,
+                        backend_ttfb_ms: latency.map(|latency| latency.backend_ttfb).filter(|ttfb| *ttfb > 0),
+                        gateway_processing_ms: latency.filter(|latency| latency.total > 0).map(|latency| latency.gateway_processing),
+                    },
+                };
+                tap::record(slot, capture);
+                Response::from_parts(parts, body)
+            },
+            _ => response,
+        };
+        
+        // Event streams stay open until configuration updates drain them
+        let event_stream = response.headers().get(hyper::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map_or(false, |value| value.trim_start().to_ascii_lowercase().starts_with("text/event-stream"));
+        let response = if event_stream {
+            let connection = long_lived::LongLivedConnection::register(route, "event_stream");
+            let (parts, body) = response.into_parts();
+            Response::from_parts(parts, long_lived::drainable_body(body, connection))
+        } else {
+            response
+        };
+        
+        // Response bytes are counted as the client reads them
+        let from_backend = response.extensions().get::<handler::BackendResponse>().is_some();
+        let (parts, response_body) = response.into_parts();
+        let response_body = body::count_body(response_body, move |bytes| {
+            metrics::track_proxy_bytes(&proxy_id, "client_sent", bytes);
+            if from_backend {
+                metrics::track_proxy_bytes(&proxy_id, "backend_received", bytes);
             }
+            metrics::consumers::record_response_bytes(
+                Labeled { id: &proxy_id, name: proxy_name.as_deref() },
+                consumer.as_ref().map(|(id, username)| Labeled { id, name: Some(username) }),
+                bytes,
+            );
+        });
+        Response::from_parts(parts, response_body)
+    }
+    
+    /// Answers a request whose body crossed one of the body limits: 413 for the
+    /// size, 408 for the timeout and the minimum transfer rate
+    fn body_limit_response(error: body::BodyLimitError, proxy_id: &str) -> Response<Body> {
+        match error {
+            body::BodyLimitError::TooLarge => {
+                gateway_error_response(StatusCode::PAYLOAD_TOO_LARGE, "body_too_large", "Request body too large")
+            },
+            body::BodyLimitError::Timeout => {
+                debug!("Client timed out sending request body for proxy {}", proxy_id);
+                metrics::track_timeout(proxy_id, "client_body");
+                
+                Self::request_timeout_response()
+            },
+            body::BodyLimitError::TooSlow => {
+                debug!("Client sent request body below the minimum transfer rate for proxy {}", proxy_id);
+                metrics::track_timeout(proxy_id, "client_min_rate");
+                
+                Self::request_timeout_response()
+            },
+            body::BodyLimitError::Client => {
+                debug!("Failed to read request body for proxy {}", proxy_id);
+                
+                gateway_error_response(StatusCode::BAD_REQUEST, "client_body_error", "Failed to read request body")
+            },
         }
     }
     
+    fn request_timeout_response() -> Response<Body> {
+        let mut response = gateway_error_response(StatusCode::REQUEST_TIMEOUT, "client_timeout", "Request Timeout");
+        response.headers_mut().insert(hyper::header::CONNECTION, hyper::header::HeaderValue::from_static("close"));
+        response
+    }
+    
     async fn warmup_dns_cache(&self) {
         info!("Warming up DNS cache with backend hostnames");
         
         // Get unique backend hostnames from all proxies
         let hostnames = {
-            let config = self.shared_config.read().await;
+            let snapshot = self.snapshot.load();
             
             let mut unique_hosts = std::collections::HashSet::new();
-            for proxy in &config.proxies {
-                // Skip if DNS override is configured for this proxy
-                if proxy.dns_override.is_some() {
+            for proxy in &snapshot.config().proxies {
+                // Skip if DNS override is configured or the backend comes from a registry
+                if proxy.dns_override.is_some() || proxy.backend_discovery.is_some() {
                     continue;
                 }
                 
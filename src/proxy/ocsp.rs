@@ -0,0 +1,555 @@
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use dashmap::DashMap;
+use hyper::client::HttpConnector;
+use hyper::{Body, Client, Method, Request};
+use sha1::{Digest, Sha1};
+use tokio_rustls::rustls::Certificate;
+use tracing::{debug, warn};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::der_parser::asn1_rs::BitString;
+use x509_parser::extensions::{GeneralName, ParsedExtension};
+use x509_parser::oid_registry::OID_PKIX_ACCESS_DESCRIPTOR_OCSP;
+use x509_parser::prelude::FromDer;
+use x509_parser::verify::verify_signature;
+use x509_parser::x509::{AlgorithmIdentifier, SubjectPublicKeyInfo};
+
+use crate::config::data_model::OcspMode;
+
+/// Time allowed for the OCSP responder to answer
+const RESPONDER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a status is cached when the response has no nextUpdate
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Upper bound on how long a status is cached
+const MAX_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Oldest thisUpdate accepted from a response without a nextUpdate
+const MAX_RESPONSE_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Clock difference tolerated between the gateway and the responder
+const CLOCK_SKEW: Duration = Duration::from_secs(5 * 60);
+
+/// AlgorithmIdentifier for SHA-1, the hash OCSP responders are required to support
+const SHA1_ALGORITHM: &[u8] = &[0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00];
+
+/// id-pkix-ocsp-basic (1.3.6.1.5.5.7.48.1.1)
+const BASIC_RESPONSE_OID: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01];
+
+/// id-pkix-ocsp-nonce (1.3.6.1.5.5.7.48.1.2)
+const NONCE_OID: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x02];
+
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_ENUMERATED: u8 = 0x0a;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_EXPLICIT_0: u8 = 0xa0;
+const TAG_EXPLICIT_1: u8 = 0xa1;
+const TAG_EXPLICIT_2: u8 = 0xa2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertStatus {
+    Good,
+    Revoked,
+    Unknown,
+}
+
+/// Checks client certificates against the OCSP responder named in their
+/// Authority Information Access extension, caching answers until the
+/// response's nextUpdate.
+///
+/// Responders are usually reached over plain HTTP, so a response only counts
+/// when the certificate's issuer, or a responder it delegated OCSP signing to,
+/// signed it, it is fresh, and it echoes the request's nonce if it has one.
+pub struct OcspChecker {
+    mode: OcspMode,
+    // CA bundle, to find the issuer when the client sends no intermediates
+    issuers: Vec<Certificate>,
+    client: Client<HttpConnector>,
+    // Encoded CertID -> status and cache expiry
+    cache: DashMap<Vec<u8>, (CertStatus, Instant)>,
+}
+
+impl OcspChecker {
+    pub fn new(mode: OcspMode, issuers: Vec<Certificate>) -> Self {
+        Self {
+            mode,
+            issuers,
+            client: Client::new(),
+            cache: DashMap::new(),
+        }
+    }
+
+    /// Checks the leaf of a verified client chain. Fails when the certificate
+    /// is revoked, or in hard mode when its status can't be determined.
+    pub async fn check(&self, chain: &[Certificate]) -> Result<()> {
+        let leaf_der = chain.first().context("No client certificate presented")?;
+        let (_, leaf) = X509Certificate::from_der(&leaf_der.0)
+            .map_err(|e| anyhow!("Failed to parse client certificate: {}", e))?;
+
+        let status = match self.status(&leaf, chain).await {
+            Ok(status) => status,
+            Err(e) if self.mode == OcspMode::Soft => {
+                warn!("OCSP check for client certificate {} failed, accepting it: {:#}", leaf.raw_serial_as_string(), e);
+                return Ok(());
+            },
+            Err(e) => return Err(e),
+        };
+
+        match status {
+            CertStatus::Good => Ok(()),
+            CertStatus::Revoked => bail!("Client certificate {} is revoked", leaf.raw_serial_as_string()),
+            CertStatus::Unknown if self.mode == OcspMode::Soft => Ok(()),
+            CertStatus::Unknown => bail!("OCSP responder doesn't know client certificate {}", leaf.raw_serial_as_string()),
+        }
+    }
+
+    async fn status(&self, leaf: &X509Certificate<'_>, chain: &[Certificate]) -> Result<CertStatus> {
+        let issuer_der = chain.iter().skip(1)
+            .chain(self.issuers.iter())
+            .find(|candidate| {
+                X509Certificate::from_der(&candidate.0)
+                    .map(|(_, candidate)| candidate.subject().as_raw() == leaf.issuer().as_raw())
+                    .unwrap_or(false)
+            })
+            .context("Issuer of the client certificate not found")?;
+        let (_, issuer) = X509Certificate::from_der(&issuer_der.0)
+            .map_err(|e| anyhow!("Failed to parse issuer certificate: {}", e))?;
+
+        let cert_id = encode_cert_id(leaf, &issuer);
+        if let Some(entry) = self.cache.get(&cert_id) {
+            if entry.1 > Instant::now() {
+                return Ok(entry.0);
+            }
+        }
+
+        let url = responder_url(leaf).context("Client certificate names no OCSP responder")?;
+        debug!("Checking client certificate {} with OCSP responder {}", leaf.raw_serial_as_string(), url);
+
+        let nonce: [u8; 16] = rand::random();
+        let body = self.query(&url, encode_request(&cert_id, &nonce)).await?;
+        let (status, next_update) = parse_response(&body, leaf.raw_serial(), &issuer, &nonce)?;
+
+        let ttl = next_update.unwrap_or(DEFAULT_CACHE_TTL).min(MAX_CACHE_TTL);
+        self.cache.insert(cert_id, (status, Instant::now() + ttl));
+
+        Ok(status)
+    }
+
+    async fn query(&self, url: &str, request: Vec<u8>) -> Result<Vec<u8>> {
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(url)
+            .header(hyper::header::CONTENT_TYPE, "application/ocsp-request")
+            .body(Body::from(request))?;
+
+        let response = tokio::time::timeout(RESPONDER_TIMEOUT, self.client.request(req))
+            .await
+            .context("OCSP responder timed out")?
+            .context("OCSP request failed")?;
+
+        if !response.status().is_success() {
+            bail!("OCSP responder returned {}", response.status());
+        }
+
+        Ok(hyper::body::to_bytes(response.into_body()).await?.to_vec())
+    }
+}
+
+/// The OCSP responder URL from the Authority Information Access extension
+fn responder_url(certificate: &X509Certificate<'_>) -> Option<String> {
+    certificate.extensions().iter().find_map(|extension| match extension.parsed_extension() {
+        ParsedExtension::AuthorityInfoAccess(aia) => aia.accessdescs.iter()
+            .filter(|description| description.access_method == OID_PKIX_ACCESS_DESCRIPTOR_OCSP)
+            .find_map(|description| match description.access_location {
+                GeneralName::URI(uri) => Some(uri.to_string()),
+                _ => None,
+            }),
+        _ => None,
+    })
+}
+
+/// Encodes the CertID naming a certificate to its issuer's responder (RFC 6960 4.1.1)
+fn encode_cert_id(leaf: &X509Certificate<'_>, issuer: &X509Certificate<'_>) -> Vec<u8> {
+    let issuer_name_hash = Sha1::digest(leaf.issuer().as_raw());
+    let issuer_key_hash = Sha1::digest(issuer.public_key().subject_public_key.data.as_ref());
+
+    der(TAG_SEQUENCE, &[
+        SHA1_ALGORITHM,
+        &der(TAG_OCTET_STRING, &issuer_name_hash),
+        &der(TAG_OCTET_STRING, &issuer_key_hash),
+        &der(TAG_INTEGER, leaf.raw_serial()),
+    ].concat())
+}
+
+/// Encodes an unsigned OCSPRequest for a single certificate, with a nonce
+/// extension the response must echo if it carries one
+fn encode_request(cert_id: &[u8], nonce: &[u8]) -> Vec<u8> {
+    let request = der(TAG_SEQUENCE, cert_id);
+    let request_list = der(TAG_SEQUENCE, &request);
+    let nonce_extension = der(TAG_SEQUENCE, &[
+        der(TAG_OID, NONCE_OID),
+        der(TAG_OCTET_STRING, &der(TAG_OCTET_STRING, nonce)),
+    ].concat());
+    let extensions = der(TAG_EXPLICIT_2, &der(TAG_SEQUENCE, &nonce_extension));
+    let tbs_request = der(TAG_SEQUENCE, &[request_list, extensions].concat());
+    der(TAG_SEQUENCE, &tbs_request)
+}
+
+/// Extracts the status of the certificate with the given serial from an
+/// OCSPResponse, along with how long until the responder's nextUpdate. The
+/// response must be signed for the issuer, fresh, and carry the request's
+/// nonce if it has one.
+fn parse_response(body: &[u8], serial: &[u8], issuer: &X509Certificate<'_>, nonce: &[u8]) -> Result<(CertStatus, Option<Duration>)> {
+    let ocsp_response = DerReader::new(body).expect(TAG_SEQUENCE)?;
+    let mut ocsp_response = DerReader::new(ocsp_response);
+
+    let response_status = ocsp_response.expect(TAG_ENUMERATED)?;
+    if response_status != [0] {
+        bail!("OCSP responder returned error status {:?}", response_status);
+    }
+
+    let response_bytes = DerReader::new(ocsp_response.expect(TAG_EXPLICIT_0)?).expect(TAG_SEQUENCE)?;
+    let mut response_bytes = DerReader::new(response_bytes);
+    if response_bytes.expect(TAG_OID)? != BASIC_RESPONSE_OID {
+        bail!("Unsupported OCSP response type");
+    }
+
+    let basic_response = DerReader::new(response_bytes.expect(TAG_OCTET_STRING)?).expect(TAG_SEQUENCE)?;
+    let mut basic_response = DerReader::new(basic_response);
+    let (tbs_response_data, response_data) = basic_response.expect_element(TAG_SEQUENCE)?;
+    let (signature_algorithm, _) = basic_response.expect_element(TAG_SEQUENCE)?;
+    let signature = basic_response.expect(TAG_BIT_STRING)?;
+    let certs = if basic_response.peek_tag() == Some(TAG_EXPLICIT_0) {
+        DerReader::new(basic_response.expect(TAG_EXPLICIT_0)?).expect(TAG_SEQUENCE)?
+    } else {
+        &[][..]
+    };
+    verify_response_signature(tbs_response_data, signature_algorithm, signature, issuer, certs)?;
+
+    let mut response_data = DerReader::new(response_data);
+    if response_data.peek_tag() == Some(TAG_EXPLICIT_0) {
+        response_data.read()?; // version
+    }
+    response_data.read()?; // responderID
+    response_data.expect(TAG_GENERALIZED_TIME)?; // producedAt
+    let responses = response_data.expect(TAG_SEQUENCE)?;
+    if response_data.peek_tag() == Some(TAG_EXPLICIT_1) {
+        let extensions = DerReader::new(response_data.expect(TAG_EXPLICIT_1)?).expect(TAG_SEQUENCE)?;
+        check_nonce(extensions, nonce)?;
+    }
+
+    let mut responses = DerReader::new(responses);
+    while !responses.is_empty() {
+        let mut single_response = DerReader::new(responses.expect(TAG_SEQUENCE)?);
+
+        let mut cert_id = DerReader::new(single_response.expect(TAG_SEQUENCE)?);
+        cert_id.expect(TAG_SEQUENCE)?; // hashAlgorithm
+        cert_id.expect(TAG_OCTET_STRING)?; // issuerNameHash
+        cert_id.expect(TAG_OCTET_STRING)?; // issuerKeyHash
+        if cert_id.expect(TAG_INTEGER)? != serial {
+            continue;
+        }
+
+        let status = match single_response.read()?.0 {
+            0x80 => CertStatus::Good,
+            0xa1 => CertStatus::Revoked,
+            _ => CertStatus::Unknown,
+        };
+
+        let this_update = generalized_time(single_response.expect(TAG_GENERALIZED_TIME)?)
+            .context("Malformed OCSP response: invalid thisUpdate")?;
+        let next_update = if single_response.peek_tag() == Some(TAG_EXPLICIT_0) {
+            let time = DerReader::new(single_response.expect(TAG_EXPLICIT_0)?).expect(TAG_GENERALIZED_TIME)?;
+            Some(generalized_time(time).context("Malformed OCSP response: invalid nextUpdate")?)
+        } else {
+            None
+        };
+
+        let now = Utc::now();
+        let skew = chrono::Duration::from_std(CLOCK_SKEW)?;
+        if this_update > now + skew {
+            bail!("OCSP response is not valid until {}", this_update);
+        }
+        match next_update {
+            Some(next_update) if next_update + skew < now => bail!("OCSP response expired at {}", next_update),
+            None if now - this_update > chrono::Duration::from_std(MAX_RESPONSE_AGE)? => {
+                bail!("OCSP response from {} is stale", this_update)
+            },
+            _ => {},
+        }
+
+        return Ok((status, next_update.and_then(|next_update| (next_update - now).to_std().ok())));
+    }
+
+    bail!("OCSP response doesn't cover the client certificate")
+}
+
+/// Checks that the issuer, or a responder the issuer delegated OCSP signing
+/// to, signed the response data (RFC 6960 4.2.2.2)
+fn verify_response_signature(
+    tbs_response_data: &[u8],
+    signature_algorithm: &[u8],
+    signature: &[u8],
+    issuer: &X509Certificate<'_>,
+    certs: &[u8],
+) -> Result<()> {
+    let (_, signature_algorithm) = AlgorithmIdentifier::from_der(signature_algorithm)
+        .map_err(|e| anyhow!("Malformed OCSP response signature algorithm: {}", e))?;
+    let (&unused_bits, signature) = signature.split_first().context("Malformed OCSP response signature")?;
+    let signature = BitString::new(unused_bits, signature);
+    let signed_by = |key: &SubjectPublicKeyInfo<'_>| {
+        verify_signature(key, &signature_algorithm, &signature, tbs_response_data).is_ok()
+    };
+
+    if signed_by(issuer.public_key()) {
+        return Ok(());
+    }
+
+    let mut certs = DerReader::new(certs);
+    while !certs.is_empty() {
+        let (certificate, _) = certs.expect_element(TAG_SEQUENCE)?;
+        let (_, responder) = X509Certificate::from_der(certificate)
+            .map_err(|e| anyhow!("Failed to parse OCSP responder certificate: {}", e))?;
+        if is_delegated_responder(&responder, issuer) && signed_by(responder.public_key()) {
+            return Ok(());
+        }
+    }
+
+    bail!("OCSP response is not signed by the client certificate's issuer or a responder it authorized")
+}
+
+/// Whether the issuer issued a certificate for signing OCSP responses on its behalf
+fn is_delegated_responder(responder: &X509Certificate<'_>, issuer: &X509Certificate<'_>) -> bool {
+    let ocsp_signing = matches!(responder.extended_key_usage(), Ok(Some(usage)) if usage.value.ocsp_signing);
+    ocsp_signing
+        && responder.validity().is_valid()
+        && responder.issuer().as_raw() == issuer.subject().as_raw()
+        && responder.verify_signature(Some(issuer.public_key())).is_ok()
+}
+
+/// Fails when the response extensions carry a nonce other than the request's.
+/// Responders serving pre-produced responses leave the nonce out, so a
+/// response without one is bounded by its thisUpdate instead.
+fn check_nonce(extensions: &[u8], nonce: &[u8]) -> Result<()> {
+    let mut extensions = DerReader::new(extensions);
+    while !extensions.is_empty() {
+        let mut extension = DerReader::new(extensions.expect(TAG_SEQUENCE)?);
+        if extension.expect(TAG_OID)? != NONCE_OID {
+            continue;
+        }
+        if extension.peek_tag() == Some(TAG_BOOLEAN) {
+            extension.read()?; // critical
+        }
+        // RFC 8954 wraps the nonce in an OCTET STRING; older responders send it bare
+        let value = extension.expect(TAG_OCTET_STRING)?;
+        if value != der(TAG_OCTET_STRING, nonce).as_slice() && value != nonce {
+            bail!("OCSP response nonce doesn't match the request");
+        }
+    }
+    Ok(())
+}
+
+/// Parses a GeneralizedTime such as "20250101120000Z"
+fn generalized_time(generalized_time: &[u8]) -> Option<DateTime<Utc>> {
+    let text = std::str::from_utf8(generalized_time).ok()?;
+    // Drop fractional seconds, which responders may include
+    let text = match text.split_once('.') {
+        Some((seconds, _)) => format!("{}Z", seconds),
+        None => text.to_string(),
+    };
+    Some(NaiveDateTime::parse_from_str(&text, "%Y%m%d%H%M%SZ").ok()?.and_utc())
+}
+
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    if content.len() < 0x80 {
+        out.push(content.len() as u8);
+    } else {
+        let length: Vec<u8> = content.len().to_be_bytes().into_iter().skip_while(|&b| b == 0).collect();
+        out.push(0x80 | length.len() as u8);
+        out.extend(length);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+/// Minimal reader over a sequence of DER encoded elements
+struct DerReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Reads the next element, returning its tag and content
+    fn read(&mut self) -> Result<(u8, &'a [u8])> {
+        let malformed = || anyhow!("Malformed OCSP response");
+
+        let (&tag, rest) = self.data.split_first().ok_or_else(malformed)?;
+        let (&first, rest) = rest.split_first().ok_or_else(malformed)?;
+
+        let (length, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > std::mem::size_of::<usize>() || rest.len() < count {
+                return Err(malformed());
+            }
+            let length = rest[..count].iter().fold(0usize, |length, &b| (length << 8) | b as usize);
+            (length, &rest[count..])
+        };
+
+        if rest.len() < length {
+            return Err(malformed());
+        }
+
+        let (content, rest) = rest.split_at(length);
+        self.data = rest;
+        Ok((tag, content))
+    }
+
+    /// Reads the next element, failing unless it has the expected tag
+    fn expect(&mut self, tag: u8) -> Result<&'a [u8]> {
+        let (actual, content) = self.read()?;
+        if actual != tag {
+            bail!("Malformed OCSP response: expected tag {:#04x}, found {:#04x}", tag, actual);
+        }
+        Ok(content)
+    }
+
+    /// Like `expect`, also returning the whole encoded element, e.g. for
+    /// checking a signature over it
+    fn expect_element(&mut self, tag: u8) -> Result<(&'a [u8], &'a [u8])> {
+        let start = self.data;
+        let content = self.expect(tag)?;
+        Ok((&start[..start.len() - self.data.len()], content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair, PKCS_ECDSA_P256_SHA256};
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+    /// AlgorithmIdentifier for ecdsa-with-SHA256
+    const ECDSA_SHA256_ALGORITHM: &[u8] = &[0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+
+    struct Signer {
+        certificate: rcgen::Certificate,
+        key: EcdsaKeyPair,
+    }
+
+    fn signer(mut params: CertificateParams) -> Signer {
+        let key_pair = KeyPair::generate(&PKCS_ECDSA_P256_SHA256).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &key_pair.serialize_der()).unwrap();
+        params.alg = &PKCS_ECDSA_P256_SHA256;
+        params.key_pair = Some(key_pair);
+        Signer { certificate: rcgen::Certificate::from_params(params).unwrap(), key }
+    }
+
+    fn generalized_time_from_now(offset: chrono::Duration) -> Vec<u8> {
+        der(TAG_GENERALIZED_TIME, (Utc::now() + offset).format("%Y%m%d%H%M%SZ").to_string().as_bytes())
+    }
+
+    /// Encodes a "good" OCSPResponse for a serial, signed by `signer` or, without
+    /// one, carrying a made-up signature
+    fn good_response(serial: &[u8], this_update: chrono::Duration, nonce: Option<&[u8]>, signer: Option<&Signer>, certs: &[&[u8]]) -> Vec<u8> {
+        let cert_id = der(TAG_SEQUENCE, &[
+            SHA1_ALGORITHM,
+            &der(TAG_OCTET_STRING, &[0; 20]),
+            &der(TAG_OCTET_STRING, &[0; 20]),
+            &der(TAG_INTEGER, serial),
+        ].concat());
+        let single_response = der(TAG_SEQUENCE, &[cert_id, vec![0x80, 0x00], generalized_time_from_now(this_update)].concat());
+        let extensions = nonce.map(|nonce| {
+            let extension = der(TAG_SEQUENCE, &[der(TAG_OID, NONCE_OID), der(TAG_OCTET_STRING, &der(TAG_OCTET_STRING, nonce))].concat());
+            der(TAG_EXPLICIT_1, &der(TAG_SEQUENCE, &extension))
+        });
+        let tbs_response_data = der(TAG_SEQUENCE, &[
+            der(TAG_EXPLICIT_2, &der(TAG_OCTET_STRING, &[0; 20])), // responderID byKey
+            generalized_time_from_now(chrono::Duration::zero()),
+            der(TAG_SEQUENCE, &single_response),
+            extensions.unwrap_or_default(),
+        ].concat());
+
+        let signature = match signer {
+            Some(signer) => signer.key.sign(&SystemRandom::new(), &tbs_response_data).unwrap().as_ref().to_vec(),
+            None => vec![0; 64],
+        };
+        let mut basic_response = [
+            tbs_response_data,
+            ECDSA_SHA256_ALGORITHM.to_vec(),
+            der(TAG_BIT_STRING, &[vec![0], signature].concat()),
+        ].concat();
+        if !certs.is_empty() {
+            basic_response.extend(der(TAG_EXPLICIT_0, &der(TAG_SEQUENCE, &certs.concat())));
+        }
+
+        let response_bytes = der(TAG_SEQUENCE, &[
+            der(TAG_OID, BASIC_RESPONSE_OID),
+            der(TAG_OCTET_STRING, &der(TAG_SEQUENCE, &basic_response)),
+        ].concat());
+        der(TAG_SEQUENCE, &[der(TAG_ENUMERATED, &[0]), der(TAG_EXPLICIT_0, &response_bytes)].concat())
+    }
+
+    #[test]
+    fn test_response_must_be_signed_for_the_issuer() {
+        let mut params = CertificateParams::new(Vec::new());
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.distinguished_name.push(DnType::CommonName, "Test CA");
+        let ca = signer(params);
+        let ca_der = ca.certificate.serialize_der().unwrap();
+        let (_, issuer) = X509Certificate::from_der(&ca_der).unwrap();
+
+        let serial = [0x01, 0x02];
+        let nonce = [7u8; 16];
+        let hour = chrono::Duration::hours(1);
+        let parse = |body: Vec<u8>| parse_response(&body, &serial, &issuer, &nonce).map(|(status, _)| status);
+
+        assert_eq!(parse(good_response(&serial, -hour, Some(&nonce), Some(&ca), &[])).unwrap(), CertStatus::Good);
+
+        // Anyone on the path to the responder can answer "good" for a revoked
+        // certificate, so unsigned and foreign signatures are rejected
+        assert!(parse(good_response(&serial, -hour, Some(&nonce), None, &[])).is_err());
+        let stranger = signer(CertificateParams::new(Vec::new()));
+        let stranger_der = stranger.certificate.serialize_der().unwrap();
+        assert!(parse(good_response(&serial, -hour, Some(&nonce), Some(&stranger), &[&stranger_der])).is_err());
+
+        // A delegated responder needs the OCSPSigning usage and a certificate from the issuer
+        let mut params = CertificateParams::new(Vec::new());
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::OcspSigning];
+        let responder = signer(params);
+        let responder_der = responder.certificate.serialize_der_with_signer(&ca.certificate).unwrap();
+        assert_eq!(parse(good_response(&serial, -hour, Some(&nonce), Some(&responder), &[&responder_der])).unwrap(), CertStatus::Good);
+        let self_issued_der = responder.certificate.serialize_der().unwrap();
+        assert!(parse(good_response(&serial, -hour, Some(&nonce), Some(&responder), &[&self_issued_der])).is_err());
+        let no_usage = signer(CertificateParams::new(Vec::new()));
+        let no_usage_der = no_usage.certificate.serialize_der_with_signer(&ca.certificate).unwrap();
+        assert!(parse(good_response(&serial, -hour, Some(&nonce), Some(&no_usage), &[&no_usage_der])).is_err());
+
+        // Replayed old answers and ones for another request are rejected
+        assert!(parse(good_response(&serial, -chrono::Duration::days(2), None, Some(&ca), &[])).is_err());
+        assert!(parse(good_response(&serial, hour, None, Some(&ca), &[])).is_err());
+        assert!(parse(good_response(&serial, -hour, Some(&[8; 16]), Some(&ca), &[])).is_err());
+        assert_eq!(parse(good_response(&serial, -hour, None, Some(&ca), &[])).unwrap(), CertStatus::Good);
+    }
+}
@@ -0,0 +1,139 @@
+//! HTTP/3 transport settings and advertisement
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use quinn::{IdleTimeout, TransportConfig, VarInt, ZeroRttAccepted};
+use hyper::header::{HeaderName, HeaderValue, ALT_SVC};
+use hyper::{Body, Method, Response, StatusCode};
+
+use crate::config::data_model::TlsVersion;
+use crate::config::env_config::EnvConfig;
+use crate::proxy::error_pages::gateway_error_response;
+
+/// Marks requests forwarded from early data (RFC 8470)
+pub const EARLY_DATA_HEADER: HeaderName = HeaderName::from_static("early-data");
+
+/// How often idle connections are kept alive, within the idle timeout
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuicSettings {
+    /// Accept requests sent as 0-RTT early data by resuming clients
+    pub enable_0rtt: bool,
+    /// Connections without traffic are closed after this long (None disables)
+    pub idle_timeout: Option<Duration>,
+    /// Requests a client may have in flight on one connection
+    pub max_bidi_streams: u32,
+    /// Unidirectional streams a client may open, used for control and QPACK streams
+    pub max_uni_streams: u32,
+}
+
+impl QuicSettings {
+    pub fn from_env_config(env_config: &EnvConfig) -> Self {
+        Self {
+            enable_0rtt: env_config.quic_0rtt,
+            idle_timeout: match env_config.quic_idle_timeout_ms {
+                0 => None,
+                ms => Some(Duration::from_millis(ms)),
+            },
+            max_bidi_streams: env_config.quic_max_bidi_streams,
+            max_uni_streams: env_config.quic_max_uni_streams,
+        }
+    }
+
+    pub fn transport_config(&self) -> TransportConfig {
+        let mut transport_config = TransportConfig::default();
+        transport_config.keep_alive_interval(Some(KEEP_ALIVE_INTERVAL));
+        transport_config.max_idle_timeout(self.idle_timeout.map(|timeout| {
+            // QUIC encodes the timeout in milliseconds as a variable-length integer
+            IdleTimeout::from(VarInt::from_u64(timeout.as_millis() as u64).unwrap_or(VarInt::MAX))
+        }));
+        transport_config.max_concurrent_bidi_streams(self.max_bidi_streams.into());
+        transport_config.max_concurrent_uni_streams(self.max_uni_streams.into());
+        transport_config
+    }
+}
+
+impl Default for QuicSettings {
+    fn default() -> Self {
+        Self { enable_0rtt: false, idle_timeout: Some(Duration::from_secs(30)), max_bidi_streams: 100, max_uni_streams: 100 }
+    }
+}
+
+/// The Alt-Svc header advertising the HTTP/3 listener, when it starts and
+/// advertisement is enabled. A listener on port 0 is not advertised since
+/// its port is only known once bound.
+pub fn alt_svc_header(env_config: &EnvConfig) -> Option<HeaderValue> {
+    let port = env_config.proxy_http3_port.filter(|port| *port != 0)?;
+    let http3_starts = env_config.proxy_tls_cert_path.is_some()
+        && env_config.proxy_tls_key_path.is_some()
+        && env_config.proxy_tls_policy.max_version >= TlsVersion::Tls13;
+    if !env_config.http3_alt_svc || !http3_starts {
+        return None;
+    }
+    Some(alt_svc_value(port, env_config.http3_alt_svc_max_age))
+}
+
+/// `h3=":<port>"; ma=<seconds>`, the same host on another port
+pub fn alt_svc_value(port: u16, max_age_secs: u64) -> HeaderValue {
+    HeaderValue::from_str(&format!("h3=\":{}\"; ma={}", port, max_age_secs))
+        .expect("Alt-Svc value is valid")
+}
+
+/// Adds the Alt-Svc header unless the response already has one, e.g. set by a plugin
+pub fn advertise(response: &mut Response<Body>, alt_svc: Option<&HeaderValue>) {
+    if let Some(alt_svc) = alt_svc {
+        if !response.headers().contains_key(ALT_SVC) {
+            response.headers_mut().insert(ALT_SVC, alt_svc.clone());
+        }
+    }
+}
+
+/// The TLS configuration of a listener accepting 0-RTT early data. QUIC
+/// allows either no early data or an unlimited amount.
+pub fn accept_early_data(tls_config: &rustls::ServerConfig) -> Arc<rustls::ServerConfig> {
+    let mut config = tls_config.clone();
+    config.max_early_data_size = u32::MAX;
+    Arc::new(config)
+}
+
+/// Requests that can be served from early data, whose replay changes nothing
+pub fn replay_safe(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Answer to requests that have to wait for the handshake
+pub fn too_early_response() -> Response<Body> {
+    let status = StatusCode::from_u16(425).expect("425 is a valid status code");
+    gateway_error_response(status, "too_early", "Too Early")
+}
+
+/// Tracks whether the handshake of a connection has completed. Connections
+/// accepted with 0-RTT serve requests before that.
+#[derive(Debug, Clone)]
+pub struct Handshake {
+    complete: Arc<AtomicBool>,
+}
+
+impl Handshake {
+    pub fn completed() -> Self {
+        Self { complete: Arc::new(AtomicBool::new(true)) }
+    }
+
+    /// Waits for the handshake of a connection accepted with 0-RTT
+    pub fn pending(accepted: ZeroRttAccepted) -> Self {
+        let complete = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&complete);
+        tokio::spawn(async move {
+            if accepted.await {
+                flag.store(true, Ordering::Release);
+            }
+        });
+        Self { complete }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete.load(Ordering::Acquire)
+    }
+}
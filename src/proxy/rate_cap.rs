@@ -0,0 +1,77 @@
+//! Request rate caps of proxies
+
+use std::collections::HashSet;
+use std::time::Instant;
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+
+use crate::config::data_model::{Configuration, Proxy};
+use crate::metrics;
+
+lazy_static! {
+    static ref BUCKETS: DashMap<String, TokenBucket> = DashMap::new();
+}
+
+/// Refills `rate` tokens per second, up to `rate`
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    rate: u64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: u64, now: Instant) -> Self {
+        Self { rate, tokens: rate as f64, refilled_at: now }
+    }
+
+    /// Takes a token if one is left
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate as f64).min(self.rate as f64);
+        self.refilled_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// The request rate cap of a proxy, if it has one
+fn cap(proxy: &Proxy) -> Option<u64> {
+    proxy.max_requests_per_second.filter(|rate| *rate > 0)
+}
+
+/// Whether the proxy accepts one more request within its cap
+pub fn admit(proxy: &Proxy) -> bool {
+    let rate = match cap(proxy) {
+        Some(rate) => rate,
+        None => return true,
+    };
+
+    let now = Instant::now();
+    let mut bucket = BUCKETS.entry(proxy.id.clone()).or_insert_with(|| TokenBucket::new(rate, now));
+    // A changed cap starts over with a full bucket
+    if bucket.rate != rate {
+        *bucket = TokenBucket::new(rate, now);
+    }
+    let admitted = bucket.try_acquire(now);
+    drop(bucket);
+
+    if !admitted {
+        metrics::track_proxy_rate_capped(&proxy.id);
+    }
+    admitted
+}
+
+/// Drops the buckets of proxies that were deleted, renamed or lost their cap
+pub fn on_config_changed(config: &Configuration) {
+    let capped: HashSet<&str> = config.proxies.iter()
+        .filter(|proxy| cap(proxy).is_some())
+        .map(|proxy| proxy.id.as_str())
+        .collect();
+    BUCKETS.retain(|proxy_id, _| capped.contains(proxy_id.as_str()));
+}
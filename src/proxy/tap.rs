@@ -0,0 +1,283 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use hyper::{HeaderMap, Uri};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::data_model::Proxy;
+use crate::config::snapshot::ConfigurationSnapshot;
+use crate::proxy::body::BodyProgress;
+
+/// Most requests a tap can capture
+pub const MAX_COUNT: usize = 10_000;
+
+/// Most captures a tap keeps; the oldest are dropped to make room for new ones
+pub const BUFFER_SIZE: usize = 100;
+
+/// Most bytes of each body a tap can keep
+pub const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Longest a tap stays open
+pub const MAX_TTL: Duration = Duration::from_secs(3600);
+
+/// Headers whose values are never captured, since they carry credentials
+const REDACTED_HEADERS: [&str; 6] = ["authorization", "proxy-authorization", "cookie", "set-cookie", "apikey", "x-api-key"];
+
+/// Query parameters whose values are never captured: where key_auth and
+/// jwt_auth read credentials by default
+const REDACTED_QUERY_PARAMS: [&str; 2] = ["apikey", "access_token"];
+
+lazy_static! {
+    static ref TAPS: Mutex<HashMap<String, Tap>> = Mutex::new(HashMap::new());
+}
+
+/// Taps still capturing, so requests skip the lock when there are none
+static OPEN_TAPS: AtomicUsize = AtomicUsize::new(0);
+
+/// What to capture of a proxy's next requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TapSettings {
+    pub count: usize,
+    /// Bytes kept of each request and response body; 0 captures no bodies
+    pub max_body_bytes: usize,
+    #[serde(skip)]
+    pub ttl: Duration,
+}
+
+/// A capture of a proxy's next requests, kept until it is replaced or stopped
+#[derive(Debug, Clone, Serialize)]
+pub struct Tap {
+    pub proxy_id: String,
+    #[serde(flatten)]
+    pub settings: TapSettings,
+    pub started_at: DateTime<Utc>,
+    /// Requests after this time are not captured
+    pub expires_at: DateTime<Utc>,
+    /// Requests claimed but not captured yet, counted against `count`
+    #[serde(skip)]
+    claimed: usize,
+    /// The latest `BUFFER_SIZE` captures, oldest first
+    pub captures: VecDeque<Capture>,
+    /// Captures dropped from the front to keep the buffer bounded
+    pub dropped: usize,
+}
+
+impl Tap {
+    fn is_open(&self) -> bool {
+        self.claimed < self.settings.count && Utc::now() < self.expires_at
+    }
+}
+
+/// A captured body, cut at the tap's `max_body_bytes`
+#[derive(Debug, Clone, Serialize)]
+pub struct CapturedBody {
+    /// The body as UTF-8, with invalid sequences replaced
+    pub text: String,
+    /// Size of the whole body, when known
+    pub size: Option<usize>,
+    pub truncated: bool,
+}
+
+impl CapturedBody {
+    pub fn new(body: &[u8], size: Option<usize>, max_bytes: usize) -> Self {
+        let kept = &body[..body.len().min(max_bytes)];
+        Self {
+            text: String::from_utf8_lossy(kept).into_owned(),
+            size,
+            truncated: size.map_or(true, |size| size > kept.len()),
+        }
+    }
+}
+
+/// One request and its response, as the client saw them
+#[derive(Debug, Clone, Serialize)]
+pub struct Capture {
+    pub captured_at: DateTime<Utc>,
+    pub request_id: String,
+    pub client_addr: SocketAddr,
+    pub method: String,
+    pub uri: String,
+    pub request_headers: Vec<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<CapturedBody>,
+    pub status: u16,
+    pub response_headers: Vec<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_body: Option<CapturedBody>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consumer_id: Option<String>,
+    pub timings: CaptureTimings,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CaptureTimings {
+    pub total_ms: u64,
+    /// Until the backend's response headers; absent when no backend was reached
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend_ttfb_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gateway_processing_ms: Option<u64>,
+}
+
+/// A request claimed by a tap, to be captured once its response is known
+#[derive(Debug)]
+pub struct TapSlot {
+    pub proxy_id: String,
+    pub max_body_bytes: usize,
+    started_at: DateTime<Utc>,
+}
+
+fn update_open_taps(taps: &HashMap<String, Tap>) {
+    OPEN_TAPS.store(taps.values().filter(|tap| tap.is_open()).count(), Ordering::Relaxed);
+}
+
+/// Header names and values, with credentials redacted
+pub fn capture_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers.iter()
+        .map(|(name, value)| {
+            let value = if REDACTED_HEADERS.contains(&name.as_str()) {
+                "[redacted]".to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+/// The query parameters a proxy's key_auth plugins, its own and its
+/// workspace's global ones, read API keys from
+pub fn credential_query_params(snapshot: &ConfigurationSnapshot, proxy: &Proxy) -> Vec<String> {
+    let attached = proxy.plugins.iter().filter_map(|association| {
+        snapshot.plugin_config_by_id(&association.plugin_config_id)
+            .map(|plugin_config| (plugin_config, association.embedded_config.as_ref().unwrap_or(&plugin_config.config)))
+    });
+    let global = snapshot.global_plugin_configs(&proxy.workspace)
+        .map(|plugin_config| (plugin_config, &plugin_config.config));
+    attached.chain(global)
+        .filter(|(plugin_config, _)| plugin_config.plugin_name == "key_auth")
+        .filter_map(|(_, config)| config.get("query_name").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The request URI with the values of credential query parameters redacted:
+/// the defaults of the auth plugins and the given names
+pub fn capture_uri(uri: &Uri, credential_params: &[String]) -> String {
+    let uri = uri.to_string();
+    let (base, query) = match uri.split_once('?') {
+        Some(parts) => parts,
+        None => return uri,
+    };
+    let query: Vec<String> = query.split('&')
+        .map(|pair| {
+            let name = pair.split('=').next().unwrap_or(pair);
+            if REDACTED_QUERY_PARAMS.contains(&name) || credential_params.iter().any(|param| param == name) {
+                format!("{}=[redacted]", name)
+            } else {
+                pair.to_string()
+            }
+        })
+        .collect();
+    format!("{}?{}", base, query.join("&"))
+}
+
+/// Starts capturing a proxy's next requests, replacing a tap it already has
+pub fn start(proxy_id: &str, settings: TapSettings) -> Tap {
+    let now = Utc::now();
+    let tap = Tap {
+        proxy_id: proxy_id.to_string(),
+        settings,
+        started_at: now,
+        expires_at: now + chrono::Duration::from_std(settings.ttl).unwrap_or_else(|_| chrono::Duration::seconds(0)),
+        claimed: 0,
+        captures: VecDeque::with_capacity(settings.count.min(BUFFER_SIZE)),
+        dropped: 0,
+    };
+
+    let mut taps = TAPS.lock().unwrap();
+    taps.insert(proxy_id.to_string(), tap.clone());
+    update_open_taps(&taps);
+    tap
+}
+
+/// The tap of a proxy with what it has captured so far
+pub fn get(proxy_id: &str) -> Option<Tap> {
+    TAPS.lock().unwrap().get(proxy_id).cloned()
+}
+
+/// Stops a proxy's tap and discards its captures, returning whether it had one
+pub fn stop(proxy_id: &str) -> bool {
+    let mut taps = TAPS.lock().unwrap();
+    let removed = taps.remove(proxy_id).is_some();
+    update_open_taps(&taps);
+    removed
+}
+
+/// Claims a request of a proxy for its tap, if it has one still capturing
+pub fn claim(proxy_id: &str) -> Option<TapSlot> {
+    if OPEN_TAPS.load(Ordering::Relaxed) == 0 {
+        return None;
+    }
+
+    let mut taps = TAPS.lock().unwrap();
+    let slot = match taps.get_mut(proxy_id) {
+        Some(tap) if tap.is_open() => {
+            tap.claimed += 1;
+            Some(TapSlot {
+                proxy_id: proxy_id.to_string(),
+                max_body_bytes: tap.settings.max_body_bytes,
+                started_at: tap.started_at,
+            })
+        },
+        _ => None,
+    };
+    // Taps that filled up or expired no longer make requests take the lock
+    update_open_taps(&taps);
+    slot
+}
+
+/// Keeps the capture of a claimed request. Captures of a tap that has since
+/// been stopped or replaced are dropped.
+pub fn record(slot: TapSlot, capture: Capture) {
+    let mut taps = TAPS.lock().unwrap();
+    if let Some(tap) = taps.get_mut(&slot.proxy_id).filter(|tap| tap.started_at == slot.started_at) {
+        if tap.captures.len() == BUFFER_SIZE {
+            tap.captures.pop_front();
+            tap.dropped += 1;
+        }
+        tap.captures.push_back(capture);
+    }
+}
+
+/// Reads a body to capture it when its Content-Length is at most
+/// `MAX_BODY_BYTES`, so large and streamed responses such as server-sent
+/// events are passed on untouched. Gives the body
+/// to send on and the capture, if one was taken.
+pub async fn capture_body(body: hyper::Body, headers: &HeaderMap, max_bytes: usize) -> (hyper::Body, Option<CapturedBody>) {
+    let size = headers.get(hyper::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    match size {
+        Some(size) if size <= MAX_BODY_BYTES => match hyper::body::to_bytes(body).await {
+            Ok(bytes) => {
+                let captured = CapturedBody::new(&bytes, Some(bytes.len()), max_bytes);
+                (hyper::Body::from(bytes), Some(captured))
+            },
+            Err(_) => (hyper::Body::empty(), None),
+        },
+        _ => (body, None),
+    }
+}
+
+/// Captures the leading bytes of a request body kept while it was passed on.
+/// A body the client did not finish has no size.
+pub fn capture_request_body(progress: &BodyProgress, max_bytes: usize) -> CapturedBody {
+    let size = progress.is_complete().then(|| progress.received() as usize);
+    CapturedBody::new(&progress.captured(), size, max_bytes)
+}
@@ -1,17 +1,32 @@
-use std::fs::File;
+use std::collections::HashMap;
+use std::fs::{self, File};
 use std::io::BufReader;
 use std::path::Path;
 use std::sync::Arc;
 use anyhow::{Result, Context};
 use tokio::net::TcpStream;
-use tokio_rustls::rustls::{self, Certificate, PrivateKey, ServerConfig};
+use tokio_rustls::rustls::{self, Certificate, PrivateKey, ServerConfig, SupportedCipherSuite, SupportedProtocolVersion};
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert, WantsServerCert};
+use tokio_rustls::rustls::ConfigBuilder;
+use tokio_rustls::rustls::sign::{self, CertifiedKey};
 use tokio_rustls::TlsAcceptor;
 use tokio_rustls::server::TlsStream;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 use rustls_native_certs;
 
-/// Loads a server TLS configuration from certificate and key files
-pub fn load_server_config(cert_path: &str, key_path: &str) -> Result<Arc<ServerConfig>> {
+use crate::acme;
+use crate::config::data_model::{self, TlsPolicy, TlsVersion};
+use crate::config::snapshot::SharedSnapshot;
+use crate::proxy::client_auth::ClientAuth;
+
+/// Loads a server TLS configuration from certificate and key files,
+/// optionally verifying client certificates
+pub fn load_server_config(
+    cert_path: &str,
+    key_path: &str,
+    policy: &TlsPolicy,
+    client_auth: Option<&ClientAuth>,
+) -> Result<Arc<ServerConfig>> {
     debug!("Loading TLS certificate from {} and key from {}", cert_path, key_path);
     
     // Load and parse the certificate chain
@@ -56,18 +71,245 @@ pub fn load_server_config(cert_path: &str, key_path: &str) -> Result<Arc<ServerC
     };
     
     // Create a server config
-    let mut config = ServerConfig::builder()
-        .with_safe_defaults()
-        .with_no_client_auth()
+    let mut config = server_config_builder(policy, client_auth)?
         .with_single_cert(cert_chain, private_key)
         .context("Failed to create TLS server config")?;
     
-    // Enable ALPN protocols (HTTP/1.1, HTTP/2)
-    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    config.alpn_protocols = alpn_protocols(policy);
+    
+    Ok(Arc::new(config))
+}
+
+/// Loads the PEM files of a certificate chain and private key into a key usable
+/// for handshakes. Only for the operator's own settings, such as the default
+/// listener certificate; certificates in the configuration are PEM data.
+pub fn load_certified_key_files(cert_path: &str, key_path: &str) -> Result<CertifiedKey> {
+    let cert_pem = fs::read_to_string(cert_path).context(format!("Failed to open PEM file: {}", cert_path))?;
+    let key_pem = fs::read_to_string(key_path).context(format!("Failed to open PEM file: {}", key_path))?;
+    load_certified_key(&cert_pem, &key_pem)
+}
+
+/// Loads a PEM certificate chain and private key into a key usable for
+/// handshakes. Anything but PEM data is rejected, never read as a file path,
+/// since certificates come from the Admin API and the configuration.
+pub fn load_certified_key(cert_pem: &str, key_pem: &str) -> Result<CertifiedKey> {
+    if !is_pem(cert_pem) {
+        anyhow::bail!("Certificate must be PEM data");
+    }
+    if !is_pem(key_pem) {
+        anyhow::bail!("Private key must be PEM data");
+    }
+    let (cert_pem, key_pem) = (cert_pem.as_bytes(), key_pem.as_bytes());
+    
+    let cert_chain: Vec<Certificate> = rustls_pemfile::certs(&mut &cert_pem[..])
+        .context("Failed to parse certificate chain")?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    if cert_chain.is_empty() {
+        anyhow::bail!("No certificates found");
+    }
+    
+    // Take the first PKCS8, RSA or EC key in the PEM data
+    let private_key = rustls_pemfile::read_all(&mut &key_pem[..])
+        .context("Failed to parse private key")?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .context("No supported private key found")?;
+    
+    let signing_key = sign::any_supported_type(&private_key)
+        .map_err(|e| anyhow::anyhow!("Unsupported private key: {}", e))?;
+    
+    Ok(CertifiedKey::new(cert_chain, signing_key))
+}
+
+fn is_pem(data: &str) -> bool {
+    data.trim_start().starts_with("-----BEGIN")
+}
+
+/// Certificates from the configuration indexed by the SNI hostnames they serve.
+/// Hostnames are either exact ("api.example.com") or a single-label wildcard
+/// ("*.example.com"); exact matches win over wildcards.
+#[derive(Default)]
+pub struct SniCertificates {
+    exact: HashMap<String, Arc<CertifiedKey>>,
+    // Keyed by the suffix after the wildcard label, e.g. "example.com"
+    wildcard: HashMap<String, Arc<CertifiedKey>>,
+}
+
+impl SniCertificates {
+    /// Builds the index from the hostnames listed on each certificate and the
+    /// separately managed SNIs, skipping certificates that fail to load so one
+    /// bad entry doesn't take down TLS for every other hostname
+    pub fn build(certificates: &[data_model::Certificate], snis: &[data_model::Sni]) -> Self {
+        let mut index = Self::default();
+        let mut loaded = HashMap::with_capacity(certificates.len());
+        
+        for certificate in certificates {
+            let certified_key = match load_certified_key(&certificate.cert, &certificate.key) {
+                Ok(key) => Arc::new(key),
+                Err(e) => {
+                    error!("Failed to load certificate {}: {:#}", certificate.id, e);
+                    continue;
+                }
+            };
+            
+            for sni in &certificate.snis {
+                index.insert(sni, &certificate.id, &certified_key);
+            }
+            loaded.insert(certificate.id.as_str(), certified_key);
+        }
+        
+        for sni in snis {
+            match loaded.get(sni.certificate_id.as_str()) {
+                Some(certified_key) => index.insert(&sni.name, &sni.certificate_id, certified_key),
+                None => warn!("SNI {} refers to certificate {} which is missing or failed to load", sni.name, sni.certificate_id),
+            }
+        }
+        
+        index
+    }
+    
+    fn insert(&mut self, sni: &str, certificate_id: &str, certified_key: &Arc<CertifiedKey>) {
+        let sni = sni.trim().trim_end_matches('.').to_ascii_lowercase();
+        let previous = match sni.strip_prefix("*.") {
+            Some(suffix) => self.wildcard.insert(suffix.to_string(), Arc::clone(certified_key)),
+            None => self.exact.insert(sni.clone(), Arc::clone(certified_key)),
+        };
+        if previous.is_some() {
+            warn!("SNI {} is mapped to more than one certificate, using certificate {}", sni, certificate_id);
+        }
+    }
+    
+    /// Finds the certificate for a server name sent by the client
+    pub fn resolve(&self, server_name: &str) -> Option<Arc<CertifiedKey>> {
+        let server_name = server_name.trim_end_matches('.').to_ascii_lowercase();
+        
+        if let Some(key) = self.exact.get(&server_name) {
+            return Some(Arc::clone(key));
+        }
+        
+        // A wildcard only covers a single label
+        server_name.split_once('.')
+            .and_then(|(_, suffix)| self.wildcard.get(suffix))
+            .map(Arc::clone)
+    }
+    
+    /// Number of SNI hostnames with a certificate
+    pub fn len(&self) -> usize {
+        self.exact.len() + self.wildcard.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Selects the listener certificate by SNI from the current configuration
+/// snapshot, then from certificates issued over ACME by this process, falling
+/// back to the default certificate from FERRUM_PROXY_TLS_CERT_PATH when the
+/// client sends no SNI or no certificate matches.
+pub struct SniCertResolver {
+    snapshot: SharedSnapshot,
+    default: Option<Arc<CertifiedKey>>,
+}
+
+impl SniCertResolver {
+    pub fn new(snapshot: SharedSnapshot, default: Option<CertifiedKey>) -> Self {
+        Self {
+            snapshot,
+            default: default.map(Arc::new),
+        }
+    }
+}
+
+impl ResolvesServerCert for SniCertResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        let matched = client_hello.server_name().and_then(|server_name| {
+            self.snapshot.load().sni_certificates().resolve(server_name)
+                .or_else(|| acme::resolve_certificate(server_name))
+        });
+        
+        if matched.is_none() {
+            debug!("No SNI certificate for {:?}, using the default certificate", client_hello.server_name());
+        }
+        
+        matched.or_else(|| self.default.clone())
+    }
+}
+
+/// Builds a server TLS configuration that selects certificates by SNI,
+/// optionally verifying client certificates
+pub fn build_sni_server_config(
+    resolver: Arc<SniCertResolver>,
+    policy: &TlsPolicy,
+    client_auth: Option<&ClientAuth>,
+) -> Result<Arc<ServerConfig>> {
+    let mut config = server_config_builder(policy, client_auth)?
+        .with_cert_resolver(resolver);
+    
+    config.alpn_protocols = alpn_protocols(policy);
     
     Ok(Arc::new(config))
 }
 
+/// Starts a server configuration limited to the policy's protocol versions and
+/// cipher suites, requesting client certificates when client_auth is set
+fn server_config_builder(
+    policy: &TlsPolicy,
+    client_auth: Option<&ClientAuth>,
+) -> Result<ConfigBuilder<ServerConfig, WantsServerCert>> {
+    let builder = ServerConfig::builder()
+        .with_cipher_suites(&cipher_suites(&policy.cipher_suites)?)
+        .with_safe_default_kx_groups()
+        .with_protocol_versions(&protocol_versions(policy))
+        .map_err(|e| anyhow::anyhow!("Invalid TLS version and cipher suite combination: {}", e))?;
+    
+    Ok(match client_auth {
+        Some(client_auth) => builder.with_client_cert_verifier(client_auth.verifier()),
+        None => builder.with_no_client_auth(),
+    })
+}
+
+/// Resolves rustls cipher suite names (case-insensitive), falling back to the
+/// rustls defaults when none are configured
+pub fn cipher_suites(names: &[String]) -> Result<Vec<SupportedCipherSuite>> {
+    if names.is_empty() {
+        return Ok(rustls::DEFAULT_CIPHER_SUITES.to_vec());
+    }
+    
+    names.iter()
+        .map(|name| {
+            rustls::ALL_CIPHER_SUITES.iter()
+                .find(|suite| format!("{:?}", suite.suite()).eq_ignore_ascii_case(name))
+                .copied()
+                .context(format!("Unknown TLS cipher suite: {}", name))
+        })
+        .collect()
+}
+
+fn protocol_versions(policy: &TlsPolicy) -> Vec<&'static SupportedProtocolVersion> {
+    let mut versions = Vec::new();
+    if policy.min_version <= TlsVersion::Tls13 && policy.max_version >= TlsVersion::Tls13 {
+        versions.push(&rustls::version::TLS13);
+    }
+    if policy.min_version <= TlsVersion::Tls12 && policy.max_version >= TlsVersion::Tls12 {
+        versions.push(&rustls::version::TLS12);
+    }
+    versions
+}
+
+fn alpn_protocols(policy: &TlsPolicy) -> Vec<Vec<u8>> {
+    policy.alpn_protocols.iter()
+        .map(|protocol| protocol.as_bytes().to_vec())
+        .collect()
+}
+
 /// Accepts a TLS connection by performing the handshake
 pub async fn accept_connection(
     tcp_stream: TcpStream,
@@ -1,31 +1,132 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::{RwLock, mpsc, broadcast};
-use anyhow::Result;
+use chrono::{DateTime, Utc};
+use tokio::sync::{Mutex, RwLock, broadcast};
+use anyhow::{anyhow, Result};
 use tracing::{debug, warn, error, info};
 
-use crate::proxy::router::Router;
+use crate::config::data_model::{self, Configuration, ConfigurationDelta, Consumer, PluginConfig, Proxy};
+use crate::config::snapshot::{ConfigurationSnapshot, SharedSnapshot};
+use crate::discovery::ServiceDiscovery;
+use crate::proxy::{long_lived, rate_cap};
 
 /// Message type for router update events
 #[derive(Debug, Clone)]
 pub enum RouterUpdate {
-    /// Configuration has changed, rebuild the routing tree
+    /// Configuration has changed, rebuild the configuration snapshot
     ConfigChanged,
+    /// A swapped in snapshot added, changed or deleted proxies
+    ProxiesChanged(Arc<EntityChanges<Proxy>>),
+    /// A swapped in snapshot added, changed or deleted consumers
+    ConsumersChanged(Arc<EntityChanges<Consumer>>),
+    /// A swapped in snapshot added, changed or deleted plugin configs
+    PluginConfigsChanged(Arc<EntityChanges<PluginConfig>>),
 }
 
-/// The UpdateManager handles notifying relevant components when configuration changes
+/// Entities of one kind that differ between two snapshots: the added or
+/// changed ones as they are now, and the IDs of the deleted ones
+#[derive(Debug, Clone)]
+pub struct EntityChanges<T> {
+    pub upserted: Vec<T>,
+    pub deleted: Vec<String>,
+}
+
+impl<T: Clone> EntityChanges<T> {
+    /// Compares entities by ID, version and update time
+    fn between(old: &[T], new: &[T], key: impl Fn(&T) -> (&str, i64, DateTime<Utc>)) -> Self {
+        let old_keys: HashMap<&str, (i64, DateTime<Utc>)> = old.iter()
+            .map(|entity| {
+                let (id, version, updated_at) = key(entity);
+                (id, (version, updated_at))
+            })
+            .collect();
+        let new_ids: HashSet<&str> = new.iter().map(|entity| key(entity).0).collect();
+        
+        let upserted = new.iter()
+            .filter(|entity| {
+                let (id, version, updated_at) = key(entity);
+                old_keys.get(id) != Some(&(version, updated_at))
+            })
+            .cloned()
+            .collect();
+        let deleted = old_keys.keys()
+            .filter(|id| !new_ids.contains(*id))
+            .map(|id| id.to_string())
+            .collect();
+        Self { upserted, deleted }
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.upserted.is_empty() && self.deleted.is_empty()
+    }
+}
+
+/// The typed events describing what changed from one configuration to the next,
+/// one per kind of entity that changed
+pub fn changes_between(old: &Configuration, new: &Configuration) -> Vec<RouterUpdate> {
+    let mut updates = Vec::new();
+    
+    let proxies = EntityChanges::between(&old.proxies, &new.proxies, |p| (p.id.as_str(), p.version, p.updated_at));
+    if !proxies.is_empty() {
+        updates.push(RouterUpdate::ProxiesChanged(Arc::new(proxies)));
+    }
+    let consumers = EntityChanges::between(&old.consumers, &new.consumers, |c| (c.id.as_str(), c.version, c.updated_at));
+    if !consumers.is_empty() {
+        updates.push(RouterUpdate::ConsumersChanged(Arc::new(consumers)));
+    }
+    let plugin_configs = EntityChanges::between(&old.plugin_configs, &new.plugin_configs, |p| (p.id.as_str(), p.version, p.updated_at));
+    if !plugin_configs.is_empty() {
+        updates.push(RouterUpdate::PluginConfigsChanged(Arc::new(plugin_configs)));
+    }
+    
+    updates
+}
+
+/// The configuration with a batch of changes applied, or an error when the
+/// result would be invalid, in which case none of them apply
+pub fn apply_delta(config: &Configuration, delta: &ConfigurationDelta) -> Result<Configuration> {
+    let mut updated = config.clone();
+    delta.apply_to(&mut updated);
+    data_model::validate_proxy_listen_paths(&updated.proxies)
+        .map_err(|e| anyhow!("Configuration update rejected: {}", e))?;
+    Ok(updated)
+}
+
+/// The UpdateManager handles notifying relevant components when configuration changes.
+/// It owns the rebuild of the ConfigurationSnapshot read by the request path: the
+/// writable configuration is read once per change, the derived indexes are rebuilt
+/// and the new snapshot is swapped in atomically. Service discovery follows the
+/// registry entries of the new configuration, and long-lived connections routed
+/// with changed or deleted proxies are drained or kept as configured.
+///
+/// Batches of entity changes go through `apply`, so that requests see all of
+/// them or none. After every swap subscribers get typed events naming the
+/// proxies, consumers and plugin configs that changed.
 pub struct UpdateManager {
-    router: Arc<Router>,
+    shared_config: Arc<RwLock<Configuration>>,
+    snapshot: SharedSnapshot,
+    discovery: Arc<ServiceDiscovery>,
     update_tx: broadcast::Sender<RouterUpdate>,
+    // Held from reading the configuration until its snapshot is swapped in,
+    // so that snapshots are swapped in the order their configurations were written
+    swap_lock: Arc<Mutex<()>>,
 }
 
 impl UpdateManager {
-    pub fn new(router: Arc<Router>) -> Self {
+    pub fn new(
+        shared_config: Arc<RwLock<Configuration>>,
+        snapshot: SharedSnapshot,
+        discovery: Arc<ServiceDiscovery>,
+    ) -> Self {
         // Create a channel for router updates with buffer size of 32
         let (update_tx, _) = broadcast::channel(32);
         
         let manager = Self {
-            router,
+            shared_config,
+            snapshot,
+            discovery,
             update_tx,
+            swap_lock: Arc::new(Mutex::new(())),
         };
         
         // Spawn a task to handle updates
@@ -50,6 +151,65 @@ impl UpdateManager {
         }
     }
     
+    /// Applies a batch of entity changes, e.g. a proxy and its plugin configs,
+    /// to the configuration and swaps in a snapshot holding all of them. When
+    /// the result is invalid nothing changes and the error is returned.
+    pub async fn apply(&self, delta: &ConfigurationDelta) -> Result<()> {
+        let _swapping = self.swap_lock.lock().await;
+        let config = {
+            let mut shared_config = self.shared_config.write().await;
+            let updated = apply_delta(&shared_config, delta)?;
+            *shared_config = updated.clone();
+            updated
+        };
+        
+        Self::install(config, &self.snapshot, &self.discovery, &self.update_tx);
+        Ok(())
+    }
+    
+    /// Rebuilds the snapshot from the current configuration and swaps it in
+    pub async fn rebuild_snapshot(&self) {
+        Self::swap_snapshot(&self.shared_config, &self.snapshot, &self.discovery, &self.update_tx, &self.swap_lock).await;
+    }
+    
+    async fn swap_snapshot(
+        shared_config: &RwLock<Configuration>,
+        snapshot: &SharedSnapshot,
+        discovery: &Arc<ServiceDiscovery>,
+        update_tx: &broadcast::Sender<RouterUpdate>,
+        swap_lock: &Mutex<()>,
+    ) {
+        let _swapping = swap_lock.lock().await;
+        // Clone under the read lock and build outside of it so writers are not held up
+        let config = shared_config.read().await.clone();
+        Self::install(config, snapshot, discovery, update_tx);
+    }
+    
+    /// Builds the snapshot of a configuration, swaps it in and tells subscribers what changed
+    fn install(
+        config: Configuration,
+        snapshot: &SharedSnapshot,
+        discovery: &Arc<ServiceDiscovery>,
+        update_tx: &broadcast::Sender<RouterUpdate>,
+    ) {
+        discovery.sync(&config);
+        let new_snapshot = Arc::new(ConfigurationSnapshot::build(config));
+        let route_count = new_snapshot.route_count();
+        
+        let previous = snapshot.swap(Arc::clone(&new_snapshot));
+        info!("Swapped in new configuration snapshot with {} routes", route_count);
+        
+        // Connections routed with changed or deleted proxies follow the update policy
+        long_lived::on_config_changed(new_snapshot.config());
+        // Rate caps of proxies that are gone are forgotten
+        rate_cap::on_config_changed(new_snapshot.config());
+        
+        for update in changes_between(previous.config(), new_snapshot.config()) {
+            // No subscribers is fine
+            let _ = update_tx.send(update);
+        }
+    }
+    
     /// Get a receiver for router updates
     pub fn subscribe(&self) -> broadcast::Receiver<RouterUpdate> {
         self.update_tx.subscribe()
@@ -57,24 +217,29 @@ impl UpdateManager {
     
     /// Spawns a background task to handle update events
     fn spawn_update_handler(&self) {
-        let router = Arc::clone(&self.router);
+        let shared_config = Arc::clone(&self.shared_config);
+        let snapshot = Arc::clone(&self.snapshot);
+        let discovery = Arc::clone(&self.discovery);
+        let update_tx = self.update_tx.clone();
+        let swap_lock = Arc::clone(&self.swap_lock);
         let mut rx = self.update_tx.subscribe();
         
         tokio::spawn(async move {
             loop {
                 match rx.recv().await {
                     Ok(RouterUpdate::ConfigChanged) => {
-                        debug!("Received config change notification, rebuilding routing tree");
-                        if let Err(e) = router.rebuild_route_tree().await {
-                            error!("Failed to rebuild routing tree: {}", e);
-                        }
+                        debug!("Received config change notification, rebuilding configuration snapshot");
+                        Self::swap_snapshot(&shared_config, &snapshot, &discovery, &update_tx, &swap_lock).await;
                     },
+                    // Typed events are for other subscribers
+                    Ok(_) => {},
                     Err(e) => {
                         warn!("Error receiving router update: {}", e);
                         // Try to resubscribe if the channel is lagged
-                        if e.is_lagged() {
-                            debug!("Resubscribing to router updates due to lag");
-                            // Continue the loop, which will use the current rx
+                        if matches!(e, broadcast::error::RecvError::Lagged(_)) {
+                            // Missed notifications collapse into a single rebuild
+                            debug!("Router updates lagged, rebuilding configuration snapshot");
+                            Self::swap_snapshot(&shared_config, &snapshot, &discovery, &update_tx, &swap_lock).await;
                         } else {
                             // Exit the loop for other errors
                             error!("Terminating router update handler due to error: {}", e);
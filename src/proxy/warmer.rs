@@ -0,0 +1,80 @@
+//! Cache warming on configuration updates
+
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+use crate::config::data_model::{PluginConfig, Proxy};
+use crate::config::snapshot::SharedSnapshot;
+use crate::dns::{self, DnsCache};
+use crate::plugins::PluginManager;
+use crate::proxy::update_manager::{EntityChanges, RouterUpdate};
+
+/// Creates the plugins of the current configuration, then warms the caches
+/// for every update received until the update manager is dropped
+pub fn start(
+    snapshot: SharedSnapshot,
+    mut updates: broadcast::Receiver<RouterUpdate>,
+    dns_cache: Arc<DnsCache>,
+    plugin_manager: Arc<PluginManager>,
+) {
+    tokio::spawn(async move {
+        let current = snapshot.load_full();
+        let initial = EntityChanges { upserted: current.config().plugin_configs.clone(), deleted: Vec::new() };
+        warm_plugin_configs(&initial, &plugin_manager);
+        for proxy in &current.config().proxies {
+            if let Err(e) = plugin_manager.warm_proxy(proxy).await {
+                warn!("Failed to create the plugins of proxy {}: {}", proxy.id, e);
+            }
+        }
+        drop(current);
+
+        loop {
+            match updates.recv().await {
+                Ok(RouterUpdate::ProxiesChanged(changes)) => {
+                    warm_proxies(&changes, &dns_cache, &plugin_manager).await;
+                },
+                Ok(RouterUpdate::PluginConfigsChanged(changes)) => {
+                    warm_plugin_configs(&changes, &plugin_manager);
+                },
+                Ok(_) => {},
+                // Missed proxies are resolved and created by their first request
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    debug!("Cache warmer missed {} configuration updates", missed);
+                },
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+async fn warm_proxies(changes: &EntityChanges<Proxy>, dns_cache: &DnsCache, plugin_manager: &PluginManager) {
+    plugin_manager.evict(&[], &changes.deleted);
+
+    for proxy in &changes.upserted {
+        if let Err(e) = plugin_manager.warm_proxy(proxy).await {
+            warn!("Failed to create the plugins of proxy {}: {}", proxy.id, e);
+        }
+    }
+
+    // Proxies with a service registry resolve their instances through it
+    let resolved: Vec<Proxy> = changes.upserted.iter()
+        .filter(|proxy| proxy.backend_discovery.is_none())
+        .cloned()
+        .collect();
+    if !resolved.is_empty() {
+        if let Err(e) = dns::warm_up_dns_cache(dns_cache, &resolved).await {
+            warn!("DNS cache warmup for changed proxies failed: {}", e);
+        }
+    }
+}
+
+fn warm_plugin_configs(changes: &EntityChanges<PluginConfig>, plugin_manager: &PluginManager) {
+    plugin_manager.evict(&changes.deleted, &[]);
+
+    for plugin_config in changes.upserted.iter().filter(|plugin_config| plugin_config.enabled) {
+        if let Err(e) = plugin_manager.warm_plugin_config(plugin_config) {
+            warn!("Failed to create the {} plugin of plugin config {}: {}", plugin_config.plugin_name, plugin_config.id, e);
+        }
+    }
+}
@@ -5,17 +5,26 @@ use hyper::{Body, Client, Request, Response, StatusCode};
 use hyper_tls::HttpsConnector;
 use tokio::net::TcpStream;
 use tokio_tungstenite::{
-    connect_async, tungstenite::Message, WebSocketStream, MaybeTlsStream
+    client_async_tls, tungstenite::Message, WebSocketStream, MaybeTlsStream
 };
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
 use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 use std::sync::Arc;
 use std::net::SocketAddr;
+use std::time::Duration;
 use http::Uri;
 
 use crate::proxy::handler::RequestContext;
+use crate::proxy::egress::{self, EgressRoute};
+use crate::proxy::long_lived::{LongLivedConnection, Route};
 use crate::config::data_model::Protocol;
 
+/// How long both sides of a drained connection have to complete the close
+/// handshake before the connection is dropped
+const CLOSE_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Handles WebSocket proxying for the gateway
 pub async fn handle_websocket(
     req: Request<Body>, 
@@ -46,6 +55,14 @@ pub async fn handle_websocket(
     // Get the peer address for logging
     let client_addr = ctx.client_addr;
     
+    // Tracked so that configuration updates can drain the connection
+    let connection = LongLivedConnection::register(Route::of(&ctx.proxy), "websocket");
+    
+    // Validation rejects egress proxies that do not parse
+    let egress = EgressRoute::parse(ctx.proxy.egress_proxy.as_deref()).unwrap_or_default();
+    let backend_host = ctx.proxy.backend_host.clone();
+    let backend_port = ctx.proxy.backend_port;
+    
     // Spawn a task to handle the WebSocket upgrade and proxying
     tokio::spawn(async move {
         match hyper::upgrade::on(req).await {
@@ -53,7 +70,8 @@ pub async fn handle_websocket(
                 debug!("WebSocket connection upgraded for client: {}", client_addr);
                 
                 // Connect to the backend WebSocket
-                if let Err(e) = proxy_websocket(upgraded, backend_uri, client_addr).await {
+                let proxied = proxy_websocket(upgraded, backend_uri, egress, backend_host, backend_port, client_addr, connection);
+                if let Err(e) = proxied.await {
                     error!("WebSocket proxy error: {}", e);
                 }
             },
@@ -123,7 +141,11 @@ fn build_ws_backend_uri(ctx: &RequestContext, original_uri: &Uri) -> Result<Stri
 async fn proxy_websocket(
     client_ws: Upgraded,
     backend_uri: String,
+    egress: EgressRoute,
+    backend_host: String,
+    backend_port: u16,
     client_addr: SocketAddr,
+    connection: LongLivedConnection,
 ) -> Result<()> {
     // Create a WebSocketStream from the upgraded connection
     let client_ws_stream = tokio_tungstenite::WebSocketStream::from_raw_socket(
@@ -134,8 +156,10 @@ async fn proxy_websocket(
     
     debug!("Connecting to backend WebSocket at {}", backend_uri);
     
-    // Connect to the backend WebSocket
-    let (backend_ws_stream, _) = connect_async(&backend_uri).await
+    // Connect to the backend WebSocket, through the egress proxy if there is one
+    let stream = egress::connect(&egress, &backend_host, backend_port).await
+        .map_err(|e| anyhow!("Failed to connect to backend WebSocket: {}", e))?;
+    let (backend_ws_stream, _) = client_async_tls(backend_uri.as_str(), stream).await
         .map_err(|e| anyhow!("Failed to connect to backend WebSocket: {}", e))?;
     
     debug!("Connected to backend WebSocket, setting up bidirectional proxy");
@@ -148,15 +172,19 @@ async fn proxy_websocket(
     let (client_to_backend_tx, client_to_backend_rx) = mpsc::channel(32);
     let (backend_to_client_tx, backend_to_client_rx) = mpsc::channel(32);
     
+    // Weak handles let a drain send close frames without keeping the channels open
+    let to_backend = client_to_backend_tx.downgrade();
+    let to_client = backend_to_client_tx.downgrade();
+    
     // Set up the client-to-backend direction
-    tokio::spawn(proxy_ws_messages(
+    let client_reader = tokio::spawn(proxy_ws_messages(
         client_read,
         client_to_backend_tx,
         format!("client-{}", client_addr),
         "backend".to_string(),
     ));
     
-    tokio::spawn(forward_ws_messages(
+    let backend_writer = tokio::spawn(forward_ws_messages(
         client_to_backend_rx,
         backend_write,
         format!("client-{}", client_addr),
@@ -164,14 +192,14 @@ async fn proxy_websocket(
     ));
     
     // Set up the backend-to-client direction
-    tokio::spawn(proxy_ws_messages(
+    let backend_reader = tokio::spawn(proxy_ws_messages(
         backend_read,
         backend_to_client_tx,
         "backend".to_string(),
         format!("client-{}", client_addr),
     ));
     
-    tokio::spawn(forward_ws_messages(
+    let client_writer = tokio::spawn(forward_ws_messages(
         backend_to_client_rx,
         client_write,
         "backend".to_string(),
@@ -180,6 +208,38 @@ async fn proxy_websocket(
     
     debug!("WebSocket proxy established between client {} and backend {}", client_addr, backend_uri);
     
+    // Keep the connection tracked while it is open, and close it when a
+    // configuration update drains it
+    tokio::spawn(async move {
+        let aborts = [
+            client_reader.abort_handle(),
+            backend_writer.abort_handle(),
+            backend_reader.abort_handle(),
+            client_writer.abort_handle(),
+        ];
+        let forwarding = futures_util::future::join(backend_writer, client_writer);
+        tokio::pin!(forwarding);
+        
+        tokio::select! {
+            _ = &mut forwarding => return,
+            _ = connection.closing() => {},
+        }
+        
+        debug!("Draining WebSocket connection of client {} after a configuration update", client_addr);
+        for sender in [to_client, to_backend] {
+            if let Some(sender) = sender.upgrade() {
+                let frame = CloseFrame { code: CloseCode::Away, reason: "proxy configuration changed".into() };
+                let _ = sender.send(Message::Close(Some(frame))).await;
+            }
+        }
+        if tokio::time::timeout(CLOSE_HANDSHAKE_TIMEOUT, &mut forwarding).await.is_err() {
+            for abort in aborts {
+                abort.abort();
+            }
+        }
+        drop(connection);
+    });
+    
     Ok(())
 }
 
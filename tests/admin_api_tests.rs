@@ -9,6 +9,7 @@ mod admin_api_tests {
     use tokio::sync::RwLock;
     
     use ferrumgw::config::data_model::{Configuration, Proxy, Consumer, PluginConfig, Protocol, AuthMode};
+    use ferrumgw::config::env_config::EnvConfig;
     use ferrumgw::admin::routes::{proxies, consumers, plugins, metrics};
     use ferrumgw::database::memory::InMemoryAdapter;
     
@@ -18,6 +19,8 @@ mod admin_api_tests {
             proxies: Vec::new(),
             consumers: Vec::new(),
             plugin_configs: Vec::new(),
+            certificates: Vec::new(),
+            snis: Vec::new(),
             last_updated_at: Utc::now(),
         };
         
@@ -475,6 +478,10 @@ mod admin_api_tests {
             config.proxies.push(Proxy {
                 id: "proxy1".to_string(),
                 name: Some("Test Proxy".to_string()),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
+                enabled: true,
                 listen_path: "/api/test".to_string(),
                 backend_protocol: Protocol::Http,
                 backend_host: "example.com".to_string(),
@@ -491,8 +498,18 @@ mod admin_api_tests {
                 backend_tls_server_ca_cert_path: None,
                 dns_override: None,
                 dns_cache_ttl_seconds: None,
+                dns_resolvers: Vec::new(),
+                backend_discovery: None,
                 auth_mode: AuthMode::Single,
                 plugins: Vec::new(),
+                error_templates: HashMap::new(),
+                log_sampling: None,
+                api_versioning: None,
+                max_requests_per_second: None,
+                backend_keepalive: None,
+                backend_header_case: None,
+                upstream_host: None,
+                egress_proxy: None,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             });
@@ -500,6 +517,9 @@ mod admin_api_tests {
             config.consumers.push(Consumer {
                 id: "consumer1".to_string(),
                 username: "testuser".to_string(),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
                 custom_id: None,
                 credentials: HashMap::new(),
                 created_at: Utc::now(),
@@ -538,4 +558,474 @@ mod admin_api_tests {
         assert_eq!(status_codes["200"], 2);
         assert_eq!(status_codes["404"], 1);
     }
+    
+    // Helper function to create a test proxy
+    fn create_test_proxy(id: &str, listen_path: &str) -> Proxy {
+        Proxy {
+            id: id.to_string(),
+            name: Some(format!("Test Proxy {}", id)),
+            workspace: "default".to_string(),
+            version: 1,
+            tags: Vec::new(),
+            enabled: true,
+            listen_path: listen_path.to_string(),
+            backend_protocol: Protocol::Http,
+            backend_host: "example.com".to_string(),
+            backend_port: 80,
+            backend_path: Some("/api".to_string()),
+            strip_listen_path: true,
+            preserve_host_header: false,
+            backend_connect_timeout_ms: 5000,
+            backend_read_timeout_ms: 30000,
+            backend_write_timeout_ms: 30000,
+            backend_tls_client_cert_path: None,
+            backend_tls_client_key_path: None,
+            backend_tls_verify_server_cert: true,
+            backend_tls_server_ca_cert_path: None,
+            dns_override: None,
+            dns_cache_ttl_seconds: None,
+            dns_resolvers: Vec::new(),
+            backend_discovery: None,
+            auth_mode: AuthMode::Single,
+            plugins: Vec::new(),
+            error_templates: HashMap::new(),
+            log_sampling: None,
+            api_versioning: None,
+            max_requests_per_second: None,
+            backend_keepalive: None,
+            backend_header_case: None,
+            upstream_host: None,
+            egress_proxy: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+    
+    #[test]
+    fn test_audit_targets() {
+        use ferrumgw::admin::audit::{self, AuditTarget};
+        use hyper::Method;
+        
+        let target = AuditTarget::from_request(&Method::PUT, "/proxies/p1").unwrap();
+        assert_eq!((target.action, target.entity_type), ("update", "proxy"));
+        assert_eq!(target.entity_id.as_deref(), Some("p1"));
+        
+        // Creations learn the ID from the response
+        let target = AuditTarget::from_request(&Method::POST, "/plugins/config").unwrap();
+        assert_eq!((target.action, target.entity_type, target.entity_id), ("create", "plugin_config", None));
+        
+        let target = AuditTarget::from_request(&Method::DELETE, "/consumers/c1/credentials/key-auth").unwrap();
+        assert_eq!(target.entity_id.as_deref(), Some("c1"));
+        assert!(target.credentials);
+        
+        // Changes outside the configuration are audited as well
+        let target = AuditTarget::from_request(&Method::PUT, "/users/alice").unwrap();
+        assert_eq!((target.action, target.entity_type), ("update", "user"));
+        assert_eq!(target.entity_id.as_deref(), Some("alice"));
+        let target = AuditTarget::from_request(&Method::POST, "/users").unwrap();
+        assert_eq!(audit::entity_id(&target, br#"{"username":"bob","role":"operator"}"#).as_deref(), Some("bob"));
+        let target = AuditTarget::from_request(&Method::DELETE, "/tokens/3f2a").unwrap();
+        assert_eq!((target.action, target.entity_type), ("delete", "api_token"));
+        let target = AuditTarget::from_request(&Method::POST, "/auth/revoke").unwrap();
+        assert_eq!((target.action, target.entity_type), ("revoke", "admin_token"));
+        let target = AuditTarget::from_request(&Method::DELETE, "/config/pins/eu-west").unwrap();
+        assert_eq!((target.action, target.entity_type, target.entity_id.as_deref()), ("delete", "group_pin", Some("eu-west")));
+        let target = AuditTarget::from_request(&Method::POST, "/config/versions/42/promote").unwrap();
+        assert_eq!((target.action, target.entity_id.as_deref()), ("promote", Some("42")));
+        let target = AuditTarget::from_request(&Method::POST, "/nodes/dp-1/resync").unwrap();
+        assert_eq!((target.action, target.entity_type, target.entity_id.as_deref()), ("resync", "node", Some("dp-1")));
+        let target = AuditTarget::from_request(&Method::POST, "/proxies/p1/tap").unwrap();
+        assert_eq!((target.entity_type, target.entity_id.as_deref()), ("tap", Some("p1")));
+        assert!(AuditTarget::from_request(&Method::POST, "/debug/route").is_none());
+        
+        // Issued API tokens are not kept
+        let token = audit::redact("api_token", serde_json::json!({ "id": "3f2a", "token": "eyJ..." }));
+        assert_eq!(token["token"], "[redacted]");
+        
+        let target = AuditTarget::from_request(&Method::POST, "/config/versions/4/rollback").unwrap();
+        assert_eq!((target.action, target.entity_type), ("rollback", "configuration"));
+        
+        assert!(AuditTarget::from_request(&Method::POST, "/config/validate").is_none());
+        assert!(AuditTarget::from_request(&Method::GET, "/proxies/p1").is_none());
+        
+        // Secrets never reach the audit log
+        let consumer = audit::redact("consumer", serde_json::json!({
+            "id": "c1",
+            "credentials": { "keyauth": { "key": "secret" } }
+        }));
+        assert_eq!(consumer["credentials"]["keyauth"], "[redacted]");
+        assert_eq!(consumer["id"], "c1");
+    }
+    
+    #[test]
+    fn test_list_query() {
+        use ferrumgw::admin::pagination::ListQuery;
+        use hyper::{Body, Request};
+        
+        let items: Vec<serde_json::Value> = (1..=5)
+            .map(|i| serde_json::json!({ "id": format!("p{}", i), "name": format!("api-{}", 6 - i), "protocol": "http" }))
+            .chain(std::iter::once(serde_json::json!({ "id": "p6", "name": "internal", "protocol": "grpc" })))
+            .collect();
+        let query = |uri: &str| ListQuery::from_request(&Request::get(uri).body(Body::empty()).unwrap()).unwrap();
+        
+        // Filtered and sorted by name, two per page
+        let (page, meta) = query("/proxies?name_contains=API&protocol=http&sort=name&limit=2").apply(&items).unwrap();
+        assert_eq!(page.iter().map(|item| item["id"].as_str().unwrap()).collect::<Vec<_>>(), ["p5", "p4"]);
+        assert_eq!(meta.total, 5);
+        
+        // The cursor continues after the last item
+        let cursor = meta.next_cursor.unwrap();
+        let (page, meta) = query(&format!("/proxies?name_contains=API&protocol=http&sort=name&limit=2&cursor={}", cursor))
+            .apply(&items).unwrap();
+        assert_eq!(page.iter().map(|item| item["id"].as_str().unwrap()).collect::<Vec<_>>(), ["p3", "p2"]);
+        assert!(meta.next_cursor.is_some());
+        
+        // Last page by page number, descending
+        let (page, meta) = query("/proxies?sort=-id&page=3&limit=2").apply(&items).unwrap();
+        assert_eq!(page.iter().map(|item| item["id"].as_str().unwrap()).collect::<Vec<_>>(), ["p2", "p1"]);
+        assert!(meta.next_cursor.is_none());
+        
+        // Cursors are tied to their sort order
+        assert!(query(&format!("/proxies?sort=id&cursor={}", cursor)).apply(&items).is_err());
+        assert!(ListQuery::from_request(&Request::get("/proxies?sort=-").body(Body::empty()).unwrap()).is_err());
+    }
+    
+    #[test]
+    fn test_admin_rbac() {
+        use hyper::Method;
+        use ferrumgw::admin::rbac::required_role;
+        use ferrumgw::config::data_model::AdminRole;
+        
+        assert!(AdminRole::ReadOnly < AdminRole::Operator && AdminRole::Operator < AdminRole::Admin);
+        assert_eq!("read_only".parse::<AdminRole>().unwrap(), AdminRole::ReadOnly);
+        assert!("root".parse::<AdminRole>().is_err());
+        
+        assert_eq!(required_role(&Method::GET, "/proxies"), AdminRole::ReadOnly);
+        assert_eq!(required_role(&Method::POST, "/config/validate"), AdminRole::ReadOnly);
+        assert_eq!(required_role(&Method::PUT, "/proxies/orders"), AdminRole::Operator);
+        assert_eq!(required_role(&Method::DELETE, "/consumers/alice/credentials/basicauth"), AdminRole::Operator);
+        assert_eq!(required_role(&Method::POST, "/config/import"), AdminRole::Admin);
+        assert_eq!(required_role(&Method::POST, "/config/versions/4/rollback"), AdminRole::Admin);
+        assert_eq!(required_role(&Method::POST, "/nodes/dp-1/resync"), AdminRole::Admin);
+        // Even reading users needs an admin
+        assert_eq!(required_role(&Method::GET, "/users"), AdminRole::Admin);
+        assert_eq!(required_role(&Method::GET, "/users/alice"), AdminRole::Admin);
+    }
+    
+    #[test]
+    fn test_admin_token_settings() {
+        use std::time::Duration;
+        use hyper::Method;
+        use ferrumgw::admin::rbac::required_role;
+        use ferrumgw::config::data_model::AdminRole;
+        
+        let mut env_vars = HashMap::new();
+        env_vars.insert("FERRUM_MODE".to_string(), "dp".to_string());
+        env_vars.insert("FERRUM_DP_CP_GRPC_URL".to_string(), "http://cp-host:50051".to_string());
+        env_vars.insert("FERRUM_DP_GRPC_AUTH_TOKEN".to_string(), "jwt_token".to_string());
+        
+        let config = EnvConfig::from_map(&env_vars).unwrap();
+        assert_eq!(config.admin_token_ttl, Duration::from_secs(900));
+        assert_eq!(config.admin_refresh_token_ttl, Duration::from_secs(86400));
+        
+        env_vars.insert("FERRUM_ADMIN_TOKEN_TTL".to_string(), "0".to_string());
+        assert!(EnvConfig::from_map(&env_vars).is_err());
+        
+        // Refresh tokens cannot expire before the access tokens they renew
+        env_vars.insert("FERRUM_ADMIN_TOKEN_TTL".to_string(), "3600".to_string());
+        env_vars.insert("FERRUM_ADMIN_REFRESH_TOKEN_TTL".to_string(), "600".to_string());
+        assert!(EnvConfig::from_map(&env_vars).is_err());
+        
+        // Every role may revoke its own tokens
+        assert_eq!(required_role(&Method::POST, "/auth/revoke"), AdminRole::ReadOnly);
+    }
+    
+    #[test]
+    fn test_admin_openapi_document() {
+        use ferrumgw::admin::openapi::{self, ROUTES};
+        
+        let document = openapi::document();
+        assert_eq!(document["openapi"], "3.0.3");
+        for route in ROUTES {
+            let operation = &document["paths"][route.path][route.method.to_lowercase()];
+            assert!(operation.is_object(), "{} {} is missing", route.method, route.path);
+        }
+        
+        let get_proxy = &document["paths"]["/proxies/{proxy_id}"]["get"];
+        assert_eq!(get_proxy["parameters"][0]["name"], "proxy_id");
+        assert_eq!(get_proxy["responses"]["200"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/Proxy");
+        assert!(document["components"]["schemas"]["Proxy"]["properties"]["listen_path"].is_object());
+        
+        // Roles follow the RBAC rules, and logging in needs no token
+        assert_eq!(document["paths"]["/users"]["post"]["x-required-role"], "admin");
+        assert_eq!(document["paths"]["/proxies"]["post"]["x-required-role"], "operator");
+        assert_eq!(document["paths"]["/auth/login"]["post"]["security"], serde_json::json!([]));
+        assert!(serde_json::from_str::<serde_json::Value>(openapi::document_json()).is_ok());
+    }
+    
+    #[test]
+    fn test_admin_change_events() {
+        use ferrumgw::admin::audit::{self, AuditTarget};
+        use ferrumgw::admin::events::{self, ChangeEvents};
+        use hyper::Method;
+        
+        let events = ChangeEvents::new();
+        let mut receiver = events.subscribe();
+        
+        // A creation's ID comes from the entity in the response
+        let target = AuditTarget::from_request(&Method::POST, "/proxies").unwrap();
+        let entity_id = audit::entity_id(&target, br#"{"id":"orders","listen_path":"/orders"}"#);
+        events.publish(&target, entity_id, "alice");
+        let target = AuditTarget::from_request(&Method::DELETE, "/consumers/bob").unwrap();
+        events.publish(&target, audit::entity_id(&target, b""), "alice");
+        
+        let created = receiver.try_recv().unwrap();
+        assert_eq!((created.id, created.event, created.entity_type), (1, "created", "proxy"));
+        assert_eq!(created.entity_id.as_deref(), Some("orders"));
+        let deleted = receiver.try_recv().unwrap();
+        assert_eq!((deleted.id, deleted.event, deleted.entity_type), (2, "deleted", "consumer"));
+        assert_eq!(deleted.entity_id.as_deref(), Some("bob"));
+        
+        let message = events::sse_message(&deleted);
+        assert!(message.starts_with("id: 2\nevent: deleted\ndata: {"));
+        assert!(message.ends_with("}\n\n"));
+    }
+    
+    #[test]
+    fn test_list_query_fields_and_links() {
+        use ferrumgw::admin::pagination::ListQuery;
+        use hyper::{Body, Request};
+        
+        let items: Vec<serde_json::Value> = (1..=5)
+            .map(|i| serde_json::json!({
+                "id": format!("p{}", i),
+                "backend": { "host": "example.com", "port": 8000 + i },
+                "plugins": [{ "plugin_config_id": if i % 2 == 0 { "auth" } else { "limits" } }],
+            }))
+            .collect();
+        let query = |uri: &str| ListQuery::from_request(&Request::get(uri).body(Body::empty()).unwrap()).unwrap();
+        
+        // Filters reach into arrays of objects, and only the selected fields are returned
+        let (page, meta) = query("/proxies?plugins.plugin_config_id=limits&fields=id,backend.port&offset=1&limit=1").apply(&items).unwrap();
+        assert_eq!(page, [serde_json::json!({ "id": "p3", "backend": { "port": 8003 } })]);
+        assert_eq!((meta.total, meta.page), (3, 2));
+        
+        // The next link keeps the filters and fields, and continues after the page
+        let next = meta.next.unwrap();
+        assert!(next.starts_with("/proxies?plugins.plugin_config_id=limits&fields=id%2Cbackend.port&limit=1&cursor="));
+        let (page, meta) = query(&next).apply(&items).unwrap();
+        assert_eq!(page[0]["id"], "p5");
+        assert!(meta.next.is_none());
+        
+        assert!(ListQuery::from_request(&Request::get("/proxies?fields=,").body(Body::empty()).unwrap()).is_err());
+    }
+    
+    #[test]
+    fn test_admin_cors_settings() {
+        use std::time::Duration;
+        
+        let mut env_vars = HashMap::new();
+        env_vars.insert("FERRUM_MODE".to_string(), "dp".to_string());
+        env_vars.insert("FERRUM_DP_CP_GRPC_URL".to_string(), "http://cp-host:50051".to_string());
+        env_vars.insert("FERRUM_DP_GRPC_AUTH_TOKEN".to_string(), "jwt_token".to_string());
+        
+        let config = EnvConfig::from_map(&env_vars).unwrap();
+        assert!(config.admin_cors_allowed_origins.is_empty());
+        assert_eq!(config.admin_cors_max_age, Duration::from_secs(600));
+        assert!(config.admin_ui_path.is_none());
+        
+        env_vars.insert("FERRUM_ADMIN_CORS_ALLOWED_ORIGINS".to_string(), "https://admin.example.com/, http://localhost:5173".to_string());
+        let config = EnvConfig::from_map(&env_vars).unwrap();
+        assert_eq!(config.admin_cors_allowed_origins, vec!["https://admin.example.com", "http://localhost:5173"]);
+        
+        // An origin is a scheme and host, not a bare hostname
+        env_vars.insert("FERRUM_ADMIN_CORS_ALLOWED_ORIGINS".to_string(), "admin.example.com".to_string());
+        assert!(EnvConfig::from_map(&env_vars).is_err());
+    }
+    
+    #[test]
+    fn test_health_readiness() {
+        use ferrumgw::health::{self, Status};
+        use ferrumgw::modes::OperationMode;
+        
+        // Not ready until a configuration has been loaded
+        let report = health::report(OperationMode::DataPlane);
+        assert_eq!(report.status, Status::NotReady);
+        assert!(!report.is_ready());
+        assert!(report.control_plane.is_none());
+        
+        health::record_config_loaded();
+        let report = health::report(OperationMode::DataPlane);
+        assert_eq!(report.status, Status::Ready);
+        assert!(report.config.detail.loaded_at.is_some());
+        
+        // Losing the Control Plane degrades, but the last configuration keeps serving
+        health::record_control_plane(Err("connection refused".to_string()));
+        let report = health::report(OperationMode::DataPlane);
+        assert_eq!(report.status, Status::Degraded);
+        assert!(report.is_ready());
+        let control_plane = report.control_plane.unwrap();
+        assert!(!control_plane.detail.reachable);
+        assert_eq!(control_plane.detail.error.as_deref(), Some("connection refused"));
+        
+        health::record_control_plane(Ok(()));
+        let report = health::report(OperationMode::DataPlane);
+        assert_eq!(report.status, Status::Ready);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["status"], "ready");
+        assert_eq!(json["control_plane"]["reachable"], true);
+        assert!(json["control_plane"].get("error").is_none());
+    }
+    
+    #[test]
+    fn test_admin_problem_details() {
+        use ferrumgw::admin::problem::{FieldError, Problem};
+        use ferrumgw::config::validation;
+        use hyper::StatusCode;
+        
+        let mut config = Configuration::default();
+        config.proxies.push(create_test_proxy("p1", "/one"));
+        let mut proxy = create_test_proxy("p2", "/two");
+        proxy.tags = vec!["billing, team".to_string()];
+        config.proxies.push(proxy);
+        
+        // Only the issues of the entity being written are reported, relative to it
+        let report = validation::check(&config);
+        let errors: Vec<FieldError> = report.errors.iter()
+            .filter_map(|issue| FieldError::from_issue(issue, "proxies[1]"))
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "tags[0]");
+        assert_eq!(errors[0].code, "invalid_tag");
+        assert!(report.errors.iter().all(|issue| FieldError::from_issue(issue, "proxies[0]").is_none()));
+        
+        let problem = Problem::validation("proxy", errors);
+        assert_eq!(problem.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let json = serde_json::to_value(&problem).unwrap();
+        assert_eq!(json["type"], "urn:ferrumgw:problem:validation");
+        assert_eq!(json["detail"], "The proxy has an invalid field");
+        assert_eq!(json["errors"][0]["field"], "tags[0]");
+        
+        // Plain problems have no errors member
+        let problem = Problem::new(StatusCode::CONFLICT, "A proxy with listen_path '/one' already exists");
+        let json = serde_json::to_value(&problem).unwrap();
+        assert_eq!(json["type"], "about:blank");
+        assert_eq!(json["title"], "Conflict");
+        assert_eq!(json["status"], 409);
+        assert!(json.get("errors").is_none());
+        
+        let problem = problem.with_field("listen_path", "duplicate_listen_path");
+        assert_eq!(problem.errors[0].field, "listen_path");
+    }
+    
+    #[test]
+    fn test_api_token_scope() {
+        use hyper::Method;
+        use ferrumgw::admin::rbac::required_role;
+        use ferrumgw::config::data_model::{AdminRole, ApiToken, EntityType, TokenScope};
+        
+        let token: ApiToken = serde_json::from_value(serde_json::json!({
+            "id": "3f2a", "name": "team-a-ci", "username": "alice", "role": "operator",
+            "scope": { "entity_types": ["proxies"], "tags": ["team-a"] },
+            "expires_at": "2030-01-01T00:00:00Z", "created_at": "2026-01-01T00:00:00Z"
+        })).unwrap();
+        assert_eq!(token.role, AdminRole::Operator);
+        assert!(token.scope.allows(EntityType::Proxies));
+        assert!(!token.scope.allows(EntityType::Consumers));
+        assert!(!token.scope.is_unrestricted());
+        
+        // An empty scope reaches every entity and is left out of the JSON form
+        let unrestricted = TokenScope::default();
+        assert!(unrestricted.is_unrestricted() && unrestricted.allows(EntityType::PluginConfigs));
+        assert_eq!(serde_json::to_value(&unrestricted).unwrap(), serde_json::json!({}));
+        
+        // Any user may manage tokens of their own
+        assert_eq!(required_role(&Method::POST, "/tokens"), AdminRole::ReadOnly);
+        assert_eq!(required_role(&Method::DELETE, "/tokens/3f2a"), AdminRole::ReadOnly);
+    }
+    
+    #[test]
+    fn test_proxy_tap() {
+        use std::time::Duration;
+        use ferrumgw::proxy::tap::{self, Capture, CaptureTimings, CapturedBody, TapSettings};
+        
+        let mut headers = hyper::HeaderMap::new();
+        headers.insert("authorization", "Bearer secret".parse().unwrap());
+        headers.insert("accept", "application/json".parse().unwrap());
+        let captured = tap::capture_headers(&headers);
+        assert!(captured.contains(&("authorization".to_string(), "[redacted]".to_string())));
+        assert!(captured.contains(&("accept".to_string(), "application/json".to_string())));
+        
+        // Credentials in the query string are redacted: the auth plugins'
+        // defaults and the query names of the proxy's key_auth configs
+        use ferrumgw::config::data_model::{PluginAssociation, PluginScope};
+        use ferrumgw::config::snapshot::ConfigurationSnapshot;
+        let mut proxy = create_test_proxy("tap-proxy", "/orders");
+        proxy.plugins.push(PluginAssociation { plugin_config_id: "tap-key-auth".to_string(), embedded_config: None });
+        let mut config = Configuration::default();
+        config.plugin_configs.push(PluginConfig {
+            id: "tap-key-auth".to_string(),
+            plugin_name: "key_auth".to_string(),
+            workspace: "default".to_string(),
+            version: 1,
+            tags: Vec::new(),
+            config: json!({"query_name": "key"}),
+            scope: PluginScope::Proxy,
+            proxy_id: Some("tap-proxy".to_string()),
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        });
+        config.proxies.push(proxy.clone());
+        let snapshot = ConfigurationSnapshot::build(config);
+        let params = tap::credential_query_params(&snapshot, &proxy);
+        assert_eq!(params, vec!["key".to_string()]);
+        let uri: hyper::Uri = "/orders?key=k1&page=2&apikey=k2&access_token=t".parse().unwrap();
+        assert_eq!(tap::capture_uri(&uri, &params), "/orders?key=[redacted]&page=2&apikey=[redacted]&access_token=[redacted]");
+        assert_eq!(tap::capture_uri(&"/orders?page=2".parse().unwrap(), &params), "/orders?page=2");
+        assert_eq!(tap::capture_uri(&"/orders".parse().unwrap(), &params), "/orders");
+        
+        let body = CapturedBody::new(b"hello world", Some(11), 5);
+        assert_eq!(body.text, "hello");
+        assert!(body.truncated);
+        
+        // A tap claims only its next `count` requests
+        assert!(tap::claim("tap-test").is_none());
+        tap::start("tap-test", TapSettings { count: 1, max_body_bytes: 0, ttl: Duration::from_secs(60) });
+        let slot = tap::claim("tap-test").expect("the tap is open");
+        assert!(tap::claim("tap-test").is_none());
+        
+        let capture = |request_id: &str| Capture {
+            captured_at: chrono::Utc::now(),
+            request_id: request_id.to_string(),
+            client_addr: "127.0.0.1:4000".parse().unwrap(),
+            method: "GET".to_string(),
+            uri: "/orders".to_string(),
+            request_headers: captured.clone(),
+            request_body: None,
+            status: 200,
+            response_headers: Vec::new(),
+            response_body: None,
+            consumer_id: None,
+            timings: CaptureTimings::default(),
+        };
+        tap::record(slot, capture("req-1"));
+        assert_eq!(tap::get("tap-test").unwrap().captures.len(), 1);
+        
+        assert!(tap::stop("tap-test"));
+        assert!(tap::get("tap-test").is_none());
+        
+        // Captures past the buffer size push out the oldest
+        tap::start("tap-ring", TapSettings { count: tap::BUFFER_SIZE + 2, max_body_bytes: 0, ttl: Duration::from_secs(60) });
+        for i in 0..tap::BUFFER_SIZE + 2 {
+            let slot = tap::claim("tap-ring").expect("the tap is open");
+            tap::record(slot, capture(&format!("req-{}", i)));
+        }
+        let ring = tap::get("tap-ring").unwrap();
+        assert_eq!(ring.captures.len(), tap::BUFFER_SIZE);
+        assert_eq!(ring.dropped, 2);
+        assert_eq!(ring.captures.front().unwrap().request_id, "req-2");
+        assert_eq!(ring.captures.back().unwrap().request_id, format!("req-{}", tap::BUFFER_SIZE + 1));
+        assert!(tap::stop("tap-ring"));
+    }
 }
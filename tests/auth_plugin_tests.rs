@@ -9,6 +9,7 @@ mod auth_plugin_tests {
     use serde_json::json;
     
     use ferrumgw::config::data_model::{Configuration, Proxy, Consumer, PluginConfig, Protocol, AuthMode};
+    use ferrumgw::config::consumer_index::ConsumerIndex;
     use ferrumgw::proxy::handler::RequestContext;
     use ferrumgw::plugins::{Plugin, PluginManager};
     use ferrumgw::plugins::jwt_auth::{JwtAuthPlugin, JwtAuthConfig};
@@ -54,6 +55,9 @@ mod auth_plugin_tests {
         let consumer = Consumer {
             id: "consumer1".to_string(),
             username: "testuser".to_string(),
+            workspace: "default".to_string(),
+            version: 1,
+            tags: Vec::new(),
             custom_id: Some("custom1".to_string()),
             credentials,
             created_at: Utc::now(),
@@ -64,6 +68,8 @@ mod auth_plugin_tests {
             proxies: Vec::new(),
             consumers: vec![consumer],
             plugin_configs: Vec::new(),
+            certificates: Vec::new(),
+            snis: Vec::new(),
             last_updated_at: Utc::now(),
         }
     }
@@ -367,4 +373,74 @@ mod auth_plugin_tests {
         assert!(!ctx.authenticated);
         assert!(ctx.consumer.is_none());
     }
+    
+    #[test]
+    fn test_consumer_index_lookups() {
+        let mut config = create_test_config();
+        
+        // A second consumer with a hashed API key and no custom_id
+        let mut credentials = HashMap::new();
+        credentials.insert("api_keys".to_string(), json!(["$2b$12$abcdefghijklmnopqrstuv"]));
+        config.consumers.push(Consumer {
+            id: "consumer2".to_string(),
+            username: "hasheduser".to_string(),
+            workspace: "default".to_string(),
+            version: 1,
+            tags: Vec::new(),
+            custom_id: None,
+            credentials,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        });
+        
+        let index = ConsumerIndex::build(&config.consumers);
+        assert_eq!(index.len(), 2);
+        
+        assert_eq!(index.by_id("consumer1").unwrap().username, "testuser");
+        assert_eq!(index.by_username("hasheduser").unwrap().id, "consumer2");
+        assert_eq!(index.by_custom_id("custom1").unwrap().id, "consumer1");
+        assert!(index.by_custom_id("missing").is_none());
+        
+        // Plaintext keys are indexed directly, hashed keys are kept for verification
+        assert_eq!(index.by_api_key("test-api-key").unwrap().id, "consumer1");
+        assert!(index.by_api_key("$2b$12$abcdefghijklmnopqrstuv").is_none());
+        let hashed: Vec<_> = index.hashed_api_keys().map(|(_, c)| c.id.as_str()).collect();
+        assert_eq!(hashed, vec!["consumer2"]);
+        
+        // Identifiers resolve by ID, then custom_id, then username
+        assert_eq!(index.find_by_identifier("consumer2").unwrap().id, "consumer2");
+        assert_eq!(index.find_by_identifier("custom1").unwrap().id, "consumer1");
+        assert_eq!(index.find_by_identifier("testuser").unwrap().id, "consumer1");
+        assert!(index.find_by_identifier("nobody").is_none());
+    }
+    
+    #[test]
+    fn test_credential_hashing() {
+        use ferrumgw::config::credentials::{self, PasswordHashAlgorithm};
+        
+        let mut config = create_test_config();
+        let consumer = &mut config.consumers[0];
+        consumer.credentials.insert("oauth2".to_string(), json!({ "client_secret": "s3cret" }));
+        
+        assert!(credentials::hash_consumer(consumer, PasswordHashAlgorithm::Argon2).unwrap());
+        
+        // API keys become digests that can still be looked up directly
+        let key = consumer.credentials["keyauth"]["key"].as_str().unwrap().to_string();
+        assert_eq!(key, credentials::hash_api_key("test-api-key"));
+        assert!(credentials::verify_api_key("test-api-key", &key));
+        
+        // Secrets are salted hashes; the existing bcrypt password is kept
+        let secret = consumer.credentials["oauth2"]["client_secret"].as_str().unwrap().to_string();
+        assert!(secret.starts_with("$argon2"));
+        assert!(credentials::verify_password("s3cret", &secret));
+        assert!(!credentials::verify_password("wrong", &secret));
+        assert!(consumer.credentials["basicauth"]["password"].as_str().unwrap().starts_with("$2y$"));
+        
+        // Hashing again changes nothing
+        assert!(!credentials::hash_consumer(consumer, PasswordHashAlgorithm::Bcrypt).unwrap());
+        
+        let index = ConsumerIndex::build(&config.consumers);
+        assert_eq!(index.by_api_key("test-api-key").unwrap().id, "consumer1");
+        assert!(index.by_api_key(&key).is_none());
+    }
 }
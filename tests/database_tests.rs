@@ -55,6 +55,10 @@ mod database_tests {
             let proxy = Proxy {
                 id: "test1".to_string(),
                 name: Some("Test Proxy".to_string()),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
+                enabled: true,
                 listen_path: "/api/test".to_string(),
                 backend_protocol: Protocol::Http,
                 backend_host: "example.com".to_string(),
@@ -71,8 +75,18 @@ mod database_tests {
                 backend_tls_server_ca_cert_path: None,
                 dns_override: None,
                 dns_cache_ttl_seconds: None,
+                dns_resolvers: Vec::new(),
+                backend_discovery: None,
                 auth_mode: AuthMode::Single,
                 plugins: Vec::new(),
+                error_templates: HashMap::new(),
+                log_sampling: None,
+                api_versioning: None,
+                max_requests_per_second: None,
+                backend_keepalive: None,
+                backend_header_case: None,
+                upstream_host: None,
+                egress_proxy: None,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             };
@@ -140,6 +154,9 @@ mod database_tests {
             let consumer = Consumer {
                 id: "consumer1".to_string(),
                 username: "testuser".to_string(),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
                 custom_id: Some("custom1".to_string()),
                 credentials,
                 created_at: Utc::now(),
@@ -206,6 +223,10 @@ mod database_tests {
             let proxy = Proxy {
                 id: "test-proxy".to_string(),
                 name: Some("Test Proxy".to_string()),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
+                enabled: true,
                 listen_path: "/api/test".to_string(),
                 backend_protocol: Protocol::Http,
                 backend_host: "example.com".to_string(),
@@ -222,8 +243,18 @@ mod database_tests {
                 backend_tls_server_ca_cert_path: None,
                 dns_override: None,
                 dns_cache_ttl_seconds: None,
+                dns_resolvers: Vec::new(),
+                backend_discovery: None,
                 auth_mode: AuthMode::Single,
                 plugins: Vec::new(),
+                error_templates: HashMap::new(),
+                log_sampling: None,
+                api_versioning: None,
+                max_requests_per_second: None,
+                backend_keepalive: None,
+                backend_header_case: None,
+                upstream_host: None,
+                egress_proxy: None,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             };
@@ -234,6 +265,9 @@ mod database_tests {
             let plugin_config = PluginConfig {
                 id: "plugin1".to_string(),
                 plugin_name: "key_auth".to_string(),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
                 config: serde_json::json!({
                     "key_location": "header",
                     "header_name": "X-API-Key"
@@ -303,6 +337,10 @@ mod database_tests {
             let proxy1 = Proxy {
                 id: "test1".to_string(),
                 name: Some("Test Proxy 1".to_string()),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
+                enabled: true,
                 listen_path: "/api/test".to_string(),
                 backend_protocol: Protocol::Http,
                 backend_host: "example.com".to_string(),
@@ -319,8 +357,18 @@ mod database_tests {
                 backend_tls_server_ca_cert_path: None,
                 dns_override: None,
                 dns_cache_ttl_seconds: None,
+                dns_resolvers: Vec::new(),
+                backend_discovery: None,
                 auth_mode: AuthMode::Single,
                 plugins: Vec::new(),
+                error_templates: HashMap::new(),
+                log_sampling: None,
+                api_versioning: None,
+                max_requests_per_second: None,
+                backend_keepalive: None,
+                backend_header_case: None,
+                upstream_host: None,
+                egress_proxy: None,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             };
@@ -331,6 +379,10 @@ mod database_tests {
             let proxy2 = Proxy {
                 id: "test2".to_string(),
                 name: Some("Test Proxy 2".to_string()),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
+                enabled: true,
                 listen_path: "/api/test".to_string(), // Same as proxy1
                 backend_protocol: Protocol::Http,
                 backend_host: "other.example.com".to_string(),
@@ -347,8 +399,18 @@ mod database_tests {
                 backend_tls_server_ca_cert_path: None,
                 dns_override: None,
                 dns_cache_ttl_seconds: None,
+                dns_resolvers: Vec::new(),
+                backend_discovery: None,
                 auth_mode: AuthMode::Single,
                 plugins: Vec::new(),
+                error_templates: HashMap::new(),
+                log_sampling: None,
+                api_versioning: None,
+                max_requests_per_second: None,
+                backend_keepalive: None,
+                backend_header_case: None,
+                upstream_host: None,
+                egress_proxy: None,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             };
@@ -362,6 +424,9 @@ mod database_tests {
             let consumer1 = Consumer {
                 id: "c1".to_string(),
                 username: "testuser".to_string(),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
                 custom_id: None,
                 credentials: HashMap::new(),
                 created_at: Utc::now(),
@@ -374,6 +439,9 @@ mod database_tests {
             let consumer2 = Consumer {
                 id: "c2".to_string(),
                 username: "testuser".to_string(), // Same as consumer1
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
                 custom_id: None,
                 credentials: HashMap::new(),
                 created_at: Utc::now(),
@@ -436,6 +504,10 @@ mod database_tests {
             let proxy = Proxy {
                 id: "test1".to_string(),
                 name: Some("Test Proxy".to_string()),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
+                enabled: true,
                 listen_path: "/api/test".to_string(),
                 backend_protocol: Protocol::Http,
                 backend_host: "example.com".to_string(),
@@ -452,8 +524,18 @@ mod database_tests {
                 backend_tls_server_ca_cert_path: None,
                 dns_override: None,
                 dns_cache_ttl_seconds: None,
+                dns_resolvers: Vec::new(),
+                backend_discovery: None,
                 auth_mode: AuthMode::Single,
                 plugins: Vec::new(),
+                error_templates: HashMap::new(),
+                log_sampling: None,
+                api_versioning: None,
+                max_requests_per_second: None,
+                backend_keepalive: None,
+                backend_header_case: None,
+                upstream_host: None,
+                egress_proxy: None,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             };
@@ -547,6 +629,10 @@ mod database_tests {
             let proxy = Proxy {
                 id: "test1".to_string(),
                 name: Some("Test Proxy".to_string()),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
+                enabled: true,
                 listen_path: "/api/test".to_string(),
                 backend_protocol: Protocol::Http,
                 backend_host: "example.com".to_string(),
@@ -563,8 +649,18 @@ mod database_tests {
                 backend_tls_server_ca_cert_path: None,
                 dns_override: None,
                 dns_cache_ttl_seconds: None,
+                dns_resolvers: Vec::new(),
+                backend_discovery: None,
                 auth_mode: AuthMode::Single,
                 plugins: Vec::new(),
+                error_templates: HashMap::new(),
+                log_sampling: None,
+                api_versioning: None,
+                max_requests_per_second: None,
+                backend_keepalive: None,
+                backend_header_case: None,
+                upstream_host: None,
+                egress_proxy: None,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             };
@@ -583,4 +679,109 @@ mod database_tests {
             // Additional SQLite-specific tests as needed
         }
     }
+    
+    #[tokio::test]
+    async fn test_sqlite_schema_migrations() {
+        use ferrumgw::database::DatabaseType;
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_url = format!("sqlite://{}?mode=rwc", temp_dir.path().join("ferrum.db").display());
+        let client = ferrumgw::DatabaseClient::new(DatabaseType::SQLite, &db_url).await.unwrap();
+        
+        // A fresh database has every migration pending and is refused at startup
+        let status = client.schema_status().await.unwrap();
+        assert_eq!(status.current_version, None);
+        assert_eq!(status.pending().count(), status.migrations.len());
+        assert!(client.check_schema(false).await.is_err());
+        
+        let status = client.migrate().await.unwrap();
+        assert!(status.is_up_to_date());
+        assert_eq!(status.current_version, Some(status.latest_version));
+        assert!(client.check_schema(false).await.is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_sqlite_tombstone_retention() {
+        use ferrumgw::database::{DatabaseOptions, DatabaseType};
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_url = format!("sqlite://{}?mode=rwc", temp_dir.path().join("ferrum.db").display());
+        let options = DatabaseOptions {
+            tombstone_retention: std::time::Duration::from_secs(24 * 60 * 60),
+            ..DatabaseOptions::default()
+        };
+        let client = ferrumgw::DatabaseClient::with_options(DatabaseType::SQLite, &db_url, &options).await.unwrap();
+        client.migrate().await.unwrap();
+        
+        assert_eq!(client.purge_tombstones().await.unwrap(), 0);
+        
+        // A delta reaching past the retention window could miss purged deletions
+        let horizon = client.tombstone_horizon().unwrap();
+        assert!(client.load_configuration_delta(horizon - chrono::Duration::hours(1)).await.is_err());
+        assert!(client.load_configuration_delta(Utc::now() - chrono::Duration::hours(1)).await.is_ok());
+    }
+    
+    #[test]
+    fn test_circuit_breaker_and_retry_backoff() {
+        use std::time::Duration;
+        use ferrumgw::database::resilience::{CircuitBreaker, RetryPolicy};
+        
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(50));
+        breaker.record_failure();
+        assert!(!breaker.is_open());
+        assert!(breaker.allow());
+        
+        // Opens at the threshold and fails reads fast until the cooldown ends
+        breaker.record_failure();
+        assert!(breaker.is_open());
+        assert!(!breaker.allow());
+        
+        // One trial read after the cooldown; a success closes the circuit
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breaker.allow());
+        assert!(!breaker.allow());
+        breaker.record_success();
+        assert!(!breaker.is_open());
+        assert!(breaker.allow());
+        
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+        };
+        assert!(policy.backoff(1) <= Duration::from_millis(100));
+        assert!(policy.backoff(2) <= Duration::from_millis(200));
+        assert!(policy.backoff(10) <= Duration::from_millis(300));
+    }
+    
+    #[test]
+    fn test_mongo_document_conversion() {
+        use ferrumgw::database::mongo::{from_document, to_document};
+        
+        let created_at = chrono::DateTime::parse_from_rfc3339("2024-01-02T03:04:05.678Z").unwrap().with_timezone(&Utc);
+        let consumer = Consumer {
+            id: "consumer-1".to_string(),
+            username: "alice".to_string(),
+            workspace: "default".to_string(),
+            version: 3,
+            tags: vec!["team-a".to_string()],
+            custom_id: None,
+            credentials: HashMap::from([("keyauth".to_string(), serde_json::json!({ "key": "sha256:abc" }))]),
+            created_at,
+            updated_at: created_at,
+        };
+        
+        // The ID is the document key and timestamps are dates, so deltas can query them
+        let document = to_document(&consumer).unwrap();
+        assert_eq!(document.get_str("_id").unwrap(), "consumer-1");
+        assert!(!document.contains_key("id"));
+        assert_eq!(document.get_datetime("updated_at").unwrap().to_chrono(), created_at);
+        
+        let stored: Consumer = from_document(document).unwrap();
+        assert_eq!(stored.id, consumer.id);
+        assert_eq!(stored.version, 3);
+        assert_eq!(stored.tags, consumer.tags);
+        assert_eq!(stored.credentials, consumer.credentials);
+        assert_eq!(stored.created_at, created_at);
+    }
 }
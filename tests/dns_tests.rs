@@ -164,4 +164,165 @@ mod dns_tests {
             );
         }
     }
+    
+    #[test]
+    fn test_happy_eyeballs_connect_order() {
+        use std::net::IpAddr;
+        use ferrumgw::dns::resolver::connect_order;
+        
+        let ip = |value: &str| value.parse::<IpAddr>().unwrap();
+        let ordered = connect_order(vec![ip("10.0.0.1"), ip("2001:db8::1"), ip("10.0.0.2"), ip("2001:db8::2")]);
+        assert_eq!(ordered, vec![ip("2001:db8::1"), ip("2001:db8::2"), ip("10.0.0.1"), ip("10.0.0.2")]);
+        
+        // Single-stack backends keep their order
+        assert_eq!(connect_order(vec![ip("10.0.0.2"), ip("10.0.0.1")]), vec![ip("10.0.0.2"), ip("10.0.0.1")]);
+        assert_eq!(connect_order(vec![ip("::1")]), vec![ip("::1")]);
+    }
+    
+    #[tokio::test]
+    async fn test_dns_negative_caching() {
+        use std::time::{Duration, Instant};
+        use ferrumgw::dns::DnsCache;
+        use ferrumgw::dns::upstream::parse_resolver;
+        
+        // Nothing answers on this port, so every lookup fails
+        let resolver = parse_resolver("udp://127.0.0.1:9?timeout_ms=200").unwrap();
+        let dns_cache = DnsCache::new(300, HashMap::new())
+            .with_negative_ttl(Duration::from_secs(30))
+            .with_resolvers(&[resolver])
+            .unwrap();
+        
+        assert!(dns_cache.lookup("backend.example").await.is_err());
+        assert!(dns_cache.failing_hosts().contains_key("backend.example"));
+        
+        // The failure is answered from the cache without asking the resolver again
+        let started = Instant::now();
+        let cached = dns_cache.lookup("backend.example").await.unwrap_err();
+        assert!(format!("{:#}", cached).contains("cached"));
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+    
+    /// A DNS server answering every A query with 10.0.0.7 and the given TTL
+    async fn spawn_dns_server(ttl: u32) -> (std::net::SocketAddr, tokio::task::JoinHandle<()>) {
+        use hickory_resolver::proto::op::{Message, MessageType};
+        use hickory_resolver::proto::rr::{RData, Record, RecordType};
+        use hickory_resolver::proto::rr::rdata::A;
+        
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let query = Message::from_vec(&buf[..len]).unwrap();
+                let mut response = Message::new();
+                response.set_id(query.id()).set_message_type(MessageType::Response).set_recursion_available(true);
+                for question in query.queries() {
+                    response.add_query(question.clone());
+                    if question.query_type() == RecordType::A {
+                        response.add_answer(Record::from_rdata(question.name().clone(), ttl, RData::A(A::new(10, 0, 0, 7))));
+                    }
+                }
+                let _ = socket.send_to(&response.to_vec().unwrap(), peer).await;
+            }
+        });
+        (addr, server)
+    }
+    
+    #[tokio::test]
+    async fn test_dns_record_ttls() {
+        use std::time::Duration;
+        use ferrumgw::dns::DnsCache;
+        use ferrumgw::dns::upstream::parse_resolver;
+        
+        // Records with a one-second TTL
+        let (addr, _server) = spawn_dns_server(1).await;
+        let resolver = parse_resolver(&format!("udp://{}", addr)).unwrap();
+        let dns_cache = DnsCache::new(300, HashMap::new())
+            .with_min_ttl(Duration::ZERO)
+            .with_resolvers(&[resolver])
+            .unwrap();
+        assert_eq!(dns_cache.lookup("backend.example").await.unwrap(), "10.0.0.7");
+        
+        // The entry expires with its record rather than after the 300 second cap
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert_eq!(dns_cache.stats().expired_entries, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_dns_serve_stale() {
+        use std::time::Duration;
+        use ferrumgw::dns::DnsCache;
+        use ferrumgw::dns::upstream::parse_resolver;
+        
+        let (addr, server) = spawn_dns_server(1).await;
+        let resolver = parse_resolver(&format!("udp://{}?timeout_ms=200", addr)).unwrap();
+        let dns_cache = DnsCache::new(300, HashMap::new())
+            .with_min_ttl(Duration::ZERO)
+            .with_stale_ttl(Duration::from_secs(60))
+            .with_resolvers(&[resolver])
+            .unwrap();
+        assert_eq!(dns_cache.lookup("backend.example").await.unwrap(), "10.0.0.7");
+        
+        // The resolver goes away and the entry expires: its addresses are still served
+        server.abort();
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert_eq!(dns_cache.lookup("backend.example").await.unwrap(), "10.0.0.7");
+        assert_eq!(dns_cache.lookup("backend.example").await.unwrap(), "10.0.0.7");
+        assert_eq!(dns_cache.stats().stale_serves, 2);
+        assert!(dns_cache.failing_hosts().contains_key("backend.example"));
+        
+        // Without a stale window the failure reaches the request
+        let resolver = parse_resolver(&format!("udp://{}?timeout_ms=200", addr)).unwrap();
+        let dns_cache = DnsCache::new(300, HashMap::new()).with_resolvers(&[resolver]).unwrap();
+        assert!(dns_cache.lookup("backend.example").await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_dns_hosts_overrides() {
+        use ferrumgw::dns::DnsCache;
+        use ferrumgw::dns::hosts::{parse_hosts, parse_overrides};
+        
+        let mut overrides = parse_hosts("# split horizon\n10.0.1.10 orders.internal\n10.0.1.11 orders.internal payments.internal # both\n\n").unwrap();
+        assert_eq!(overrides["orders.internal"], vec!["10.0.1.10", "10.0.1.11"]);
+        assert_eq!(overrides["payments.internal"], vec!["10.0.1.11"]);
+        assert!(parse_hosts("not-an-ip orders.internal").is_err());
+        assert!(parse_hosts("10.0.1.10").is_err());
+        
+        // FERRUM_DNS_OVERRIDES wins over the hosts file and takes single addresses too
+        overrides.extend(parse_overrides(r#"{"payments.internal": "10.0.2.20", "users.internal": ["::1", "10.0.2.21"]}"#).unwrap());
+        assert!(parse_overrides(r#"{"users.internal": []}"#).is_err());
+        
+        let dns_cache = DnsCache::new(300, overrides);
+        assert_eq!(dns_cache.lookup("orders.internal").await.unwrap(), "10.0.1.10");
+        assert_eq!(dns_cache.lookup("orders.internal").await.unwrap(), "10.0.1.11");
+        assert_eq!(dns_cache.lookup("payments.internal").await.unwrap(), "10.0.2.20");
+        assert_eq!(dns_cache.lookup_for_connect("users.internal").await.unwrap(), vec!["::1", "10.0.2.21"]);
+    }
+    
+    #[tokio::test]
+    async fn test_dns_cache_inspection() {
+        use ferrumgw::dns::DnsCache;
+        use ferrumgw::dns::cache::DnsEntryKind;
+        use ferrumgw::dns::upstream::parse_resolver;
+        
+        let resolver = parse_resolver("udp://127.0.0.1:9?timeout_ms=200").unwrap();
+        let overrides = HashMap::from([("orders.internal".to_string(), vec!["10.0.1.10".to_string()])]);
+        let dns_cache = DnsCache::new(300, overrides).with_resolvers(&[resolver]).unwrap();
+        
+        assert!(dns_cache.lookup("missing.example").await.is_err());
+        assert!(dns_cache.lookup("missing.example").await.is_err());
+        let stats = dns_cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        
+        let entries = dns_cache.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].kind, DnsEntryKind::Failure);
+        assert!(entries[0].error.is_some());
+        assert_eq!((entries[1].name.as_str(), entries[1].kind), ("orders.internal", DnsEntryKind::Override));
+        
+        // Invalidating a failure lets the next request look the name up again
+        assert!(dns_cache.invalidate("missing.example"));
+        assert!(!dns_cache.invalidate("missing.example"));
+        assert!(dns_cache.failing_hosts().is_empty());
+    }
 }
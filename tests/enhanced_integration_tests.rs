@@ -33,6 +33,10 @@ mod enhanced_integration_tests {
         Proxy {
             id: id.to_string(),
             name: Some(format!("Test Proxy {}", id)),
+            workspace: "default".to_string(),
+            version: 1,
+            tags: Vec::new(),
+            enabled: true,
             listen_path: listen_path.to_string(),
             backend_protocol: Protocol::Http,
             backend_host: backend_host.to_string(),
@@ -49,8 +53,18 @@ mod enhanced_integration_tests {
             backend_tls_server_ca_cert_path: None,
             dns_override: None,
             dns_cache_ttl_seconds: None,
+            dns_resolvers: Vec::new(),
+            backend_discovery: None,
             auth_mode: AuthMode::Single,
             plugins,
+            error_templates: HashMap::new(),
+            log_sampling: None,
+            api_versioning: None,
+            max_requests_per_second: None,
+            backend_keepalive: None,
+            backend_header_case: None,
+            upstream_host: None,
+            egress_proxy: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -85,6 +99,9 @@ mod enhanced_integration_tests {
         Consumer {
             id: id.to_string(),
             username: username.to_string(),
+            workspace: "default".to_string(),
+            version: 1,
+            tags: Vec::new(),
             custom_id: Some(format!("custom-{}", id)),
             credentials,
             created_at: Utc::now(),
@@ -99,6 +116,9 @@ mod enhanced_integration_tests {
             PluginConfig {
                 id: "key_auth_config".to_string(),
                 plugin_name: "key_auth".to_string(),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
                 config: json!({
                     "key_location": "header",
                     "header_name": "X-API-Key"
@@ -115,6 +135,9 @@ mod enhanced_integration_tests {
             PluginConfig {
                 id: "rate_limit_config".to_string(),
                 plugin_name: "rate_limiting".to_string(),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
                 config: json!({
                     "limit_by": "ip",
                     "requests_per_second": 5,
@@ -133,6 +156,9 @@ mod enhanced_integration_tests {
             PluginConfig {
                 id: "req_transform_config".to_string(),
                 plugin_name: "request_transformer".to_string(),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
                 config: json!({
                     "add_headers": {
                         "X-Test-Header": "test-value",
@@ -155,6 +181,9 @@ mod enhanced_integration_tests {
             PluginConfig {
                 id: "resp_transform_config".to_string(),
                 plugin_name: "response_transformer".to_string(),
+                workspace: "default".to_string(),
+                version: 1,
+                tags: Vec::new(),
                 config: json!({
                     "add_headers": {
                         "X-Powered-By": "Ferrum Gateway"
@@ -302,6 +331,8 @@ mod enhanced_integration_tests {
             proxies: vec![ws_proxy],
             consumers: vec![],
             plugin_configs: vec![],
+            certificates: Vec::new(),
+            snis: Vec::new(),
             last_updated_at: Utc::now(),
         };
         
@@ -397,6 +428,8 @@ mod enhanced_integration_tests {
             proxies: vec![key_auth_proxy],
             consumers: vec![consumer],
             plugin_configs,
+            certificates: Vec::new(),
+            snis: Vec::new(),
             last_updated_at: Utc::now(),
         };
         
@@ -482,6 +515,8 @@ mod enhanced_integration_tests {
             proxies: vec![transform_proxy],
             consumers: vec![],
             plugin_configs,
+            certificates: Vec::new(),
+            snis: Vec::new(),
             last_updated_at: Utc::now(),
         };
         
@@ -558,6 +593,8 @@ mod enhanced_integration_tests {
             proxies: vec![rate_limited_proxy],
             consumers: vec![],
             plugin_configs,
+            certificates: Vec::new(),
+            snis: Vec::new(),
             last_updated_at: Utc::now(),
         };
         
@@ -0,0 +1,456 @@
+#[cfg(test)]
+mod grpc_tests {
+    use std::collections::HashMap;
+    use chrono::Utc;
+    
+    use ferrumgw::config::data_model::{Configuration, Proxy, Protocol, AuthMode};
+    use ferrumgw::config::env_config::EnvConfig;
+    
+    // Helper function to create a test proxy
+    fn create_test_proxy(id: &str, listen_path: &str) -> Proxy {
+        Proxy {
+            id: id.to_string(),
+            name: Some(format!("Test Proxy {}", id)),
+            workspace: "default".to_string(),
+            version: 1,
+            tags: Vec::new(),
+            enabled: true,
+            listen_path: listen_path.to_string(),
+            backend_protocol: Protocol::Http,
+            backend_host: "example.com".to_string(),
+            backend_port: 80,
+            backend_path: Some("/api".to_string()),
+            strip_listen_path: true,
+            preserve_host_header: false,
+            backend_connect_timeout_ms: 5000,
+            backend_read_timeout_ms: 30000,
+            backend_write_timeout_ms: 30000,
+            backend_tls_client_cert_path: None,
+            backend_tls_client_key_path: None,
+            backend_tls_verify_server_cert: true,
+            backend_tls_server_ca_cert_path: None,
+            dns_override: None,
+            dns_cache_ttl_seconds: None,
+            dns_resolvers: Vec::new(),
+            backend_discovery: None,
+            auth_mode: AuthMode::Single,
+            plugins: Vec::new(),
+            error_templates: HashMap::new(),
+            log_sampling: None,
+            api_versioning: None,
+            max_requests_per_second: None,
+            backend_keepalive: None,
+            backend_header_case: None,
+            upstream_host: None,
+            egress_proxy: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+    
+    #[test]
+    fn test_grpc_config_delta() {
+        use ferrumgw::grpc::delta::{self, SyncError, SyncedSnapshot};
+        use ferrumgw::grpc::proto::ConfigSnapshot;
+        
+        let mut config = Configuration::default();
+        config.proxies = vec![create_test_proxy("p1", "/one"), create_test_proxy("p2", "/two")];
+        let mut sent = ConfigSnapshot::from(&config);
+        let initial = delta::full_update(&mut sent, 1);
+        
+        let mut synced = SyncedSnapshot::default();
+        synced.apply(&initial).unwrap();
+        assert_eq!(synced.version(), 1);
+        
+        // Nothing changed, nothing to send
+        let mut unchanged = ConfigSnapshot::from(&config);
+        assert!(delta::delta_update(&sent, &mut unchanged).is_none());
+        
+        // Only the changed and removed proxies are sent
+        config.proxies[0].backend_port = 8080;
+        config.proxies.remove(1);
+        let mut current = ConfigSnapshot::from(&config);
+        let update = delta::delta_update(&sent, &mut current).unwrap();
+        assert_eq!((update.version, update.base_version), (2, 1));
+        
+        let mut corrupted = update.clone();
+        corrupted.checksum = delta::checksum(&sent);
+        assert!(matches!(synced.apply(&corrupted), Err(SyncError::ChecksumMismatch { .. })));
+        assert_eq!(synced.version(), 1);
+        
+        synced.apply(&update).unwrap();
+        assert_eq!(synced.version(), 2);
+        assert_eq!(delta::checksum(synced.snapshot()), current.checksum);
+        assert_eq!(synced.snapshot().proxies.len(), 1);
+        
+        // A delta on top of a version the data plane does not hold is refused
+        assert_eq!(synced.apply(&update), Err(SyncError::VersionGap { held: 2, base: 1 }));
+    }
+    
+    #[test]
+    fn test_grpc_node_authentication() {
+        use ferrumgw::grpc::security::{NodeAuthenticator, NodeAuthError};
+        use ferrumgw::proxy::client_auth::ClientCertificate;
+        use jsonwebtoken::{encode, EncodingKey, Header};
+        
+        let jwt_for = |node_id: &str| encode(
+            &Header::default(),
+            &serde_json::json!({ "sub": node_id, "exp": Utc::now().timestamp() + 3600 }),
+            &EncodingKey::from_secret(b"grpc-secret"),
+        ).unwrap();
+        let certificate_for = |common_name: &str| ClientCertificate {
+            subject: format!("CN={}, O=Ferrum", common_name),
+            issuer: "CN=Nodes CA".to_string(),
+            serial: "01".to_string(),
+            dns_names: Vec::new(),
+            emails: Vec::new(),
+            uris: Vec::new(),
+            ip_addresses: Vec::new(),
+            fingerprint_sha256: String::new(),
+        };
+        
+        // Without an allowlist any node with a valid JWT for itself is accepted,
+        // but it cannot claim another node's ID
+        let open = NodeAuthenticator::new(Some("grpc-secret".to_string()), HashMap::new(), Vec::new());
+        let bearer = format!("Bearer {}", jwt_for("dp-random"));
+        assert!(open.authenticate("dp-random", Some(&bearer), None).is_ok());
+        assert!(open.authenticate("dp-random", Some(&bearer), Some(&certificate_for("dp-random"))).is_ok());
+        assert!(matches!(
+            open.authenticate("dp-1", Some(&bearer), None),
+            Err(NodeAuthError::InvalidToken(_))
+        ));
+        assert!(matches!(
+            open.authenticate("dp-random", Some(&bearer), Some(&certificate_for("dp-1"))),
+            Err(NodeAuthError::CertificateMismatch { .. })
+        ));
+        assert_eq!(open.authenticate("dp-random", None, None), Err(NodeAuthError::MissingToken));
+        
+        let node_tokens = HashMap::from([("dp-1".to_string(), "s3cr3t".to_string())]);
+        let auth = NodeAuthenticator::new(Some("grpc-secret".to_string()), node_tokens, vec!["dp-2".to_string()]);
+        
+        // Pre-shared token of an allowlisted node, with a certificate naming it
+        assert!(auth.authenticate("dp-1", Some("Bearer s3cr3t"), Some(&certificate_for("dp-1"))).is_ok());
+        assert!(matches!(auth.authenticate("dp-1", Some("Bearer wrong"), None), Err(NodeAuthError::InvalidToken(_))));
+        assert!(matches!(
+            auth.authenticate("dp-1", Some("Bearer s3cr3t"), Some(&certificate_for("dp-2"))),
+            Err(NodeAuthError::CertificateMismatch { .. })
+        ));
+        
+        // A JWT only authenticates the node named in its subject
+        assert!(auth.authenticate("dp-2", Some(&format!("Bearer {}", jwt_for("dp-2"))), None).is_ok());
+        assert!(matches!(
+            auth.authenticate("dp-2", Some(&format!("Bearer {}", jwt_for("dp-1"))), None),
+            Err(NodeAuthError::InvalidToken(_))
+        ));
+        
+        // Unknown nodes are refused whatever they present
+        assert_eq!(
+            auth.authenticate("dp-3", Some(&format!("Bearer {}", jwt_for("dp-3"))), None),
+            Err(NodeAuthError::UnknownNode("dp-3".to_string()))
+        );
+    }
+    
+    #[test]
+    fn test_grpc_staged_rollout() {
+        use std::collections::BTreeMap;
+        use std::time::{Duration, Instant};
+        use ferrumgw::grpc::rollout::{NodeStatus, Rollout, RolloutPolicy, RolloutStep};
+        
+        let policy = RolloutPolicy {
+            canary_group: Some("canary".to_string()),
+            stages: vec![50, 100],
+            stage_interval: Duration::from_secs(60),
+        };
+        let mut nodes: BTreeMap<String, NodeStatus> = BTreeMap::new();
+        nodes.insert("dp-a".to_string(), NodeStatus::new("default", 1));
+        nodes.insert("dp-b".to_string(), NodeStatus::new("default", 1));
+        nodes.insert("dp-c".to_string(), NodeStatus::new("default", 1));
+        nodes.insert("dp-z".to_string(), NodeStatus::new("canary", 1));
+        
+        let start = Instant::now();
+        let mut rollout = Rollout::new(policy, 2, start);
+        
+        // The canary group goes first
+        let selected = rollout.selected(&nodes);
+        assert_eq!(selected.len(), 1);
+        assert!(selected.contains("dp-z"));
+        nodes.get_mut("dp-z").unwrap().version = 2;
+        
+        assert_eq!(rollout.tick(&nodes, start + Duration::from_secs(30)), RolloutStep::Wait);
+        assert_eq!(rollout.tick(&nodes, start + Duration::from_secs(60)), RolloutStep::Advanced);
+        
+        // Half the nodes, counting the canary
+        let selected = rollout.selected(&nodes);
+        assert_eq!(selected.len(), 2);
+        assert!(selected.contains("dp-z") && selected.contains("dp-a"));
+        nodes.get_mut("dp-a").unwrap().version = 2;
+        
+        // A degraded node on the new version halts the rollout
+        let node = nodes.get_mut("dp-a").unwrap();
+        node.reported_version = 2;
+        node.health = "degraded".to_string();
+        assert!(matches!(rollout.tick(&nodes, start + Duration::from_secs(61)), RolloutStep::Halted(_)));
+        assert!(rollout.halted().unwrap().contains("dp-a"));
+        assert_eq!(rollout.selected(&nodes).len(), 2);
+        assert_eq!(rollout.tick(&nodes, start + Duration::from_secs(600)), RolloutStep::Wait);
+    }
+    
+    #[test]
+    fn test_grpc_xds_resources() {
+        use ferrumgw::grpc::proto::envoy::{self, Cluster, DiscoveryRequest, DiscoveryType, RouteConfiguration};
+        use ferrumgw::grpc::xds::{AdsSession, XdsSnapshot, ROUTE_CONFIGURATION_NAME};
+        use prost::Message;
+        
+        let mut config = Configuration::default();
+        config.proxies = vec![create_test_proxy("short", "/api"), create_test_proxy("long", "/api/v2")];
+        config.proxies[1].dns_override = Some("10.0.0.5".to_string());
+        let snapshot = XdsSnapshot::build(&config, 1, "0.0.0.0:10000".parse().unwrap());
+        
+        // One cluster per proxy, resolved by Envoy unless the backend is an IP
+        let clusters: Vec<Cluster> = snapshot.resources(envoy::CLUSTER_TYPE_URL, &[]).iter()
+            .map(|resource| Cluster::decode(resource.value.as_slice()).unwrap())
+            .collect();
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].r#type, Some(DiscoveryType::StrictDns as i32));
+        assert_eq!(clusters[1].r#type, Some(DiscoveryType::Static as i32));
+        
+        // Longest listen path first, with the Host header rewritten
+        let routes = snapshot.resources(envoy::ROUTE_CONFIGURATION_TYPE_URL, &[ROUTE_CONFIGURATION_NAME.to_string()]);
+        let routes = RouteConfiguration::decode(routes[0].value.as_slice()).unwrap();
+        let routes = &routes.virtual_hosts[0].routes;
+        assert_eq!(routes[0].r#match.as_ref().unwrap().prefix.as_deref(), Some("/api/v2"));
+        let action = routes[0].route.as_ref().unwrap();
+        assert_eq!(action.cluster.as_deref(), Some("long"));
+        assert_eq!(action.host_rewrite_literal.as_deref(), Some("example.com"));
+        assert_eq!(action.regex_rewrite.as_ref().unwrap().pattern.as_ref().unwrap().regex, "^/api/v2/?");
+        
+        // Responses are sent on subscription and on new versions, not on acknowledgements
+        let mut session = AdsSession::new();
+        let mut request = DiscoveryRequest { type_url: envoy::CLUSTER_TYPE_URL.to_string(), ..Default::default() };
+        let response = session.on_request(&request, &snapshot).unwrap();
+        assert_eq!((response.version_info.as_str(), response.resources.len()), ("1", 2));
+        
+        request.version_info = response.version_info.clone();
+        request.response_nonce = response.nonce.clone();
+        assert!(session.on_request(&request, &snapshot).is_none());
+        
+        config.proxies.pop();
+        let updated = XdsSnapshot::build(&config, 2, "0.0.0.0:10000".parse().unwrap());
+        let responses = session.on_snapshot(&updated);
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].resources.len(), 1);
+        
+        // A request answering the superseded response is ignored
+        assert!(session.on_request(&request, &updated).is_none());
+    }
+    
+    #[test]
+    fn test_grpc_fleet_metrics() {
+        use std::time::{Duration, Instant};
+        use ferrumgw::grpc::proto::{HealthReport, ProxyMetrics};
+        use ferrumgw::metrics::ProxyTraffic;
+        use ferrumgw::metrics::fleet::FleetMetrics;
+        
+        // p99 is interpolated within the bucket holding the 99th request
+        let total = ProxyTraffic { requests: 100, latency_buckets: vec![(0.1, 50), (0.5, 90), (1.0, 100)], ..Default::default() };
+        assert!((total.latency_quantile(0.99) - 0.95).abs() < 1e-9);
+        let earlier = ProxyTraffic { requests: 50, latency_buckets: vec![(0.1, 50), (0.5, 50), (1.0, 50)], ..Default::default() };
+        let interval = total.since(&earlier);
+        assert_eq!(interval.requests, 50);
+        assert!((interval.latency_quantile(0.5) - 0.35).abs() < 1e-9);
+        
+        let report = |node_id: &str, rps: f64, p99: f64, ok: u64| HealthReport {
+            node_id: node_id.to_string(),
+            status: "healthy".to_string(),
+            interval_seconds: 10.0,
+            proxy_metrics: vec![ProxyMetrics {
+                proxy_id: "orders".to_string(),
+                requests: ok + 10,
+                requests_per_second: rps,
+                p99_latency_ms: p99,
+                status_codes: HashMap::from([("200".to_string(), ok), ("500".to_string(), 10)]),
+            }],
+            ..Default::default()
+        };
+        
+        // Rates and counts add up across nodes, p99 is the worst node's
+        let start = Instant::now();
+        let mut fleet = FleetMetrics::default();
+        fleet.record(&report("dp-a", 10.0, 100.0, 90), start);
+        fleet.record(&report("dp-b", 5.0, 250.0, 40), start + Duration::from_secs(20));
+        let proxies = fleet.proxies(start + Duration::from_secs(20));
+        assert_eq!(proxies.len(), 1);
+        assert_eq!(proxies[0].requests_per_second, 15.0);
+        assert_eq!(proxies[0].p99_latency_ms, 250.0);
+        assert_eq!(proxies[0].status_codes["200"], 130);
+        assert_eq!(proxies[0].status_codes["500"], 20);
+        assert_eq!(fleet.node("dp-b").unwrap().proxies[0].requests, 50);
+        
+        // Nodes that stopped reporting drop out of the aggregate
+        let later = start + Duration::from_secs(40);
+        assert_eq!(fleet.proxies(later)[0].requests_per_second, 5.0);
+        assert_eq!(fleet.node_counts(later).get("stale"), Some(&1));
+        assert_eq!(fleet.node_counts(later).get("healthy"), Some(&1));
+    }
+    
+    #[test]
+    fn test_grpc_rate_limit_sync() {
+        use ferrumgw::grpc::ratelimit::{self, RateLimitAggregator};
+        use ferrumgw::plugins::rate_limiting::cluster::ClusterCounters;
+        
+        // Windows are aligned to the clock, so all nodes count into the same one
+        let now = 1_700_000_010;
+        let (node_a, node_b) = (ClusterCounters::new(), ClusterCounters::new());
+        for _ in 0..3 {
+            node_a.record("orders:ip:10.0.0.1", 60, now);
+        }
+        node_b.record("orders:ip:10.0.0.1", 60, now + 5);
+        
+        let mut aggregator = RateLimitAggregator::new();
+        aggregator.record(&ratelimit::report("dp-a", node_a.take_unreported(now)), now);
+        aggregator.record(&ratelimit::report("dp-b", node_b.take_unreported(now + 5)), now + 5);
+        assert!(node_a.take_unreported(now).is_empty());
+        
+        // Each node receives what the others counted and adds its own requests
+        let update = aggregator.others("dp-a", now + 5);
+        assert_eq!(update.counters.len(), 1);
+        assert_eq!(update.counters[0].count, 1);
+        node_a.apply_remote(&ratelimit::counts(&update.counters), now + 5);
+        assert_eq!(node_a.count("orders:ip:10.0.0.1", 60, now + 5), 4);
+        assert_eq!(aggregator.others("dp-b", now + 5).counters[0].count, 3);
+        
+        // Later reports add up, and counts end with their window
+        node_b.record("orders:ip:10.0.0.1", 60, now + 6);
+        aggregator.record(&ratelimit::report("dp-b", node_b.take_unreported(now + 6)), now + 6);
+        assert_eq!(aggregator.others("dp-a", now + 6).counters[0].count, 2);
+        assert!(aggregator.others("dp-a", now + 60).counters.is_empty());
+        assert_eq!(node_a.count("orders:ip:10.0.0.1", 60, now + 60), 0);
+    }
+    
+    #[test]
+    fn test_grpc_group_pins() {
+        use ferrumgw::grpc::pinning::GroupPins;
+        
+        let pins = GroupPins::new();
+        let mut staged = Configuration::default();
+        staged.proxies.push(create_test_proxy("orders", "/orders"));
+        pins.pin("production", 41, Configuration::default(), "FERRUM_CP_GROUP_PINS");
+        
+        // Promoting another version replaces the group's pin
+        let pinned = pins.pin("production", 42, staged, "admin");
+        assert_eq!(pinned.version_id, 42);
+        assert_eq!(pins.get("production").unwrap().config.proxies.len(), 1);
+        assert!(pins.get("staging").is_none());
+        
+        pins.pin("eu-production", 40, Configuration::default(), "admin");
+        let groups: Vec<_> = pins.list().iter().map(|pin| (pin.group.clone(), pin.version_id)).collect();
+        assert_eq!(groups, vec![("eu-production".to_string(), 40), ("production".to_string(), 42)]);
+        
+        // The configuration stays out of the Admin API responses
+        let json = serde_json::to_value(&*pinned).unwrap();
+        assert_eq!(json["pinned_by"], "admin");
+        assert!(json.get("config").is_none());
+        
+        assert!(pins.unpin("production"));
+        assert!(!pins.unpin("production"));
+        assert_eq!(pins.list().len(), 1);
+    }
+    
+    #[test]
+    fn test_grpc_node_debugger() {
+        use ferrumgw::grpc::debug::{NodeDebugger, HEALTH_HISTORY};
+        use ferrumgw::grpc::proto::HealthReport;
+        
+        let debugger = NodeDebugger::new();
+        let mut following = debugger.subscribe();
+        let report = |node_id: &str, version: u64| HealthReport {
+            node_id: node_id.to_string(),
+            status: "healthy".to_string(),
+            config_version: version,
+            metrics: HashMap::from([("uptime_seconds".to_string(), "30".to_string())]),
+            ..Default::default()
+        };
+        
+        // Only the latest reports of each node are kept, oldest first
+        for version in 1..=HEALTH_HISTORY as u64 + 5 {
+            debugger.record_report(&report("dp-a", version));
+        }
+        debugger.record_report(&report("dp-b", 3));
+        let reports = debugger.health_reports("dp-a");
+        assert_eq!(reports.len(), HEALTH_HISTORY);
+        assert_eq!(reports[0].config_version, 6);
+        assert_eq!(reports[HEALTH_HISTORY - 1].metrics["uptime_seconds"], "30");
+        assert!(debugger.health_reports("dp-c").is_empty());
+        
+        // Followers see every report as it arrives
+        assert_eq!(following.try_recv().unwrap().config_version, 1);
+        
+        // Only one server answers the commands
+        assert!(debugger.take_commands().is_some());
+        assert!(debugger.take_commands().is_none());
+    }
+    
+    #[test]
+    fn test_grpc_connection_settings() {
+        use std::time::Duration;
+        use ferrumgw::grpc::transfer::TransferSettings;
+        
+        let mut env_vars = HashMap::new();
+        env_vars.insert("FERRUM_MODE".to_string(), "dp".to_string());
+        env_vars.insert("FERRUM_DP_CP_GRPC_URL".to_string(), "http://cp-host:50051".to_string());
+        env_vars.insert("FERRUM_DP_GRPC_AUTH_TOKEN".to_string(), "jwt_token".to_string());
+        
+        let settings = TransferSettings::from_env_config(&EnvConfig::from_map(&env_vars).unwrap());
+        assert_eq!(settings.keepalive_interval, Some(Duration::from_secs(30)));
+        assert_eq!(settings.keepalive_timeout, Duration::from_secs(10));
+        assert_eq!(settings.subscriber_buffer, 10);
+        
+        env_vars.insert("FERRUM_GRPC_KEEPALIVE_INTERVAL".to_string(), "0".to_string());
+        env_vars.insert("FERRUM_CP_GRPC_SUBSCRIBER_BUFFER".to_string(), "64".to_string());
+        let settings = TransferSettings::from_env_config(&EnvConfig::from_map(&env_vars).unwrap());
+        assert_eq!(settings.keepalive_interval, None);
+        assert_eq!(settings.subscriber_buffer, 64);
+        
+        env_vars.insert("FERRUM_CP_GRPC_SUBSCRIBER_BUFFER".to_string(), "0".to_string());
+        assert!(EnvConfig::from_map(&env_vars).is_err());
+    }
+    
+    #[test]
+    fn test_grpc_snapshot_chunks() {
+        use ferrumgw::grpc::delta;
+        use ferrumgw::grpc::proto::ConfigSnapshot;
+        use ferrumgw::grpc::transfer::{self, Compression, SnapshotAssembler};
+        
+        let mut config = Configuration::default();
+        config.proxies = (0..500).map(|i| create_test_proxy(&format!("p{}", i), &format!("/api/{}", i))).collect();
+        let mut snapshot = ConfigSnapshot::from(&config);
+        delta::stamp(&mut snapshot, 7);
+        
+        for compression in [Compression::None, Compression::Gzip, Compression::Zstd] {
+            let chunks = transfer::split_snapshot(&snapshot, compression, 4096).unwrap();
+            assert!(chunks.len() > 1);
+            assert!(chunks.iter().all(|chunk| chunk.data.len() <= 4096 && chunk.total == chunks.len() as u32));
+            
+            let mut assembler = SnapshotAssembler::new();
+            let (last, rest) = chunks.split_last().unwrap();
+            for chunk in rest {
+                assert!(assembler.push(chunk.clone()).unwrap().is_none());
+            }
+            assert_eq!(assembler.push(last.clone()).unwrap(), Some(snapshot.clone()));
+        }
+        
+        // zstd makes the repetitive proxy list much smaller
+        let plain = transfer::split_snapshot(&snapshot, Compression::None, 4096).unwrap();
+        let zstd = transfer::split_snapshot(&snapshot, Compression::Zstd, 4096).unwrap();
+        assert!(zstd.len() * 2 < plain.len());
+        
+        // Chunks out of order or from another snapshot are refused
+        let mut assembler = SnapshotAssembler::new();
+        assert!(assembler.push(plain[1].clone()).is_err());
+        assembler.push(plain[0].clone()).unwrap();
+        let mut other = plain[1].clone();
+        other.version = 8;
+        assert!(assembler.push(other).is_err());
+    }
+}
+}
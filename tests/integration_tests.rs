@@ -8,11 +8,11 @@ mod integration_tests {
     use http::{HeaderMap, Method, Request, Response, StatusCode};
     use hyper::{Body, Client, Server};
     use hyper::service::{make_service_fn, service_fn};
-    use tokio::sync::RwLock;
     use tokio::time::timeout;
     use serde_json::json;
     
     use ferrumgw::config::data_model::{Configuration, Proxy, Consumer, PluginConfig, Protocol, AuthMode};
+    use ferrumgw::config::snapshot::ConfigurationSnapshot;
     use ferrumgw::proxy::handler::ProxyHandler;
     use ferrumgw::proxy::router::Router;
     use ferrumgw::plugins::PluginManager;
@@ -22,6 +22,10 @@ mod integration_tests {
         Proxy {
             id: id.to_string(),
             name: Some(format!("Test Proxy {}", id)),
+            workspace: "default".to_string(),
+            version: 1,
+            tags: Vec::new(),
+            enabled: true,
             listen_path: listen_path.to_string(),
             backend_protocol: Protocol::Http,
             backend_host: backend_host.to_string(),
@@ -38,8 +42,18 @@ mod integration_tests {
             backend_tls_server_ca_cert_path: None,
             dns_override: None,
             dns_cache_ttl_seconds: None,
+            dns_resolvers: Vec::new(),
+            backend_discovery: None,
             auth_mode: AuthMode::Single,
             plugins: Vec::new(),
+            error_templates: HashMap::new(),
+            log_sampling: None,
+            api_versioning: None,
+            max_requests_per_second: None,
+            backend_keepalive: None,
+            backend_header_case: None,
+            upstream_host: None,
+            egress_proxy: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -69,6 +83,16 @@ mod integration_tests {
                         .body(Body::from("Internal server error"))
                         .unwrap()
                 },
+                (Method::GET, "/slow") => {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Response::new(Body::from("Too late"))
+                },
+                (Method::POST, "/first-chunk") => {
+                    // Answers with the first chunk, without waiting for the rest of the body
+                    let mut body = req.into_body();
+                    let chunk = hyper::body::HttpBody::data(&mut body).await.unwrap_or(Ok(Default::default()))?;
+                    Response::new(Body::from(chunk))
+                },
                 (Method::POST, "/users") => {
                     // Echo back the request body
                     let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
@@ -127,10 +151,12 @@ mod integration_tests {
             proxies: vec![proxy],
             consumers: Vec::new(),
             plugin_configs: Vec::new(),
+            certificates: Vec::new(),
+            snis: Vec::new(),
             last_updated_at: Utc::now(),
         };
         
-        let config_store = Arc::new(RwLock::new(config));
+        let config_store = ConfigurationSnapshot::shared(config);
         let router = Router::new(config_store.clone());
         let plugin_manager = PluginManager::new();
         
@@ -181,10 +207,12 @@ mod integration_tests {
             proxies: vec![proxy],
             consumers: Vec::new(),
             plugin_configs: Vec::new(),
+            certificates: Vec::new(),
+            snis: Vec::new(),
             last_updated_at: Utc::now(),
         };
         
-        let config_store = Arc::new(RwLock::new(config));
+        let config_store = ConfigurationSnapshot::shared(config);
         let router = Router::new(config_store.clone());
         let plugin_manager = PluginManager::new();
         
@@ -233,10 +261,12 @@ mod integration_tests {
             proxies: vec![proxy],
             consumers: Vec::new(),
             plugin_configs: Vec::new(),
+            certificates: Vec::new(),
+            snis: Vec::new(),
             last_updated_at: Utc::now(),
         };
         
-        let config_store = Arc::new(RwLock::new(config));
+        let config_store = ConfigurationSnapshot::shared(config);
         let router = Router::new(config_store.clone());
         let plugin_manager = PluginManager::new();
         
@@ -278,10 +308,12 @@ mod integration_tests {
             proxies: vec![proxy],
             consumers: Vec::new(),
             plugin_configs: Vec::new(),
+            certificates: Vec::new(),
+            snis: Vec::new(),
             last_updated_at: Utc::now(),
         };
         
-        let config_store = Arc::new(RwLock::new(config));
+        let config_store = ConfigurationSnapshot::shared(config);
         let router = Router::new(config_store.clone());
         let plugin_manager = PluginManager::new();
         
@@ -319,6 +351,127 @@ mod integration_tests {
         assert_eq!(body_str, request_body);
     }
     
+    #[tokio::test]
+    async fn test_embedded_gateway() {
+        let backend_addr = start_mock_backend().await;
+        
+        let gateway = ferrumgw::Gateway::builder()
+            .http_port(0)
+            .proxy(create_test_proxy("test", "/api", "127.0.0.1", backend_addr.port()))
+            .start()
+            .await
+            .unwrap();
+        let port = gateway.http_addr().unwrap().port();
+        assert_ne!(port, 0);
+        
+        let client = Client::new();
+        let uri: hyper::Uri = format!("http://127.0.0.1:{}/api/echo", port).parse().unwrap();
+        let response = client.get(uri).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        // Configuration changes apply without restarting the listeners
+        gateway.update_configuration(Configuration::default()).await.unwrap();
+        let uri: hyper::Uri = format!("http://127.0.0.1:{}/api/echo", port).parse().unwrap();
+        let response = client.get(uri).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        
+        gateway.shutdown();
+    }
+    
+    #[tokio::test]
+    async fn test_error_page_request_id_is_not_injected() {
+        use ferrumgw::config::data_model::ErrorTemplate;
+        
+        let mut env_config = ferrumgw::config::env_config::EnvConfig::default();
+        env_config.error_templates.insert("404".to_string(), ErrorTemplate {
+            status: None,
+            content_type: "text/html".to_string(),
+            body: "<p>Not found ({{request_id}})</p>".to_string(),
+        });
+        let gateway = ferrumgw::Gateway::builder()
+            .env_config(env_config)
+            .http_port(0)
+            .start()
+            .await
+            .unwrap();
+        let port = gateway.http_addr().unwrap().port();
+        
+        // A request ID that would need escaping is replaced by a generated one
+        let request = Request::builder()
+            .uri(format!("http://127.0.0.1:{}/missing", port))
+            .header("x-request-id", "<script>alert(1)</script>")
+            .body(Body::empty())
+            .unwrap();
+        let response = Client::new().request(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let request_id = response.headers()["x-request-id"].to_str().unwrap().to_string();
+        assert!(!request_id.contains('<'));
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(String::from_utf8(body.to_vec()).unwrap(), format!("<p>Not found ({})</p>", request_id));
+        
+        gateway.shutdown();
+    }
+    
+    #[tokio::test]
+    async fn test_request_body_streaming_and_timeouts() {
+        let backend_addr = start_mock_backend().await;
+        
+        let mut env_config = ferrumgw::config::env_config::EnvConfig::default();
+        env_config.client_body_timeout_ms = 200;
+        env_config.request_timeout_ms = 500;
+        env_config.max_body_size_bytes = 16;
+        let gateway = ferrumgw::Gateway::builder()
+            .env_config(env_config)
+            .http_port(0)
+            .proxy(create_test_proxy("test", "/api", "127.0.0.1", backend_addr.port()))
+            .start()
+            .await
+            .unwrap();
+        let port = gateway.http_addr().unwrap().port();
+        let client = Client::new();
+        let post = |path: &str, body: Body| Request::builder()
+            .method(Method::POST)
+            .uri(format!("http://127.0.0.1:{}/api{}", port, path))
+            .body(body)
+            .unwrap();
+        
+        // The body streams to the backend, which answers before the client ends it
+        let (mut sender, body) = Body::channel();
+        sender.send_data("first".into()).await.unwrap();
+        let response = timeout(Duration::from_secs(2), client.request(post("/first-chunk", body))).await.unwrap().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(&hyper::body::to_bytes(response.into_body()).await.unwrap()[..], b"first");
+        drop(sender);
+        
+        // A client that stops sending its body gets 408
+        let (mut sender, body) = Body::channel();
+        sender.send_data("partial".into()).await.unwrap();
+        let response = client.request(post("/users", body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+        assert_eq!(response.headers()["x-gateway-error"], "client_timeout");
+        drop(sender);
+        
+        // A chunked body growing past the maximum size gets 413
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            for _ in 0..4 {
+                if sender.send_data("0123456789".into()).await.is_err() {
+                    break;
+                }
+            }
+        });
+        let response = client.request(post("/users", body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        
+        // A backend slower than the total request timeout gets 504
+        let uri: hyper::Uri = format!("http://127.0.0.1:{}/api/slow", port).parse().unwrap();
+        let response = client.get(uri).await.unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(response.headers()["x-gateway-error"], "request_timeout");
+        
+        gateway.shutdown();
+    }
+    
     // Additional tests would go here:
     // 1. Test WebSocket proxying
     // 2. Test gRPC proxying
@@ -0,0 +1,361 @@
+#[cfg(test)]
+mod metrics_tests {
+    use std::collections::HashMap;
+    use chrono::Utc;
+    
+    use ferrumgw::config::data_model::{Proxy, Protocol, AuthMode};
+    use ferrumgw::config::env_config::EnvConfig;
+    use ferrumgw::config::settings::Settings;
+    
+    // Helper function to create a test proxy
+    fn create_test_proxy(id: &str, listen_path: &str) -> Proxy {
+        Proxy {
+            id: id.to_string(),
+            name: Some(format!("Test Proxy {}", id)),
+            workspace: "default".to_string(),
+            version: 1,
+            tags: Vec::new(),
+            enabled: true,
+            listen_path: listen_path.to_string(),
+            backend_protocol: Protocol::Http,
+            backend_host: "example.com".to_string(),
+            backend_port: 80,
+            backend_path: Some("/api".to_string()),
+            strip_listen_path: true,
+            preserve_host_header: false,
+            backend_connect_timeout_ms: 5000,
+            backend_read_timeout_ms: 30000,
+            backend_write_timeout_ms: 30000,
+            backend_tls_client_cert_path: None,
+            backend_tls_client_key_path: None,
+            backend_tls_verify_server_cert: true,
+            backend_tls_server_ca_cert_path: None,
+            dns_override: None,
+            dns_cache_ttl_seconds: None,
+            dns_resolvers: Vec::new(),
+            backend_discovery: None,
+            auth_mode: AuthMode::Single,
+            plugins: Vec::new(),
+            error_templates: HashMap::new(),
+            log_sampling: None,
+            api_versioning: None,
+            max_requests_per_second: None,
+            backend_keepalive: None,
+            backend_header_case: None,
+            upstream_host: None,
+            egress_proxy: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+    
+    #[test]
+    fn test_recent_proxy_metrics() {
+        use ferrumgw::metrics::recent::{RecentMetrics, WINDOW_SECONDS};
+        use std::time::Duration;
+        
+        let mut recent = RecentMetrics::default();
+        for i in 0..100 {
+            let status = if i < 90 { 200 } else { 502 };
+            recent.record(10, "orders", Some("alice"), status, Duration::from_millis(if i < 50 { 4 } else { 40 }));
+        }
+        recent.record(11, "orders", None, 200, Duration::from_millis(4));
+        
+        let orders = recent.proxy("orders", 11);
+        assert_eq!(orders.requests, 101);
+        assert_eq!(orders.status_codes.get("502"), Some(&10));
+        assert!(orders.latency_quantile(0.5) <= 0.005);
+        assert!(orders.latency_quantile(0.99) > 0.025 && orders.latency_quantile(0.99) <= 0.05);
+        assert_eq!(recent.consumer("alice", 11).requests, 100);
+        
+        // Traffic older than the window is no longer counted
+        assert_eq!(recent.proxy("orders", 10 + WINDOW_SECONDS).requests, 1);
+        assert_eq!(recent.proxy("orders", 11 + WINDOW_SECONDS).requests, 0);
+        assert_eq!(recent.proxy("payments", 11).requests, 0);
+    }
+    
+    #[test]
+    fn test_consumer_metric_labels() {
+        use ferrumgw::metrics::consumers::{self, ConsumerMetricsSettings, Labeled, OTHER};
+        use std::time::Duration;
+        
+        consumers::configure(ConsumerMetricsSettings {
+            enabled: true,
+            proxies: vec!["orders".to_string()],
+            consumers: Vec::new(),
+            max_label_sets: 2,
+        });
+        let orders = Labeled { id: "p1", name: Some("orders") };
+        let consumer = |id| Some(Labeled { id, name: None });
+        consumers::record(orders, consumer("alice"), Duration::from_millis(5), 10);
+        consumers::record(orders, consumer("alice"), Duration::from_millis(5), 0);
+        consumers::record_response_bytes(orders, consumer("alice"), 20);
+        consumers::record(orders, None, Duration::from_millis(5), 0);
+        assert_eq!(consumers::label_sets(), 2);
+        
+        // Past the limit, new label sets are counted under __other__
+        consumers::record(orders, consumer("bob"), Duration::from_millis(5), 0);
+        assert_eq!(consumers::label_sets(), 2);
+        let others: f64 = prometheus::gather().iter()
+            .filter(|family| family.get_name() == "ferrumgw_consumer_requests_total")
+            .flat_map(|family| family.get_metric().to_vec())
+            .filter(|metric| metric.get_label().iter().all(|label| label.get_value() == OTHER))
+            .map(|metric| metric.get_counter().get_value())
+            .sum();
+        assert!(others >= 1.0);
+        
+        // Proxies off the allowlist are not counted
+        consumers::configure(ConsumerMetricsSettings {
+            enabled: true,
+            proxies: vec!["orders".to_string()],
+            consumers: vec!["alice".to_string()],
+            max_label_sets: 10,
+        });
+        consumers::record(Labeled { id: "p2", name: Some("billing") }, consumer("alice"), Duration::from_millis(5), 0);
+        consumers::record(orders, None, Duration::from_millis(5), 0);
+        assert_eq!(consumers::label_sets(), 0);
+        consumers::record(Labeled { id: "orders", name: None }, consumer("alice"), Duration::from_millis(5), 0);
+        assert_eq!(consumers::label_sets(), 1);
+        
+        consumers::configure(ConsumerMetricsSettings::default());
+    }
+    
+    #[test]
+    fn test_otlp_metrics_encoding() {
+        use ferrumgw::metrics::otlp;
+        use prometheus::{Histogram, HistogramOpts, IntCounterVec, Opts, Registry};
+        
+        let registry = Registry::new();
+        let requests = IntCounterVec::new(Opts::new("test_requests_total", "Requests"), &["proxy"]).unwrap();
+        let duration = Histogram::with_opts(HistogramOpts::new("test_duration_seconds", "Duration").buckets(vec![0.1, 1.0])).unwrap();
+        registry.register(Box::new(requests.clone())).unwrap();
+        registry.register(Box::new(duration.clone())).unwrap();
+        requests.with_label_values(&["orders"]).inc_by(3);
+        for seconds in [0.05, 0.5, 0.5, 5.0] {
+            duration.observe(seconds);
+        }
+        
+        let batches = otlp::encode(&registry.gather(), Utc::now(), Utc::now(), 1);
+        assert_eq!(batches.len(), 2);
+        let metric = |batch: &serde_json::Value| batch["resourceMetrics"][0]["scopeMetrics"][0]["metrics"][0].clone();
+        
+        let histogram = metric(&batches[0]);
+        assert_eq!(histogram["name"], "test_duration_seconds");
+        let point = &histogram["histogram"]["dataPoints"][0];
+        assert_eq!(point["count"], "4");
+        assert_eq!(point["explicitBounds"], serde_json::json!([0.1, 1.0]));
+        // Buckets are counted on their own, ending with the overflow bucket
+        assert_eq!(point["bucketCounts"], serde_json::json!(["1", "2", "1"]));
+        
+        let counter = metric(&batches[1]);
+        assert_eq!(counter["sum"]["isMonotonic"], true);
+        assert_eq!(counter["sum"]["dataPoints"][0]["asDouble"], 3.0);
+        assert_eq!(counter["sum"]["dataPoints"][0]["attributes"][0]["value"]["stringValue"], "orders");
+        
+        let flag = |name: &str, value: &str| (name.to_string(), value.to_string());
+        let mut flags = vec![
+            flag("FERRUM_MODE", "dp"),
+            flag("FERRUM_DP_CP_GRPC_URL", "http://cp-host:50051"),
+            flag("FERRUM_DP_GRPC_AUTH_TOKEN", "jwt_token"),
+            flag("FERRUM_OTLP_METRICS_HEADERS", "authorization=Bearer abc, x-tenant=ops"),
+        ];
+        let config = EnvConfig::from_settings(&Settings::load(None, flags.clone()).unwrap()).unwrap();
+        assert_eq!(config.otlp_metrics_headers[1], ("x-tenant".to_string(), "ops".to_string()));
+        flags.push(flag("FERRUM_OTLP_METRICS_ENDPOINT", "collector:4318"));
+        assert!(EnvConfig::from_settings(&Settings::load(None, flags).unwrap()).is_err());
+    }
+    
+    #[test]
+    fn test_statsd_lines() {
+        use ferrumgw::metrics::{self, statsd::{self, StatsdFormat, StatsdSettings}};
+        use std::net::UdpSocket;
+        use std::time::Duration;
+        
+        let mut settings = StatsdSettings {
+            addr: String::new(),
+            prefix: "gw".to_string(),
+            format: StatsdFormat::DogStatsd,
+            tags: vec!["env:test".to_string()],
+        };
+        assert_eq!(
+            statsd::format_line(&settings, "requests", "1", "c", &[("proxy", "a,b")]),
+            "gw.requests:1|c|#env:test,proxy:a_b"
+        );
+        settings.format = StatsdFormat::Statsd;
+        assert_eq!(statsd::format_line(&settings, "requests", "1", "c", &[("proxy", "orders")]), "gw.requests:1|c");
+        
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        settings.addr = server.local_addr().unwrap().to_string();
+        settings.format = StatsdFormat::DogStatsd;
+        statsd::configure(settings).unwrap();
+        metrics::track_plugin_duration("statsd-test", "authenticate", Duration::from_millis(2));
+        statsd::disable();
+        
+        // Requests of other tests may be sent meanwhile
+        let mut buf = [0u8; 512];
+        let line = loop {
+            let len = server.recv(&mut buf).unwrap();
+            let line = String::from_utf8_lossy(&buf[..len]).into_owned();
+            if line.contains("statsd-test") {
+                break line;
+            }
+        };
+        assert_eq!(line, "gw.plugin.duration:2.000|ms|#env:test,plugin:statsd-test,hook:authenticate");
+    }
+    
+    #[tokio::test]
+    async fn test_metrics_listener() {
+        use ferrumgw::metrics::endpoint;
+        use hyper::{Body, Request};
+        use std::time::Duration;
+        
+        let mut env_config = EnvConfig::default();
+        env_config.metrics_port = Some(0);
+        env_config.metrics_path = "/scrape".to_string();
+        env_config.metrics_on_admin = true;
+        endpoint::start(&env_config);
+        
+        let addr = loop {
+            if let Some(addr) = ferrumgw::health::listeners().get("metrics").copied() {
+                break addr;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        let client = hyper::Client::new();
+        let response = client.get(format!("http://127.0.0.1:{}/scrape", addr.port()).parse().unwrap()).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+        let response = client.get(format!("http://127.0.0.1:{}/metrics", addr.port()).parse().unwrap()).await.unwrap();
+        assert_eq!(response.status(), 404);
+        
+        // The path is answered on the admin listeners but not on the proxy ones
+        let request = Request::get("/scrape").body(Body::empty()).unwrap();
+        assert!(endpoint::admin_response(&request).await.is_some());
+        assert!(endpoint::proxy_response(&request).await.is_none());
+        assert!(endpoint::admin_response(&Request::post("/scrape").body(Body::empty()).unwrap()).await.is_none());
+    }
+    
+    #[test]
+    fn test_upstream_request_metrics() {
+        use ferrumgw::metrics;
+        
+        let gauge = |name: &str| prometheus::gather().iter()
+            .filter(|family| family.get_name() == name)
+            .flat_map(|family| family.get_metric().to_vec())
+            .find(|metric| metric.get_label().iter().any(|label| label.get_value() == "pool-test.internal"))
+            .map(|metric| metric.get_gauge().get_value());
+        
+        let first = metrics::start_upstream_request("pool-test.internal", 4);
+        let second = metrics::start_upstream_request("pool-test.internal", 4);
+        assert_eq!(gauge("ferrumgw_upstream_requests_in_flight"), Some(2.0));
+        assert_eq!(gauge("ferrumgw_upstream_pool_saturation"), Some(0.5));
+        
+        drop(first);
+        drop(second);
+        assert_eq!(gauge("ferrumgw_upstream_requests_in_flight"), Some(0.0));
+        assert_eq!(gauge("ferrumgw_upstream_pool_saturation"), Some(0.0));
+    }
+    
+    #[test]
+    fn test_latency_percentiles() {
+        use ferrumgw::metrics::latency::{LatencyHistogram, RecentLatencies};
+        use std::time::Duration;
+        
+        let mut histogram = LatencyHistogram::default();
+        for ms in 1..=1000 {
+            histogram.record(Duration::from_millis(ms));
+        }
+        // Within the bucket precision of the exact percentiles
+        let percentiles = histogram.percentiles();
+        assert_eq!(percentiles.requests, 1000);
+        assert!((percentiles.p50 - 500.0).abs() / 500.0 < 0.07, "p50 {}", percentiles.p50);
+        assert!((percentiles.p95 - 950.0).abs() / 950.0 < 0.07, "p95 {}", percentiles.p95);
+        assert!((percentiles.p99 - 990.0).abs() / 990.0 < 0.07, "p99 {}", percentiles.p99);
+        assert_eq!(LatencyHistogram::default().quantile_ms(0.99), 0.0);
+        
+        let mut latencies = RecentLatencies::default();
+        for _ in 0..99 {
+            latencies.record(10, "orders", Duration::from_millis(20));
+        }
+        latencies.record(12, "payments", Duration::from_millis(900));
+        let overall = latencies.overall(12);
+        assert_eq!(overall.requests, 100);
+        assert!(overall.p50 > 18.0 && overall.p50 < 22.0);
+        assert!(overall.p99 > 18.0 && overall.p99 < 22.0);
+        assert!(latencies.proxies(12)["payments"].p50 > 850.0);
+        
+        // Latencies older than a minute are dropped
+        assert_eq!(latencies.overall(75).requests, 0);
+        assert!(latencies.proxies(75).is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_body_byte_counting() {
+        use ferrumgw::proxy::body::count_body;
+        use hyper::body::HttpBody;
+        use hyper::{Body, HeaderMap};
+        use tokio::sync::oneshot;
+        
+        let (mut sender, body) = Body::channel();
+        let (counted_tx, counted_rx) = oneshot::channel();
+        let mut counted = count_body(body, move |bytes| { let _ = counted_tx.send(bytes); });
+        tokio::spawn(async move {
+            sender.send_data("hello ".into()).await.unwrap();
+            sender.send_data("world".into()).await.unwrap();
+            let mut trailers = HeaderMap::new();
+            trailers.insert("grpc-status", "0".parse().unwrap());
+            sender.send_trailers(trailers).await.unwrap();
+        });
+        
+        let mut data = Vec::new();
+        while let Some(chunk) = counted.data().await {
+            data.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(data, b"hello world");
+        let trailers = counted.trailers().await.unwrap().unwrap();
+        assert_eq!(trailers["grpc-status"], "0");
+        assert_eq!(counted_rx.await.unwrap(), 11);
+        
+        // Empty bodies are counted right away
+        let (counted_tx, counted_rx) = oneshot::channel();
+        count_body(Body::empty(), move |bytes| { let _ = counted_tx.send(bytes); });
+        assert_eq!(counted_rx.await.unwrap(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_rate_limit_and_auth_metrics() {
+        use std::sync::Arc;
+        use hyper::{Body, Request};
+        use ferrumgw::config::consumer_index::ConsumerIndex;
+        use ferrumgw::plugins::{Plugin, PluginRegistry};
+        use ferrumgw::proxy::handler::RequestContext;
+        
+        let counter = |name: &str, labels: &[&str]| prometheus::gather().iter()
+            .filter(|family| family.get_name() == name)
+            .flat_map(|family| family.get_metric().to_vec())
+            .find(|metric| labels.iter().all(|value| metric.get_label().iter().any(|label| label.get_value() == *value)))
+            .map(|metric| metric.get_counter().get_value());
+        
+        let mut ctx = RequestContext {
+            proxy: create_test_proxy("metrics-rl", "/metrics-rl"),
+            client_addr: "10.0.0.1:40000".parse().unwrap(),
+            consumer: None,
+            consumers: Arc::new(ConsumerIndex::default()),
+            client_certificate: None,
+            latency: Default::default(),
+            request_bytes: 0,
+        };
+        let registry = PluginRegistry::new();
+        
+        let rate_limiting = registry.create_plugin("rate_limiting", serde_json::json!({ "limit_by": "ip", "requests_per_minute": 1 })).unwrap();
+        let mut req = Request::new(Body::empty());
+        assert!(rate_limiting.authenticate(&mut req, &mut ctx).await.unwrap());
+        assert!(!rate_limiting.authenticate(&mut req, &mut ctx).await.unwrap());
+        assert_eq!(counter("ferrumgw_rate_limit_rejections_total", &["metrics-rl", "minute", "ip"]), Some(1.0));
+        
+        let key_auth = registry.create_plugin("key_auth", serde_json::json!({})).unwrap();
+        assert!(!key_auth.authenticate(&mut req, &mut ctx).await.unwrap());
+        assert_eq!(counter("ferrumgw_auth_failures", &["key_auth", "metrics-rl", "missing_credentials"]), Some(1.0));
+    }
+}
@@ -308,4 +308,29 @@ mod mode_tests {
         if let Some(val) = old_cp_url { env::set_var("FERRUM_DP_CP_GRPC_URL", val); } else { env::remove_var("FERRUM_DP_CP_GRPC_URL"); }
         if let Some(val) = old_auth_token { env::set_var("FERRUM_DP_GRPC_AUTH_TOKEN", val); } else { env::remove_var("FERRUM_DP_GRPC_AUTH_TOKEN"); }
     }
+    
+    #[tokio::test]
+    async fn test_dp_snapshot_cache() {
+        use ferrumgw::grpc::cache::SnapshotCache;
+        use ferrumgw::grpc::delta;
+        use ferrumgw::grpc::proto::ConfigSnapshot;
+        
+        let dir = tempdir().unwrap();
+        let cache = SnapshotCache::new(dir.path().join("dp-config.pb"));
+        
+        // Nothing cached before the first configuration arrives
+        assert!(cache.load().await.unwrap().is_none());
+        
+        let mut snapshot = ConfigSnapshot::from(&Configuration::default());
+        delta::full_update(&mut snapshot, 7);
+        cache.store(&snapshot).await.unwrap();
+        
+        let cached = cache.load().await.unwrap().unwrap();
+        assert_eq!(cached.version(), 7);
+        assert_eq!(cached.snapshot().checksum, snapshot.checksum);
+        
+        // A damaged cache is refused rather than served
+        fs::write(cache.path(), b"not a snapshot").unwrap();
+        assert!(cache.load().await.is_err());
+    }
 }
@@ -9,6 +9,8 @@ mod plugin_tests {
     use async_trait::async_trait;
     
     use ferrumgw::config::data_model::{Consumer, PluginConfig, Proxy, Protocol, AuthMode};
+    use ferrumgw::config::env_config::EnvConfig;
+    use ferrumgw::config::settings::Settings;
     use ferrumgw::plugins::{Plugin, PluginManager};
     use ferrumgw::proxy::handler::RequestContext;
     
@@ -46,6 +48,9 @@ mod plugin_tests {
                 ctx.consumer = Some(Consumer {
                     id: "test_consumer".to_string(),
                     username: "test_user".to_string(),
+                    workspace: "default".to_string(),
+                    version: 1,
+                    tags: Vec::new(),
                     custom_id: None,
                     credentials: HashMap::new(),
                     created_at: Utc::now(),
@@ -67,6 +72,10 @@ mod plugin_tests {
         let proxy = Proxy {
             id: "test_proxy".to_string(),
             name: Some("Test Proxy".to_string()),
+            workspace: "default".to_string(),
+            version: 1,
+            tags: Vec::new(),
+            enabled: true,
             listen_path: "/api".to_string(),
             backend_protocol: Protocol::Http,
             backend_host: "example.com".to_string(),
@@ -83,8 +92,18 @@ mod plugin_tests {
             backend_tls_server_ca_cert_path: None,
             dns_override: None,
             dns_cache_ttl_seconds: None,
+            dns_resolvers: Vec::new(),
+            backend_discovery: None,
             auth_mode: AuthMode::Single,
             plugins: Vec::new(),
+            error_templates: HashMap::new(),
+            log_sampling: None,
+            api_versioning: None,
+            max_requests_per_second: None,
+            backend_keepalive: None,
+            backend_header_case: None,
+            upstream_host: None,
+            egress_proxy: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -175,6 +194,9 @@ mod plugin_tests {
         ctx.consumer = Some(Consumer {
             id: "test_consumer".to_string(),
             username: "test_user".to_string(),
+            workspace: "default".to_string(),
+            version: 1,
+            tags: Vec::new(),
             custom_id: None,
             credentials: HashMap::new(),
             created_at: Utc::now(),
@@ -253,4 +275,43 @@ mod plugin_tests {
         // 3. Run the plugin's after_proxy hook
         // 4. Verify that headers are modified as expected
     }
+    
+    #[test]
+    fn test_log_sampling() {
+        use ferrumgw::config::data_model::LogSampling;
+        use ferrumgw::plugins::log_sampling::keeps;
+        
+        let every = LogSampling::default();
+        assert!((0..10).all(|draw| keeps(&every, 200, 5, draw)));
+        
+        // One in four, by the random draw, with errors and slow requests always kept
+        let sampled = LogSampling { one_in: 4, errors: true, slow_ms: Some(500) };
+        assert_eq!((0..100).filter(|draw| keeps(&sampled, 200, 5, *draw)).count(), 25);
+        assert!(keeps(&sampled, 503, 5, 1));
+        assert!(keeps(&sampled, 200, 500, 1));
+        assert!(!keeps(&sampled, 404, 499, 1));
+        
+        let slow_only = LogSampling { one_in: 0, errors: false, slow_ms: Some(500) };
+        assert!(!keeps(&slow_only, 502, 10, 0));
+        assert!(keeps(&slow_only, 200, 750, 0));
+        
+        // Proxies take the gateway's settings unless they have their own
+        let flag = |name: &str, value: &str| (name.to_string(), value.to_string());
+        let flags = vec![
+            flag("FERRUM_MODE", "dp"),
+            flag("FERRUM_DP_CP_GRPC_URL", "http://cp-host:50051"),
+            flag("FERRUM_DP_GRPC_AUTH_TOKEN", "jwt_token"),
+            flag("FERRUM_LOG_SAMPLE_ONE_IN", "10"),
+            flag("FERRUM_LOG_SAMPLE_SLOW_MS", "250"),
+        ];
+        let env_config = EnvConfig::from_settings(&Settings::load(None, flags).unwrap()).unwrap();
+        assert_eq!(env_config.log_sampling, LogSampling { one_in: 10, errors: true, slow_ms: Some(250) });
+        let proxy: Proxy = serde_json::from_value(serde_json::json!({
+            "id": "p1", "listen_path": "/p1", "backend_protocol": "http", "backend_host": "orders", "backend_port": 80,
+            "backend_connect_timeout_ms": 1000, "backend_read_timeout_ms": 1000, "backend_write_timeout_ms": 1000,
+            "log_sampling": { "one_in": 0 },
+            "created_at": Utc::now(), "updated_at": Utc::now(),
+        })).unwrap();
+        assert_eq!(proxy.log_sampling, Some(LogSampling { one_in: 0, errors: true, slow_ms: None }));
+    }
 }
@@ -9,6 +9,10 @@ mod simple_tests {
         let proxy = Proxy {
             id: "test1".to_string(),
             name: Some("Test Proxy".to_string()),
+            workspace: "default".to_string(),
+            version: 1,
+            tags: Vec::new(),
+            enabled: true,
             listen_path: "/api/test".to_string(),
             backend_protocol: Protocol::Http,
             backend_host: "example.com".to_string(),
@@ -25,8 +29,18 @@ mod simple_tests {
             backend_tls_server_ca_cert_path: None,
             dns_override: None,
             dns_cache_ttl_seconds: None,
+            dns_resolvers: Vec::new(),
+            backend_discovery: None,
             auth_mode: AuthMode::Single,
             plugins: Vec::new(),
+            error_templates: HashMap::new(),
+            log_sampling: None,
+            api_versioning: None,
+            max_requests_per_second: None,
+            backend_keepalive: None,
+            backend_header_case: None,
+            upstream_host: None,
+            egress_proxy: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
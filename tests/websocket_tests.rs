@@ -10,12 +10,12 @@ mod websocket_tests {
     use hyper::{Body, Client, Server};
     use hyper::service::{make_service_fn, service_fn};
     use tokio::net::TcpListener;
-    use tokio::sync::RwLock;
     use tokio::time::sleep;
     use tokio_tungstenite::{connect_async, tungstenite::Message, WebSocketStream};
     use serde_json::json;
     
     use ferrumgw::config::data_model::{Configuration, Proxy, Consumer, PluginConfig, Protocol, AuthMode};
+    use ferrumgw::config::snapshot::ConfigurationSnapshot;
     use ferrumgw::proxy::router::Router;
     use ferrumgw::proxy::websocket::handle_websocket;
     use ferrumgw::proxy::handler::RequestContext;
@@ -25,6 +25,10 @@ mod websocket_tests {
         Proxy {
             id: id.to_string(),
             name: Some(format!("Test Proxy {}", id)),
+            workspace: "default".to_string(),
+            version: 1,
+            tags: Vec::new(),
+            enabled: true,
             listen_path: listen_path.to_string(),
             backend_protocol: Protocol::Ws,
             backend_host: backend_host.to_string(),
@@ -41,8 +45,18 @@ mod websocket_tests {
             backend_tls_server_ca_cert_path: None,
             dns_override: None,
             dns_cache_ttl_seconds: None,
+            dns_resolvers: Vec::new(),
+            backend_discovery: None,
             auth_mode: AuthMode::Single,
             plugins: Vec::new(),
+            error_templates: HashMap::new(),
+            log_sampling: None,
+            api_versioning: None,
+            max_requests_per_second: None,
+            backend_keepalive: None,
+            backend_header_case: None,
+            upstream_host: None,
+            egress_proxy: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -159,11 +173,13 @@ mod websocket_tests {
             proxies: vec![proxy.clone()],
             consumers: Vec::new(),
             plugin_configs: Vec::new(),
+            certificates: Vec::new(),
+            snis: Vec::new(),
             last_updated_at: Utc::now(),
         };
         
         // Set up a simple HTTP server that will handle the upgrade and proxy the WebSocket
-        let config_store = Arc::new(RwLock::new(config));
+        let config_store = ConfigurationSnapshot::shared(config);
         let router = Router::new(config_store.clone());
         
         let server_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 0);